@@ -0,0 +1 @@
+console.log('I am fingerprinted!');
@@ -0,0 +1,103 @@
+use hyper::{body::Payload, Body, Response};
+use log::debug;
+use serde::Deserialize;
+
+use crate::extractor::{internal, ExtractionError};
+use crate::helpers::http::PercentDecoded;
+use crate::router::response::extender::StaticResponseExtender;
+use crate::router::tree::segment::SegmentMapping;
+use crate::state::{request_id, State, StateData};
+
+/// Defines a binding for storing the label captured from the host of the `Request` in `State`,
+/// for the routes of a host pattern such as `{tenant}.example.com` defined through
+/// `RouterBuilder::host_with_extractor`. The label is deserialized into the field named by the
+/// pattern. On failure the `StaticResponseExtender` implementation extends the `Response` to
+/// indicate why the extraction process failed.
+///
+/// This trait is automatically implemented when the struct implements the `Deserialize`,
+/// `StateData` and `StaticResponseExtender` traits, as with `PathExtractor`.
+///
+/// # Examples
+///
+/// ```rust
+/// # extern crate gotham;
+/// # #[macro_use]
+/// # extern crate gotham_derive;
+/// # extern crate hyper;
+/// # #[macro_use]
+/// # extern crate serde_derive;
+/// #
+/// # use hyper::header::HOST;
+/// # use gotham::state::{FromState, State};
+/// # use gotham::router::Router;
+/// # use gotham::router::builder::*;
+/// # use gotham::test::TestServer;
+/// #
+/// #[derive(Deserialize, StateData, StaticResponseExtender)]
+/// struct Tenant {
+///     tenant: String,
+/// }
+///
+/// fn dashboard(state: State) -> (State, String) {
+///     let body = format!("dashboard of {}", Tenant::borrow_from(&state).tenant);
+///     (state, body)
+/// }
+///
+/// fn router() -> Router {
+///     build_simple_router(|route| {
+///         route.host_with_extractor::<Tenant, _>("{tenant}.example.com", |route| {
+///             route.get("/").to(dashboard);
+///         });
+///     })
+/// }
+/// #
+/// # fn main() {
+/// #   let test_server = TestServer::new(router()).unwrap();
+/// #   let response = test_server.client()
+/// #       .get("http://localhost/")
+/// #       .with_header(HOST, "acme.example.com".parse().unwrap())
+/// #       .perform()
+/// #       .unwrap();
+/// #   assert_eq!(response.read_utf8_body().unwrap(), "dashboard of acme");
+/// # }
+/// ```
+pub trait HostExtractor<B>:
+    for<'de> Deserialize<'de> + StaticResponseExtender<ResBody = B> + StateData
+where
+    B: Payload,
+{
+}
+
+impl<T, B> HostExtractor<B> for T
+where
+    B: Payload,
+    for<'de> T: Deserialize<'de> + StaticResponseExtender<ResBody = B> + StateData,
+{
+}
+
+/// Extracts the label captured from the host of the `Request` into `State`, providing the
+/// response of the `StaticResponseExtender` to send instead where it can't be deserialized.
+pub(crate) fn extract_host<HE>(state: &mut State, name: &str, label: &str) -> Option<Response<Body>>
+where
+    HE: HostExtractor<Body>,
+{
+    let decoded = PercentDecoded::new(label);
+    let mut mapping = SegmentMapping::new();
+    if let Some(ref decoded) = decoded {
+        mapping.insert(name, vec![decoded]);
+    }
+
+    match internal::from_segment_mapping::<HE>(mapping) {
+        Ok(val) => {
+            state.put(val);
+            None
+        }
+        Err(e) => {
+            debug!("[{}] host extractor failed: {}", request_id(state), e);
+            state.put(ExtractionError::new(e.to_string()));
+            let mut res = Response::new(Body::empty());
+            HE::extend(state, &mut res);
+            Some(res)
+        }
+    }
+}
@@ -82,6 +82,11 @@ pub(crate) enum ExtractorError {
     /// informative error messages.
     Custom(String),
 
+    /// The value of the named field couldn't be parsed or deserialized. For example, in a route
+    /// for `/resource/:id`, and with `id: i32` in the `PathExtractor` struct, a request for
+    /// `/resource/abc` would fail to parse the `id` field.
+    InvalidValue(String, String),
+
     // Variants may be added in future, and it will not be considered a breaking change.
     #[doc(hidden)]
     __NonExhaustive,
@@ -89,7 +94,12 @@ pub(crate) enum ExtractorError {
 
 impl Display for ExtractorError {
     fn fmt(&self, out: &mut fmt::Formatter) -> fmt::Result {
-        out.write_fmt(format_args!("{:?}", self))
+        match *self {
+            ExtractorError::InvalidValue(ref name, ref message) => {
+                out.write_fmt(format_args!("invalid value for `{}`: {}", name, message))
+            }
+            _ => out.write_fmt(format_args!("{:?}", self)),
+        }
     }
 }
 
@@ -114,12 +124,12 @@ macro_rules! single_value_type {
     ($trait_fn:ident, $visitor_fn:ident) => {
         fn $trait_fn<V>(self, visitor: V) -> Result<V::Value, Self::Error>
         where
-            V: Visitor<'de>
+            V: Visitor<'de>,
         {
             let v = parse_single_value(self.values)?;
             visitor.$visitor_fn(v)
         }
-    }
+    };
 }
 
 /// Implements one `Deserializer` function (`$trait_fn`) to return the error defined by the `$err`
@@ -407,11 +417,16 @@ where
         V: DeserializeSeed<'de>,
     {
         match self.current.take() {
-            Some((_k, values)) => {
+            Some((k, values)) => {
                 let deserializer = DeserializeValues {
                     values: values.into_iter().map(convert_to_string_ref),
                 };
-                seed.deserialize(deserializer)
+                seed.deserialize(deserializer).map_err(|e| match e {
+                    ExtractorError::ParseError(message) | ExtractorError::Custom(message) => {
+                        ExtractorError::InvalidValue(k.to_owned(), message)
+                    }
+                    e => e,
+                })
             }
             None => Err(ExtractorError::NoCurrentItem),
         }
@@ -1153,4 +1168,25 @@ mod tests {
 
         assert_eq!(p.wrapped_int_val, IntWrapper(100));
     }
+
+    #[derive(Debug, Deserialize)]
+    struct WithId {
+        #[allow(dead_code)]
+        id: u32,
+    }
+
+    #[test]
+    fn invalid_value_names_field_tests() {
+        let id = PercentDecoded::new("abc").unwrap();
+
+        let mut sm = SegmentMapping::new();
+        sm.insert("id", vec![&id]);
+
+        let e = from_segment_mapping::<WithId>(sm).unwrap_err();
+
+        assert_eq!(
+            e.to_string(),
+            "invalid value for `id`: invalid digit found in string"
+        );
+    }
 }
@@ -1,16 +1,56 @@
 //! Extracts request data into type-safe structs using Serde.
 //!
-//! Extractors are added to route definitions when defining a `Router`. The `PathExtractor` and
-//! `QueryStringExtractor` traits provide usage examples.
+//! Extractors are added to route definitions when defining a `Router`. The `PathExtractor`,
+//! `QueryStringExtractor` and `HostExtractor` traits provide usage examples.
 //!
 //! The request data is extracted by the `Route` implementation when dispatching the request. The
 //! application-provided data structure which implements the extractor trait is used to deserialize
 //! the data and store it within the request `State` before the request is dispatched to the
 //! `Handler`.
 
+pub(crate) mod host;
 pub(crate) mod internal;
 mod path;
 mod query_string;
 
+pub use self::host::HostExtractor;
 pub use self::path::*;
 pub use self::query_string::*;
+
+use std::fmt::{self, Display};
+
+use crate::state::StateData;
+
+/// Describes why the `PathExtractor` or `QueryStringExtractor` of a route failed to extract the
+/// request data, such as a segment which couldn't be parsed as the type of its field.
+///
+/// It is stored in `State` before the `StaticResponseExtender` of the extractor is invoked, so
+/// that the response can explain the failure. Where the extender leaves a `400 Bad Request`
+/// response without a body, as the derived extender does, the `Router` uses the message as a
+/// plain text body.
+#[derive(Clone, Debug, PartialEq)]
+pub struct ExtractionError {
+    message: String,
+}
+
+impl ExtractionError {
+    pub(crate) fn new<M: Into<String>>(message: M) -> ExtractionError {
+        ExtractionError {
+            message: message.into(),
+        }
+    }
+
+    /// Returns a description of the failure, naming the field that couldn't be extracted where
+    /// known.
+    pub fn message(&self) -> &str {
+        &self.message
+    }
+}
+
+impl Display for ExtractionError {
+    fn fmt(&self, out: &mut fmt::Formatter) -> fmt::Result {
+        out.write_str(&self.message)
+    }
+}
+
+impl StateData for ExtractionError {}
@@ -1,8 +1,11 @@
 use hyper::{body::Payload, Body, Response};
+use log::debug;
 use serde::{Deserialize, Deserializer};
 
+use crate::extractor::{internal, ExtractionError};
 use crate::router::response::extender::StaticResponseExtender;
-use crate::state::{State, StateData};
+use crate::router::tree::segment::SegmentMapping;
+use crate::state::{request_id, State, StateData};
 
 /// Defines a binding for storing the dynamic segments of the `Request` path in `State`. On failure
 /// the `StaticResponseExtender` implementation extends the `Response` to indicate why the
@@ -113,3 +116,28 @@ impl StaticResponseExtender for NoopPathExtractor {
     type ResBody = Body;
     fn extend(_state: &mut State, _res: &mut Response<Body>) {}
 }
+
+/// Extracts the dynamic segments of the request path into `State`, for a scope defined with
+/// `ScopeBuilder::with_path_extractor`, providing the response of the `StaticResponseExtender`
+/// to send instead where they can't be deserialized.
+pub(crate) fn extract_path<PE>(
+    state: &mut State,
+    params: &SegmentMapping<'_>,
+) -> Option<Response<Body>>
+where
+    PE: PathExtractor<Body>,
+{
+    match internal::from_segment_mapping::<PE>(params.clone()) {
+        Ok(val) => {
+            state.put(val);
+            None
+        }
+        Err(e) => {
+            debug!("[{}] scope path extractor failed: {}", request_id(state), e);
+            state.put(ExtractionError::new(e.to_string()));
+            let mut res = Response::new(Body::empty());
+            PE::extend(state, &mut res);
+            Some(res)
+        }
+    }
+}
@@ -123,3 +123,123 @@ impl StaticResponseExtender for NoopQueryStringExtractor {
     type ResBody = Body;
     fn extend(_state: &mut State, _res: &mut Response<Body>) {}
 }
+
+/// Determines how the query string of a request is decoded for the `QueryStringExtractor` of a
+/// route, and for matching the query string with `QueryStringRouteMatcher`, as configured by
+/// `RouterBuilder::query_string_options`.
+///
+/// By default, `+` is decoded as a space, every value of a repeated key is kept, and brackets
+/// are left in keys. Clients generated for PHP or Rails style servers are commonly accommodated
+/// by retaining the last value of a repeated key, and removing the brackets of array keys:
+///
+/// ```rust
+/// # extern crate gotham;
+/// # #[macro_use]
+/// # extern crate gotham_derive;
+/// # #[macro_use]
+/// # extern crate serde_derive;
+/// #
+/// # use gotham::extractor::{QueryStringOptions, RepeatedKeys};
+/// # use gotham::router::builder::*;
+/// # use gotham::state::{FromState, State};
+/// # use gotham::test::TestServer;
+/// #
+/// #[derive(Deserialize, StateData, StaticResponseExtender)]
+/// struct Search {
+///     q: String,
+///     tag: Vec<String>,
+/// }
+///
+/// fn search(state: State) -> (State, String) {
+///     let body = {
+///         let search = Search::borrow_from(&state);
+///         format!("{} {:?}", search.q, search.tag)
+///     };
+///     (state, body)
+/// }
+///
+/// # fn main() {
+/// let router = build_simple_router(|route| {
+///     route.query_string_options(
+///         QueryStringOptions::default()
+///             .with_repeated_keys(RepeatedKeys::Last)
+///             .with_bracketed_arrays(true),
+///     );
+///
+///     route
+///         .get("/search")
+///         .with_query_string_extractor::<Search>()
+///         .to(search);
+/// });
+///
+/// let test_server = TestServer::new(router).unwrap();
+/// let response = test_server
+///     .client()
+///     .get("http://localhost/search?q=old&q=new&tag%5B%5D=a&tag%5B%5D=b")
+///     .perform()
+///     .unwrap();
+///
+/// assert_eq!(response.read_utf8_body().unwrap(), r#"new ["a", "b"]"#);
+/// # }
+/// ```
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub struct QueryStringOptions {
+    pub(crate) plus_as_space: bool,
+    pub(crate) repeated_keys: RepeatedKeys,
+    pub(crate) bracketed_arrays: bool,
+}
+
+impl Default for QueryStringOptions {
+    fn default() -> Self {
+        QueryStringOptions {
+            plus_as_space: true,
+            repeated_keys: RepeatedKeys::All,
+            bracketed_arrays: false,
+        }
+    }
+}
+
+impl QueryStringOptions {
+    /// Sets whether `+` is decoded as a space, as in `application/x-www-form-urlencoded` data,
+    /// rather than kept as a literal `+`. An encoded `%2B` is always decoded as `+`.
+    pub fn with_plus_as_space(self, plus_as_space: bool) -> Self {
+        QueryStringOptions {
+            plus_as_space,
+            ..self
+        }
+    }
+
+    /// Sets which values are kept for a key which is repeated in the query string.
+    pub fn with_repeated_keys(self, repeated_keys: RepeatedKeys) -> Self {
+        QueryStringOptions {
+            repeated_keys,
+            ..self
+        }
+    }
+
+    /// Sets whether a key ending with `[]`, as in `a[]=1&a[]=2`, is treated as the key without
+    /// the brackets. Every value of such a key is kept, regardless of `RepeatedKeys`, so the
+    /// values can be extracted into a `Vec`.
+    pub fn with_bracketed_arrays(self, bracketed_arrays: bool) -> Self {
+        QueryStringOptions {
+            bracketed_arrays,
+            ..self
+        }
+    }
+}
+
+impl StateData for QueryStringOptions {}
+
+/// Determines which values are kept for a key which is repeated in the query string, as
+/// configured by `QueryStringOptions::with_repeated_keys`.
+#[derive(Clone, Copy, Debug, Default, PartialEq)]
+pub enum RepeatedKeys {
+    /// Every value is kept, so the key can be extracted into a `Vec`, and extracting it as a
+    /// single value fails. This is the default.
+    #[default]
+    All,
+    /// Only the first value is kept.
+    First,
+    /// Only the last value is kept.
+    Last,
+}
@@ -0,0 +1,179 @@
+//! Defines `FileCache`, an in-memory LRU cache of static asset contents,
+//! used to serve frequently requested small files without reading them
+//! from disk.
+
+use bytes::Bytes;
+use linked_hash_map::LinkedHashMap;
+use log::trace;
+
+use std::fmt;
+use std::path::{Path, PathBuf};
+use std::sync::{Arc, Mutex, MutexGuard, PoisonError};
+
+use super::vfs::FileMetadata;
+
+/// A cache of file contents keyed by path, which is shared between all handlers cloned from the
+/// same `FileOptions`. Entries are only used while the size and modification time of the file
+/// are unchanged, and the least recently used entries are evicted once the total size of the
+/// cache exceeds its limit.
+#[derive(Clone)]
+pub struct FileCache {
+    entries: Arc<Mutex<CacheEntries>>,
+    max_size: u64,
+    max_entry_size: u64,
+}
+
+struct CacheEntries {
+    files: LinkedHashMap<PathBuf, (FileMetadata, Bytes)>,
+    size: u64,
+}
+
+impl FileCache {
+    /// Creates an empty `FileCache` which holds up to `max_size` bytes in total, and does not
+    /// hold files larger than `max_entry_size` bytes.
+    pub fn new(max_size: u64, max_entry_size: u64) -> FileCache {
+        FileCache {
+            entries: Arc::new(Mutex::new(CacheEntries {
+                files: LinkedHashMap::new(),
+                size: 0,
+            })),
+            max_size,
+            max_entry_size: max_entry_size.min(max_size),
+        }
+    }
+
+    /// Determines whether the file described by `meta` is small enough to be cached.
+    pub fn accepts(&self, meta: &FileMetadata) -> bool {
+        meta.is_file() && meta.len() <= self.max_entry_size
+    }
+
+    /// Returns the cached contents of the file at `path`, if present and still current
+    /// according to `meta`. Stale entries are removed.
+    pub fn get(&self, path: &Path, meta: &FileMetadata) -> Option<Bytes> {
+        let mut entries = self.lock();
+
+        let current = match entries.files.get_refresh(path) {
+            Some(&mut (ref cached, ref bytes)) if is_current(cached, meta) => {
+                return Some(bytes.clone())
+            }
+            Some(_) => false,
+            None => return None,
+        };
+
+        if !current {
+            trace!(" removing stale cache entry for {:?}", path);
+            if let Some((_, bytes)) = entries.files.remove(path) {
+                entries.size -= bytes.len() as u64;
+            }
+        }
+        None
+    }
+
+    /// Stores the contents of the file at `path`, evicting the least recently used entries to
+    /// stay within the size limit.
+    pub fn insert(&self, path: PathBuf, meta: FileMetadata, bytes: Bytes) {
+        let len = bytes.len() as u64;
+        if len > self.max_entry_size {
+            return;
+        }
+
+        let mut entries = self.lock();
+        if let Some((_, previous)) = entries.files.insert(path, (meta, bytes)) {
+            entries.size -= previous.len() as u64;
+        }
+        entries.size += len;
+
+        while entries.size > self.max_size {
+            match entries.files.pop_front() {
+                Some((path, (_, bytes))) => {
+                    trace!(" evicted {:?} from file cache", path);
+                    entries.size -= bytes.len() as u64;
+                }
+                None => break,
+            }
+        }
+    }
+
+    fn lock(&self) -> MutexGuard<'_, CacheEntries> {
+        match self.entries.lock() {
+            Ok(entries) => entries,
+            Err(PoisonError { .. }) => unreachable!("file cache lock poisoned, HashMap panicked?"),
+        }
+    }
+}
+
+impl PartialEq for FileCache {
+    fn eq(&self, other: &Self) -> bool {
+        Arc::ptr_eq(&self.entries, &other.entries)
+    }
+}
+
+impl fmt::Debug for FileCache {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        f.debug_struct("FileCache")
+            .field("max_size", &self.max_size)
+            .field("max_entry_size", &self.max_entry_size)
+            .finish()
+    }
+}
+
+fn is_current(cached: &FileMetadata, meta: &FileMetadata) -> bool {
+    cached.len() == meta.len() && cached.modified() == meta.modified()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::FileCache;
+    use crate::handler::assets::vfs::FileMetadata;
+    use bytes::Bytes;
+    use std::fs;
+    use std::path::PathBuf;
+
+    const DOC: &str = "resources/test/assets/doc.html";
+    const FILE: &str = "resources/test/assets/file.txt";
+
+    #[test]
+    fn file_cache_get_and_insert() {
+        let cache = FileCache::new(1024, 1024);
+        let meta = FileMetadata::from(&fs::metadata(DOC).unwrap());
+
+        assert!(cache.get(DOC.as_ref(), &meta).is_none());
+
+        cache.insert(PathBuf::from(DOC), meta.clone(), Bytes::from("doc"));
+        assert_eq!(cache.get(DOC.as_ref(), &meta).unwrap(), Bytes::from("doc"));
+
+        // A different file's metadata makes the entry stale
+        let other = FileMetadata::from(&fs::metadata(FILE).unwrap());
+        assert!(cache.get(DOC.as_ref(), &other).is_none());
+        assert!(cache.get(DOC.as_ref(), &meta).is_none());
+        assert_eq!(cache.lock().size, 0);
+    }
+
+    #[test]
+    fn file_cache_evicts_least_recently_used() {
+        let cache = FileCache::new(10, 10);
+        let meta = FileMetadata::from(&fs::metadata(DOC).unwrap());
+
+        cache.insert(PathBuf::from("a"), meta.clone(), Bytes::from("aaaa"));
+        cache.insert(PathBuf::from("b"), meta.clone(), Bytes::from("bbbb"));
+
+        // Using "a" makes "b" the least recently used entry
+        assert!(cache.get("a".as_ref(), &meta).is_some());
+        cache.insert(PathBuf::from("c"), meta.clone(), Bytes::from("cccc"));
+
+        assert!(cache.get("a".as_ref(), &meta).is_some());
+        assert!(cache.get("b".as_ref(), &meta).is_none());
+        assert!(cache.get("c".as_ref(), &meta).is_some());
+        assert_eq!(cache.lock().size, 8);
+    }
+
+    #[test]
+    fn file_cache_ignores_large_entries() {
+        let cache = FileCache::new(100, 3);
+        let meta = FileMetadata::from(&fs::metadata(DOC).unwrap());
+
+        cache.insert(PathBuf::from("a"), meta.clone(), Bytes::from("aaaa"));
+        assert!(cache.get("a".as_ref(), &meta).is_none());
+        assert!(!cache.accepts(&meta));
+    }
+}
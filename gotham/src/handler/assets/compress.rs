@@ -0,0 +1,111 @@
+//! Defines on-the-fly gzip compression of static asset bodies, used when
+//! no precompressed side-by-side file is available.
+
+use flate2::write::GzEncoder;
+use flate2::Compression;
+use futures::{stream, Stream};
+use hyper::header::HeaderMap;
+use hyper::Chunk;
+use mime::{self, Mime};
+
+use std::io::{self, Write};
+use std::mem;
+
+use super::accepted_encoding::accepted_encodings;
+
+/// Determines whether a file of the given type is worth compressing. Most
+/// binary formats (images, video, archives) are already compressed.
+pub fn is_compressible(mime_type: &Mime) -> bool {
+    matches!(
+        (mime_type.type_(), mime_type.subtype()),
+        (mime::TEXT, _) | (mime::APPLICATION, mime::JAVASCRIPT) | (mime::APPLICATION, mime::JSON)
+    )
+}
+
+/// Determines whether the client accepts gzip encoded content, either explicitly
+/// or via a wildcard, with a non-zero quality value.
+pub fn accepts_gzip(headers: &HeaderMap) -> bool {
+    accepted_encodings(headers)
+        .iter()
+        .any(|e| (e.encoding == "gzip" || e.encoding == "*") && e.quality > 0f32)
+}
+
+/// Compresses the chunks of the given stream with gzip at the given level,
+/// emitting compressed output as it becomes available.
+pub fn gzip_stream<S>(stream: S, level: u32) -> impl Stream<Item = Chunk, Error = io::Error> + Send
+where
+    S: Stream<Item = Chunk, Error = io::Error> + Send,
+{
+    let mut encoder = Some(GzEncoder::new(Vec::new(), Compression::new(level)));
+
+    stream
+        .map(Some)
+        .chain(stream::once(Ok(None)))
+        .and_then(move |chunk| match (chunk, encoder.as_mut()) {
+            (Some(chunk), Some(encoder)) => {
+                encoder.write_all(&chunk)?;
+                Ok(Chunk::from(mem::take(encoder.get_mut())))
+            }
+            // The end of the input, so the trailer is written
+            _ => match encoder.take() {
+                Some(encoder) => encoder.finish().map(Chunk::from),
+                None => Ok(Chunk::from(Vec::new())),
+            },
+        })
+        .filter(|chunk| !chunk.is_empty())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::{accepts_gzip, gzip_stream, is_compressible};
+    use flate2::read::GzDecoder;
+    use futures::{stream, Future, Stream};
+    use hyper::header::{HeaderMap, ACCEPT_ENCODING};
+    use hyper::Chunk;
+    use std::io::{self, Read};
+
+    #[test]
+    fn compressible_types() {
+        assert!(is_compressible(&mime::TEXT_HTML));
+        assert!(is_compressible(&mime::TEXT_CSS));
+        assert!(is_compressible(&mime::APPLICATION_JAVASCRIPT));
+        assert!(is_compressible(&mime::APPLICATION_JSON));
+        assert!(!is_compressible(&mime::IMAGE_PNG));
+        assert!(!is_compressible(&mime::APPLICATION_OCTET_STREAM));
+    }
+
+    #[test]
+    fn accepts_gzip_values() {
+        let mut headers = HeaderMap::new();
+        assert!(!accepts_gzip(&headers));
+
+        headers.insert(ACCEPT_ENCODING, "br, gzip;q=0.5".parse().unwrap());
+        assert!(accepts_gzip(&headers));
+
+        headers.insert(ACCEPT_ENCODING, "gzip;q=0".parse().unwrap());
+        assert!(!accepts_gzip(&headers));
+
+        headers.insert(ACCEPT_ENCODING, "*".parse().unwrap());
+        assert!(accepts_gzip(&headers));
+    }
+
+    #[test]
+    fn gzip_stream_round_trip() {
+        let chunks = vec!["hello, ", "compressed ", "world"]
+            .into_iter()
+            .map(|s| Ok::<_, io::Error>(Chunk::from(s)));
+
+        let compressed: Vec<u8> = gzip_stream(stream::iter_result(chunks), 6)
+            .concat2()
+            .wait()
+            .unwrap()
+            .to_vec();
+
+        let mut decoded = String::new();
+        GzDecoder::new(&compressed[..])
+            .read_to_string(&mut decoded)
+            .unwrap();
+
+        assert_eq!(decoded, "hello, compressed world");
+    }
+}
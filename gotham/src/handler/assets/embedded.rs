@@ -0,0 +1,307 @@
+//! Defines `EmbeddedFileHandler`, which serves static assets compiled into the binary rather
+//! than read from the filesystem.
+
+use bytes::Bytes;
+use futures::future;
+use http;
+use hyper::header::*;
+use hyper::{Body, Method, StatusCode};
+
+use std::collections::hash_map::DefaultHasher;
+use std::collections::HashMap;
+use std::hash::Hasher;
+use std::io;
+use std::iter::FromIterator;
+use std::path::{Component, Path, PathBuf};
+use std::sync::Arc;
+
+use super::{mime_for_path, normalize_path, FilePathExtractor};
+use crate::error::Result;
+use crate::handler::{Handler, HandlerFuture, IntoHandlerError, NewHandler};
+use crate::state::{FromState, State};
+
+/// Serves files which are embedded in the binary, such as via `include_bytes!` or a map generated
+/// by a build script, so that an application can be deployed without a directory of assets.
+///
+/// Files are looked up by the path matched by the trailing glob segment of the route, as with
+/// `DirHandler`, and the content type is guessed from the file extension. A request which
+/// resolves to a directory is served the "index.html" file beneath it, if embedded.
+///
+/// ```rust
+/// # extern crate gotham;
+/// # use gotham::handler::assets::EmbeddedFileHandler;
+/// # use gotham::router::builder::*;
+/// #
+/// # fn main() {
+/// build_simple_router(|route| {
+///     route.get("/*").to_embedded(
+///         EmbeddedFileHandler::new()
+///             .with_file("doc.html", include_bytes!("../../../resources/test/assets/doc.html"))
+///             .with_file("styles/style.css", b".styled { border: none; }"),
+///     );
+/// });
+/// # }
+/// ```
+#[derive(Clone, Debug)]
+pub struct EmbeddedFileHandler {
+    files: Arc<HashMap<String, EmbeddedFile>>,
+    cache_control: String,
+}
+
+#[derive(Clone, Debug)]
+struct EmbeddedFile {
+    contents: Bytes,
+    etag: String,
+}
+
+impl EmbeddedFileHandler {
+    /// Create a new `EmbeddedFileHandler` without any files.
+    pub fn new() -> EmbeddedFileHandler {
+        EmbeddedFileHandler {
+            files: Arc::new(HashMap::new()),
+            cache_control: "public".to_string(),
+        }
+    }
+
+    /// Adds a file to be served at the given path, relative to the route, replacing any file
+    /// previously added at the same path.
+    pub fn with_file(mut self, path: &str, contents: &'static [u8]) -> EmbeddedFileHandler {
+        let contents = Bytes::from_static(contents);
+        let file = EmbeddedFile {
+            etag: entity_tag(&contents),
+            contents,
+        };
+        Arc::make_mut(&mut self.files).insert(normalize_key(Path::new(path)), file);
+        self
+    }
+
+    /// Sets the "cache_control" header in responses to the given value (defaults to "public").
+    pub fn with_cache_control(mut self, cache_control: &str) -> EmbeddedFileHandler {
+        self.cache_control = cache_control.to_owned();
+        self
+    }
+
+    // Finds the file for the request path, falling back to the index file of a directory.
+    fn find(&self, path: &str) -> Option<(&str, &EmbeddedFile)> {
+        let index = if path.is_empty() {
+            "index.html".to_owned()
+        } else {
+            format!("{}/index.html", path)
+        };
+
+        self.files
+            .get_key_value(path)
+            .or_else(|| self.files.get_key_value(&index))
+            .map(|(key, file)| (key.as_str(), file))
+    }
+}
+
+impl Default for EmbeddedFileHandler {
+    fn default() -> EmbeddedFileHandler {
+        EmbeddedFileHandler::new()
+    }
+}
+
+/// Collects files from pairs of path and contents, as might be generated by a build script.
+impl<'a> FromIterator<(&'a str, &'static [u8])> for EmbeddedFileHandler {
+    fn from_iter<I>(files: I) -> EmbeddedFileHandler
+    where
+        I: IntoIterator<Item = (&'a str, &'static [u8])>,
+    {
+        files
+            .into_iter()
+            .fold(EmbeddedFileHandler::new(), |handler, (path, contents)| {
+                handler.with_file(path, contents)
+            })
+    }
+}
+
+impl NewHandler for EmbeddedFileHandler {
+    type Instance = Self;
+
+    fn new_handler(&self) -> Result<Self::Instance> {
+        Ok(self.clone())
+    }
+}
+
+impl Handler for EmbeddedFileHandler {
+    fn handle(self, state: State) -> Box<HandlerFuture> {
+        let path = match FilePathExtractor::try_borrow_from(&state) {
+            Some(extractor) => normalize_key(&PathBuf::from_iter(&extractor.parts)),
+            None => String::new(),
+        };
+
+        let (key, file) = match self.find(&path) {
+            Some(found) => found,
+            None => {
+                let err = io::Error::new(io::ErrorKind::NotFound, "file is not embedded");
+                return Box::new(future::err((
+                    state,
+                    err.into_handler_error().with_status(StatusCode::NOT_FOUND),
+                )));
+            }
+        };
+
+        let not_modified = HeaderMap::borrow_from(&state)
+            .get_all(IF_NONE_MATCH)
+            .iter()
+            .any(|v| v == file.etag.as_str());
+
+        let mut response = http::Response::builder();
+        response.header(ETAG, file.etag.as_str());
+        response.header(CACHE_CONTROL, self.cache_control.as_str());
+
+        let response = if not_modified {
+            response
+                .status(StatusCode::NOT_MODIFIED)
+                .body(Body::empty())
+                .unwrap()
+        } else {
+            let body = if Method::borrow_from(&state) == Method::HEAD {
+                Body::empty()
+            } else {
+                Body::from(file.contents.clone())
+            };
+            response
+                .status(StatusCode::OK)
+                .header(CONTENT_TYPE, mime_for_path(Path::new(key)).as_ref())
+                .header(CONTENT_LENGTH, file.contents.len())
+                .body(body)
+                .unwrap()
+        };
+
+        Box::new(future::ok((state, response)))
+    }
+}
+
+// Converts a path to the form used to look up embedded files, without leading, trailing or
+// repeated separators, or any components which would escape the root.
+fn normalize_key(path: &Path) -> String {
+    normalize_path(path)
+        .components()
+        .filter_map(|c| match c {
+            Component::Normal(name) => Some(name.to_string_lossy().into_owned()),
+            _ => None,
+        })
+        .collect::<Vec<_>>()
+        .join("/")
+}
+
+// Embedded files never change while the application runs, so a strong tag is derived from the
+// contents alone.
+fn entity_tag(contents: &[u8]) -> String {
+    let mut hasher = DefaultHasher::new();
+    hasher.write(contents);
+    format!("\"{:x}-{:x}\"", contents.len(), hasher.finish())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::EmbeddedFileHandler;
+    use crate::router::builder::{build_simple_router, DefineSingleRoute, DrawRoutes};
+    use crate::test::TestServer;
+    use hyper::header::*;
+    use hyper::StatusCode;
+
+    fn test_server() -> TestServer {
+        let handler: EmbeddedFileHandler = vec![
+            ("docs/index.html", &b"<html>I am the index.</html>"[..]),
+            ("/docs/doc.html", &b"<html>I am a doc.</html>"[..]),
+            (
+                "scripts/script.js",
+                &b"console.log('I am javascript!');"[..],
+            ),
+        ]
+        .into_iter()
+        .collect();
+
+        TestServer::new(build_simple_router(|route| {
+            route.get_or_head("/*").to_embedded(handler);
+        }))
+        .unwrap()
+    }
+
+    #[test]
+    fn embedded_serves_files() {
+        let server = test_server();
+
+        let response = server
+            .client()
+            .get("http://localhost/docs/doc.html")
+            .perform()
+            .unwrap();
+
+        assert_eq!(response.status(), StatusCode::OK);
+        assert_eq!(response.headers().get(CONTENT_TYPE).unwrap(), "text/html");
+        assert_eq!(response.headers().get(CONTENT_LENGTH).unwrap(), "24");
+        assert_eq!(response.headers().get(CACHE_CONTROL).unwrap(), "public");
+        assert_eq!(
+            &response.read_body().unwrap()[..],
+            b"<html>I am a doc.</html>"
+        );
+
+        let response = server
+            .client()
+            .get("http://localhost/docs/")
+            .perform()
+            .unwrap();
+        assert_eq!(response.status(), StatusCode::OK);
+        assert_eq!(
+            &response.read_body().unwrap()[..],
+            b"<html>I am the index.</html>"
+        );
+    }
+
+    #[test]
+    fn embedded_not_found() {
+        for path in &["missing.txt", "scripts", "../docs/doc.html/x"] {
+            let response = test_server()
+                .client()
+                .get(&format!("http://localhost/{}", path))
+                .perform()
+                .unwrap();
+
+            assert_eq!(response.status(), StatusCode::NOT_FOUND);
+        }
+    }
+
+    #[test]
+    fn embedded_path_traversal() {
+        let response = test_server()
+            .client()
+            .get("http://localhost/docs/../scripts/script.js")
+            .perform()
+            .unwrap();
+
+        assert_eq!(response.status(), StatusCode::OK);
+        assert_eq!(
+            &response.read_body().unwrap()[..],
+            b"console.log('I am javascript!');"
+        );
+    }
+
+    #[test]
+    fn embedded_if_none_match_and_head() {
+        let server = test_server();
+
+        let response = server
+            .client()
+            .head("http://localhost/docs/doc.html")
+            .perform()
+            .unwrap();
+
+        assert_eq!(response.status(), StatusCode::OK);
+        assert_eq!(response.headers().get(CONTENT_LENGTH).unwrap(), "24");
+        let etag = response.headers().get(ETAG).unwrap().clone();
+        assert!(response.read_body().unwrap().is_empty());
+
+        let response = server
+            .client()
+            .get("http://localhost/docs/doc.html")
+            .with_header(IF_NONE_MATCH, etag)
+            .perform()
+            .unwrap();
+
+        assert_eq!(response.status(), StatusCode::NOT_MODIFIED);
+    }
+}
@@ -0,0 +1,142 @@
+//! Defines the HTML directory listings which `DirHandler` can render for
+//! requests that resolve to a directory.
+
+use httpdate::fmt_http_date;
+use percent_encoding::{utf8_percent_encode, AsciiSet, NON_ALPHANUMERIC};
+
+use std::time::SystemTime;
+
+// Characters which may be left as-is in the links of a listing.
+const ENTRY_NAME: &AsciiSet = &NON_ALPHANUMERIC
+    .remove(b'-')
+    .remove(b'.')
+    .remove(b'_')
+    .remove(b'~');
+
+/// A single file or directory shown in a directory listing.
+#[derive(Clone, Debug, PartialEq)]
+pub struct DirectoryEntry {
+    /// The file name of the entry.
+    pub name: String,
+    /// Whether the entry is itself a directory.
+    pub is_dir: bool,
+    /// The size of the entry in bytes.
+    pub size: u64,
+    /// The time the entry was last modified, if the platform reports it.
+    pub modified: Option<SystemTime>,
+}
+
+impl DirectoryEntry {
+    /// Returns the link to this entry, relative to the listed directory.
+    pub fn href(&self) -> String {
+        let mut href = utf8_percent_encode(&self.name, ENTRY_NAME).to_string();
+        if self.is_dir {
+            href.push('/');
+        }
+        href
+    }
+}
+
+/// Renders a plain HTML table of the entries in a directory, with the name, size and
+/// modification time of each entry.
+///
+/// This is the default renderer for directory listings, which can be replaced via
+/// `FileOptions::with_directory_listing_renderer`.
+pub fn render_directory_listing(request_path: &str, entries: &[DirectoryEntry]) -> String {
+    let title = escape_html(request_path);
+    // Links are absolute, so they resolve correctly whether or not the request path
+    // has a trailing slash.
+    let base = request_path.trim_end_matches('/');
+    let mut body = format!(
+        "<!DOCTYPE html>\n<html>\n<head><meta charset=\"utf-8\"><title>Index of {0}</title></head>\n\
+         <body>\n<h1>Index of {0}</h1>\n<table>\n\
+         <tr><th>Name</th><th>Size</th><th>Last modified</th></tr>\n",
+        title
+    );
+
+    for entry in entries {
+        let name = if entry.is_dir {
+            format!("{}/", entry.name)
+        } else {
+            entry.name.clone()
+        };
+        let size = if entry.is_dir {
+            "-".to_owned()
+        } else {
+            entry.size.to_string()
+        };
+        let modified = entry.modified.map(fmt_http_date).unwrap_or_default();
+
+        body.push_str(&format!(
+            "<tr><td><a href=\"{}/{}\">{}</a></td><td>{}</td><td>{}</td></tr>\n",
+            escape_html(base),
+            escape_html(&entry.href()),
+            escape_html(&name),
+            size,
+            modified
+        ));
+    }
+
+    body.push_str("</table>\n</body>\n</html>\n");
+    body
+}
+
+// Sorts the entries of a directory for display, with directories first.
+pub(super) fn sort_entries(mut entries: Vec<DirectoryEntry>) -> Vec<DirectoryEntry> {
+    entries.sort_by(|a, b| b.is_dir.cmp(&a.is_dir).then_with(|| a.name.cmp(&b.name)));
+    entries
+}
+
+fn escape_html(s: &str) -> String {
+    let mut escaped = String::with_capacity(s.len());
+    for c in s.chars() {
+        match c {
+            '&' => escaped.push_str("&amp;"),
+            '<' => escaped.push_str("&lt;"),
+            '>' => escaped.push_str("&gt;"),
+            '"' => escaped.push_str("&quot;"),
+            '\'' => escaped.push_str("&#39;"),
+            _ => escaped.push(c),
+        }
+    }
+    escaped
+}
+
+#[cfg(test)]
+mod tests {
+    use super::{render_directory_listing, DirectoryEntry};
+
+    #[test]
+    fn directory_entry_href() {
+        let file = DirectoryEntry {
+            name: "my file.txt".to_owned(),
+            is_dir: false,
+            size: 10,
+            modified: None,
+        };
+        assert_eq!(file.href(), "my%20file.txt");
+
+        let dir = DirectoryEntry {
+            name: "styles".to_owned(),
+            is_dir: true,
+            size: 0,
+            modified: None,
+        };
+        assert_eq!(dir.href(), "styles/");
+    }
+
+    #[test]
+    fn render_escapes_names() {
+        let entries = vec![DirectoryEntry {
+            name: "<script>.txt".to_owned(),
+            is_dir: false,
+            size: 3,
+            modified: None,
+        }];
+
+        let body = render_directory_listing("/files/", &entries);
+        assert!(body.contains("<title>Index of /files/</title>"));
+        assert!(body.contains("<a href=\"/files/%3Cscript%3E.txt\">&lt;script&gt;.txt</a>"));
+        assert!(!body.contains("<script>"));
+    }
+}
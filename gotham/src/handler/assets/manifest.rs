@@ -0,0 +1,144 @@
+//! Defines `AssetManifest`, which maps the logical names of assets to the
+//! fingerprinted file names produced by an asset pipeline.
+
+use serde_json;
+
+use std::collections::{HashMap, HashSet};
+use std::fs;
+use std::io;
+use std::iter::FromIterator;
+use std::path::Path;
+use std::sync::Arc;
+
+use crate::state::StateData;
+
+/// A mapping from the logical names of assets, such as "app.js", to fingerprinted file names,
+/// such as "app.3f9ab2.js", which change whenever the contents of the file change.
+///
+/// A manifest can be attached to request `State` with `StateMiddleware`, so that templates can
+/// link to the current fingerprinted names. Passing the manifest to
+/// `FileOptions::with_manifest` serves the fingerprinted files with caching headers allowing
+/// them to be cached indefinitely.
+///
+/// ```rust
+/// # extern crate gotham;
+/// # use gotham::handler::assets::AssetManifest;
+/// #
+/// # fn main() {
+/// let manifest = AssetManifest::from_json(r#"{ "js/app.js": "js/app.3f9ab2.js" }"#).unwrap();
+///
+/// assert_eq!(manifest.resolve("js/app.js"), Some("js/app.3f9ab2.js"));
+/// assert_eq!(manifest.resolve("js/other.js"), None);
+/// # }
+/// ```
+#[derive(Clone, Debug, PartialEq)]
+pub struct AssetManifest {
+    inner: Arc<ManifestEntries>,
+}
+
+#[derive(Debug, PartialEq)]
+struct ManifestEntries {
+    names: HashMap<String, String>,
+    fingerprinted: HashSet<String>,
+}
+
+impl AssetManifest {
+    /// Loads a manifest from a JSON file containing an object which maps logical names to
+    /// fingerprinted names, as written by most asset pipelines.
+    pub fn load<P: AsRef<Path>>(path: P) -> io::Result<AssetManifest> {
+        AssetManifest::from_json(&fs::read_to_string(path)?)
+    }
+
+    /// Parses a manifest from a JSON object which maps logical names to fingerprinted names.
+    pub fn from_json(json: &str) -> io::Result<AssetManifest> {
+        let names: HashMap<String, String> = serde_json::from_str(json)
+            .map_err(|e| io::Error::new(io::ErrorKind::InvalidData, e))?;
+        Ok(names.into_iter().collect())
+    }
+
+    /// Returns the fingerprinted name for the asset with the given logical name, if present.
+    pub fn resolve(&self, name: &str) -> Option<&str> {
+        self.inner
+            .names
+            .get(name.trim_start_matches('/'))
+            .map(String::as_str)
+    }
+
+    /// Determines whether the file at `path` is one of the fingerprinted files in the manifest,
+    /// by comparing the trailing components of the path with the fingerprinted names.
+    pub fn is_fingerprinted(&self, path: &Path) -> bool {
+        let components: Vec<_> = path
+            .components()
+            .map(|c| c.as_os_str().to_string_lossy())
+            .collect();
+
+        (0..components.len()).any(|i| {
+            self.inner
+                .fingerprinted
+                .contains(&components[i..].join("/"))
+        })
+    }
+}
+
+impl StateData for AssetManifest {}
+
+/// Collects a manifest from pairs of logical and fingerprinted names.
+impl<S: Into<String>> FromIterator<(S, S)> for AssetManifest {
+    fn from_iter<I>(names: I) -> AssetManifest
+    where
+        I: IntoIterator<Item = (S, S)>,
+    {
+        let names: HashMap<String, String> = names
+            .into_iter()
+            .map(|(name, fingerprinted)| {
+                let name: String = name.into();
+                let fingerprinted: String = fingerprinted.into();
+                (
+                    name.trim_start_matches('/').to_owned(),
+                    fingerprinted.trim_start_matches('/').to_owned(),
+                )
+            })
+            .collect();
+        let fingerprinted = names.values().cloned().collect();
+
+        AssetManifest {
+            inner: Arc::new(ManifestEntries {
+                names,
+                fingerprinted,
+            }),
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::AssetManifest;
+    use std::path::Path;
+
+    #[test]
+    fn manifest_from_json() {
+        let manifest = AssetManifest::from_json(
+            r#"{"app.js": "app.3f9ab2.js", "/css/site.css": "/css/site.1c2d.css"}"#,
+        )
+        .unwrap();
+
+        assert_eq!(manifest.resolve("app.js"), Some("app.3f9ab2.js"));
+        assert_eq!(manifest.resolve("/css/site.css"), Some("css/site.1c2d.css"));
+        assert_eq!(manifest.resolve("app.3f9ab2.js"), None);
+
+        assert!(AssetManifest::from_json("[]").is_err());
+        assert!(AssetManifest::load("resources/test/missing.json").is_err());
+    }
+
+    #[test]
+    fn manifest_is_fingerprinted() {
+        let manifest: AssetManifest = vec![("css/site.css", "css/site.1c2d.css")]
+            .into_iter()
+            .collect();
+
+        assert!(manifest.is_fingerprinted(Path::new("public/css/site.1c2d.css")));
+        assert!(manifest.is_fingerprinted(Path::new("css/site.1c2d.css")));
+        assert!(!manifest.is_fingerprinted(Path::new("site.1c2d.css")));
+        assert!(!manifest.is_fingerprinted(Path::new("public/css/site.css")));
+    }
+}
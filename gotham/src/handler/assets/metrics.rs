@@ -0,0 +1,169 @@
+//! Defines `FileMetrics`, a hook for observing the requests served by
+//! static file handlers.
+
+use futures::{Async, Future, Poll, Stream};
+use hyper::body::Payload;
+use hyper::header::CONTENT_LENGTH;
+use hyper::{Body, Chunk, StatusCode, Uri};
+
+use std::panic::RefUnwindSafe;
+use std::sync::Arc;
+use std::time::{Duration, Instant};
+
+use crate::handler::HandlerFuture;
+use crate::state::{FromState, State};
+
+/// Receives a record of each request served by a `FileHandler` or `DirHandler`, as configured
+/// by `FileOptions::with_metrics`.
+///
+/// ```rust
+/// # extern crate gotham;
+/// # extern crate hyper;
+/// # use std::sync::atomic::{AtomicU64, Ordering};
+/// # use std::time::Duration;
+/// # use gotham::handler::assets::{FileMetrics, FileOptions};
+/// # use hyper::StatusCode;
+/// #
+/// #[derive(Default)]
+/// struct BytesSent(AtomicU64);
+///
+/// impl FileMetrics for BytesSent {
+///     fn record(&self, _path: &str, _status: StatusCode, bytes_sent: u64, _duration: Duration) {
+///         self.0.fetch_add(bytes_sent, Ordering::Relaxed);
+///     }
+/// }
+///
+/// FileOptions::new("my_static_path")
+///     .with_metrics(BytesSent::default())
+///     .build();
+/// ```
+pub trait FileMetrics: Send + Sync + RefUnwindSafe {
+    /// Called once the response for the request `path` has been sent, or the client has gone
+    /// away. `bytes_sent` counts only the body, and `duration` runs from the start of handling
+    /// until the last of the body was sent.
+    fn record(&self, path: &str, status: StatusCode, bytes_sent: u64, duration: Duration);
+}
+
+// The measurements for a single request, which are recorded exactly once.
+struct Measurement {
+    metrics: Arc<dyn FileMetrics>,
+    path: String,
+    status: StatusCode,
+    start: Instant,
+    bytes_sent: u64,
+}
+
+impl Measurement {
+    fn finish(self) {
+        self.metrics.record(
+            &self.path,
+            self.status,
+            self.bytes_sent,
+            self.start.elapsed(),
+        );
+    }
+}
+
+// Wraps a response body, to count the bytes sent and record the measurement at the end. When
+// the length of the body is known, the measurement is recorded as the last chunk is sent.
+struct MeteredBody {
+    body: Body,
+    content_length: Option<u64>,
+    measurement: Option<Measurement>,
+}
+
+impl Stream for MeteredBody {
+    type Item = Chunk;
+    type Error = hyper::Error;
+
+    fn poll(&mut self) -> Poll<Option<Chunk>, hyper::Error> {
+        let result = self.body.poll();
+        match result {
+            Ok(Async::Ready(Some(ref chunk))) => {
+                if let Some(ref mut measurement) = self.measurement {
+                    measurement.bytes_sent += chunk.len() as u64;
+                }
+                let complete = match (self.content_length, self.measurement.as_ref()) {
+                    (Some(len), Some(measurement)) => measurement.bytes_sent >= len,
+                    _ => false,
+                };
+                if complete {
+                    if let Some(measurement) = self.measurement.take() {
+                        measurement.finish();
+                    }
+                }
+            }
+            Ok(Async::NotReady) => (),
+            Ok(Async::Ready(None)) | Err(_) => {
+                if let Some(measurement) = self.measurement.take() {
+                    measurement.finish();
+                }
+            }
+        }
+        result
+    }
+}
+
+impl Drop for MeteredBody {
+    fn drop(&mut self) {
+        if let Some(measurement) = self.measurement.take() {
+            measurement.finish();
+        }
+    }
+}
+
+/// Handles the request with `handle`, recording the outcome with `metrics` if given.
+pub(super) fn observe<F>(
+    metrics: Option<Arc<dyn FileMetrics>>,
+    state: State,
+    handle: F,
+) -> Box<HandlerFuture>
+where
+    F: FnOnce(State) -> Box<HandlerFuture>,
+{
+    let metrics = match metrics {
+        Some(metrics) => metrics,
+        None => return handle(state),
+    };
+    let path = Uri::borrow_from(&state).path().to_owned();
+    let start = Instant::now();
+    let response = handle(state);
+
+    Box::new(response.then(move |result| {
+        let mut measurement = Measurement {
+            metrics,
+            path,
+            status: StatusCode::OK,
+            start,
+            bytes_sent: 0,
+        };
+
+        match result {
+            Ok((state, response)) => {
+                measurement.status = response.status();
+                if response.body().is_end_stream() {
+                    measurement.finish();
+                    return Ok((state, response));
+                }
+                let content_length = response
+                    .headers()
+                    .get(CONTENT_LENGTH)
+                    .and_then(|len| len.to_str().ok())
+                    .and_then(|len| len.parse().ok());
+                let response = response.map(|body| {
+                    Body::wrap_stream(MeteredBody {
+                        body,
+                        content_length,
+                        measurement: Some(measurement),
+                    })
+                });
+                Ok((state, response))
+            }
+            Err((state, err)) => {
+                measurement.status = err.status();
+                measurement.finish();
+                Err((state, err))
+            }
+        }
+    }))
+}
@@ -1,37 +1,99 @@
 //! Defines handlers for static assets, used by `to_file` and `to_dir` routes.
 //! Both 'If-None-Match' (etags) and 'If-Modified-Since' are supported to check
-//! file modification.
-//! Side-by-side compressed files for gzip and brotli are supported if enabled
+//! file modification, and 'Last-Modified' is sent from the file metadata. The
+//! 'If-Match' and 'If-Unmodified-Since' preconditions are also checked.
+//! Side-by-side compressed files for gzip and brotli are supported if enabled,
+//! and other text files can optionally be gzipped on the fly.
+//! Byte ranges requested via the 'Range' header are served as partial content, subject
+//! to 'If-Range', with multiple ranges sent as a "multipart/byteranges" body.
+//! Files are opened and read on the Tokio blocking thread pool, and bodies are streamed
+//! in block sized chunks rather than being read into memory up front, unless small
+//! files are kept in an optional in-memory cache.
+//! Requests for a directory are served an index file if present, and can optionally
+//! be rendered as an HTML listing by `DirHandler`.
+//! Files are read through a `FileSystem`, which defaults to the local filesystem but can be
+//! replaced to serve files from elsewhere, such as an archive or an object store.
+//! Files compiled into the binary can be served by `EmbeddedFileHandler`, and the
+//! fingerprinted files named by an `AssetManifest` are served with long-lived caching headers.
 //! See 'FileOptions' for more details.
 
 mod accepted_encoding;
+mod cache;
+mod compress;
+mod embedded;
+mod listing;
+mod manifest;
+mod metrics;
+mod range;
+mod source;
+mod vfs;
 
 use crate::error::Result;
-use bytes::{BufMut, BytesMut};
+use bytes::{BufMut, Bytes, BytesMut};
+use futures::future::{self, Either, Loop};
 use futures::{stream, try_ready, Future, Stream};
 use http;
-use httpdate::parse_http_date;
+use httpdate::{fmt_http_date, parse_http_date};
 use hyper::header::*;
-use hyper::{Body, Chunk, Response, StatusCode};
+use hyper::{Body, Chunk, Method, Response, StatusCode, Uri};
 use log::debug;
 use mime::{self, Mime};
 use mime_guess::from_path;
+use percent_encoding::{utf8_percent_encode, AsciiSet, NON_ALPHANUMERIC};
 use serde_derive::Deserialize;
 use tokio::fs::File;
 use tokio::io::AsyncRead;
+use uuid::Uuid;
 
 use self::accepted_encoding::accepted_encodings;
+use self::cache::FileCache;
+use self::compress::{accepts_gzip, gzip_stream, is_compressible};
+use self::listing::sort_entries;
+use self::metrics::observe;
+use self::range::{requested_ranges, ByteRange, RangeRequest};
+use self::source::BodyPart;
 use crate::handler::{Handler, HandlerFuture, IntoHandlerError, NewHandler};
+use crate::helpers::blocking::run_blocking;
 use crate::router::response::extender::StaticResponseExtender;
 use crate::state::{FromState, State, StateData};
 
+pub use self::embedded::EmbeddedFileHandler;
+pub use self::listing::{render_directory_listing, DirectoryEntry};
+pub use self::manifest::AssetManifest;
+pub use self::metrics::FileMetrics;
+pub use self::source::{FileSource, FileStream, ReadFile};
+pub use self::vfs::{
+    FileMetadata, FileSystem, MetadataFuture, OpenFuture, ReadDirFuture, StdFileSystem,
+};
+
 use std::cmp;
+use std::collections::{HashMap, VecDeque};
 use std::convert::From;
-use std::fs::Metadata;
-use std::io;
+use std::fmt;
+use std::io::{self, SeekFrom};
 use std::iter::FromIterator;
+use std::panic::RefUnwindSafe;
 use std::path::{Component, Path, PathBuf};
-use std::time::UNIX_EPOCH;
+use std::sync::Arc;
+use std::time::{Duration, SystemTime, UNIX_EPOCH};
+
+// Characters allowed unencoded in an extended header parameter, per RFC 5987.
+const ATTR_CHAR: &AsciiSet = &NON_ALPHANUMERIC
+    .remove(b'!')
+    .remove(b'#')
+    .remove(b'$')
+    .remove(b'&')
+    .remove(b'+')
+    .remove(b'-')
+    .remove(b'.')
+    .remove(b'^')
+    .remove(b'_')
+    .remove(b'`')
+    .remove(b'|')
+    .remove(b'~');
+
+// The "max-age" sent for fingerprinted files, which is one year.
+const FINGERPRINTED_MAX_AGE: Duration = Duration::from_secs(31_536_000);
 
 /// Represents a handler for any files under a directory.
 #[derive(Clone)]
@@ -57,13 +119,23 @@ pub struct FileHandler {
 ///
 /// ```rust
 /// # extern crate gotham;
-/// # use gotham::handler::assets::FileOptions;
+/// # use gotham::handler::assets::{FileOptions, SymlinkPolicy};
 ///
 /// let default_options = FileOptions::from("my_static_path");
 /// let from_builder = FileOptions::new("my_static_path")
 ///     .with_cache_control("public")
+///     .with_immutable(false)
 ///     .with_gzip(false)
 ///     .with_brotli(false)
+///     .with_etag(true)
+///     .with_dynamic_gzip(false)
+///     .with_compression_min_size(1024)
+///     .with_compression_level(6)
+///     .with_directory_listing(false)
+///     .with_index_files(&["index.html"])
+///     .with_symlink_policy(SymlinkPolicy::AllowAll)
+///     .with_dotfiles(true)
+///     .with_charset(Some("utf-8"))
 ///     .build();
 ///
 /// assert_eq!(default_options, from_builder);
@@ -72,8 +144,77 @@ pub struct FileHandler {
 pub struct FileOptions {
     path: PathBuf,
     cache_control: String,
+    max_age: Option<Duration>,
+    immutable: bool,
     gzip: bool,
     brotli: bool,
+    etag: bool,
+    dynamic_gzip: bool,
+    compression_min_size: u64,
+    compression_level: u32,
+    directory_listing: bool,
+    listing_renderer: Option<Callback<ListingRenderer>>,
+    index_files: Vec<String>,
+    attachment: Option<String>,
+    symlink_policy: SymlinkPolicy,
+    fallback_roots: Vec<PathBuf>,
+    dotfiles: bool,
+    max_file_size: Option<u64>,
+    oversized_not_found: bool,
+    allowed_extensions: Option<Vec<String>>,
+    denied_extensions: Vec<String>,
+    errors: ErrorResponses,
+    cache: Option<FileCache>,
+    mime_types: HashMap<String, Mime>,
+    charset: Option<String>,
+    metrics: Option<Callback<dyn FileMetrics>>,
+    manifest: Option<AssetManifest>,
+    file_system: Option<Callback<dyn FileSystem>>,
+}
+
+// Responses used in place of the default, empty, error responses.
+#[derive(Clone, Debug, Default, PartialEq)]
+struct ErrorResponses {
+    pages: Vec<(StatusCode, PathBuf)>,
+    not_found_handler: Option<Callback<FallbackHandler>>,
+}
+
+type FallbackHandler = dyn Fn(State) -> Box<HandlerFuture> + Send + Sync + RefUnwindSafe;
+
+/// Determines whether a `DirHandler` will serve files which are reached through a symbolic
+/// link beneath its root directory. Requests which violate the policy are treated as not found.
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub enum SymlinkPolicy {
+    /// Symbolic links are never followed.
+    Deny,
+    /// Symbolic links are followed only if the file they resolve to is within the root.
+    AllowWithinRoot,
+    /// Symbolic links are always followed, wherever they lead.
+    AllowAll,
+}
+
+type ListingRenderer = dyn Fn(&str, &[DirectoryEntry]) -> String + Send + Sync + RefUnwindSafe;
+
+// Wraps a function provided by the application, so that `FileOptions` can still be compared
+// and printed. Two callbacks are only equal if they were cloned from the same value.
+struct Callback<T: ?Sized>(Arc<T>);
+
+impl<T: ?Sized> Clone for Callback<T> {
+    fn clone(&self) -> Self {
+        Callback(self.0.clone())
+    }
+}
+
+impl<T: ?Sized> PartialEq for Callback<T> {
+    fn eq(&self, other: &Self) -> bool {
+        Arc::ptr_eq(&self.0, &other.0)
+    }
+}
+
+impl<T: ?Sized> fmt::Debug for Callback<T> {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        f.write_str("Callback")
+    }
 }
 
 impl FileOptions {
@@ -85,8 +226,32 @@ impl FileOptions {
         FileOptions {
             path: PathBuf::from(path),
             cache_control: "public".to_string(),
+            max_age: None,
+            immutable: false,
             gzip: false,
             brotli: false,
+            etag: true,
+            dynamic_gzip: false,
+            compression_min_size: 1024,
+            compression_level: 6,
+            directory_listing: false,
+            listing_renderer: None,
+            index_files: vec!["index.html".to_string()],
+            attachment: None,
+            symlink_policy: SymlinkPolicy::AllowAll,
+            fallback_roots: vec![],
+            dotfiles: true,
+            max_file_size: None,
+            oversized_not_found: false,
+            allowed_extensions: None,
+            denied_extensions: vec![],
+            errors: ErrorResponses::default(),
+            cache: None,
+            mime_types: HashMap::new(),
+            charset: Some("utf-8".to_string()),
+            metrics: None,
+            manifest: None,
+            file_system: None,
         }
     }
 
@@ -96,6 +261,49 @@ impl FileOptions {
         self
     }
 
+    /// Adds a "max-age" directive to the "cache_control" header, and a matching "expires" header
+    /// for HTTP/1.0 caches, allowing responses to be cached for the given duration (defaults to
+    /// not set).
+    ///
+    /// ```rust
+    /// # extern crate gotham;
+    /// # use std::time::Duration;
+    /// # use gotham::handler::assets::FileOptions;
+    /// #
+    /// // Sends "cache-control: private, max-age=3600"
+    /// FileOptions::new("my_static_path")
+    ///     .with_cache_control("private")
+    ///     .with_max_age(Duration::from_secs(3600))
+    ///     .build();
+    /// ```
+    pub fn with_max_age(&mut self, max_age: Duration) -> &mut Self {
+        self.max_age = Some(max_age);
+        self
+    }
+
+    /// If `true`, adds the "immutable" directive to the "cache_control" header, indicating that
+    /// files will never change while cached (defaults to false). Intended for fingerprinted
+    /// assets, generally alongside a long `with_max_age`.
+    pub fn with_immutable(&mut self, immutable: bool) -> &mut Self {
+        self.immutable = immutable;
+        self
+    }
+
+    // Builds the value of the "cache_control" header from the configured directives.
+    fn cache_control_header(&self) -> String {
+        let mut directives = vec![];
+        if !self.cache_control.is_empty() {
+            directives.push(self.cache_control.clone());
+        }
+        if let Some(max_age) = self.max_age {
+            directives.push(format!("max-age={}", max_age.as_secs()));
+        }
+        if self.immutable {
+            directives.push("immutable".to_string());
+        }
+        directives.join(", ")
+    }
+
     /// If `true`, given a request for FILE, serves FILE.gz if it exists in the static directory and
     /// if the accept-encoding header is set to allow gzipped content (defaults to false).
     pub fn with_gzip(&mut self, gzip: bool) -> &mut Self {
@@ -110,6 +318,350 @@ impl FileOptions {
         self
     }
 
+    /// If `true`, responses include an 'ETag' header derived from the file size and modification
+    /// time, and requests with a matching 'If-None-Match' header receive a '304 Not Modified'
+    /// response (defaults to true). Asset pipelines which fingerprint file names can disable
+    /// this, in which case 'If-None-Match' is ignored.
+    pub fn with_etag(&mut self, etag: bool) -> &mut Self {
+        self.etag = etag;
+        self
+    }
+
+    /// If `true`, text, JavaScript and JSON files are gzip compressed as they are served, when the
+    /// client accepts gzipped content and no precompressed file is available (defaults to false).
+    /// Compressed responses are sent without a 'Content-Length', and do not support ranges.
+    pub fn with_dynamic_gzip(&mut self, dynamic_gzip: bool) -> &mut Self {
+        self.dynamic_gzip = dynamic_gzip;
+        self
+    }
+
+    /// Sets the size in bytes below which files are not compressed on the fly, as the overhead
+    /// outweighs the saving for very small files (defaults to 1024).
+    pub fn with_compression_min_size(&mut self, min_size: u64) -> &mut Self {
+        self.compression_min_size = min_size;
+        self
+    }
+
+    /// Sets the gzip level used for on the fly compression, from 0 (none) to 9 (best)
+    /// (defaults to 6).
+    pub fn with_compression_level(&mut self, level: u32) -> &mut Self {
+        self.compression_level = cmp::min(level, 9);
+        self
+    }
+
+    /// If `true`, requests to a `DirHandler` which resolve to a directory receive an HTML listing
+    /// of the directory contents (defaults to false). Otherwise, such requests are treated as
+    /// not found.
+    pub fn with_directory_listing(&mut self, directory_listing: bool) -> &mut Self {
+        self.directory_listing = directory_listing;
+        self
+    }
+
+    /// Sets the function used to render directory listings, when enabled via
+    /// `with_directory_listing` (defaults to `render_directory_listing`).
+    ///
+    /// The function receives the request path of the directory and its entries, sorted with
+    /// directories first and then by name, and returns an HTML document.
+    pub fn with_directory_listing_renderer<F>(&mut self, renderer: F) -> &mut Self
+    where
+        F: Fn(&str, &[DirectoryEntry]) -> String + Send + Sync + RefUnwindSafe + 'static,
+    {
+        self.listing_renderer = Some(Callback(Arc::new(renderer)));
+        self
+    }
+
+    /// Sets the file names which are tried, in order, when a request to a `DirHandler` resolves to
+    /// a directory (defaults to "index.html"). The first file that exists in the directory is
+    /// served. If none exist, a listing is rendered when enabled via `with_directory_listing`.
+    ///
+    /// An empty list disables index files.
+    pub fn with_index_files<S: AsRef<str>>(&mut self, index_files: &[S]) -> &mut Self {
+        self.index_files = index_files.iter().map(|f| f.as_ref().to_owned()).collect();
+        self
+    }
+
+    /// Serves files as a download, by sending a "content-disposition" header which names the
+    /// downloaded file `filename` (defaults to not set, so files are displayed inline).
+    ///
+    /// Names which are not plain ASCII are encoded as described in RFC 6266, with an ASCII
+    /// fallback for older clients.
+    pub fn with_attachment(&mut self, filename: &str) -> &mut Self {
+        self.attachment = Some(filename.to_owned());
+        self
+    }
+
+    /// Sets how symbolic links beneath the root directory of a `DirHandler` are treated
+    /// (defaults to `SymlinkPolicy::AllowAll`).
+    pub fn with_symlink_policy(&mut self, symlink_policy: SymlinkPolicy) -> &mut Self {
+        self.symlink_policy = symlink_policy;
+        self
+    }
+
+    /// Sets further directories which a `DirHandler` searches, in order, for files which do not
+    /// exist beneath its root (defaults to none). This allows a directory of overrides, such as
+    /// a theme, to be layered over a directory of defaults.
+    ///
+    /// ```rust
+    /// # extern crate gotham;
+    /// # use gotham::handler::assets::FileOptions;
+    /// #
+    /// // Files in "theme" take precedence over those in "defaults"
+    /// FileOptions::new("theme")
+    ///     .with_fallback_roots(&["defaults"])
+    ///     .build();
+    /// ```
+    pub fn with_fallback_roots<P: AsRef<Path>>(&mut self, roots: &[P]) -> &mut Self {
+        self.fallback_roots = roots.iter().map(|r| r.as_ref().to_path_buf()).collect();
+        self
+    }
+
+    /// If `false`, requests to a `DirHandler` for any path with a segment starting with '.', such
+    /// as "/.env" or "/.git/config", are treated as not found, whether or not the file exists
+    /// (defaults to true).
+    pub fn with_dotfiles(&mut self, dotfiles: bool) -> &mut Self {
+        self.dotfiles = dotfiles;
+        self
+    }
+
+    /// Refuses to serve files larger than `max_size` bytes, responding with `403 Forbidden`
+    /// instead (defaults to no limit). This guards against large build artifacts or backups
+    /// which happen to be beneath a static directory being served to anyone who asks.
+    pub fn with_max_file_size(&mut self, max_size: u64) -> &mut Self {
+        self.max_file_size = Some(max_size);
+        self
+    }
+
+    /// If `true`, requests for files over the limit set by `with_max_file_size` are treated as
+    /// not found, hiding the existence of the file, rather than forbidden (defaults to false).
+    pub fn with_oversized_not_found(&mut self, not_found: bool) -> &mut Self {
+        self.oversized_not_found = not_found;
+        self
+    }
+
+    // Returns an error if the file described by `meta` is over the configured maximum size.
+    fn check_file_size(&self, meta: &FileMetadata) -> io::Result<()> {
+        match self.max_file_size {
+            Some(max_size) if meta.is_file() && meta.len() > max_size => {
+                let kind = if self.oversized_not_found {
+                    io::ErrorKind::NotFound
+                } else {
+                    io::ErrorKind::PermissionDenied
+                };
+                Err(io::Error::new(kind, "file exceeds the maximum size"))
+            }
+            _ => Ok(()),
+        }
+    }
+
+    /// Restricts the files which are served to those with one of the given extensions, such as
+    /// "css" or "png", compared case-insensitively (defaults to serving any extension). Requests
+    /// for other files, including those without an extension, are treated as not found.
+    ///
+    /// ```rust
+    /// # extern crate gotham;
+    /// # use gotham::handler::assets::FileOptions;
+    /// #
+    /// FileOptions::new("my_static_path")
+    ///     .with_allowed_extensions(&["css", "js", "png"])
+    ///     .build();
+    /// ```
+    pub fn with_allowed_extensions<S: AsRef<str>>(&mut self, extensions: &[S]) -> &mut Self {
+        self.allowed_extensions = Some(normalize_extensions(extensions));
+        self
+    }
+
+    /// Treats requests for files with any of the given extensions, such as "php" or "bak", as
+    /// not found even if the file exists (defaults to none). This takes precedence over
+    /// `with_allowed_extensions`.
+    pub fn with_denied_extensions<S: AsRef<str>>(&mut self, extensions: &[S]) -> &mut Self {
+        self.denied_extensions = normalize_extensions(extensions);
+        self
+    }
+
+    // Determines whether the extension of the file is permitted by the allowed and denied lists.
+    fn extension_allowed(&self) -> bool {
+        self.is_allowed_extension(&self.path)
+    }
+
+    // Determines whether the extension of the file at `path` is permitted by the allowed and
+    // denied lists.
+    fn is_allowed_extension(&self, path: &Path) -> bool {
+        let extension = path
+            .extension()
+            .map(|ext| ext.to_string_lossy().to_lowercase());
+
+        match extension {
+            Some(ref ext) if self.denied_extensions.contains(ext) => false,
+            Some(ref ext) => match self.allowed_extensions {
+                Some(ref allowed) => allowed.contains(ext),
+                None => true,
+            },
+            None => self.allowed_extensions.is_none(),
+        }
+    }
+
+    // Determines whether an entry is named in directory listings, which only name the hidden
+    // files and extensions which are served.
+    fn is_listed(&self, entry: &DirectoryEntry) -> bool {
+        (self.dotfiles || !entry.name.starts_with('.'))
+            && (entry.is_dir || self.is_allowed_extension(Path::new(&entry.name)))
+    }
+
+    /// Serves the file at `path` as the body of responses with the given status, such as
+    /// `404 Not Found` for missing files or `403 Forbidden` for unreadable ones (defaults to
+    /// none, so error responses are empty).
+    ///
+    /// ```rust
+    /// # extern crate gotham;
+    /// # extern crate hyper;
+    /// # use gotham::handler::assets::FileOptions;
+    /// # use hyper::StatusCode;
+    /// #
+    /// FileOptions::new("my_static_path")
+    ///     .with_error_page(StatusCode::NOT_FOUND, "my_static_path/404.html")
+    ///     .build();
+    /// ```
+    pub fn with_error_page<P: AsRef<Path>>(&mut self, status: StatusCode, path: P) -> &mut Self {
+        self.errors.pages.retain(|&(s, _)| s != status);
+        self.errors
+            .pages
+            .push((status, path.as_ref().to_path_buf()));
+        self
+    }
+
+    /// Delegates requests for files which are not found to the given handler, for example to
+    /// render a branded page or to fall back to an application route (defaults to none). This
+    /// takes precedence over an error page registered for `404 Not Found`.
+    pub fn with_not_found_handler<NH>(&mut self, new_handler: NH) -> &mut Self
+    where
+        NH: NewHandler + 'static,
+    {
+        let handler = move |state: State| match new_handler.new_handler() {
+            Ok(handler) => handler.handle(state),
+            Err(e) => Box::new(future::err((state, e.compat().into_handler_error())))
+                as Box<HandlerFuture>,
+        };
+        self.errors.not_found_handler = Some(Callback(Arc::new(handler)));
+        self
+    }
+
+    /// Keeps the contents of recently served files in memory, so that frequently requested files
+    /// are not read from disk each time (defaults to disabled). Up to `max_size` bytes are held
+    /// in total, with the least recently used files evicted first, and files larger than
+    /// `max_entry_size` bytes are always read from disk.
+    ///
+    /// Cached contents are only used while the size and modification time of the file are
+    /// unchanged. The cache is shared by all handlers created from these options.
+    ///
+    /// ```rust
+    /// # extern crate gotham;
+    /// # use gotham::handler::assets::FileOptions;
+    /// #
+    /// // Holds up to 16MiB of files which are at most 256KiB each
+    /// FileOptions::new("my_static_path")
+    ///     .with_memory_cache(16 * 1024 * 1024, 256 * 1024)
+    ///     .build();
+    /// ```
+    pub fn with_memory_cache(&mut self, max_size: u64, max_entry_size: u64) -> &mut Self {
+        self.cache = Some(FileCache::new(max_size, max_entry_size));
+        self
+    }
+
+    /// Serves files with the given extension as `mime_type`, in place of the type guessed from
+    /// the extension (defaults to none). The extension is matched case insensitively, without
+    /// the leading dot.
+    ///
+    /// ```rust
+    /// # extern crate gotham;
+    /// # extern crate mime;
+    /// # use gotham::handler::assets::FileOptions;
+    /// #
+    /// # fn main() {
+    /// FileOptions::new("my_static_path")
+    ///     .with_mime_type("wasm", "application/wasm".parse().unwrap())
+    ///     .with_mime_type("map", mime::APPLICATION_JSON)
+    ///     .build();
+    /// # }
+    /// ```
+    pub fn with_mime_type(&mut self, extension: &str, mime_type: Mime) -> &mut Self {
+        let extension = normalize_extension(extension);
+        self.mime_types.insert(extension, mime_type);
+        self
+    }
+
+    /// Sets the charset parameter added to the "content-type" of text, HTML, CSS and JavaScript
+    /// files (defaults to "utf-8"). `None` sends the guessed type as-is, leaving browsers to
+    /// detect the charset.
+    pub fn with_charset(&mut self, charset: Option<&str>) -> &mut Self {
+        self.charset = charset.map(str::to_owned);
+        self
+    }
+
+    // Determines the type of the file at the `FileOptions` path, preferring any override
+    // registered for its extension, with the configured charset for text types.
+    fn mime_type(&self) -> Mime {
+        let mime_type = self
+            .path
+            .extension()
+            .and_then(|ext| self.mime_types.get(&ext.to_string_lossy().to_lowercase()))
+            .cloned()
+            .unwrap_or_else(|| mime_for_path(&self.path));
+
+        match self.charset {
+            Some(ref charset)
+                if is_text(&mime_type) && mime_type.get_param(mime::CHARSET).is_none() =>
+            {
+                format!("{}; charset={}", mime_type, charset)
+                    .parse()
+                    .unwrap_or(mime_type)
+            }
+            _ => mime_type,
+        }
+    }
+
+    /// Records the path, status, bytes sent and duration of each request served with these
+    /// options with the given `FileMetrics` (defaults to none).
+    pub fn with_metrics<M: FileMetrics + 'static>(&mut self, metrics: M) -> &mut Self {
+        self.metrics = Some(Callback(Arc::new(metrics)));
+        self
+    }
+
+    // Returns the configured `FileMetrics`, if any.
+    fn metrics(&self) -> Option<Arc<dyn FileMetrics>> {
+        self.metrics.as_ref().map(|metrics| metrics.0.clone())
+    }
+
+    /// Serves the fingerprinted files named in the given `AssetManifest` with a "max-age" of one
+    /// year and the "immutable" directive, since their names change whenever their contents do
+    /// (defaults to none). Other files are served with the configured caching headers.
+    pub fn with_manifest(&mut self, manifest: AssetManifest) -> &mut Self {
+        self.manifest = Some(manifest);
+        self
+    }
+
+    // Determines whether the file is fingerprinted by the configured manifest.
+    fn is_fingerprinted(&self) -> bool {
+        match self.manifest {
+            Some(ref manifest) => manifest.is_fingerprinted(&self.path),
+            None => false,
+        }
+    }
+
+    /// Reads files from the given `FileSystem` rather than the local filesystem (defaults to
+    /// `StdFileSystem`). The `SymlinkPolicy` is only enforced by the default.
+    pub fn with_file_system<F: FileSystem + 'static>(&mut self, file_system: F) -> &mut Self {
+        self.file_system = Some(Callback(Arc::new(file_system)));
+        self
+    }
+
+    // Returns the configured `FileSystem`.
+    fn file_system(&self) -> Arc<dyn FileSystem> {
+        match self.file_system {
+            Some(Callback(ref file_system)) => file_system.clone(),
+            None => Arc::new(StdFileSystem),
+        }
+    }
+
     /// Clones `self` to return an owned value for passing to a handler.
     pub fn build(&mut self) -> Self {
         self.clone()
@@ -147,6 +699,30 @@ impl FileHandler {
     }
 }
 
+impl FileHandler {
+    /// Serves the file as a download named `filename`, rather than inline.
+    ///
+    /// ```rust
+    /// # extern crate gotham;
+    /// # use gotham::handler::assets::FileHandler;
+    /// # use gotham::router::builder::*;
+    /// #
+    /// # fn main() {
+    /// build_simple_router(|route| {
+    ///     route
+    ///         .get("/report")
+    ///         .to_new_handler(FileHandler::new("reports/latest.pdf").as_attachment("report.pdf"));
+    /// });
+    /// # }
+    /// ```
+    ///
+    /// See `FileOptions::with_attachment` for details.
+    pub fn as_attachment(mut self, filename: &str) -> FileHandler {
+        self.options.with_attachment(filename);
+        self
+    }
+}
+
 impl DirHandler {
     /// Create a new `DirHandler` with the given root path.
     pub fn new<P>(path: P) -> DirHandler
@@ -177,109 +753,528 @@ impl NewHandler for DirHandler {
 
 impl Handler for DirHandler {
     fn handle(self, state: State) -> Box<HandlerFuture> {
-        let path = {
-            let mut base_path = self.options.path;
-            let file_path = PathBuf::from_iter(&FilePathExtractor::borrow_from(&state).parts);
-            base_path.extend(&normalize_path(&file_path));
-            base_path
+        observe(self.options.metrics(), state, |state| self.serve(state))
+    }
+}
+
+impl DirHandler {
+    // Serves the file or directory beneath the root which the request path resolves to.
+    fn serve(self, state: State) -> Box<HandlerFuture> {
+        let root = self.options.path.clone();
+        // Segments are percent decoded by the router, so any which still contain a separator
+        // or NUL were encoded to evade normalization, and are never served.
+        let file_path = {
+            let parts = &FilePathExtractor::borrow_from(&state).parts;
+            if !parts.iter().all(|part| is_safe_segment(part)) {
+                let err = io::Error::new(io::ErrorKind::NotFound, "unsafe path segment");
+                return create_error_response(self.options.errors, state, err);
+            }
+            normalize_path(&PathBuf::from_iter(parts))
         };
-        create_file_response(
-            FileOptions {
-                path,
-                ..self.options
-            },
-            state,
-        )
+
+        if !self.options.dotfiles && is_hidden(&file_path) {
+            let err = io::Error::new(io::ErrorKind::NotFound, "hidden files are not served");
+            return create_error_response(self.options.errors, state, err);
+        }
+        // Symbolic links only exist on the local filesystem.
+        let policy = match self.options.file_system {
+            Some(_) => SymlinkPolicy::AllowAll,
+            None => self.options.symlink_policy,
+        };
+        let file_system = self.options.file_system();
+        let mut roots = vec![root];
+        roots.extend(self.options.fallback_roots.iter().cloned());
+
+        let resolved =
+            resolve_path(file_system.clone(), roots, &file_path).and_then(move |(root, path)| {
+                if policy == SymlinkPolicy::AllowAll {
+                    Either::A(future::ok(path))
+                } else {
+                    Either::B(run_blocking(move || {
+                        check_symlink_policy(&root, &path, policy).map(|()| path.clone())
+                    }))
+                }
+            });
+
+        let options = self.options;
+        Box::new(resolved.then(move |result| match result {
+            Err(err) => create_error_response(options.errors, state, err),
+            Ok(path) => {
+                let options = FileOptions { path, ..options };
+                if options.index_files.is_empty() && !options.directory_listing {
+                    return create_file_response(options, state);
+                }
+                Box::new(
+                    file_system
+                        .metadata(&options.path)
+                        .then(move |result| match result {
+                            Ok(ref meta) if meta.is_dir() => {
+                                create_directory_response(options, state)
+                            }
+                            _ => create_file_response(options, state),
+                        }),
+                )
+            }
+        }))
     }
 }
 
 impl Handler for FileHandler {
     fn handle(self, state: State) -> Box<HandlerFuture> {
-        create_file_response(self.options, state)
+        let options = self.options;
+        observe(options.metrics(), state, |state| {
+            create_file_response(options, state)
+        })
     }
 }
 
 // Creates the `HandlerFuture` response based on the given `FileOptions`.
-fn create_file_response(options: FileOptions, state: State) -> Box<HandlerFuture> {
-    let mime_type = mime_for_path(&options.path);
+fn create_file_response(mut options: FileOptions, state: State) -> Box<HandlerFuture> {
+    if !options.extension_allowed() {
+        let err = io::Error::new(io::ErrorKind::NotFound, "file extension is not served");
+        return create_error_response(options.errors, state, err);
+    }
+    if options.is_fingerprinted() {
+        options.max_age = Some(FINGERPRINTED_MAX_AGE);
+        options.immutable = true;
+    }
+    let mime_type = options.mime_type();
     let headers = HeaderMap::borrow_from(&state).clone();
+    let method = Method::borrow_from(&state);
+    let ranges_allowed = method == Method::GET;
+    // Responses to HEAD requests carry the same headers as GET, but the file is never read.
+    let head = method == Method::HEAD;
+
+    let compressed_paths = compressed_paths(&options, &headers);
+    let errors = options.errors.clone();
+    let file_system = options.file_system();
+
+    // Finds the path to read, along with an optional encoding to return as the
+    // "Content-Encoding".
+    let path = options.path.clone();
+    let resolved =
+        find_path(file_system.clone(), compressed_paths, |_| true).map(move |found| match found {
+            Some((path, encoding)) => (path, Some(encoding)),
+            None => (path, None),
+        });
 
-    let (path, encoding) = check_compressed_options(&options, &headers);
-
-    let response_future =
-        File::open(path)
-            .and_then(File::metadata)
-            .and_then(move |(file, meta)| {
-                if not_modified(&meta, &headers) {
-                    return Ok(http::Response::builder()
-                        .status(StatusCode::NOT_MODIFIED)
-                        .body(Body::empty())
-                        .unwrap());
-                }
-                let len = meta.len();
-                let buf_size = optimal_buf_size(&meta);
-
-                let stream = file_stream(file, buf_size, len);
-                let body = Body::wrap_stream(stream);
-                let mut response = http::Response::builder();
-                response.status(StatusCode::OK);
-                response.header(CONTENT_LENGTH, len);
-                response.header(CONTENT_TYPE, mime_type.as_ref());
-                response.header(CACHE_CONTROL, options.cache_control);
+    let response_future = resolved.and_then(move |(path, encoding)| {
+        let cache = options.cache.clone();
+        FileSource::open(file_system, path, cache).and_then(move |(source, meta)| {
+            options.check_file_size(&meta)?;
+            if precondition_failed(&meta, &headers, options.etag) {
+                return Ok(http::Response::builder()
+                    .status(StatusCode::PRECONDITION_FAILED)
+                    .body(Body::empty())
+                    .unwrap());
+            }
+            if not_modified(&meta, &headers, options.etag) {
+                return Ok(http::Response::builder()
+                    .status(StatusCode::NOT_MODIFIED)
+                    .body(Body::empty())
+                    .unwrap());
+            }
+            if meta.is_dir() {
+                return Err(io::Error::new(
+                    io::ErrorKind::NotFound,
+                    "path is a directory",
+                ));
+            }
+            let len = meta.len();
+            let compress = encoding.is_none()
+                && options.dynamic_gzip
+                && len >= options.compression_min_size
+                && is_compressible(&mime_type)
+                && accepts_gzip(&headers);
+
+            let mut response = http::Response::builder();
+            response.header(CACHE_CONTROL, options.cache_control_header());
+            if let Some(max_age) = options.max_age {
+                response.header(EXPIRES, fmt_http_date(SystemTime::now() + max_age));
+            }
+            if !compress {
+                response.header(ACCEPT_RANGES, "bytes");
+            }
 
+            if options.etag {
                 if let Some(etag) = entity_tag(&meta) {
                     response.header(ETAG, etag);
                 }
-                if let Some(content_encoding) = encoding {
-                    response.header(CONTENT_ENCODING, content_encoding);
+            }
+            if let Some(modified) = meta.modified() {
+                response.header(LAST_MODIFIED, fmt_http_date(modified));
+            }
+            if let Some(content_encoding) = encoding {
+                response.header(CONTENT_ENCODING, content_encoding);
+            }
+            if let Some(ref filename) = options.attachment {
+                response.header(CONTENT_DISPOSITION, content_disposition(filename));
+            }
+            // The representation depends on 'Accept-Encoding' whenever compressed
+            // files may be served, even if this response is uncompressed.
+            if options.gzip || options.brotli || options.dynamic_gzip {
+                response.header(VARY, ACCEPT_ENCODING.as_str());
+            }
+
+            let ranges = if ranges_allowed && !compress && if_range(&meta, &headers, options.etag) {
+                requested_ranges(&headers, len)
+            } else {
+                RangeRequest::Full
+            };
+
+            // The parts of the body, read from the file contents, if any.
+            let parts = match ranges {
+                _ if compress => {
+                    response.status(StatusCode::OK);
+                    response.header(CONTENT_TYPE, mime_type.as_ref());
+                    response.header(CONTENT_ENCODING, "gzip");
+                    Some(vec![BodyPart::Range(0, len)])
+                }
+                RangeRequest::Partial(ref ranges) if ranges.len() == 1 => {
+                    let range = ranges[0];
+                    response.status(StatusCode::PARTIAL_CONTENT);
+                    response.header(CONTENT_TYPE, mime_type.as_ref());
+                    response.header(CONTENT_LENGTH, range.len());
+                    response.header(CONTENT_RANGE, range.content_range(len));
+                    Some(vec![BodyPart::Range(range.start, range.len())])
+                }
+                // Requests for multiple ranges which together are no larger than the file are
+                // served as "multipart/byteranges". Otherwise the ranges overlap heavily, and
+                // the whole file is served instead, as allowed by RFC 7233.
+                RangeRequest::Partial(ref ranges)
+                    if ranges.iter().map(ByteRange::len).sum::<u64>() <= len =>
+                {
+                    let boundary = Uuid::new_v4().to_simple().to_string();
+                    let parts = multipart_byteranges(&boundary, &mime_type, ranges, len);
+                    let body_len: u64 = parts
+                        .iter()
+                        .map(|part| match *part {
+                            BodyPart::Range(_, part_len) => part_len,
+                            BodyPart::Bytes(ref bytes) => bytes.len() as u64,
+                        })
+                        .sum();
+
+                    response.status(StatusCode::PARTIAL_CONTENT);
+                    response.header(
+                        CONTENT_TYPE,
+                        format!("multipart/byteranges; boundary={}", boundary),
+                    );
+                    response.header(CONTENT_LENGTH, body_len);
+                    Some(parts)
                 }
+                RangeRequest::Unsatisfiable => {
+                    response.status(StatusCode::RANGE_NOT_SATISFIABLE);
+                    response.header(CONTENT_TYPE, mime_type.as_ref());
+                    response.header(CONTENT_RANGE, format!("bytes */{}", len));
+                    None
+                }
+                _ => {
+                    response.status(StatusCode::OK);
+                    response.header(CONTENT_TYPE, mime_type.as_ref());
+                    response.header(CONTENT_LENGTH, len);
+                    Some(vec![BodyPart::Range(0, len)])
+                }
+            };
 
-                Ok(response.body(body).unwrap())
-            });
-    Box::new(response_future.then(|result| match result {
-        Ok(response) => Ok((state, response)),
-        Err(err) => {
-            let status = match err.kind() {
-                io::ErrorKind::NotFound => StatusCode::NOT_FOUND,
-                io::ErrorKind::PermissionDenied => StatusCode::FORBIDDEN,
-                _ => StatusCode::INTERNAL_SERVER_ERROR,
+            let body = match parts {
+                Some(parts) if !head => {
+                    let stream = source.stream(parts);
+                    if compress {
+                        Body::wrap_stream(gzip_stream(stream, options.compression_level))
+                    } else {
+                        Body::wrap_stream(stream)
+                    }
+                }
+                _ => Body::empty(),
             };
-            Err((state, err.into_handler_error().with_status(status)))
-        }
+            Ok(response.body(body).unwrap())
+        })
+    });
+    Box::new(response_future.then(move |result| match result {
+        Ok(response) => Box::new(future::ok((state, response))),
+        Err(err) => create_error_response(errors, state, err),
     }))
 }
 
-// Checks for existence of compressed files if `FileOptions` and
-// "Accept-Encoding" headers allow. Returns the final path to read,
-// along with an optional encoding to return as the "Content-Encoding".
-fn check_compressed_options(
-    options: &FileOptions,
-    headers: &HeaderMap,
-) -> (PathBuf, Option<String>) {
-    options
-        .path
-        .file_name()
-        .and_then(|filename| {
-            accepted_encodings(headers)
-                .iter()
-                .filter_map(|e| {
-                    get_extension(&e.encoding, &options).map(|ext| (e.encoding.to_string(), ext))
-                })
-                .filter_map(|(encoding, ext)| {
-                    let path = options.path.with_file_name(format!(
-                        "{}.{}",
-                        filename.to_string_lossy(),
-                        ext
-                    ));
-                    if path.exists() {
-                        Some((path, Some(encoding)))
-                    } else {
-                        None
-                    }
-                })
-                .next()
+// Creates the `HandlerFuture` response for a request which resolved to the directory at the
+// `FileOptions` path, serving the first index file found or otherwise a listing if enabled.
+fn create_directory_response(options: FileOptions, state: State) -> Box<HandlerFuture> {
+    let index_paths = options
+        .index_files
+        .iter()
+        .map(|name| (options.path.join(name), ()))
+        .collect();
+    let index = find_path(options.file_system(), index_paths, FileMetadata::is_file);
+
+    Box::new(index.then(move |result| match result {
+        Ok(Some((path, ()))) => create_file_response(FileOptions { path, ..options }, state),
+        Ok(None) if options.directory_listing => create_listing_response(options, state),
+        Ok(None) => create_file_response(options, state),
+        Err(err) => create_error_response(options.errors, state, err),
+    }))
+}
+
+// Creates the `HandlerFuture` response listing the directory at the `FileOptions` path.
+fn create_listing_response(options: FileOptions, state: State) -> Box<HandlerFuture> {
+    let request_path = Uri::borrow_from(&state).path().to_owned();
+    let entries = options.file_system().read_dir(&options.path);
+    let listed = options.clone();
+    let renderer = options.listing_renderer;
+    let cache_control = options.cache_control;
+    let errors = options.errors;
+
+    let response_future = entries
+        .map(move |entries| {
+            // files which aren't served aren't named in listings
+            entries
+                .into_iter()
+                .filter(|entry| listed.is_listed(entry))
+                .collect()
+        })
+        .map(sort_entries)
+        .map(move |entries| {
+            let body = match renderer {
+                Some(Callback(ref renderer)) => renderer(&request_path, &entries),
+                None => render_directory_listing(&request_path, &entries),
+            };
+            http::Response::builder()
+                .status(StatusCode::OK)
+                .header(CONTENT_TYPE, mime::TEXT_HTML_UTF_8.as_ref())
+                .header(CONTENT_LENGTH, body.len())
+                .header(CACHE_CONTROL, cache_control.as_str())
+                .body(Body::from(body))
+                .unwrap()
+        });
+
+    Box::new(response_future.then(move |result| match result {
+        Ok(response) => Box::new(future::ok((state, response))),
+        Err(err) => create_error_response(errors, state, err),
+    }))
+}
+
+// Converts an extension to the form in which extensions are compared, without a leading '.'.
+fn normalize_extension(extension: &str) -> String {
+    extension.trim_start_matches('.').to_lowercase()
+}
+
+fn normalize_extensions<S: AsRef<str>>(extensions: &[S]) -> Vec<String> {
+    extensions
+        .iter()
+        .map(|ext| normalize_extension(ext.as_ref()))
+        .collect()
+}
+
+// Creates the `HandlerFuture` response for an IO error from serving a file, using the
+// not found handler or error pages from `ErrorResponses` where configured.
+fn create_error_response(
+    errors: ErrorResponses,
+    state: State,
+    err: io::Error,
+) -> Box<HandlerFuture> {
+    let status = match err.kind() {
+        io::ErrorKind::NotFound => StatusCode::NOT_FOUND,
+        io::ErrorKind::PermissionDenied => StatusCode::FORBIDDEN,
+        _ => StatusCode::INTERNAL_SERVER_ERROR,
+    };
+
+    if status == StatusCode::NOT_FOUND {
+        if let Some(Callback(handler)) = errors.not_found_handler {
+            return handler(state);
+        }
+    }
+
+    let page = errors
+        .pages
+        .into_iter()
+        .find(|&(s, _)| s == status)
+        .map(|(_, page)| page);
+
+    match page {
+        Some(page) => {
+            let mime_type = mime_for_path(&page);
+            Box::new(tokio::fs::read(page).then(move |result| match result {
+                Ok(body) => {
+                    let response = http::Response::builder()
+                        .status(status)
+                        .header(CONTENT_TYPE, mime_type.as_ref())
+                        .header(CONTENT_LENGTH, body.len())
+                        .body(Body::from(body))
+                        .unwrap();
+                    Ok((state, response))
+                }
+                Err(page_err) => {
+                    debug!("error page could not be read: {}", page_err);
+                    Err((state, err.into_handler_error().with_status(status)))
+                }
+            }))
+        }
+        None => Box::new(future::err((
+            state,
+            err.into_handler_error().with_status(status),
+        ))),
+    }
+}
+
+// Formats a "content-disposition" header for downloading a file with the given name, as
+// described in RFC 6266. Quotes, backslashes and non-ASCII characters are replaced in the
+// plain `filename` parameter, with the exact name given as UTF-8 in `filename*`.
+fn content_disposition(filename: &str) -> String {
+    let fallback: String = filename
+        .chars()
+        .map(|c| match c {
+            '"' | '\\' => '_',
+            c if c.is_ascii() && !c.is_ascii_control() => c,
+            _ => '_',
         })
-        .unwrap_or((options.path.clone(), None))
+        .collect();
+
+    if fallback == filename {
+        format!("attachment; filename=\"{}\"", filename)
+    } else {
+        format!(
+            "attachment; filename=\"{}\"; filename*=UTF-8''{}",
+            fallback,
+            utf8_percent_encode(filename, ATTR_CHAR)
+        )
+    }
+}
+
+// Builds the parts of a "multipart/byteranges" body, as described in RFC 7233, with each range
+// preceded by its own 'Content-Type' and 'Content-Range' headers.
+fn multipart_byteranges(
+    boundary: &str,
+    mime_type: &Mime,
+    ranges: &[ByteRange],
+    len: u64,
+) -> Vec<BodyPart> {
+    let mut parts = Vec::with_capacity(ranges.len() * 2 + 1);
+    for (i, range) in ranges.iter().enumerate() {
+        let separator = if i == 0 { "" } else { "\r\n" };
+        let headers = format!(
+            "{}--{}\r\n{}: {}\r\n{}: {}\r\n\r\n",
+            separator,
+            boundary,
+            CONTENT_TYPE,
+            mime_type,
+            CONTENT_RANGE,
+            range.content_range(len)
+        );
+        parts.push(BodyPart::Bytes(Bytes::from(headers)));
+        parts.push(BodyPart::Range(range.start, range.len()));
+    }
+    parts.push(BodyPart::Bytes(Bytes::from(format!(
+        "\r\n--{}--\r\n",
+        boundary
+    ))));
+    parts
+}
+
+// Determines whether a decoded request path segment can be joined to the root directory as a
+// single path component.
+fn is_safe_segment(segment: &str) -> bool {
+    segment != ".." && !segment.contains(&['/', '\\', '\0'][..])
+}
+
+// Determines whether any segment of a normalized request path names a hidden file or directory.
+fn is_hidden(path: &Path) -> bool {
+    path.components().any(|c| match c {
+        Component::Normal(name) => name.to_string_lossy().starts_with('.'),
+        _ => false,
+    })
+}
+
+// Joins the request path to the first of the roots beneath which it exists, or to the first
+// root if none contain it, returning the root along with the joined path.
+fn resolve_path(
+    file_system: Arc<dyn FileSystem>,
+    roots: Vec<PathBuf>,
+    file_path: &Path,
+) -> impl Future<Item = (PathBuf, PathBuf), Error = io::Error> + Send {
+    let mut candidates: Vec<(PathBuf, PathBuf)> = roots
+        .into_iter()
+        .map(|root| {
+            let mut path = root.clone();
+            path.extend(file_path);
+            (path, root)
+        })
+        .collect();
+
+    let first = candidates[0].clone();
+    // Without fallbacks, the root is used whether or not the file exists.
+    if candidates.len() == 1 {
+        candidates.clear();
+    }
+    find_path(file_system, candidates, |_| true)
+        .map(move |found| found.unwrap_or(first))
+        .map(|(path, root)| (root, path))
+}
+
+// Finds the first of the candidate paths which exists in the `FileSystem` with metadata
+// satisfying the predicate, checking each in turn.
+fn find_path<T: Send + 'static>(
+    file_system: Arc<dyn FileSystem>,
+    candidates: Vec<(PathBuf, T)>,
+    predicate: fn(&FileMetadata) -> bool,
+) -> impl Future<Item = Option<(PathBuf, T)>, Error = io::Error> + Send {
+    future::loop_fn(
+        candidates.into_iter(),
+        move |mut remaining| match remaining.next() {
+            None => Either::A(future::ok(Loop::Break(None))),
+            Some((path, value)) => Either::B(file_system.metadata(&path).then(move |result| {
+                Ok(match result {
+                    Ok(ref meta) if predicate(meta) => Loop::Break(Some((path, value))),
+                    _ => Loop::Continue(remaining),
+                })
+            })),
+        },
+    )
+}
+
+// Checks that the path beneath the root directory is permitted by the `SymlinkPolicy`.
+// Paths which do not exist are allowed here, and fail when the file is opened.
+fn check_symlink_policy(root: &Path, path: &Path, policy: SymlinkPolicy) -> io::Result<()> {
+    let violation = || io::Error::new(io::ErrorKind::NotFound, "symbolic link not permitted");
+
+    match policy {
+        SymlinkPolicy::AllowAll => Ok(()),
+        SymlinkPolicy::Deny => {
+            let mut current = root.to_path_buf();
+            for component in path.strip_prefix(root).unwrap_or(path).components() {
+                current.push(component);
+                match current.symlink_metadata() {
+                    Ok(ref meta) if meta.file_type().is_symlink() => return Err(violation()),
+                    Ok(_) => (),
+                    Err(_) => break,
+                }
+            }
+            Ok(())
+        }
+        SymlinkPolicy::AllowWithinRoot => match (root.canonicalize(), path.canonicalize()) {
+            (Ok(ref root), Ok(ref path)) if !path.starts_with(root) => Err(violation()),
+            _ => Ok(()),
+        },
+    }
+}
+
+// Lists the compressed versions of the file which `FileOptions` and "Accept-Encoding" headers
+// allow, in order of preference, along with the encoding to return as the "Content-Encoding".
+fn compressed_paths(options: &FileOptions, headers: &HeaderMap) -> Vec<(PathBuf, String)> {
+    match options.path.file_name() {
+        Some(filename) => accepted_encodings(headers)
+            .iter()
+            .filter_map(|e| {
+                get_extension(&e.encoding, &options).map(|ext| (e.encoding.to_string(), ext))
+            })
+            .map(|(encoding, ext)| {
+                let path =
+                    options
+                        .path
+                        .with_file_name(format!("{}.{}", filename.to_string_lossy(), ext));
+                (path, encoding)
+            })
+            .collect(),
+        None => vec![],
+    }
 }
 
 // Gets the file extension for the compressed version of a file
@@ -294,6 +1289,14 @@ fn get_extension(encoding: &str, options: &FileOptions) -> Option<String> {
     None
 }
 
+// Determines whether a charset parameter applies to the given type.
+fn is_text(mime_type: &Mime) -> bool {
+    matches!(
+        (mime_type.type_(), mime_type.subtype()),
+        (mime::TEXT, _) | (mime::APPLICATION, mime::JAVASCRIPT)
+    )
+}
+
 fn mime_for_path(path: &Path) -> Mime {
     from_path(path)
         .first()
@@ -315,11 +1318,71 @@ fn normalize_path(path: &Path) -> PathBuf {
         })
 }
 
+// Checks whether the 'If-Match' or 'If-Unmodified-Since' preconditions of a request fail, as
+// described in RFC 7232. 'If-Match' takes precedence, and only matches "*" or the current entity
+// tag (when entity tags are enabled) by strong comparison, so a weak tag on either side never
+// matches. The entity tags given here are weak, so in practice only "*" matches.
+fn precondition_failed(metadata: &FileMetadata, headers: &HeaderMap, etag: bool) -> bool {
+    if headers.contains_key(IF_MATCH) {
+        let current = if etag { entity_tag(metadata) } else { None };
+        let matched = headers
+            .get_all(IF_MATCH)
+            .iter()
+            .filter_map(|v| v.to_str().ok())
+            .flat_map(|v| v.split(','))
+            .map(str::trim)
+            .any(|tag| {
+                tag == "*"
+                    || (!tag.starts_with("W/")
+                        && current
+                            .as_deref()
+                            .is_some_and(|current| !current.starts_with("W/") && tag == current))
+            });
+        return !matched;
+    }
+
+    headers
+        .get(IF_UNMODIFIED_SINCE)
+        .and_then(|v| v.to_str().ok())
+        .and_then(|v| parse_http_date(v).ok())
+        .and_then(|if_unmodified_time| {
+            metadata
+                .modified()
+                .and_then(|modified| parse_http_date(&fmt_http_date(modified)).ok())
+                .map(|modified| modified > if_unmodified_time)
+        })
+        .unwrap_or(false)
+}
+
+// Checks whether the 'Range' header of a request should be honoured, given its 'If-Range'
+// header. The range only applies if the entity tag or modification date the client holds is
+// still current, otherwise the full file is sent so a resumed download is not corrupted.
+fn if_range(metadata: &FileMetadata, headers: &HeaderMap, etag: bool) -> bool {
+    let value = match headers.get(IF_RANGE).and_then(|v| v.to_str().ok()) {
+        Some(value) => value.trim(),
+        None => return true,
+    };
+
+    if value.starts_with('"') || value.starts_with("W/") {
+        etag && entity_tag(metadata).as_deref() == Some(value)
+    } else {
+        // Dates are compared at the one second precision of 'Last-Modified'
+        let last_modified = metadata
+            .modified()
+            .and_then(|modified| parse_http_date(&fmt_http_date(modified)).ok());
+        match (parse_http_date(value), last_modified) {
+            (Ok(date), Some(last_modified)) => date == last_modified,
+            _ => false,
+        }
+    }
+}
+
 // Checks whether a file is modified based on metadata and request headers.
-fn not_modified(metadata: &Metadata, headers: &HeaderMap) -> bool {
+// 'If-None-Match' is only considered when entity tags are enabled.
+fn not_modified(metadata: &FileMetadata, headers: &HeaderMap, etag: bool) -> bool {
     // If-None-Match header takes precedence over If-Modified-Since
     match headers.get(IF_NONE_MATCH) {
-        Some(_) => entity_tag(&metadata)
+        Some(_) if etag => entity_tag(&metadata)
             .map(|etag| headers.get_all(IF_NONE_MATCH).iter().any(|v| v == &etag))
             .unwrap_or(false),
         _ => headers
@@ -330,14 +1393,13 @@ fn not_modified(metadata: &Metadata, headers: &HeaderMap) -> bool {
                 metadata
                     .modified()
                     .map(|modified| modified <= if_modified_time)
-                    .ok()
             })
             .unwrap_or(false),
     }
 }
 
-fn entity_tag(metadata: &Metadata) -> Option<String> {
-    metadata.modified().ok().and_then(|modified| {
+fn entity_tag(metadata: &FileMetadata) -> Option<String> {
+    metadata.modified().and_then(|modified| {
         modified.duration_since(UNIX_EPOCH).ok().map(|duration| {
             format!(
                 "W/\"{0:x}-{1:x}.{2:x}\"",
@@ -363,21 +1425,37 @@ impl StaticResponseExtender for FilePathExtractor {
     fn extend(_state: &mut State, _res: &mut Response<Self::ResBody>) {}
 }
 
-// Creates a Stream from the given file, for streaming as part of the Response.
+// Creates a Stream from the given file, for streaming as part of the Response. Ranges of the
+// file are read in the order given, with any other parts emitted between them as-is.
 // Borrowed from Warp https://github.com/seanmonstar/warp/blob/master/src/filters/fs.rs
 // Thanks @seanmonstar.
 fn file_stream(
     mut f: File,
     buf_size: usize,
-    mut len: u64,
+    parts: Vec<BodyPart>,
 ) -> impl Stream<Item = Chunk, Error = io::Error> + Send {
+    let mut parts = VecDeque::from(parts);
+    let mut position = 0;
     let mut buf = BytesMut::new();
-    stream::poll_fn(move || {
-        if len == 0 {
-            return Ok(None.into());
-        }
-        if buf.remaining_mut() < buf_size {
-            buf.reserve(buf_size);
+    stream::poll_fn(move || loop {
+        let (start, len) = match parts.front_mut() {
+            None => return Ok(None.into()),
+            Some(BodyPart::Range(_, 0)) => {
+                parts.pop_front();
+                continue;
+            }
+            Some(BodyPart::Range(start, len)) => (start, len),
+            Some(BodyPart::Bytes(_)) => match parts.pop_front() {
+                Some(BodyPart::Bytes(bytes)) => return Ok(Some(Chunk::from(bytes)).into()),
+                _ => unreachable!(),
+            },
+        };
+
+        if *start != position {
+            position = try_ready!(f.poll_seek(SeekFrom::Start(*start)));
+        }
+        if buf.remaining_mut() < buf_size {
+            buf.reserve(buf_size);
         }
         let n = try_ready!(f.read_buf(&mut buf).map_err(|err| {
             debug!("file read error: {}", err);
@@ -389,40 +1467,32 @@ fn file_stream(
             return Ok(None.into());
         }
 
+        // the cursor moves by the whole read, even where it runs past the end of the range
+        position += n;
+
         let mut chunk = buf.take().freeze();
-        if n > len {
-            chunk = chunk.split_to(len as usize);
-            len = 0;
-        } else {
-            len -= n;
+        if n > *len {
+            chunk = chunk.split_to(*len as usize);
         }
+        let n = chunk.len() as u64;
+        *start += n;
+        *len -= n;
 
-        Ok(Some(Chunk::from(chunk)).into())
+        return Ok(Some(Chunk::from(chunk)).into());
     })
 }
 
-fn optimal_buf_size(metadata: &Metadata) -> usize {
-    let block_size = get_block_size(metadata);
+fn optimal_buf_size(metadata: &FileMetadata) -> usize {
+    let block_size = metadata.block_size().unwrap_or(8_192);
 
     // If file length is smaller than block size, don't waste space
     // reserving a bigger-than-needed buffer.
     cmp::min(block_size as u64, metadata.len()) as usize
 }
 
-#[cfg(unix)]
-fn get_block_size(metadata: &Metadata) -> usize {
-    use std::os::unix::fs::MetadataExt;
-    metadata.blksize() as usize
-}
-
-#[cfg(not(unix))]
-fn get_block_size(metadata: &Metadata) -> usize {
-    8_192
-}
-
 #[cfg(test)]
 mod tests {
-    use super::FileOptions;
+    use super::{AssetManifest, FileMetadata, FileOptions};
     use crate::router::builder::{build_simple_router, DefineSingleRoute, DrawRoutes};
     use crate::router::Router;
     use crate::test::TestServer;
@@ -436,22 +1506,22 @@ mod tests {
         let expected_docs = vec![
             (
                 "doc.html",
-                HeaderValue::from_static("text/html"),
+                HeaderValue::from_static("text/html; charset=utf-8"),
                 "<html>I am a doc.</html>",
             ),
             (
                 "file.txt",
-                HeaderValue::from_static("text/plain"),
+                HeaderValue::from_static("text/plain; charset=utf-8"),
                 "I am a file",
             ),
             (
                 "styles/style.css",
-                HeaderValue::from_static("text/css"),
+                HeaderValue::from_static("text/css; charset=utf-8"),
                 ".styled { border: none; }",
             ),
             (
                 "scripts/script.js",
-                HeaderValue::from_static("application/javascript"),
+                HeaderValue::from_static("application/javascript; charset=utf-8"),
                 "console.log('I am javascript!');",
             ),
         ];
@@ -471,6 +1541,36 @@ mod tests {
         }
     }
 
+    #[test]
+    fn assets_percent_decoded_segments() {
+        let server = test_server();
+        let get = |path: &str| {
+            server
+                .client()
+                .get(&format!("http://localhost/{}", path))
+                .perform()
+                .unwrap()
+        };
+
+        let response = get("encoded/my%20file.txt");
+        assert_eq!(response.status(), StatusCode::OK);
+        assert_eq!(&response.read_body().unwrap()[..], b"I have a space");
+
+        let response = get("encoded/%C3%BCn%C3%AFc%C3%B8d%C3%A9.txt");
+        assert_eq!(response.status(), StatusCode::OK);
+        assert_eq!(&response.read_body().unwrap()[..], b"I am unicode");
+
+        for attempt in &[
+            "encoded/..%2fdoc.html",
+            "encoded/%2e%2e/doc.html",
+            "encoded/../doc.html",
+            "doc.html%00.txt",
+            "encoded%5c..%5cdoc.html",
+        ] {
+            assert_eq!(get(attempt).status(), StatusCode::NOT_FOUND);
+        }
+    }
+
     // Examples derived from https://www.owasp.org/index.php/Path_Traversal
     #[test]
     fn assets_path_traversal() {
@@ -513,7 +1613,10 @@ mod tests {
             .unwrap();
 
         assert_eq!(response.status(), StatusCode::OK);
-        assert_eq!(response.headers().get(CONTENT_TYPE).unwrap(), "text/html");
+        assert_eq!(
+            response.headers().get(CONTENT_TYPE).unwrap(),
+            "text/html; charset=utf-8"
+        );
 
         let body = response.read_body().unwrap();
         assert_eq!(&body[..], b"<html>I am a doc.</html>");
@@ -530,7 +1633,7 @@ mod tests {
 
         let etag = File::open(path)
             .and_then(|file| file.metadata())
-            .map(|meta| super::entity_tag(&meta).expect("entity tag"))
+            .map(|meta| super::entity_tag(&FileMetadata::from(&meta)).expect("entity tag"))
             .unwrap();
 
         // matching etag
@@ -561,6 +1664,79 @@ mod tests {
         );
     }
 
+    #[test]
+    fn assets_preconditions() {
+        use std::fs::File;
+
+        let path = "resources/test/assets/doc.html";
+        let meta = File::open(path).and_then(|f| f.metadata()).unwrap();
+        let etag = super::entity_tag(&FileMetadata::from(&meta)).unwrap();
+        let last_modified = httpdate::fmt_http_date(meta.modified().unwrap());
+
+        let server = test_server();
+        let get = |name: HeaderName, value: &str| {
+            server
+                .client()
+                .get("http://localhost/doc.html")
+                .with_header(name, HeaderValue::from_str(value).unwrap())
+                .perform()
+                .unwrap()
+                .status()
+        };
+
+        assert_eq!(get(IF_MATCH, &etag), StatusCode::PRECONDITION_FAILED);
+        assert_eq!(
+            get(IF_MATCH, &format!("\"other\", {}", etag)),
+            StatusCode::PRECONDITION_FAILED
+        );
+        assert_eq!(
+            get(IF_MATCH, etag.trim_start_matches("W/")),
+            StatusCode::PRECONDITION_FAILED
+        );
+        assert_eq!(get(IF_MATCH, "*"), StatusCode::OK);
+        assert_eq!(get(IF_MATCH, "\"other\", *"), StatusCode::OK);
+        assert_eq!(get(IF_MATCH, "\"other\""), StatusCode::PRECONDITION_FAILED);
+
+        assert_eq!(get(IF_UNMODIFIED_SINCE, &last_modified), StatusCode::OK);
+        assert_eq!(
+            get(IF_UNMODIFIED_SINCE, "Thu, 01 Jan 1970 00:00:00 GMT"),
+            StatusCode::PRECONDITION_FAILED
+        );
+        assert_eq!(get(IF_UNMODIFIED_SINCE, "invalid"), StatusCode::OK);
+    }
+
+    #[test]
+    fn assets_etag_disabled() {
+        use hyper::header::{ETAG, IF_NONE_MATCH};
+        use std::fs::File;
+
+        let path = "resources/test/assets/doc.html";
+        let test_server = TestServer::new(build_simple_router(|route| {
+            route
+                .get("/")
+                .to_file(FileOptions::new(path).with_etag(false).build())
+        }))
+        .unwrap();
+
+        let etag = File::open(path)
+            .and_then(|file| file.metadata())
+            .map(|meta| super::entity_tag(&FileMetadata::from(&meta)).expect("entity tag"))
+            .unwrap();
+
+        let response = test_server
+            .client()
+            .get("http://localhost/")
+            .with_header(
+                IF_NONE_MATCH,
+                HeaderValue::from_bytes(etag.as_bytes()).unwrap(),
+            )
+            .perform()
+            .unwrap();
+
+        assert_eq!(response.status(), StatusCode::OK);
+        assert!(response.headers().get(ETAG).is_none());
+    }
+
     #[test]
     fn assets_if_modified_since() {
         use httpdate::fmt_http_date;
@@ -606,6 +1782,38 @@ mod tests {
         assert_eq!(response.status(), StatusCode::OK);
     }
 
+    #[test]
+    fn assets_last_modified() {
+        use hyper::header::LAST_MODIFIED;
+        use std::fs::File;
+
+        let path = "resources/test/assets/doc.html";
+        let test_server =
+            TestServer::new(build_simple_router(|route| route.get("/").to_file(path))).unwrap();
+
+        let modified = File::open(path)
+            .and_then(|file| file.metadata())
+            .and_then(|meta| meta.modified())
+            .unwrap();
+
+        let response = test_server
+            .client()
+            .get("http://localhost/")
+            .perform()
+            .unwrap();
+
+        assert_eq!(response.status(), StatusCode::OK);
+        assert_eq!(
+            response
+                .headers()
+                .get(LAST_MODIFIED)
+                .unwrap()
+                .to_str()
+                .unwrap(),
+            httpdate::fmt_http_date(modified)
+        );
+    }
+
     #[test]
     fn assets_with_cache_control() {
         let router = build_simple_router(|route| {
@@ -634,6 +1842,79 @@ mod tests {
         );
     }
 
+    #[test]
+    fn assets_with_max_age_and_immutable() {
+        use std::time::{Duration, SystemTime};
+
+        let router = build_simple_router(|route| {
+            route.get("/*").to_dir(
+                FileOptions::new("resources/test/assets")
+                    .with_max_age(Duration::from_secs(31_536_000))
+                    .with_immutable(true)
+                    .build(),
+            )
+        });
+        let server = TestServer::new(router).unwrap();
+
+        let response = server
+            .client()
+            .get("http://localhost/doc.html")
+            .perform()
+            .unwrap();
+
+        assert_eq!(
+            response
+                .headers()
+                .get(CACHE_CONTROL)
+                .unwrap()
+                .to_str()
+                .unwrap(),
+            "public, max-age=31536000, immutable"
+        );
+
+        let expires = response.headers().get(EXPIRES).unwrap().to_str().unwrap();
+        let expires = httpdate::parse_http_date(expires).unwrap();
+        assert!(expires > SystemTime::now() + Duration::from_secs(31_535_000));
+    }
+
+    #[test]
+    fn assets_manifest_fingerprinted_files() {
+        let manifest = AssetManifest::load("resources/test/assets_manifest/manifest.json").unwrap();
+        assert_eq!(manifest.resolve("js/app.js"), Some("js/app.3f9ab2.js"));
+
+        let router = build_simple_router(|route| {
+            route.get("/*").to_dir(
+                FileOptions::new("resources/test/assets_manifest")
+                    .with_manifest(manifest)
+                    .build(),
+            )
+        });
+        let server = TestServer::new(router).unwrap();
+
+        let response = server
+            .client()
+            .get("http://localhost/js/app.3f9ab2.js")
+            .perform()
+            .unwrap();
+
+        assert_eq!(response.status(), StatusCode::OK);
+        assert_eq!(
+            response.headers().get(CACHE_CONTROL).unwrap(),
+            "public, max-age=31536000, immutable"
+        );
+        assert!(response.headers().get(EXPIRES).is_some());
+
+        let response = server
+            .client()
+            .get("http://localhost/js/app.js")
+            .perform()
+            .unwrap();
+
+        assert_eq!(response.status(), StatusCode::OK);
+        assert_eq!(response.headers().get(CACHE_CONTROL).unwrap(), "public");
+        assert!(response.headers().get(EXPIRES).is_none());
+    }
+
     #[test]
     fn assets_default_cache_control() {
         let router = build_simple_router(|route| route.get("/*").to_dir("resources/test/assets"));
@@ -654,6 +1935,7 @@ mod tests {
                 .unwrap(),
             "public"
         );
+        assert!(response.headers().get(VARY).is_none());
     }
 
     #[test]
@@ -702,7 +1984,12 @@ mod tests {
                     .unwrap()
                     .to_str()
                     .unwrap(),
-                "text/html"
+                "text/html; charset=utf-8"
+            );
+
+            assert_eq!(
+                response.headers().get(VARY).unwrap().to_str().unwrap(),
+                "accept-encoding"
             );
 
             let expected_body =
@@ -738,7 +2025,11 @@ mod tests {
                 .unwrap()
                 .to_str()
                 .unwrap(),
-            "text/html"
+            "text/html; charset=utf-8"
+        );
+        assert_eq!(
+            response.headers().get(VARY).unwrap().to_str().unwrap(),
+            "accept-encoding"
         );
 
         let expected_body = fs::read("resources/test/assets/doc.html").unwrap();
@@ -773,7 +2064,7 @@ mod tests {
                 .unwrap()
                 .to_str()
                 .unwrap(),
-            "text/html"
+            "text/html; charset=utf-8"
         );
 
         let expected_body = fs::read("resources/test/assets_uncompressed/doc.html").unwrap();
@@ -809,7 +2100,7 @@ mod tests {
                 .unwrap()
                 .to_str()
                 .unwrap(),
-            "text/html"
+            "text/html; charset=utf-8"
         );
 
         assert_eq!(
@@ -825,8 +2116,1092 @@ mod tests {
         assert_eq!(response.read_body().unwrap(), expected_body);
     }
 
-    fn test_server() -> TestServer {
-        TestServer::new(static_router("/*", "resources/test/assets")).unwrap()
+    #[test]
+    fn assets_range_request() {
+        let server = test_server();
+
+        let response = server
+            .client()
+            .get("http://localhost/doc.html")
+            .with_header(RANGE, HeaderValue::from_static("bytes=6-17"))
+            .perform()
+            .unwrap();
+
+        assert_eq!(response.status(), StatusCode::PARTIAL_CONTENT);
+        assert_eq!(
+            response.headers().get(CONTENT_RANGE).unwrap(),
+            "bytes 6-17/24"
+        );
+        assert_eq!(response.headers().get(CONTENT_LENGTH).unwrap(), "12");
+        assert_eq!(response.headers().get(ACCEPT_RANGES).unwrap(), "bytes");
+        assert_eq!(&response.read_body().unwrap()[..], b"I am a doc.<");
+
+        let response = server
+            .client()
+            .get("http://localhost/doc.html")
+            .with_header(RANGE, HeaderValue::from_static("bytes=-7"))
+            .perform()
+            .unwrap();
+
+        assert_eq!(response.status(), StatusCode::PARTIAL_CONTENT);
+        assert_eq!(&response.read_body().unwrap()[..], b"</html>");
+    }
+
+    #[test]
+    fn assets_multipart_range_request() {
+        let server = test_server();
+
+        let response = server
+            .client()
+            .get("http://localhost/doc.html")
+            .with_header(RANGE, HeaderValue::from_static("bytes=0-5, 18-"))
+            .perform()
+            .unwrap();
+
+        assert_eq!(response.status(), StatusCode::PARTIAL_CONTENT);
+        let content_type = response
+            .headers()
+            .get(CONTENT_TYPE)
+            .unwrap()
+            .to_str()
+            .unwrap()
+            .to_owned();
+        assert!(content_type.starts_with("multipart/byteranges; boundary="));
+        let boundary = &content_type["multipart/byteranges; boundary=".len()..];
+        let content_length = response.headers().get(CONTENT_LENGTH).unwrap().clone();
+
+        let body = response.read_body().unwrap();
+        assert_eq!(content_length, body.len().to_string().as_str());
+
+        let expected = format!(
+            "--{0}\r\ncontent-type: text/html; charset=utf-8\r\ncontent-range: bytes 0-5/24\r\n\r\n<html>\r\n\
+             --{0}\r\ncontent-type: text/html; charset=utf-8\r\ncontent-range: bytes 18-23/24\r\n\r\n/html>\r\n\
+             --{0}--\r\n",
+            boundary
+        );
+        assert_eq!(str::from_utf8(&body).unwrap(), expected);
+
+        // Adjacent ranges are each read from their own start
+        let response = server
+            .client()
+            .get("http://localhost/doc.html")
+            .with_header(RANGE, HeaderValue::from_static("bytes=0-1, 2-3"))
+            .perform()
+            .unwrap();
+
+        assert_eq!(response.status(), StatusCode::PARTIAL_CONTENT);
+        let content_type = response.headers()[CONTENT_TYPE]
+            .to_str()
+            .unwrap()
+            .to_owned();
+        let boundary = &content_type["multipart/byteranges; boundary=".len()..];
+        let content_length = response.headers()[CONTENT_LENGTH].clone();
+
+        let body = response.read_body().unwrap();
+        assert_eq!(content_length, body.len().to_string().as_str());
+
+        let expected = format!(
+            "--{0}\r\ncontent-type: text/html; charset=utf-8\r\ncontent-range: bytes 0-1/24\r\n\r\n<h\r\n\
+             --{0}\r\ncontent-type: text/html; charset=utf-8\r\ncontent-range: bytes 2-3/24\r\n\r\ntm\r\n\
+             --{0}--\r\n",
+            boundary
+        );
+        assert_eq!(str::from_utf8(&body).unwrap(), expected);
+
+        // Overlapping ranges larger than the file are ignored
+        let response = server
+            .client()
+            .get("http://localhost/doc.html")
+            .with_header(RANGE, HeaderValue::from_static("bytes=0-19, 4-23"))
+            .perform()
+            .unwrap();
+
+        assert_eq!(response.status(), StatusCode::OK);
+        assert_eq!(
+            &response.read_body().unwrap()[..],
+            b"<html>I am a doc.</html>"
+        );
+    }
+
+    #[test]
+    fn assets_range_not_satisfiable() {
+        let response = test_server()
+            .client()
+            .get("http://localhost/doc.html")
+            .with_header(RANGE, HeaderValue::from_static("bytes=100-"))
+            .perform()
+            .unwrap();
+
+        assert_eq!(response.status(), StatusCode::RANGE_NOT_SATISFIABLE);
+        assert_eq!(response.headers().get(CONTENT_RANGE).unwrap(), "bytes */24");
+    }
+
+    #[test]
+    fn assets_if_range() {
+        use std::fs::File;
+
+        let path = "resources/test/assets/doc.html";
+        let meta = File::open(path).and_then(|f| f.metadata()).unwrap();
+        let etag = super::entity_tag(&FileMetadata::from(&meta)).unwrap();
+        let last_modified = httpdate::fmt_http_date(meta.modified().unwrap());
+
+        let server = test_server();
+        let get = |if_range: &str| {
+            server
+                .client()
+                .get("http://localhost/doc.html")
+                .with_header(RANGE, HeaderValue::from_static("bytes=0-5"))
+                .with_header(IF_RANGE, HeaderValue::from_str(if_range).unwrap())
+                .perform()
+                .unwrap()
+        };
+
+        for current in &[etag.as_str(), last_modified.as_str()] {
+            let response = get(current);
+            assert_eq!(response.status(), StatusCode::PARTIAL_CONTENT);
+            assert_eq!(&response.read_body().unwrap()[..], b"<html>");
+        }
+
+        for stale in &[
+            "\"bogus\"",
+            "W/\"1-2.3\"",
+            "Thu, 01 Jan 1970 00:00:00 GMT",
+            "invalid",
+        ] {
+            let response = get(stale);
+            assert_eq!(response.status(), StatusCode::OK);
+            assert_eq!(
+                &response.read_body().unwrap()[..],
+                b"<html>I am a doc.</html>"
+            );
+        }
+    }
+
+    #[test]
+    fn assets_accept_ranges_without_range() {
+        let response = test_server()
+            .client()
+            .get("http://localhost/doc.html")
+            .perform()
+            .unwrap();
+
+        assert_eq!(response.status(), StatusCode::OK);
+        assert_eq!(response.headers().get(ACCEPT_RANGES).unwrap(), "bytes");
+        assert_eq!(
+            &response.read_body().unwrap()[..],
+            b"<html>I am a doc.</html>"
+        );
+    }
+
+    #[test]
+    fn assets_file_stream_stops_at_length() {
+        use super::BodyPart;
+        use bytes::Bytes;
+        use futures::{Future, Stream};
+        use tokio::fs::File;
+        use tokio::runtime::Runtime;
+
+        let mut runtime = Runtime::new().unwrap();
+        let chunks = runtime
+            .block_on(
+                File::open("resources/test/assets/doc.html").and_then(|file| {
+                    super::file_stream(file, 4, vec![BodyPart::Range(0, 10)]).collect()
+                }),
+            )
+            .unwrap();
+
+        // Reading stops once the requested length has been streamed
+        let body: Vec<u8> = chunks.iter().flat_map(|c| c.to_vec()).collect();
+        assert_eq!(&body[..], b"<html>I am");
+
+        let parts = vec![
+            BodyPart::Range(18, 6),
+            BodyPart::Bytes(Bytes::from_static(b", ")),
+            BodyPart::Range(0, 6),
+        ];
+        let chunks = runtime
+            .block_on(
+                File::open("resources/test/assets/doc.html")
+                    .and_then(|file| super::file_stream(file, 4, parts).collect()),
+            )
+            .unwrap();
+
+        let body: Vec<u8> = chunks.iter().flat_map(|c| c.to_vec()).collect();
+        assert_eq!(&body[..], b"/html>, <html>");
+    }
+
+    #[test]
+    fn assets_dynamic_gzip() {
+        use flate2::read::GzDecoder;
+        use std::io::Read;
+
+        let router = build_simple_router(|route| {
+            route.get("/*").to_dir(
+                FileOptions::new("resources/test/assets_uncompressed")
+                    .with_dynamic_gzip(true)
+                    .with_compression_min_size(0)
+                    .build(),
+            )
+        });
+        let server = TestServer::new(router).unwrap();
+
+        let response = server
+            .client()
+            .get("http://localhost/doc.html")
+            .with_header(ACCEPT_ENCODING, HeaderValue::from_static("gzip"))
+            .perform()
+            .unwrap();
+
+        assert_eq!(response.status(), StatusCode::OK);
+        assert_eq!(response.headers().get(CONTENT_ENCODING).unwrap(), "gzip");
+        assert_eq!(response.headers().get(VARY).unwrap(), "accept-encoding");
+        assert!(response.headers().get(ACCEPT_RANGES).is_none());
+
+        let compressed = response.read_body().unwrap();
+        let mut body = Vec::new();
+        GzDecoder::new(&compressed[..])
+            .read_to_end(&mut body)
+            .unwrap();
+
+        let expected_body = fs::read("resources/test/assets_uncompressed/doc.html").unwrap();
+        assert_eq!(body, expected_body);
+    }
+
+    #[test]
+    fn assets_dynamic_gzip_below_min_size() {
+        let router = build_simple_router(|route| {
+            route.get("/*").to_dir(
+                FileOptions::new("resources/test/assets_uncompressed")
+                    .with_dynamic_gzip(true)
+                    .build(),
+            )
+        });
+        let server = TestServer::new(router).unwrap();
+
+        let response = server
+            .client()
+            .get("http://localhost/doc.html")
+            .with_header(ACCEPT_ENCODING, HeaderValue::from_static("gzip"))
+            .perform()
+            .unwrap();
+
+        assert_eq!(response.status(), StatusCode::OK);
+        assert!(response.headers().get(CONTENT_ENCODING).is_none());
+
+        let expected_body = fs::read("resources/test/assets_uncompressed/doc.html").unwrap();
+        assert_eq!(response.read_body().unwrap(), expected_body);
+    }
+
+    #[test]
+    fn assets_directory_not_found_without_listing() {
+        let response = test_server()
+            .client()
+            .get("http://localhost/styles")
+            .perform()
+            .unwrap();
+
+        assert_eq!(response.status(), StatusCode::NOT_FOUND);
+    }
+
+    #[test]
+    fn assets_directory_listing() {
+        let router = build_simple_router(|route| {
+            route.get("/*").to_dir(
+                FileOptions::new("resources/test/assets")
+                    .with_directory_listing(true)
+                    .build(),
+            )
+        });
+        let server = TestServer::new(router).unwrap();
+
+        let response = server
+            .client()
+            .get("http://localhost/styles/")
+            .perform()
+            .unwrap();
+
+        assert_eq!(response.status(), StatusCode::OK);
+        assert_eq!(
+            response.headers().get(CONTENT_TYPE).unwrap(),
+            "text/html; charset=utf-8"
+        );
+
+        let body = response.read_utf8_body().unwrap();
+        assert!(body.contains("Index of /styles/"));
+        assert!(body.contains("<a href=\"/styles/style.css\">style.css</a>"));
+
+        // files are still served as usual
+        let response = server
+            .client()
+            .get("http://localhost/styles/style.css")
+            .perform()
+            .unwrap();
+
+        assert_eq!(response.status(), StatusCode::OK);
+    }
+
+    #[test]
+    fn assets_directory_listing_custom_renderer() {
+        use super::DirectoryEntry;
+
+        fn renderer(path: &str, entries: &[DirectoryEntry]) -> String {
+            let names: Vec<&str> = entries.iter().map(|e| e.name.as_str()).collect();
+            format!("{}: {}", path, names.join(","))
+        }
+
+        let router = build_simple_router(|route| {
+            route.get("/*").to_dir(
+                FileOptions::new("resources/test/assets")
+                    .with_directory_listing(true)
+                    .with_directory_listing_renderer(renderer)
+                    .build(),
+            )
+        });
+        let server = TestServer::new(router).unwrap();
+
+        let response = server
+            .client()
+            .get("http://localhost/scripts")
+            .perform()
+            .unwrap();
+
+        assert_eq!(response.status(), StatusCode::OK);
+        assert_eq!(response.read_utf8_body().unwrap(), "/scripts: script.js");
+    }
+
+    #[test]
+    fn assets_directory_index_file() {
+        for path in &["/docs", "/docs/"] {
+            let response = test_server()
+                .client()
+                .get(&format!("http://localhost{}", path))
+                .perform()
+                .unwrap();
+
+            assert_eq!(response.status(), StatusCode::OK);
+            assert_eq!(
+                response.headers().get(CONTENT_TYPE).unwrap(),
+                "text/html; charset=utf-8"
+            );
+            assert_eq!(
+                &response.read_body().unwrap()[..],
+                b"<html>I am the docs index.</html>"
+            );
+        }
+    }
+
+    #[test]
+    fn assets_directory_index_files_in_order() {
+        let router = build_simple_router(|route| {
+            route.get("/*").to_dir(
+                FileOptions::new("resources/test/assets")
+                    .with_index_files(&["default.html", "index.htm", "index.html"])
+                    .build(),
+            )
+        });
+        let server = TestServer::new(router).unwrap();
+
+        let response = server
+            .client()
+            .get("http://localhost/docs/")
+            .perform()
+            .unwrap();
+
+        assert_eq!(response.status(), StatusCode::OK);
+        assert_eq!(
+            &response.read_body().unwrap()[..],
+            b"<html>I am an alternate docs index.</html>"
+        );
+    }
+
+    #[test]
+    fn assets_directory_index_files_disabled() {
+        let empty: &[&str] = &[];
+        let router = build_simple_router(|route| {
+            route.get("/*").to_dir(
+                FileOptions::new("resources/test/assets")
+                    .with_index_files(empty)
+                    .build(),
+            )
+        });
+        let server = TestServer::new(router).unwrap();
+
+        let response = server
+            .client()
+            .get("http://localhost/docs/")
+            .perform()
+            .unwrap();
+
+        assert_eq!(response.status(), StatusCode::NOT_FOUND);
+    }
+
+    #[test]
+    fn assets_as_attachment() {
+        use super::FileHandler;
+
+        let test_server = TestServer::new(build_simple_router(|route| {
+            route.get("/").to_new_handler(
+                FileHandler::new("resources/test/assets/file.txt").as_attachment("notes.txt"),
+            )
+        }))
+        .unwrap();
+
+        let response = test_server
+            .client()
+            .get("http://localhost/")
+            .perform()
+            .unwrap();
+
+        assert_eq!(response.status(), StatusCode::OK);
+        assert_eq!(
+            response.headers().get(CONTENT_DISPOSITION).unwrap(),
+            "attachment; filename=\"notes.txt\""
+        );
+        assert_eq!(&response.read_body().unwrap()[..], b"I am a file");
+    }
+
+    #[test]
+    fn assets_content_disposition_encoding() {
+        use super::content_disposition;
+
+        assert_eq!(
+            content_disposition("report.pdf"),
+            "attachment; filename=\"report.pdf\""
+        );
+        assert_eq!(
+            content_disposition("r\u{e9}sum\u{e9} \"final\".pdf"),
+            "attachment; filename=\"r_sum_ _final_.pdf\"; \
+             filename*=UTF-8''r%C3%A9sum%C3%A9%20%22final%22.pdf"
+        );
+    }
+
+    #[test]
+    fn assets_symlink_policy() {
+        use super::SymlinkPolicy;
+
+        let expectations = vec![
+            (SymlinkPolicy::AllowAll, StatusCode::OK, StatusCode::OK),
+            (
+                SymlinkPolicy::AllowWithinRoot,
+                StatusCode::OK,
+                StatusCode::NOT_FOUND,
+            ),
+            (
+                SymlinkPolicy::Deny,
+                StatusCode::NOT_FOUND,
+                StatusCode::NOT_FOUND,
+            ),
+        ];
+
+        for (policy, within_root, escaping_root) in expectations {
+            let router = build_simple_router(|route| {
+                route.get("/*").to_dir(
+                    FileOptions::new("resources/test/assets_symlinks")
+                        .with_symlink_policy(policy)
+                        .build(),
+                )
+            });
+            let server = TestServer::new(router).unwrap();
+
+            let get = |path: &str| {
+                server
+                    .client()
+                    .get(&format!("http://localhost/{}", path))
+                    .perform()
+                    .unwrap()
+                    .status()
+            };
+
+            assert_eq!(get("real.txt"), StatusCode::OK);
+            assert_eq!(get("link.txt"), within_root);
+            assert_eq!(get("escape.txt"), escaping_root);
+        }
+    }
+
+    #[test]
+    fn assets_head_request() {
+        use hyper::Method;
+
+        let router = build_simple_router(|route| {
+            route
+                .request(vec![Method::GET, Method::HEAD], "/*")
+                .to_dir("resources/test/assets")
+        });
+        let server = TestServer::new(router).unwrap();
+
+        let get = server
+            .client()
+            .get("http://localhost/doc.html")
+            .perform()
+            .unwrap();
+        let head = server
+            .client()
+            .head("http://localhost/doc.html")
+            .perform()
+            .unwrap();
+
+        assert_eq!(head.status(), StatusCode::OK);
+        for header in &[
+            CONTENT_LENGTH,
+            CONTENT_TYPE,
+            CACHE_CONTROL,
+            ETAG,
+            LAST_MODIFIED,
+        ] {
+            assert_eq!(head.headers().get(header), get.headers().get(header));
+        }
+        assert!(head.read_body().unwrap().is_empty());
+    }
+
+    #[test]
+    fn assets_allowed_and_denied_extensions() {
+        let router = build_simple_router(|route| {
+            route.get("/allowed/*").to_dir(
+                FileOptions::new("resources/test/assets")
+                    .with_allowed_extensions(&[".CSS", "txt"])
+                    .build(),
+            );
+            route.get("/denied/*").to_dir(
+                FileOptions::new("resources/test/assets")
+                    .with_allowed_extensions(&["html", "txt"])
+                    .with_denied_extensions(&["HTML"])
+                    .build(),
+            );
+        });
+        let server = TestServer::new(router).unwrap();
+
+        for &(path, status) in &[
+            ("allowed/styles/style.css", StatusCode::OK),
+            ("allowed/file.txt", StatusCode::OK),
+            ("allowed/doc.html", StatusCode::NOT_FOUND),
+            ("allowed/doc.html.gz", StatusCode::NOT_FOUND),
+            ("denied/file.txt", StatusCode::OK),
+            ("denied/doc.html", StatusCode::NOT_FOUND),
+            ("denied/docs/", StatusCode::NOT_FOUND),
+        ] {
+            let response = server
+                .client()
+                .get(&format!("http://localhost/{}", path))
+                .perform()
+                .unwrap();
+
+            assert_eq!(response.status(), status, "{}", path);
+        }
+    }
+
+    #[test]
+    fn assets_max_file_size() {
+        let server = |not_found| {
+            let router = build_simple_router(|route| {
+                route.get_or_head("/*").to_dir(
+                    FileOptions::new("resources/test/assets")
+                        .with_max_file_size(20)
+                        .with_oversized_not_found(not_found)
+                        .build(),
+                )
+            });
+            TestServer::new(router).unwrap()
+        };
+
+        for &(not_found, status) in &[
+            (false, StatusCode::FORBIDDEN),
+            (true, StatusCode::NOT_FOUND),
+        ] {
+            let server = server(not_found);
+
+            let response = server
+                .client()
+                .get("http://localhost/file.txt")
+                .perform()
+                .unwrap();
+            assert_eq!(response.status(), StatusCode::OK);
+            assert_eq!(&response.read_body().unwrap()[..], b"I am a file");
+
+            let client = server.client();
+            let get = client.get("http://localhost/doc.html").perform().unwrap();
+            let head = client.head("http://localhost/doc.html").perform().unwrap();
+            assert_eq!(get.status(), status);
+            assert_eq!(head.status(), status);
+            assert!(get.read_body().unwrap().is_empty());
+        }
+    }
+
+    #[test]
+    fn assets_error_page() {
+        let router = build_simple_router(|route| {
+            route.get("/*").to_dir(
+                FileOptions::new("resources/test/assets")
+                    .with_error_page(StatusCode::NOT_FOUND, "resources/test/assets/file.txt")
+                    .build(),
+            )
+        });
+        let server = TestServer::new(router).unwrap();
+
+        let response = server
+            .client()
+            .get("http://localhost/missing.html")
+            .perform()
+            .unwrap();
+
+        assert_eq!(response.status(), StatusCode::NOT_FOUND);
+        assert_eq!(response.headers().get(CONTENT_TYPE).unwrap(), "text/plain");
+        assert_eq!(&response.read_body().unwrap()[..], b"I am a file");
+    }
+
+    #[test]
+    fn assets_not_found_handler() {
+        use crate::helpers::http::response::create_response;
+        use crate::state::State;
+
+        let router = build_simple_router(|route| {
+            route.get("/*").to_dir(
+                FileOptions::new("resources/test/assets")
+                    .with_error_page(StatusCode::NOT_FOUND, "resources/test/assets/file.txt")
+                    .with_not_found_handler(|| {
+                        Ok(|state: State| {
+                            let response = create_response(
+                                &state,
+                                StatusCode::OK,
+                                mime::TEXT_PLAIN,
+                                "fallback",
+                            );
+                            (state, response)
+                        })
+                    })
+                    .build(),
+            )
+        });
+        let server = TestServer::new(router).unwrap();
+
+        let response = server
+            .client()
+            .get("http://localhost/missing.html")
+            .perform()
+            .unwrap();
+
+        assert_eq!(response.status(), StatusCode::OK);
+        assert_eq!(response.read_utf8_body().unwrap(), "fallback");
+
+        // existing files are unaffected
+        let response = server
+            .client()
+            .get("http://localhost/doc.html")
+            .perform()
+            .unwrap();
+
+        assert_eq!(response.status(), StatusCode::OK);
+        assert_eq!(
+            &response.read_body().unwrap()[..],
+            b"<html>I am a doc.</html>"
+        );
+    }
+
+    #[test]
+    fn assets_memory_cache() {
+        use std::path::Path;
+
+        let options = FileOptions::new("resources/test/assets")
+            .with_memory_cache(1024, 1024)
+            .build();
+        let cache = options.cache.clone().unwrap();
+        let test_server = TestServer::new(build_simple_router(|route| {
+            route.get("/*").to_dir(options);
+        }))
+        .unwrap();
+
+        let path = Path::new("resources/test/assets/doc.html");
+        let meta = FileMetadata::from(&fs::metadata(path).unwrap());
+        assert!(cache.get(path, &meta).is_none());
+
+        for _ in 0..2 {
+            let response = test_server
+                .client()
+                .get("http://localhost/doc.html")
+                .perform()
+                .unwrap();
+
+            assert_eq!(response.status(), StatusCode::OK);
+            assert_eq!(response.headers().get(CONTENT_LENGTH).unwrap(), "24");
+            assert_eq!(
+                &response.read_body().unwrap()[..],
+                b"<html>I am a doc.</html>"
+            );
+            assert!(cache.get(path, &meta).is_some());
+        }
+
+        // Ranges are served from the cached contents
+        let response = test_server
+            .client()
+            .get("http://localhost/doc.html")
+            .with_header(RANGE, HeaderValue::from_static("bytes=6-17"))
+            .perform()
+            .unwrap();
+
+        assert_eq!(response.status(), StatusCode::PARTIAL_CONTENT);
+        assert_eq!(&response.read_body().unwrap()[..], b"I am a doc.<");
+    }
+
+    #[test]
+    fn assets_mime_type_overrides() {
+        let test_server = TestServer::new(build_simple_router(|route| {
+            route.get("/*").to_dir(
+                FileOptions::new("resources/test/assets")
+                    .with_mime_type(".TXT", mime::TEXT_CSV)
+                    .with_mime_type("js", mime::APPLICATION_JAVASCRIPT)
+                    .build(),
+            );
+        }))
+        .unwrap();
+
+        for &(path, expected) in &[
+            ("file.txt", "text/csv; charset=utf-8"),
+            ("scripts/script.js", "application/javascript; charset=utf-8"),
+            ("doc.html", "text/html; charset=utf-8"),
+        ] {
+            let response = test_server
+                .client()
+                .get(&format!("http://localhost/{}", path))
+                .perform()
+                .unwrap();
+
+            assert_eq!(response.status(), StatusCode::OK);
+            assert_eq!(response.headers().get(CONTENT_TYPE).unwrap(), expected);
+        }
+    }
+
+    #[test]
+    fn assets_dotfiles() {
+        for &(dotfiles, hidden) in &[(true, StatusCode::OK), (false, StatusCode::NOT_FOUND)] {
+            let server = TestServer::new(build_simple_router(|route| {
+                route.get("/*").to_dir(
+                    FileOptions::new("resources/test/assets_hidden")
+                        .with_dotfiles(dotfiles)
+                        .build(),
+                )
+            }))
+            .unwrap();
+
+            let get = |path: &str| {
+                server
+                    .client()
+                    .get(&format!("http://localhost/{}", path))
+                    .perform()
+                    .unwrap()
+                    .status()
+            };
+
+            assert_eq!(get("visible.txt"), StatusCode::OK);
+            assert_eq!(get(".env"), hidden);
+            assert_eq!(get(".git/config"), hidden);
+        }
+    }
+
+    #[test]
+    fn assets_dotfiles_listing() {
+        for &dotfiles in &[true, false] {
+            let server = TestServer::new(build_simple_router(|route| {
+                route.get("/*").to_dir(
+                    FileOptions::new("resources/test")
+                        .with_directory_listing(true)
+                        .with_dotfiles(dotfiles)
+                        .build(),
+                )
+            }))
+            .unwrap();
+
+            let body = server
+                .client()
+                .get("http://localhost/assets_hidden/")
+                .perform()
+                .unwrap()
+                .read_utf8_body()
+                .unwrap();
+
+            assert!(body.contains(">visible.txt<"));
+            assert_eq!(body.contains(">.env<"), dotfiles);
+            assert_eq!(body.contains(">.git/<"), dotfiles);
+        }
+    }
+
+    #[test]
+    fn assets_extensions_listing() {
+        let server = TestServer::new(build_simple_router(|route| {
+            route.get("/*").to_dir(
+                FileOptions::new("resources/test")
+                    .with_directory_listing(true)
+                    .with_allowed_extensions(&["html", "gz", "txt"])
+                    .with_denied_extensions(&["gz"])
+                    .build(),
+            )
+        }))
+        .unwrap();
+
+        let body = server
+            .client()
+            .get("http://localhost/assets/")
+            .perform()
+            .unwrap()
+            .read_utf8_body()
+            .unwrap();
+
+        assert!(body.contains(">doc.html<"));
+        assert!(body.contains(">file.txt<"));
+        assert!(body.contains(">docs/<"));
+        assert!(!body.contains(">doc.html.br<"));
+        assert!(!body.contains(">doc.html.gz<"));
+    }
+
+    #[test]
+    fn assets_fallback_roots() {
+        let server = TestServer::new(build_simple_router(|route| {
+            route.get("/*").to_dir(
+                FileOptions::new("resources/test/assets_overlay")
+                    .with_fallback_roots(&["resources/test/assets_hidden", "resources/test/assets"])
+                    .build(),
+            )
+        }))
+        .unwrap();
+
+        let get = |path: &str| {
+            server
+                .client()
+                .get(&format!("http://localhost/{}", path))
+                .perform()
+                .unwrap()
+        };
+
+        let response = get("doc.html");
+        assert_eq!(response.status(), StatusCode::OK);
+        assert_eq!(
+            &response.read_body().unwrap()[..],
+            b"<html>I am an overriding doc.</html>"
+        );
+
+        let response = get("visible.txt");
+        assert_eq!(response.status(), StatusCode::OK);
+        assert_eq!(&response.read_body().unwrap()[..], b"I am visible");
+
+        let response = get("styles/style.css");
+        assert_eq!(response.status(), StatusCode::OK);
+        assert_eq!(
+            &response.read_body().unwrap()[..],
+            b".styled { border: none; }"
+        );
+
+        assert_eq!(get("missing.txt").status(), StatusCode::NOT_FOUND);
+    }
+
+    #[test]
+    fn assets_charset() {
+        for &(charset, expected) in &[
+            (None, "text/html"),
+            (Some("iso-8859-1"), "text/html; charset=iso-8859-1"),
+        ] {
+            let test_server = TestServer::new(build_simple_router(|route| {
+                route.get("/").to_file(
+                    FileOptions::new("resources/test/assets/doc.html")
+                        .with_charset(charset)
+                        .build(),
+                )
+            }))
+            .unwrap();
+
+            let response = test_server
+                .client()
+                .get("http://localhost/")
+                .perform()
+                .unwrap();
+            assert_eq!(response.headers().get(CONTENT_TYPE).unwrap(), expected);
+        }
+
+        // A charset given in an overridden type is kept
+        let test_server = TestServer::new(build_simple_router(|route| {
+            route.get("/").to_file(
+                FileOptions::new("resources/test/assets/file.txt")
+                    .with_mime_type("txt", "text/plain; charset=us-ascii".parse().unwrap())
+                    .build(),
+            )
+        }))
+        .unwrap();
+
+        let response = test_server
+            .client()
+            .get("http://localhost/")
+            .perform()
+            .unwrap();
+        assert_eq!(
+            response.headers().get(CONTENT_TYPE).unwrap(),
+            "text/plain; charset=us-ascii"
+        );
+    }
+
+    #[test]
+    fn assets_metrics() {
+        use super::FileMetrics;
+        use std::sync::{Arc, Mutex};
+        use std::time::Duration;
+
+        #[derive(Clone, Default)]
+        struct Records(Arc<Mutex<Vec<(String, StatusCode, u64)>>>);
+
+        impl FileMetrics for Records {
+            fn record(&self, path: &str, status: StatusCode, bytes_sent: u64, _: Duration) {
+                self.0
+                    .lock()
+                    .unwrap()
+                    .push((path.to_owned(), status, bytes_sent));
+            }
+        }
+
+        let records = Records::default();
+        let server = TestServer::new(build_simple_router(|route| {
+            route.get_or_head("/*").to_dir(
+                FileOptions::new("resources/test/assets")
+                    .with_metrics(records.clone())
+                    .build(),
+            )
+        }))
+        .unwrap();
+
+        let client = server.client();
+        client.get("http://localhost/doc.html").perform().unwrap();
+        client.head("http://localhost/doc.html").perform().unwrap();
+        client
+            .get("http://localhost/missing.txt")
+            .perform()
+            .unwrap();
+        client
+            .get("http://localhost/doc.html")
+            .with_header(RANGE, HeaderValue::from_static("bytes=0-5"))
+            .perform()
+            .unwrap();
+
+        assert_eq!(
+            *records.0.lock().unwrap(),
+            vec![
+                ("/doc.html".to_owned(), StatusCode::OK, 24),
+                ("/doc.html".to_owned(), StatusCode::OK, 0),
+                ("/missing.txt".to_owned(), StatusCode::NOT_FOUND, 0),
+                ("/doc.html".to_owned(), StatusCode::PARTIAL_CONTENT, 6),
+            ]
+        );
+    }
+
+    fn test_server() -> TestServer {
+        TestServer::new(static_router("/*", "resources/test/assets")).unwrap()
+    }
+
+    #[test]
+    fn assets_custom_file_system() {
+        use super::{FileSource, FileStream, FileSystem, MetadataFuture, OpenFuture, ReadFile};
+        use futures::{future, stream};
+        use hyper::Chunk;
+        use std::collections::HashMap;
+        use std::io;
+        use std::path::{Path, PathBuf};
+        use std::time::UNIX_EPOCH;
+
+        // Serves files from memory, with text files read by range as from a remote store.
+        struct MemoryFileSystem(HashMap<PathBuf, &'static [u8]>);
+
+        struct RangeReader(&'static [u8]);
+
+        impl ReadFile for RangeReader {
+            fn read_range(&mut self, start: u64, len: u64) -> FileStream {
+                let range = &self.0[start as usize..(start + len) as usize];
+                Box::new(stream::once(Ok(Chunk::from(range))))
+            }
+        }
+
+        impl MemoryFileSystem {
+            fn get(&self, path: &Path) -> io::Result<FileMetadata> {
+                match self.0.get(path) {
+                    Some(file) => Ok(FileMetadata::file(file.len() as u64, Some(UNIX_EPOCH))),
+                    None if self.0.keys().any(|p| p.starts_with(path)) => {
+                        Ok(FileMetadata::directory(None))
+                    }
+                    None => Err(io::Error::new(io::ErrorKind::NotFound, "no such file")),
+                }
+            }
+        }
+
+        impl FileSystem for MemoryFileSystem {
+            fn metadata(&self, path: &Path) -> Box<MetadataFuture> {
+                Box::new(future::result(self.get(path)))
+            }
+
+            fn open(&self, path: &Path) -> Box<OpenFuture> {
+                let result = self.get(path).map(|meta| {
+                    let contents = self.0[path];
+                    let source = if path.extension() == Some("txt".as_ref()) {
+                        FileSource::from_reader(RangeReader(contents))
+                    } else {
+                        FileSource::from_bytes(contents)
+                    };
+                    (source, meta)
+                });
+                Box::new(future::result(result))
+            }
+        }
+
+        let mut files = HashMap::new();
+        files.insert(
+            PathBuf::from("memory/doc.html"),
+            &b"<html>I am a doc.</html>"[..],
+        );
+        files.insert(PathBuf::from("memory/file.txt"), &b"I am a file"[..]);
+        files.insert(
+            PathBuf::from("memory/docs/index.html"),
+            &b"<html>I am the index.</html>"[..],
+        );
+
+        let router = build_simple_router(|route| {
+            route.get("/*").to_dir(
+                FileOptions::new("memory")
+                    .with_file_system(MemoryFileSystem(files))
+                    .build(),
+            )
+        });
+        let server = TestServer::new(router).unwrap();
+
+        let response = server
+            .client()
+            .get("http://localhost/doc.html")
+            .perform()
+            .unwrap();
+        assert_eq!(response.status(), StatusCode::OK);
+        assert!(response.headers().get(ETAG).is_some());
+        assert_eq!(
+            &response.read_body().unwrap()[..],
+            b"<html>I am a doc.</html>"
+        );
+
+        let response = server
+            .client()
+            .get("http://localhost/docs/")
+            .perform()
+            .unwrap();
+        assert_eq!(response.status(), StatusCode::OK);
+        assert_eq!(
+            &response.read_body().unwrap()[..],
+            b"<html>I am the index.</html>"
+        );
+
+        let response = server
+            .client()
+            .get("http://localhost/file.txt")
+            .with_header(RANGE, HeaderValue::from_static("bytes=5-"))
+            .perform()
+            .unwrap();
+        assert_eq!(response.status(), StatusCode::PARTIAL_CONTENT);
+        assert_eq!(&response.read_body().unwrap()[..], b"a file");
+
+        let response = server
+            .client()
+            .get("http://localhost/missing.txt")
+            .perform()
+            .unwrap();
+        assert_eq!(response.status(), StatusCode::NOT_FOUND);
     }
 
     fn static_router(mount: &str, path: &str) -> Router {
@@ -0,0 +1,215 @@
+//! Defines `ByteRange` for parsing 'Range' header values in requests,
+//! used to serve partial content for static assets.
+
+use hyper::header::{HeaderMap, RANGE};
+
+/// A single, satisfiable range of bytes within a file. Both `start`
+/// and `end` are inclusive offsets, as in the 'Content-Range' header.
+#[derive(Clone, Copy, PartialEq, Debug)]
+pub struct ByteRange {
+    pub start: u64,
+    pub end: u64,
+}
+
+impl ByteRange {
+    /// Returns the number of bytes covered by this range.
+    pub fn len(&self) -> u64 {
+        self.end - self.start + 1
+    }
+
+    /// Formats the value of a 'Content-Range' header for this range
+    /// within a file of `complete_length` bytes.
+    pub fn content_range(&self, complete_length: u64) -> String {
+        format!("bytes {}-{}/{}", self.start, self.end, complete_length)
+    }
+}
+
+/// The outcome of inspecting the 'Range' header of a request.
+#[derive(PartialEq, Debug)]
+pub enum RangeRequest {
+    /// No usable 'Range' header was sent, so the full file should be served.
+    Full,
+    /// One or more satisfiable ranges were requested.
+    Partial(Vec<ByteRange>),
+    /// A valid 'Range' header was sent, but none of its ranges overlap the file.
+    Unsatisfiable,
+}
+
+/// Determines which bytes of a file of `len` bytes have been requested.
+///
+/// Headers which are malformed, or which use a unit other than "bytes",
+/// are ignored as permitted by RFC 7233, and the full file is served.
+///
+/// e.g.
+/// Range: bytes=0-499
+/// Range: bytes=500-
+/// Range: bytes=-500
+/// Range: bytes=0-0, -1
+pub fn requested_ranges(headers: &HeaderMap, len: u64) -> RangeRequest {
+    let specs = match headers
+        .get(RANGE)
+        .and_then(|val| val.to_str().ok())
+        .and_then(parse_range_specs)
+    {
+        Some(specs) => specs,
+        None => return RangeRequest::Full,
+    };
+
+    let ranges: Vec<ByteRange> = specs
+        .into_iter()
+        .filter_map(|spec| spec.resolve(len))
+        .collect();
+
+    if ranges.is_empty() {
+        RangeRequest::Unsatisfiable
+    } else {
+        RangeRequest::Partial(ranges)
+    }
+}
+
+// A byte range spec as it appears in the header, before being
+// resolved against the length of the file.
+#[derive(PartialEq, Debug)]
+enum RangeSpec {
+    FromTo(u64, u64),
+    From(u64),
+    Last(u64),
+}
+
+impl RangeSpec {
+    fn resolve(self, len: u64) -> Option<ByteRange> {
+        match self {
+            RangeSpec::FromTo(start, end) if start < len => Some(ByteRange {
+                start,
+                end: end.min(len - 1),
+            }),
+            RangeSpec::From(start) if start < len => Some(ByteRange {
+                start,
+                end: len - 1,
+            }),
+            RangeSpec::Last(suffix) if suffix > 0 && len > 0 => Some(ByteRange {
+                start: len.saturating_sub(suffix),
+                end: len - 1,
+            }),
+            _ => None,
+        }
+    }
+}
+
+// Parses the full header value, returning `None` if any part of it is invalid.
+fn parse_range_specs(value: &str) -> Option<Vec<RangeSpec>> {
+    let mut parts = value.trim().splitn(2, '=');
+    if parts.next().map(str::trim) != Some("bytes") {
+        return None;
+    }
+
+    let specs = parts
+        .next()?
+        .split(',')
+        .map(str::trim)
+        .filter(|spec| !spec.is_empty())
+        .map(parse_range_spec)
+        .collect::<Option<Vec<RangeSpec>>>()?;
+
+    if specs.is_empty() {
+        None
+    } else {
+        Some(specs)
+    }
+}
+
+fn parse_range_spec(spec: &str) -> Option<RangeSpec> {
+    let mut bounds = spec.splitn(2, '-');
+    let start = bounds.next()?.trim();
+    let end = bounds.next()?.trim();
+
+    match (start.is_empty(), end.is_empty()) {
+        (true, false) => end.parse().ok().map(RangeSpec::Last),
+        (false, true) => start.parse().ok().map(RangeSpec::From),
+        (false, false) => {
+            let start = start.parse().ok()?;
+            let end = end.parse().ok()?;
+            if start <= end {
+                Some(RangeSpec::FromTo(start, end))
+            } else {
+                None
+            }
+        }
+        (true, true) => None,
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::{requested_ranges, ByteRange, RangeRequest};
+    use hyper::header::{HeaderMap, RANGE};
+
+    fn ranges_for(value: &str, len: u64) -> RangeRequest {
+        let mut headers = HeaderMap::new();
+        headers.insert(RANGE, value.parse().unwrap());
+        requested_ranges(&headers, len)
+    }
+
+    #[test]
+    fn range_absent() {
+        assert_eq!(requested_ranges(&HeaderMap::new(), 100), RangeRequest::Full);
+    }
+
+    #[test]
+    fn range_single_forms() {
+        assert_eq!(
+            ranges_for("bytes=0-9", 100),
+            RangeRequest::Partial(vec![ByteRange { start: 0, end: 9 }])
+        );
+        assert_eq!(
+            ranges_for("bytes=90-", 100),
+            RangeRequest::Partial(vec![ByteRange { start: 90, end: 99 }])
+        );
+        assert_eq!(
+            ranges_for("bytes=-10", 100),
+            RangeRequest::Partial(vec![ByteRange { start: 90, end: 99 }])
+        );
+        assert_eq!(
+            ranges_for("bytes=95-200", 100),
+            RangeRequest::Partial(vec![ByteRange { start: 95, end: 99 }])
+        );
+        assert_eq!(
+            ranges_for("bytes=-200", 100),
+            RangeRequest::Partial(vec![ByteRange { start: 0, end: 99 }])
+        );
+    }
+
+    #[test]
+    fn range_multiple() {
+        assert_eq!(
+            ranges_for("bytes=0-0, -1", 100),
+            RangeRequest::Partial(vec![
+                ByteRange { start: 0, end: 0 },
+                ByteRange { start: 99, end: 99 },
+            ])
+        );
+    }
+
+    #[test]
+    fn range_unsatisfiable() {
+        assert_eq!(ranges_for("bytes=100-", 100), RangeRequest::Unsatisfiable);
+        assert_eq!(ranges_for("bytes=-0", 100), RangeRequest::Unsatisfiable);
+        assert_eq!(ranges_for("bytes=0-", 0), RangeRequest::Unsatisfiable);
+    }
+
+    #[test]
+    fn range_malformed_is_ignored() {
+        assert_eq!(ranges_for("items=0-9", 100), RangeRequest::Full);
+        assert_eq!(ranges_for("bytes=9-0", 100), RangeRequest::Full);
+        assert_eq!(ranges_for("bytes=-", 100), RangeRequest::Full);
+        assert_eq!(ranges_for("bytes=a-b", 100), RangeRequest::Full);
+        assert_eq!(ranges_for("bytes=", 100), RangeRequest::Full);
+    }
+
+    #[test]
+    fn range_content_range() {
+        let range = ByteRange { start: 10, end: 19 };
+        assert_eq!(range.len(), 10);
+        assert_eq!(range.content_range(100), "bytes 10-19/100");
+    }
+}
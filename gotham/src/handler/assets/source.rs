@@ -0,0 +1,142 @@
+//! Defines `FileSource`, which provides the contents of a static asset
+//! either from an open file, from memory, or from a `ReadFile`.
+
+use bytes::Bytes;
+use futures::future::{self, Either};
+use futures::{stream, Future, Stream};
+use hyper::Chunk;
+use tokio::fs::File;
+
+use std::io;
+use std::path::PathBuf;
+use std::sync::Arc;
+
+use super::cache::FileCache;
+use super::file_stream;
+use super::vfs::{FileSystem, OpenFuture};
+
+/// A stream of chunks making up a response body.
+pub type FileStream = Box<dyn Stream<Item = Chunk, Error = io::Error> + Send>;
+
+/// The contents of a file which is to be served, as opened by a `FileSystem`.
+pub struct FileSource {
+    contents: Contents,
+}
+
+enum Contents {
+    // An open file, which is read in chunks of the given size.
+    Disk(File, usize),
+    // The full contents of the file, held in memory.
+    Memory(Bytes),
+    // A file which is read by range, such as from a remote store.
+    Reader(Box<dyn ReadFile>),
+}
+
+/// A file opened by a `FileSystem`, from which ranges of bytes are streamed. The full file is
+/// read as a single range, and requests for multiple ranges read each in turn.
+pub trait ReadFile: Send {
+    /// Streams the `len` bytes of the file starting at offset `start`.
+    fn read_range(&mut self, start: u64, len: u64) -> FileStream;
+}
+
+/// A part of a response body built from a file.
+#[derive(Debug, PartialEq)]
+pub(super) enum BodyPart {
+    /// The given number of bytes of the file, from the given offset.
+    Range(u64, u64),
+    /// Bytes which are not part of the file, such as the headers of a multipart body.
+    Bytes(Bytes),
+}
+
+impl FileSource {
+    /// Creates a `FileSource` for a file whose contents are held in memory.
+    pub fn from_bytes<B: Into<Bytes>>(bytes: B) -> FileSource {
+        FileSource {
+            contents: Contents::Memory(bytes.into()),
+        }
+    }
+
+    /// Creates a `FileSource` for a file which is streamed by the given `ReadFile`.
+    pub fn from_reader<R: ReadFile + 'static>(reader: R) -> FileSource {
+        FileSource {
+            contents: Contents::Reader(Box::new(reader)),
+        }
+    }
+
+    // Creates a `FileSource` for a file on disk, read in chunks of `buf_size` bytes.
+    pub(super) fn disk(file: File, buf_size: usize) -> FileSource {
+        FileSource {
+            contents: Contents::Disk(file, buf_size),
+        }
+    }
+
+    /// Opens the file at `path` from the `FileSystem`, returning its contents along with its
+    /// metadata. When a cache is given, the contents are taken from the cache where possible,
+    /// and the cache is populated if the file is small enough.
+    pub(super) fn open(
+        fs: Arc<dyn FileSystem>,
+        path: PathBuf,
+        cache: Option<FileCache>,
+    ) -> Box<OpenFuture> {
+        let cache = match cache {
+            Some(cache) => cache,
+            None => return fs.open(&path),
+        };
+
+        Box::new(fs.metadata(&path).and_then(move |meta| {
+            if let Some(bytes) = cache.get(&path, &meta) {
+                return Either::A(future::ok((FileSource::from_bytes(bytes), meta)));
+            }
+
+            if cache.accepts(&meta) {
+                Either::B(Either::A(fs.open(&path).and_then(move |(source, meta)| {
+                    source
+                        .stream(vec![BodyPart::Range(0, meta.len())])
+                        .concat2()
+                        .map(move |contents| {
+                            let bytes = contents.into_bytes();
+                            cache.insert(path, meta.clone(), bytes.clone());
+                            (FileSource::from_bytes(bytes), meta)
+                        })
+                })))
+            } else {
+                Either::B(Either::B(fs.open(&path)))
+            }
+        }))
+    }
+
+    /// Streams the given parts in order, reading ranges from the contents.
+    pub(super) fn stream(self, parts: Vec<BodyPart>) -> FileStream {
+        match self.contents {
+            Contents::Disk(file, buf_size) => Box::new(file_stream(file, buf_size, parts)),
+            Contents::Memory(bytes) => {
+                let chunks = parts.into_iter().map(move |part| match part {
+                    BodyPart::Range(start, len) => {
+                        let start = clamp_offset(start, &bytes);
+                        let end = clamp_offset(start as u64 + len, &bytes);
+                        Chunk::from(bytes.slice(start, end))
+                    }
+                    BodyPart::Bytes(bytes) => Chunk::from(bytes),
+                });
+                Box::new(stream::iter_ok(chunks))
+            }
+            Contents::Reader(mut reader) => {
+                let streams: Vec<FileStream> = parts
+                    .into_iter()
+                    .map(|part| match part {
+                        BodyPart::Range(start, len) => reader.read_range(start, len),
+                        BodyPart::Bytes(bytes) => {
+                            Box::new(stream::once(Ok(Chunk::from(bytes)))) as FileStream
+                        }
+                    })
+                    .collect();
+                Box::new(stream::iter_ok::<_, io::Error>(streams).flatten())
+            }
+        }
+    }
+}
+
+// Limits an offset to the length of the contents.
+fn clamp_offset(offset: u64, bytes: &Bytes) -> usize {
+    offset.min(bytes.len() as u64) as usize
+}
@@ -0,0 +1,214 @@
+//! Defines `FileSystem`, the abstraction through which static file handlers
+//! read files, along with `StdFileSystem`, which reads the local filesystem.
+
+use futures::{future, Future, Stream};
+use tokio::fs::{self, File};
+
+use std::fs::Metadata;
+use std::io;
+use std::panic::RefUnwindSafe;
+use std::path::Path;
+use std::time::SystemTime;
+
+use super::listing::DirectoryEntry;
+use super::optimal_buf_size;
+use super::source::FileSource;
+
+/// Type alias for the future returned by `FileSystem::metadata`.
+pub type MetadataFuture = dyn Future<Item = FileMetadata, Error = io::Error> + Send;
+
+/// Type alias for the future returned by `FileSystem::open`.
+pub type OpenFuture = dyn Future<Item = (FileSource, FileMetadata), Error = io::Error> + Send;
+
+/// Type alias for the future returned by `FileSystem::read_dir`.
+pub type ReadDirFuture = dyn Future<Item = Vec<DirectoryEntry>, Error = io::Error> + Send;
+
+/// The source of the files served by a `FileHandler` or `DirHandler`, as configured by
+/// `FileOptions::with_file_system`. This allows files to be served from an archive, a remote
+/// object store or memory, while conditional requests, ranges, compression and caching are
+/// handled as they are for files on disk.
+///
+/// Paths are those which would be read from disk, formed by joining the request path to the
+/// configured root, and errors of kind `NotFound` or `PermissionDenied` are reported to the
+/// client as `404 Not Found` and `403 Forbidden` responses.
+///
+/// ```rust
+/// # extern crate futures;
+/// # extern crate gotham;
+/// # use std::collections::HashMap;
+/// # use std::io;
+/// # use std::path::Path;
+/// # use futures::future;
+/// # use gotham::handler::assets::*;
+/// #
+/// struct MemoryFileSystem(HashMap<&'static str, &'static [u8]>);
+///
+/// impl MemoryFileSystem {
+///     fn get(&self, path: &Path) -> io::Result<&'static [u8]> {
+///         path.to_str()
+///             .and_then(|path| self.0.get(path).cloned())
+///             .ok_or_else(|| io::Error::new(io::ErrorKind::NotFound, "no such file"))
+///     }
+/// }
+///
+/// impl FileSystem for MemoryFileSystem {
+///     fn metadata(&self, path: &Path) -> Box<MetadataFuture> {
+///         let result = self.get(path).map(|f| FileMetadata::file(f.len() as u64, None));
+///         Box::new(future::result(result))
+///     }
+///
+///     fn open(&self, path: &Path) -> Box<OpenFuture> {
+///         let result = self.get(path).map(|f| {
+///             let meta = FileMetadata::file(f.len() as u64, None);
+///             (FileSource::from_bytes(f), meta)
+///         });
+///         Box::new(future::result(result))
+///     }
+/// }
+///
+/// let mut files = HashMap::new();
+/// files.insert("assets/app.js", &b"console.log('I am in memory!');"[..]);
+///
+/// FileOptions::new("assets")
+///     .with_file_system(MemoryFileSystem(files))
+///     .build();
+/// ```
+pub trait FileSystem: Send + Sync + RefUnwindSafe {
+    /// Returns the metadata of the file or directory at `path`.
+    fn metadata(&self, path: &Path) -> Box<MetadataFuture>;
+
+    /// Opens the file at `path` for reading, returning its contents along with its metadata.
+    fn open(&self, path: &Path) -> Box<OpenFuture>;
+
+    /// Lists the entries of the directory at `path`, for rendering directory listings. By
+    /// default, listings are not supported and this fails.
+    fn read_dir(&self, _path: &Path) -> Box<ReadDirFuture> {
+        Box::new(future::err(io::Error::other(
+            "directory listings are not supported",
+        )))
+    }
+}
+
+/// The metadata of a file or directory in a `FileSystem`.
+#[derive(Clone, Debug, PartialEq)]
+pub struct FileMetadata {
+    is_dir: bool,
+    len: u64,
+    modified: Option<SystemTime>,
+    block_size: Option<usize>,
+}
+
+impl FileMetadata {
+    /// Creates the metadata of a file of `len` bytes. The modification time is used for the
+    /// "last-modified" and "etag" headers, which are not sent without it.
+    pub fn file(len: u64, modified: Option<SystemTime>) -> FileMetadata {
+        FileMetadata {
+            is_dir: false,
+            len,
+            modified,
+            block_size: None,
+        }
+    }
+
+    /// Creates the metadata of a directory.
+    pub fn directory(modified: Option<SystemTime>) -> FileMetadata {
+        FileMetadata {
+            is_dir: true,
+            len: 0,
+            modified,
+            block_size: None,
+        }
+    }
+
+    /// Returns `true` if this is the metadata of a directory.
+    pub fn is_dir(&self) -> bool {
+        self.is_dir
+    }
+
+    /// Returns `true` if this is the metadata of a file.
+    pub fn is_file(&self) -> bool {
+        !self.is_dir
+    }
+
+    /// Returns the length of the file in bytes.
+    pub fn len(&self) -> u64 {
+        self.len
+    }
+
+    /// Returns `true` if the file is empty.
+    pub fn is_empty(&self) -> bool {
+        self.len == 0
+    }
+
+    /// Returns the time the file was last modified, if known.
+    pub fn modified(&self) -> Option<SystemTime> {
+        self.modified
+    }
+
+    /// Returns the block size of the underlying filesystem, if known, which is used as the
+    /// size of the chunks read from disk.
+    pub(super) fn block_size(&self) -> Option<usize> {
+        self.block_size
+    }
+}
+
+impl<'a> From<&'a Metadata> for FileMetadata {
+    fn from(meta: &'a Metadata) -> FileMetadata {
+        FileMetadata {
+            is_dir: meta.is_dir(),
+            len: meta.len(),
+            modified: meta.modified().ok(),
+            block_size: Some(get_block_size(meta)),
+        }
+    }
+}
+
+/// The default `FileSystem`, which reads files from the local filesystem on the Tokio blocking
+/// thread pool.
+#[derive(Clone, Copy, Debug, Default, PartialEq)]
+pub struct StdFileSystem;
+
+impl FileSystem for StdFileSystem {
+    fn metadata(&self, path: &Path) -> Box<MetadataFuture> {
+        Box::new(fs::metadata(path.to_path_buf()).map(|meta| FileMetadata::from(&meta)))
+    }
+
+    fn open(&self, path: &Path) -> Box<OpenFuture> {
+        Box::new(
+            File::open(path.to_path_buf())
+                .and_then(File::metadata)
+                .map(|(file, meta)| {
+                    let meta = FileMetadata::from(&meta);
+                    (FileSource::disk(file, optimal_buf_size(&meta)), meta)
+                }),
+        )
+    }
+
+    fn read_dir(&self, path: &Path) -> Box<ReadDirFuture> {
+        Box::new(
+            fs::read_dir(path.to_path_buf())
+                .flatten_stream()
+                .and_then(|entry| {
+                    let name = entry.file_name().to_string_lossy().into_owned();
+                    future::poll_fn(move || entry.poll_metadata()).map(move |meta| DirectoryEntry {
+                        name,
+                        is_dir: meta.is_dir(),
+                        size: meta.len(),
+                        modified: meta.modified().ok(),
+                    })
+                })
+                .collect(),
+        )
+    }
+}
+
+#[cfg(unix)]
+fn get_block_size(metadata: &Metadata) -> usize {
+    use std::os::unix::fs::MetadataExt;
+    metadata.blksize() as usize
+}
+
+#[cfg(not(unix))]
+fn get_block_size(_metadata: &Metadata) -> usize {
+    8_192
+}
@@ -6,7 +6,7 @@ use log::{debug, trace};
 
 use crate::handler::IntoResponse;
 use crate::helpers::http::response::create_empty_response;
-use crate::state::{request_id, State};
+use crate::state::{request_id, State, StateData};
 
 /// Describes an error which occurred during handler execution, and allows the creation of a HTTP
 /// `Response`.
@@ -126,8 +126,42 @@ impl HandlerError {
             ..self
         }
     }
+
+    /// Returns the HTTP status code of the response which will be generated for this error.
+    pub fn status(&self) -> StatusCode {
+        self.status_code
+    }
+
+    /// Returns the error which caused this `HandlerError`, where it's of the type `E`, so that
+    /// the response can depend on the specific error, such as in an `ErrorMapper`.
+    ///
+    /// ```rust
+    /// # extern crate gotham;
+    /// #
+    /// # use std::io;
+    /// # use gotham::handler::IntoHandlerError;
+    /// #
+    /// # fn main() {
+    /// let cause = io::Error::new(io::ErrorKind::NotFound, "missing");
+    /// let handler_error = cause.into_handler_error();
+    ///
+    /// let cause = handler_error.downcast_cause_ref::<io::Error>().unwrap();
+    /// assert_eq!(cause.kind(), io::ErrorKind::NotFound);
+    /// assert!(handler_error.downcast_cause_ref::<std::fmt::Error>().is_none());
+    /// # }
+    /// ```
+    pub fn downcast_cause_ref<E>(&self) -> Option<&E>
+    where
+        E: Error + 'static,
+    {
+        self.cause.downcast_ref::<E>()
+    }
 }
 
+/// A `HandlerError` is put into `State` before the handler registered with
+/// `RouterBuilder::internal_error` is called, so that it can describe the error.
+impl StateData for HandlerError {}
+
 impl IntoResponse for HandlerError {
     fn into_response(self, state: &State) -> Response<Body> {
         debug!(
@@ -20,6 +20,8 @@ use crate::error::*;
 /// Defines handlers for serving static assets.
 pub mod assets;
 
+pub mod redirect;
+
 pub use self::error::{HandlerError, IntoHandlerError};
 
 /// A type alias for the trait objects returned by `HandlerService`.
@@ -36,8 +38,8 @@ pub type HandlerFuture =
 /// the `Router` API to describe how a request should be dispatched and handled.
 ///
 /// The `Handler` is created and consumed by each request. In the most common case (a bare function
-/// acting as a `Handler`) the `Handler + Copy` traits allow the `Handler` to be copied for each
-/// request, and the copy consumed. For a closure or a custom handler, the `NewHandler`
+/// or a closure acting as a `Handler`) the `Handler + Clone` traits allow the `Handler` to be
+/// cloned for each request, and the clone consumed. For a custom handler, the `NewHandler`
 /// implementation creates a `Handler` value for each request.
 ///
 /// # Examples
@@ -0,0 +1,104 @@
+//! Defines a handler which redirects requests to another location.
+
+use futures::future;
+use hyper::header::{HeaderValue, LOCATION};
+use hyper::{StatusCode, Uri};
+
+use crate::error::Result;
+use crate::handler::{Handler, HandlerFuture, NewHandler};
+use crate::helpers::http::response::create_empty_response;
+use crate::state::{FromState, State};
+
+/// A `Handler` which responds to every request with a redirect to a fixed location, as used by
+/// `DefineSingleRoute::redirect_to`.
+///
+/// Where the location doesn't include a query string, the query string of the request is
+/// appended to it, so that parameters are kept when a legacy path is redirected.
+#[derive(Clone, Debug)]
+pub struct RedirectHandler {
+    location: String,
+    status: StatusCode,
+}
+
+impl RedirectHandler {
+    /// Creates a new `RedirectHandler` for the given `location`, using the given redirection
+    /// `status`, such as `StatusCode::MOVED_PERMANENTLY`.
+    ///
+    /// # Panics
+    ///
+    /// When the `status` isn't a redirection (`3xx`) status, or the `location` isn't a valid
+    /// header value.
+    pub fn new(location: &str, status: StatusCode) -> Self {
+        assert!(
+            status.is_redirection(),
+            "redirect status must be 3xx, not {}",
+            status
+        );
+        assert!(
+            HeaderValue::from_str(location).is_ok(),
+            "invalid redirect location {:?}",
+            location
+        );
+
+        RedirectHandler {
+            location: location.to_owned(),
+            status,
+        }
+    }
+
+    /// Determines the location to redirect the request to.
+    fn location(&self, uri: &Uri) -> String {
+        match uri.query() {
+            Some(query) if !self.location.contains('?') => {
+                format!("{}?{}", self.location, query)
+            }
+            _ => self.location.clone(),
+        }
+    }
+}
+
+impl NewHandler for RedirectHandler {
+    type Instance = Self;
+
+    fn new_handler(&self) -> Result<Self::Instance> {
+        Ok(self.clone())
+    }
+}
+
+impl Handler for RedirectHandler {
+    fn handle(self, state: State) -> Box<HandlerFuture> {
+        let location = self.location(Uri::borrow_from(&state));
+
+        let mut res = create_empty_response(&state, self.status);
+        res.headers_mut()
+            .insert(LOCATION, location.parse().unwrap());
+
+        Box::new(future::ok((state, res)))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn keeps_query_string() {
+        let handler = RedirectHandler::new("/new", StatusCode::MOVED_PERMANENTLY);
+
+        let uri = "/old?page=2".parse().unwrap();
+        assert_eq!(handler.location(&uri), "/new?page=2");
+
+        let uri = "/old".parse().unwrap();
+        assert_eq!(handler.location(&uri), "/new");
+
+        let handler = RedirectHandler::new("/new?tab=1", StatusCode::FOUND);
+        let uri = "/old?page=2".parse().unwrap();
+        assert_eq!(handler.location(&uri), "/new?tab=1");
+    }
+
+    #[test]
+    #[should_panic(expected = "redirect status must be 3xx, not 200 OK")]
+    fn rejects_non_redirect_status() {
+        RedirectHandler::new("/new", StatusCode::OK);
+    }
+}
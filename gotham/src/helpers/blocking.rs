@@ -0,0 +1,27 @@
+//! Defines a helper for running blocking I/O without stalling the thread serving connections.
+use futures::{future, Async, Future};
+
+/// Runs blocking I/O, such as a request to a network store, a write to disk or a filesystem
+/// operation which `tokio::fs` has no equivalent for, on the blocking threads of the Tokio thread
+/// pool, so that it doesn't stall the other connections served by the thread.
+///
+/// Where the future isn't polled by the Tokio thread pool, such as on a `current_thread`
+/// runtime, the operation runs on the polling thread instead.
+pub(crate) fn run_blocking<F, T, E>(f: F) -> impl Future<Item = T, Error = E> + Send
+where
+    F: FnOnce() -> Result<T, E> + Send,
+    T: Send,
+    E: Send,
+{
+    // `blocking` may defer running the operation until a blocking thread is available, so the
+    // operation is only taken once it runs
+    let mut f = Some(f);
+    future::poll_fn(move || {
+        let result = tokio_threadpool::blocking(|| (f.take().expect("polled after completion"))());
+        match result {
+            Ok(Async::Ready(result)) => result.map(Async::Ready),
+            Ok(Async::NotReady) => Ok(Async::NotReady),
+            Err(_) => (f.take().expect("polled after completion"))().map(Async::Ready),
+        }
+    })
+}
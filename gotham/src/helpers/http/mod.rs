@@ -44,15 +44,24 @@ impl AsRef<str> for PercentDecoded {
 }
 
 /// Decode form-urlencoded strings (e.g. query string, or request body with Content-Type:
-/// application/x-www-form-urlencoded
-fn form_url_decode(raw: &str) -> Result<String, std::str::Utf8Error> {
-    match percent_decode(raw.replace("+", " ").as_bytes()).decode_utf8() {
+/// application/x-www-form-urlencoded), optionally keeping `+` as a literal `+` rather than
+/// decoding it as a space
+fn url_decode(raw: &str, plus_as_space: bool) -> Result<String, std::str::Utf8Error> {
+    let spaced;
+    let source = if plus_as_space {
+        spaced = raw.replace("+", " ");
+        &spaced
+    } else {
+        raw
+    };
+
+    match percent_decode(source.as_bytes()).decode_utf8() {
         Ok(pd) => {
-            trace!(" form_url_decode: {}, src: {}", pd, raw);
+            trace!(" url_decode: {}, src: {}", pd, raw);
             Ok(pd.into_owned())
         }
         Err(e) => {
-            trace!(" form_url_decode: error, src: {}", raw);
+            trace!(" url_decode: error, src: {}", raw);
             Err(e)
         }
     }
@@ -72,8 +81,15 @@ impl FormUrlDecoded {
     /// On success, the decoded data is returned as a `FormUrlDecoded` value, which allows a
     /// compile-time check that the decode has occurred in places where it's assumed to have
     /// occurred.
+    #[cfg(test)]
     pub(crate) fn new(raw: &str) -> Option<Self> {
-        match form_url_decode(raw) {
+        FormUrlDecoded::decode(raw, true)
+    }
+
+    /// Attempt to decode data in the same way as `new`, optionally keeping `+` as a literal `+`
+    /// rather than decoding it as a space.
+    pub(crate) fn decode(raw: &str, plus_as_space: bool) -> Option<Self> {
+        match url_decode(raw, plus_as_space) {
             Ok(val) => Some(FormUrlDecoded { val }),
             Err(_) => None,
         }
@@ -100,5 +116,8 @@ mod tests {
     fn ensure_valid_www_form_url_encoded_value() {
         let f = FormUrlDecoded::new("%41+%42%2B%63%20%64").unwrap();
         assert_eq!("A B+c d", f.as_ref());
+
+        let f = FormUrlDecoded::decode("%41+%42%2B%63%20%64", false).unwrap();
+        assert_eq!("A+B+c d", f.as_ref());
     }
 }
@@ -16,6 +16,11 @@ pub(crate) fn split_path_segments<'a>(path: &'a str) -> impl Iterator<Item = &'a
     path.split('/').filter(|s| !EXCLUDED_SEGMENTS.contains(s))
 }
 
+/// Determines whether a path ends with a slash, other than a path of just `/`.
+pub(crate) fn has_trailing_slash(path: &str) -> bool {
+    path.len() > 1 && path.ends_with('/')
+}
+
 impl RequestPathSegments {
     /// Creates a new RequestPathSegments instance by splitting a `Request` URI path.
     ///
@@ -40,6 +45,15 @@ impl RequestPathSegments {
         }
     }
 
+    /// Creates a new RequestPathSegments instance with the given segment ahead of these segments.
+    pub(crate) fn prefixed(&self, segment: &str) -> Self {
+        let mut segments = Vec::with_capacity(self.segments.len() + 1);
+        segments.extend(PercentDecoded::new(segment));
+        segments.extend(self.segments.iter().cloned());
+
+        RequestPathSegments { segments }
+    }
+
     /// Provide segments that still need to be processed.
     ///
     /// This will always include a "/" node to represent the root as well as all segments
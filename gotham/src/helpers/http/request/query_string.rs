@@ -2,18 +2,23 @@
 
 use std::collections::HashMap;
 
-use crate::helpers::http::{form_url_decode, FormUrlDecoded};
+use crate::extractor::{QueryStringOptions, RepeatedKeys};
+use crate::helpers::http::{url_decode, FormUrlDecoded};
 
 /// Provides a mapping of keys from `Request` query string to their supplied values
 pub(crate) type QueryStringMapping = HashMap<String, Vec<FormUrlDecoded>>;
 
-/// Splits a query string into pairs and provides a mapping of keys to values.
+/// Splits a query string into pairs and provides a mapping of keys to values, decoded as
+/// described by the `QueryStringOptions`.
 ///
 /// For keys which are represented 1..n times in the query string the mapped `Vec` will be
-/// populated with each value provided.
+/// populated with each value provided, unless the options only keep the first or last value.
 ///
 /// Keys that are provided but with no value associated are skipped.
-pub(crate) fn split<'r>(query: Option<&'r str>) -> QueryStringMapping {
+pub(crate) fn split<'r>(
+    query: Option<&'r str>,
+    options: &QueryStringOptions,
+) -> QueryStringMapping {
     let mut query_string_mapping = QueryStringMapping::new();
 
     if let Some(query) = query {
@@ -23,10 +28,17 @@ pub(crate) fn split<'r>(query: Option<&'r str>) -> QueryStringMapping {
             let mut sp = p.splitn(2, '=');
             let (k, v) = (sp.next().unwrap(), sp.next().unwrap());
 
-            if let Ok(k) = form_url_decode(k) {
+            if let Some((k, bracketed)) = decode_key(k, options) {
                 let vec = query_string_mapping.entry(k).or_insert_with(Vec::new);
-                if let Some(dv) = FormUrlDecoded::new(v) {
-                    vec.push(dv);
+                if let Some(dv) = FormUrlDecoded::decode(v, options.plus_as_space) {
+                    match options.repeated_keys {
+                        RepeatedKeys::First if !bracketed && !vec.is_empty() => {}
+                        RepeatedKeys::Last if !bracketed => {
+                            vec.clear();
+                            vec.push(dv);
+                        }
+                        _ => vec.push(dv),
+                    }
                 }
             };
         }
@@ -35,6 +47,19 @@ pub(crate) fn split<'r>(query: Option<&'r str>) -> QueryStringMapping {
     query_string_mapping
 }
 
+/// Decodes a key of the query string as described by the `QueryStringOptions`, indicating
+/// whether the brackets of an array key were removed.
+pub(crate) fn decode_key(raw: &str, options: &QueryStringOptions) -> Option<(String, bool)> {
+    let mut key = url_decode(raw, options.plus_as_space).ok()?;
+
+    if options.bracketed_arrays && key.len() > 2 && key.ends_with("[]") {
+        key.truncate(key.len() - 2);
+        return Some((key, true));
+    }
+
+    Some((key, false))
+}
+
 fn is_separator(c: char) -> bool {
     c == '&' || c == ';'
 }
@@ -60,28 +85,60 @@ mod tests {
 
     #[test]
     fn query_string_mapping_tests() {
-        let qsm = split(Some("a=b&c=d&e=f"));
+        let qsm = split(Some("a=b&c=d&e=f"), &QueryStringOptions::default());
         assert_eq!(
             to_pairs(&qsm),
             vec![("a", vec!["b"]), ("c", vec!["d"]), ("e", vec!["f"])],
         );
 
-        let qsm = split(Some("a=b&a=d&e=f"));
+        let qsm = split(Some("a=b&a=d&e=f"), &QueryStringOptions::default());
         assert_eq!(
             to_pairs(&qsm),
             vec![("a", vec!["b", "d"]), ("e", vec!["f"])],
         );
 
-        let qsm = split(Some("a&b"));
+        let qsm = split(Some("a&b"), &QueryStringOptions::default());
         assert_eq!(to_pairs(&qsm), vec![],);
 
-        let qsm = split(Some("a=b;c=d&e=f"));
+        let qsm = split(Some("a=b;c=d&e=f"), &QueryStringOptions::default());
         assert_eq!(
             to_pairs(&qsm),
             vec![("a", vec!["b"]), ("c", vec!["d"]), ("e", vec!["f"])],
         );
 
-        let qsm = split(Some("a=b=c&d=e"));
+        let qsm = split(Some("a=b=c&d=e"), &QueryStringOptions::default());
         assert_eq!(to_pairs(&qsm), vec![("a", vec!["b=c"]), ("d", vec!["e"])],);
     }
+
+    #[test]
+    fn query_string_options_tests() {
+        let query = Some("a=1+2&a=3&b%5B%5D=x&b[]=y&c[]=z");
+
+        let qsm = split(query, &QueryStringOptions::default());
+        assert_eq!(
+            to_pairs(&qsm),
+            vec![
+                ("a", vec!["1 2", "3"]),
+                ("b[]", vec!["x", "y"]),
+                ("c[]", vec!["z"])
+            ],
+        );
+
+        let options = QueryStringOptions::default()
+            .with_plus_as_space(false)
+            .with_repeated_keys(RepeatedKeys::First)
+            .with_bracketed_arrays(true);
+        let qsm = split(query, &options);
+        assert_eq!(
+            to_pairs(&qsm),
+            vec![("a", vec!["1+2"]), ("b", vec!["x", "y"]), ("c", vec!["z"])],
+        );
+
+        let options = options.with_repeated_keys(RepeatedKeys::Last);
+        let qsm = split(query, &options);
+        assert_eq!(
+            to_pairs(&qsm),
+            vec![("a", vec!["3"]), ("b", vec!["x", "y"]), ("c", vec!["z"])],
+        );
+    }
 }
@@ -1,4 +1,5 @@
 //! Helpers, e.g. for HTTP request handling and response generation
 
+pub(crate) mod blocking;
 pub mod http;
 pub(crate) mod timing;
@@ -54,7 +54,7 @@ use tokio::net::{TcpListener, TcpStream};
 use tokio::runtime::{self, Runtime};
 use tokio_io::{AsyncRead, AsyncWrite};
 
-use crate::{handler::NewHandler, service::GothamService};
+use crate::{handler::NewHandler, service::GothamService, state::Scheme};
 
 pub use plain::*;
 #[cfg(feature = "rustls")]
@@ -87,10 +87,29 @@ where
 /// support. The wrap argument is a function that will receive a tokio-io TcpStream and should wrap
 /// the socket as necessary. Errors returned by this function will be ignored and the connection
 /// will be dropped if the future returned by the wrapper resolves to an error.
+///
+/// Requests are reported by `state::request_scheme` as `Scheme::Http`, so servers which set up
+/// their own TLS support should use `bind_server_with_scheme` instead.
 pub fn bind_server<NH, F, Wrapped, Wrap>(
+    listener: TcpListener,
+    new_handler: NH,
+    wrap: Wrap,
+) -> impl Future<Item = (), Error = ()>
+where
+    NH: NewHandler + 'static,
+    F: Future<Item = Wrapped, Error = ()> + Send + 'static,
+    Wrapped: AsyncRead + AsyncWrite + Send + 'static,
+    Wrap: FnMut(TcpStream) -> F,
+{
+    bind_server_with_scheme(listener, new_handler, wrap, Scheme::Http)
+}
+
+/// Same as `bind_server`, but reporting the given `Scheme` for requests.
+pub fn bind_server_with_scheme<NH, F, Wrapped, Wrap>(
     listener: TcpListener,
     new_handler: NH,
     mut wrap: Wrap,
+    scheme: Scheme,
 ) -> impl Future<Item = (), Error = ()>
 where
     NH: NewHandler + 'static,
@@ -99,7 +118,7 @@ where
     Wrap: FnMut(TcpStream) -> F,
 {
     let protocol = Arc::new(Http::new());
-    let gotham_service = GothamService::new(new_handler);
+    let gotham_service = GothamService::new(new_handler).with_scheme(scheme);
 
     listener
         .incoming()
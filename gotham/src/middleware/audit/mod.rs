@@ -0,0 +1,583 @@
+//! Audit middleware, which records who made each request, what it was for and how it was
+//! answered, and hands the record to a pluggable `AuditSink`.
+use std::fs::{File, OpenOptions};
+use std::io::{self, Write};
+use std::panic::RefUnwindSafe;
+use std::path::Path;
+use std::sync::{Arc, Mutex, PoisonError};
+use std::time::{Duration, Instant};
+
+use chrono::{DateTime, Utc};
+use futures::{future, Future};
+use hyper::{Method, StatusCode, Uri};
+use log::{error, info};
+use serde_json::json;
+
+use crate::handler::HandlerFuture;
+use crate::helpers::blocking::run_blocking;
+use crate::helpers::http::request::path::{has_trailing_slash, split_path_segments};
+use crate::helpers::http::{FormUrlDecoded, PercentDecoded};
+use crate::middleware::auth::AuthenticatedUser;
+use crate::middleware::{Middleware, NewMiddleware};
+use crate::router::introspection::RouteTemplate;
+use crate::state::request_id::request_id;
+use crate::state::{FromState, State};
+
+const REDACTED: &str = "[redacted]";
+
+/// The audit record of a single request, as given to an `AuditSink`.
+#[derive(Clone, Debug, PartialEq)]
+pub struct AuditRecord {
+    request_id: String,
+    time: DateTime<Utc>,
+    actor: Option<String>,
+    method: Method,
+    path: String,
+    route: Option<String>,
+    params: Vec<(String, String)>,
+    status: StatusCode,
+    latency: Duration,
+}
+
+impl AuditRecord {
+    /// The ID of the request.
+    pub fn request_id(&self) -> &str {
+        &self.request_id
+    }
+
+    /// The time the request was received.
+    pub fn time(&self) -> &DateTime<Utc> {
+        &self.time
+    }
+
+    /// The user who made the request, where one was identified.
+    pub fn actor(&self) -> Option<&str> {
+        self.actor.as_deref()
+    }
+
+    /// The method of the request.
+    pub fn method(&self) -> &Method {
+        &self.method
+    }
+
+    /// The path of the request, with the segments of redacted path parameters replaced.
+    pub fn path(&self) -> &str {
+        &self.path
+    }
+
+    /// The path template of the route the request was dispatched to, such as `/users/:id`, where
+    /// it had a route.
+    pub fn route(&self) -> Option<&str> {
+        self.route.as_deref()
+    }
+
+    /// The parameters of the request, being those of the route's path followed by those of the
+    /// query string, with the values of redacted fields replaced.
+    pub fn params(&self) -> &[(String, String)] {
+        &self.params
+    }
+
+    /// The status of the response, or of the `HandlerError` serving the request failed with.
+    pub fn status(&self) -> StatusCode {
+        self.status
+    }
+
+    /// The time taken to produce the response.
+    pub fn latency(&self) -> Duration {
+        self.latency
+    }
+
+    /// Formats the record as a JSON object, with the fields `time`, `request_id`, `actor`,
+    /// `method`, `path`, `route`, `params`, `status` and `latency_us`.
+    pub fn to_json(&self) -> String {
+        let params: serde_json::Map<String, serde_json::Value> = self
+            .params
+            .iter()
+            .map(|(name, value)| (name.clone(), value.clone().into()))
+            .collect();
+
+        json!({
+            "time": self.time.to_rfc3339(),
+            "request_id": self.request_id,
+            "actor": self.actor,
+            "method": self.method.as_str(),
+            "path": self.path,
+            "route": self.route,
+            "params": params,
+            "status": self.status.as_u16(),
+            "latency_us": self.latency.as_micros() as u64,
+        })
+        .to_string()
+    }
+}
+
+/// Type alias for the trait objects returned by `AuditSink::write`.
+pub type AuditFuture = dyn Future<Item = (), Error = io::Error> + Send;
+
+/// Receives the records produced by `AuditMiddleware`, such as to write them to a file, to syslog
+/// or to an HTTP collector.
+///
+/// `LogSink` writes each record to the log, and `FileSink` appends each record to a file. The
+/// future returned by `write` is chained into the future of the request, so the response is sent
+/// once the record is written. A sink which sends records over the network can buffer them and
+/// return a future which has already resolved, where responses shouldn't wait on the network.
+pub trait AuditSink: Send + Sync + RefUnwindSafe {
+    /// Writes a record. A sink which fails to write is logged, and doesn't fail the request.
+    fn write(&self, record: AuditRecord) -> Box<AuditFuture>;
+}
+
+impl<S> AuditSink for Arc<S>
+where
+    S: AuditSink + ?Sized,
+{
+    fn write(&self, record: AuditRecord) -> Box<AuditFuture> {
+        (**self).write(record)
+    }
+}
+
+/// An `AuditSink` which writes each record to the log at the `info` level, as JSON.
+#[derive(Clone, Copy, Debug, Default)]
+pub struct LogSink;
+
+impl AuditSink for LogSink {
+    fn write(&self, record: AuditRecord) -> Box<AuditFuture> {
+        info!("[audit] {}", record.to_json());
+        Box::new(future::ok(()))
+    }
+}
+
+/// An `AuditSink` which appends each record to a file, as a line of JSON.
+pub struct FileSink {
+    file: Arc<Mutex<File>>,
+}
+
+impl FileSink {
+    /// Opens the file at `path` to append records to, creating it where it doesn't exist.
+    pub fn open<P>(path: P) -> io::Result<FileSink>
+    where
+        P: AsRef<Path>,
+    {
+        let file = OpenOptions::new().create(true).append(true).open(path)?;
+        Ok(FileSink {
+            file: Arc::new(Mutex::new(file)),
+        })
+    }
+}
+
+impl AuditSink for FileSink {
+    fn write(&self, record: AuditRecord) -> Box<AuditFuture> {
+        let mut line = record.to_json();
+        line.push('\n');
+
+        // the write is made on the blocking threads of the Tokio thread pool, so waiting on the
+        // disk or on other writers doesn't stall other connections
+        let file = self.file.clone();
+        Box::new(run_blocking(move || {
+            let mut file = file.lock().unwrap_or_else(PoisonError::into_inner);
+            file.write_all(line.as_bytes())
+        }))
+    }
+}
+
+type Actor = dyn Fn(&State) -> Option<String> + Send + Sync + RefUnwindSafe;
+
+fn authenticated_user(state: &State) -> Option<String> {
+    AuthenticatedUser::try_borrow_from(state).map(|user| user.username().to_owned())
+}
+
+/// Middleware binding which produces an `AuditRecord` for each request, and writes it to an
+/// `AuditSink`.
+///
+/// The record is produced once the response has been produced, so that the actor reflects any
+/// authentication middleware later in the pipeline chain. By default, the actor is the
+/// `AuthenticatedUser` in `State`, and a different actor can be taken from `State` with
+/// `with_actor`. The values of parameters named by `with_redacted_fields`, such as passwords and
+/// tokens, are replaced before the record reaches the sink, including in the path where the
+/// parameter is a segment of the route. Records are written to a `LogSink` unless `with_sink` is
+/// used.
+///
+/// # Examples
+///
+/// ```rust
+/// # extern crate gotham;
+/// #
+/// # use gotham::middleware::audit::AuditMiddleware;
+/// # use gotham::pipeline::new_pipeline;
+/// # use gotham::pipeline::single::single_pipeline;
+/// # use gotham::router::builder::*;
+/// # use gotham::state::State;
+/// # use gotham::test::TestServer;
+/// #
+/// # fn handler(state: State) -> (State, &'static str) {
+/// #     (state, "hello")
+/// # }
+/// #
+/// # fn main() {
+/// let middleware = AuditMiddleware::new().with_redacted_fields(vec!["password", "token"]);
+///
+/// let (chain, pipelines) = single_pipeline(new_pipeline().add(middleware).build());
+/// let router = build_router(chain, pipelines, |route| {
+///     route.get("/login").to(handler);
+/// });
+///
+/// let test_server = TestServer::new(router).unwrap();
+/// let response = test_server
+///     .client()
+///     .get("http://localhost/login?user=alice&password=secret")
+///     .perform()
+///     .unwrap();
+///
+/// assert_eq!(response.read_utf8_body().unwrap(), "hello");
+/// # }
+/// ```
+pub struct AuditMiddleware<S = LogSink>
+where
+    S: AuditSink,
+{
+    sink: Arc<S>,
+    actor: Arc<Actor>,
+    redacted: Arc<Vec<String>>,
+}
+
+impl AuditMiddleware<LogSink> {
+    /// Creates an `AuditMiddleware` which writes records to the log.
+    pub fn new() -> Self {
+        AuditMiddleware {
+            sink: Arc::new(LogSink),
+            actor: Arc::new(authenticated_user),
+            redacted: Arc::new(Vec::new()),
+        }
+    }
+}
+
+impl Default for AuditMiddleware<LogSink> {
+    fn default() -> Self {
+        AuditMiddleware::new()
+    }
+}
+
+impl<S> AuditMiddleware<S>
+where
+    S: AuditSink,
+{
+    /// Writes records to `sink`, in place of a `LogSink`.
+    pub fn with_sink<T>(self, sink: T) -> AuditMiddleware<T>
+    where
+        T: AuditSink,
+    {
+        AuditMiddleware {
+            sink: Arc::new(sink),
+            actor: self.actor,
+            redacted: self.redacted,
+        }
+    }
+
+    /// Takes the actor of each request from `State` with `actor`, in place of the
+    /// `AuthenticatedUser`.
+    pub fn with_actor<F>(self, actor: F) -> Self
+    where
+        F: Fn(&State) -> Option<String> + Send + Sync + RefUnwindSafe + 'static,
+    {
+        AuditMiddleware {
+            actor: Arc::new(actor),
+            ..self
+        }
+    }
+
+    /// Replaces the values of the parameters with any of the given names, ignoring case.
+    pub fn with_redacted_fields(self, fields: Vec<&str>) -> Self {
+        let mut redacted = (*self.redacted).clone();
+        redacted.extend(fields.into_iter().map(str::to_lowercase));
+
+        AuditMiddleware {
+            redacted: Arc::new(redacted),
+            ..self
+        }
+    }
+
+    fn is_redacted(&self, name: &str) -> bool {
+        self.redacted.contains(&name.to_lowercase())
+    }
+
+    fn record(&self, state: &State, time: DateTime<Utc>, status: StatusCode) -> AuditRecord {
+        let uri = Uri::borrow_from(state);
+        let route = state.try_borrow::<RouteTemplate>();
+
+        let (mut params, path) = match route {
+            Some(route) => self.path_params(route, uri),
+            None => (Vec::new(), uri.path().to_owned()),
+        };
+        params.extend(query_params(uri));
+        for (name, value) in &mut params {
+            if self.is_redacted(name) {
+                *value = REDACTED.to_owned();
+            }
+        }
+
+        AuditRecord {
+            request_id: request_id(state).to_owned(),
+            time,
+            actor: (self.actor)(state),
+            method: Method::borrow_from(state).clone(),
+            path,
+            route: route.map(|route| route.as_str().to_owned()),
+            params,
+            status,
+            latency: Duration::default(),
+        }
+    }
+
+    // Pairs the dynamic segments of the route's template with the segments of the request path,
+    // where a glob takes the rest of the path. The path is given back with the segments of
+    // redacted parameters replaced, so that their values don't reach the sink through it.
+    fn path_params(&self, route: &RouteTemplate, uri: &Uri) -> (Vec<(String, String)>, String) {
+        let decode = |segment: &str| {
+            PercentDecoded::new(segment)
+                .map(|decoded| decoded.as_ref().to_owned())
+                .unwrap_or_else(|| segment.to_owned())
+        };
+
+        let mut segments = split_path_segments(uri.path());
+        let mut path = Vec::new();
+        let mut params = Vec::new();
+        let mut redacted = false;
+
+        for template in split_path_segments(route.as_str()) {
+            if template.starts_with('*') {
+                let rest: Vec<&str> = segments.by_ref().collect();
+                let value = rest.iter().map(|s| decode(s)).collect::<Vec<_>>().join("/");
+                if self.is_redacted(template) {
+                    path.push(REDACTED);
+                    redacted = true;
+                } else {
+                    path.extend(rest);
+                }
+                params.push((template.to_owned(), value));
+                break;
+            }
+
+            let segment = match segments.next() {
+                Some(segment) => segment,
+                None => break,
+            };
+
+            match template.strip_prefix(':') {
+                Some(name) => {
+                    let name = name.split(':').next().unwrap_or(name);
+                    if self.is_redacted(name) {
+                        path.push(REDACTED);
+                        redacted = true;
+                    } else {
+                        path.push(segment);
+                    }
+                    params.push((name.to_owned(), decode(segment)));
+                }
+                None => path.push(segment),
+            }
+        }
+
+        if !redacted {
+            return (params, uri.path().to_owned());
+        }
+
+        path.extend(segments);
+        let mut path = format!("/{}", path.join("/"));
+        if has_trailing_slash(uri.path()) {
+            path.push('/');
+        }
+        (params, path)
+    }
+}
+
+fn query_params(uri: &Uri) -> Vec<(String, String)> {
+    uri.query()
+        .into_iter()
+        .flat_map(|query| query.split(['&', ';']))
+        .filter_map(|pair| {
+            let mut parts = pair.splitn(2, '=');
+            let name = FormUrlDecoded::decode(parts.next()?, true)?;
+            let value = FormUrlDecoded::decode(parts.next().unwrap_or(""), true)?;
+            Some((name.as_ref().to_owned(), value.as_ref().to_owned()))
+        })
+        .filter(|(name, _)| !name.is_empty())
+        .collect()
+}
+
+impl<S> Clone for AuditMiddleware<S>
+where
+    S: AuditSink,
+{
+    fn clone(&self) -> Self {
+        AuditMiddleware {
+            sink: self.sink.clone(),
+            actor: self.actor.clone(),
+            redacted: self.redacted.clone(),
+        }
+    }
+}
+
+/// `Middleware` trait implementation.
+impl<S> Middleware for AuditMiddleware<S>
+where
+    S: AuditSink + 'static,
+{
+    /// Writes the record of the request to the sink once the response has been produced.
+    fn call<Chain>(self, state: State, chain: Chain) -> Box<HandlerFuture>
+    where
+        Chain: FnOnce(State) -> Box<HandlerFuture>,
+    {
+        let time = Utc::now();
+        let started = Instant::now();
+
+        let f = chain(state).then(move |result| {
+            let mut record = match result {
+                Ok((ref state, ref response)) => self.record(state, time, response.status()),
+                Err((ref state, ref err)) => self.record(state, time, err.status()),
+            };
+            record.latency = started.elapsed();
+
+            let request_id = record.request_id.clone();
+            self.sink.write(record).then(move |written| {
+                if let Err(e) = written {
+                    error!("[{}] failed to write audit record: {}", request_id, e);
+                }
+                result
+            })
+        });
+
+        Box::new(f)
+    }
+}
+
+/// `NewMiddleware` trait implementation.
+impl<S> NewMiddleware for AuditMiddleware<S>
+where
+    S: AuditSink + 'static,
+{
+    type Instance = Self;
+
+    /// Clones the current middleware to a new instance.
+    fn new_middleware(&self) -> io::Result<Self::Instance> {
+        Ok(self.clone())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    use crate::pipeline::new_pipeline;
+    use crate::pipeline::single::single_pipeline;
+    use crate::router::builder::*;
+    use crate::test::TestServer;
+
+    #[derive(Default)]
+    struct RecordingSink(Mutex<Vec<AuditRecord>>);
+
+    impl AuditSink for RecordingSink {
+        fn write(&self, record: AuditRecord) -> Box<AuditFuture> {
+            self.0.lock().unwrap().push(record);
+            Box::new(future::ok(()))
+        }
+    }
+
+    fn handler(mut state: State) -> (State, &'static str) {
+        state.put(AuthenticatedUser::new("alice"));
+        (state, "updated")
+    }
+
+    fn health(state: State) -> (State, &'static str) {
+        (state, "ok")
+    }
+
+    #[test]
+    fn writes_redacted_records() {
+        let sink = Arc::new(RecordingSink::default());
+        let middleware = AuditMiddleware::new()
+            .with_sink(sink.clone())
+            .with_redacted_fields(vec!["Token"]);
+
+        let (chain, pipelines) = single_pipeline(new_pipeline().add(middleware).build());
+        let test_server = TestServer::new(build_router(chain, pipelines, |route| {
+            route.get("/users/:id/*").to(handler);
+            route.get("/health").to(health);
+            route.get("/reset/:token/confirm").to(health);
+        }))
+        .unwrap();
+
+        test_server
+            .client()
+            .get("http://localhost/users/42/files/a.txt?token=secret&view=full")
+            .perform()
+            .unwrap();
+        test_server
+            .client()
+            .get("http://localhost/health")
+            .perform()
+            .unwrap();
+        test_server
+            .client()
+            .get("http://localhost/reset/s3cr3t/confirm")
+            .perform()
+            .unwrap();
+
+        let records = sink.0.lock().unwrap();
+        assert_eq!(records.len(), 3);
+
+        let record = &records[0];
+        assert_eq!(record.actor(), Some("alice"));
+        assert_eq!(record.method(), Method::GET);
+        assert_eq!(record.path(), "/users/42/files/a.txt");
+        assert_eq!(record.route(), Some("/users/:id/*"));
+        assert_eq!(
+            record.params(),
+            &[
+                ("id".to_owned(), "42".to_owned()),
+                ("*".to_owned(), "files/a.txt".to_owned()),
+                ("token".to_owned(), REDACTED.to_owned()),
+                ("view".to_owned(), "full".to_owned()),
+            ][..]
+        );
+        assert_eq!(record.status(), StatusCode::OK);
+
+        let record = &records[1];
+        assert_eq!(record.actor(), None);
+        assert_eq!(record.route(), Some("/health"));
+        assert!(record.params().is_empty());
+
+        // redacted path parameters are also replaced in the path
+        let record = &records[2];
+        assert_eq!(record.path(), "/reset/[redacted]/confirm");
+        assert_eq!(
+            record.params(),
+            &[("token".to_owned(), REDACTED.to_owned())][..]
+        );
+        assert!(!record.to_json().contains("s3cr3t"));
+    }
+
+    #[test]
+    fn appends_records_to_file() {
+        let path = std::env::temp_dir().join(format!("gotham-audit-{}.log", rand::random::<u64>()));
+        let middleware = AuditMiddleware::new().with_sink(FileSink::open(&path).unwrap());
+
+        let (chain, pipelines) = single_pipeline(new_pipeline().add(middleware).build());
+        let test_server = TestServer::new(build_router(chain, pipelines, |route| {
+            route.get("/health").to(health);
+        }))
+        .unwrap();
+
+        for _ in 0..2 {
+            test_server
+                .client()
+                .get("http://localhost/health")
+                .perform()
+                .unwrap();
+        }
+
+        let log = std::fs::read_to_string(&path).unwrap();
+        let _ = std::fs::remove_file(&path);
+        let lines: Vec<&str> = log.lines().collect();
+        assert_eq!(lines.len(), 2);
+        assert!(lines[0].contains("\"path\":\"/health\""));
+    }
+}
@@ -0,0 +1,322 @@
+//! HTTP Basic authentication, as described by RFC 7617.
+use std::io;
+use std::panic::RefUnwindSafe;
+use std::sync::Arc;
+
+use futures::{future, Future, IntoFuture};
+use hyper::header::{HeaderMap, HeaderValue, AUTHORIZATION, WWW_AUTHENTICATE};
+use hyper::StatusCode;
+use log::trace;
+
+use crate::handler::{HandlerError, HandlerFuture};
+use crate::helpers::http::response::create_empty_response;
+use crate::middleware::auth::AuthenticatedUser;
+use crate::middleware::{Middleware, NewMiddleware};
+use crate::state::{request_id, FromState, State};
+
+/// Middleware binding which requires requests to carry HTTP Basic credentials accepted by a
+/// validator, putting an `AuthenticatedUser` into `State` for the handler.
+///
+/// The validator is given the username and password of each request, and resolves to whether
+/// they're valid. Validators run asynchronously, so they can check credentials against a database
+/// or another service without blocking the server. A validator which fails with a `HandlerError`
+/// fails the request in the same way as a handler would.
+///
+/// Requests without credentials, with malformed credentials or with credentials the validator
+/// rejects are answered with `401 Unauthorized` and a `WWW-Authenticate` header naming the realm,
+/// without invoking the rest of the pipeline or the handler.
+///
+/// # Examples
+///
+/// ```rust
+/// # extern crate gotham;
+/// # extern crate hyper;
+/// #
+/// # use hyper::header::{HeaderValue, AUTHORIZATION};
+/// # use gotham::handler::HandlerError;
+/// # use gotham::middleware::auth::{AuthenticatedUser, BasicAuthMiddleware};
+/// # use gotham::pipeline::new_pipeline;
+/// # use gotham::pipeline::single::single_pipeline;
+/// # use gotham::router::builder::*;
+/// # use gotham::state::{FromState, State};
+/// # use gotham::test::TestServer;
+/// #
+/// fn handler(state: State) -> (State, String) {
+///     let greeting = format!("Hello, {}!", AuthenticatedUser::borrow_from(&state).username());
+///     (state, greeting)
+/// }
+///
+/// fn validate(username: &str, password: &str) -> Result<bool, HandlerError> {
+///     Ok(username == "alice" && password == "open sesame")
+/// }
+///
+/// # fn main() {
+/// let middleware = BasicAuthMiddleware::new(validate).with_realm("admin");
+///
+/// let (chain, pipelines) = single_pipeline(new_pipeline().add(middleware).build());
+/// let router = build_router(chain, pipelines, |route| {
+///     route.get("/").to(handler);
+/// });
+///
+/// let test_server = TestServer::new(router).unwrap();
+///
+/// let response = test_server
+///     .client()
+///     .get("http://localhost/")
+///     .perform()
+///     .unwrap();
+/// assert_eq!(response.status(), 401);
+/// assert_eq!(
+///     response.headers()["WWW-Authenticate"],
+///     "Basic realm=\"admin\", charset=\"UTF-8\""
+/// );
+///
+/// let response = test_server
+///     .client()
+///     .get("http://localhost/")
+///     .with_header(
+///         AUTHORIZATION,
+///         HeaderValue::from_static("Basic YWxpY2U6b3BlbiBzZXNhbWU="),
+///     )
+///     .perform()
+///     .unwrap();
+/// assert_eq!(response.status(), 200);
+/// assert_eq!(response.read_utf8_body().unwrap(), "Hello, alice!");
+/// # }
+/// ```
+pub struct BasicAuthMiddleware<V> {
+    validator: Arc<V>,
+    challenge: Arc<HeaderValue>,
+}
+
+impl<V, F> BasicAuthMiddleware<V>
+where
+    V: Fn(&str, &str) -> F + Send + Sync + RefUnwindSafe + 'static,
+    F: IntoFuture<Item = bool, Error = HandlerError>,
+    F::Future: Send + 'static,
+{
+    /// Creates a `BasicAuthMiddleware` which accepts the credentials `validator` resolves to
+    /// `true` for, in the realm "gotham".
+    pub fn new(validator: V) -> BasicAuthMiddleware<V> {
+        BasicAuthMiddleware {
+            validator: Arc::new(validator),
+            challenge: Arc::new(challenge("gotham")),
+        }
+    }
+
+    /// Changes the realm named in the `WWW-Authenticate` header, which browsers show when asking
+    /// for credentials.
+    ///
+    /// # Panics
+    ///
+    /// Panics where `realm` contains a double quote, a backslash or a character which isn't
+    /// allowed in a header value.
+    pub fn with_realm(self, realm: &str) -> BasicAuthMiddleware<V> {
+        BasicAuthMiddleware {
+            challenge: Arc::new(challenge(realm)),
+            ..self
+        }
+    }
+}
+
+impl<V> Clone for BasicAuthMiddleware<V> {
+    fn clone(&self) -> Self {
+        BasicAuthMiddleware {
+            validator: self.validator.clone(),
+            challenge: self.challenge.clone(),
+        }
+    }
+}
+
+fn challenge(realm: &str) -> HeaderValue {
+    assert!(
+        !realm.contains(['"', '\\']),
+        "realm can't contain quotes or backslashes"
+    );
+
+    let value = format!("Basic realm=\"{}\", charset=\"UTF-8\"", realm);
+    HeaderValue::from_str(&value).expect("realm isn't a valid header value")
+}
+
+// Parses the username and password from an `Authorization` header using the Basic scheme.
+fn credentials(headers: &HeaderMap) -> Option<(String, String)> {
+    let value = headers.get(AUTHORIZATION)?.to_str().ok()?;
+
+    let mut parts = value.splitn(2, ' ');
+    let scheme = parts.next()?;
+    if !scheme.eq_ignore_ascii_case("basic") {
+        return None;
+    }
+
+    let decoded = base64::decode(parts.next()?.trim()).ok()?;
+    let decoded = String::from_utf8(decoded).ok()?;
+
+    let mut parts = decoded.splitn(2, ':');
+    let username = parts.next()?.to_owned();
+    let password = parts.next()?.to_owned();
+    Some((username, password))
+}
+
+fn unauthorized(state: State, challenge: &HeaderValue) -> Box<HandlerFuture> {
+    let mut res = create_empty_response(&state, StatusCode::UNAUTHORIZED);
+    res.headers_mut()
+        .insert(WWW_AUTHENTICATE, challenge.clone());
+    Box::new(future::ok((state, res)))
+}
+
+/// `Middleware` trait implementation.
+impl<V, F> Middleware for BasicAuthMiddleware<V>
+where
+    V: Fn(&str, &str) -> F + Send + Sync + RefUnwindSafe + 'static,
+    F: IntoFuture<Item = bool, Error = HandlerError>,
+    F::Future: Send + 'static,
+{
+    /// Validates the credentials of the request, and only passes it on once they're accepted.
+    fn call<Chain>(self, mut state: State, chain: Chain) -> Box<HandlerFuture>
+    where
+        Chain: FnOnce(State) -> Box<HandlerFuture> + Send + 'static,
+    {
+        let (username, password) = match credentials(HeaderMap::borrow_from(&state)) {
+            Some(credentials) => credentials,
+            None => {
+                trace!("[{}] missing basic credentials", request_id(&state));
+                return unauthorized(state, &self.challenge);
+            }
+        };
+
+        let challenge = self.challenge;
+        let f =
+            (self.validator)(&username, &password)
+                .into_future()
+                .then(move |result| match result {
+                    Ok(true) => {
+                        trace!("[{}] authenticated {}", request_id(&state), username);
+                        state.put(AuthenticatedUser::new(username));
+                        chain(state)
+                    }
+                    Ok(false) => {
+                        trace!("[{}] rejected basic credentials", request_id(&state));
+                        unauthorized(state, &challenge)
+                    }
+                    Err(e) => Box::new(future::err((state, e))),
+                });
+
+        Box::new(f)
+    }
+}
+
+/// `NewMiddleware` trait implementation.
+impl<V, F> NewMiddleware for BasicAuthMiddleware<V>
+where
+    V: Fn(&str, &str) -> F + Send + Sync + RefUnwindSafe + 'static,
+    F: IntoFuture<Item = bool, Error = HandlerError>,
+    F::Future: Send + 'static,
+{
+    type Instance = Self;
+
+    /// Clones the current middleware to a new instance.
+    fn new_middleware(&self) -> io::Result<Self::Instance> {
+        Ok(self.clone())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    use futures::future::FutureResult;
+
+    use crate::handler::IntoHandlerError;
+    use crate::pipeline::new_pipeline;
+    use crate::pipeline::single::single_pipeline;
+    use crate::router::builder::*;
+    use crate::test::TestServer;
+
+    fn handler(state: State) -> (State, String) {
+        let username = AuthenticatedUser::borrow_from(&state).username().to_owned();
+        (state, username)
+    }
+
+    fn validate(username: &str, password: &str) -> FutureResult<bool, HandlerError> {
+        if username == "error" {
+            let e = io::Error::other("validator failed");
+            return future::err(e.into_handler_error());
+        }
+        future::ok(password == "secret")
+    }
+
+    fn test_server() -> TestServer {
+        let middleware = BasicAuthMiddleware::new(validate);
+        let (chain, pipelines) = single_pipeline(new_pipeline().add(middleware).build());
+        TestServer::new(build_router(chain, pipelines, |route| {
+            route.get("/").to(handler);
+        }))
+        .unwrap()
+    }
+
+    fn get(test_server: &TestServer, authorization: &str) -> (StatusCode, HeaderMap, String) {
+        let client = test_server.client();
+        let mut req = client.get("http://localhost/");
+        if !authorization.is_empty() {
+            req = req.with_header(AUTHORIZATION, HeaderValue::from_str(authorization).unwrap());
+        }
+
+        let res = req.perform().unwrap();
+        let status = res.status();
+        let headers = res.headers().clone();
+        (status, headers, res.read_utf8_body().unwrap())
+    }
+
+    fn basic(credentials: &str) -> String {
+        format!("Basic {}", base64::encode(credentials))
+    }
+
+    #[test]
+    fn accepts_valid_credentials() {
+        let test_server = test_server();
+
+        let (status, _, body) = get(&test_server, &basic("alice:secret"));
+        assert_eq!(status, StatusCode::OK);
+        assert_eq!(body, "alice");
+
+        // the scheme is case insensitive
+        let (status, _, body) = get(&test_server, &basic("bob:secret").replace("Basic", "basic"));
+        assert_eq!(status, StatusCode::OK);
+        assert_eq!(body, "bob");
+    }
+
+    #[test]
+    fn rejects_missing_or_invalid_credentials() {
+        let test_server = test_server();
+
+        for authorization in &[
+            String::new(),
+            basic("alice:wrong"),
+            basic("alice"),
+            "Basic not base64".to_owned(),
+            "Bearer c2VjcmV0".to_owned(),
+        ] {
+            let (status, headers, _) = get(&test_server, authorization);
+            assert_eq!(status, StatusCode::UNAUTHORIZED);
+            assert_eq!(
+                headers[WWW_AUTHENTICATE],
+                "Basic realm=\"gotham\", charset=\"UTF-8\""
+            );
+        }
+    }
+
+    #[test]
+    fn fails_when_the_validator_fails() {
+        let test_server = test_server();
+
+        let (status, headers, _) = get(&test_server, &basic("error:secret"));
+        assert_eq!(status, StatusCode::INTERNAL_SERVER_ERROR);
+        assert!(headers.get(WWW_AUTHENTICATE).is_none());
+    }
+
+    #[test]
+    #[should_panic(expected = "realm can't contain quotes or backslashes")]
+    fn rejects_quoted_realms() {
+        BasicAuthMiddleware::new(validate).with_realm("\"admin\"");
+    }
+}
@@ -0,0 +1,36 @@
+//! Authentication middleware, which identifies the user making a request before it reaches the
+//! handler.
+use crate::state::StateData;
+
+mod basic;
+
+pub use self::basic::BasicAuthMiddleware;
+
+/// The user who made the current request, put into `State` by authentication middleware once the
+/// request has been authenticated.
+///
+/// Handlers behind authentication middleware can borrow the `AuthenticatedUser` from `State`
+/// without checking whether it's present, as unauthenticated requests never reach them.
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct AuthenticatedUser {
+    username: String,
+}
+
+impl AuthenticatedUser {
+    /// Creates an `AuthenticatedUser` for the user called `username`.
+    pub fn new<S>(username: S) -> AuthenticatedUser
+    where
+        S: Into<String>,
+    {
+        AuthenticatedUser {
+            username: username.into(),
+        }
+    }
+
+    /// The name of the user.
+    pub fn username(&self) -> &str {
+        &self.username
+    }
+}
+
+impl StateData for AuthenticatedUser {}
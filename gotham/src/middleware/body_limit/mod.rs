@@ -0,0 +1,173 @@
+//! Body limit middleware, which protects applications from request bodies too large to handle.
+use std::io;
+
+use futures::future;
+
+use crate::handler::HandlerFuture;
+use crate::middleware::{Middleware, NewMiddleware};
+use crate::router::limit::BodyLimit;
+use crate::state::State;
+
+/// Middleware binding which responds with `413 Payload Too Large` to requests whose body is
+/// larger than a limit.
+///
+/// Requests with a `Content-Length` over the limit are refused without invoking the rest of the
+/// pipeline chain or the handler. The length of a chunked body is only known as it's read, so the
+/// body is counted as it's read instead, failing once the limit is exceeded, and the error from a
+/// handler which then fails is given the `413 Payload Too Large` status.
+///
+/// This applies the same limit as `DefineSingleRoute::with_body_limit` and
+/// `ScopeBuilder::body_limit`, for applications which configure their limits in pipelines.
+///
+/// # Examples
+///
+/// ```rust
+/// # extern crate gotham;
+/// # extern crate mime;
+/// #
+/// # use gotham::middleware::body_limit::BodyLimitMiddleware;
+/// # use gotham::pipeline::new_pipeline;
+/// # use gotham::pipeline::single::single_pipeline;
+/// # use gotham::router::builder::*;
+/// # use gotham::state::State;
+/// # use gotham::test::TestServer;
+/// #
+/// # fn handler(state: State) -> (State, &'static str) {
+/// #     (state, "hello")
+/// # }
+/// #
+/// # fn main() {
+/// let (chain, pipelines) =
+///     single_pipeline(new_pipeline().add(BodyLimitMiddleware::new(1024)).build());
+/// let router = build_router(chain, pipelines, |route| {
+///     route.post("/").to(handler);
+/// });
+///
+/// let test_server = TestServer::new(router).unwrap();
+/// let response = test_server
+///     .client()
+///     .post("http://localhost/", vec![0; 2048], mime::APPLICATION_OCTET_STREAM)
+///     .perform()
+///     .unwrap();
+///
+/// assert_eq!(response.status(), 413);
+/// # }
+/// ```
+#[derive(Clone, Copy, Debug)]
+pub struct BodyLimitMiddleware {
+    limit: u64,
+}
+
+impl BodyLimitMiddleware {
+    /// Creates a `BodyLimitMiddleware` which allows request bodies of up to `limit` bytes.
+    pub fn new(limit: u64) -> BodyLimitMiddleware {
+        BodyLimitMiddleware { limit }
+    }
+}
+
+/// `Middleware` trait implementation.
+impl Middleware for BodyLimitMiddleware {
+    /// Refuses requests which declare a body over the limit, and limits the body of the others.
+    fn call<Chain>(self, mut state: State, chain: Chain) -> Box<HandlerFuture>
+    where
+        Chain: FnOnce(State) -> Box<HandlerFuture>,
+    {
+        let limit = BodyLimit::new(self.limit);
+        if let Some(res) = limit.apply(&mut state) {
+            return Box::new(future::ok((state, res)));
+        }
+
+        limit.finish(chain(state))
+    }
+}
+
+/// `NewMiddleware` trait implementation.
+impl NewMiddleware for BodyLimitMiddleware {
+    type Instance = Self;
+
+    /// Clones the current middleware to a new instance.
+    fn new_middleware(&self) -> io::Result<Self::Instance> {
+        Ok(*self)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    use futures::{stream, Future, Stream};
+    use hyper::{Body, StatusCode};
+
+    use crate::handler::IntoHandlerError;
+    use crate::helpers::http::response::create_response;
+    use crate::pipeline::new_pipeline;
+    use crate::pipeline::single::single_pipeline;
+    use crate::router::builder::*;
+    use crate::state::FromState;
+    use crate::test::TestServer;
+
+    fn handler(mut state: State) -> Box<HandlerFuture> {
+        let f = Body::take_from(&mut state)
+            .concat2()
+            .then(move |result| match result {
+                Ok(body) => {
+                    let len = body.len().to_string();
+                    let res = create_response(&state, StatusCode::OK, mime::TEXT_PLAIN, len);
+                    Ok((state, res))
+                }
+                Err(e) => Err((state, e.into_handler_error())),
+            });
+
+        Box::new(f)
+    }
+
+    fn test_server() -> TestServer {
+        let middleware = BodyLimitMiddleware::new(10);
+        let (chain, pipelines) = single_pipeline(new_pipeline().add(middleware).build());
+        TestServer::new(build_router(chain, pipelines, |route| {
+            route.post("/").to(handler);
+        }))
+        .unwrap()
+    }
+
+    fn post<B>(test_server: &TestServer, body: B) -> (StatusCode, String)
+    where
+        B: Into<Body>,
+    {
+        let response = test_server
+            .client()
+            .post("http://localhost/", body, mime::APPLICATION_OCTET_STREAM)
+            .perform()
+            .unwrap();
+        let status = response.status();
+        (status, response.read_utf8_body().unwrap())
+    }
+
+    fn chunked(chunks: Vec<&'static str>) -> Body {
+        Body::wrap_stream(stream::iter_ok::<_, io::Error>(chunks))
+    }
+
+    #[test]
+    fn limits_bodies_with_a_content_length() {
+        let test_server = test_server();
+
+        assert_eq!(
+            post(&test_server, "0123456789"),
+            (StatusCode::OK, "10".to_owned())
+        );
+
+        let (status, _) = post(&test_server, "0123456789a");
+        assert_eq!(status, StatusCode::PAYLOAD_TOO_LARGE);
+    }
+
+    #[test]
+    fn limits_chunked_bodies() {
+        let test_server = test_server();
+
+        let (status, body) = post(&test_server, chunked(vec!["01234", "56789"]));
+        assert_eq!((status, body.as_str()), (StatusCode::OK, "10"));
+
+        let (status, _) = post(&test_server, chunked(vec!["01234", "56789", "a"]));
+        assert_eq!(status, StatusCode::PAYLOAD_TOO_LARGE);
+    }
+}
@@ -2,6 +2,7 @@
 
 use log::trace;
 
+use std::any::type_name;
 use std::io;
 use std::panic::RefUnwindSafe;
 
@@ -19,6 +20,9 @@ pub unsafe trait NewMiddlewareChain: RefUnwindSafe + Sized {
 
     /// Create and return a new `MiddlewareChain` value.
     fn construct(&self) -> io::Result<Self::Instance>;
+
+    /// Appends the type names of the `NewMiddleware` values, in the order they were added.
+    fn middleware_names(names: &mut Vec<&'static str>);
 }
 
 unsafe impl<T, U> NewMiddlewareChain for (T, U)
@@ -38,6 +42,12 @@ where
         let (ref nm, ref tail) = *self;
         Ok((nm.new_middleware()?, tail.construct()?))
     }
+
+    fn middleware_names(names: &mut Vec<&'static str>) {
+        // The list is reversed, so the tail was added first.
+        U::middleware_names(names);
+        names.push(type_name::<T>());
+    }
 }
 
 unsafe impl NewMiddlewareChain for () {
@@ -48,6 +58,8 @@ unsafe impl NewMiddlewareChain for () {
         trace!(" completed middleware pipeline construction");
         Ok(())
     }
+
+    fn middleware_names(_names: &mut Vec<&'static str>) {}
 }
 
 /// A recursive type representing an instance of a pipeline, which is used to process a single
@@ -0,0 +1,458 @@
+//! Circuit breaker middleware, which stops sending requests to a failing downstream dependency
+//! until it has had time to recover.
+use std::io;
+use std::sync::atomic::{AtomicBool, Ordering};
+use std::sync::{Arc, Mutex, MutexGuard, PoisonError};
+use std::time::{Duration, Instant};
+
+use futures::{future, Future};
+use hyper::header::{HeaderValue, RETRY_AFTER};
+use hyper::StatusCode;
+use log::{debug, trace};
+
+use crate::handler::HandlerFuture;
+use crate::helpers::http::response::create_empty_response;
+use crate::middleware::rate_limit::retry_after_secs;
+use crate::middleware::{Middleware, NewMiddleware};
+use crate::state::{request_id, State, StateData};
+
+/// The state of a `CircuitBreaker`.
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub enum CircuitState {
+    /// Requests are served, and failures are counted.
+    Closed,
+    /// Requests are refused, until the breaker has been open for its open duration.
+    Open,
+    /// A single request is served as a probe of the dependency, which closes the breaker where it
+    /// succeeds and opens it again where it fails.
+    HalfOpen,
+}
+
+#[derive(Debug)]
+enum Circuit {
+    Closed { failures: u32 },
+    Open { until: Instant },
+    HalfOpen { probing: bool },
+}
+
+impl Circuit {
+    // Moves an open circuit to half open once it has been open for long enough.
+    fn refresh(&mut self, now: Instant) {
+        if let Circuit::Open { until } = *self {
+            if now >= until {
+                *self = Circuit::HalfOpen { probing: false };
+            }
+        }
+    }
+}
+
+// The decision made for a request.
+enum Admission {
+    Allowed { probe: bool },
+    Refused { retry_after: Option<Duration> },
+}
+
+/// A handle to the state of a circuit breaker, which is shared by its clones.
+///
+/// The breaker is closed initially. Once `failure_threshold` consecutive failures have been
+/// recorded, it opens for `open_duration`, and then lets a single request through to probe
+/// whether the dependency has recovered. Clones of the breaker share its state, so a clone may be
+/// kept by a health check to report the state of the dependency.
+#[derive(Clone, Debug)]
+pub struct CircuitBreaker {
+    circuit: Arc<Mutex<Circuit>>,
+    failure_threshold: u32,
+    open_duration: Duration,
+}
+
+impl CircuitBreaker {
+    /// Creates a closed `CircuitBreaker`, which opens for 30 seconds after 5 consecutive
+    /// failures.
+    pub fn new() -> CircuitBreaker {
+        CircuitBreaker {
+            circuit: Arc::new(Mutex::new(Circuit::Closed { failures: 0 })),
+            failure_threshold: 5,
+            open_duration: Duration::from_secs(30),
+        }
+    }
+
+    /// Changes the number of consecutive failures which open the breaker.
+    ///
+    /// # Panics
+    ///
+    /// Panics where `failures` is zero.
+    pub fn with_failure_threshold(self, failures: u32) -> CircuitBreaker {
+        assert!(
+            failures > 0,
+            "a circuit breaker must allow at least one failure"
+        );
+        CircuitBreaker {
+            failure_threshold: failures,
+            ..self
+        }
+    }
+
+    /// Changes the time for which the breaker stays open before a request is let through as a
+    /// probe.
+    pub fn with_open_duration(self, open_duration: Duration) -> CircuitBreaker {
+        CircuitBreaker {
+            open_duration,
+            ..self
+        }
+    }
+
+    /// The current state of the breaker.
+    pub fn state(&self) -> CircuitState {
+        let mut circuit = self.circuit();
+        circuit.refresh(Instant::now());
+
+        match *circuit {
+            Circuit::Closed { .. } => CircuitState::Closed,
+            Circuit::Open { .. } => CircuitState::Open,
+            Circuit::HalfOpen { .. } => CircuitState::HalfOpen,
+        }
+    }
+
+    fn circuit(&self) -> MutexGuard<'_, Circuit> {
+        self.circuit.lock().unwrap_or_else(PoisonError::into_inner)
+    }
+
+    fn admit(&self) -> Admission {
+        let now = Instant::now();
+        let mut circuit = self.circuit();
+        circuit.refresh(now);
+
+        match *circuit {
+            Circuit::Closed { .. } => Admission::Allowed { probe: false },
+            Circuit::Open { until } => Admission::Refused {
+                retry_after: Some(until - now),
+            },
+            Circuit::HalfOpen { ref mut probing } if !*probing => {
+                *probing = true;
+                Admission::Allowed { probe: true }
+            }
+            Circuit::HalfOpen { .. } => Admission::Refused { retry_after: None },
+        }
+    }
+
+    fn record(&self, success: bool, probe: bool) {
+        let mut circuit = self.circuit();
+
+        let next = match (&*circuit, success) {
+            (Circuit::Closed { .. }, true) => Circuit::Closed { failures: 0 },
+            (&Circuit::Closed { failures }, false) if failures + 1 < self.failure_threshold => {
+                Circuit::Closed {
+                    failures: failures + 1,
+                }
+            }
+            (Circuit::Closed { .. }, false) => self.opened(),
+            (Circuit::HalfOpen { .. }, true) if probe => Circuit::Closed { failures: 0 },
+            (Circuit::HalfOpen { .. }, false) if probe => self.opened(),
+            // outcomes of requests allowed before the breaker opened don't change it
+            _ => return,
+        };
+
+        *circuit = next;
+    }
+
+    fn opened(&self) -> Circuit {
+        debug!(" circuit breaker opened for {:?}", self.open_duration);
+        Circuit::Open {
+            until: Instant::now() + self.open_duration,
+        }
+    }
+
+    // Allows another probe where a probe finished without recording its outcome.
+    fn release_probe(&self) {
+        if let Circuit::HalfOpen { ref mut probing } = *self.circuit() {
+            *probing = false;
+        }
+    }
+}
+
+impl Default for CircuitBreaker {
+    fn default() -> CircuitBreaker {
+        CircuitBreaker::new()
+    }
+}
+
+/// Records the outcome of the requests a handler makes to the dependency guarded by a
+/// `CircuitBreakerMiddleware`, which puts a recorder in `State` for each request it serves.
+///
+/// Each failure, such as an error or a timeout of a request to the dependency, counts towards
+/// opening the breaker, and a success resets the count. Requests which record nothing don't
+/// change the breaker.
+pub struct CircuitRecorder {
+    breaker: CircuitBreaker,
+    probe: bool,
+    recorded: Arc<AtomicBool>,
+}
+
+impl CircuitRecorder {
+    /// Records a successful request to the dependency.
+    pub fn record_success(&self) {
+        self.recorded.store(true, Ordering::SeqCst);
+        self.breaker.record(true, self.probe);
+    }
+
+    /// Records a failed request to the dependency.
+    pub fn record_failure(&self) {
+        self.recorded.store(true, Ordering::SeqCst);
+        self.breaker.record(false, self.probe);
+    }
+}
+
+impl StateData for CircuitRecorder {}
+
+// Holds the probe slot of a half open breaker for the duration of the probe, and releases it
+// unless the probe recorded an outcome, including where the future of the request is dropped
+// before it completes, such as when the client disconnects or the request times out.
+struct ProbeGuard {
+    breaker: CircuitBreaker,
+    recorded: Arc<AtomicBool>,
+}
+
+impl Drop for ProbeGuard {
+    fn drop(&mut self) {
+        if !self.recorded.load(Ordering::SeqCst) {
+            self.breaker.release_probe();
+        }
+    }
+}
+
+/// Middleware binding which responds with `503 Service Unavailable` to requests while its
+/// `CircuitBreaker` is open, without invoking the rest of the pipeline chain or the handler.
+///
+/// Requests which are served have a `CircuitRecorder` in `State`, which the handler uses to record
+/// the outcome of its requests to the dependency. Errors of the handler aren't recorded as
+/// failures, as not all of them are caused by the dependency. Responses to refused requests have
+/// a `Retry-After` header while the breaker is open, and none while a probe is in progress.
+///
+/// # Examples
+///
+/// ```rust
+/// # extern crate gotham;
+/// # extern crate hyper;
+/// #
+/// # use std::time::Duration;
+/// # use hyper::StatusCode;
+/// # use gotham::middleware::circuit_breaker::*;
+/// # use gotham::pipeline::new_pipeline;
+/// # use gotham::pipeline::single::single_pipeline;
+/// # use gotham::router::builder::*;
+/// # use gotham::state::{FromState, State};
+/// # use gotham::test::TestServer;
+/// #
+/// fn handler(state: State) -> (State, &'static str) {
+///     // the request to the upstream service timed out
+///     CircuitRecorder::borrow_from(&state).record_failure();
+///     (state, "upstream unavailable")
+/// }
+///
+/// # fn main() {
+/// let breaker = CircuitBreaker::new()
+///     .with_failure_threshold(2)
+///     .with_open_duration(Duration::from_secs(10));
+/// let middleware = CircuitBreakerMiddleware::new(breaker.clone());
+///
+/// let (chain, pipelines) = single_pipeline(new_pipeline().add(middleware).build());
+/// let router = build_router(chain, pipelines, |route| {
+///     route.get("/").to(handler);
+/// });
+///
+/// let test_server = TestServer::new(router).unwrap();
+/// let client = test_server.client();
+/// for _ in 0..2 {
+///     let response = client.get("http://localhost/").perform().unwrap();
+///     assert_eq!(response.status(), StatusCode::OK);
+/// }
+///
+/// assert_eq!(breaker.state(), CircuitState::Open);
+/// let response = client.get("http://localhost/").perform().unwrap();
+/// assert_eq!(response.status(), StatusCode::SERVICE_UNAVAILABLE);
+/// assert_eq!(response.headers()["Retry-After"], "10");
+/// # }
+/// ```
+#[derive(Clone, Debug)]
+pub struct CircuitBreakerMiddleware {
+    breaker: CircuitBreaker,
+}
+
+impl CircuitBreakerMiddleware {
+    /// Creates a `CircuitBreakerMiddleware` which refuses requests while `breaker` is open.
+    pub fn new(breaker: CircuitBreaker) -> CircuitBreakerMiddleware {
+        CircuitBreakerMiddleware { breaker }
+    }
+}
+
+/// `Middleware` trait implementation.
+impl Middleware for CircuitBreakerMiddleware {
+    /// Refuses requests while the breaker is open, and gives the handler a `CircuitRecorder`.
+    fn call<Chain>(self, mut state: State, chain: Chain) -> Box<HandlerFuture>
+    where
+        Chain: FnOnce(State) -> Box<HandlerFuture>,
+    {
+        let probe = match self.breaker.admit() {
+            Admission::Allowed { probe } => probe,
+            Admission::Refused { retry_after } => {
+                trace!("[{}] refusing request, circuit is open", request_id(&state));
+                let mut res = create_empty_response(&state, StatusCode::SERVICE_UNAVAILABLE);
+                if let Some(retry_after) = retry_after {
+                    res.headers_mut().insert(
+                        RETRY_AFTER,
+                        HeaderValue::from(retry_after_secs(retry_after)),
+                    );
+                }
+                return Box::new(future::ok((state, res)));
+            }
+        };
+
+        let recorded = Arc::new(AtomicBool::new(false));
+        let guard = if probe {
+            trace!("[{}] probing half open circuit", request_id(&state));
+            Some(ProbeGuard {
+                breaker: self.breaker.clone(),
+                recorded: recorded.clone(),
+            })
+        } else {
+            None
+        };
+
+        state.put(CircuitRecorder {
+            breaker: self.breaker,
+            probe,
+            recorded,
+        });
+
+        let f = chain(state).then(move |result| {
+            drop(guard);
+            result
+        });
+
+        Box::new(f)
+    }
+}
+
+/// `NewMiddleware` trait implementation.
+impl NewMiddleware for CircuitBreakerMiddleware {
+    type Instance = Self;
+
+    /// Clones the current middleware to a new instance.
+    fn new_middleware(&self) -> io::Result<Self::Instance> {
+        Ok(self.clone())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    use std::thread;
+
+    use hyper::Uri;
+
+    use crate::pipeline::new_pipeline;
+    use crate::pipeline::single::single_pipeline;
+    use crate::router::builder::*;
+    use crate::state::FromState;
+    use crate::test::TestServer;
+
+    fn handler(state: State) -> (State, &'static str) {
+        {
+            let recorder = CircuitRecorder::borrow_from(&state);
+            match Uri::borrow_from(&state).path() {
+                "/fail" => recorder.record_failure(),
+                "/ok" => recorder.record_success(),
+                _ => (),
+            }
+        }
+        (state, "done")
+    }
+
+    #[test]
+    fn opens_and_probes_circuit() {
+        let breaker = CircuitBreaker::new()
+            .with_failure_threshold(2)
+            .with_open_duration(Duration::from_millis(100));
+        let middleware = CircuitBreakerMiddleware::new(breaker.clone());
+        let (chain, pipelines) = single_pipeline(new_pipeline().add(middleware).build());
+        let test_server = TestServer::new(build_router(chain, pipelines, |route| {
+            route.get("/*").to(handler);
+        }))
+        .unwrap();
+        let client = test_server.client();
+        let status = |path: &str| {
+            let uri = format!("http://localhost{}", path);
+            client.get(uri).perform().unwrap().status()
+        };
+
+        // a success resets the count of consecutive failures
+        assert_eq!(status("/fail"), StatusCode::OK);
+        assert_eq!(status("/ok"), StatusCode::OK);
+        assert_eq!(status("/fail"), StatusCode::OK);
+        assert_eq!(breaker.state(), CircuitState::Closed);
+
+        assert_eq!(status("/fail"), StatusCode::OK);
+        assert_eq!(breaker.state(), CircuitState::Open);
+        assert_eq!(status("/ok"), StatusCode::SERVICE_UNAVAILABLE);
+
+        // a probe which records nothing lets another probe through, and a failed probe opens the
+        // breaker again
+        thread::sleep(Duration::from_millis(150));
+        assert_eq!(breaker.state(), CircuitState::HalfOpen);
+        assert_eq!(status("/other"), StatusCode::OK);
+        assert_eq!(status("/fail"), StatusCode::OK);
+        assert_eq!(breaker.state(), CircuitState::Open);
+
+        thread::sleep(Duration::from_millis(150));
+        assert_eq!(status("/ok"), StatusCode::OK);
+        assert_eq!(breaker.state(), CircuitState::Closed);
+    }
+
+    #[test]
+    fn refuses_requests_during_probe() {
+        let breaker = CircuitBreaker::new()
+            .with_failure_threshold(1)
+            .with_open_duration(Duration::from_millis(0));
+        breaker.record(false, false);
+
+        match breaker.admit() {
+            Admission::Allowed { probe } => assert!(probe),
+            Admission::Refused { .. } => panic!("probe should be allowed"),
+        }
+        match breaker.admit() {
+            Admission::Refused { retry_after } => assert_eq!(retry_after, None),
+            Admission::Allowed { .. } => panic!("only one probe should be allowed"),
+        }
+
+        // outcomes of requests which aren't the probe are ignored while half open
+        breaker.record(true, false);
+        assert_eq!(breaker.state(), CircuitState::HalfOpen);
+        breaker.record(true, true);
+        assert_eq!(breaker.state(), CircuitState::Closed);
+    }
+
+    #[test]
+    fn releases_probe_of_dropped_request() {
+        let breaker = CircuitBreaker::new()
+            .with_failure_threshold(1)
+            .with_open_duration(Duration::from_millis(0));
+        breaker.record(false, false);
+        let middleware = CircuitBreakerMiddleware::new(breaker.clone());
+
+        // the probe never completes, as when the client disconnects
+        let f = middleware
+            .clone()
+            .call(State::new(), |_| Box::new(future::empty()));
+        match breaker.admit() {
+            Admission::Refused { retry_after } => assert_eq!(retry_after, None),
+            Admission::Allowed { .. } => panic!("the probe should be in progress"),
+        }
+        drop(f);
+
+        match breaker.admit() {
+            Admission::Allowed { probe } => assert!(probe),
+            Admission::Refused { .. } => panic!("the dropped probe should have been released"),
+        }
+    }
+}
@@ -0,0 +1,236 @@
+//! Conditional middleware, which applies other middleware to only some requests.
+use std::io;
+use std::panic::RefUnwindSafe;
+use std::sync::Arc;
+
+use hyper::header::{HeaderMap, HeaderName};
+use hyper::{Method, Uri};
+use log::trace;
+
+use crate::handler::HandlerFuture;
+use crate::middleware::{Middleware, NewMiddleware};
+use crate::state::{request_id, FromState, State};
+
+type Predicate = dyn Fn(&State) -> bool + Send + Sync + RefUnwindSafe;
+
+/// Middleware binding which only applies the wrapped middleware to requests for which a predicate
+/// holds, passing other requests straight on to the rest of the pipeline chain.
+///
+/// This allows a single pipeline to treat some requests differently, where building a pipeline
+/// for each variation would otherwise repeat most of the middleware. The `path_prefix`,
+/// `has_header` and `method` functions provide common predicates, and any closure over `&State`
+/// can be used.
+///
+/// # Examples
+///
+/// ```rust
+/// # extern crate gotham;
+/// #
+/// # use gotham::middleware::conditional::{path_prefix, ConditionalMiddleware};
+/// # use gotham::middleware::security::SecurityMiddleware;
+/// # use gotham::pipeline::new_pipeline;
+/// # use gotham::pipeline::single::single_pipeline;
+/// # use gotham::router::builder::*;
+/// # use gotham::state::State;
+/// # use gotham::test::TestServer;
+/// #
+/// # fn handler(state: State) -> (State, &'static str) {
+/// #     (state, "hello")
+/// # }
+/// #
+/// # fn main() {
+/// let middleware = ConditionalMiddleware::new(SecurityMiddleware, path_prefix("/admin"));
+///
+/// let (chain, pipelines) = single_pipeline(new_pipeline().add(middleware).build());
+/// let router = build_router(chain, pipelines, |route| {
+///     route.get("/").to(handler);
+///     route.get("/admin").to(handler);
+/// });
+///
+/// let test_server = TestServer::new(router).unwrap();
+/// let client = test_server.client();
+///
+/// let response = client.get("http://localhost/").perform().unwrap();
+/// assert!(response.headers().get("x-frame-options").is_none());
+///
+/// let response = client.get("http://localhost/admin").perform().unwrap();
+/// assert_eq!(response.headers()["x-frame-options"], "DENY");
+/// # }
+/// ```
+pub struct ConditionalMiddleware<M> {
+    middleware: M,
+    predicate: Arc<Predicate>,
+}
+
+impl<M> ConditionalMiddleware<M>
+where
+    M: NewMiddleware,
+{
+    /// Creates a `ConditionalMiddleware` which applies `middleware` to the requests for which
+    /// `predicate` returns `true`.
+    pub fn new<P>(middleware: M, predicate: P) -> ConditionalMiddleware<M>
+    where
+        P: Fn(&State) -> bool + Send + Sync + RefUnwindSafe + 'static,
+    {
+        ConditionalMiddleware {
+            middleware,
+            predicate: Arc::new(predicate),
+        }
+    }
+}
+
+/// Creates a predicate which holds for requests whose path is `prefix`, or starts with `prefix`
+/// followed by `/`.
+pub fn path_prefix(prefix: &str) -> impl Fn(&State) -> bool + Send + Sync + RefUnwindSafe {
+    let prefix = prefix.trim_end_matches('/').to_owned();
+
+    move |state| {
+        let path = Uri::borrow_from(state).path();
+        path.starts_with(&prefix)
+            && (path.len() == prefix.len() || path[prefix.len()..].starts_with('/'))
+    }
+}
+
+/// Creates a predicate which holds for requests which have the header `name`.
+pub fn has_header(name: HeaderName) -> impl Fn(&State) -> bool + Send + Sync + RefUnwindSafe {
+    move |state| HeaderMap::borrow_from(state).contains_key(&name)
+}
+
+/// Creates a predicate which holds for requests which use one of `methods`.
+pub fn method(methods: Vec<Method>) -> impl Fn(&State) -> bool + Send + Sync + RefUnwindSafe {
+    move |state| methods.contains(Method::borrow_from(state))
+}
+
+/// An instance of a `ConditionalMiddleware`, serving a single request.
+pub struct ConditionalMiddlewareInstance<M> {
+    middleware: M,
+    predicate: Arc<Predicate>,
+}
+
+/// `Middleware` trait implementation.
+impl<M> Middleware for ConditionalMiddlewareInstance<M>
+where
+    M: Middleware,
+{
+    /// Applies the wrapped middleware where the predicate holds, and skips it otherwise.
+    fn call<Chain>(self, state: State, chain: Chain) -> Box<HandlerFuture>
+    where
+        Chain: FnOnce(State) -> Box<HandlerFuture> + Send + 'static,
+    {
+        if (self.predicate)(&state) {
+            self.middleware.call(state, chain)
+        } else {
+            trace!("[{}] skipping conditional middleware", request_id(&state));
+            chain(state)
+        }
+    }
+}
+
+/// `NewMiddleware` trait implementation.
+impl<M> NewMiddleware for ConditionalMiddleware<M>
+where
+    M: NewMiddleware,
+{
+    type Instance = ConditionalMiddlewareInstance<M::Instance>;
+
+    /// Creates an instance of the wrapped middleware, to apply where the predicate holds.
+    fn new_middleware(&self) -> io::Result<Self::Instance> {
+        Ok(ConditionalMiddlewareInstance {
+            middleware: self.middleware.new_middleware()?,
+            predicate: self.predicate.clone(),
+        })
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    use futures::{future, Future};
+    use hyper::header::HeaderValue;
+
+    use crate::pipeline::new_pipeline;
+    use crate::pipeline::single::single_pipeline;
+    use crate::router::builder::*;
+    use crate::test::TestServer;
+
+    #[derive(Clone)]
+    struct Marker;
+
+    impl Middleware for Marker {
+        fn call<Chain>(self, state: State, chain: Chain) -> Box<HandlerFuture>
+        where
+            Chain: FnOnce(State) -> Box<HandlerFuture>,
+        {
+            Box::new(chain(state).and_then(|(state, mut res)| {
+                res.headers_mut()
+                    .insert("x-marker", HeaderValue::from_static("1"));
+                future::ok((state, res))
+            }))
+        }
+    }
+
+    impl NewMiddleware for Marker {
+        type Instance = Self;
+
+        fn new_middleware(&self) -> io::Result<Self::Instance> {
+            Ok(self.clone())
+        }
+    }
+
+    fn handler(state: State) -> (State, &'static str) {
+        (state, "hello")
+    }
+
+    fn marked<P>(predicate: P, method: Method, uri: &str, header: Option<&'static str>) -> bool
+    where
+        P: Fn(&State) -> bool + Send + Sync + RefUnwindSafe + 'static,
+    {
+        let middleware = ConditionalMiddleware::new(Marker, predicate);
+        let (chain, pipelines) = single_pipeline(new_pipeline().add(middleware).build());
+        let test_server = TestServer::new(build_router(chain, pipelines, |route| {
+            route
+                .request(vec![Method::GET, Method::POST], "/*")
+                .to(handler);
+        }))
+        .unwrap();
+
+        let client = test_server.client();
+        let mut req = client.build_request(method, uri);
+        if let Some(header) = header {
+            req = req.with_header(header, HeaderValue::from_static("1"));
+        }
+        req.perform().unwrap().headers().contains_key("x-marker")
+    }
+
+    #[test]
+    fn applies_middleware_by_path_prefix() {
+        let get = |uri| marked(path_prefix("/api/"), Method::GET, uri, None);
+
+        assert!(get("http://localhost/api"));
+        assert!(get("http://localhost/api/users"));
+        assert!(!get("http://localhost/apis"));
+        assert!(!get("http://localhost/other"));
+    }
+
+    #[test]
+    fn applies_middleware_by_header_and_method() {
+        let has_header = || has_header(HeaderName::from_static("x-debug"));
+        assert!(marked(
+            has_header(),
+            Method::GET,
+            "http://localhost/a",
+            Some("x-debug")
+        ));
+        assert!(!marked(
+            has_header(),
+            Method::GET,
+            "http://localhost/a",
+            None
+        ));
+
+        let post = || method(vec![Method::POST]);
+        assert!(marked(post(), Method::POST, "http://localhost/a", None));
+        assert!(!marked(post(), Method::GET, "http://localhost/a", None));
+    }
+}
@@ -1,18 +1,64 @@
 //! Defines a cookie parsing middleware to be attach cookies on requests.
 use std::io;
+use std::sync::Arc;
 
-use cookie::{Cookie, CookieJar};
-use hyper::header::{HeaderMap, HeaderValue, COOKIE};
+use cookie::{Cookie, CookieJar, Key, PrivateJar, SignedJar};
+use futures::{future, Future};
+use hyper::header::{HeaderMap, HeaderValue, COOKIE, SET_COOKIE};
+use hyper::{Body, Response};
 
 use super::{Middleware, NewMiddleware};
 use crate::handler::HandlerFuture;
-use crate::state::{FromState, State};
+use crate::state::{FromState, State, StateData};
 
 /// A struct that can act as a cookie parsing middleware for Gotham.
 ///
 /// We implement `NewMiddleware` here for Gotham to allow us to work with the request
 /// lifecycle correctly. This trait requires `Clone`, so that is also included. Cookies
 /// become availabe on the request state as the `CookieJar` type.
+///
+/// Cookies which handlers add to or remove from the `CookieJar` are written to the response as
+/// `Set-Cookie` headers. A cookie is removed by adding a cookie which expires immediately, so it
+/// must be removed with the same path and domain it was set with. Signed and private cookies
+/// are supported by `CookieParser::with_key`.
+///
+/// # Examples
+///
+/// ```rust
+/// # extern crate cookie;
+/// # extern crate gotham;
+/// # extern crate hyper;
+/// #
+/// # use cookie::{Cookie, CookieJar};
+/// # use hyper::header::SET_COOKIE;
+/// # use gotham::middleware::cookie::CookieParser;
+/// # use gotham::pipeline::new_pipeline;
+/// # use gotham::pipeline::single::single_pipeline;
+/// # use gotham::router::builder::*;
+/// # use gotham::state::{FromState, State};
+/// # use gotham::test::TestServer;
+/// #
+/// fn handler(mut state: State) -> (State, &'static str) {
+///     CookieJar::borrow_mut_from(&mut state).add(Cookie::new("theme", "dark"));
+///     (state, "saved")
+/// }
+///
+/// # fn main() {
+/// let (chain, pipelines) = single_pipeline(new_pipeline().add(CookieParser).build());
+/// let router = build_router(chain, pipelines, |route| {
+///     route.get("/theme").to(handler);
+/// });
+///
+/// let test_server = TestServer::new(router).unwrap();
+/// let response = test_server
+///     .client()
+///     .get("http://localhost/theme")
+///     .perform()
+///     .unwrap();
+///
+/// assert_eq!(response.headers()[SET_COOKIE], "theme=dark");
+/// # }
+/// ```
 #[derive(Copy, Clone)]
 pub struct CookieParser;
 
@@ -31,18 +77,52 @@ impl CookieParser {
                 jar
             })
     }
+
+    /// Creates a `KeyedCookieParser`, which is a `CookieParser` that also puts `key` into
+    /// `State`, for use by `signed_cookies` and `private_cookies`.
+    pub fn with_key(key: Key) -> KeyedCookieParser {
+        KeyedCookieParser {
+            key: CookieKey(Arc::new(key)),
+        }
+    }
+}
+
+/// Parses the cookies of the request into `State`, and writes the changes made to them into the
+/// `Set-Cookie` headers of the response.
+fn parse_cookies<Chain>(mut state: State, chain: Chain) -> Box<HandlerFuture>
+where
+    Chain: FnOnce(State) -> Box<HandlerFuture>,
+{
+    let cookies = { CookieParser::from_state(&state) };
+    state.put(cookies);
+
+    let f = chain(state).and_then(|(state, mut response)| {
+        if let Some(jar) = CookieJar::try_borrow_from(&state) {
+            write_cookies(jar, &mut response);
+        }
+        future::ok((state, response))
+    });
+
+    Box::new(f)
+}
+
+fn write_cookies(jar: &CookieJar, response: &mut Response<Body>) {
+    let headers = response.headers_mut();
+    for cookie in jar.delta() {
+        if let Ok(value) = HeaderValue::from_str(&cookie.to_string()) {
+            headers.append(SET_COOKIE, value);
+        }
+    }
 }
 
 /// `Middleware` trait implementation.
 impl Middleware for CookieParser {
     /// Attaches a set of parsed cookies to the request state.
-    fn call<Chain>(self, mut state: State, chain: Chain) -> Box<HandlerFuture>
+    fn call<Chain>(self, state: State, chain: Chain) -> Box<HandlerFuture>
     where
         Chain: FnOnce(State) -> Box<HandlerFuture>,
     {
-        let cookies = { CookieParser::from_state(&state) };
-        state.put(cookies);
-        chain(state)
+        parse_cookies(state, chain)
     }
 }
 
@@ -55,3 +135,182 @@ impl NewMiddleware for CookieParser {
         Ok(*self)
     }
 }
+
+/// The key used to sign and encrypt cookies, which `KeyedCookieParser` puts into `State`.
+#[derive(Clone)]
+pub struct CookieKey(Arc<Key>);
+
+impl CookieKey {
+    /// The key used to sign and encrypt cookies.
+    pub fn key(&self) -> &Key {
+        &self.0
+    }
+}
+
+impl StateData for CookieKey {}
+
+/// A `CookieParser` which also puts a `CookieKey` into `State`, so that handlers can use signed
+/// and private cookies. Created by `CookieParser::with_key`.
+///
+/// # Examples
+///
+/// ```rust
+/// # extern crate cookie;
+/// # extern crate gotham;
+/// #
+/// # use cookie::{Cookie, Key};
+/// # use gotham::middleware::cookie::{private_cookies, CookieParser};
+/// # use gotham::pipeline::new_pipeline;
+/// # use gotham::pipeline::single::single_pipeline;
+/// # use gotham::router::builder::*;
+/// # use gotham::state::State;
+/// # use gotham::test::TestServer;
+/// #
+/// fn login(mut state: State) -> (State, &'static str) {
+///     private_cookies(&mut state).add(Cookie::new("user", "alice"));
+///     (state, "welcome")
+/// }
+///
+/// # fn main() {
+/// let middleware = CookieParser::with_key(Key::generate());
+///
+/// let (chain, pipelines) = single_pipeline(new_pipeline().add(middleware).build());
+/// let router = build_router(chain, pipelines, |route| {
+///     route.get("/login").to(login);
+/// });
+///
+/// let test_server = TestServer::new(router).unwrap();
+/// let response = test_server
+///     .client()
+///     .get("http://localhost/login")
+///     .perform()
+///     .unwrap();
+///
+/// let set_cookie = response.headers()["Set-Cookie"].to_str().unwrap();
+/// assert!(set_cookie.starts_with("user="));
+/// assert!(!set_cookie.contains("alice"));
+/// # }
+/// ```
+#[derive(Clone)]
+pub struct KeyedCookieParser {
+    key: CookieKey,
+}
+
+/// `Middleware` trait implementation.
+impl Middleware for KeyedCookieParser {
+    /// Attaches a set of parsed cookies and the key to the request state.
+    fn call<Chain>(self, mut state: State, chain: Chain) -> Box<HandlerFuture>
+    where
+        Chain: FnOnce(State) -> Box<HandlerFuture>,
+    {
+        state.put(self.key);
+        parse_cookies(state, chain)
+    }
+}
+
+/// `NewMiddleware` trait implementation.
+impl NewMiddleware for KeyedCookieParser {
+    type Instance = Self;
+
+    /// Clones the current middleware to a new instance.
+    fn new_middleware(&self) -> io::Result<Self::Instance> {
+        Ok(self.clone())
+    }
+}
+
+/// Borrows the `CookieJar` of the request as a `SignedJar`, whose cookies are signed with the
+/// `CookieKey` so that they can be read by the client but not changed.
+///
+/// # Panics
+///
+/// Will panic if `State` does not contain the `CookieJar` and `CookieKey`, which are put there by
+/// a `KeyedCookieParser`.
+pub fn signed_cookies(state: &mut State) -> SignedJar<'_> {
+    let key = CookieKey::borrow_from(state).clone();
+    CookieJar::borrow_mut_from(state).signed(key.key())
+}
+
+/// Borrows the `CookieJar` of the request as a `PrivateJar`, whose cookies are encrypted with the
+/// `CookieKey` so that they can be neither read nor changed by the client.
+///
+/// # Panics
+///
+/// Will panic if `State` does not contain the `CookieJar` and `CookieKey`, which are put there by
+/// a `KeyedCookieParser`.
+pub fn private_cookies(state: &mut State) -> PrivateJar<'_> {
+    let key = CookieKey::borrow_from(state).clone();
+    CookieJar::borrow_mut_from(state).private(key.key())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    use hyper::header::HeaderValue;
+    use hyper::StatusCode;
+
+    use crate::pipeline::new_pipeline;
+    use crate::pipeline::single::single_pipeline;
+    use crate::router::builder::*;
+    use crate::test::TestServer;
+
+    fn handler(mut state: State) -> (State, String) {
+        let user = signed_cookies(&mut state)
+            .get("user")
+            .map(|cookie| cookie.value().to_owned());
+
+        let jar = CookieJar::borrow_mut_from(&mut state);
+        jar.remove(Cookie::named("visited"));
+        jar.add(Cookie::new("theme", "dark"));
+
+        (state, user.unwrap_or_default())
+    }
+
+    #[test]
+    fn reads_and_writes_cookies() {
+        let key = Key::generate();
+
+        let mut signed = CookieJar::new();
+        signed.signed(&key).add(Cookie::new("user", "alice"));
+        let user = signed.get("user").unwrap().to_string();
+
+        let middleware = CookieParser::with_key(key);
+        let (chain, pipelines) = single_pipeline(new_pipeline().add(middleware).build());
+        let test_server = TestServer::new(build_router(chain, pipelines, |route| {
+            route.get("/").to(handler);
+        }))
+        .unwrap();
+
+        let cookies = format!("{}; visited=yes", user);
+        let response = test_server
+            .client()
+            .get("http://localhost/")
+            .with_header(COOKIE, HeaderValue::from_str(&cookies).unwrap())
+            .perform()
+            .unwrap();
+
+        assert_eq!(response.status(), StatusCode::OK);
+        let mut set_cookies: Vec<_> = response
+            .headers()
+            .get_all(SET_COOKIE)
+            .iter()
+            .map(|value| value.to_str().unwrap().to_owned())
+            .collect();
+        set_cookies.sort();
+
+        assert_eq!(set_cookies.len(), 2);
+        assert_eq!(set_cookies[0], "theme=dark");
+        assert!(set_cookies[1].starts_with("visited=; Max-Age=0"));
+        assert_eq!(response.read_utf8_body().unwrap(), "alice");
+
+        // a cookie which doesn't carry a valid signature isn't read
+        let response = test_server
+            .client()
+            .get("http://localhost/")
+            .with_header(COOKIE, HeaderValue::from_static("user=alice"))
+            .perform()
+            .unwrap();
+
+        assert_eq!(response.read_utf8_body().unwrap(), "");
+    }
+}
@@ -0,0 +1,567 @@
+//! Cross-Origin Resource Sharing middleware, which allows browsers to make requests to the
+//! application from pages served by other origins.
+use std::io;
+use std::sync::Arc;
+use std::time::Duration;
+
+use futures::{future, Future};
+use hyper::header::{
+    HeaderMap, HeaderName, HeaderValue, ACCESS_CONTROL_ALLOW_CREDENTIALS,
+    ACCESS_CONTROL_ALLOW_HEADERS, ACCESS_CONTROL_ALLOW_METHODS, ACCESS_CONTROL_ALLOW_ORIGIN,
+    ACCESS_CONTROL_EXPOSE_HEADERS, ACCESS_CONTROL_MAX_AGE, ACCESS_CONTROL_REQUEST_HEADERS,
+    ACCESS_CONTROL_REQUEST_METHOD, ORIGIN, VARY,
+};
+use hyper::{Method, Response, StatusCode};
+use log::trace;
+use regex::Regex;
+
+use crate::handler::HandlerFuture;
+use crate::helpers::http::response::create_empty_response;
+use crate::middleware::{Middleware, NewMiddleware};
+use crate::state::{request_id, FromState, State};
+
+#[derive(Clone, Debug)]
+enum AllowedOrigins {
+    Any,
+    Only(Vec<OriginPattern>),
+}
+
+#[derive(Clone, Debug)]
+enum OriginPattern {
+    Exact(String),
+    Pattern(Regex),
+}
+
+impl OriginPattern {
+    fn matches(&self, origin: &str) -> bool {
+        match self {
+            OriginPattern::Exact(allowed) => allowed == origin,
+            OriginPattern::Pattern(pattern) => pattern.is_match(origin),
+        }
+    }
+}
+
+#[derive(Clone, Debug)]
+struct CorsConfig {
+    origins: AllowedOrigins,
+    methods: Vec<Method>,
+    headers: Vec<HeaderName>,
+    exposed_headers: Vec<HeaderName>,
+    credentials: bool,
+    max_age: Option<Duration>,
+}
+
+/// Middleware binding which adds Cross-Origin Resource Sharing (CORS) headers to responses, and
+/// answers the preflight requests made by browsers before sending a cross-origin request which
+/// isn't "simple".
+///
+/// By default, requests from any origin are allowed, with the `GET`, `HEAD` and `POST` methods,
+/// and without any request headers beyond those browsers always allow. Once an origin is
+/// configured with `with_allowed_origin` or `with_allowed_origin_pattern`, only the configured
+/// origins are allowed.
+///
+/// Preflight requests are answered with `204 No Content`, without invoking the rest of the
+/// pipeline or the handler, or with `403 Forbidden` where the origin, method or headers of the
+/// request aren't allowed. Preflights use the `OPTIONS` method, so they only reach the middleware
+/// for routes which accept `OPTIONS` requests. Responses to requests from origins which aren't
+/// allowed are sent without CORS headers, so browsers withhold them from the page.
+///
+/// # Examples
+///
+/// ```rust
+/// # extern crate gotham;
+/// # extern crate hyper;
+/// #
+/// # use std::time::Duration;
+/// # use hyper::header::{HeaderValue, CONTENT_TYPE};
+/// # use hyper::Method;
+/// # use gotham::middleware::cors::CorsMiddleware;
+/// # use gotham::pipeline::new_pipeline;
+/// # use gotham::pipeline::single::single_pipeline;
+/// # use gotham::router::builder::*;
+/// # use gotham::state::State;
+/// # use gotham::test::TestServer;
+/// #
+/// # fn handler(state: State) -> (State, &'static str) {
+/// #     (state, "widgets")
+/// # }
+/// #
+/// # fn main() {
+/// let cors = CorsMiddleware::default()
+///     .with_allowed_origin("https://example.com")
+///     .with_allowed_origin("https://*.example.com")
+///     .with_allowed_methods(vec![Method::GET, Method::PUT])
+///     .with_allowed_headers(vec![CONTENT_TYPE])
+///     .with_max_age(Duration::from_secs(3600));
+///
+/// let (chain, pipelines) = single_pipeline(new_pipeline().add(cors).build());
+/// let router = build_router(chain, pipelines, |route| {
+///     route
+///         .request(vec![Method::GET, Method::PUT, Method::OPTIONS], "/widgets")
+///         .to(handler);
+/// });
+///
+/// let test_server = TestServer::new(router).unwrap();
+/// let response = test_server
+///     .client()
+///     .options("http://localhost/widgets")
+///     .with_header("Origin", HeaderValue::from_static("https://shop.example.com"))
+///     .with_header("Access-Control-Request-Method", HeaderValue::from_static("PUT"))
+///     .with_header("Access-Control-Request-Headers", HeaderValue::from_static("content-type"))
+///     .perform()
+///     .unwrap();
+///
+/// assert_eq!(response.status(), 204);
+/// assert_eq!(
+///     response.headers()["Access-Control-Allow-Origin"],
+///     "https://shop.example.com"
+/// );
+/// assert_eq!(response.headers()["Access-Control-Allow-Methods"], "GET, PUT");
+/// assert_eq!(response.headers()["Access-Control-Max-Age"], "3600");
+/// # }
+/// ```
+#[derive(Clone, Debug)]
+pub struct CorsMiddleware {
+    config: Arc<CorsConfig>,
+}
+
+impl Default for CorsMiddleware {
+    fn default() -> CorsMiddleware {
+        CorsMiddleware {
+            config: Arc::new(CorsConfig {
+                origins: AllowedOrigins::Any,
+                methods: vec![Method::GET, Method::HEAD, Method::POST],
+                headers: vec![],
+                exposed_headers: vec![],
+                credentials: false,
+                max_age: None,
+            }),
+        }
+    }
+}
+
+impl CorsMiddleware {
+    fn rebuild<F>(self, f: F) -> CorsMiddleware
+    where
+        F: FnOnce(&mut CorsConfig),
+    {
+        let mut config = (*self.config).clone();
+        f(&mut config);
+        CorsMiddleware {
+            config: Arc::new(config),
+        }
+    }
+
+    fn allow_origin(self, pattern: OriginPattern) -> CorsMiddleware {
+        self.rebuild(|config| match config.origins {
+            AllowedOrigins::Only(ref mut patterns) => patterns.push(pattern),
+            AllowedOrigins::Any => config.origins = AllowedOrigins::Only(vec![pattern]),
+        })
+    }
+
+    /// Allows requests from `origin`, such as `"https://example.com"`. Each `*` in `origin`
+    /// matches any part of a host name, so that `"https://*.example.com"` allows every subdomain
+    /// of `example.com`.
+    pub fn with_allowed_origin(self, origin: &str) -> CorsMiddleware {
+        if !origin.contains('*') {
+            return self.allow_origin(OriginPattern::Exact(origin.to_owned()));
+        }
+
+        let pattern = origin
+            .split('*')
+            .map(regex::escape)
+            .collect::<Vec<_>>()
+            .join("[^/]+");
+        let pattern = Regex::new(&format!("^{}$", pattern)).expect("escaped origin pattern");
+        self.allow_origin(OriginPattern::Pattern(pattern))
+    }
+
+    /// Allows requests from origins which match `pattern`. The pattern is matched against the
+    /// whole of the `Origin` header unless it's anchored, so it should usually begin with `^` and
+    /// end with `$`.
+    pub fn with_allowed_origin_pattern(self, pattern: Regex) -> CorsMiddleware {
+        self.allow_origin(OriginPattern::Pattern(pattern))
+    }
+
+    /// Sets the methods which may be used by cross-origin requests, in place of `GET`, `HEAD` and
+    /// `POST`.
+    pub fn with_allowed_methods(self, methods: Vec<Method>) -> CorsMiddleware {
+        self.rebuild(|config| config.methods = methods)
+    }
+
+    /// Sets the request headers which may be sent by cross-origin requests, beyond those browsers
+    /// always allow.
+    pub fn with_allowed_headers(self, headers: Vec<HeaderName>) -> CorsMiddleware {
+        self.rebuild(|config| config.headers = headers)
+    }
+
+    /// Sets the response headers which the page making a cross-origin request may read, beyond
+    /// those browsers always expose.
+    pub fn with_exposed_headers(self, headers: Vec<HeaderName>) -> CorsMiddleware {
+        self.rebuild(|config| config.exposed_headers = headers)
+    }
+
+    /// Allows cross-origin requests from the configured origins to include credentials, such as
+    /// cookies. The origin of the request is then named in the `Access-Control-Allow-Origin`
+    /// header, as browsers refuse credentialed responses allowing any origin.
+    ///
+    /// Credentials are only allowed once origins are configured with `with_allowed_origin` or
+    /// `with_allowed_origin_pattern`. Where any origin is allowed, responses are sent without the
+    /// `Access-Control-Allow-Credentials` header, as allowing credentials would let every website
+    /// read responses made with the user's cookies.
+    pub fn with_credentials(self) -> CorsMiddleware {
+        self.rebuild(|config| config.credentials = true)
+    }
+
+    /// Allows browsers to cache the response to a preflight request for `max_age`.
+    pub fn with_max_age(self, max_age: Duration) -> CorsMiddleware {
+        self.rebuild(|config| config.max_age = Some(max_age))
+    }
+}
+
+impl CorsConfig {
+    fn is_allowed_origin(&self, origin: &str) -> bool {
+        match self.origins {
+            AllowedOrigins::Any => true,
+            AllowedOrigins::Only(ref patterns) => {
+                patterns.iter().any(|pattern| pattern.matches(origin))
+            }
+        }
+    }
+
+    /// Determines whether the method and headers a preflight request asks to use are allowed.
+    fn is_allowed_preflight(&self, headers: &HeaderMap) -> bool {
+        let method = headers
+            .get(ACCESS_CONTROL_REQUEST_METHOD)
+            .and_then(|method| Method::from_bytes(method.as_bytes()).ok());
+
+        let method_allowed = match method {
+            Some(method) => self.methods.contains(&method),
+            None => false,
+        };
+
+        let headers_allowed = headers
+            .get_all(ACCESS_CONTROL_REQUEST_HEADERS)
+            .iter()
+            .flat_map(|value| value.to_str().unwrap_or(",").split(','))
+            .map(str::trim)
+            .filter(|name| !name.is_empty())
+            .all(|name| {
+                HeaderName::from_bytes(name.as_bytes())
+                    .map(|name| self.headers.contains(&name))
+                    .unwrap_or(false)
+            });
+
+        method_allowed && headers_allowed
+    }
+
+    /// Adds the headers which apply to both preflight and other responses.
+    fn add_origin_headers<B>(&self, origin: &HeaderValue, res: &mut Response<B>) {
+        let headers = res.headers_mut();
+
+        match self.origins {
+            AllowedOrigins::Any => {
+                // credentials are never allowed for any origin
+                headers.insert(ACCESS_CONTROL_ALLOW_ORIGIN, HeaderValue::from_static("*"));
+            }
+            AllowedOrigins::Only(_) => {
+                headers.insert(ACCESS_CONTROL_ALLOW_ORIGIN, origin.clone());
+                headers.append(VARY, HeaderValue::from_static("Origin"));
+
+                if self.credentials {
+                    headers.insert(
+                        ACCESS_CONTROL_ALLOW_CREDENTIALS,
+                        HeaderValue::from_static("true"),
+                    );
+                }
+            }
+        }
+    }
+
+    fn preflight_response(&self, state: &State, origin: &HeaderValue) -> Response<hyper::Body> {
+        let mut res = create_empty_response(state, StatusCode::NO_CONTENT);
+        self.add_origin_headers(origin, &mut res);
+
+        let headers = res.headers_mut();
+        headers.insert(
+            ACCESS_CONTROL_ALLOW_METHODS,
+            join(self.methods.iter().map(Method::as_str)),
+        );
+
+        if !self.headers.is_empty() {
+            headers.insert(
+                ACCESS_CONTROL_ALLOW_HEADERS,
+                join(self.headers.iter().map(HeaderName::as_str)),
+            );
+        }
+
+        if let Some(max_age) = self.max_age {
+            headers.insert(ACCESS_CONTROL_MAX_AGE, max_age.as_secs().into());
+        }
+
+        res
+    }
+}
+
+fn join<'a, I>(values: I) -> HeaderValue
+where
+    I: Iterator<Item = &'a str>,
+{
+    let joined = values.collect::<Vec<_>>().join(", ");
+    HeaderValue::from_str(&joined).expect("methods and header names are valid header values")
+}
+
+/// `Middleware` trait implementation.
+impl Middleware for CorsMiddleware {
+    /// Answers preflight requests, and adds CORS headers to the responses to other cross-origin
+    /// requests.
+    fn call<Chain>(self, state: State, chain: Chain) -> Box<HandlerFuture>
+    where
+        Chain: FnOnce(State) -> Box<HandlerFuture>,
+    {
+        let config = self.config;
+        let (origin, preflight) = {
+            let headers = HeaderMap::borrow_from(&state);
+            let preflight = *Method::borrow_from(&state) == Method::OPTIONS
+                && headers.contains_key(ACCESS_CONTROL_REQUEST_METHOD);
+            (headers.get(ORIGIN).cloned(), preflight)
+        };
+
+        // requests without an `Origin` header aren't cross-origin requests
+        let origin = match origin {
+            Some(origin) => origin,
+            None => return chain(state),
+        };
+
+        let allowed = origin
+            .to_str()
+            .map(|origin| config.is_allowed_origin(origin))
+            .unwrap_or(false);
+
+        if preflight {
+            let res = if allowed && config.is_allowed_preflight(HeaderMap::borrow_from(&state)) {
+                trace!("[{}] answering CORS preflight", request_id(&state));
+                config.preflight_response(&state, &origin)
+            } else {
+                trace!("[{}] refusing CORS preflight", request_id(&state));
+                create_empty_response(&state, StatusCode::FORBIDDEN)
+            };
+            return Box::new(future::ok((state, res)));
+        }
+
+        if !allowed {
+            trace!("[{}] origin not allowed by CORS", request_id(&state));
+            return chain(state);
+        }
+
+        let f = chain(state).and_then(move |(state, mut res)| {
+            config.add_origin_headers(&origin, &mut res);
+
+            if !config.exposed_headers.is_empty() {
+                res.headers_mut().insert(
+                    ACCESS_CONTROL_EXPOSE_HEADERS,
+                    join(config.exposed_headers.iter().map(HeaderName::as_str)),
+                );
+            }
+
+            future::ok((state, res))
+        });
+
+        Box::new(f)
+    }
+}
+
+/// `NewMiddleware` trait implementation.
+impl NewMiddleware for CorsMiddleware {
+    type Instance = Self;
+
+    /// Clones the current middleware to a new instance.
+    fn new_middleware(&self) -> io::Result<Self::Instance> {
+        Ok(self.clone())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    use hyper::header::{CONTENT_TYPE, ETAG};
+
+    use crate::pipeline::new_pipeline;
+    use crate::pipeline::single::single_pipeline;
+    use crate::router::builder::*;
+    use crate::test::{TestResponse, TestServer};
+
+    fn handler(state: State) -> (State, &'static str) {
+        (state, "handled")
+    }
+
+    fn test_server(cors: CorsMiddleware) -> TestServer {
+        let (chain, pipelines) = single_pipeline(new_pipeline().add(cors).build());
+        let router = build_router(chain, pipelines, |route| {
+            route
+                .request(vec![Method::GET, Method::POST, Method::OPTIONS], "/")
+                .to(handler);
+        });
+        TestServer::new(router).unwrap()
+    }
+
+    fn get(test_server: &TestServer, origin: Option<&'static str>) -> TestResponse {
+        let client = test_server.client();
+        let mut req = client.get("http://localhost/");
+        if let Some(origin) = origin {
+            req = req.with_header(ORIGIN, HeaderValue::from_static(origin));
+        }
+        req.perform().unwrap()
+    }
+
+    fn preflight(
+        test_server: &TestServer,
+        origin: &'static str,
+        method: &'static str,
+        headers: Option<&'static str>,
+    ) -> TestResponse {
+        let client = test_server.client();
+        let mut req = client
+            .options("http://localhost/")
+            .with_header(ORIGIN, HeaderValue::from_static(origin))
+            .with_header(
+                ACCESS_CONTROL_REQUEST_METHOD,
+                HeaderValue::from_static(method),
+            );
+        if let Some(headers) = headers {
+            req = req.with_header(
+                ACCESS_CONTROL_REQUEST_HEADERS,
+                HeaderValue::from_static(headers),
+            );
+        }
+        req.perform().unwrap()
+    }
+
+    #[test]
+    fn allows_any_origin_by_default() {
+        let test_server = test_server(CorsMiddleware::default());
+
+        let res = get(&test_server, Some("https://example.com"));
+        assert_eq!(res.headers()[ACCESS_CONTROL_ALLOW_ORIGIN], "*");
+        assert!(res.headers().get(VARY).is_none());
+
+        let res = get(&test_server, None);
+        assert!(res.headers().get(ACCESS_CONTROL_ALLOW_ORIGIN).is_none());
+        assert_eq!(res.read_utf8_body().unwrap(), "handled");
+
+        let res = preflight(&test_server, "https://example.com", "POST", None);
+        assert_eq!(res.status(), StatusCode::NO_CONTENT);
+        assert_eq!(
+            res.headers()[ACCESS_CONTROL_ALLOW_METHODS],
+            "GET, HEAD, POST"
+        );
+        assert!(res.headers().get(ACCESS_CONTROL_MAX_AGE).is_none());
+        assert_eq!(res.read_body().unwrap(), b"");
+
+        let res = preflight(&test_server, "https://example.com", "DELETE", None);
+        assert_eq!(res.status(), StatusCode::FORBIDDEN);
+
+        // an `OPTIONS` request which isn't a preflight reaches the handler
+        let res = test_server
+            .client()
+            .options("http://localhost/")
+            .with_header(ORIGIN, HeaderValue::from_static("https://example.com"))
+            .perform()
+            .unwrap();
+        assert_eq!(res.status(), StatusCode::OK);
+        assert_eq!(res.read_utf8_body().unwrap(), "handled");
+    }
+
+    #[test]
+    fn allows_configured_origins() {
+        let cors = CorsMiddleware::default()
+            .with_allowed_origin("https://example.com")
+            .with_allowed_origin("https://*.example.com")
+            .with_allowed_origin_pattern(Regex::new(r"^http://localhost:\d+$").unwrap());
+        let test_server = test_server(cors);
+
+        for origin in &[
+            "https://example.com",
+            "https://api.example.com",
+            "http://localhost:8080",
+        ] {
+            let res = get(&test_server, Some(origin));
+            assert_eq!(res.headers()[ACCESS_CONTROL_ALLOW_ORIGIN], *origin);
+            assert_eq!(res.headers()[VARY], "Origin");
+        }
+
+        for origin in &[
+            "https://example.org",
+            "https://notexample.com",
+            "https://example.com.evil.org",
+            "http://localhost",
+        ] {
+            let res = get(&test_server, Some(origin));
+            assert!(res.headers().get(ACCESS_CONTROL_ALLOW_ORIGIN).is_none());
+            assert_eq!(res.read_utf8_body().unwrap(), "handled");
+        }
+
+        let res = preflight(&test_server, "https://evil.org", "GET", None);
+        assert_eq!(res.status(), StatusCode::FORBIDDEN);
+        assert!(res.headers().get(ACCESS_CONTROL_ALLOW_ORIGIN).is_none());
+    }
+
+    #[test]
+    fn answers_preflights_with_configured_headers() {
+        let cors = CorsMiddleware::default()
+            .with_allowed_origin("https://example.com")
+            .with_allowed_methods(vec![Method::GET, Method::PUT])
+            .with_allowed_headers(vec![CONTENT_TYPE, HeaderName::from_static("x-token")])
+            .with_exposed_headers(vec![ETAG])
+            .with_credentials()
+            .with_max_age(Duration::from_secs(600));
+        let test_server = test_server(cors);
+
+        let res = preflight(
+            &test_server,
+            "https://example.com",
+            "PUT",
+            Some("Content-Type, X-Token"),
+        );
+        assert_eq!(res.status(), StatusCode::NO_CONTENT);
+        let headers = res.headers();
+        assert_eq!(headers[ACCESS_CONTROL_ALLOW_ORIGIN], "https://example.com");
+        assert_eq!(headers[ACCESS_CONTROL_ALLOW_METHODS], "GET, PUT");
+        assert_eq!(
+            headers[ACCESS_CONTROL_ALLOW_HEADERS],
+            "content-type, x-token"
+        );
+        assert_eq!(headers[ACCESS_CONTROL_ALLOW_CREDENTIALS], "true");
+        assert_eq!(headers[ACCESS_CONTROL_MAX_AGE], "600");
+
+        let res = preflight(&test_server, "https://example.com", "PUT", Some("x-other"));
+        assert_eq!(res.status(), StatusCode::FORBIDDEN);
+
+        let res = get(&test_server, Some("https://example.com"));
+        let headers = res.headers();
+        assert_eq!(headers[ACCESS_CONTROL_ALLOW_ORIGIN], "https://example.com");
+        assert_eq!(headers[ACCESS_CONTROL_ALLOW_CREDENTIALS], "true");
+        assert_eq!(headers[ACCESS_CONTROL_EXPOSE_HEADERS], "etag");
+        assert!(headers.get(ACCESS_CONTROL_ALLOW_METHODS).is_none());
+    }
+
+    #[test]
+    fn withholds_credentials_from_any_origin() {
+        let test_server = test_server(CorsMiddleware::default().with_credentials());
+
+        let res = get(&test_server, Some("https://evil.org"));
+        assert_eq!(res.headers()[ACCESS_CONTROL_ALLOW_ORIGIN], "*");
+        assert!(res
+            .headers()
+            .get(ACCESS_CONTROL_ALLOW_CREDENTIALS)
+            .is_none());
+
+        let res = preflight(&test_server, "https://evil.org", "POST", None);
+        assert_eq!(res.status(), StatusCode::NO_CONTENT);
+        assert!(res
+            .headers()
+            .get(ACCESS_CONTROL_ALLOW_CREDENTIALS)
+            .is_none());
+    }
+}
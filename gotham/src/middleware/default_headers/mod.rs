@@ -0,0 +1,180 @@
+//! Default headers middleware, which applies a header policy to every response.
+use std::io;
+
+use futures::{future, Future};
+use hyper::header::{HeaderMap, HeaderName, HeaderValue};
+
+use crate::handler::HandlerFuture;
+use crate::middleware::{Middleware, NewMiddleware};
+use crate::state::State;
+
+/// Middleware binding which adds headers to responses which don't already have them, and removes
+/// headers which shouldn't be sent.
+///
+/// A header set by the handler, or by middleware later in the pipeline chain, is kept in place of
+/// the default, so that a default such as `Cache-Control: no-cache` only applies where the
+/// response doesn't describe its own caching. Headers given to `without_header`, such as a
+/// `X-Powered-By` header added by a library, are removed from every response.
+///
+/// Responses the `Router` creates for a `HandlerError` are created after the middleware has seen
+/// the error, and don't receive the headers.
+///
+/// # Examples
+///
+/// ```rust
+/// # extern crate gotham;
+/// # extern crate hyper;
+/// #
+/// # use hyper::header::{HeaderName, HeaderValue, CACHE_CONTROL, SERVER};
+/// # use gotham::middleware::default_headers::DefaultHeadersMiddleware;
+/// # use gotham::pipeline::new_pipeline;
+/// # use gotham::pipeline::single::single_pipeline;
+/// # use gotham::router::builder::*;
+/// # use gotham::state::State;
+/// # use gotham::test::TestServer;
+/// #
+/// # fn handler(state: State) -> (State, &'static str) {
+/// #     (state, "hello")
+/// # }
+/// #
+/// # fn main() {
+/// let middleware = DefaultHeadersMiddleware::new()
+///     .with_header(SERVER, HeaderValue::from_static("gotham"))
+///     .with_header(CACHE_CONTROL, HeaderValue::from_static("no-cache"))
+///     .without_header(HeaderName::from_static("x-powered-by"));
+///
+/// let (chain, pipelines) = single_pipeline(new_pipeline().add(middleware).build());
+/// let router = build_router(chain, pipelines, |route| {
+///     route.get("/").to(handler);
+/// });
+///
+/// let test_server = TestServer::new(router).unwrap();
+/// let response = test_server
+///     .client()
+///     .get("http://localhost/")
+///     .perform()
+///     .unwrap();
+///
+/// assert_eq!(response.headers()["Server"], "gotham");
+/// assert_eq!(response.headers()["Cache-Control"], "no-cache");
+/// # }
+/// ```
+#[derive(Clone, Debug, Default)]
+pub struct DefaultHeadersMiddleware {
+    defaults: HeaderMap,
+    removed: Vec<HeaderName>,
+}
+
+impl DefaultHeadersMiddleware {
+    /// Creates a `DefaultHeadersMiddleware` which doesn't change any headers.
+    pub fn new() -> DefaultHeadersMiddleware {
+        DefaultHeadersMiddleware::default()
+    }
+
+    /// Adds `value` to responses which don't have the header `name`. A header given more than
+    /// once is added with each of its values.
+    pub fn with_header(mut self, name: HeaderName, value: HeaderValue) -> DefaultHeadersMiddleware {
+        self.defaults.append(name, value);
+        self
+    }
+
+    /// Removes the header `name` from every response.
+    pub fn without_header(mut self, name: HeaderName) -> DefaultHeadersMiddleware {
+        self.defaults.remove(&name);
+        self.removed.push(name);
+        self
+    }
+
+    fn apply(&self, headers: &mut HeaderMap) {
+        for name in self.defaults.keys() {
+            if !headers.contains_key(name) {
+                for value in self.defaults.get_all(name) {
+                    headers.append(name.clone(), value.clone());
+                }
+            }
+        }
+
+        for name in &self.removed {
+            headers.remove(name);
+        }
+    }
+}
+
+/// `Middleware` trait implementation.
+impl Middleware for DefaultHeadersMiddleware {
+    /// Applies the default and removed headers to the response.
+    fn call<Chain>(self, state: State, chain: Chain) -> Box<HandlerFuture>
+    where
+        Chain: FnOnce(State) -> Box<HandlerFuture>,
+    {
+        let f = chain(state).and_then(move |(state, mut response)| {
+            self.apply(response.headers_mut());
+            future::ok((state, response))
+        });
+
+        Box::new(f)
+    }
+}
+
+/// `NewMiddleware` trait implementation.
+impl NewMiddleware for DefaultHeadersMiddleware {
+    type Instance = Self;
+
+    /// Clones the current middleware to a new instance.
+    fn new_middleware(&self) -> io::Result<Self::Instance> {
+        Ok(self.clone())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    use hyper::header::{CACHE_CONTROL, SERVER, VARY};
+    use hyper::{Body, Response};
+
+    use crate::pipeline::new_pipeline;
+    use crate::pipeline::single::single_pipeline;
+    use crate::router::builder::*;
+    use crate::test::TestServer;
+
+    fn handler(state: State) -> (State, Response<Body>) {
+        let res = Response::builder()
+            .header(CACHE_CONTROL, "max-age=60")
+            .header("x-powered-by", "framework")
+            .body(Body::empty())
+            .unwrap();
+        (state, res)
+    }
+
+    #[test]
+    fn applies_default_headers() {
+        let middleware = DefaultHeadersMiddleware::new()
+            .with_header(SERVER, HeaderValue::from_static("gotham"))
+            .with_header(CACHE_CONTROL, HeaderValue::from_static("no-cache"))
+            .with_header(VARY, HeaderValue::from_static("accept"))
+            .with_header(VARY, HeaderValue::from_static("accept-encoding"))
+            .without_header(HeaderName::from_static("x-powered-by"));
+
+        let (chain, pipelines) = single_pipeline(new_pipeline().add(middleware).build());
+        let test_server = TestServer::new(build_router(chain, pipelines, |route| {
+            route.get("/").to(handler);
+        }))
+        .unwrap();
+
+        let response = test_server
+            .client()
+            .get("http://localhost/")
+            .perform()
+            .unwrap();
+        let headers = response.headers();
+
+        assert_eq!(headers[SERVER], "gotham");
+        assert_eq!(headers[CACHE_CONTROL], "max-age=60");
+        assert_eq!(
+            headers.get_all(VARY).iter().collect::<Vec<_>>(),
+            vec!["accept", "accept-encoding"]
+        );
+        assert!(headers.get("x-powered-by").is_none());
+    }
+}
@@ -0,0 +1,270 @@
+//! Error mapping middleware, which turns the errors of the rest of the pipeline chain and the
+//! handler into responses.
+use std::io;
+use std::panic::RefUnwindSafe;
+use std::sync::Arc;
+
+use futures::Future;
+use hyper::{Body, Response};
+use log::trace;
+
+use crate::handler::{HandlerError, HandlerFuture};
+use crate::middleware::{Middleware, NewMiddleware};
+use crate::state::{request_id, State};
+
+/// Turns a `HandlerError` into the response to send in its place, such as a JSON body describing
+/// the error with a status suited to it.
+///
+/// `HandlerError::downcast_cause_ref` gives the error the `HandlerError` was created from, so that
+/// errors of the application's own types can be told apart. Any closure taking the `State` and
+/// the `HandlerError` is an `ErrorMapper`.
+pub trait ErrorMapper: Send + Sync + RefUnwindSafe {
+    /// Returns the response to send in place of `error`, or `None` to leave the error to the
+    /// middleware earlier in the chain and the `Router`, which responds with the status of the
+    /// error by default.
+    fn map_error(&self, state: &State, error: &HandlerError) -> Option<Response<Body>>;
+}
+
+impl<F> ErrorMapper for F
+where
+    F: Fn(&State, &HandlerError) -> Option<Response<Body>> + Send + Sync + RefUnwindSafe,
+{
+    fn map_error(&self, state: &State, error: &HandlerError) -> Option<Response<Body>> {
+        self(state, error)
+    }
+}
+
+/// Middleware binding which passes each `HandlerError` of the rest of the pipeline chain and the
+/// handler to an `ErrorMapper`, and responds with the response it returns.
+///
+/// This allows one place to define how the errors of the routes of a pipeline become responses,
+/// rather than each handler creating its own error responses. Responses the handler succeeded in
+/// creating aren't changed, including those with an error status.
+///
+/// # Examples
+///
+/// ```rust
+/// # extern crate futures;
+/// # extern crate gotham;
+/// # extern crate hyper;
+/// # extern crate mime;
+/// #
+/// # use std::error::Error;
+/// # use std::fmt::{self, Display};
+/// # use futures::future;
+/// # use hyper::StatusCode;
+/// # use gotham::handler::{HandlerError, HandlerFuture, IntoHandlerError};
+/// # use gotham::helpers::http::response::create_response;
+/// # use gotham::middleware::error_map::ErrorMapMiddleware;
+/// # use gotham::pipeline::new_pipeline;
+/// # use gotham::pipeline::single::single_pipeline;
+/// # use gotham::router::builder::*;
+/// # use gotham::state::State;
+/// # use gotham::test::TestServer;
+/// #
+/// #[derive(Debug)]
+/// struct UnknownUser;
+///
+/// impl Display for UnknownUser {
+///     fn fmt(&self, out: &mut fmt::Formatter) -> fmt::Result {
+///         out.write_str("unknown user")
+///     }
+/// }
+///
+/// impl Error for UnknownUser {}
+///
+/// fn handler(state: State) -> Box<HandlerFuture> {
+///     Box::new(future::err((state, UnknownUser.into_handler_error())))
+/// }
+///
+/// # fn main() {
+/// let middleware = ErrorMapMiddleware::new(|state: &State, error: &HandlerError| {
+///     let cause = error.downcast_cause_ref::<UnknownUser>()?;
+///     let body = format!(r#"{{"error":"{}"}}"#, cause);
+///     Some(create_response(state, StatusCode::NOT_FOUND, mime::APPLICATION_JSON, body))
+/// });
+///
+/// let (chain, pipelines) = single_pipeline(new_pipeline().add(middleware).build());
+/// let router = build_router(chain, pipelines, |route| {
+///     route.get("/users/:id").to(handler);
+/// });
+///
+/// let test_server = TestServer::new(router).unwrap();
+/// let response = test_server
+///     .client()
+///     .get("http://localhost/users/1")
+///     .perform()
+///     .unwrap();
+///
+/// assert_eq!(response.status(), StatusCode::NOT_FOUND);
+/// assert_eq!(response.read_utf8_body().unwrap(), r#"{"error":"unknown user"}"#);
+/// # }
+/// ```
+pub struct ErrorMapMiddleware<M>
+where
+    M: ErrorMapper,
+{
+    mapper: Arc<M>,
+}
+
+impl<M> ErrorMapMiddleware<M>
+where
+    M: ErrorMapper,
+{
+    /// Creates an `ErrorMapMiddleware` which gives errors to `mapper`.
+    pub fn new(mapper: M) -> ErrorMapMiddleware<M> {
+        ErrorMapMiddleware {
+            mapper: Arc::new(mapper),
+        }
+    }
+}
+
+impl<M> Clone for ErrorMapMiddleware<M>
+where
+    M: ErrorMapper,
+{
+    fn clone(&self) -> Self {
+        ErrorMapMiddleware {
+            mapper: self.mapper.clone(),
+        }
+    }
+}
+
+/// `Middleware` trait implementation.
+impl<M> Middleware for ErrorMapMiddleware<M>
+where
+    M: ErrorMapper + 'static,
+{
+    /// Replaces errors of the rest of the chain with the responses of the mapper.
+    fn call<Chain>(self, state: State, chain: Chain) -> Box<HandlerFuture>
+    where
+        Chain: FnOnce(State) -> Box<HandlerFuture>,
+    {
+        let mapper = self.mapper;
+        let f = chain(state).or_else(move |(state, error)| {
+            let res = match mapper.map_error(&state, &error) {
+                Some(res) => res,
+                None => return Err((state, error)),
+            };
+
+            trace!("[{}] mapped error to {}", request_id(&state), res.status());
+            Ok((state, res))
+        });
+
+        Box::new(f)
+    }
+}
+
+/// `NewMiddleware` trait implementation.
+impl<M> NewMiddleware for ErrorMapMiddleware<M>
+where
+    M: ErrorMapper + 'static,
+{
+    type Instance = Self;
+
+    /// Clones the current middleware to a new instance.
+    fn new_middleware(&self) -> io::Result<Self::Instance> {
+        Ok(self.clone())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    use std::error::Error;
+    use std::fmt::{self, Display};
+
+    use futures::future;
+    use hyper::{StatusCode, Uri};
+
+    use crate::handler::IntoHandlerError;
+    use crate::helpers::http::response::{create_empty_response, create_response};
+    use crate::pipeline::new_pipeline;
+    use crate::pipeline::single::single_pipeline;
+    use crate::router::builder::*;
+    use crate::state::FromState;
+    use crate::test::TestServer;
+
+    #[derive(Debug)]
+    enum DomainError {
+        NotFound(u32),
+        Conflict,
+    }
+
+    impl Display for DomainError {
+        fn fmt(&self, out: &mut fmt::Formatter) -> fmt::Result {
+            match *self {
+                DomainError::NotFound(id) => write!(out, "no item {}", id),
+                DomainError::Conflict => out.write_str("item was changed"),
+            }
+        }
+    }
+
+    impl Error for DomainError {}
+
+    struct DomainErrors;
+
+    impl ErrorMapper for DomainErrors {
+        fn map_error(&self, state: &State, error: &HandlerError) -> Option<Response<Body>> {
+            let cause = error.downcast_cause_ref::<DomainError>()?;
+            let status = match cause {
+                DomainError::NotFound(_) => StatusCode::NOT_FOUND,
+                DomainError::Conflict => StatusCode::CONFLICT,
+            };
+            let body = serde_json::json!({ "error": cause.to_string() });
+            Some(create_response(
+                state,
+                status,
+                mime::APPLICATION_JSON,
+                body.to_string(),
+            ))
+        }
+    }
+
+    fn handler(state: State) -> Box<HandlerFuture> {
+        let error = match Uri::borrow_from(&state).path() {
+            "/missing" => DomainError::NotFound(7).into_handler_error(),
+            "/conflict" => DomainError::Conflict.into_handler_error(),
+            "/unavailable" => {
+                let res = create_empty_response(&state, StatusCode::SERVICE_UNAVAILABLE);
+                return Box::new(future::ok((state, res)));
+            }
+            _ => io::Error::other("disk failed")
+                .into_handler_error()
+                .with_status(StatusCode::BAD_GATEWAY),
+        };
+        Box::new(future::err((state, error)))
+    }
+
+    #[test]
+    fn maps_errors_to_responses() {
+        let middleware = ErrorMapMiddleware::new(DomainErrors);
+        let (chain, pipelines) = single_pipeline(new_pipeline().add(middleware).build());
+        let test_server = TestServer::new(build_router(chain, pipelines, |route| {
+            route.get("/*").to(handler);
+        }))
+        .unwrap();
+        let client = test_server.client();
+
+        let response = client.get("http://localhost/missing").perform().unwrap();
+        assert_eq!(response.status(), StatusCode::NOT_FOUND);
+        assert_eq!(
+            response.read_utf8_body().unwrap(),
+            r#"{"error":"no item 7"}"#
+        );
+
+        let response = client.get("http://localhost/conflict").perform().unwrap();
+        assert_eq!(response.status(), StatusCode::CONFLICT);
+
+        // errors the mapper doesn't know are left unchanged
+        let response = client.get("http://localhost/io").perform().unwrap();
+        assert_eq!(response.status(), StatusCode::BAD_GATEWAY);
+
+        let response = client
+            .get("http://localhost/unavailable")
+            .perform()
+            .unwrap();
+        assert_eq!(response.status(), StatusCode::SERVICE_UNAVAILABLE);
+    }
+}
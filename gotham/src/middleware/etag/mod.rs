@@ -0,0 +1,363 @@
+//! Entity tag middleware, which lets clients revalidate dynamic responses they have cached.
+use std::collections::hash_map::DefaultHasher;
+use std::hash::Hasher;
+use std::io;
+use std::mem;
+
+use futures::{future, stream, Future, Stream};
+use hyper::header::{
+    HeaderMap, HeaderValue, CACHE_CONTROL, CONTENT_ENCODING, CONTENT_LENGTH, CONTENT_TYPE, ETAG,
+    IF_NONE_MATCH, TRANSFER_ENCODING,
+};
+use hyper::{Body, Chunk, Method, Response, StatusCode};
+use log::trace;
+use mime::Mime;
+
+use crate::handler::{HandlerFuture, IntoHandlerError};
+use crate::middleware::{Middleware, NewMiddleware};
+use crate::state::{request_id, FromState, State};
+
+/// Middleware binding which adds a strong `ETag` header, computed over the body, to successful
+/// responses to `GET` and `HEAD` requests, and replaces the response with `304 Not Modified` when
+/// the request's `If-None-Match` header holds the same tag.
+///
+/// Producing the response still requires the handler to run, but a client which already holds the
+/// current representation doesn't download it again. To compute the tag, the body is read into
+/// memory, up to 1 MiB by default. Larger bodies are sent on unchanged without a tag, as are
+/// responses which already have an `ETag` header or are marked `Cache-Control: no-store`.
+/// `with_content_types` restricts the middleware to bodies of some content types, such as JSON.
+///
+/// # Examples
+///
+/// ```rust
+/// # extern crate gotham;
+/// # extern crate hyper;
+/// # extern crate mime;
+/// #
+/// # use hyper::header::{ETAG, IF_NONE_MATCH};
+/// # use hyper::StatusCode;
+/// # use gotham::middleware::etag::ETagMiddleware;
+/// # use gotham::pipeline::new_pipeline;
+/// # use gotham::pipeline::single::single_pipeline;
+/// # use gotham::router::builder::*;
+/// # use gotham::state::State;
+/// # use gotham::test::TestServer;
+/// #
+/// # fn handler(state: State) -> (State, (mime::Mime, &'static str)) {
+/// #     (state, (mime::APPLICATION_JSON, "{\"id\":1}"))
+/// # }
+/// #
+/// # fn main() {
+/// let middleware = ETagMiddleware::new()
+///     .with_max_size(64 * 1024)
+///     .with_content_types(vec![mime::APPLICATION_JSON]);
+///
+/// let (chain, pipelines) = single_pipeline(new_pipeline().add(middleware).build());
+/// let router = build_router(chain, pipelines, |route| {
+///     route.get("/").to(handler);
+/// });
+///
+/// let test_server = TestServer::new(router).unwrap();
+/// let client = test_server.client();
+///
+/// let response = client.get("http://localhost/").perform().unwrap();
+/// let etag = response.headers()[ETAG].clone();
+///
+/// let response = client
+///     .get("http://localhost/")
+///     .with_header(IF_NONE_MATCH, etag)
+///     .perform()
+///     .unwrap();
+/// assert_eq!(response.status(), StatusCode::NOT_MODIFIED);
+/// # }
+/// ```
+#[derive(Clone, Debug)]
+pub struct ETagMiddleware {
+    max_size: usize,
+    content_types: Option<Vec<Mime>>,
+}
+
+impl ETagMiddleware {
+    /// Creates an `ETagMiddleware` which tags bodies of any content type of up to 1 MiB.
+    pub fn new() -> ETagMiddleware {
+        ETagMiddleware {
+            max_size: 1024 * 1024,
+            content_types: None,
+        }
+    }
+
+    /// Changes the size of the largest body which is tagged, in bytes.
+    pub fn with_max_size(self, max_size: usize) -> ETagMiddleware {
+        ETagMiddleware { max_size, ..self }
+    }
+
+    /// Only tags bodies of one of `content_types`. Parameters such as `charset` are ignored, and a
+    /// wildcard such as `text/*` matches any subtype.
+    pub fn with_content_types(self, content_types: Vec<Mime>) -> ETagMiddleware {
+        ETagMiddleware {
+            content_types: Some(content_types),
+            ..self
+        }
+    }
+
+    fn is_eligible(&self, response: &Response<Body>) -> bool {
+        let headers = response.headers();
+        if response.status() != StatusCode::OK || headers.contains_key(ETAG) {
+            return false;
+        }
+
+        let no_store = headers
+            .get_all(CACHE_CONTROL)
+            .iter()
+            .filter_map(|value| value.to_str().ok())
+            .flat_map(|value| value.split(','))
+            .any(|directive| directive.trim().eq_ignore_ascii_case("no-store"));
+        if no_store {
+            return false;
+        }
+
+        let too_large = headers
+            .get(CONTENT_LENGTH)
+            .and_then(|value| value.to_str().ok())
+            .and_then(|value| value.parse::<u64>().ok())
+            .is_some_and(|len| len > self.max_size as u64);
+        if too_large {
+            return false;
+        }
+
+        match self.content_types {
+            None => true,
+            Some(ref content_types) => headers
+                .get(CONTENT_TYPE)
+                .and_then(|value| value.to_str().ok())
+                .and_then(|value| value.parse::<Mime>().ok())
+                .is_some_and(|mime| {
+                    content_types.iter().any(|allowed| {
+                        allowed.type_() == mime.type_()
+                            && (allowed.subtype() == mime::STAR
+                                || allowed.subtype() == mime.subtype())
+                    })
+                }),
+        }
+    }
+}
+
+impl Default for ETagMiddleware {
+    fn default() -> ETagMiddleware {
+        ETagMiddleware::new()
+    }
+}
+
+// The outcome of reading a body into memory, which stops once `max_size` is exceeded.
+enum Buffered {
+    Complete(Vec<u8>),
+    TooLarge(Vec<u8>, Body),
+}
+
+fn buffer(body: Body, max_size: usize) -> impl Future<Item = Buffered, Error = hyper::Error> {
+    future::loop_fn((Vec::new(), body), move |(mut buffered, body)| {
+        body.into_future()
+            .map_err(|(e, _)| e)
+            .map(move |(chunk, body)| match chunk {
+                None => future::Loop::Break(Buffered::Complete(buffered)),
+                Some(chunk) => {
+                    buffered.extend_from_slice(&chunk);
+                    if buffered.len() > max_size {
+                        future::Loop::Break(Buffered::TooLarge(buffered, body))
+                    } else {
+                        future::Loop::Continue((buffered, body))
+                    }
+                }
+            })
+    })
+}
+
+fn entity_tag(body: &[u8]) -> String {
+    let mut hasher = DefaultHasher::new();
+    hasher.write(body);
+    format!("\"{:x}-{:x}\"", body.len(), hasher.finish())
+}
+
+// Compares the tags of an 'If-None-Match' header with the weak comparison of RFC 7232, under
+// which a weak tag matches the strong tag with the same value.
+fn none_match(headers: &HeaderMap, etag: &str) -> bool {
+    headers
+        .get_all(IF_NONE_MATCH)
+        .iter()
+        .filter_map(|value| value.to_str().ok())
+        .flat_map(|value| value.split(','))
+        .map(str::trim)
+        .any(|tag| tag == "*" || tag.trim_start_matches("W/") == etag)
+}
+
+/// `Middleware` trait implementation.
+impl Middleware for ETagMiddleware {
+    /// Tags eligible responses, replacing those the client already holds with `304 Not Modified`.
+    fn call<Chain>(self, state: State, chain: Chain) -> Box<HandlerFuture>
+    where
+        Chain: FnOnce(State) -> Box<HandlerFuture>,
+    {
+        let method = Method::borrow_from(&state);
+        if method != Method::GET && method != Method::HEAD {
+            return chain(state);
+        }
+
+        let f = chain(state).and_then(move |(state, mut response)| {
+            if !self.is_eligible(&response) {
+                return future::Either::A(future::ok((state, response)));
+            }
+
+            let body = mem::replace(response.body_mut(), Body::empty());
+            let f = buffer(body, self.max_size).then(move |result| {
+                let (etag, body) = match result {
+                    Ok(Buffered::Complete(body)) => (entity_tag(&body), body),
+                    Ok(Buffered::TooLarge(buffered, rest)) => {
+                        trace!("[{}] body too large to tag", request_id(&state));
+                        let chunk = stream::once(Ok(Chunk::from(buffered)));
+                        *response.body_mut() = Body::wrap_stream(chunk.chain(rest));
+                        return Ok((state, response));
+                    }
+                    Err(e) => return Err((state, e.into_handler_error())),
+                };
+
+                // generated tags are always valid header values
+                if let Ok(value) = HeaderValue::from_str(&etag) {
+                    response.headers_mut().insert(ETAG, value);
+                }
+
+                if none_match(HeaderMap::borrow_from(&state), &etag) {
+                    trace!("[{}] client holds current entity", request_id(&state));
+                    *response.status_mut() = StatusCode::NOT_MODIFIED;
+                    let headers = response.headers_mut();
+                    for name in &[
+                        CONTENT_LENGTH,
+                        CONTENT_TYPE,
+                        CONTENT_ENCODING,
+                        TRANSFER_ENCODING,
+                    ] {
+                        headers.remove(name);
+                    }
+                } else {
+                    *response.body_mut() = Body::from(body);
+                }
+
+                Ok((state, response))
+            });
+
+            future::Either::B(f)
+        });
+
+        Box::new(f)
+    }
+}
+
+/// `NewMiddleware` trait implementation.
+impl NewMiddleware for ETagMiddleware {
+    type Instance = Self;
+
+    /// Clones the current middleware to a new instance.
+    fn new_middleware(&self) -> io::Result<Self::Instance> {
+        Ok(self.clone())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    use hyper::Uri;
+
+    use crate::helpers::http::response::create_response;
+    use crate::pipeline::new_pipeline;
+    use crate::pipeline::single::single_pipeline;
+    use crate::router::builder::*;
+    use crate::test::TestServer;
+
+    fn handler(state: State) -> (State, Response<Body>) {
+        let res = match Uri::borrow_from(&state).path() {
+            "/json" => create_response(&state, StatusCode::OK, mime::APPLICATION_JSON, "[1,2,3]"),
+            "/large" => create_response(&state, StatusCode::OK, mime::TEXT_PLAIN, vec![b'a'; 20]),
+            "/chunked" => {
+                let chunks = vec!["0123456789", "0123456789", "0123456789"];
+                let body = Body::wrap_stream(stream::iter_ok::<_, io::Error>(chunks));
+                Response::builder()
+                    .header(CONTENT_TYPE, "text/plain")
+                    .body(body)
+                    .unwrap()
+            }
+            _ => create_response(&state, StatusCode::OK, mime::TEXT_HTML, "<p>hi</p>"),
+        };
+        (state, res)
+    }
+
+    fn test_server() -> TestServer {
+        let middleware = ETagMiddleware::new()
+            .with_max_size(16)
+            .with_content_types(vec![mime::APPLICATION_JSON, mime::TEXT_STAR]);
+        let (chain, pipelines) = single_pipeline(new_pipeline().add(middleware).build());
+        TestServer::new(build_router(chain, pipelines, |route| {
+            route.get("/*").to(handler);
+        }))
+        .unwrap()
+    }
+
+    #[test]
+    fn tags_responses_and_answers_revalidation() {
+        let test_server = test_server();
+        let client = test_server.client();
+
+        let response = client.get("http://localhost/json").perform().unwrap();
+        assert_eq!(response.status(), StatusCode::OK);
+        let etag = response.headers()[ETAG].to_str().unwrap().to_owned();
+        assert!(etag.starts_with('"') && etag.ends_with('"'));
+        assert_eq!(response.read_utf8_body().unwrap(), "[1,2,3]");
+
+        let revalidate = |tags: String| {
+            client
+                .get("http://localhost/json")
+                .with_header(IF_NONE_MATCH, HeaderValue::from_str(&tags).unwrap())
+                .perform()
+                .unwrap()
+        };
+
+        let response = revalidate(format!("\"other\", W/{}", etag));
+        assert_eq!(response.status(), StatusCode::NOT_MODIFIED);
+        assert_eq!(response.headers()[ETAG], etag.as_str());
+        assert!(response.headers().get(CONTENT_TYPE).is_none());
+        assert_eq!(response.read_body().unwrap().len(), 0);
+
+        let response = revalidate("\"other\"".to_owned());
+        assert_eq!(response.status(), StatusCode::OK);
+        assert_eq!(response.read_utf8_body().unwrap(), "[1,2,3]");
+    }
+
+    #[test]
+    fn skips_ineligible_responses() {
+        let test_server = test_server();
+        let client = test_server.client();
+
+        let response = client.get("http://localhost/large").perform().unwrap();
+        assert!(response.headers().get(ETAG).is_none());
+        assert_eq!(response.read_body().unwrap().len(), 20);
+
+        let response = client.get("http://localhost/chunked").perform().unwrap();
+        assert!(response.headers().get(ETAG).is_none());
+        assert_eq!(response.read_body().unwrap().len(), 30);
+
+        let response = client.get("http://localhost/html").perform().unwrap();
+        assert!(response.headers().get(ETAG).is_some());
+
+        let middleware = ETagMiddleware::new().with_content_types(vec![mime::APPLICATION_JSON]);
+        let (chain, pipelines) = single_pipeline(new_pipeline().add(middleware).build());
+        let test_server = TestServer::new(build_router(chain, pipelines, |route| {
+            route.get("/*").to(handler);
+        }))
+        .unwrap();
+        let response = test_server
+            .client()
+            .get("http://localhost/html")
+            .perform()
+            .unwrap();
+        assert!(response.headers().get(ETAG).is_none());
+    }
+}
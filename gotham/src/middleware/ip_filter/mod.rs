@@ -0,0 +1,367 @@
+//! IP filter middleware, which only serves requests from some client addresses.
+use std::error::Error;
+use std::fmt::{self, Display};
+use std::io;
+use std::net::IpAddr;
+use std::str::FromStr;
+
+use futures::future;
+use hyper::header::{HeaderMap, HeaderName};
+use hyper::StatusCode;
+use log::trace;
+
+use crate::handler::HandlerFuture;
+use crate::helpers::http::response::create_empty_response;
+use crate::middleware::{Middleware, NewMiddleware};
+use crate::state::{client_addr, request_id, FromState, State};
+
+/// A range of IP addresses, written in CIDR notation such as `10.0.0.0/8` or `fd00::/8`. A lone
+/// address, such as `192.168.1.10`, is a range of that address alone.
+///
+/// IPv4 addresses mapped into IPv6, such as `::ffff:10.0.0.1`, are treated as the IPv4 address
+/// they map.
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub struct IpNetwork {
+    addr: IpAddr,
+    prefix_len: u8,
+}
+
+impl IpNetwork {
+    /// Creates the range of addresses which share the first `prefix_len` bits of `addr`.
+    pub fn new(addr: IpAddr, prefix_len: u8) -> Result<IpNetwork, IpNetworkError> {
+        let addr = canonical(addr);
+        let max = if addr.is_ipv4() { 32 } else { 128 };
+        if prefix_len > max {
+            return Err(IpNetworkError(format!("{}/{}", addr, prefix_len)));
+        }
+
+        Ok(IpNetwork { addr, prefix_len })
+    }
+
+    /// Checks whether `ip` is within the range.
+    pub fn contains(&self, ip: IpAddr) -> bool {
+        match (self.addr, canonical(ip)) {
+            (IpAddr::V4(net), IpAddr::V4(ip)) => {
+                let mask = u32::MAX
+                    .checked_shl(32 - u32::from(self.prefix_len))
+                    .unwrap_or(0);
+                u32::from(net) & mask == u32::from(ip) & mask
+            }
+            (IpAddr::V6(net), IpAddr::V6(ip)) => {
+                let mask = u128::MAX
+                    .checked_shl(128 - u32::from(self.prefix_len))
+                    .unwrap_or(0);
+                u128::from(net) & mask == u128::from(ip) & mask
+            }
+            _ => false,
+        }
+    }
+}
+
+impl FromStr for IpNetwork {
+    type Err = IpNetworkError;
+
+    fn from_str(s: &str) -> Result<IpNetwork, IpNetworkError> {
+        let invalid = || IpNetworkError(s.to_owned());
+        let (addr, prefix_len) = match s.find('/') {
+            Some(i) => (&s[..i], Some(&s[i + 1..])),
+            None => (s, None),
+        };
+
+        let addr = canonical(addr.parse::<IpAddr>().map_err(|_| invalid())?);
+        let prefix_len = match prefix_len {
+            Some(prefix_len) => prefix_len.parse::<u8>().map_err(|_| invalid())?,
+            None if addr.is_ipv4() => 32,
+            None => 128,
+        };
+
+        IpNetwork::new(addr, prefix_len).map_err(|_| invalid())
+    }
+}
+
+/// The error returned when an `IpNetwork` is not a valid range of addresses.
+#[derive(Clone, Debug, PartialEq)]
+pub struct IpNetworkError(String);
+
+impl Display for IpNetworkError {
+    fn fmt(&self, out: &mut fmt::Formatter) -> fmt::Result {
+        write!(out, "invalid IP network `{}`", self.0)
+    }
+}
+
+impl Error for IpNetworkError {}
+
+fn canonical(addr: IpAddr) -> IpAddr {
+    match addr {
+        IpAddr::V6(v6) => v6.to_ipv4_mapped().map_or(addr, IpAddr::V4),
+        IpAddr::V4(_) => addr,
+    }
+}
+
+fn any_contains(networks: &[IpNetwork], ip: IpAddr) -> bool {
+    networks.iter().any(|network| network.contains(ip))
+}
+
+/// Middleware binding which responds with `403 Forbidden` to requests from client IP addresses
+/// which aren't allowed.
+///
+/// A request is refused when its client address is within one of the ranges given to
+/// `with_denied`, or when `with_allowed` has been given ranges and the address is within none of
+/// them. Where the client address isn't known, the request is only served if there's no allow
+/// list.
+///
+/// The client address is the address of the connection, unless that is one of the proxies given
+/// to `with_trusted_proxies`. The `X-Forwarded-For` header of requests from a trusted proxy is
+/// then read from the right, skipping the addresses of further trusted proxies, and the first
+/// other address is taken to be the client. The header is ignored for requests from any other
+/// address, as clients can send any value in it. Where a trusted proxy forwards a malformed
+/// address, the client address isn't known.
+///
+/// # Examples
+///
+/// ```rust
+/// # extern crate gotham;
+/// # extern crate hyper;
+/// #
+/// # use hyper::StatusCode;
+/// # use gotham::middleware::ip_filter::IpFilterMiddleware;
+/// # use gotham::pipeline::new_pipeline;
+/// # use gotham::pipeline::single::single_pipeline;
+/// # use gotham::router::builder::*;
+/// # use gotham::state::State;
+/// # use gotham::test::TestServer;
+/// #
+/// # fn handler(state: State) -> (State, &'static str) {
+/// #     (state, "hello")
+/// # }
+/// #
+/// # fn main() {
+/// let internal = IpFilterMiddleware::new()
+///     .with_allowed(vec!["10.0.0.0/8".parse().unwrap(), "fd00::/8".parse().unwrap()])
+///     .with_trusted_proxies(vec!["127.0.0.1".parse().unwrap()]);
+///
+/// let (chain, pipelines) = single_pipeline(new_pipeline().add(internal).build());
+/// let router = build_router(chain, pipelines, |route| {
+///     route.scope("/admin", |route| {
+///         route.get("/").to(handler);
+///     });
+/// });
+///
+/// let test_server = TestServer::new(router).unwrap();
+/// let response = test_server
+///     .client()
+///     .get("http://localhost/admin")
+///     .perform()
+///     .unwrap();
+///
+/// assert_eq!(response.status(), StatusCode::FORBIDDEN);
+/// # }
+/// ```
+#[derive(Clone, Debug, Default)]
+pub struct IpFilterMiddleware {
+    allowed: Vec<IpNetwork>,
+    denied: Vec<IpNetwork>,
+    trusted_proxies: Vec<IpNetwork>,
+}
+
+impl IpFilterMiddleware {
+    /// Creates an `IpFilterMiddleware` which serves requests from any address.
+    pub fn new() -> IpFilterMiddleware {
+        IpFilterMiddleware::default()
+    }
+
+    /// Only serves requests from addresses within one of `allowed`.
+    pub fn with_allowed(self, allowed: Vec<IpNetwork>) -> IpFilterMiddleware {
+        IpFilterMiddleware { allowed, ..self }
+    }
+
+    /// Refuses requests from addresses within any of `denied`, even where they're also allowed.
+    pub fn with_denied(self, denied: Vec<IpNetwork>) -> IpFilterMiddleware {
+        IpFilterMiddleware { denied, ..self }
+    }
+
+    /// Reads the client address from the `X-Forwarded-For` header of requests from addresses
+    /// within one of `trusted_proxies`.
+    pub fn with_trusted_proxies(self, trusted_proxies: Vec<IpNetwork>) -> IpFilterMiddleware {
+        IpFilterMiddleware {
+            trusted_proxies,
+            ..self
+        }
+    }
+
+    fn client_ip(&self, state: &State) -> Option<IpAddr> {
+        let mut ip = client_addr(state)?.ip();
+        let forwarded = HeaderMap::borrow_from(state)
+            .get_all(HeaderName::from_static("x-forwarded-for"))
+            .iter()
+            .filter_map(|value| value.to_str().ok())
+            .flat_map(|value| value.split(','))
+            .map(str::trim)
+            .collect::<Vec<_>>();
+
+        for hop in forwarded.into_iter().rev() {
+            if !any_contains(&self.trusted_proxies, ip) {
+                break;
+            }
+
+            // a trusted proxy only forwards addresses, so the client is unknown where the hop it
+            // added is malformed, rather than taken to be the proxy itself
+            match hop.parse() {
+                Ok(hop) => ip = hop,
+                Err(_) => return None,
+            }
+        }
+
+        Some(ip)
+    }
+
+    fn is_allowed(&self, ip: Option<IpAddr>) -> bool {
+        match ip {
+            Some(ip) if any_contains(&self.denied, ip) => false,
+            Some(ip) => self.allowed.is_empty() || any_contains(&self.allowed, ip),
+            None => self.allowed.is_empty(),
+        }
+    }
+}
+
+/// `Middleware` trait implementation.
+impl Middleware for IpFilterMiddleware {
+    /// Refuses requests from addresses which aren't allowed.
+    fn call<Chain>(self, state: State, chain: Chain) -> Box<HandlerFuture>
+    where
+        Chain: FnOnce(State) -> Box<HandlerFuture>,
+    {
+        let ip = self.client_ip(&state);
+        if self.is_allowed(ip) {
+            return chain(state);
+        }
+
+        trace!("[{}] refusing request from {:?}", request_id(&state), ip);
+        let res = create_empty_response(&state, StatusCode::FORBIDDEN);
+        Box::new(future::ok((state, res)))
+    }
+}
+
+/// `NewMiddleware` trait implementation.
+impl NewMiddleware for IpFilterMiddleware {
+    type Instance = Self;
+
+    /// Clones the current middleware to a new instance.
+    fn new_middleware(&self) -> io::Result<Self::Instance> {
+        Ok(self.clone())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    use hyper::header::HeaderValue;
+
+    use crate::pipeline::new_pipeline;
+    use crate::pipeline::single::single_pipeline;
+    use crate::router::builder::*;
+    use crate::test::TestServer;
+
+    fn handler(state: State) -> (State, &'static str) {
+        (state, "hello")
+    }
+
+    fn networks(networks: &[&str]) -> Vec<IpNetwork> {
+        networks.iter().map(|n| n.parse().unwrap()).collect()
+    }
+
+    fn status(middleware: IpFilterMiddleware, forwarded_for: Option<&'static str>) -> StatusCode {
+        let (chain, pipelines) = single_pipeline(new_pipeline().add(middleware).build());
+        let test_server = TestServer::new(build_router(chain, pipelines, |route| {
+            route.get("/").to(handler);
+        }))
+        .unwrap();
+
+        let client = test_server.client();
+        let mut req = client.get("http://localhost/");
+        if let Some(forwarded_for) = forwarded_for {
+            req = req.with_header("x-forwarded-for", HeaderValue::from_static(forwarded_for));
+        }
+        req.perform().unwrap().status()
+    }
+
+    #[test]
+    fn parses_networks() {
+        let network: IpNetwork = "10.0.0.0/8".parse().unwrap();
+        assert!(network.contains("10.20.30.40".parse().unwrap()));
+        assert!(network.contains("::ffff:10.0.0.1".parse().unwrap()));
+        assert!(!network.contains("11.0.0.1".parse().unwrap()));
+
+        let network: IpNetwork = "fd00::/8".parse().unwrap();
+        assert!(network.contains("fd12::1".parse().unwrap()));
+        assert!(!network.contains("fe80::1".parse().unwrap()));
+        assert!(!network.contains("10.0.0.1".parse().unwrap()));
+
+        let network: IpNetwork = "192.168.1.10".parse().unwrap();
+        assert!(network.contains("192.168.1.10".parse().unwrap()));
+        assert!(!network.contains("192.168.1.11".parse().unwrap()));
+
+        let network: IpNetwork = "0.0.0.0/0".parse().unwrap();
+        assert!(network.contains("203.0.113.1".parse().unwrap()));
+
+        assert!("10.0.0.0/33".parse::<IpNetwork>().is_err());
+        assert!("10.0.0/8".parse::<IpNetwork>().is_err());
+        assert_eq!(
+            "::/129".parse::<IpNetwork>().unwrap_err().to_string(),
+            "invalid IP network `::/129`"
+        );
+    }
+
+    #[test]
+    fn filters_by_connection_address() {
+        let local = || networks(&["127.0.0.0/8", "::1"]);
+
+        assert_eq!(status(IpFilterMiddleware::new(), None), StatusCode::OK);
+        let allowed = IpFilterMiddleware::new().with_allowed(local());
+        assert_eq!(status(allowed, None), StatusCode::OK);
+
+        let allowed = IpFilterMiddleware::new().with_allowed(networks(&["10.0.0.0/8"]));
+        assert_eq!(status(allowed, None), StatusCode::FORBIDDEN);
+
+        let denied = IpFilterMiddleware::new()
+            .with_allowed(local())
+            .with_denied(networks(&["127.0.0.1"]));
+        assert_eq!(status(denied, None), StatusCode::FORBIDDEN);
+    }
+
+    #[test]
+    fn trusts_forwarded_for_from_proxies() {
+        let internal = || IpFilterMiddleware::new().with_allowed(networks(&["10.0.0.0/8"]));
+
+        // an untrusted client can't claim another address
+        assert_eq!(status(internal(), Some("10.1.2.3")), StatusCode::FORBIDDEN);
+
+        let proxied = || internal().with_trusted_proxies(networks(&["127.0.0.1", "172.16.0.0/12"]));
+        assert_eq!(status(proxied(), Some("10.1.2.3")), StatusCode::OK);
+        assert_eq!(
+            status(proxied(), Some("203.0.113.1, 10.1.2.3, 172.16.0.5")),
+            StatusCode::OK
+        );
+        assert_eq!(
+            status(proxied(), Some("10.1.2.3, 203.0.113.1")),
+            StatusCode::FORBIDDEN
+        );
+
+        // a malformed hop leaves the client unknown, rather than judged as the proxy
+        let proxy_allowed = || {
+            IpFilterMiddleware::new()
+                .with_allowed(networks(&["127.0.0.1"]))
+                .with_trusted_proxies(networks(&["127.0.0.1"]))
+        };
+        assert_eq!(
+            status(proxy_allowed(), Some("garbage")),
+            StatusCode::FORBIDDEN
+        );
+        assert_eq!(
+            status(proxy_allowed(), Some("10.1.2.3, garbage")),
+            StatusCode::FORBIDDEN
+        );
+        assert_eq!(status(proxy_allowed(), Some("127.0.0.1")), StatusCode::OK);
+    }
+}
@@ -2,17 +2,22 @@
 //!
 //! This module contains several logging implementations, with varying degrees
 //! of complexity. The default `RequestLogger` will log out using the standard
-//! [Common Log Format](https://en.wikipedia.org/wiki/Common_Log_Format) (CLF).
+//! [Common Log Format](https://en.wikipedia.org/wiki/Common_Log_Format) (CLF), or as JSON
+//! objects, as configured by `LogFormat`.
 //!
 //! There is also a `SimpleLogger` which emits only basic request logs.
+use chrono::{DateTime, Utc};
 use futures::{future, Future};
-use hyper::{header::CONTENT_LENGTH, Method, Uri, Version};
+use hyper::body::Payload;
+use hyper::{header::CONTENT_LENGTH, Body, Method, Response, Uri, Version};
 use log::Level;
 use log::{log, log_enabled};
+use serde_json::json;
 use std::io;
+use std::net::IpAddr;
 
 use crate::handler::HandlerFuture;
-use crate::helpers::timing::Timer;
+use crate::helpers::timing::{Timer, Timing};
 use crate::middleware::{Middleware, NewMiddleware};
 use crate::state::request_id::request_id;
 use crate::state::{client_addr, FromState, State};
@@ -24,12 +29,32 @@ use crate::state::{client_addr, FromState, State};
 #[derive(Copy, Clone)]
 pub struct RequestLogger {
     level: Level,
+    format: LogFormat,
+}
+
+/// The format of the entries logged by `RequestLogger`.
+#[derive(Copy, Clone, Debug, PartialEq)]
+pub enum LogFormat {
+    /// The Common Log Format, followed by the time taken to respond. This is the default.
+    Common,
+    /// A JSON object for each request, with the fields `remote_addr`, `time`, `request_id`,
+    /// `method`, `path`, `query`, `version`, `status`, `size` and `latency_us`. Fields which are
+    /// unknown, such as the size of a streamed response, are `null`.
+    Json,
 }
 
 impl RequestLogger {
     /// Constructs a new `RequestLogger` instance.
     pub fn new(level: Level) -> Self {
-        RequestLogger { level }
+        RequestLogger {
+            level,
+            format: LogFormat::Common,
+        }
+    }
+
+    /// Changes the format of the entries logged, from the Common Log Format.
+    pub fn with_format(self, format: LogFormat) -> Self {
+        RequestLogger { format, ..self }
     }
 }
 
@@ -63,39 +88,14 @@ impl Middleware for RequestLogger {
 
         // hook onto the end of the request to log the access
         let f = chain(state).and_then(move |(state, response)| {
-            // format the start time to the CLF formats
-            let datetime = timer.start_time().format("%d/%b/%Y:%H:%M:%S %z");
-
-            // grab the ip address from the state
-            let ip = client_addr(&state).unwrap().ip();
-
             {
-                // borrows from the state
-                let path = Uri::borrow_from(&state);
-                let method = Method::borrow_from(&state);
-                let version = Version::borrow_from(&state);
-
-                // take references based on the response
-                let status = response.status().as_u16();
-                let length = response
-                    .headers()
-                    .get(CONTENT_LENGTH)
-                    .map(|len| len.to_str().unwrap())
-                    .unwrap_or("0");
+                let entry = LogEntry::new(&state, &response, &timer);
 
                 // log out
-                log!(
-                    self.level,
-                    "{} - - [{}] \"{} {} {:?}\" {} {} - {}",
-                    ip,
-                    datetime,
-                    method,
-                    path,
-                    version,
-                    status,
-                    length,
-                    timer.elapsed()
-                );
+                match self.format {
+                    LogFormat::Common => log!(self.level, "{}", entry.common()),
+                    LogFormat::Json => log!(self.level, "{}", entry.json()),
+                }
             }
 
             // continue the response chain
@@ -107,6 +107,80 @@ impl Middleware for RequestLogger {
     }
 }
 
+/// The details of a request logged by `RequestLogger`, borrowed from the `State` and response.
+struct LogEntry<'a> {
+    remote_addr: Option<IpAddr>,
+    time: &'a DateTime<Utc>,
+    request_id: &'a str,
+    method: &'a Method,
+    uri: &'a Uri,
+    version: &'a Version,
+    status: u16,
+    size: Option<u64>,
+    elapsed: Timing,
+}
+
+impl<'a> LogEntry<'a> {
+    fn new(state: &'a State, response: &Response<Body>, timer: &'a Timer) -> Self {
+        // a streamed response only declares its length where it's known up front
+        let size = response
+            .headers()
+            .get(CONTENT_LENGTH)
+            .and_then(|len| len.to_str().ok())
+            .and_then(|len| len.parse().ok())
+            .or_else(|| response.body().content_length());
+
+        LogEntry {
+            remote_addr: client_addr(state).map(|addr| addr.ip()),
+            time: timer.start_time(),
+            request_id: request_id(state),
+            method: Method::borrow_from(state),
+            uri: Uri::borrow_from(state),
+            version: Version::borrow_from(state),
+            status: response.status().as_u16(),
+            size,
+            elapsed: timer.elapsed(),
+        }
+    }
+
+    fn common(&self) -> String {
+        format!(
+            "{} - - [{}] \"{} {} {:?}\" {} {} - {}",
+            self.remote_addr
+                .map(|ip| ip.to_string())
+                .unwrap_or_else(|| "-".to_owned()),
+            self.time.format("%d/%b/%Y:%H:%M:%S %z"),
+            self.method,
+            self.uri,
+            self.version,
+            self.status,
+            self.size.unwrap_or(0),
+            self.elapsed
+        )
+    }
+
+    fn json(&self) -> String {
+        let latency = match self.elapsed {
+            Timing::Microseconds(us) => Some(us),
+            Timing::Invalid => None,
+        };
+
+        json!({
+            "remote_addr": self.remote_addr.map(|ip| ip.to_string()),
+            "time": self.time.to_rfc3339(),
+            "request_id": self.request_id,
+            "method": self.method.as_str(),
+            "path": self.uri.path(),
+            "query": self.uri.query(),
+            "version": format!("{:?}", self.version),
+            "status": self.status,
+            "size": self.size,
+            "latency_us": latency,
+        })
+        .to_string()
+    }
+}
+
 /// A struct that can act as a simple logging middleware for Gotham.
 ///
 /// We implement `NewMiddleware` here for Gotham to allow us to work with the request
@@ -168,3 +242,78 @@ impl Middleware for SimpleLogger {
         Box::new(f)
     }
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    use chrono::TimeZone;
+    use serde_json::Value;
+
+    fn entry<'a>(time: &'a DateTime<Utc>, method: &'a Method, uri: &'a Uri) -> LogEntry<'a> {
+        LogEntry {
+            remote_addr: Some("127.0.0.1".parse().unwrap()),
+            time,
+            request_id: "4b8e3c2d",
+            method,
+            uri,
+            version: &Version::HTTP_11,
+            status: 200,
+            size: Some(1234),
+            elapsed: Timing::Microseconds(1500),
+        }
+    }
+
+    #[test]
+    fn formats_common_log_entries() {
+        let time = Utc.with_ymd_and_hms(2019, 10, 2, 13, 55, 36).unwrap();
+        let uri = "/widgets?page=2".parse().unwrap();
+        let entry = entry(&time, &Method::GET, &uri);
+
+        assert_eq!(
+            entry.common(),
+            "127.0.0.1 - - [02/Oct/2019:13:55:36 +0000] \"GET /widgets?page=2 HTTP/1.1\" 200 1234 - 1.50ms"
+        );
+
+        let entry = LogEntry {
+            remote_addr: None,
+            size: None,
+            ..entry
+        };
+        assert!(entry.common().starts_with("- - - ["));
+        assert!(entry.common().contains(" 200 0 - "));
+    }
+
+    #[test]
+    fn formats_json_log_entries() {
+        let time = Utc.with_ymd_and_hms(2019, 10, 2, 13, 55, 36).unwrap();
+        let uri = "/widgets?page=2".parse().unwrap();
+        let entry = entry(&time, &Method::POST, &uri);
+
+        let value: Value = serde_json::from_str(&entry.json()).unwrap();
+        assert_eq!(
+            value,
+            json!({
+                "remote_addr": "127.0.0.1",
+                "time": "2019-10-02T13:55:36+00:00",
+                "request_id": "4b8e3c2d",
+                "method": "POST",
+                "path": "/widgets",
+                "query": "page=2",
+                "version": "HTTP/1.1",
+                "status": 200,
+                "size": 1234,
+                "latency_us": 1500,
+            })
+        );
+
+        let entry = LogEntry {
+            size: None,
+            elapsed: Timing::Invalid,
+            ..entry
+        };
+        let value: Value = serde_json::from_str(&entry.json()).unwrap();
+        assert_eq!(value["size"], Value::Null);
+        assert_eq!(value["latency_us"], Value::Null);
+    }
+}
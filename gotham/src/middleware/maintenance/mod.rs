@@ -0,0 +1,216 @@
+//! Maintenance mode middleware, which stops serving requests while the application is under
+//! maintenance, without restarting it.
+use std::io;
+use std::panic::RefUnwindSafe;
+use std::sync::atomic::{AtomicBool, Ordering};
+use std::sync::Arc;
+use std::time::Duration;
+
+use futures::future;
+use hyper::header::{HeaderValue, RETRY_AFTER};
+use hyper::StatusCode;
+use log::trace;
+
+use crate::handler::HandlerFuture;
+use crate::helpers::http::response::create_empty_response;
+use crate::middleware::conditional::path_prefix;
+use crate::middleware::{Middleware, NewMiddleware};
+use crate::state::{request_id, State};
+
+type Predicate = dyn Fn(&State) -> bool + Send + Sync + RefUnwindSafe;
+
+/// A handle which turns maintenance mode on and off while the application runs.
+///
+/// Clones of a switch share its state, so a clone kept by an administrative route, a signal
+/// handler or a task watching a file can turn maintenance mode on for every
+/// `MaintenanceMiddleware` created from the switch. Maintenance mode is off initially.
+#[derive(Clone, Debug, Default)]
+pub struct MaintenanceSwitch {
+    enabled: Arc<AtomicBool>,
+}
+
+impl MaintenanceSwitch {
+    /// Creates a switch with maintenance mode off.
+    pub fn new() -> MaintenanceSwitch {
+        MaintenanceSwitch::default()
+    }
+
+    /// Turns maintenance mode on.
+    pub fn enable(&self) {
+        self.enabled.store(true, Ordering::SeqCst);
+    }
+
+    /// Turns maintenance mode off.
+    pub fn disable(&self) {
+        self.enabled.store(false, Ordering::SeqCst);
+    }
+
+    /// Checks whether maintenance mode is on.
+    pub fn is_enabled(&self) -> bool {
+        self.enabled.load(Ordering::SeqCst)
+    }
+}
+
+/// Middleware binding which responds with `503 Service Unavailable` and a `Retry-After` header to
+/// requests while its `MaintenanceSwitch` is enabled, without invoking the rest of the pipeline
+/// chain or the handler.
+///
+/// Clients are asked to retry after 60 seconds by default. Paths given to `with_allowed_paths`,
+/// such as health checks and the route which turns the switch off again, are served as usual
+/// during maintenance.
+///
+/// # Examples
+///
+/// ```rust
+/// # extern crate gotham;
+/// # extern crate hyper;
+/// #
+/// # use std::time::Duration;
+/// # use hyper::StatusCode;
+/// # use gotham::middleware::maintenance::{MaintenanceMiddleware, MaintenanceSwitch};
+/// # use gotham::pipeline::new_pipeline;
+/// # use gotham::pipeline::single::single_pipeline;
+/// # use gotham::router::builder::*;
+/// # use gotham::state::State;
+/// # use gotham::test::TestServer;
+/// #
+/// # fn handler(state: State) -> (State, &'static str) {
+/// #     (state, "hello")
+/// # }
+/// #
+/// # fn main() {
+/// let switch = MaintenanceSwitch::new();
+/// let middleware = MaintenanceMiddleware::new(switch.clone())
+///     .with_retry_after(Duration::from_secs(300))
+///     .with_allowed_paths(vec!["/health"]);
+///
+/// let (chain, pipelines) = single_pipeline(new_pipeline().add(middleware).build());
+/// let router = build_router(chain, pipelines, |route| {
+///     route.get("/").to(handler);
+///     route.get("/health").to(handler);
+/// });
+///
+/// let test_server = TestServer::new(router).unwrap();
+/// let client = test_server.client();
+///
+/// switch.enable();
+/// let response = client.get("http://localhost/").perform().unwrap();
+/// assert_eq!(response.status(), StatusCode::SERVICE_UNAVAILABLE);
+/// assert_eq!(response.headers()["Retry-After"], "300");
+///
+/// let response = client.get("http://localhost/health").perform().unwrap();
+/// assert_eq!(response.status(), StatusCode::OK);
+/// # }
+/// ```
+#[derive(Clone)]
+pub struct MaintenanceMiddleware {
+    switch: MaintenanceSwitch,
+    retry_after: Duration,
+    allowed: Vec<Arc<Predicate>>,
+}
+
+impl MaintenanceMiddleware {
+    /// Creates a `MaintenanceMiddleware` which refuses requests while `switch` is enabled.
+    pub fn new(switch: MaintenanceSwitch) -> MaintenanceMiddleware {
+        MaintenanceMiddleware {
+            switch,
+            retry_after: Duration::from_secs(60),
+            allowed: vec![],
+        }
+    }
+
+    /// Changes the time clients are asked to wait before retrying, which is sent in whole
+    /// seconds.
+    pub fn with_retry_after(self, retry_after: Duration) -> MaintenanceMiddleware {
+        MaintenanceMiddleware {
+            retry_after,
+            ..self
+        }
+    }
+
+    /// Serves requests for `paths`, and the paths below them, during maintenance.
+    pub fn with_allowed_paths(self, paths: Vec<&str>) -> MaintenanceMiddleware {
+        let allowed = paths
+            .into_iter()
+            .map(|path| Arc::new(path_prefix(path)) as Arc<Predicate>)
+            .collect();
+        MaintenanceMiddleware { allowed, ..self }
+    }
+}
+
+/// `Middleware` trait implementation.
+impl Middleware for MaintenanceMiddleware {
+    /// Refuses requests for paths which aren't allowed while maintenance mode is on.
+    fn call<Chain>(self, state: State, chain: Chain) -> Box<HandlerFuture>
+    where
+        Chain: FnOnce(State) -> Box<HandlerFuture>,
+    {
+        if !self.switch.is_enabled() || self.allowed.iter().any(|allowed| allowed(&state)) {
+            return chain(state);
+        }
+
+        trace!(
+            "[{}] refusing request during maintenance",
+            request_id(&state)
+        );
+        let mut res = create_empty_response(&state, StatusCode::SERVICE_UNAVAILABLE);
+        res.headers_mut()
+            .insert(RETRY_AFTER, HeaderValue::from(self.retry_after.as_secs()));
+        Box::new(future::ok((state, res)))
+    }
+}
+
+/// `NewMiddleware` trait implementation.
+impl NewMiddleware for MaintenanceMiddleware {
+    type Instance = Self;
+
+    /// Clones the current middleware to a new instance.
+    fn new_middleware(&self) -> io::Result<Self::Instance> {
+        Ok(self.clone())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    use crate::pipeline::new_pipeline;
+    use crate::pipeline::single::single_pipeline;
+    use crate::router::builder::*;
+    use crate::test::TestServer;
+
+    fn handler(state: State) -> (State, &'static str) {
+        (state, "hello")
+    }
+
+    #[test]
+    fn refuses_requests_during_maintenance() {
+        let switch = MaintenanceSwitch::new();
+        let middleware = MaintenanceMiddleware::new(switch.clone())
+            .with_allowed_paths(vec!["/health", "/admin/maintenance"]);
+        let (chain, pipelines) = single_pipeline(new_pipeline().add(middleware).build());
+        let test_server = TestServer::new(build_router(chain, pipelines, |route| {
+            route.get("/*").to(handler);
+        }))
+        .unwrap();
+        let client = test_server.client();
+        let status = |path: &str| {
+            let uri = format!("http://localhost{}", path);
+            client.get(uri).perform().unwrap().status()
+        };
+
+        assert!(!switch.is_enabled());
+        assert_eq!(status("/users"), StatusCode::OK);
+
+        switch.enable();
+        let response = client.get("http://localhost/users").perform().unwrap();
+        assert_eq!(response.status(), StatusCode::SERVICE_UNAVAILABLE);
+        assert_eq!(response.headers()[RETRY_AFTER], "60");
+        assert_eq!(status("/health"), StatusCode::OK);
+        assert_eq!(status("/admin/maintenance/off"), StatusCode::OK);
+        assert_eq!(status("/admin"), StatusCode::SERVICE_UNAVAILABLE);
+
+        switch.disable();
+        assert_eq!(status("/users"), StatusCode::OK);
+    }
+}
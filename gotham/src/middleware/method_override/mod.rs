@@ -0,0 +1,299 @@
+//! Method override middleware, which lets clients that can only send `GET` and `POST` requests,
+//! such as HTML forms, make requests of other methods.
+use std::io;
+
+use futures::{future, Future, Stream};
+use hyper::header::{HeaderMap, CONTENT_TYPE};
+use hyper::{Body, Method, Uri};
+use log::trace;
+
+use crate::handler::{HandlerFuture, IntoHandlerError};
+use crate::helpers::http::FormUrlDecoded;
+use crate::middleware::{Middleware, NewMiddleware};
+use crate::router::forward::forward;
+use crate::state::{request_id, FromState, State};
+
+// The form field naming the method, as used by HTML forms in many frameworks.
+const METHOD_FIELD: &str = "_method";
+
+// The header naming the method, as read by `RouterBuilder::method_override`.
+const METHOD_OVERRIDE_HEADER: &str = "x-http-method-override";
+
+/// Middleware binding which serves a `POST` request as a request of another method, named by
+/// its `X-HTTP-Method-Override` header or the `_method` field of its
+/// `application/x-www-form-urlencoded` body.
+///
+/// Only `PUT`, `PATCH` and `DELETE` are allowed by default, and `with_methods` changes which
+/// methods a request may be overridden to. Names of other methods are ignored, and the request is
+/// handled as a `POST` request. The header takes precedence over the form field, and the body of a
+/// request is left in `State` for the handler after the field has been read.
+///
+/// The pipelines of a route run once the route has been matched, so the middleware changes the
+/// method in `State` and forwards the request to the same path, where it's routed again by its
+/// new method. The path must have a `POST` route, with the middleware in its pipelines, for the
+/// middleware to receive the request. Where clients only send the header,
+/// `RouterBuilder::method_override` applies the override before the request is routed instead.
+///
+/// # Examples
+///
+/// ```rust
+/// # extern crate gotham;
+/// # extern crate hyper;
+/// # extern crate mime;
+/// #
+/// # use hyper::Method;
+/// # use gotham::middleware::method_override::MethodOverrideMiddleware;
+/// # use gotham::pipeline::new_pipeline;
+/// # use gotham::pipeline::single::single_pipeline;
+/// # use gotham::router::builder::*;
+/// # use gotham::state::State;
+/// # use gotham::test::TestServer;
+/// #
+/// # fn create_comment(state: State) -> (State, &'static str) {
+/// #     (state, "created")
+/// # }
+/// #
+/// # fn delete_comment(state: State) -> (State, &'static str) {
+/// #     (state, "deleted")
+/// # }
+/// #
+/// # fn main() {
+/// let middleware = MethodOverrideMiddleware::new().with_methods(vec![Method::DELETE]);
+///
+/// let (chain, pipelines) = single_pipeline(new_pipeline().add(middleware).build());
+/// let router = build_router(chain, pipelines, |route| {
+///     route.post("/comments/:id").to(create_comment);
+///     route.delete("/comments/:id").to(delete_comment);
+/// });
+///
+/// let test_server = TestServer::new(router).unwrap();
+/// let response = test_server
+///     .client()
+///     .post(
+///         "http://localhost/comments/1",
+///         "_method=DELETE",
+///         mime::APPLICATION_WWW_FORM_URLENCODED,
+///     )
+///     .perform()
+///     .unwrap();
+///
+/// assert_eq!(response.read_utf8_body().unwrap(), "deleted");
+/// # }
+/// ```
+#[derive(Clone, Debug)]
+pub struct MethodOverrideMiddleware {
+    methods: Vec<Method>,
+}
+
+impl MethodOverrideMiddleware {
+    /// Creates a `MethodOverrideMiddleware` which allows requests to be overridden to `PUT`,
+    /// `PATCH` and `DELETE`.
+    pub fn new() -> MethodOverrideMiddleware {
+        MethodOverrideMiddleware {
+            methods: vec![Method::PUT, Method::PATCH, Method::DELETE],
+        }
+    }
+
+    /// Changes the methods which requests may be overridden to. `POST` is ignored, as the request
+    /// is already a `POST` request.
+    pub fn with_methods(self, methods: Vec<Method>) -> MethodOverrideMiddleware {
+        let methods = methods.into_iter().filter(|m| *m != Method::POST).collect();
+        MethodOverrideMiddleware { methods }
+    }
+
+    fn allowed(&self, name: &str) -> Option<Method> {
+        let method = Method::from_bytes(name.trim().to_ascii_uppercase().as_bytes()).ok()?;
+        if self.methods.contains(&method) {
+            Some(method)
+        } else {
+            None
+        }
+    }
+}
+
+impl Default for MethodOverrideMiddleware {
+    fn default() -> MethodOverrideMiddleware {
+        MethodOverrideMiddleware::new()
+    }
+}
+
+fn is_form(headers: &HeaderMap) -> bool {
+    headers
+        .get(CONTENT_TYPE)
+        .and_then(|value| value.to_str().ok())
+        .and_then(|value| value.parse::<mime::Mime>().ok())
+        .is_some_and(|mime| mime.essence_str() == mime::APPLICATION_WWW_FORM_URLENCODED)
+}
+
+fn method_field(body: &[u8]) -> Option<String> {
+    String::from_utf8_lossy(body)
+        .split('&')
+        .filter_map(|pair| {
+            let mut parts = pair.splitn(2, '=');
+            let name = FormUrlDecoded::decode(parts.next()?, true)?;
+            let value = FormUrlDecoded::decode(parts.next().unwrap_or(""), true)?;
+            Some((name, value))
+        })
+        .find(|(name, _)| name.as_ref() == METHOD_FIELD)
+        .map(|(_, value)| value.as_ref().to_owned())
+}
+
+// Changes the method of the request and routes it again, to the route of the new method.
+fn override_method(mut state: State, method: Method) -> Box<HandlerFuture> {
+    trace!(
+        "[{}] forwarding POST request as {} for method override",
+        request_id(&state),
+        method
+    );
+
+    let path = Uri::borrow_from(&state)
+        .path_and_query()
+        .map(|path| path.as_str().to_owned())
+        .unwrap_or_else(|| "/".to_owned());
+    state.put(method);
+
+    Box::new(future::ok(forward(state, &path)))
+}
+
+/// `Middleware` trait implementation.
+impl Middleware for MethodOverrideMiddleware {
+    /// Forwards `POST` requests which name an allowed method as requests of that method.
+    fn call<Chain>(self, mut state: State, chain: Chain) -> Box<HandlerFuture>
+    where
+        Chain: FnOnce(State) -> Box<HandlerFuture> + Send + 'static,
+    {
+        if *Method::borrow_from(&state) != Method::POST {
+            return chain(state);
+        }
+
+        let headers = HeaderMap::borrow_from(&state);
+        let header = headers
+            .get(METHOD_OVERRIDE_HEADER)
+            .and_then(|value| value.to_str().ok())
+            .and_then(|value| self.allowed(value));
+
+        if let Some(method) = header {
+            return override_method(state, method);
+        }
+
+        if !is_form(headers) {
+            return chain(state);
+        }
+
+        let f = Body::take_from(&mut state)
+            .concat2()
+            .then(move |result| match result {
+                Ok(body) => {
+                    let method = method_field(&body).and_then(|name| self.allowed(&name));
+                    state.put(Body::from(body));
+                    match method {
+                        Some(method) => override_method(state, method),
+                        None => chain(state),
+                    }
+                }
+                Err(e) => Box::new(future::err((state, e.into_handler_error()))),
+            });
+
+        Box::new(f)
+    }
+}
+
+/// `NewMiddleware` trait implementation.
+impl NewMiddleware for MethodOverrideMiddleware {
+    type Instance = Self;
+
+    /// Clones the current middleware to a new instance.
+    fn new_middleware(&self) -> io::Result<Self::Instance> {
+        Ok(self.clone())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    use hyper::header::HeaderValue;
+    use hyper::StatusCode;
+
+    use crate::helpers::http::response::create_response;
+    use crate::pipeline::new_pipeline;
+    use crate::pipeline::single::single_pipeline;
+    use crate::router::builder::*;
+    use crate::test::TestServer;
+
+    // Responds with the method and the body of the request, to show what reached the handler.
+    fn echo(mut state: State) -> Box<HandlerFuture> {
+        let f = Body::take_from(&mut state).concat2().then(move |result| {
+            let body = result.unwrap();
+            let text = format!(
+                "{} {}",
+                Method::borrow_from(&state),
+                String::from_utf8_lossy(&body)
+            );
+            let res = create_response(&state, StatusCode::OK, mime::TEXT_PLAIN, text);
+            Ok((state, res))
+        });
+        Box::new(f)
+    }
+
+    fn test_server() -> TestServer {
+        let middleware = MethodOverrideMiddleware::new().with_methods(vec![Method::DELETE]);
+        let (chain, pipelines) = single_pipeline(new_pipeline().add(middleware).build());
+        TestServer::new(build_router(chain, pipelines, |route| {
+            route
+                .request(vec![Method::POST, Method::DELETE, Method::PUT], "/items")
+                .to(echo);
+        }))
+        .unwrap()
+    }
+
+    fn post(test_server: &TestServer, body: &'static str, header: Option<&'static str>) -> String {
+        let client = test_server.client();
+        let mut req = client.post(
+            "http://localhost/items?page=2",
+            body,
+            mime::APPLICATION_WWW_FORM_URLENCODED,
+        );
+        if let Some(header) = header {
+            req = req.with_header(METHOD_OVERRIDE_HEADER, HeaderValue::from_static(header));
+        }
+
+        let response = req.perform().unwrap();
+        assert_eq!(response.status(), StatusCode::OK);
+        response.read_utf8_body().unwrap()
+    }
+
+    #[test]
+    fn overrides_by_form_field() {
+        let test_server = test_server();
+
+        assert_eq!(
+            post(&test_server, "name=a+b&_method=delete", None),
+            "DELETE name=a+b&_method=delete"
+        );
+        assert_eq!(post(&test_server, "name=a", None), "POST name=a");
+
+        // methods which aren't allowed are ignored
+        assert_eq!(post(&test_server, "_method=PUT", None), "POST _method=PUT");
+    }
+
+    #[test]
+    fn overrides_by_header() {
+        let test_server = test_server();
+
+        assert_eq!(post(&test_server, "a=1", Some("DELETE")), "DELETE a=1");
+        assert_eq!(post(&test_server, "a=1", Some("PUT")), "POST a=1");
+
+        // only POST requests are overridden
+        let response = test_server
+            .client()
+            .put(
+                "http://localhost/items",
+                "_method=DELETE",
+                mime::APPLICATION_WWW_FORM_URLENCODED,
+            )
+            .perform()
+            .unwrap();
+        assert_eq!(response.read_utf8_body().unwrap(), "PUT _method=DELETE");
+    }
+}
@@ -0,0 +1,423 @@
+//! Metrics middleware, which records the requests served by the application and exposes them to
+//! Prometheus.
+use std::collections::BTreeMap;
+use std::fmt::Write;
+use std::io;
+use std::sync::{Arc, Mutex, MutexGuard, PoisonError};
+use std::time::Instant;
+
+use futures::{future, Future};
+use hyper::header::{HeaderValue, CONTENT_TYPE};
+use hyper::{Method, StatusCode};
+
+use crate::handler::{Handler, HandlerFuture, NewHandler};
+use crate::helpers::http::response::create_response;
+use crate::middleware::{Middleware, NewMiddleware};
+use crate::router::introspection::RouteTemplate;
+use crate::state::{FromState, State};
+
+// The latency buckets used by the Prometheus client libraries, in seconds.
+const DEFAULT_BUCKETS: &[f64] = &[
+    0.005, 0.01, 0.025, 0.05, 0.1, 0.25, 0.5, 1.0, 2.5, 5.0, 10.0,
+];
+
+// The content type of the Prometheus text exposition format.
+const CONTENT_TYPE_TEXT: &str = "text/plain; version=0.0.4; charset=utf-8";
+
+/// The metrics recorded by `MetricsMiddleware`, which also serves them in the Prometheus text
+/// format when used as a handler.
+///
+/// Three metrics are recorded:
+///
+/// - `http_requests_total`, a counter of the requests served, labelled by `route`, `method` and
+///   `status`.
+/// - `http_request_duration_seconds`, a histogram of the time taken to produce each response,
+///   labelled by `route`, `method` and `status`.
+/// - `http_requests_in_flight`, a gauge of the requests being served, labelled by `route` and
+///   `method`.
+///
+/// The `route` label is the path template of the route, such as `/users/:id`, so that requests
+/// for different resources of the same route are counted together.
+///
+/// `Metrics` is cheap to clone, and clones share the same metrics.
+///
+/// # Examples
+///
+/// ```rust
+/// # extern crate gotham;
+/// #
+/// # use gotham::middleware::metrics::{Metrics, MetricsMiddleware};
+/// # use gotham::pipeline::new_pipeline;
+/// # use gotham::pipeline::single::single_pipeline;
+/// # use gotham::router::builder::*;
+/// # use gotham::state::State;
+/// # use gotham::test::TestServer;
+/// #
+/// # fn show_user(state: State) -> (State, &'static str) {
+/// #     (state, "user")
+/// # }
+/// #
+/// # fn main() {
+/// let metrics = Metrics::new();
+///
+/// let (chain, pipelines) = single_pipeline(
+///     new_pipeline()
+///         .add(MetricsMiddleware::new(metrics.clone()))
+///         .build(),
+/// );
+/// let router = build_router(chain, pipelines, |route| {
+///     route.get("/users/:id").to(show_user);
+///     route.get("/metrics").to_new_handler(metrics);
+/// });
+///
+/// let test_server = TestServer::new(router).unwrap();
+/// let client = test_server.client();
+/// client.get("http://localhost/users/1").perform().unwrap();
+/// client.get("http://localhost/users/2").perform().unwrap();
+///
+/// let response = client.get("http://localhost/metrics").perform().unwrap();
+/// let body = response.read_utf8_body().unwrap();
+/// assert!(body.contains(
+///     r#"http_requests_total{route="/users/:id",method="GET",status="200"} 2"#
+/// ));
+/// # }
+/// ```
+#[derive(Clone)]
+pub struct Metrics {
+    data: Arc<Mutex<MetricsData>>,
+    buckets: Arc<Vec<f64>>,
+}
+
+#[derive(Default)]
+struct MetricsData {
+    requests: BTreeMap<(String, String, u16), Histogram>,
+    in_flight: BTreeMap<(String, String), i64>,
+}
+
+// A histogram whose bucket counts are cumulative, as they're exposed.
+struct Histogram {
+    buckets: Vec<u64>,
+    sum: f64,
+    count: u64,
+}
+
+impl Default for Metrics {
+    fn default() -> Self {
+        Metrics::new()
+    }
+}
+
+impl Metrics {
+    /// Creates an empty set of metrics, with the latency buckets used by the Prometheus client
+    /// libraries.
+    pub fn new() -> Metrics {
+        Metrics::with_buckets(DEFAULT_BUCKETS.to_vec())
+    }
+
+    /// Creates an empty set of metrics, whose latency histogram has the given bucket boundaries in
+    /// seconds.
+    pub fn with_buckets(mut buckets: Vec<f64>) -> Metrics {
+        buckets.retain(|bound| bound.is_finite());
+        buckets.sort_by(|a, b| a.partial_cmp(b).unwrap());
+        buckets.dedup();
+
+        Metrics {
+            data: Arc::new(Mutex::new(MetricsData::default())),
+            buckets: Arc::new(buckets),
+        }
+    }
+
+    fn lock(&self) -> MutexGuard<'_, MetricsData> {
+        self.data.lock().unwrap_or_else(PoisonError::into_inner)
+    }
+
+    fn start(&self, route: &str, method: &str) {
+        let mut data = self.lock();
+        let key = (route.to_owned(), method.to_owned());
+        *data.in_flight.entry(key).or_insert(0) += 1;
+    }
+
+    fn finish(&self, route: &str, method: &str, status: Option<StatusCode>, seconds: f64) {
+        let mut data = self.lock();
+        if let Some(in_flight) = data
+            .in_flight
+            .get_mut(&(route.to_owned(), method.to_owned()))
+        {
+            *in_flight -= 1;
+        }
+
+        let status = match status {
+            Some(status) => status.as_u16(),
+            None => return,
+        };
+
+        let key = (route.to_owned(), method.to_owned(), status);
+        let buckets = &self.buckets;
+        let histogram = data.requests.entry(key).or_insert_with(|| Histogram {
+            buckets: vec![0; buckets.len()],
+            sum: 0.0,
+            count: 0,
+        });
+
+        for (count, bound) in histogram.buckets.iter_mut().zip(buckets.iter()) {
+            if seconds <= *bound {
+                *count += 1;
+            }
+        }
+        histogram.sum += seconds;
+        histogram.count += 1;
+    }
+
+    /// Renders the metrics in the Prometheus text exposition format.
+    pub fn render(&self) -> String {
+        let data = self.lock();
+        let mut out = String::new();
+
+        out.push_str("# HELP http_requests_total The number of HTTP requests served.\n");
+        out.push_str("# TYPE http_requests_total counter\n");
+        for ((route, method, status), histogram) in &data.requests {
+            let labels = labels(route, method, Some(*status));
+            let _ = writeln!(out, "http_requests_total{{{}}} {}", labels, histogram.count);
+        }
+
+        out.push_str(
+            "# HELP http_request_duration_seconds The time taken to respond to HTTP requests.\n",
+        );
+        out.push_str("# TYPE http_request_duration_seconds histogram\n");
+        for ((route, method, status), histogram) in &data.requests {
+            let labels = labels(route, method, Some(*status));
+            for (count, bound) in histogram.buckets.iter().zip(self.buckets.iter()) {
+                let _ = writeln!(
+                    out,
+                    "http_request_duration_seconds_bucket{{{},le=\"{}\"}} {}",
+                    labels, bound, count
+                );
+            }
+            let _ = writeln!(
+                out,
+                "http_request_duration_seconds_bucket{{{},le=\"+Inf\"}} {}",
+                labels, histogram.count
+            );
+            let _ = writeln!(
+                out,
+                "http_request_duration_seconds_sum{{{}}} {}",
+                labels, histogram.sum
+            );
+            let _ = writeln!(
+                out,
+                "http_request_duration_seconds_count{{{}}} {}",
+                labels, histogram.count
+            );
+        }
+
+        out.push_str("# HELP http_requests_in_flight The number of HTTP requests being served.\n");
+        out.push_str("# TYPE http_requests_in_flight gauge\n");
+        for ((route, method), in_flight) in &data.in_flight {
+            let labels = labels(route, method, None);
+            let _ = writeln!(out, "http_requests_in_flight{{{}}} {}", labels, in_flight);
+        }
+
+        out
+    }
+}
+
+fn labels(route: &str, method: &str, status: Option<u16>) -> String {
+    let mut labels = format!(
+        "route=\"{}\",method=\"{}\"",
+        escape_label(route),
+        escape_label(method)
+    );
+    if let Some(status) = status {
+        let _ = write!(labels, ",status=\"{}\"", status);
+    }
+    labels
+}
+
+fn escape_label(value: &str) -> String {
+    value
+        .replace('\\', "\\\\")
+        .replace('"', "\\\"")
+        .replace('\n', "\\n")
+}
+
+impl NewHandler for Metrics {
+    type Instance = Self;
+
+    fn new_handler(&self) -> crate::error::Result<Self::Instance> {
+        Ok(self.clone())
+    }
+}
+
+impl Handler for Metrics {
+    fn handle(self, state: State) -> Box<HandlerFuture> {
+        let mut res = create_response(&state, StatusCode::OK, mime::TEXT_PLAIN, self.render());
+        res.headers_mut()
+            .insert(CONTENT_TYPE, HeaderValue::from_static(CONTENT_TYPE_TEXT));
+        Box::new(future::ok((state, res)))
+    }
+}
+
+/// Middleware binding which records each request in `Metrics`.
+///
+/// The middleware is usually added to the pipelines of every route, including the route serving
+/// the metrics. The time recorded is the time taken by the rest of the pipeline chain and the
+/// handler to produce the response, excluding the time taken to send the body. Requests which
+/// are dropped before their response is produced, such as when a `TimeoutMiddleware` earlier in
+/// the chain gives up on them, leave the in-flight gauge without being counted.
+#[derive(Clone)]
+pub struct MetricsMiddleware {
+    metrics: Metrics,
+}
+
+impl MetricsMiddleware {
+    /// Creates a `MetricsMiddleware` which records requests in `metrics`.
+    pub fn new(metrics: Metrics) -> MetricsMiddleware {
+        MetricsMiddleware { metrics }
+    }
+}
+
+// Records a request as in flight until it's finished or dropped.
+struct InFlight {
+    metrics: Option<Metrics>,
+    route: String,
+    method: String,
+    start: Instant,
+}
+
+impl InFlight {
+    // Records the request as finished, with the status of its response where it has one.
+    fn finish(&mut self, status: Option<StatusCode>) {
+        if let Some(metrics) = self.metrics.take() {
+            let seconds = self.start.elapsed().as_secs_f64();
+            metrics.finish(&self.route, &self.method, status, seconds);
+        }
+    }
+}
+
+impl Drop for InFlight {
+    fn drop(&mut self) {
+        self.finish(None);
+    }
+}
+
+/// `Middleware` trait implementation.
+impl Middleware for MetricsMiddleware {
+    /// Records the request once the rest of the chain has responded to it.
+    fn call<Chain>(self, state: State, chain: Chain) -> Box<HandlerFuture>
+    where
+        Chain: FnOnce(State) -> Box<HandlerFuture>,
+    {
+        let route = state
+            .try_borrow::<RouteTemplate>()
+            .map(|template| template.as_str().to_owned())
+            .unwrap_or_else(|| "-".to_owned());
+        let method = Method::borrow_from(&state).as_str().to_owned();
+
+        self.metrics.start(&route, &method);
+        let mut in_flight = InFlight {
+            metrics: Some(self.metrics),
+            route,
+            method,
+            start: Instant::now(),
+        };
+
+        let f = chain(state).then(move |result| {
+            in_flight.finish(Some(match result {
+                Ok((_, ref response)) => response.status(),
+                Err((_, ref err)) => err.status(),
+            }));
+            result
+        });
+
+        Box::new(f)
+    }
+}
+
+/// `NewMiddleware` trait implementation.
+impl NewMiddleware for MetricsMiddleware {
+    type Instance = Self;
+
+    /// Clones the current middleware to a new instance.
+    fn new_middleware(&self) -> io::Result<Self::Instance> {
+        Ok(self.clone())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    use crate::handler::IntoHandlerError;
+    use crate::pipeline::new_pipeline;
+    use crate::pipeline::single::single_pipeline;
+    use crate::router::builder::*;
+    use crate::test::TestServer;
+
+    fn ok(state: State) -> (State, &'static str) {
+        (state, "ok")
+    }
+
+    fn fail(state: State) -> Box<HandlerFuture> {
+        let err = io::Error::other("failed").into_handler_error();
+        Box::new(future::err((state, err)))
+    }
+
+    #[test]
+    fn records_requests() {
+        let metrics = Metrics::with_buckets(vec![60.0, 0.0]);
+        let (chain, pipelines) = single_pipeline(
+            new_pipeline()
+                .add(MetricsMiddleware::new(metrics.clone()))
+                .build(),
+        );
+        let test_server = TestServer::new(build_router(chain, pipelines, |route| {
+            route.get("/items/:id").to(ok);
+            route.post("/fail").to(fail);
+            route.get("/metrics").to_new_handler(metrics.clone());
+        }))
+        .unwrap();
+
+        let client = test_server.client();
+        client.get("http://localhost/items/1").perform().unwrap();
+        client.get("http://localhost/items/2").perform().unwrap();
+        client
+            .post("http://localhost/fail", "", mime::TEXT_PLAIN)
+            .perform()
+            .unwrap();
+
+        let response = client.get("http://localhost/metrics").perform().unwrap();
+        assert_eq!(response.headers()[CONTENT_TYPE], CONTENT_TYPE_TEXT);
+        let body = response.read_utf8_body().unwrap();
+        let lines: Vec<&str> = body.lines().collect();
+
+        for line in &[
+            r#"http_requests_total{route="/items/:id",method="GET",status="200"} 2"#,
+            r#"http_requests_total{route="/fail",method="POST",status="500"} 1"#,
+            r#"http_request_duration_seconds_bucket{route="/items/:id",method="GET",status="200",le="60"} 2"#,
+            r#"http_request_duration_seconds_bucket{route="/items/:id",method="GET",status="200",le="+Inf"} 2"#,
+            r#"http_request_duration_seconds_count{route="/fail",method="POST",status="500"} 1"#,
+            r#"http_requests_in_flight{route="/items/:id",method="GET"} 0"#,
+            // the request for the metrics is still being served
+            r#"http_requests_in_flight{route="/metrics",method="GET"} 1"#,
+            "# TYPE http_request_duration_seconds histogram",
+        ] {
+            assert!(lines.contains(line), "missing {} in {}", line, body);
+        }
+
+        // buckets are sorted
+        let first_bucket = lines
+            .iter()
+            .position(|line| line.contains(r#"method="GET",status="200",le="0""#))
+            .unwrap();
+        assert!(lines[first_bucket + 1].contains(r#"le="60""#));
+    }
+
+    #[test]
+    fn escapes_label_values() {
+        assert_eq!(
+            labels("/a\"b\\c\n", "GET", Some(404)),
+            r#"route="/a\"b\\c\n",method="GET",status="404""#
+        );
+        assert_eq!(labels("/", "GET", None), r#"route="/",method="GET""#);
+    }
+}
@@ -7,13 +7,32 @@ use std::panic::RefUnwindSafe;
 use crate::handler::HandlerFuture;
 use crate::state::State;
 
+pub mod audit;
+pub mod auth;
+pub mod body_limit;
 pub mod chain;
+pub mod circuit_breaker;
+pub mod conditional;
+pub mod cors;
 pub mod cookie;
+pub mod default_headers;
+pub mod error_map;
+pub mod etag;
+pub mod ip_filter;
 pub mod logger;
+pub mod maintenance;
+pub mod method_override;
+pub mod metrics;
+pub mod quota;
+pub mod rate_limit;
+pub mod request_id;
+pub mod response_map;
 pub mod security;
 pub mod session;
 pub mod state;
+pub mod timeout;
 pub mod timer;
+pub mod tracing;
 
 /// `Middleware` has the opportunity to provide additional behaviour to the `Request` / `Response`
 /// interaction. For example:
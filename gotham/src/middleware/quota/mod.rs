@@ -0,0 +1,446 @@
+//! API key quota middleware, which limits how many requests each API key may make over a period
+//! of time.
+use std::collections::hash_map::DefaultHasher;
+use std::collections::HashMap;
+use std::hash::{Hash, Hasher};
+use std::io;
+use std::panic::RefUnwindSafe;
+use std::sync::Arc;
+use std::time::Duration;
+
+use futures::{future, Future};
+use hyper::header::{HeaderMap, HeaderName, HeaderValue, RETRY_AFTER};
+use hyper::StatusCode;
+use log::{error, trace};
+
+use crate::handler::HandlerFuture;
+use crate::helpers::http::response::create_empty_response;
+use crate::middleware::rate_limit::retry_after_secs;
+use crate::middleware::{Middleware, NewMiddleware};
+use crate::state::{request_id, FromState, State};
+
+mod store;
+
+pub use self::store::{MemoryStore, QuotaFuture, QuotaOutcome, QuotaStore};
+
+const X_RATELIMIT_LIMIT: &str = "x-ratelimit-limit";
+const X_RATELIMIT_REMAINING: &str = "x-ratelimit-remaining";
+
+/// The number of requests an API key may make in any window of time of a given length.
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub struct Quota {
+    requests: u32,
+    window: Duration,
+}
+
+impl Quota {
+    /// Allows `requests` requests in any `window`.
+    ///
+    /// # Panics
+    ///
+    /// Panics where `requests` is zero, or `window` is empty.
+    pub fn new(requests: u32, window: Duration) -> Quota {
+        assert!(requests > 0, "a quota must allow at least one request");
+        assert!(
+            window > Duration::from_secs(0),
+            "a quota must have a window"
+        );
+        Quota { requests, window }
+    }
+
+    /// Allows `requests` requests in any minute.
+    pub fn per_minute(requests: u32) -> Quota {
+        Quota::new(requests, Duration::from_secs(60))
+    }
+
+    /// Allows `requests` requests in any hour.
+    pub fn per_hour(requests: u32) -> Quota {
+        Quota::new(requests, Duration::from_secs(60 * 60))
+    }
+
+    /// Allows `requests` requests in any day.
+    pub fn per_day(requests: u32) -> Quota {
+        Quota::new(requests, Duration::from_secs(24 * 60 * 60))
+    }
+
+    /// The number of requests allowed in a window.
+    pub fn requests(&self) -> u32 {
+        self.requests
+    }
+
+    /// The length of the window.
+    pub fn window(&self) -> Duration {
+        self.window
+    }
+}
+
+type KeyValidator = dyn Fn(&str) -> bool + Send + Sync + RefUnwindSafe;
+
+fn any_key(_key: &str) -> bool {
+    true
+}
+
+// A digest of an API key which identifies it in logs without revealing it.
+fn key_digest(key: &str) -> String {
+    let mut hasher = DefaultHasher::new();
+    key.hash(&mut hasher);
+    format!("{:016x}", hasher.finish())
+}
+
+/// Middleware binding which identifies callers by the API key in a request header, and limits
+/// the requests made with each key to a `Quota` over a sliding window.
+///
+/// The key is read from the `X-Api-Key` header, unless another header is given to `with_header`.
+/// Requests without a key are refused with `401 Unauthorized`, as they can't be counted against a
+/// quota, and requests over the quota are refused with `429 Too Many Requests` and a
+/// `Retry-After` header. Responses have `X-RateLimit-Limit` and `X-RateLimit-Remaining` headers
+/// describing the quota of the key.
+///
+/// Every key has the quota given to `new`, and `with_key_quota` gives a key a quota of its own,
+/// such as for a customer on a larger plan. Counters are kept in a `MemoryStore` of the
+/// middleware, unless another `QuotaStore` is given by `with_store`. A store which fails is
+/// logged, and the request is allowed.
+///
+/// The middleware doesn't check that a key is valid unless it's given a check by
+/// `with_key_validator`, which refuses requests with other keys with `401 Unauthorized` before
+/// they're counted. Without a check, each key which is made up is counted separately, so the
+/// counters a store keeps should be bounded, as they are by `MemoryStore::with_max_keys`. Keys
+/// appear in logs as a digest, rather than as they were sent.
+///
+/// # Examples
+///
+/// ```rust
+/// # extern crate gotham;
+/// # extern crate hyper;
+/// #
+/// # use hyper::header::HeaderValue;
+/// # use hyper::StatusCode;
+/// # use gotham::middleware::quota::{Quota, QuotaMiddleware};
+/// # use gotham::pipeline::new_pipeline;
+/// # use gotham::pipeline::single::single_pipeline;
+/// # use gotham::router::builder::*;
+/// # use gotham::state::State;
+/// # use gotham::test::TestServer;
+/// #
+/// # fn handler(state: State) -> (State, &'static str) {
+/// #     (state, "hello")
+/// # }
+/// #
+/// # fn main() {
+/// let middleware =
+///     QuotaMiddleware::new(Quota::per_hour(1000)).with_key_quota("partner", Quota::per_hour(1));
+///
+/// let (chain, pipelines) = single_pipeline(new_pipeline().add(middleware).build());
+/// let router = build_router(chain, pipelines, |route| {
+///     route.get("/").to(handler);
+/// });
+///
+/// let test_server = TestServer::new(router).unwrap();
+/// let get = || {
+///     test_server
+///         .client()
+///         .get("http://localhost/")
+///         .with_header("x-api-key", HeaderValue::from_static("partner"))
+///         .perform()
+///         .unwrap()
+/// };
+///
+/// let response = get();
+/// assert_eq!(response.status(), StatusCode::OK);
+/// assert_eq!(response.headers()["X-RateLimit-Limit"], "1");
+/// assert_eq!(response.headers()["X-RateLimit-Remaining"], "0");
+///
+/// let response = get();
+/// assert_eq!(response.status(), StatusCode::TOO_MANY_REQUESTS);
+/// assert_eq!(response.headers()["Retry-After"], "3600");
+/// # }
+/// ```
+pub struct QuotaMiddleware<S = MemoryStore>
+where
+    S: QuotaStore,
+{
+    quota: Quota,
+    key_quotas: Arc<HashMap<String, Quota>>,
+    header: HeaderName,
+    validator: Arc<KeyValidator>,
+    store: Arc<S>,
+}
+
+impl QuotaMiddleware<MemoryStore> {
+    /// Creates a `QuotaMiddleware` which limits each API key to `quota`, keeping counters in a
+    /// new `MemoryStore`.
+    pub fn new(quota: Quota) -> Self {
+        QuotaMiddleware {
+            quota,
+            key_quotas: Arc::new(HashMap::new()),
+            header: HeaderName::from_static("x-api-key"),
+            validator: Arc::new(any_key),
+            store: Arc::new(MemoryStore::new()),
+        }
+    }
+}
+
+impl<S> QuotaMiddleware<S>
+where
+    S: QuotaStore,
+{
+    /// Keeps counters in `store`, in place of a `MemoryStore`.
+    pub fn with_store<T>(self, store: T) -> QuotaMiddleware<T>
+    where
+        T: QuotaStore,
+    {
+        QuotaMiddleware {
+            quota: self.quota,
+            key_quotas: self.key_quotas,
+            header: self.header,
+            validator: self.validator,
+            store: Arc::new(store),
+        }
+    }
+
+    /// Reads the API key from the header `header`, in place of `X-Api-Key`.
+    pub fn with_header(self, header: HeaderName) -> Self {
+        QuotaMiddleware { header, ..self }
+    }
+
+    /// Only counts requests whose API key is accepted by `validator`, such as keys which have
+    /// been issued, refusing others with `401 Unauthorized`.
+    pub fn with_key_validator<F>(self, validator: F) -> Self
+    where
+        F: Fn(&str) -> bool + Send + Sync + RefUnwindSafe + 'static,
+    {
+        QuotaMiddleware {
+            validator: Arc::new(validator),
+            ..self
+        }
+    }
+
+    /// Limits the API key `key` to `quota`, in place of the quota of the middleware.
+    pub fn with_key_quota(self, key: &str, quota: Quota) -> Self {
+        let mut key_quotas = (*self.key_quotas).clone();
+        key_quotas.insert(key.to_owned(), quota);
+        QuotaMiddleware {
+            key_quotas: Arc::new(key_quotas),
+            ..self
+        }
+    }
+}
+
+impl<S> Clone for QuotaMiddleware<S>
+where
+    S: QuotaStore,
+{
+    fn clone(&self) -> Self {
+        QuotaMiddleware {
+            quota: self.quota,
+            key_quotas: self.key_quotas.clone(),
+            header: self.header.clone(),
+            validator: self.validator.clone(),
+            store: self.store.clone(),
+        }
+    }
+}
+
+fn insert_quota_headers(headers: &mut HeaderMap, quota: &Quota, remaining: u32) {
+    headers.insert(X_RATELIMIT_LIMIT, HeaderValue::from(quota.requests()));
+    headers.insert(X_RATELIMIT_REMAINING, HeaderValue::from(remaining));
+}
+
+/// `Middleware` trait implementation.
+impl<S> Middleware for QuotaMiddleware<S>
+where
+    S: QuotaStore + 'static,
+{
+    /// Counts the request against the quota of its API key, refusing it where the quota is
+    /// exceeded.
+    fn call<Chain>(self, state: State, chain: Chain) -> Box<HandlerFuture>
+    where
+        Chain: FnOnce(State) -> Box<HandlerFuture> + Send + 'static,
+    {
+        let key = HeaderMap::borrow_from(&state)
+            .get(&self.header)
+            .and_then(|value| value.to_str().ok())
+            .filter(|key| !key.is_empty())
+            .map(ToOwned::to_owned);
+
+        let key = match key {
+            Some(key) => key,
+            None => {
+                trace!("[{}] refusing request without API key", request_id(&state));
+                let res = create_empty_response(&state, StatusCode::UNAUTHORIZED);
+                return Box::new(future::ok((state, res)));
+            }
+        };
+
+        if !(self.validator)(&key) {
+            trace!(
+                "[{}] refusing request with invalid API key {}",
+                request_id(&state),
+                key_digest(&key)
+            );
+            let res = create_empty_response(&state, StatusCode::UNAUTHORIZED);
+            return Box::new(future::ok((state, res)));
+        }
+
+        let quota = self.key_quotas.get(&key).copied().unwrap_or(self.quota);
+
+        let recorded = self.store.record(&key, &quota);
+        Box::new(recorded.then(move |result| -> Box<HandlerFuture> {
+            match result {
+                Ok(QuotaOutcome::Allowed { remaining }) => {
+                    trace!(
+                        "[{}] quota allows request, {} remaining",
+                        request_id(&state),
+                        remaining
+                    );
+                    let f = chain(state).and_then(move |(state, mut res)| {
+                        insert_quota_headers(res.headers_mut(), &quota, remaining);
+                        future::ok((state, res))
+                    });
+                    Box::new(f)
+                }
+                Ok(QuotaOutcome::Exceeded { retry_after }) => {
+                    trace!(
+                        "[{}] quota exceeded by API key {}",
+                        request_id(&state),
+                        key_digest(&key)
+                    );
+                    let mut res = create_empty_response(&state, StatusCode::TOO_MANY_REQUESTS);
+                    insert_quota_headers(res.headers_mut(), &quota, 0);
+                    res.headers_mut().insert(
+                        RETRY_AFTER,
+                        HeaderValue::from(retry_after_secs(retry_after)),
+                    );
+                    Box::new(future::ok((state, res)))
+                }
+                Err(e) => {
+                    error!(
+                        "[{}] quota store failed, allowing request: {}",
+                        request_id(&state),
+                        e
+                    );
+                    chain(state)
+                }
+            }
+        }))
+    }
+}
+
+/// `NewMiddleware` trait implementation.
+impl<S> NewMiddleware for QuotaMiddleware<S>
+where
+    S: QuotaStore + 'static,
+{
+    type Instance = Self;
+
+    /// Clones the current middleware to a new instance.
+    fn new_middleware(&self) -> io::Result<Self::Instance> {
+        Ok(self.clone())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    use std::sync::Mutex;
+
+    use crate::pipeline::new_pipeline;
+    use crate::pipeline::single::single_pipeline;
+    use crate::router::builder::*;
+    use crate::test::{TestResponse, TestServer};
+
+    fn handler(state: State) -> (State, &'static str) {
+        (state, "hello")
+    }
+
+    fn test_server<S>(middleware: QuotaMiddleware<S>) -> TestServer
+    where
+        S: QuotaStore + 'static,
+    {
+        let (chain, pipelines) = single_pipeline(new_pipeline().add(middleware).build());
+        TestServer::new(build_router(chain, pipelines, |route| {
+            route.get("/").to(handler);
+        }))
+        .unwrap()
+    }
+
+    fn get(test_server: &TestServer, header: &'static str, key: &'static str) -> TestResponse {
+        test_server
+            .client()
+            .get("http://localhost/")
+            .with_header(header, HeaderValue::from_static(key))
+            .perform()
+            .unwrap()
+    }
+
+    #[test]
+    fn limits_requests_by_api_key() {
+        let middleware = QuotaMiddleware::new(Quota::per_minute(2))
+            .with_header(HeaderName::from_static("x-client-key"))
+            .with_key_quota("large", Quota::per_minute(100));
+        let test_server = test_server(middleware);
+
+        let response = get(&test_server, "x-client-key", "a");
+        assert_eq!(response.status(), StatusCode::OK);
+        assert_eq!(response.headers()[X_RATELIMIT_LIMIT], "2");
+        assert_eq!(response.headers()[X_RATELIMIT_REMAINING], "1");
+        assert_eq!(
+            get(&test_server, "x-client-key", "a").status(),
+            StatusCode::OK
+        );
+
+        let response = get(&test_server, "x-client-key", "a");
+        assert_eq!(response.status(), StatusCode::TOO_MANY_REQUESTS);
+        assert_eq!(response.headers()[X_RATELIMIT_REMAINING], "0");
+        assert!(response.headers().contains_key(RETRY_AFTER));
+
+        let response = get(&test_server, "x-client-key", "large");
+        assert_eq!(response.headers()[X_RATELIMIT_LIMIT], "100");
+        assert_eq!(response.headers()[X_RATELIMIT_REMAINING], "99");
+
+        // requests without a key are refused
+        let response = get(&test_server, "x-api-key", "a");
+        assert_eq!(response.status(), StatusCode::UNAUTHORIZED);
+    }
+
+    #[test]
+    fn refuses_keys_which_fail_validation() {
+        let middleware = QuotaMiddleware::new(Quota::per_minute(1))
+            .with_key_validator(|key| key.starts_with("issued-"));
+        let test_server = test_server(middleware);
+
+        let response = get(&test_server, "x-api-key", "issued-a");
+        assert_eq!(response.status(), StatusCode::OK);
+        assert_eq!(response.headers()[X_RATELIMIT_REMAINING], "0");
+
+        let response = get(&test_server, "x-api-key", "made-up");
+        assert_eq!(response.status(), StatusCode::UNAUTHORIZED);
+        assert!(!response.headers().contains_key(X_RATELIMIT_REMAINING));
+    }
+
+    struct FailingStore {
+        keys: Mutex<Vec<String>>,
+    }
+
+    impl QuotaStore for FailingStore {
+        fn record(&self, key: &str, _quota: &Quota) -> Box<QuotaFuture> {
+            self.keys.lock().unwrap().push(key.to_owned());
+            Box::new(future::err(io::Error::other("unavailable")))
+        }
+    }
+
+    #[test]
+    fn allows_requests_when_the_store_fails() {
+        let store = Arc::new(FailingStore {
+            keys: Mutex::new(vec![]),
+        });
+        let middleware = QuotaMiddleware::new(Quota::per_minute(1)).with_store(store.clone());
+        let test_server = test_server(middleware);
+
+        let response = get(&test_server, "x-api-key", "a");
+        assert_eq!(response.status(), StatusCode::OK);
+        assert!(!response.headers().contains_key(X_RATELIMIT_REMAINING));
+        assert_eq!(*store.keys.lock().unwrap(), vec!["a".to_owned()]);
+    }
+}
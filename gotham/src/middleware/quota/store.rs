@@ -0,0 +1,307 @@
+//! Defines the storage of the request counters used by `QuotaMiddleware`.
+
+use std::collections::HashMap;
+use std::io;
+use std::panic::RefUnwindSafe;
+use std::sync::{Arc, Mutex, PoisonError};
+use std::time::{Duration, Instant};
+
+use futures::{future, Future};
+
+use crate::middleware::quota::Quota;
+
+// How often counters of windows which have passed are removed from a `MemoryStore`.
+const SWEEP_INTERVAL: Duration = Duration::from_secs(60);
+
+// The number of API keys a `MemoryStore` keeps counters for, unless told otherwise.
+const DEFAULT_MAX_KEYS: usize = 100_000;
+
+/// The outcome of counting a request against the quota of an API key.
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub enum QuotaOutcome {
+    /// The request is within the quota, and `remaining` requests are left in the window.
+    Allowed {
+        /// The number of requests which could be made immediately after this one.
+        remaining: u32,
+    },
+    /// The request exceeds the quota, and another request is next allowed after `retry_after`.
+    Exceeded {
+        /// The time until the next request would be allowed.
+        retry_after: Duration,
+    },
+}
+
+/// Type alias for the trait objects returned by `QuotaStore::record`.
+pub type QuotaFuture = dyn Future<Item = QuotaOutcome, Error = io::Error> + Send;
+
+/// Stores the request counters of `QuotaMiddleware`, keyed by API key.
+///
+/// `MemoryStore` keeps counters within the process. Where an application runs as several
+/// processes, a store which keeps counters in a shared service can be provided instead, so that
+/// each API key has one quota between all of the processes.
+///
+/// `record` returns a future, which the middleware chains into the future of the request, so
+/// that a store using a network service can wait for the service without blocking the thread
+/// serving requests. A store which completes its work immediately returns a future which has
+/// already resolved, such as one created by `futures::future::result`.
+pub trait QuotaStore: Send + Sync + RefUnwindSafe {
+    /// Counts a request against the quota of `key`, which allows `quota.requests()` requests in
+    /// any window of `quota.window()`. Requests which exceed the quota aren't counted.
+    fn record(&self, key: &str, quota: &Quota) -> Box<QuotaFuture>;
+}
+
+impl<S> QuotaStore for Arc<S>
+where
+    S: QuotaStore + ?Sized,
+{
+    fn record(&self, key: &str, quota: &Quota) -> Box<QuotaFuture> {
+        (**self).record(key, quota)
+    }
+}
+
+/// A `QuotaStore` which keeps counters in memory.
+///
+/// The sliding window is approximated by counting requests in fixed windows, and weighting the
+/// count of the previous window by how much of it the sliding window still covers. Counters of
+/// windows which have passed are forgotten periodically.
+///
+/// Counters are kept for at most 100,000 API keys, unless another bound is given to
+/// `with_max_keys`, so that requests with made up keys can't grow the store without limit. Once
+/// the store is full, requests with keys it doesn't have a counter for exceed their quota until
+/// counters are forgotten, while the keys it has counters for are unaffected.
+pub struct MemoryStore {
+    state: Mutex<MemoryState>,
+    max_keys: usize,
+}
+
+struct MemoryState {
+    counters: HashMap<String, Counter>,
+    started: Instant,
+    swept: Instant,
+}
+
+struct Counter {
+    // The length of the fixed windows, which is that of the quota the counter was last used with.
+    length: Duration,
+    window: u64,
+    current: u32,
+    previous: u32,
+}
+
+impl Counter {
+    fn new(quota: &Quota, window: u64) -> Counter {
+        Counter {
+            length: quota.window(),
+            window,
+            current: 0,
+            previous: 0,
+        }
+    }
+
+    // Moves the counter on to the fixed window `window`.
+    fn advance(&mut self, window: u64) {
+        if window == self.window + 1 {
+            self.previous = self.current;
+        } else if window != self.window {
+            self.previous = 0;
+        }
+        if window != self.window {
+            self.current = 0;
+            self.window = window;
+        }
+    }
+
+    // The number of requests in the sliding window which ends `elapsed` of the way through the
+    // current fixed window, where the requests of the previous window are counted in whole
+    // requests.
+    fn estimate(&self, elapsed: f64) -> u32 {
+        let previous = (f64::from(self.previous) * (1.0 - elapsed)).floor() as u32;
+        previous.saturating_add(self.current)
+    }
+
+    // The time until another request would be allowed, from `elapsed` of the way through the
+    // current fixed window.
+    fn retry_after(&self, quota: &Quota, elapsed: f64) -> Duration {
+        let requests = f64::from(quota.requests());
+        let current = f64::from(self.current);
+        let window = quota.window().as_secs_f64();
+
+        let wait = if current < requests {
+            // the weight of the previous window falls until fewer of its requests count than
+            // the current window leaves room for
+            let until = 1.0 - (requests - current) / f64::from(self.previous);
+            (until - elapsed) * window
+        } else {
+            // the current window becomes the previous window, and its weight falls in turn
+            let until = 1.0 - requests / current;
+            (1.0 - elapsed + until) * window
+        };
+
+        Duration::from_secs_f64(wait.max(0.0))
+    }
+}
+
+impl MemoryStore {
+    /// Creates a new, empty `MemoryStore`.
+    pub fn new() -> Self {
+        let now = Instant::now();
+        MemoryStore {
+            state: Mutex::new(MemoryState {
+                counters: HashMap::new(),
+                started: now,
+                swept: now,
+            }),
+            max_keys: DEFAULT_MAX_KEYS,
+        }
+    }
+
+    /// Keeps counters for at most `max_keys` API keys, in place of 100,000.
+    pub fn with_max_keys(self, max_keys: usize) -> Self {
+        MemoryStore { max_keys, ..self }
+    }
+
+    fn record_at(&self, key: &str, quota: &Quota, now: Instant) -> QuotaOutcome {
+        let mut state = self.state.lock().unwrap_or_else(PoisonError::into_inner);
+
+        let since = now.duration_since(state.started).as_secs_f64();
+        let windows = since / quota.window().as_secs_f64();
+        let window = windows.floor() as u64;
+        let elapsed = windows.fract();
+
+        if now.duration_since(state.swept) >= SWEEP_INTERVAL {
+            // keys may have quotas of different lengths, so each counter is checked against the
+            // windows of its own quota
+            state.counters.retain(|_, counter| {
+                let current = (since / counter.length.as_secs_f64()) as u64;
+                counter.window + 1 >= current
+            });
+            state.swept = now;
+        }
+
+        if state.counters.len() >= self.max_keys && !state.counters.contains_key(key) {
+            return QuotaOutcome::Exceeded {
+                retry_after: quota.window(),
+            };
+        }
+
+        let counter = state
+            .counters
+            .entry(key.to_owned())
+            .or_insert_with(|| Counter::new(quota, window));
+        if counter.length != quota.window() {
+            // the quota of the key has changed, so its windows no longer line up
+            *counter = Counter::new(quota, window);
+        }
+        counter.advance(window);
+
+        let estimate = counter.estimate(elapsed);
+        if estimate < quota.requests() {
+            counter.current += 1;
+            QuotaOutcome::Allowed {
+                remaining: quota.requests() - estimate - 1,
+            }
+        } else {
+            QuotaOutcome::Exceeded {
+                retry_after: counter.retry_after(quota, elapsed),
+            }
+        }
+    }
+}
+
+impl Default for MemoryStore {
+    fn default() -> Self {
+        MemoryStore::new()
+    }
+}
+
+impl QuotaStore for MemoryStore {
+    fn record(&self, key: &str, quota: &Quota) -> Box<QuotaFuture> {
+        Box::new(future::ok(self.record_at(key, quota, Instant::now())))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn assert_exceeded(outcome: QuotaOutcome, secs: f64) {
+        match outcome {
+            QuotaOutcome::Exceeded { retry_after } => {
+                assert!(
+                    (retry_after.as_secs_f64() - secs).abs() < 1e-6,
+                    "{:?}",
+                    retry_after
+                )
+            }
+            outcome => panic!("unexpected outcome: {:?}", outcome),
+        }
+    }
+
+    #[test]
+    fn counts_requests_over_a_sliding_window() {
+        let store = MemoryStore::new();
+        let started = store.state.lock().unwrap().started;
+        let quota = Quota::new(4, Duration::from_secs(60));
+        let at = |secs: u64| started + Duration::from_secs(secs);
+
+        for remaining in (0..4).rev() {
+            assert_eq!(
+                store.record_at("a", &quota, at(30)),
+                QuotaOutcome::Allowed { remaining }
+            );
+        }
+        assert_exceeded(store.record_at("a", &quota, at(45)), 15.0);
+
+        // a third of the way through the next window, two of the requests of the previous
+        // window still count
+        assert_eq!(
+            store.record_at("a", &quota, at(80)),
+            QuotaOutcome::Allowed { remaining: 1 }
+        );
+        assert_eq!(
+            store.record_at("a", &quota, at(80)),
+            QuotaOutcome::Allowed { remaining: 0 }
+        );
+        assert_exceeded(store.record_at("a", &quota, at(80)), 10.0);
+
+        // counters are kept for each key
+        assert_eq!(
+            store.record_at("b", &quota, at(80)),
+            QuotaOutcome::Allowed { remaining: 3 }
+        );
+
+        // a window without requests resets the counter
+        assert_eq!(
+            store.record_at("a", &quota, at(240)),
+            QuotaOutcome::Allowed { remaining: 3 }
+        );
+    }
+
+    #[test]
+    fn bounds_the_number_of_keys() {
+        let store = MemoryStore::new().with_max_keys(2);
+        let started = store.state.lock().unwrap().started;
+        let quota = Quota::new(4, Duration::from_secs(60));
+        let at = |secs: u64| started + Duration::from_secs(secs);
+
+        for key in &["a", "b"] {
+            assert_eq!(
+                store.record_at(key, &quota, at(0)),
+                QuotaOutcome::Allowed { remaining: 3 }
+            );
+        }
+
+        // a key without a counter exceeds its quota, while keys with counters are unaffected
+        assert_exceeded(store.record_at("c", &quota, at(0)), 60.0);
+        assert_eq!(
+            store.record_at("a", &quota, at(10)),
+            QuotaOutcome::Allowed { remaining: 2 }
+        );
+
+        // once the counters of passed windows are forgotten, there's room for other keys
+        assert_eq!(
+            store.record_at("c", &quota, at(180)),
+            QuotaOutcome::Allowed { remaining: 3 }
+        );
+    }
+}
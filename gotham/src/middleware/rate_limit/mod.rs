@@ -0,0 +1,429 @@
+//! Rate limiting middleware, which limits how often each client may make requests.
+use std::io;
+use std::panic::RefUnwindSafe;
+use std::sync::Arc;
+use std::time::Duration;
+
+use futures::{future, Future};
+use hyper::header::{HeaderValue, RETRY_AFTER};
+use hyper::StatusCode;
+use log::{error, trace};
+
+use crate::handler::HandlerFuture;
+use crate::helpers::http::response::create_empty_response;
+use crate::middleware::{Middleware, NewMiddleware};
+use crate::state::{client_addr, request_id, State};
+
+mod store;
+
+pub use self::store::{MemoryStore, RateLimitFuture, RateLimitOutcome, RateLimitStore};
+
+/// The rate at which a client may make requests, as a token bucket.
+///
+/// Each client has a bucket of up to `burst` tokens, which starts full. A request takes a token
+/// from the bucket, and is refused where the bucket is empty. A token is added back after each
+/// `interval`, so that clients may make bursts of requests as long as they keep to the rate on
+/// average.
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub struct RateLimit {
+    burst: u32,
+    interval: Duration,
+}
+
+impl RateLimit {
+    /// Allows `requests` requests in each `period`, in a burst of up to `requests` requests.
+    ///
+    /// # Panics
+    ///
+    /// Panics where `requests` is zero.
+    pub fn new(requests: u32, period: Duration) -> RateLimit {
+        assert!(requests > 0, "a rate limit must allow at least one request");
+
+        RateLimit {
+            burst: requests,
+            interval: period / requests,
+        }
+    }
+
+    /// Allows `requests` requests each second.
+    pub fn per_second(requests: u32) -> RateLimit {
+        RateLimit::new(requests, Duration::from_secs(1))
+    }
+
+    /// Allows `requests` requests each minute.
+    pub fn per_minute(requests: u32) -> RateLimit {
+        RateLimit::new(requests, Duration::from_secs(60))
+    }
+
+    /// Changes the number of requests which may be made at once, without changing the rate.
+    ///
+    /// # Panics
+    ///
+    /// Panics where `burst` is zero.
+    pub fn with_burst(self, burst: u32) -> RateLimit {
+        assert!(burst > 0, "a rate limit must allow at least one request");
+        RateLimit { burst, ..self }
+    }
+
+    /// The number of tokens a bucket holds when it's full.
+    pub fn burst(&self) -> u32 {
+        self.burst
+    }
+
+    /// The time after which a token is added back to a bucket.
+    pub fn interval(&self) -> Duration {
+        self.interval
+    }
+}
+
+/// Extracts the key of the bucket a request takes a token from, or `None` where the request
+/// isn't limited.
+pub type KeyExtractor = dyn Fn(&State) -> Option<String> + Send + Sync + RefUnwindSafe;
+
+fn client_ip(state: &State) -> Option<String> {
+    client_addr(state).map(|addr| addr.ip().to_string())
+}
+
+/// Middleware binding which limits the rate of requests made by each client, responding with
+/// `429 Too Many Requests` and a `Retry-After` header to requests over the limit.
+///
+/// Clients are identified by their IP address by default, and `with_key` allows requests to be
+/// grouped some other way, such as by an API key. Buckets are kept in a `MemoryStore` of the
+/// middleware, unless another `RateLimitStore` is given by `with_store`. A store which fails is
+/// logged, and the request is allowed.
+///
+/// A route or scope can be given a different limit by adding another `RateLimitMiddleware` to a
+/// pipeline of its own. Requests then take a token from the bucket of each middleware they pass
+/// through, so the limit of a route applies in addition to the limit of the router. Where
+/// several middleware share a store, `with_key_prefix` keeps their buckets apart.
+///
+/// # Examples
+///
+/// ```rust
+/// # extern crate gotham;
+/// #
+/// # use gotham::middleware::rate_limit::{RateLimit, RateLimitMiddleware};
+/// # use gotham::pipeline::new_pipeline;
+/// # use gotham::pipeline::set::{finalize_pipeline_set, new_pipeline_set};
+/// # use gotham::router::builder::*;
+/// # use gotham::state::State;
+/// # use gotham::test::TestServer;
+/// #
+/// # fn handler(state: State) -> (State, &'static str) {
+/// #     (state, "hello")
+/// # }
+/// #
+/// # fn main() {
+/// let pipelines = new_pipeline_set();
+/// let (pipelines, default) = pipelines.add(
+///     new_pipeline()
+///         .add(RateLimitMiddleware::new(RateLimit::per_second(100)))
+///         .build(),
+/// );
+/// let (pipelines, login) = pipelines.add(
+///     new_pipeline()
+///         .add(RateLimitMiddleware::new(RateLimit::per_minute(1)))
+///         .build(),
+/// );
+/// let pipelines = finalize_pipeline_set(pipelines);
+///
+/// let router = build_router((default, ()), pipelines, |route| {
+///     route.get("/").to(handler);
+///     route.with_pipeline_chain((login, (default, ())), |route| {
+///         route.post("/login").to(handler);
+///     });
+/// });
+///
+/// let test_server = TestServer::new(router).unwrap();
+/// let login = || {
+///     test_server
+///         .client()
+///         .post("http://localhost/login", "", mime::TEXT_PLAIN)
+///         .perform()
+///         .unwrap()
+/// };
+///
+/// assert_eq!(login().status(), 200);
+///
+/// let response = login();
+/// assert_eq!(response.status(), 429);
+/// assert_eq!(response.headers()["Retry-After"], "60");
+/// # }
+/// ```
+pub struct RateLimitMiddleware<S = MemoryStore>
+where
+    S: RateLimitStore,
+{
+    limit: RateLimit,
+    store: Arc<S>,
+    key: Arc<KeyExtractor>,
+    key_prefix: Arc<str>,
+}
+
+impl RateLimitMiddleware<MemoryStore> {
+    /// Creates a `RateLimitMiddleware` which limits each client IP address to `limit`, keeping
+    /// buckets in a new `MemoryStore`.
+    pub fn new(limit: RateLimit) -> Self {
+        RateLimitMiddleware {
+            limit,
+            store: Arc::new(MemoryStore::new()),
+            key: Arc::new(client_ip),
+            key_prefix: Arc::from(""),
+        }
+    }
+}
+
+impl<S> RateLimitMiddleware<S>
+where
+    S: RateLimitStore,
+{
+    /// Keeps buckets in `store`, in place of a `MemoryStore`.
+    pub fn with_store<T>(self, store: T) -> RateLimitMiddleware<T>
+    where
+        T: RateLimitStore,
+    {
+        RateLimitMiddleware {
+            limit: self.limit,
+            store: Arc::new(store),
+            key: self.key,
+            key_prefix: self.key_prefix,
+        }
+    }
+
+    /// Groups requests into buckets by the key returned by `key`, in place of the client IP
+    /// address. Requests for which `key` returns `None` aren't limited.
+    pub fn with_key<F>(self, key: F) -> Self
+    where
+        F: Fn(&State) -> Option<String> + Send + Sync + RefUnwindSafe + 'static,
+    {
+        RateLimitMiddleware {
+            key: Arc::new(key),
+            ..self
+        }
+    }
+
+    /// Prefixes the keys of buckets with `prefix`, so that middleware with different limits can
+    /// share a store.
+    pub fn with_key_prefix(self, prefix: &str) -> Self {
+        RateLimitMiddleware {
+            key_prefix: Arc::from(prefix),
+            ..self
+        }
+    }
+}
+
+impl<S> Clone for RateLimitMiddleware<S>
+where
+    S: RateLimitStore,
+{
+    fn clone(&self) -> Self {
+        RateLimitMiddleware {
+            limit: self.limit,
+            store: self.store.clone(),
+            key: self.key.clone(),
+            key_prefix: self.key_prefix.clone(),
+        }
+    }
+}
+
+// Retry-After is given in whole seconds, rounded up so that the client doesn't retry too soon.
+pub(crate) fn retry_after_secs(retry_after: Duration) -> u64 {
+    let secs = retry_after.as_secs();
+    if retry_after.subsec_nanos() > 0 {
+        secs + 1
+    } else {
+        secs.max(1)
+    }
+}
+
+/// `Middleware` trait implementation.
+impl<S> Middleware for RateLimitMiddleware<S>
+where
+    S: RateLimitStore + 'static,
+{
+    /// Takes a token for the request, refusing it where none are left.
+    fn call<Chain>(self, state: State, chain: Chain) -> Box<HandlerFuture>
+    where
+        Chain: FnOnce(State) -> Box<HandlerFuture> + Send + 'static,
+    {
+        let key = match (self.key)(&state) {
+            Some(key) => format!("{}{}", self.key_prefix, key),
+            None => return chain(state),
+        };
+
+        let taken = self.store.take(&key, &self.limit);
+        Box::new(taken.then(move |result| match result {
+            Ok(RateLimitOutcome::Allowed { remaining }) => {
+                trace!(
+                    "[{}] rate limit allows request, {} remaining",
+                    request_id(&state),
+                    remaining
+                );
+                chain(state)
+            }
+            Ok(RateLimitOutcome::Limited { retry_after }) => {
+                trace!("[{}] rate limit exceeded by {}", request_id(&state), key);
+                let mut res = create_empty_response(&state, StatusCode::TOO_MANY_REQUESTS);
+                res.headers_mut().insert(
+                    RETRY_AFTER,
+                    HeaderValue::from(retry_after_secs(retry_after)),
+                );
+                Box::new(future::ok((state, res)))
+            }
+            Err(e) => {
+                error!(
+                    "[{}] rate limit store failed, allowing request: {}",
+                    request_id(&state),
+                    e
+                );
+                chain(state)
+            }
+        }))
+    }
+}
+
+/// `NewMiddleware` trait implementation.
+impl<S> NewMiddleware for RateLimitMiddleware<S>
+where
+    S: RateLimitStore + 'static,
+{
+    type Instance = Self;
+
+    /// Clones the current middleware to a new instance.
+    fn new_middleware(&self) -> io::Result<Self::Instance> {
+        Ok(self.clone())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    use hyper::header::HeaderMap;
+    use std::sync::Mutex;
+
+    use crate::pipeline::new_pipeline;
+    use crate::pipeline::single::single_pipeline;
+    use crate::router::builder::*;
+    use crate::state::FromState;
+    use crate::test::TestServer;
+
+    fn handler(state: State) -> (State, &'static str) {
+        (state, "hello")
+    }
+
+    fn test_server<S>(middleware: RateLimitMiddleware<S>) -> TestServer
+    where
+        S: RateLimitStore + 'static,
+    {
+        let (chain, pipelines) = single_pipeline(new_pipeline().add(middleware).build());
+        TestServer::new(build_router(chain, pipelines, |route| {
+            route.get("/").to(handler);
+        }))
+        .unwrap()
+    }
+
+    fn get(test_server: &TestServer, api_key: Option<&'static str>) -> StatusCode {
+        let client = test_server.client();
+        let mut req = client.get("http://localhost/");
+        if let Some(api_key) = api_key {
+            req = req.with_header("x-api-key", HeaderValue::from_static(api_key));
+        }
+        req.perform().unwrap().status()
+    }
+
+    #[test]
+    fn limits_requests_by_client_ip() {
+        let test_server = test_server(RateLimitMiddleware::new(
+            RateLimit::per_minute(60).with_burst(2),
+        ));
+
+        assert_eq!(get(&test_server, None), StatusCode::OK);
+        assert_eq!(get(&test_server, None), StatusCode::OK);
+
+        let res = test_server
+            .client()
+            .get("http://localhost/")
+            .perform()
+            .unwrap();
+        assert_eq!(res.status(), StatusCode::TOO_MANY_REQUESTS);
+        assert_eq!(res.headers()[RETRY_AFTER], "1");
+    }
+
+    #[test]
+    fn limits_requests_by_key() {
+        let middleware = RateLimitMiddleware::new(RateLimit::per_minute(1)).with_key(|state| {
+            HeaderMap::borrow_from(state)
+                .get("x-api-key")
+                .and_then(|key| key.to_str().ok())
+                .map(ToOwned::to_owned)
+        });
+        let test_server = test_server(middleware);
+
+        assert_eq!(get(&test_server, Some("a")), StatusCode::OK);
+        assert_eq!(get(&test_server, Some("a")), StatusCode::TOO_MANY_REQUESTS);
+        assert_eq!(get(&test_server, Some("b")), StatusCode::OK);
+
+        // requests without a key aren't limited
+        assert_eq!(get(&test_server, None), StatusCode::OK);
+        assert_eq!(get(&test_server, None), StatusCode::OK);
+    }
+
+    struct RecordingStore {
+        keys: Mutex<Vec<String>>,
+        fail: bool,
+    }
+
+    impl RateLimitStore for RecordingStore {
+        fn take(&self, key: &str, _limit: &RateLimit) -> Box<RateLimitFuture> {
+            self.keys.lock().unwrap().push(key.to_owned());
+            if self.fail {
+                Box::new(future::err(io::Error::other("unavailable")))
+            } else {
+                Box::new(future::ok(RateLimitOutcome::Limited {
+                    retry_after: Duration::from_millis(2500),
+                }))
+            }
+        }
+    }
+
+    #[test]
+    fn uses_the_configured_store() {
+        let store = Arc::new(RecordingStore {
+            keys: Mutex::new(vec![]),
+            fail: false,
+        });
+        let middleware = RateLimitMiddleware::new(RateLimit::per_second(1))
+            .with_store(store.clone())
+            .with_key_prefix("api:");
+        let test_server = test_server(middleware);
+
+        let res = test_server
+            .client()
+            .get("http://localhost/")
+            .perform()
+            .unwrap();
+        assert_eq!(res.status(), StatusCode::TOO_MANY_REQUESTS);
+        assert_eq!(res.headers()[RETRY_AFTER], "3");
+        assert_eq!(store.keys.lock().unwrap()[0], "api:127.0.0.1");
+    }
+
+    #[test]
+    fn allows_requests_when_the_store_fails() {
+        let store = RecordingStore {
+            keys: Mutex::new(vec![]),
+            fail: true,
+        };
+        let test_server =
+            test_server(RateLimitMiddleware::new(RateLimit::per_second(1)).with_store(store));
+        assert_eq!(get(&test_server, None), StatusCode::OK);
+    }
+
+    #[test]
+    fn rounds_retry_after_up() {
+        assert_eq!(retry_after_secs(Duration::from_millis(1)), 1);
+        assert_eq!(retry_after_secs(Duration::from_secs(0)), 1);
+        assert_eq!(retry_after_secs(Duration::from_secs(2)), 2);
+        assert_eq!(retry_after_secs(Duration::from_millis(2001)), 3);
+    }
+}
@@ -0,0 +1,193 @@
+//! Defines the storage of the token buckets used by `RateLimitMiddleware`.
+
+use std::collections::HashMap;
+use std::io;
+use std::panic::RefUnwindSafe;
+use std::sync::{Arc, Mutex, PoisonError};
+use std::time::{Duration, Instant};
+
+use futures::{future, Future};
+
+use crate::middleware::rate_limit::RateLimit;
+
+// How often buckets which have refilled completely are removed from a `MemoryStore`.
+const SWEEP_INTERVAL: Duration = Duration::from_secs(60);
+
+/// The outcome of taking a token from the bucket of a client.
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub enum RateLimitOutcome {
+    /// The request is allowed, and `remaining` whole tokens are left in the bucket.
+    Allowed {
+        /// The number of requests which could be made immediately after this one.
+        remaining: u32,
+    },
+    /// The request exceeds the limit, and a token is next available after `retry_after`.
+    Limited {
+        /// The time until the next request would be allowed.
+        retry_after: Duration,
+    },
+}
+
+/// Type alias for the trait objects returned by `RateLimitStore::take`.
+pub type RateLimitFuture = dyn Future<Item = RateLimitOutcome, Error = io::Error> + Send;
+
+/// Stores the token buckets of `RateLimitMiddleware`, keyed by the client each bucket belongs
+/// to.
+///
+/// `MemoryStore` keeps buckets within the process. Where an application runs as several
+/// processes, a store which keeps buckets in a shared service can be provided instead, so that
+/// each client has one bucket between all of the processes.
+///
+/// `take` returns a future, which the middleware chains into the future of the request, so that
+/// a store using a network service can wait for the service without blocking the thread serving
+/// requests. A store which completes its work immediately returns a future which has already
+/// resolved, such as one created by `futures::future::result`.
+pub trait RateLimitStore: Send + Sync + RefUnwindSafe {
+    /// Takes a token from the bucket for `key`, which holds up to `limit.burst()` tokens and
+    /// regains one after each `limit.interval()`, creating a full bucket where there wasn't one.
+    fn take(&self, key: &str, limit: &RateLimit) -> Box<RateLimitFuture>;
+}
+
+impl<S> RateLimitStore for Arc<S>
+where
+    S: RateLimitStore + ?Sized,
+{
+    fn take(&self, key: &str, limit: &RateLimit) -> Box<RateLimitFuture> {
+        (**self).take(key, limit)
+    }
+}
+
+/// A `RateLimitStore` which keeps buckets in memory. Buckets which have refilled completely are
+/// forgotten periodically, as they're equivalent to a new bucket.
+#[derive(Default)]
+pub struct MemoryStore {
+    state: Mutex<MemoryState>,
+}
+
+struct MemoryState {
+    buckets: HashMap<String, Bucket>,
+    swept: Instant,
+}
+
+impl Default for MemoryState {
+    fn default() -> Self {
+        MemoryState {
+            buckets: HashMap::new(),
+            swept: Instant::now(),
+        }
+    }
+}
+
+struct Bucket {
+    tokens: f64,
+    updated: Instant,
+}
+
+impl Bucket {
+    fn refill(&mut self, limit: &RateLimit, now: Instant) {
+        let elapsed = now.duration_since(self.updated).as_secs_f64();
+        let regained = elapsed / limit.interval().as_secs_f64();
+        self.tokens = (self.tokens + regained).min(f64::from(limit.burst()));
+        self.updated = now;
+    }
+}
+
+impl MemoryStore {
+    /// Creates a new, empty `MemoryStore`.
+    pub fn new() -> Self {
+        MemoryStore::default()
+    }
+
+    #[cfg(test)]
+    fn len(&self) -> usize {
+        self.state.lock().unwrap().buckets.len()
+    }
+
+    fn take_now(&self, key: &str, limit: &RateLimit) -> RateLimitOutcome {
+        let mut state = self.state.lock().unwrap_or_else(PoisonError::into_inner);
+        let now = Instant::now();
+
+        if now.duration_since(state.swept) >= SWEEP_INTERVAL {
+            state.buckets.retain(|_, bucket| {
+                bucket.refill(limit, now);
+                bucket.tokens < f64::from(limit.burst())
+            });
+            state.swept = now;
+        }
+
+        let bucket = state.buckets.entry(key.to_owned()).or_insert(Bucket {
+            tokens: f64::from(limit.burst()),
+            updated: now,
+        });
+        bucket.refill(limit, now);
+
+        if bucket.tokens >= 1.0 {
+            bucket.tokens -= 1.0;
+            RateLimitOutcome::Allowed {
+                remaining: bucket.tokens as u32,
+            }
+        } else {
+            let wait = (1.0 - bucket.tokens) * limit.interval().as_secs_f64();
+            RateLimitOutcome::Limited {
+                retry_after: Duration::from_secs_f64(wait),
+            }
+        }
+    }
+}
+
+impl RateLimitStore for MemoryStore {
+    fn take(&self, key: &str, limit: &RateLimit) -> Box<RateLimitFuture> {
+        Box::new(future::ok(self.take_now(key, limit)))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn takes_tokens_until_the_bucket_is_empty() {
+        let store = MemoryStore::new();
+        let limit = RateLimit::new(2, Duration::from_secs(60));
+
+        assert_eq!(
+            store.take("a", &limit).wait().unwrap(),
+            RateLimitOutcome::Allowed { remaining: 1 }
+        );
+        assert_eq!(
+            store.take("a", &limit).wait().unwrap(),
+            RateLimitOutcome::Allowed { remaining: 0 }
+        );
+
+        match store.take("a", &limit).wait().unwrap() {
+            RateLimitOutcome::Limited { retry_after } => {
+                assert!(retry_after <= Duration::from_secs(30));
+                assert!(retry_after > Duration::from_secs(29));
+            }
+            outcome => panic!("unexpected outcome: {:?}", outcome),
+        }
+
+        // buckets are kept for each key
+        assert_eq!(
+            store.take("b", &limit).wait().unwrap(),
+            RateLimitOutcome::Allowed { remaining: 1 }
+        );
+        assert_eq!(store.len(), 2);
+    }
+
+    #[test]
+    fn refills_buckets_over_time() {
+        let limit = RateLimit::new(1, Duration::from_secs(10));
+        let now = Instant::now();
+        let mut bucket = Bucket {
+            tokens: 0.0,
+            updated: now,
+        };
+
+        bucket.refill(&limit, now + Duration::from_secs(5));
+        assert!((bucket.tokens - 0.5).abs() < 1e-9);
+
+        bucket.refill(&limit, now + Duration::from_secs(60));
+        assert!((bucket.tokens - 1.0).abs() < 1e-9);
+    }
+}
@@ -0,0 +1,204 @@
+//! Request ID middleware, which takes the request ID from a configurable header and returns it in
+//! the response, so that requests can be correlated across services.
+use futures::{future, Future};
+use hyper::header::{HeaderMap, HeaderName, HeaderValue};
+use std::io;
+use uuid::Uuid;
+
+use crate::handler::HandlerFuture;
+use crate::middleware::{Middleware, NewMiddleware};
+use crate::state::{replace_request_id, request_id, FromState, State};
+
+// Longer values are most likely not request IDs, and aren't worth repeating in every log entry.
+const MAX_REQUEST_ID_LENGTH: usize = 200;
+
+/// Middleware binding which sets the request ID returned by `request_id` from a request header,
+/// or to a generated UUID where the header is missing, and adds it to the response in the same
+/// header.
+///
+/// The header is `X-Request-ID` by default. Values which are empty, longer than 200 bytes or
+/// contain anything other than visible ASCII characters are replaced by a generated UUID.
+///
+/// # Examples
+///
+/// ```rust
+/// # extern crate gotham;
+/// # extern crate hyper;
+/// #
+/// # use hyper::header::{HeaderName, HeaderValue};
+/// # use gotham::middleware::request_id::RequestIdMiddleware;
+/// # use gotham::pipeline::new_pipeline;
+/// # use gotham::pipeline::single::single_pipeline;
+/// # use gotham::router::builder::*;
+/// # use gotham::state::{request_id, State};
+/// # use gotham::test::TestServer;
+/// #
+/// fn handler(state: State) -> (State, String) {
+///     let id = request_id(&state).to_owned();
+///     (state, id)
+/// }
+///
+/// # fn main() {
+/// let middleware =
+///     RequestIdMiddleware::default().with_header_name(HeaderName::from_static("x-correlation-id"));
+///
+/// let (chain, pipelines) = single_pipeline(new_pipeline().add(middleware).build());
+/// let router = build_router(chain, pipelines, |route| {
+///     route.get("/").to(handler);
+/// });
+///
+/// let test_server = TestServer::new(router).unwrap();
+/// let response = test_server
+///     .client()
+///     .get("http://localhost/")
+///     .with_header("x-correlation-id", HeaderValue::from_static("4b8e3c2d"))
+///     .perform()
+///     .unwrap();
+///
+/// assert_eq!(response.headers()["x-correlation-id"], "4b8e3c2d");
+/// assert_eq!(response.read_utf8_body().unwrap(), "4b8e3c2d");
+/// # }
+/// ```
+#[derive(Clone, Debug)]
+pub struct RequestIdMiddleware {
+    header: HeaderName,
+}
+
+impl Default for RequestIdMiddleware {
+    fn default() -> RequestIdMiddleware {
+        RequestIdMiddleware {
+            header: HeaderName::from_static("x-request-id"),
+        }
+    }
+}
+
+impl RequestIdMiddleware {
+    /// Changes the header the request ID is read from and written to.
+    pub fn with_header_name(self, header: HeaderName) -> RequestIdMiddleware {
+        RequestIdMiddleware { header }
+    }
+}
+
+fn is_valid_request_id(value: &HeaderValue) -> bool {
+    let bytes = value.as_bytes();
+    !bytes.is_empty()
+        && bytes.len() <= MAX_REQUEST_ID_LENGTH
+        && bytes.iter().all(|b| b.is_ascii_graphic())
+}
+
+/// `Middleware` trait implementation.
+impl Middleware for RequestIdMiddleware {
+    /// Sets the request ID, and attaches it to the response headers.
+    fn call<Chain>(self, mut state: State, chain: Chain) -> Box<HandlerFuture>
+    where
+        Chain: FnOnce(State) -> Box<HandlerFuture>,
+    {
+        let incoming = HeaderMap::borrow_from(&state)
+            .get(&self.header)
+            .filter(|value| is_valid_request_id(value))
+            .and_then(|value| value.to_str().ok())
+            .map(ToOwned::to_owned);
+
+        let id = incoming.unwrap_or_else(|| Uuid::new_v4().to_hyphenated().to_string());
+        replace_request_id(&mut state, id);
+
+        let header = self.header;
+        let f = chain(state).and_then(move |(state, mut response)| {
+            // generated and validated IDs are always valid header values
+            if let Ok(value) = HeaderValue::from_str(request_id(&state)) {
+                response.headers_mut().insert(header, value);
+            }
+
+            future::ok((state, response))
+        });
+
+        Box::new(f)
+    }
+}
+
+/// `NewMiddleware` trait implementation.
+impl NewMiddleware for RequestIdMiddleware {
+    type Instance = Self;
+
+    /// Clones the current middleware to a new instance.
+    fn new_middleware(&self) -> io::Result<Self::Instance> {
+        Ok(self.clone())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    use crate::pipeline::new_pipeline;
+    use crate::pipeline::single::single_pipeline;
+    use crate::router::builder::*;
+    use crate::test::TestServer;
+
+    fn handler(state: State) -> (State, String) {
+        let id = request_id(&state).to_owned();
+        (state, id)
+    }
+
+    fn test_server(middleware: RequestIdMiddleware) -> TestServer {
+        let (chain, pipelines) = single_pipeline(new_pipeline().add(middleware).build());
+        TestServer::new(build_router(chain, pipelines, |route| {
+            route.get("/").to(handler);
+        }))
+        .unwrap()
+    }
+
+    #[test]
+    fn uses_or_generates_request_ids() {
+        let test_server = test_server(RequestIdMiddleware::default());
+
+        let response = test_server
+            .client()
+            .get("http://localhost/")
+            .with_header("x-request-id", HeaderValue::from_static("abc-123"))
+            .perform()
+            .unwrap();
+        assert_eq!(response.headers()["x-request-id"], "abc-123");
+        assert_eq!(response.read_utf8_body().unwrap(), "abc-123");
+
+        let response = test_server
+            .client()
+            .get("http://localhost/")
+            .perform()
+            .unwrap();
+        let header = response.headers()["x-request-id"]
+            .to_str()
+            .unwrap()
+            .to_owned();
+        assert_eq!(4, Uuid::parse_str(&header).unwrap().get_version_num());
+        assert_eq!(response.read_utf8_body().unwrap(), header);
+    }
+
+    #[test]
+    fn replaces_invalid_request_ids() {
+        let test_server = test_server(
+            RequestIdMiddleware::default().with_header_name(HeaderName::from_static("x-trace")),
+        );
+
+        let long = "a".repeat(MAX_REQUEST_ID_LENGTH + 1);
+        for value in &["", "has space", long.as_str()] {
+            let response = test_server
+                .client()
+                .get("http://localhost/")
+                .with_header("x-trace", HeaderValue::from_str(value).unwrap())
+                .perform()
+                .unwrap();
+            let header = response.headers()["x-trace"].to_str().unwrap().to_owned();
+            assert!(Uuid::parse_str(&header).is_ok());
+        }
+
+        // only the configured header is trusted
+        let response = test_server
+            .client()
+            .get("http://localhost/")
+            .with_header("x-request-id", HeaderValue::from_static("abc-123"))
+            .perform()
+            .unwrap();
+        assert_ne!(response.read_utf8_body().unwrap(), "abc-123");
+    }
+}
@@ -0,0 +1,208 @@
+//! Response mapping middleware, which changes the responses produced by the rest of the pipeline
+//! chain and the handler.
+use std::io;
+use std::panic::RefUnwindSafe;
+use std::sync::Arc;
+
+use futures::{future, Future, IntoFuture};
+use hyper::{Body, Response};
+
+use crate::handler::{HandlerError, HandlerFuture};
+use crate::middleware::{Middleware, NewMiddleware};
+use crate::state::State;
+
+type ResponseFuture = dyn Future<Item = Response<Body>, Error = HandlerError> + Send;
+
+type ResponseMap =
+    dyn Fn(&State, Response<Body>) -> Box<ResponseFuture> + Send + Sync + RefUnwindSafe;
+
+/// Middleware binding which passes each response produced by the rest of the pipeline chain and
+/// the handler through a closure, so that adding a header or wrapping a body doesn't need a
+/// `Middleware` implementation of its own.
+///
+/// The closure is given the `State` of the request alongside the response. Responses to requests
+/// which failed with a `HandlerError` aren't passed to the closure. A closure which needs to do
+/// asynchronous work, such as reading the body, can be given to `new_async` instead, and may fail
+/// the request with a `HandlerError`.
+///
+/// # Examples
+///
+/// ```rust
+/// # extern crate gotham;
+/// # extern crate hyper;
+/// #
+/// # use hyper::header::{HeaderValue, SERVER};
+/// # use gotham::middleware::response_map::ResponseMapMiddleware;
+/// # use gotham::pipeline::new_pipeline;
+/// # use gotham::pipeline::single::single_pipeline;
+/// # use gotham::router::builder::*;
+/// # use gotham::state::State;
+/// # use gotham::test::TestServer;
+/// #
+/// # fn handler(state: State) -> (State, &'static str) {
+/// #     (state, "hello")
+/// # }
+/// #
+/// # fn main() {
+/// let middleware = ResponseMapMiddleware::new(|_state, mut response| {
+///     response
+///         .headers_mut()
+///         .insert(SERVER, HeaderValue::from_static("gotham"));
+///     response
+/// });
+///
+/// let (chain, pipelines) = single_pipeline(new_pipeline().add(middleware).build());
+/// let router = build_router(chain, pipelines, |route| {
+///     route.get("/").to(handler);
+/// });
+///
+/// let test_server = TestServer::new(router).unwrap();
+/// let response = test_server
+///     .client()
+///     .get("http://localhost/")
+///     .perform()
+///     .unwrap();
+///
+/// assert_eq!(response.headers()["Server"], "gotham");
+/// # }
+/// ```
+#[derive(Clone)]
+pub struct ResponseMapMiddleware {
+    map: Arc<ResponseMap>,
+}
+
+impl ResponseMapMiddleware {
+    /// Creates a `ResponseMapMiddleware` which replaces each response with the one returned by
+    /// `map`.
+    pub fn new<F>(map: F) -> ResponseMapMiddleware
+    where
+        F: Fn(&State, Response<Body>) -> Response<Body> + Send + Sync + RefUnwindSafe + 'static,
+    {
+        ResponseMapMiddleware::new_async(move |state, response| {
+            future::ok::<_, HandlerError>(map(state, response))
+        })
+    }
+
+    /// Creates a `ResponseMapMiddleware` which replaces each response with the one `map`
+    /// resolves to, failing the request where `map` fails.
+    pub fn new_async<F, R>(map: F) -> ResponseMapMiddleware
+    where
+        F: Fn(&State, Response<Body>) -> R + Send + Sync + RefUnwindSafe + 'static,
+        R: IntoFuture<Item = Response<Body>, Error = HandlerError>,
+        R::Future: Send + 'static,
+    {
+        ResponseMapMiddleware {
+            map: Arc::new(move |state, response| Box::new(map(state, response).into_future())),
+        }
+    }
+}
+
+/// `Middleware` trait implementation.
+impl Middleware for ResponseMapMiddleware {
+    /// Passes the response of the rest of the chain through the closure.
+    fn call<Chain>(self, state: State, chain: Chain) -> Box<HandlerFuture>
+    where
+        Chain: FnOnce(State) -> Box<HandlerFuture>,
+    {
+        let map = self.map;
+        let f = chain(state).and_then(move |(state, response)| {
+            map(&state, response).then(move |result| match result {
+                Ok(response) => Ok((state, response)),
+                Err(e) => Err((state, e)),
+            })
+        });
+
+        Box::new(f)
+    }
+}
+
+/// `NewMiddleware` trait implementation.
+impl NewMiddleware for ResponseMapMiddleware {
+    type Instance = Self;
+
+    /// Clones the current middleware to a new instance.
+    fn new_middleware(&self) -> io::Result<Self::Instance> {
+        Ok(self.clone())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    use futures::Stream;
+    use hyper::header::{HeaderValue, CONTENT_LENGTH};
+    use hyper::{StatusCode, Uri};
+
+    use crate::handler::IntoHandlerError;
+    use crate::pipeline::new_pipeline;
+    use crate::pipeline::single::single_pipeline;
+    use crate::router::builder::*;
+    use crate::state::FromState;
+    use crate::test::TestServer;
+
+    fn handler(state: State) -> (State, &'static str) {
+        (state, "hello")
+    }
+
+    fn test_server(middleware: ResponseMapMiddleware) -> TestServer {
+        let (chain, pipelines) = single_pipeline(new_pipeline().add(middleware).build());
+        TestServer::new(build_router(chain, pipelines, |route| {
+            route.get("/").to(handler);
+            route.get("/fail").to(handler);
+        }))
+        .unwrap()
+    }
+
+    #[test]
+    fn maps_responses() {
+        let test_server = test_server(ResponseMapMiddleware::new(|state, mut response| {
+            let path = Uri::borrow_from(state).path();
+            let value = HeaderValue::from_str(path).unwrap();
+            response.headers_mut().insert("x-path", value);
+            response
+        }));
+
+        let response = test_server
+            .client()
+            .get("http://localhost/")
+            .perform()
+            .unwrap();
+        assert_eq!(response.headers()["x-path"], "/");
+        assert_eq!(response.read_utf8_body().unwrap(), "hello");
+    }
+
+    #[test]
+    fn maps_responses_asynchronously() {
+        let test_server = test_server(ResponseMapMiddleware::new_async(|state, response| {
+            let fail = Uri::borrow_from(state).path() == "/fail";
+            let (mut parts, body) = response.into_parts();
+
+            body.concat2().then(move |result| {
+                let body = result.map_err(|e| e.into_handler_error())?;
+                if fail {
+                    let e = io::Error::other("mapping failed");
+                    return Err(e.into_handler_error().with_status(StatusCode::BAD_GATEWAY));
+                }
+
+                let wrapped = format!("<{}>", String::from_utf8_lossy(&body));
+                parts.headers.remove(CONTENT_LENGTH);
+                Ok(Response::from_parts(parts, Body::from(wrapped)))
+            })
+        }));
+
+        let response = test_server
+            .client()
+            .get("http://localhost/")
+            .perform()
+            .unwrap();
+        assert_eq!(response.read_utf8_body().unwrap(), "<hello>");
+
+        let response = test_server
+            .client()
+            .get("http://localhost/fail")
+            .perform()
+            .unwrap();
+        assert_eq!(response.status(), StatusCode::BAD_GATEWAY);
+    }
+}
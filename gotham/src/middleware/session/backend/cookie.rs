@@ -0,0 +1,294 @@
+use std::io;
+use std::sync::Arc;
+
+use ::cookie::{Cookie, CookieJar, Key};
+use base64;
+use futures::{future, Future};
+use log::trace;
+
+use crate::middleware::session::backend::{
+    Backend, NewBackend, SessionCookieFuture, SessionFuture, SessionWriteFuture,
+};
+use crate::middleware::session::{SessionError, SessionIdentifier};
+
+// The name under which a session is sealed. The private cookies of the `cookie` crate bind their
+// value to the name of the cookie, so the name must not change between requests.
+const SEALED_NAME: &str = "_gotham_session";
+
+// Browsers store at least 4096 bytes of each cookie, including its name and attributes.
+const DEFAULT_MAX_SIZE: usize = 3800;
+
+/// Defines a session storage which keeps no state on the server, holding the session data in the
+/// session cookie instead.
+///
+/// The session data is signed with HMAC-SHA256, so that it can't be altered by the user agent,
+/// and by calling `encrypted` it's encrypted with AES-256-GCM instead, so that it also can't be
+/// read by the user agent. Sessions are sealed with the newest key, and opened with any of the
+/// keys given by `with_previous_key`, so that keys can be rotated without invalidating every
+/// session. A session which can't be opened is replaced by a new session.
+///
+/// Cookies are limited in size, so this is only suited to small sessions. A session which is
+/// larger than the limit given by `with_max_size` once sealed isn't persisted, and the response
+/// is replaced by `500 Internal Server Error`.
+///
+/// As the session is held by the user agent, discarding a session only removes it from the user
+/// agent, and an earlier copy of the session cookie remains valid until the key is retired.
+///
+/// ## Examples
+///
+/// ```rust
+/// # extern crate gotham;
+/// # use gotham::middleware::session::{CookieBackend, NewSessionMiddleware};
+/// # fn main() {
+/// let backend = CookieBackend::new(b"a new key of at least thirty-two bytes")
+///     .with_previous_key(b"a retired key of at least thirty-two bytes")
+///     .encrypted();
+///
+/// NewSessionMiddleware::new(backend)
+/// # ;}
+/// ```
+#[derive(Clone)]
+pub struct CookieBackend {
+    keys: Arc<Vec<Key>>,
+    encrypted: bool,
+    max_size: usize,
+}
+
+impl CookieBackend {
+    /// Creates a new `CookieBackend` which signs sessions with a key derived from `master_key`.
+    ///
+    /// # Panics
+    ///
+    /// Panics where `master_key` is shorter than 32 bytes. The key must be cryptographically
+    /// random.
+    pub fn new(master_key: &[u8]) -> CookieBackend {
+        CookieBackend {
+            keys: Arc::new(vec![Key::from_master(master_key)]),
+            encrypted: false,
+            max_size: DEFAULT_MAX_SIZE,
+        }
+    }
+
+    /// Accepts sessions sealed with a key derived from `master_key`, which has been replaced.
+    /// Sessions are only accepted with a previous key until they're next persisted, which seals them
+    /// with the newest key.
+    ///
+    /// # Panics
+    ///
+    /// Panics where `master_key` is shorter than 32 bytes.
+    pub fn with_previous_key(self, master_key: &[u8]) -> CookieBackend {
+        let mut keys = (*self.keys).clone();
+        keys.push(Key::from_master(master_key));
+
+        CookieBackend {
+            keys: Arc::new(keys),
+            ..self
+        }
+    }
+
+    /// Encrypts sessions, so that their content is hidden from the user agent as well as being
+    /// protected from alteration. Sessions which were only signed are no longer accepted.
+    pub fn encrypted(self) -> CookieBackend {
+        CookieBackend {
+            encrypted: true,
+            ..self
+        }
+    }
+
+    /// Limits the size of a sealed session to `max_size` bytes, which defaults to 3800 bytes to
+    /// leave room for the name and attributes of the session cookie.
+    pub fn with_max_size(self, max_size: usize) -> CookieBackend {
+        CookieBackend { max_size, ..self }
+    }
+
+    fn seal(&self, content: &[u8]) -> Result<String, SessionError> {
+        let value = base64::encode_config(content, base64::URL_SAFE_NO_PAD);
+        let cookie = Cookie::new(SEALED_NAME, value);
+
+        let mut jar = CookieJar::new();
+        if self.encrypted {
+            jar.private(&self.keys[0]).add(cookie);
+        } else {
+            jar.signed(&self.keys[0]).add(cookie);
+        }
+
+        let sealed = jar
+            .get(SEALED_NAME)
+            .map(|cookie| cookie.value().to_owned())
+            .unwrap_or_default();
+
+        if sealed.len() > self.max_size {
+            return Err(SessionError::Backend(format!(
+                "cookie: session of {} bytes exceeds the limit of {} bytes",
+                sealed.len(),
+                self.max_size
+            )));
+        }
+
+        Ok(sealed)
+    }
+
+    fn open(&self, sealed: &str) -> Option<Vec<u8>> {
+        let opened = self.keys.iter().filter_map(|key| {
+            let mut jar = CookieJar::new();
+            jar.add_original(Cookie::new(SEALED_NAME, sealed.to_owned()));
+
+            if self.encrypted {
+                jar.private(key).get(SEALED_NAME)
+            } else {
+                jar.signed(key).get(SEALED_NAME)
+            }
+        });
+
+        for cookie in opened {
+            if let Ok(content) = base64::decode_config(cookie.value(), base64::URL_SAFE_NO_PAD) {
+                return Some(content);
+            }
+        }
+
+        trace!(" session cookie could not be opened with any key");
+        None
+    }
+}
+
+impl NewBackend for CookieBackend {
+    type Instance = CookieBackend;
+
+    fn new_backend(&self) -> io::Result<Self::Instance> {
+        Ok(self.clone())
+    }
+}
+
+impl Backend for CookieBackend {
+    fn persist_session(
+        &self,
+        identifier: SessionIdentifier,
+        content: &[u8],
+    ) -> Box<SessionWriteFuture> {
+        Box::new(self.persist_session_cookie(identifier, content).map(|_| ()))
+    }
+
+    fn persist_session_cookie(
+        &self,
+        _identifier: SessionIdentifier,
+        content: &[u8],
+    ) -> Box<SessionCookieFuture> {
+        let sealed = self
+            .seal(content)
+            .map(|value| Some(SessionIdentifier { value }));
+        Box::new(future::result(sealed))
+    }
+
+    fn read_session(&self, identifier: SessionIdentifier) -> Box<SessionFuture> {
+        Box::new(future::ok(self.open(&identifier.value)))
+    }
+
+    fn drop_session(&self, _identifier: SessionIdentifier) -> Box<SessionWriteFuture> {
+        // nothing is stored, and the session cookie is removed by the middleware
+        Box::new(future::ok(()))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const KEY: &[u8] = b"0123456789abcdef0123456789abcdef";
+    const OTHER_KEY: &[u8] = b"fedcba9876543210fedcba9876543210";
+
+    fn identifier(value: &str) -> SessionIdentifier {
+        SessionIdentifier {
+            value: value.to_owned(),
+        }
+    }
+
+    fn seal(backend: &CookieBackend, content: &[u8]) -> SessionIdentifier {
+        backend
+            .persist_session_cookie(identifier("unused"), content)
+            .wait()
+            .expect("failed to persist")
+            .expect("no identifier for the session cookie")
+    }
+
+    fn read(backend: &CookieBackend, identifier: SessionIdentifier) -> Option<Vec<u8>> {
+        backend
+            .read_session(identifier)
+            .wait()
+            .expect("no response from backend")
+    }
+
+    #[test]
+    fn cookie_backend_signed_test() {
+        let backend = CookieBackend::new(KEY);
+        let sealed = seal(&backend, b"session content");
+        assert_eq!(
+            read(&backend, sealed.clone()),
+            Some(b"session content".to_vec())
+        );
+
+        // the content is readable from the cookie, but can't be altered
+        let content = base64::encode_config(b"session content", base64::URL_SAFE_NO_PAD);
+        assert!(sealed.value.ends_with(&content));
+
+        let altered = base64::encode_config(b"altered content", base64::URL_SAFE_NO_PAD);
+        let altered = sealed.value.replace(&content, &altered);
+        assert_eq!(read(&backend, identifier(&altered)), None);
+        assert_eq!(read(&backend, identifier("not a session")), None);
+
+        // sessions sealed with an unknown key, or differently, aren't accepted
+        assert_eq!(read(&CookieBackend::new(OTHER_KEY), sealed.clone()), None);
+        assert_eq!(read(&backend.clone().encrypted(), sealed), None);
+    }
+
+    #[test]
+    fn cookie_backend_encrypted_test() {
+        let backend = CookieBackend::new(KEY).encrypted();
+        let sealed = seal(&backend, b"session content");
+        assert_eq!(
+            read(&backend, sealed.clone()),
+            Some(b"session content".to_vec())
+        );
+
+        let content = base64::encode_config(b"session content", base64::URL_SAFE_NO_PAD);
+        assert!(!sealed.value.contains(&content));
+
+        assert_eq!(read(&CookieBackend::new(KEY), sealed.clone()), None);
+        assert_eq!(
+            read(&CookieBackend::new(OTHER_KEY).encrypted(), sealed),
+            None
+        );
+    }
+
+    #[test]
+    fn cookie_backend_key_rotation_test() {
+        let old = CookieBackend::new(KEY).encrypted();
+        let sealed = seal(&old, b"session content");
+
+        let rotated = CookieBackend::new(OTHER_KEY)
+            .with_previous_key(KEY)
+            .encrypted();
+        assert_eq!(read(&rotated, sealed), Some(b"session content".to_vec()));
+
+        // persisting the session again seals it with the new key
+        let resealed = seal(&rotated, b"session content");
+        assert_eq!(read(&old, resealed.clone()), None);
+        assert_eq!(read(&rotated, resealed), Some(b"session content".to_vec()));
+    }
+
+    #[test]
+    fn cookie_backend_max_size_test() {
+        let backend = CookieBackend::new(KEY).with_max_size(128);
+        assert!(backend
+            .persist_session(identifier("unused"), &[0; 32])
+            .wait()
+            .is_ok());
+
+        match backend
+            .persist_session(identifier("unused"), &[0; 128])
+            .wait()
+        {
+            Err(SessionError::Backend(ref message)) => assert!(message.contains("exceeds")),
+            other => panic!("unexpected result: {:?}", other),
+        }
+    }
+}
@@ -0,0 +1,393 @@
+use std::fs::{self, File, OpenOptions};
+use std::io::{self, Write};
+use std::path::{Path, PathBuf};
+use std::sync::{Arc, Weak};
+use std::thread;
+use std::time::{Duration, SystemTime};
+
+use futures::future;
+use log::{debug, trace};
+use rand::RngCore;
+
+use crate::helpers::blocking::run_blocking;
+use crate::middleware::session::backend::{Backend, NewBackend, SessionFuture, SessionWriteFuture};
+use crate::middleware::session::{SessionError, SessionIdentifier};
+
+const LOCK_FILE: &str = ".lock";
+const TEMP_EXTENSION: &str = "tmp";
+
+/// Defines a session storage which keeps each session in a file of its own, beneath a directory,
+/// so that sessions survive the application being restarted.
+///
+/// Sessions are written to a temporary file which then replaces the file of the session, so a
+/// session is never read while it's partially written, including by another process sharing the
+/// directory. Sessions expire once they haven't been read or written for the `ttl`, and expired
+/// sessions are removed by a background thread, which sweeps the directory once per `ttl`. A lock
+/// on a file in the directory keeps sessions from being written while the directory is swept.
+/// Sessions are read and written on the blocking threads of the Tokio thread pool, so that a slow
+/// disk, or a write waiting out a sweep, doesn't stall the other connections being served.
+///
+/// The directory shouldn't be used for anything else, as files which look like expired sessions
+/// are removed from it.
+///
+/// ## Examples
+///
+/// ```rust
+/// # extern crate gotham;
+/// # use std::time::Duration;
+/// # use gotham::middleware::session::{FileBackend, NewSessionMiddleware};
+/// # fn main() {
+/// # let directory = std::env::temp_dir().join("gotham-file-backend-doctest");
+/// let backend = FileBackend::new(directory, Duration::from_secs(86400))
+///     .expect("unable to create the session directory");
+///
+/// NewSessionMiddleware::new(backend)
+/// # ;}
+/// ```
+#[derive(Clone)]
+pub struct FileBackend {
+    storage: Arc<FileStorage>,
+}
+
+struct FileStorage {
+    directory: PathBuf,
+    ttl: Duration,
+}
+
+impl FileBackend {
+    /// Creates a new `FileBackend` which keeps sessions beneath `directory`, creating the
+    /// directory where it doesn't exist, where sessions expire after the `ttl` has elapsed.
+    pub fn new<P>(directory: P, ttl: Duration) -> io::Result<FileBackend>
+    where
+        P: Into<PathBuf>,
+    {
+        let directory = directory.into();
+        fs::create_dir_all(&directory)?;
+
+        let storage = Arc::new(FileStorage { directory, ttl });
+
+        {
+            let storage = Arc::downgrade(&storage);
+            thread::spawn(move || cleanup_loop(storage, ttl));
+        }
+
+        Ok(FileBackend { storage })
+    }
+}
+
+impl NewBackend for FileBackend {
+    type Instance = FileBackend;
+
+    fn new_backend(&self) -> io::Result<Self::Instance> {
+        Ok(self.clone())
+    }
+}
+
+impl Backend for FileBackend {
+    fn persist_session(
+        &self,
+        identifier: SessionIdentifier,
+        content: &[u8],
+    ) -> Box<SessionWriteFuture> {
+        let path = match self.storage.session_path(&identifier) {
+            Some(path) => path,
+            None => {
+                let e = SessionError::Backend("invalid session identifier".to_owned());
+                return Box::new(future::err(e));
+            }
+        };
+
+        let storage = self.storage.clone();
+        let content = content.to_vec();
+        Box::new(run_blocking(move || {
+            storage.write(&path, &content).map_err(|e| {
+                debug!(" failed to persist session {}: {}", identifier.value, e);
+                backend_error(e)
+            })
+        }))
+    }
+
+    fn read_session(&self, identifier: SessionIdentifier) -> Box<SessionFuture> {
+        let path = match self.storage.session_path(&identifier) {
+            Some(path) => path,
+            None => return Box::new(future::ok(None)),
+        };
+
+        let storage = self.storage.clone();
+        Box::new(run_blocking(move || {
+            storage.read(&path).map_err(|e| {
+                debug!(" failed to read session {}: {}", identifier.value, e);
+                backend_error(e)
+            })
+        }))
+    }
+
+    fn drop_session(&self, identifier: SessionIdentifier) -> Box<SessionWriteFuture> {
+        let path = match self.storage.session_path(&identifier) {
+            Some(path) => path,
+            None => return Box::new(future::ok(())),
+        };
+
+        Box::new(run_blocking(move || match fs::remove_file(&path) {
+            Ok(()) => Ok(()),
+            Err(ref e) if e.kind() == io::ErrorKind::NotFound => Ok(()),
+            Err(e) => {
+                debug!(" failed to drop session {}: {}", identifier.value, e);
+                Err(backend_error(e))
+            }
+        }))
+    }
+}
+
+fn backend_error(e: io::Error) -> SessionError {
+    SessionError::Backend(format!("file: {}", e))
+}
+
+impl FileStorage {
+    /// The path of the file of a session. Identifiers are given by the user agent, so those which
+    /// could name a file outside of the directory have no path.
+    fn session_path(&self, identifier: &SessionIdentifier) -> Option<PathBuf> {
+        let valid = !identifier.value.is_empty()
+            && identifier
+                .value
+                .bytes()
+                .all(|b| b.is_ascii_alphanumeric() || b == b'-' || b == b'_');
+
+        if valid {
+            Some(self.directory.join(&identifier.value))
+        } else {
+            None
+        }
+    }
+
+    fn lock(&self, exclusive: bool) -> io::Result<File> {
+        let lock = OpenOptions::new()
+            .create(true)
+            .truncate(false)
+            .write(true)
+            .open(self.directory.join(LOCK_FILE))?;
+
+        if exclusive {
+            lock.lock()?;
+        } else {
+            lock.lock_shared()?;
+        }
+        Ok(lock)
+    }
+
+    fn write(&self, path: &Path, content: &[u8]) -> io::Result<()> {
+        let temp = path.with_extension(format!(
+            "{:016x}.{}",
+            rand::thread_rng().next_u64(),
+            TEMP_EXTENSION
+        ));
+
+        // writers share the lock, which is held exclusively while the directory is swept
+        let _lock = self.lock(false)?;
+
+        let result = File::create(&temp)
+            .and_then(|mut file| file.write_all(content).and_then(|()| file.sync_data()))
+            .and_then(|()| fs::rename(&temp, path));
+
+        if result.is_err() {
+            let _ = fs::remove_file(&temp);
+        }
+        result
+    }
+
+    fn read(&self, path: &Path) -> io::Result<Option<Vec<u8>>> {
+        let content = match fs::read(path) {
+            Ok(content) => content,
+            Err(ref e) if e.kind() == io::ErrorKind::NotFound => return Ok(None),
+            Err(e) => return Err(e),
+        };
+
+        let file = match OpenOptions::new().write(true).open(path) {
+            Ok(file) => file,
+            Err(ref e) if e.kind() == io::ErrorKind::NotFound => return Ok(None),
+            Err(e) => return Err(e),
+        };
+
+        if self.is_expired(&file.metadata()?) {
+            trace!(" session file {} has expired", path.display());
+            return Ok(None);
+        }
+
+        // reading a session keeps it alive, as with the `MemoryBackend`
+        file.set_modified(SystemTime::now())?;
+        Ok(Some(content))
+    }
+
+    fn is_expired(&self, metadata: &fs::Metadata) -> bool {
+        match metadata.modified() {
+            Ok(modified) => match modified.elapsed() {
+                Ok(age) => age >= self.ttl,
+                // modified in the future, by a clock which has since changed
+                Err(_) => false,
+            },
+            Err(_) => false,
+        }
+    }
+
+    /// Removes the sessions, and any temporary files left by failed writes, which have expired.
+    fn cleanup(&self) -> io::Result<()> {
+        let _lock = self.lock(true)?;
+
+        for entry in fs::read_dir(&self.directory)? {
+            let entry = entry?;
+            if entry.file_name() == LOCK_FILE {
+                continue;
+            }
+
+            let metadata = entry.metadata()?;
+            if metadata.is_file() && self.is_expired(&metadata) {
+                match fs::remove_file(entry.path()) {
+                    Ok(()) => trace!(
+                        " expired session {:?} and removed from FileBackend",
+                        entry.file_name()
+                    ),
+                    Err(ref e) if e.kind() == io::ErrorKind::NotFound => (),
+                    Err(e) => return Err(e),
+                }
+            }
+        }
+
+        Ok(())
+    }
+}
+
+fn cleanup_loop(storage: Weak<FileStorage>, ttl: Duration) {
+    loop {
+        // As with the `MemoryBackend`, the thread stops once the backend has been dropped.
+        let storage = match storage.upgrade() {
+            None => break,
+            Some(storage) => storage,
+        };
+
+        if let Err(e) = storage.cleanup() {
+            debug!(
+                " failed to remove expired sessions from {}: {}",
+                storage.directory.display(),
+                e
+            );
+        }
+
+        drop(storage);
+        thread::sleep(::std::cmp::max(ttl, Duration::from_secs(1)));
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    use futures::Future;
+
+    fn directory(name: &str) -> PathBuf {
+        let directory = std::env::temp_dir().join(format!(
+            "gotham-file-backend-{}-{}",
+            name,
+            rand::thread_rng().next_u64()
+        ));
+        let _ = fs::remove_dir_all(&directory);
+        directory
+    }
+
+    fn identifier(value: &str) -> SessionIdentifier {
+        SessionIdentifier {
+            value: value.to_owned(),
+        }
+    }
+
+    fn read(backend: &FileBackend, value: &str) -> Option<Vec<u8>> {
+        backend
+            .new_backend()
+            .expect("can't create backend for read")
+            .read_session(identifier(value))
+            .wait()
+            .expect("no response from backend")
+    }
+
+    #[test]
+    fn file_backend_test() {
+        let directory = directory("persist");
+        let backend = FileBackend::new(&directory, Duration::from_secs(60)).unwrap();
+        let bytes: Vec<u8> = (0..64).map(|_| rand::random()).collect();
+
+        assert_eq!(read(&backend, "session_1"), None);
+
+        backend
+            .new_backend()
+            .expect("can't create backend for write")
+            .persist_session(identifier("session_1"), &bytes[..])
+            .wait()
+            .expect("failed to persist");
+        assert_eq!(read(&backend, "session_1"), Some(bytes.clone()));
+
+        // the session survives the backend being replaced, as when the application restarts
+        drop(backend);
+        let backend = FileBackend::new(&directory, Duration::from_secs(60)).unwrap();
+        assert_eq!(read(&backend, "session_1"), Some(bytes));
+
+        backend
+            .drop_session(identifier("session_1"))
+            .wait()
+            .unwrap();
+        assert_eq!(read(&backend, "session_1"), None);
+        backend
+            .drop_session(identifier("session_1"))
+            .wait()
+            .unwrap();
+
+        fs::remove_dir_all(&directory).unwrap();
+    }
+
+    #[test]
+    fn file_backend_expiry_test() {
+        let directory = directory("expiry");
+        let backend = FileBackend::new(&directory, Duration::from_secs(60)).unwrap();
+
+        backend
+            .persist_session(identifier("stale"), b"stale")
+            .wait()
+            .unwrap();
+        backend
+            .persist_session(identifier("fresh"), b"fresh")
+            .wait()
+            .unwrap();
+
+        let stale = OpenOptions::new()
+            .write(true)
+            .open(directory.join("stale"))
+            .unwrap();
+        stale
+            .set_modified(SystemTime::now() - Duration::from_secs(120))
+            .unwrap();
+
+        assert_eq!(read(&backend, "stale"), None);
+
+        backend.storage.cleanup().unwrap();
+        assert!(!directory.join("stale").exists());
+        assert!(directory.join("fresh").exists());
+        assert_eq!(read(&backend, "fresh"), Some(b"fresh".to_vec()));
+
+        fs::remove_dir_all(&directory).unwrap();
+    }
+
+    #[test]
+    fn file_backend_identifier_test() {
+        let directory = directory("identifier");
+        let backend = FileBackend::new(&directory, Duration::from_secs(60)).unwrap();
+
+        for value in &["", "../escape", "a/b", ".lock", "a.b"] {
+            assert!(backend
+                .persist_session(identifier(value), b"x")
+                .wait()
+                .is_err());
+            assert_eq!(read(&backend, value), None);
+            assert!(backend.drop_session(identifier(value)).wait().is_ok());
+        }
+
+        fs::remove_dir_all(&directory).unwrap();
+    }
+}
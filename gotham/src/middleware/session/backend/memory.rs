@@ -6,8 +6,8 @@ use futures::future;
 use linked_hash_map::LinkedHashMap;
 use log::trace;
 
-use crate::middleware::session::backend::{Backend, NewBackend, SessionFuture};
-use crate::middleware::session::{SessionError, SessionIdentifier};
+use crate::middleware::session::backend::{Backend, NewBackend, SessionFuture, SessionWriteFuture};
+use crate::middleware::session::SessionIdentifier;
 
 /// Type alias for the `MemoryBackend` storage container.
 type MemoryMap = Mutex<LinkedHashMap<String, (Instant, Vec<u8>)>>;
@@ -81,11 +81,11 @@ impl Backend for MemoryBackend {
         &self,
         identifier: SessionIdentifier,
         content: &[u8],
-    ) -> Result<(), SessionError> {
+    ) -> Box<SessionWriteFuture> {
         match self.storage.lock() {
             Ok(mut storage) => {
                 storage.insert(identifier.value, (Instant::now(), Vec::from(content)));
-                Ok(())
+                Box::new(future::ok(()))
             }
             Err(PoisonError { .. }) => {
                 unreachable!("session memory backend lock poisoned, HashMap panicked?")
@@ -108,11 +108,11 @@ impl Backend for MemoryBackend {
         }
     }
 
-    fn drop_session(&self, identifier: SessionIdentifier) -> Result<(), SessionError> {
+    fn drop_session(&self, identifier: SessionIdentifier) -> Box<SessionWriteFuture> {
         match self.storage.lock() {
             Ok(mut storage) => {
                 storage.remove(&identifier.value);
-                Ok(())
+                Box::new(future::ok(()))
             }
             Err(PoisonError { .. }) => {
                 unreachable!("session memory backend lock poisoned, HashMap panicked?")
@@ -231,6 +231,7 @@ mod tests {
             .new_backend()
             .expect("can't create backend for write")
             .persist_session(identifier.clone(), &bytes[..])
+            .wait()
             .expect("failed to persist");
 
         let received = new_backend
@@ -262,10 +263,12 @@ mod tests {
 
         backend
             .persist_session(identifier.clone(), &bytes[..])
+            .wait()
             .expect("failed to persist");
 
         backend
             .persist_session(identifier2.clone(), &bytes2[..])
+            .wait()
             .expect("failed to persist");
 
         {
@@ -1,4 +1,7 @@
+pub(super) mod cookie;
+pub(super) mod file;
 pub(super) mod memory;
+pub(super) mod redis;
 
 use std::io;
 use std::panic::RefUnwindSafe;
@@ -16,20 +19,48 @@ pub trait NewBackend: Sync + Clone + RefUnwindSafe {
     fn new_backend(&self) -> io::Result<Self::Instance>;
 }
 
-/// Type alias for the trait objects returned by `Backend`.
+/// Type alias for the trait objects returned by `Backend::read_session`.
 pub type SessionFuture = dyn Future<Item = Option<Vec<u8>>, Error = SessionError> + Send;
 
+/// Type alias for the trait objects returned by `Backend::persist_session` and
+/// `Backend::drop_session`.
+pub type SessionWriteFuture = dyn Future<Item = (), Error = SessionError> + Send;
+
+/// Type alias for the trait objects returned by `Backend::persist_session_cookie`.
+pub type SessionCookieFuture =
+    dyn Future<Item = Option<SessionIdentifier>, Error = SessionError> + Send;
+
 /// A `Backend` receives session data and stores it, and recalls the session data subsequently.
 ///
 /// All session data is serialized into a `Vec<u8>` which is treated as opaque by the backend. The
 /// serialization format is subject to change and must not be relied upon by the `Backend`.
+///
+/// Each operation returns a future, which the session middleware chains into the future of the
+/// request, so that a backend using a network store can wait for the store without blocking the
+/// thread serving requests. A backend which completes its work immediately returns a future which
+/// has already resolved, such as one created by `futures::future::result`.
 pub trait Backend: Send {
     /// Persists a session, either creating a new session or updating an existing session.
+    ///
+    /// The content is borrowed only for the duration of the call, so a backend which doesn't
+    /// persist the session before returning must copy it into the returned future.
     fn persist_session(
         &self,
         identifier: SessionIdentifier,
         content: &[u8],
-    ) -> Result<(), SessionError>;
+    ) -> Box<SessionWriteFuture>;
+
+    /// Persists a session as with `persist_session`, providing a new identifier to be held in the
+    /// session cookie where the backend keeps the session in the cookie itself.
+    ///
+    /// By default, the session is persisted with `persist_session` and the identifier is kept.
+    fn persist_session_cookie(
+        &self,
+        identifier: SessionIdentifier,
+        content: &[u8],
+    ) -> Box<SessionCookieFuture> {
+        Box::new(self.persist_session(identifier, content).map(|()| None))
+    }
 
     /// Retrieves a session from the underlying storage.
     ///
@@ -39,5 +70,5 @@ pub trait Backend: Send {
     fn read_session(&self, identifier: SessionIdentifier) -> Box<SessionFuture>;
 
     /// Drops a session from the underlying storage.
-    fn drop_session(&self, identifier: SessionIdentifier) -> Result<(), SessionError>;
+    fn drop_session(&self, identifier: SessionIdentifier) -> Box<SessionWriteFuture>;
 }
@@ -0,0 +1,668 @@
+use std::io::{self, BufRead, BufReader, Write};
+use std::net::TcpStream;
+use std::sync::{Arc, Mutex, PoisonError};
+use std::time::Duration;
+
+use log::{debug, trace};
+
+use crate::helpers::blocking::run_blocking;
+use crate::middleware::session::backend::{Backend, NewBackend, SessionFuture, SessionWriteFuture};
+use crate::middleware::session::{SessionError, SessionIdentifier};
+
+// The longest bulk string a Redis server sends, which bounds the memory allocated for a reply.
+const MAX_BULK_LENGTH: i64 = 512 * 1024 * 1024;
+
+/// Defines a session storage backed by a Redis server, which allows sessions to be shared by
+/// several instances of an application.
+///
+/// Each session is stored under a key made of the key prefix and the session identifier, holding
+/// the session data serialized as it is for the `MemoryBackend`. Sessions expire once they
+/// haven't been read or written for the `ttl`, which is applied to the key each time the session
+/// is used, so that Redis removes expired sessions.
+///
+/// Connections to the server are kept in a pool shared by every clone of the backend, and are
+/// opened as needed. Requests to the server are made from the blocking threads of the Tokio
+/// thread pool, so that waiting for a reply doesn't stall the other connections being served.
+///
+/// ## Examples
+///
+/// ```rust
+/// # extern crate gotham;
+/// # use std::time::Duration;
+/// # use gotham::middleware::session::{NewSessionMiddleware, RedisBackend};
+/// # fn main() {
+/// let backend = RedisBackend::new("127.0.0.1:6379")
+///     .with_key_prefix("myapp:session:")
+///     .with_ttl(Duration::from_secs(86400));
+///
+/// NewSessionMiddleware::new(backend)
+/// # ;}
+/// ```
+#[derive(Clone)]
+pub struct RedisBackend {
+    pool: Arc<ConnectionPool>,
+    key_prefix: String,
+    ttl: Duration,
+}
+
+struct ConnectionPool {
+    address: String,
+    password: Option<String>,
+    database: Option<u32>,
+    timeout: Option<Duration>,
+    max_idle: usize,
+    idle: Mutex<Vec<Connection>>,
+}
+
+impl RedisBackend {
+    /// Creates a new `RedisBackend` for the Redis server at `address`, given as `host:port`.
+    ///
+    /// Sessions are stored under keys prefixed with `gotham:session:`, and expire after an hour,
+    /// as with `MemoryBackend::default()`. No connection is made until a session is used.
+    pub fn new<A>(address: A) -> RedisBackend
+    where
+        A: Into<String>,
+    {
+        RedisBackend {
+            pool: Arc::new(ConnectionPool {
+                address: address.into(),
+                password: None,
+                database: None,
+                timeout: Some(Duration::from_secs(5)),
+                max_idle: 16,
+                idle: Mutex::new(vec![]),
+            }),
+            key_prefix: "gotham:session:".to_owned(),
+            ttl: Duration::from_secs(3600),
+        }
+    }
+
+    /// Sets the prefix of the keys which sessions are stored under, so that several applications
+    /// can share a Redis server.
+    pub fn with_key_prefix<P>(self, prefix: P) -> RedisBackend
+    where
+        P: Into<String>,
+    {
+        RedisBackend {
+            key_prefix: prefix.into(),
+            ..self
+        }
+    }
+
+    /// Sets the time after which a session which hasn't been used expires.
+    pub fn with_ttl(self, ttl: Duration) -> RedisBackend {
+        RedisBackend { ttl, ..self }
+    }
+
+    /// Sets the password used to authenticate each connection to the server.
+    pub fn with_password<P>(self, password: P) -> RedisBackend
+    where
+        P: Into<String>,
+    {
+        let password = Some(password.into());
+        self.with_pool(|pool| pool.password = password)
+    }
+
+    /// Sets the database which sessions are stored in, rather than the default database `0`.
+    pub fn with_database(self, database: u32) -> RedisBackend {
+        self.with_pool(|pool| pool.database = Some(database))
+    }
+
+    /// Sets the time to wait when connecting to the server and for each reply from the server,
+    /// where it's five seconds by default. `None` waits indefinitely.
+    pub fn with_timeout(self, timeout: Option<Duration>) -> RedisBackend {
+        self.with_pool(|pool| pool.timeout = timeout)
+    }
+
+    /// Sets the number of idle connections which are kept open for later requests, where it's
+    /// sixteen by default. Further connections are opened while the server is busy, and closed
+    /// once they're no longer needed.
+    pub fn with_max_idle_connections(self, max_idle: usize) -> RedisBackend {
+        self.with_pool(|pool| pool.max_idle = max_idle)
+    }
+
+    fn with_pool<F>(self, f: F) -> RedisBackend
+    where
+        F: FnOnce(&mut ConnectionPool),
+    {
+        let mut pool = match Arc::try_unwrap(self.pool) {
+            Ok(pool) => pool,
+            Err(pool) => ConnectionPool {
+                address: pool.address.clone(),
+                password: pool.password.clone(),
+                database: pool.database,
+                timeout: pool.timeout,
+                max_idle: pool.max_idle,
+                idle: Mutex::new(vec![]),
+            },
+        };
+        f(&mut pool);
+
+        RedisBackend {
+            pool: Arc::new(pool),
+            ..self
+        }
+    }
+
+    fn key(&self, identifier: &SessionIdentifier) -> Vec<u8> {
+        format!("{}{}", self.key_prefix, identifier.value).into_bytes()
+    }
+
+    fn ttl_millis(&self) -> Vec<u8> {
+        // Redis rejects an expiry of zero
+        let millis = self.ttl.as_millis().max(1);
+        millis.to_string().into_bytes()
+    }
+}
+
+impl NewBackend for RedisBackend {
+    type Instance = RedisBackend;
+
+    fn new_backend(&self) -> io::Result<Self::Instance> {
+        Ok(self.clone())
+    }
+}
+
+impl Backend for RedisBackend {
+    fn persist_session(
+        &self,
+        identifier: SessionIdentifier,
+        content: &[u8],
+    ) -> Box<SessionWriteFuture> {
+        let pool = self.pool.clone();
+        let key = self.key(&identifier);
+        let ttl = self.ttl_millis();
+        let content = content.to_vec();
+
+        Box::new(run_blocking(move || {
+            let command: &[&[u8]] = &[b"SET", &key, &content, b"PX", &ttl];
+            pool.execute(&[command]).map(|_| ()).map_err(|e| {
+                debug!(" failed to persist session {}: {}", identifier.value, e);
+                backend_error(e)
+            })
+        }))
+    }
+
+    fn read_session(&self, identifier: SessionIdentifier) -> Box<SessionFuture> {
+        let pool = self.pool.clone();
+        let key = self.key(&identifier);
+        let ttl = self.ttl_millis();
+
+        Box::new(run_blocking(move || {
+            let get: &[&[u8]] = &[b"GET", &key];
+            let refresh: &[&[u8]] = &[b"PEXPIRE", &key, &ttl];
+
+            match pool.execute(&[get, refresh]) {
+                Ok(mut replies) => match replies.swap_remove(0) {
+                    Reply::Bulk(content) => Ok(content),
+                    reply => {
+                        let e = io::Error::new(
+                            io::ErrorKind::InvalidData,
+                            format!("unexpected reply to GET: {:?}", reply),
+                        );
+                        Err(backend_error(e))
+                    }
+                },
+                Err(e) => {
+                    debug!(" failed to read session {}: {}", identifier.value, e);
+                    Err(backend_error(e))
+                }
+            }
+        }))
+    }
+
+    fn drop_session(&self, identifier: SessionIdentifier) -> Box<SessionWriteFuture> {
+        let pool = self.pool.clone();
+        let key = self.key(&identifier);
+
+        Box::new(run_blocking(move || {
+            let command: &[&[u8]] = &[b"DEL", &key];
+            pool.execute(&[command]).map(|_| ()).map_err(|e| {
+                debug!(" failed to drop session {}: {}", identifier.value, e);
+                backend_error(e)
+            })
+        }))
+    }
+}
+
+fn backend_error(e: io::Error) -> SessionError {
+    SessionError::Backend(format!("redis: {}", e))
+}
+
+impl ConnectionPool {
+    /// Sends the commands to the server as a pipeline, providing a reply for each.
+    ///
+    /// A pooled connection may have been closed by the server since it was last used, so the
+    /// commands are sent again over a new connection where a pooled connection fails.
+    fn execute(&self, commands: &[&[&[u8]]]) -> io::Result<Vec<Reply>> {
+        let result = match self.checkout() {
+            Some(mut connection) => match connection.execute(commands) {
+                Ok(replies) => Ok((connection, replies)),
+                Err(e) => {
+                    trace!(" pooled redis connection failed, reconnecting: {}", e);
+                    self.execute_connected(commands)
+                }
+            },
+            None => self.execute_connected(commands),
+        };
+
+        let (connection, replies) = result?;
+        self.checkin(connection);
+
+        match replies.iter().find_map(Reply::error) {
+            Some(message) => Err(io::Error::other(message.to_owned())),
+            None => Ok(replies),
+        }
+    }
+
+    fn execute_connected(&self, commands: &[&[&[u8]]]) -> io::Result<(Connection, Vec<Reply>)> {
+        let mut connection = self.connect()?;
+        let replies = connection.execute(commands)?;
+        Ok((connection, replies))
+    }
+
+    fn connect(&self) -> io::Result<Connection> {
+        trace!(" opening redis connection to {}", self.address);
+        let stream = match self.timeout {
+            Some(timeout) => {
+                let mut last_error = None;
+                let mut connected = None;
+                for addr in std::net::ToSocketAddrs::to_socket_addrs(&self.address)? {
+                    match TcpStream::connect_timeout(&addr, timeout) {
+                        Ok(stream) => {
+                            connected = Some(stream);
+                            break;
+                        }
+                        Err(e) => last_error = Some(e),
+                    }
+                }
+                match (connected, last_error) {
+                    (Some(stream), _) => stream,
+                    (None, Some(e)) => return Err(e),
+                    (None, None) => {
+                        return Err(io::Error::new(
+                            io::ErrorKind::InvalidInput,
+                            "address did not resolve",
+                        ))
+                    }
+                }
+            }
+            None => TcpStream::connect(&self.address)?,
+        };
+
+        stream.set_read_timeout(self.timeout)?;
+        stream.set_write_timeout(self.timeout)?;
+        stream.set_nodelay(true)?;
+
+        let mut connection = Connection {
+            stream: BufReader::new(stream),
+        };
+
+        let password = self.password.as_ref().map(String::as_bytes);
+        let database = self.database.map(|db| db.to_string().into_bytes());
+        let mut commands: Vec<Vec<&[u8]>> = vec![];
+        if let Some(password) = password {
+            commands.push(vec![b"AUTH", password]);
+        }
+        if let Some(ref database) = database {
+            commands.push(vec![b"SELECT", database]);
+        }
+
+        if !commands.is_empty() {
+            let commands: Vec<&[&[u8]]> = commands.iter().map(Vec::as_slice).collect();
+            let replies = connection.execute(&commands)?;
+            if let Some(message) = replies.iter().find_map(Reply::error) {
+                return Err(io::Error::other(message.to_owned()));
+            }
+        }
+
+        Ok(connection)
+    }
+
+    fn checkout(&self) -> Option<Connection> {
+        self.idle
+            .lock()
+            .unwrap_or_else(PoisonError::into_inner)
+            .pop()
+    }
+
+    fn checkin(&self, connection: Connection) {
+        let mut idle = self.idle.lock().unwrap_or_else(PoisonError::into_inner);
+        if idle.len() < self.max_idle {
+            idle.push(connection);
+        }
+    }
+}
+
+/// A reply from the Redis server, in the RESP protocol.
+#[derive(Debug, PartialEq)]
+enum Reply {
+    Status(String),
+    Error(String),
+    Integer(i64),
+    Bulk(Option<Vec<u8>>),
+    Array(Option<Vec<Reply>>),
+}
+
+impl Reply {
+    fn error(&self) -> Option<&str> {
+        match *self {
+            Reply::Error(ref message) => Some(message),
+            _ => None,
+        }
+    }
+}
+
+struct Connection {
+    stream: BufReader<TcpStream>,
+}
+
+impl Connection {
+    fn execute(&mut self, commands: &[&[&[u8]]]) -> io::Result<Vec<Reply>> {
+        let mut request = vec![];
+        for command in commands {
+            encode_command(&mut request, command);
+        }
+        self.stream.get_mut().write_all(&request)?;
+
+        commands
+            .iter()
+            .map(|_| read_reply(&mut self.stream))
+            .collect()
+    }
+}
+
+fn encode_command(buf: &mut Vec<u8>, args: &[&[u8]]) {
+    buf.extend_from_slice(format!("*{}\r\n", args.len()).as_bytes());
+    for arg in args {
+        buf.extend_from_slice(format!("${}\r\n", arg.len()).as_bytes());
+        buf.extend_from_slice(arg);
+        buf.extend_from_slice(b"\r\n");
+    }
+}
+
+fn read_reply<R>(reader: &mut R) -> io::Result<Reply>
+where
+    R: BufRead,
+{
+    let line = read_line(reader)?;
+    let (kind, rest) = match line.split_first() {
+        Some((kind, rest)) => (*kind, String::from_utf8_lossy(rest).into_owned()),
+        None => return Err(invalid_reply("empty reply")),
+    };
+
+    let length = |rest: &str| {
+        rest.parse::<i64>()
+            .map_err(|_| invalid_reply("invalid length"))
+    };
+
+    match kind {
+        b'+' => Ok(Reply::Status(rest)),
+        b'-' => Ok(Reply::Error(rest)),
+        b':' => Ok(Reply::Integer(length(&rest)?)),
+        b'$' => match length(&rest)? {
+            len if len < 0 => Ok(Reply::Bulk(None)),
+            len if len > MAX_BULK_LENGTH => Err(invalid_reply("bulk string too long")),
+            len => {
+                let mut content = vec![0; len as usize + 2];
+                reader.read_exact(&mut content)?;
+                if !content.ends_with(b"\r\n") {
+                    return Err(invalid_reply("unterminated bulk string"));
+                }
+                content.truncate(len as usize);
+                Ok(Reply::Bulk(Some(content)))
+            }
+        },
+        b'*' => match length(&rest)? {
+            len if len < 0 => Ok(Reply::Array(None)),
+            len => {
+                let replies = (0..len)
+                    .map(|_| read_reply(reader))
+                    .collect::<io::Result<_>>()?;
+                Ok(Reply::Array(Some(replies)))
+            }
+        },
+        _ => Err(invalid_reply("unknown reply type")),
+    }
+}
+
+fn read_line<R>(reader: &mut R) -> io::Result<Vec<u8>>
+where
+    R: BufRead,
+{
+    let mut line = vec![];
+    reader.read_until(b'\n', &mut line)?;
+    if !line.ends_with(b"\r\n") {
+        return Err(invalid_reply("unterminated line"));
+    }
+    line.truncate(line.len() - 2);
+    Ok(line)
+}
+
+fn invalid_reply(message: &str) -> io::Error {
+    io::Error::new(io::ErrorKind::InvalidData, message)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    use std::collections::HashMap;
+    use std::net::TcpListener;
+    use std::thread;
+
+    use futures::Future;
+
+    type Keys = Arc<Mutex<HashMap<Vec<u8>, (Vec<u8>, Vec<u8>)>>>;
+
+    // Serves the commands used by `RedisBackend` from memory, recording the expiry of each key,
+    // and responds to each connection's first command with an error where it's given `password`.
+    fn fake_server(password: Option<&'static str>) -> (String, Keys, Arc<Mutex<usize>>) {
+        let listener = TcpListener::bind("127.0.0.1:0").unwrap();
+        let address = listener.local_addr().unwrap().to_string();
+        let keys: Keys = Arc::default();
+        let connections = Arc::new(Mutex::new(0));
+
+        {
+            let keys = keys.clone();
+            let connections = connections.clone();
+            thread::spawn(move || {
+                for stream in listener.incoming() {
+                    let stream = stream.unwrap();
+                    *connections.lock().unwrap() += 1;
+                    let keys = keys.clone();
+                    thread::spawn(move || serve(stream, keys, password));
+                }
+            });
+        }
+
+        (address, keys, connections)
+    }
+
+    fn serve(stream: TcpStream, keys: Keys, password: Option<&'static str>) {
+        let mut reader = BufReader::new(stream.try_clone().unwrap());
+        let mut writer = stream;
+        let mut authenticated = password.is_none();
+
+        while let Ok(Reply::Array(Some(args))) = read_reply(&mut reader) {
+            let args: Vec<Vec<u8>> = args
+                .into_iter()
+                .map(|arg| match arg {
+                    Reply::Bulk(Some(arg)) => arg,
+                    _ => panic!("commands are sent as bulk strings"),
+                })
+                .collect();
+
+            let mut keys = keys.lock().unwrap();
+            let reply: Vec<u8> = match (&args[0][..], authenticated) {
+                (b"AUTH", _) if Some(&args[1][..]) == password.map(str::as_bytes) => {
+                    authenticated = true;
+                    b"+OK\r\n".to_vec()
+                }
+                (_, false) => b"-NOAUTH Authentication required.\r\n".to_vec(),
+                (b"SET", _) => {
+                    assert_eq!(args[3], b"PX");
+                    keys.insert(args[1].clone(), (args[2].clone(), args[4].clone()));
+                    b"+OK\r\n".to_vec()
+                }
+                (b"GET", _) => match keys.get(&args[1]) {
+                    Some((value, _)) => {
+                        let mut reply = format!("${}\r\n", value.len()).into_bytes();
+                        reply.extend_from_slice(value);
+                        reply.extend_from_slice(b"\r\n");
+                        reply
+                    }
+                    None => b"$-1\r\n".to_vec(),
+                },
+                (b"PEXPIRE", _) => match keys.get_mut(&args[1]) {
+                    Some(entry) => {
+                        entry.1 = args[2].clone();
+                        b":1\r\n".to_vec()
+                    }
+                    None => b":0\r\n".to_vec(),
+                },
+                (b"DEL", _) => match keys.remove(&args[1]) {
+                    Some(_) => b":1\r\n".to_vec(),
+                    None => b":0\r\n".to_vec(),
+                },
+                _ => b"-ERR unknown command\r\n".to_vec(),
+            };
+
+            if writer.write_all(&reply).is_err() {
+                break;
+            }
+        }
+    }
+
+    fn identifier() -> SessionIdentifier {
+        SessionIdentifier {
+            value: "totally_random_identifier".to_owned(),
+        }
+    }
+
+    #[test]
+    fn redis_backend_test() {
+        let (address, keys, connections) = fake_server(None);
+        let backend = RedisBackend::new(address)
+            .with_key_prefix("app:")
+            .with_ttl(Duration::from_secs(60));
+
+        let read = |backend: &RedisBackend| {
+            backend
+                .new_backend()
+                .unwrap()
+                .read_session(identifier())
+                .wait()
+                .unwrap()
+        };
+
+        assert_eq!(read(&backend), None);
+
+        backend
+            .new_backend()
+            .unwrap()
+            .persist_session(identifier(), b"session data")
+            .wait()
+            .unwrap();
+
+        assert_eq!(
+            keys.lock().unwrap()[&b"app:totally_random_identifier"[..]],
+            (b"session data".to_vec(), b"60000".to_vec())
+        );
+
+        // reading the session refreshes its expiry
+        keys.lock()
+            .unwrap()
+            .get_mut(&b"app:totally_random_identifier"[..])
+            .unwrap()
+            .1 = b"1".to_vec();
+        assert_eq!(read(&backend), Some(b"session data".to_vec()));
+        assert_eq!(
+            keys.lock().unwrap()[&b"app:totally_random_identifier"[..]].1,
+            b"60000".to_vec()
+        );
+
+        backend
+            .new_backend()
+            .unwrap()
+            .drop_session(identifier())
+            .wait()
+            .unwrap();
+        assert!(keys.lock().unwrap().is_empty());
+        assert_eq!(read(&backend), None);
+
+        // the connection is reused by each request
+        assert_eq!(*connections.lock().unwrap(), 1);
+    }
+
+    #[test]
+    fn redis_backend_thread_pool_test() {
+        let (address, _, _) = fake_server(None);
+        let backend = RedisBackend::new(address);
+        let mut runtime = tokio::runtime::Runtime::new().unwrap();
+
+        runtime
+            .block_on(backend.persist_session(identifier(), b"data"))
+            .unwrap();
+        assert_eq!(
+            runtime
+                .block_on(backend.read_session(identifier()))
+                .unwrap(),
+            Some(b"data".to_vec())
+        );
+    }
+
+    #[test]
+    fn redis_backend_authentication_test() {
+        let (address, _, _) = fake_server(Some("secret"));
+
+        let backend = RedisBackend::new(address.clone());
+        match backend.persist_session(identifier(), b"data").wait() {
+            Err(SessionError::Backend(message)) => assert!(message.contains("NOAUTH")),
+            _ => panic!("session should not have been persisted"),
+        }
+
+        let backend = RedisBackend::new(address).with_password("secret");
+        backend
+            .persist_session(identifier(), b"data")
+            .wait()
+            .unwrap();
+    }
+
+    #[test]
+    fn redis_backend_unavailable_test() {
+        let address = {
+            let listener = TcpListener::bind("127.0.0.1:0").unwrap();
+            listener.local_addr().unwrap().to_string()
+        };
+
+        let backend = RedisBackend::new(address);
+        match backend.read_session(identifier()).wait() {
+            Err(SessionError::Backend(_)) => (),
+            _ => panic!("session should not have been read"),
+        }
+    }
+
+    #[test]
+    fn read_reply_test() {
+        let read = |bytes: &[u8]| read_reply(&mut &bytes[..]).unwrap();
+
+        assert_eq!(read(b"+OK\r\n"), Reply::Status("OK".to_owned()));
+        assert_eq!(read(b"-ERR bad\r\n"), Reply::Error("ERR bad".to_owned()));
+        assert_eq!(read(b":42\r\n"), Reply::Integer(42));
+        assert_eq!(read(b"$-1\r\n"), Reply::Bulk(None));
+        assert_eq!(
+            read(b"$4\r\na\r\nb\r\n"),
+            Reply::Bulk(Some(b"a\r\nb".to_vec()))
+        );
+        assert_eq!(
+            read(b"*2\r\n:1\r\n$1\r\nx\r\n"),
+            Reply::Array(Some(vec![
+                Reply::Integer(1),
+                Reply::Bulk(Some(b"x".to_vec()))
+            ]))
+        );
+        assert!(read_reply(&mut &b"$4\r\nab"[..]).is_err());
+        assert!(read_reply(&mut &b"$1099511627776\r\n"[..]).is_err());
+    }
+}
@@ -0,0 +1,224 @@
+//! Defines flash messages, which are kept in a session to be shown once by a later request.
+
+use std::fmt::{self, Display, Formatter};
+use std::vec;
+
+use serde_derive::{Deserialize, Serialize};
+
+/// The level of a `FlashMessage`, which is typically used to choose how the message is shown.
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Hash, Serialize, Deserialize)]
+pub enum FlashLevel {
+    /// A message which is purely informational.
+    Info,
+    /// A message confirming that an action succeeded.
+    Success,
+    /// A message warning of a possible problem.
+    Warning,
+    /// A message reporting that an action failed.
+    Error,
+}
+
+impl FlashLevel {
+    /// The name of the level in lowercase, such as `"success"`, which suits use as a CSS class.
+    pub fn as_str(self) -> &'static str {
+        match self {
+            FlashLevel::Info => "info",
+            FlashLevel::Success => "success",
+            FlashLevel::Warning => "warning",
+            FlashLevel::Error => "error",
+        }
+    }
+}
+
+impl Display for FlashLevel {
+    fn fmt(&self, f: &mut Formatter<'_>) -> fmt::Result {
+        f.write_str(self.as_str())
+    }
+}
+
+/// A message added to `FlashMessages`, along with its level.
+#[derive(Clone, Debug, PartialEq, Serialize, Deserialize)]
+pub struct FlashMessage {
+    level: FlashLevel,
+    text: String,
+}
+
+impl FlashMessage {
+    /// The level of the message.
+    pub fn level(&self) -> FlashLevel {
+        self.level
+    }
+
+    /// The text of the message.
+    pub fn text(&self) -> &str {
+        &self.text
+    }
+}
+
+/// Messages which are kept in a session until they're taken, typically to be shown by the page
+/// a client is redirected to after submitting a form.
+///
+/// `FlashMessages` is included in the session type given to `NewSessionMiddleware`. A message
+/// which is added while handling one request is taken while handling the next, which removes it
+/// from the session, so that each message is shown exactly once.
+///
+/// # Examples
+///
+/// ```rust
+/// # extern crate gotham;
+/// # extern crate hyper;
+/// # extern crate mime;
+/// # #[macro_use]
+/// # extern crate serde_derive;
+/// #
+/// # use hyper::header::{COOKIE, LOCATION, SET_COOKIE};
+/// # use hyper::{Body, Response, StatusCode};
+/// # use gotham::helpers::http::response::create_empty_response;
+/// # use gotham::middleware::session::{FlashMessages, NewSessionMiddleware, SessionData};
+/// # use gotham::pipeline::new_pipeline;
+/// # use gotham::pipeline::single::single_pipeline;
+/// # use gotham::router::builder::*;
+/// # use gotham::state::{FromState, State};
+/// # use gotham::test::TestServer;
+/// #
+/// #[derive(Default, Serialize, Deserialize)]
+/// struct MySession {
+///     flash: FlashMessages,
+/// }
+///
+/// fn save(mut state: State) -> (State, Response<Body>) {
+///     SessionData::<MySession>::borrow_mut_from(&mut state)
+///         .flash
+///         .success("Saved successfully");
+///
+///     let mut res = create_empty_response(&state, StatusCode::SEE_OTHER);
+///     res.headers_mut().insert(LOCATION, "/".parse().unwrap());
+///     (state, res)
+/// }
+///
+/// fn show(mut state: State) -> (State, String) {
+///     let banners = SessionData::<MySession>::borrow_mut_from(&mut state)
+///         .flash
+///         .take()
+///         .map(|message| format!("{}: {}", message.level(), message.text()))
+///         .collect::<Vec<_>>()
+///         .join("\n");
+///
+///     (state, banners)
+/// }
+///
+/// # fn main() {
+/// let middleware = NewSessionMiddleware::default()
+///     .with_session_type::<MySession>()
+///     .insecure();
+///
+/// let (chain, pipelines) = single_pipeline(new_pipeline().add(middleware).build());
+/// let router = build_router(chain, pipelines, |route| {
+///     route.post("/").to(save);
+///     route.get("/").to(show);
+/// });
+///
+/// let test_server = TestServer::new(router).unwrap();
+/// let response = test_server
+///     .client()
+///     .post("http://localhost/", "", mime::TEXT_PLAIN)
+///     .perform()
+///     .unwrap();
+/// let cookie = response.headers()[SET_COOKIE].to_str().unwrap();
+/// let cookie = cookie.split(';').next().unwrap().to_owned();
+///
+/// let show = || {
+///     test_server
+///         .client()
+///         .get("http://localhost/")
+///         .with_header(COOKIE, cookie.parse().unwrap())
+///         .perform()
+///         .unwrap()
+///         .read_utf8_body()
+///         .unwrap()
+/// };
+///
+/// assert_eq!(show(), "success: Saved successfully");
+/// assert_eq!(show(), "");
+/// # }
+/// ```
+#[derive(Clone, Debug, Default, PartialEq, Serialize, Deserialize)]
+pub struct FlashMessages {
+    messages: Vec<FlashMessage>,
+}
+
+impl FlashMessages {
+    /// Adds a message at `level`, to be taken by a later request.
+    pub fn add<S>(&mut self, level: FlashLevel, text: S)
+    where
+        S: Into<String>,
+    {
+        self.messages.push(FlashMessage {
+            level,
+            text: text.into(),
+        });
+    }
+
+    /// Adds a message at `FlashLevel::Info`.
+    pub fn info<S: Into<String>>(&mut self, text: S) {
+        self.add(FlashLevel::Info, text)
+    }
+
+    /// Adds a message at `FlashLevel::Success`.
+    pub fn success<S: Into<String>>(&mut self, text: S) {
+        self.add(FlashLevel::Success, text)
+    }
+
+    /// Adds a message at `FlashLevel::Warning`.
+    pub fn warning<S: Into<String>>(&mut self, text: S) {
+        self.add(FlashLevel::Warning, text)
+    }
+
+    /// Adds a message at `FlashLevel::Error`.
+    pub fn error<S: Into<String>>(&mut self, text: S) {
+        self.add(FlashLevel::Error, text)
+    }
+
+    /// Returns `true` where there are no messages to be taken.
+    pub fn is_empty(&self) -> bool {
+        self.messages.is_empty()
+    }
+
+    /// Iterates over the messages without taking them, so that they remain in the session.
+    pub fn peek(&self) -> impl Iterator<Item = &FlashMessage> {
+        self.messages.iter()
+    }
+
+    /// Takes the messages, in the order they were added, removing them from the session.
+    pub fn take(&mut self) -> vec::IntoIter<FlashMessage> {
+        std::mem::take(&mut self.messages).into_iter()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn takes_messages_once() {
+        let mut flash = FlashMessages::default();
+        assert!(flash.is_empty());
+
+        flash.info("one");
+        flash.error(String::from("two"));
+        flash.add(FlashLevel::Warning, "three");
+        assert_eq!(flash.peek().count(), 3);
+
+        let serialized = bincode::serialize(&flash).unwrap();
+        let mut flash = bincode::deserialize::<FlashMessages>(&serialized).unwrap();
+
+        let messages = flash
+            .take()
+            .map(|message| format!("{} {}", message.level(), message.text()))
+            .collect::<Vec<_>>();
+        assert_eq!(messages, vec!["info one", "error two", "warning three"]);
+
+        assert!(flash.is_empty());
+        assert_eq!(flash.take().count(), 0);
+    }
+}
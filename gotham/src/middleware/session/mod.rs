@@ -5,14 +5,12 @@ use std::marker::PhantomData;
 use std::ops::{Deref, DerefMut};
 use std::panic::RefUnwindSafe;
 use std::sync::{Arc, Mutex, PoisonError};
+use std::time::{Duration, SystemTime, UNIX_EPOCH};
 
 use base64;
 use bincode;
 use cookie::{Cookie, CookieJar};
-use futures::{
-    future::{self, FutureResult},
-    Future,
-};
+use futures::{future, Future};
 use hyper::header::SET_COOKIE;
 use hyper::{Body, Response, StatusCode};
 use log::{error, trace, warn};
@@ -26,10 +24,17 @@ use crate::helpers::http::response::create_empty_response;
 use crate::state::{self, FromState, State, StateData};
 
 mod backend;
+mod flash;
 mod rng;
 
+pub use self::backend::cookie::CookieBackend;
+pub use self::backend::file::FileBackend;
 pub use self::backend::memory::MemoryBackend;
-pub use self::backend::{Backend, NewBackend};
+pub use self::backend::redis::RedisBackend;
+pub use self::backend::{
+    Backend, NewBackend, SessionCookieFuture, SessionFuture, SessionWriteFuture,
+};
+pub use self::flash::{FlashLevel, FlashMessage, FlashMessages};
 
 const SECURE_COOKIE_PREFIX: &str = "__Secure-";
 const HOST_COOKIE_PREFIX: &str = "__Host-";
@@ -61,6 +66,8 @@ enum SessionCookieState {
 enum SessionDataState {
     Clean,
     Dirty,
+    // a new session which hasn't been changed, which is persisted as a dirty session is
+    Fresh,
 }
 
 #[derive(Copy, Clone, PartialEq, Debug)]
@@ -86,6 +93,98 @@ struct SessionCookieConfig {
     domain: Option<String>,
 }
 
+/// Configuration for when sessions expire, in addition to any expiry of the backend.
+///
+/// Where either expiry is configured, the times at which a session was created and last used are
+/// persisted along with it, and checked as it's loaded.
+#[derive(Clone, Copy, Debug, Default)]
+struct SessionExpiry {
+    idle: Option<Duration>,
+    absolute: Option<Duration>,
+}
+
+impl SessionExpiry {
+    fn enabled(&self) -> bool {
+        self.idle.is_some() || self.absolute.is_some()
+    }
+
+    fn has_expired(&self, created: u64, accessed: u64, now: u64) -> bool {
+        let idle = self
+            .idle
+            .is_some_and(|idle| now.saturating_sub(accessed) >= idle.as_secs());
+        let absolute = self
+            .absolute
+            .is_some_and(|absolute| now.saturating_sub(created) >= absolute.as_secs());
+
+        idle || absolute
+    }
+
+    /// The number of seconds for which the session cookie of a session is kept, which is the
+    /// idle timeout, or the remainder of the absolute timeout where that's sooner.
+    fn max_age(&self, created: u64, now: u64) -> Option<u64> {
+        let idle = self.idle.map(|idle| idle.as_secs());
+        let remaining = self
+            .absolute
+            .map(|absolute| (created + absolute.as_secs()).saturating_sub(now));
+
+        match (idle, remaining) {
+            (Some(idle), Some(remaining)) => Some(idle.min(remaining)),
+            (idle, remaining) => idle.or(remaining),
+        }
+    }
+}
+
+// The tag which begins a session persisted with a schema version or with expiry. It's followed by
+// the version, which is `0` where none is set, and the times at which the session was created and
+// last used, which take 20 bytes, and then by the serialized session.
+const SCHEMA_TAG: [u8; 4] = [0xff, b'g', b's', b'v'];
+
+// The length of the tag and the fields which follow it, as `bincode` encodes them at a fixed width.
+const SCHEMA_HEADER_LEN: usize = 24;
+
+/// Type alias for the migrations given to `NewSessionMiddleware::with_migration`.
+type Migration<T> = dyn Fn(u32, &[u8]) -> Option<T> + Send + Sync + RefUnwindSafe;
+
+/// The schema version which sessions are persisted with, and the migration of sessions persisted
+/// with other versions.
+struct SessionSchema<T> {
+    version: Option<u32>,
+    migration: Option<Arc<Migration<T>>>,
+}
+
+impl<T> SessionSchema<T> {
+    // The version of persisted sessions, where sessions without a version are version `0`.
+    fn current(&self) -> u32 {
+        self.version.unwrap_or(0)
+    }
+}
+
+impl<T> Clone for SessionSchema<T> {
+    fn clone(&self) -> Self {
+        SessionSchema {
+            version: self.version,
+            migration: self.migration.clone(),
+        }
+    }
+}
+
+impl<T> Default for SessionSchema<T> {
+    fn default() -> Self {
+        SessionSchema {
+            version: None,
+            migration: None,
+        }
+    }
+}
+
+// The time as the number of seconds since the epoch, as persisted with sessions.
+fn unix_time() -> u64 {
+    SystemTime::now()
+        .duration_since(UNIX_EPOCH)
+        .map(|elapsed| elapsed.as_secs())
+        .unwrap_or(0)
+}
+
 impl Default for SessionCookieConfig {
     fn default() -> SessionCookieConfig {
         SessionCookieConfig {
@@ -199,7 +298,7 @@ impl SessionCookieConfig {
 /// #
 /// # use std::sync::Arc;
 /// # use std::time::Duration;
-/// # use futures::future;
+/// # use futures::{future, Future};
 /// # use gotham::handler::HandlerFuture;
 /// # use gotham::state::{State, FromState};
 /// # use gotham::middleware::{NewMiddleware, Middleware};
@@ -239,7 +338,7 @@ impl SessionCookieConfig {
 /// #   };
 /// #
 /// #   let bytes = bincode::serialize(&session).unwrap();
-/// #   backend.persist_session(identifier.clone(), &bytes[..]).unwrap();
+/// #   backend.persist_session(identifier.clone(), &bytes[..]).wait().unwrap();
 /// #
 /// #   let nm = NewSessionMiddleware::new(backend).with_session_type::<MySessionType>();
 /// #   let nm = Arc::new(nm);
@@ -279,10 +378,15 @@ where
     identifier: SessionIdentifier,
     backend: Box<dyn Backend + Send>,
     cookie_config: Arc<SessionCookieConfig>,
+    expiry: SessionExpiry,
+    version: Option<u32>,
+    created: u64,
 }
 
 struct SessionDropData {
     cookie_config: Arc<SessionCookieConfig>,
+    identifier: SessionIdentifier,
+    backend: Box<dyn Backend + Send>,
 }
 
 impl<T> SessionData<T>
@@ -291,12 +395,18 @@ where
 {
     /// Discards the session, invalidating it for future use and removing the data from the
     /// `Backend`.
-    // TODO: Add test case that covers this.
+    ///
+    /// The session is removed from the `Backend` once the handler has responded, before the
+    /// response is sent along with a cookie removing the session cookie from the user agent. The
+    /// response is replaced with a `500 Internal Server Error` where the `Backend` fails to remove
+    /// the session, so the `Result` returned here is always `Ok`.
     pub fn discard(self, state: &mut State) -> Result<(), SessionError> {
         state.put(SessionDropData {
             cookie_config: self.cookie_config,
+            identifier: self.identifier,
+            backend: self.backend,
         });
-        self.backend.drop_session(self.identifier)
+        Ok(())
     }
 
     // Create a new, blank `SessionData<T>`
@@ -304,12 +414,15 @@ where
     where
         B: Backend + Send + 'static,
     {
-        let state = SessionDataState::Dirty; // Always persist a new session
+        let state = SessionDataState::Fresh; // Always persist a new session
         let cookie_state = SessionCookieState::New;
         let identifier = middleware.random_identifier();
         let value = T::default();
         let backend = Box::new(middleware.backend);
         let cookie_config = middleware.cookie_config.clone();
+        let expiry = middleware.expiry;
+        let version = middleware.schema.version;
+        let created = unix_time();
 
         trace!(
             " no existing session, assigning new identifier ({})",
@@ -323,25 +436,30 @@ where
             identifier,
             backend,
             cookie_config,
+            expiry,
+            version,
+            created,
         }
     }
 
-    // Load an existing, serialized session into a `SessionData<T>`
+    // Load an existing, serialized session into a `SessionData<T>`, along with the future removing
+    // the session from the backend where it has expired
     fn construct<B>(
         middleware: SessionMiddleware<B, T>,
         identifier: SessionIdentifier,
         val: Option<Vec<u8>>,
-    ) -> SessionData<T>
+    ) -> (SessionData<T>, Option<Box<SessionWriteFuture>>)
     where
         B: Backend + Send + 'static,
     {
         let cookie_state = SessionCookieState::Existing;
-        let state = SessionDataState::Clean;
+        let expiry = middleware.expiry;
+        let version = middleware.schema.version;
 
         match val {
             Some(val) => {
-                match bincode::deserialize::<T>(&val[..]) {
-                    Ok(value) => {
+                match deserialize_session::<T>(&middleware.schema, &expiry, &val[..]) {
+                    Ok(Some((value, created, migrated))) => {
                         let backend = Box::new(middleware.backend);
                         let cookie_config = middleware.cookie_config.clone();
 
@@ -350,14 +468,36 @@ where
                             identifier.value
                         );
 
-                        SessionData {
+                        // sessions which expire when idle are persisted on each use, to record
+                        // their use, and migrated sessions are persisted with the current version
+                        let state = if migrated || expiry.idle.is_some() {
+                            SessionDataState::Dirty
+                        } else {
+                            SessionDataState::Clean
+                        };
+
+                        let session_data = SessionData {
                             value,
                             cookie_state,
                             state,
                             identifier,
                             backend,
                             cookie_config,
-                        }
+                            expiry,
+                            version,
+                            created,
+                        };
+                        (session_data, None)
+                    }
+                    Ok(None) => {
+                        trace!(
+                            " session ({}) has expired or was dropped by its migration, falling \
+                             back to new session",
+                            identifier.value
+                        );
+
+                        let expired = middleware.backend.drop_session(identifier);
+                        (SessionData::new(middleware), Some(expired))
                     }
                     Err(_) => {
                         // This is most likely caused by the application changing their session
@@ -366,15 +506,78 @@ where
                             " failed to deserialize session data ({}), falling back to new session",
                             identifier.value
                         );
-                        SessionData::new(middleware)
+                        (SessionData::new(middleware), None)
                     }
                 }
             }
-            None => SessionData::new(middleware),
+            None => (SessionData::new(middleware), None),
         }
     }
 }
 
+// Deserializes a session, along with the time at which it was created and whether it was migrated
+// from another schema version, unless it has expired or its migration dropped it.
+fn deserialize_session<T>(
+    schema: &SessionSchema<T>,
+    expiry: &SessionExpiry,
+    bytes: &[u8],
+) -> bincode::Result<Option<(T, u64, bool)>>
+where
+    T: for<'de> Deserialize<'de>,
+{
+    let now = unix_time();
+
+    let (version, created, value) = if bytes.starts_with(&SCHEMA_TAG) {
+        let (version, created, accessed) =
+            bincode::deserialize::<(u32, u64, u64)>(&bytes[SCHEMA_TAG.len()..])?;
+        if expiry.has_expired(created, accessed, now) {
+            return Ok(None);
+        }
+        (version, created, &bytes[SCHEMA_HEADER_LEN..])
+    } else if expiry.enabled() {
+        // sessions persisted with expiry always have the tag, so this one was persisted before
+        // expiry was enabled, and there's no telling when it was last used
+        trace!(" replacing session persisted without expiry times");
+        return Ok(None);
+    } else {
+        (0, now, bytes)
+    };
+
+    match schema.migration {
+        Some(ref migration) if version != schema.current() => {
+            trace!(
+                " migrating session from version {} to {}",
+                version,
+                schema.current()
+            );
+            Ok(migration(version, value).map(|value| (value, created, true)))
+        }
+        _ => bincode::deserialize::<T>(value).map(|value| Some((value, created, false))),
+    }
+}
+
+// Serializes a session, along with its schema version and the times at which it was created and
+// last used, where it has a version or expires.
+fn serialize_session<T>(session_data: &SessionData<T>) -> bincode::Result<Vec<u8>>
+where
+    T: Default + Serialize + for<'de> Deserialize<'de> + Send + 'static,
+{
+    if session_data.version.is_none() && !session_data.expiry.enabled() {
+        return bincode::serialize(&session_data.value);
+    }
+
+    let mut bytes = SCHEMA_TAG.to_vec();
+    let version = session_data.version.unwrap_or(0);
+    let fields = (
+        version,
+        session_data.created,
+        unix_time(),
+        &session_data.value,
+    );
+    bincode::serialize_into(&mut bytes, &fields)?;
+    Ok(bytes)
+}
+
 impl<T> StateData for SessionData<T> where
     T: Default + Serialize + for<'de> Deserialize<'de> + Send + 'static
 {
@@ -467,6 +670,8 @@ where
     new_backend: B,
     identifier_rng: Arc<Mutex<rng::SessionIdentifierRng>>,
     cookie_config: Arc<SessionCookieConfig>,
+    expiry: SessionExpiry,
+    schema: SessionSchema<T>,
     phantom: PhantomData<dyn SessionTypePhantom<T>>,
 }
 
@@ -481,6 +686,8 @@ where
     backend: B,
     identifier_rng: Arc<Mutex<rng::SessionIdentifierRng>>,
     cookie_config: Arc<SessionCookieConfig>,
+    expiry: SessionExpiry,
+    schema: SessionSchema<T>,
     phantom: PhantomData<T>,
 }
 
@@ -498,6 +705,8 @@ where
                 backend,
                 identifier_rng: self.identifier_rng.clone(),
                 cookie_config: self.cookie_config.clone(),
+                expiry: self.expiry,
+                schema: self.schema.clone(),
                 phantom: PhantomData,
             })
     }
@@ -513,6 +722,8 @@ where
             new_backend: self.new_backend.clone(),
             identifier_rng: self.identifier_rng.clone(),
             cookie_config: self.cookie_config.clone(),
+            expiry: self.expiry,
+            schema: self.schema.clone(),
             phantom: PhantomData,
         }
     }
@@ -535,6 +746,8 @@ where
             new_backend: b,
             identifier_rng: Arc::new(Mutex::new(rng::session_identifier_rng())),
             cookie_config: Arc::new(SessionCookieConfig::default()),
+            expiry: SessionExpiry::default(),
+            schema: SessionSchema::default(),
             phantom: PhantomData,
         }
     }
@@ -762,6 +975,161 @@ where
         self.rebuild_new_session_middleware(cookie_config)
     }
 
+    /// Expires sessions which haven't been used for `timeout`, discarding them as they're next
+    /// loaded. Each use of a session persists it again, to record the use, and refreshes the
+    /// `Max-Age` of the session cookie, which is set to `timeout`.
+    ///
+    /// Configuring an expiry changes the way sessions are persisted, so sessions persisted before
+    /// an expiry was configured are replaced by new sessions.
+    ///
+    /// ```rust
+    /// # extern crate gotham;
+    /// # #[macro_use]
+    /// # extern crate serde_derive;
+    /// #
+    /// # use std::time::Duration;
+    /// # use gotham::middleware::session::NewSessionMiddleware;
+    /// #
+    /// # #[derive(Default, Serialize, Deserialize)]
+    /// # struct MySessionType {
+    /// #   items: Vec<String>,
+    /// # }
+    /// #
+    /// # fn main() {
+    /// NewSessionMiddleware::default()
+    ///     .with_session_type::<MySessionType>()
+    ///     .with_idle_timeout(Duration::from_secs(30 * 60))
+    /// # ;}
+    /// ```
+    pub fn with_idle_timeout(self, timeout: Duration) -> NewSessionMiddleware<B, T> {
+        let expiry = SessionExpiry {
+            idle: Some(timeout),
+            ..self.expiry
+        };
+        NewSessionMiddleware { expiry, ..self }
+    }
+
+    /// Expires sessions once `timeout` has passed since they were created, regardless of their
+    /// use. The `Max-Age` of the session cookie is set to the remainder of `timeout`.
+    ///
+    /// As with `with_idle_timeout`, sessions persisted before an expiry was configured are
+    /// replaced by new sessions.
+    ///
+    /// ```rust
+    /// # extern crate gotham;
+    /// # #[macro_use]
+    /// # extern crate serde_derive;
+    /// #
+    /// # use std::time::Duration;
+    /// # use gotham::middleware::session::NewSessionMiddleware;
+    /// #
+    /// # #[derive(Default, Serialize, Deserialize)]
+    /// # struct MySessionType {
+    /// #   items: Vec<String>,
+    /// # }
+    /// #
+    /// # fn main() {
+    /// NewSessionMiddleware::default()
+    ///     .with_session_type::<MySessionType>()
+    ///     .with_idle_timeout(Duration::from_secs(30 * 60))
+    ///     .with_absolute_timeout(Duration::from_secs(24 * 60 * 60))
+    /// # ;}
+    /// ```
+    pub fn with_absolute_timeout(self, timeout: Duration) -> NewSessionMiddleware<B, T> {
+        let expiry = SessionExpiry {
+            absolute: Some(timeout),
+            ..self.expiry
+        };
+        NewSessionMiddleware { expiry, ..self }
+    }
+
+    /// Persists sessions tagged with the schema version `version`, so that sessions persisted
+    /// with an earlier version of the session type can be told apart once the type has changed.
+    ///
+    /// A session persisted with another version is given to the migration configured by
+    /// `with_migration`. Sessions persisted before a version was configured are version `0`.
+    ///
+    /// ```rust
+    /// # extern crate gotham;
+    /// # #[macro_use]
+    /// # extern crate serde_derive;
+    /// #
+    /// # use gotham::middleware::session::NewSessionMiddleware;
+    /// #
+    /// # #[derive(Default, Serialize, Deserialize)]
+    /// # struct MySessionType {
+    /// #   items: Vec<String>,
+    /// # }
+    /// #
+    /// # fn main() {
+    /// NewSessionMiddleware::default()
+    ///     .with_session_type::<MySessionType>()
+    ///     .with_schema_version(2)
+    /// # ;}
+    /// ```
+    pub fn with_schema_version(self, version: u32) -> NewSessionMiddleware<B, T> {
+        let schema = SessionSchema {
+            version: Some(version),
+            ..self.schema
+        };
+        NewSessionMiddleware { schema, ..self }
+    }
+
+    /// Migrates sessions persisted with a schema version other than the one configured by
+    /// `with_schema_version`, rather than failing to deserialize them as the current session type
+    /// and replacing them with new sessions.
+    ///
+    /// The migration is given the version of the persisted session and the session as it was
+    /// serialized by `bincode`, and returns the session converted to the current session type.
+    /// Where it returns `None`, the session is dropped from the `Backend` and replaced by a new
+    /// session. A migrated session is persisted again with the current version once the request
+    /// has been handled.
+    ///
+    /// The migration applies to the current session type, so `with_session_type` must be called
+    /// first.
+    ///
+    /// ```rust
+    /// # extern crate bincode;
+    /// # extern crate gotham;
+    /// # #[macro_use]
+    /// # extern crate serde_derive;
+    /// #
+    /// # use gotham::middleware::session::NewSessionMiddleware;
+    /// #
+    /// #[derive(Deserialize)]
+    /// struct SessionV1 {
+    ///     item: String,
+    /// }
+    ///
+    /// #[derive(Default, Serialize, Deserialize)]
+    /// struct SessionV2 {
+    ///     items: Vec<String>,
+    /// }
+    ///
+    /// # fn main() {
+    /// NewSessionMiddleware::default()
+    ///     .with_session_type::<SessionV2>()
+    ///     .with_schema_version(2)
+    ///     .with_migration(|version, bytes| match version {
+    ///         1 => {
+    ///             let v1 = bincode::deserialize::<SessionV1>(bytes).ok()?;
+    ///             Some(SessionV2 { items: vec![v1.item] })
+    ///         }
+    ///         _ => None,
+    ///     })
+    /// # ;}
+    /// ```
+    pub fn with_migration<F>(self, migration: F) -> NewSessionMiddleware<B, T>
+    where
+        F: Fn(u32, &[u8]) -> Option<T> + Send + Sync + RefUnwindSafe + 'static,
+    {
+        let schema = SessionSchema {
+            migration: Some(Arc::new(migration)),
+            ..self.schema
+        };
+        NewSessionMiddleware { schema, ..self }
+    }
+
     /// Changes the session type to the provided type parameter. This is required to override the
     /// default (unusable) session type of `()`.
     ///
@@ -789,6 +1157,11 @@ where
             new_backend: self.new_backend,
             identifier_rng: self.identifier_rng,
             cookie_config: self.cookie_config,
+            expiry: self.expiry,
+            schema: SessionSchema {
+                version: self.schema.version,
+                migration: None,
+            },
             phantom: PhantomData,
         }
     }
@@ -869,20 +1242,18 @@ where
     }
 }
 
-fn persist_session<T>(
-    (mut state, mut response): (State, Response<Body>),
-) -> FutureResult<(State, Response<Body>), (State, HandlerError)>
+fn persist_session<T>((mut state, mut response): (State, Response<Body>)) -> Box<HandlerFuture>
 where
     T: Default + Serialize + for<'de> Deserialize<'de> + Send + 'static,
 {
     match state.try_take::<SessionDropData>() {
-        Some(ref session_drop_data) => {
+        Some(session_drop_data) => {
             trace!(
                 "[{}] SessionDropData found in state, removing session cookie from user agent",
                 state::request_id(&state)
             );
-            reset_cookie(&mut response, session_drop_data);
-            return future::ok((state, response));
+            reset_cookie(&mut response, &session_drop_data);
+            return drop_session(state, response, session_drop_data);
         }
         None => {
             trace!(
@@ -893,28 +1264,76 @@ where
     }
 
     match state.try_take::<SessionData<T>>() {
-        Some(session_data) => {
-            if let SessionCookieState::New = session_data.cookie_state {
-                send_cookie(&mut response, &session_data);
+        Some(session_data) => match session_data.state {
+            SessionDataState::Dirty | SessionDataState::Fresh => {
+                write_session(state, response, session_data)
             }
-
-            match session_data.state {
-                SessionDataState::Dirty => write_session(state, response, session_data),
-                SessionDataState::Clean => future::ok((state, response)),
+            SessionDataState::Clean => {
+                if let SessionCookieState::New = session_data.cookie_state {
+                    send_cookie(&mut response, &session_data);
+                }
+                Box::new(future::ok((state, response)))
             }
-        }
-        // Session was discarded with `SessionData::discard`, or otherwise removed
-        None => future::ok((state, response)),
+        },
+        // Session was removed from `State` by the application
+        None => Box::new(future::ok((state, response))),
     }
 }
 
+fn drop_session(
+    state: State,
+    response: Response<Body>,
+    session_drop_data: SessionDropData,
+) -> Box<HandlerFuture> {
+    let SessionDropData {
+        identifier,
+        backend,
+        ..
+    } = session_drop_data;
+
+    let f = backend
+        .drop_session(identifier.clone())
+        .then(move |result| match result {
+            Ok(()) => {
+                trace!(
+                    "[{}] dropped session ({}) successfully",
+                    state::request_id(&state),
+                    identifier.value
+                );
+
+                Ok((state, response))
+            }
+            Err(e) => {
+                error!(
+                    "[{}] failed to drop session ({}): {:?}",
+                    state::request_id(&state),
+                    identifier.value,
+                    e
+                );
+
+                let response = create_empty_response(&state, StatusCode::INTERNAL_SERVER_ERROR);
+
+                Ok((state, response))
+            }
+        });
+
+    Box::new(f)
+}
+
 fn send_cookie<B, T>(response: &mut Response<B>, session_data: &SessionData<T>)
 where
     T: Default + Serialize + for<'de> Deserialize<'de> + Send + 'static,
 {
-    let cookie_string = session_data
+    let mut cookie_string = session_data
         .cookie_config
         .to_cookie_string(&session_data.identifier.value);
+
+    if let Some(max_age) = session_data
+        .expiry
+        .max_age(session_data.created, unix_time())
+    {
+        cookie_string.push_str(&format!("; Max-Age={}", max_age));
+    }
     write_cookie(cookie_string, response);
 }
 
@@ -937,13 +1356,13 @@ fn write_cookie<B>(cookie: String, response: &mut Response<B>) {
 
 fn write_session<T>(
     state: State,
-    response: Response<Body>,
-    session_data: SessionData<T>,
-) -> future::FutureResult<(State, Response<Body>), (State, HandlerError)>
+    mut response: Response<Body>,
+    mut session_data: SessionData<T>,
+) -> Box<HandlerFuture>
 where
     T: Default + Serialize + for<'de> Deserialize<'de> + Send + 'static,
 {
-    let bytes = match bincode::serialize(&session_data.value) {
+    let bytes = match serialize_session(&session_data) {
         Ok(bytes) => bytes,
         Err(e) => {
             error!(
@@ -954,33 +1373,55 @@ where
 
             let response = create_empty_response(&state, StatusCode::INTERNAL_SERVER_ERROR);
 
-            return future::ok((state, response));
+            return Box::new(future::ok((state, response)));
         }
     };
 
-    let identifier = session_data.identifier;
-    let slice = &bytes[..];
+    let identifier = session_data.identifier.clone();
 
-    let result = session_data
+    let f = session_data
         .backend
-        .persist_session(identifier.clone(), slice);
+        .persist_session_cookie(identifier.clone(), &bytes[..])
+        .then(move |result| match result {
+            Ok(replacement) => {
+                trace!(
+                    "[{}] persisted session ({}) successfully",
+                    state::request_id(&state),
+                    identifier.value
+                );
 
-    match result {
-        Ok(_) => {
-            trace!(
-                "[{}] persisted session ({}) successfully",
-                state::request_id(&state),
-                identifier.value
-            );
+                // the session cookie is sent for new sessions, where the backend has replaced the
+                // identifier held in it, and to refresh the cookie of sessions which expire when
+                // idle. A new session which hasn't been changed isn't sent where the backend keeps
+                // sessions in the cookie, as the cookie would only hold the default session.
+                let new = match session_data.cookie_state {
+                    SessionCookieState::New => true,
+                    SessionCookieState::Existing => false,
+                };
+                let fresh = matches!(session_data.state, SessionDataState::Fresh);
+                let refresh = if fresh && replacement.is_some() {
+                    false
+                } else {
+                    new || replacement.is_some() || session_data.expiry.idle.is_some()
+                };
 
-            future::ok((state, response))
-        }
-        Err(_) => {
-            let response = create_empty_response(&state, StatusCode::INTERNAL_SERVER_ERROR);
+                if let Some(replacement) = replacement {
+                    session_data.identifier = replacement;
+                }
+                if refresh {
+                    send_cookie(&mut response, &session_data);
+                }
 
-            future::ok((state, response))
-        }
-    }
+                Ok((state, response))
+            }
+            Err(_) => {
+                let response = create_empty_response(&state, StatusCode::INTERNAL_SERVER_ERROR);
+
+                Ok((state, response))
+            }
+        });
+
+    Box::new(f)
 }
 
 impl<B, T> SessionMiddleware<B, T>
@@ -993,7 +1434,7 @@ where
         mut state: State,
         identifier: SessionIdentifier,
         result: Result<Option<Vec<u8>>, SessionError>,
-    ) -> future::FutureResult<State, (State, HandlerError)> {
+    ) -> Box<dyn Future<Item = State, Error = (State, HandlerError)> + Send> {
         match result {
             Ok(v) => {
                 trace!(
@@ -1003,10 +1444,22 @@ where
                     v.is_some()
                 );
 
-                let session_data = SessionData::<T>::construct(self, identifier, v);
-
+                let (session_data, expired) = SessionData::<T>::construct(self, identifier, v);
                 state.put(session_data);
-                future::ok(state)
+
+                match expired {
+                    Some(expired) => Box::new(expired.then(move |result| {
+                        if let Err(e) = result {
+                            warn!(
+                                "[{}] failed to drop expired session: {:?}",
+                                state::request_id(&state),
+                                e
+                            );
+                        }
+                        Ok(state)
+                    })),
+                    None => Box::new(future::ok(state)),
+                }
             }
             Err(e) => {
                 error!(
@@ -1021,7 +1474,7 @@ where
                     format!("backend failed to return session: {:?}", e),
                 );
 
-                future::err((state, e.into_handler_error()))
+                Box::new(future::err((state, e.into_handler_error())))
             }
         }
     }
@@ -1057,6 +1510,42 @@ mod tests {
         val: u64,
     }
 
+    // Persists `bytes` as a session, and makes a request with its cookie, which is answered with
+    // the `val` of the session loaded for the request in an `x-val` header.
+    fn request_with_session<B>(
+        nm: &NewSessionMiddleware<B, TestSession>,
+        bytes: &[u8],
+    ) -> (SessionIdentifier, Response<Body>)
+    where
+        B: NewBackend,
+    {
+        let m = nm.new_middleware().unwrap();
+        let identifier = m.random_identifier();
+        m.backend
+            .persist_session(identifier.clone(), bytes)
+            .wait()
+            .unwrap();
+
+        let handler = |state: State| {
+            let val = state.borrow::<SessionData<TestSession>>().val;
+            let res = Response::builder()
+                .status(StatusCode::OK)
+                .header("x-val", val.to_string())
+                .body(Body::empty())
+                .unwrap();
+            Box::new(future::ok((state, res))) as Box<HandlerFuture>
+        };
+
+        let mut state = State::new();
+        let mut headers = HeaderMap::new();
+        let cookie = Cookie::build("_gotham_session", identifier.value.clone()).finish();
+        headers.insert(COOKIE, cookie.to_string().parse().unwrap());
+        state.put(headers);
+
+        let (_, response) = m.call(state, handler).wait().map_err(|(_, e)| e).unwrap();
+        (identifier, response)
+    }
+
     #[test]
     fn new_session() {
         let backend = MemoryBackend::new(Duration::from_secs(1));
@@ -1176,6 +1665,7 @@ mod tests {
 
         m.backend
             .persist_session(identifier.clone(), &bytes)
+            .wait()
             .unwrap();
 
         let received: Arc<Mutex<Option<u64>>> = Arc::new(Mutex::new(None));
@@ -1222,4 +1712,265 @@ mod tests {
 
         assert_eq!(updated.val, session.val + 1);
     }
+
+    #[test]
+    fn discard_session() {
+        let nm = NewSessionMiddleware::default().with_session_type::<TestSession>();
+        let m = nm.new_middleware().unwrap();
+
+        let identifier = m.random_identifier();
+        let bytes = bincode::serialize(&TestSession { val: 1 }).unwrap();
+        m.backend
+            .persist_session(identifier.clone(), &bytes)
+            .wait()
+            .unwrap();
+
+        let handler = |mut state: State| {
+            SessionData::<TestSession>::take_from(&mut state)
+                .discard(&mut state)
+                .unwrap();
+            let res = Response::builder()
+                .status(StatusCode::OK)
+                .body(Body::empty())
+                .unwrap();
+            Box::new(future::ok((state, res))) as Box<HandlerFuture>
+        };
+
+        let mut state = State::new();
+        let mut headers = HeaderMap::new();
+        let cookie = Cookie::build("_gotham_session", identifier.value.clone()).finish();
+        headers.insert(COOKIE, cookie.to_string().parse().unwrap());
+        state.put(headers);
+
+        let (_, response) = m.call(state, handler).wait().map_err(|(_, e)| e).unwrap();
+        assert_eq!(response.status(), StatusCode::OK);
+
+        let set_cookie = response.headers()[SET_COOKIE].to_str().unwrap();
+        assert!(set_cookie.starts_with("_gotham_session=discarded;"));
+        assert!(set_cookie.ends_with("; max-age=0"));
+
+        let m = nm.new_middleware().unwrap();
+        assert_eq!(m.backend.read_session(identifier).wait().unwrap(), None);
+    }
+
+    #[test]
+    fn session_expiry() {
+        let expiry = SessionExpiry {
+            idle: Some(Duration::from_secs(60)),
+            absolute: Some(Duration::from_secs(3600)),
+        };
+
+        assert!(!expiry.has_expired(1000, 1000, 1059));
+        assert!(expiry.has_expired(1000, 1000, 1060));
+        assert!(!expiry.has_expired(1000, 4550, 4599));
+        assert!(expiry.has_expired(1000, 4550, 4600));
+
+        assert_eq!(expiry.max_age(1000, 1000), Some(60));
+        assert_eq!(expiry.max_age(1000, 4570), Some(30));
+        assert_eq!(SessionExpiry::default().max_age(1000, 1000), None);
+    }
+
+    #[test]
+    fn idle_session() {
+        let nm = NewSessionMiddleware::default()
+            .with_session_type::<TestSession>()
+            .with_idle_timeout(Duration::from_secs(60));
+
+        let tagged = |accessed: u64| {
+            let mut bytes = SCHEMA_TAG.to_vec();
+            let fields = (0u32, accessed, accessed, TestSession { val: 7 });
+            bincode::serialize_into(&mut bytes, &fields).unwrap();
+            bytes
+        };
+
+        let request = |bytes: Vec<u8>| request_with_session(&nm, &bytes);
+
+        // a session used recently is kept, and its use recorded
+        let now = unix_time();
+        let (identifier, response) = request(tagged(now - 30));
+        assert_eq!(response.headers()["x-val"], "7");
+
+        let set_cookie = response.headers()[SET_COOKIE].to_str().unwrap();
+        assert!(set_cookie.starts_with(&format!("_gotham_session={};", identifier.value)));
+        assert!(set_cookie.ends_with("; Max-Age=60"));
+
+        let m = nm.new_middleware().unwrap();
+        let bytes = m.backend.read_session(identifier).wait().unwrap().unwrap();
+        assert!(bytes.starts_with(&SCHEMA_TAG));
+        let (version, created, accessed) =
+            bincode::deserialize::<(u32, u64, u64)>(&bytes[SCHEMA_TAG.len()..]).unwrap();
+        assert_eq!(version, 0);
+        assert_eq!(created, now - 30);
+        assert!(accessed >= now);
+
+        // a session which has been idle for too long is replaced
+        let (identifier, response) = request(tagged(now - 90));
+        assert_eq!(response.headers()["x-val"], "0");
+
+        let set_cookie = response.headers()[SET_COOKIE].to_str().unwrap();
+        assert!(!set_cookie.contains(&identifier.value));
+        assert!(m.backend.read_session(identifier).wait().unwrap().is_none());
+
+        // as is a session persisted without the tag, as its times can't be known, even where its
+        // first bytes would read as recent times
+        let mut untagged = bincode::serialize(&(now, now)).unwrap();
+        bincode::serialize_into(&mut untagged, &TestSession { val: 7 }).unwrap();
+        let (identifier, response) = request(untagged);
+        assert_eq!(response.headers()["x-val"], "0");
+        assert!(m.backend.read_session(identifier).wait().unwrap().is_none());
+    }
+
+    #[test]
+    fn migrate_session() {
+        #[derive(Serialize, Deserialize)]
+        struct OldSession {
+            count: u32,
+        }
+
+        let nm = NewSessionMiddleware::default()
+            .with_session_type::<TestSession>()
+            .with_schema_version(2)
+            .with_migration(|version, bytes| match version {
+                1 => bincode::deserialize::<OldSession>(bytes)
+                    .ok()
+                    .map(|old| TestSession {
+                        val: u64::from(old.count),
+                    }),
+                _ => None,
+            });
+
+        let tagged = |version: u32, value: &dyn Fn(&mut Vec<u8>)| {
+            let now = unix_time();
+            let mut bytes = SCHEMA_TAG.to_vec();
+            bincode::serialize_into(&mut bytes, &(version, now, now)).unwrap();
+            value(&mut bytes);
+            bytes
+        };
+
+        let request = |bytes: Vec<u8>| {
+            let (identifier, response) = request_with_session(&nm, &bytes);
+            let stored = nm
+                .new_middleware()
+                .unwrap()
+                .backend
+                .read_session(identifier)
+                .wait()
+                .unwrap();
+            (response.headers()["x-val"].to_owned(), stored)
+        };
+
+        // a session of an earlier version is migrated, and persisted with the current version
+        let old = tagged(1, &|bytes| {
+            bincode::serialize_into(bytes, &OldSession { count: 5 }).unwrap()
+        });
+        let (val, stored) = request(old);
+        assert_eq!(val, "5");
+
+        let stored = stored.unwrap();
+        assert!(stored.starts_with(&SCHEMA_TAG));
+        let (version, _, _) = bincode::deserialize::<(u32, u64, u64)>(&stored[4..]).unwrap();
+        assert_eq!(version, 2);
+        let value = bincode::deserialize::<TestSession>(&stored[SCHEMA_HEADER_LEN..]).unwrap();
+        assert_eq!(value, TestSession { val: 5 });
+
+        // a session of the current version is loaded as it is
+        let current = tagged(2, &|bytes| {
+            bincode::serialize_into(bytes, &TestSession { val: 3 }).unwrap()
+        });
+        assert_eq!(request(current).0, "3");
+
+        // a session without a version is version 0, which the migration drops
+        let untagged = bincode::serialize(&TestSession { val: 9 }).unwrap();
+        let (val, stored) = request(untagged);
+        assert_eq!(val, "0");
+        assert_eq!(stored, None);
+    }
+
+    #[test]
+    fn cookie_session() {
+        use futures::Stream;
+
+        let backend = CookieBackend::new(b"0123456789abcdef0123456789abcdef").encrypted();
+        let nm = NewSessionMiddleware::new(backend).with_session_type::<TestSession>();
+
+        let handler = |mut state: State| {
+            state.borrow_mut::<SessionData<TestSession>>().val += 1;
+            let val = state.borrow::<SessionData<TestSession>>().val;
+
+            Box::new(future::ok((
+                state,
+                Response::builder()
+                    .status(StatusCode::OK)
+                    .body(Body::from(val.to_string()))
+                    .unwrap(),
+            ))) as Box<HandlerFuture>
+        };
+
+        let request = |cookie: Option<&str>| {
+            let mut state = State::new();
+            let mut headers = HeaderMap::new();
+            if let Some(cookie) = cookie {
+                headers.insert(COOKIE, cookie.parse().unwrap());
+            }
+            state.put(headers);
+
+            let (_, response) = nm
+                .new_middleware()
+                .unwrap()
+                .call(state, handler)
+                .wait()
+                .map_err(|(_, e)| e)
+                .unwrap();
+
+            let mut set_cookies = response.headers().get_all(SET_COOKIE).iter();
+            let set_cookie = set_cookies.next().unwrap().to_str().unwrap().to_owned();
+            assert!(set_cookies.next().is_none());
+
+            let cookie = Cookie::parse(set_cookie).unwrap();
+            let body = response.into_body().concat2().wait().unwrap();
+            (
+                format!("{}={}", cookie.name(), cookie.value()),
+                String::from_utf8(body.to_vec()).unwrap(),
+            )
+        };
+
+        let (cookie, body) = request(None);
+        assert_eq!(body, "1");
+
+        let (cookie, body) = request(Some(&cookie));
+        assert_eq!(body, "2");
+
+        // a session which can't be opened is replaced by a new session
+        let (_, body) = request(Some(&format!("{}x", cookie)));
+        assert_eq!(body, "1");
+
+        // sessions which aren't changed aren't sent again, nor are new sessions which would only
+        // hold the default session
+        let read_only = |state: State| {
+            let val = state.borrow::<SessionData<TestSession>>().val;
+            let res = Response::builder()
+                .status(StatusCode::OK)
+                .body(Body::from(val.to_string()))
+                .unwrap();
+            Box::new(future::ok((state, res))) as Box<HandlerFuture>
+        };
+
+        for cookie in &[None, Some(cookie)] {
+            let mut state = State::new();
+            let mut headers = HeaderMap::new();
+            if let Some(cookie) = cookie {
+                headers.insert(COOKIE, cookie.parse().unwrap());
+            }
+            state.put(headers);
+
+            let (_, response) = nm
+                .new_middleware()
+                .unwrap()
+                .call(state, read_only)
+                .wait()
+                .map_err(|(_, e)| e)
+                .unwrap();
+            assert!(!response.headers().contains_key(SET_COOKIE));
+        }
+    }
 }
@@ -2,7 +2,7 @@
 //!
 //! This module provides generics to enable attaching (appropriate) values to
 //! the state of a request, through the use of `Middleware`. Middleware can
-//! be created via `StateMiddleware::with`, with the provided value being the
+//! be created via `StateMiddleware::new`, with the provided value being the
 //! value to attach to the request state.
 use crate::handler::HandlerFuture;
 use crate::middleware::{Middleware, NewMiddleware};
@@ -18,6 +18,53 @@ use std::panic::RefUnwindSafe;
 ///
 /// The generic types inside this struct can (and will) be cloned
 /// often, so wrap your expensive types in reference counts as needed.
+///
+/// # Examples
+///
+/// ```rust
+/// # extern crate gotham;
+/// # #[macro_use]
+/// # extern crate gotham_derive;
+/// #
+/// # use std::sync::Arc;
+/// # use gotham::middleware::state::StateMiddleware;
+/// # use gotham::pipeline::new_pipeline;
+/// # use gotham::pipeline::single::single_pipeline;
+/// # use gotham::router::builder::*;
+/// # use gotham::state::{FromState, State};
+/// # use gotham::test::TestServer;
+/// #
+/// #[derive(Clone, StateData)]
+/// struct Config {
+///     greeting: Arc<String>,
+/// }
+///
+/// fn handler(state: State) -> (State, String) {
+///     let greeting = Config::borrow_from(&state).greeting.to_string();
+///     (state, greeting)
+/// }
+///
+/// # fn main() {
+/// let config = Config {
+///     greeting: Arc::new("hello".to_owned()),
+/// };
+///
+/// let middleware = StateMiddleware::new(config);
+/// let (chain, pipelines) = single_pipeline(new_pipeline().add(middleware).build());
+/// let router = build_router(chain, pipelines, |route| {
+///     route.get("/").to(handler);
+/// });
+///
+/// let test_server = TestServer::new(router).unwrap();
+/// let response = test_server
+///     .client()
+///     .get("http://localhost/")
+///     .perform()
+///     .unwrap();
+///
+/// assert_eq!(response.read_utf8_body().unwrap(), "hello");
+/// # }
+/// ```
 #[derive(Clone)]
 pub struct StateMiddleware<T>
 where
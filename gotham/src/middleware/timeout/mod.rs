@@ -0,0 +1,216 @@
+//! Timeout middleware, which stops waiting for requests which take too long to serve.
+use std::io;
+use std::panic::RefUnwindSafe;
+use std::sync::Arc;
+use std::time::Duration;
+
+use futures::{future, Future};
+use hyper::StatusCode;
+use log::trace;
+use tokio::timer::Timeout;
+
+use crate::handler::{HandlerFuture, IntoHandlerError};
+use crate::helpers::http::response::create_empty_response;
+use crate::middleware::{Middleware, NewMiddleware};
+use crate::state::{request_id, State};
+
+type TimeoutHook = dyn Fn(&State, Duration) + Send + Sync + RefUnwindSafe;
+
+/// Middleware binding which responds with `503 Service Unavailable` to requests which the rest of
+/// the pipeline chain and the handler don't serve within a time limit. The work of serving the
+/// request is dropped once the limit elapses.
+///
+/// The `State` of a request which timed out is no longer available, so the response is created
+/// from a copy of the request data, such as the method, URI and headers, and the hook given by
+/// `with_hook` is called with the same copy. Middleware earlier in the chain receive that copy in
+/// place of the original `State`.
+///
+/// Unlike `DefineSingleRoute::with_timeout`, which limits the time taken by a single route, the
+/// middleware can be added to the pipelines of a whole router or scope, and the time it measures
+/// includes the middleware which follow it.
+///
+/// # Examples
+///
+/// ```rust
+/// # extern crate gotham;
+/// # extern crate hyper;
+/// #
+/// # use std::time::Duration;
+/// # use hyper::StatusCode;
+/// # use gotham::middleware::timeout::TimeoutMiddleware;
+/// # use gotham::pipeline::new_pipeline;
+/// # use gotham::pipeline::single::single_pipeline;
+/// # use gotham::router::builder::*;
+/// # use gotham::state::{request_id, State};
+/// #
+/// # fn handler(state: State) -> (State, &'static str) {
+/// #     (state, "hello")
+/// # }
+/// #
+/// # fn main() {
+/// let middleware = TimeoutMiddleware::new(Duration::from_secs(30))
+///     .with_status(StatusCode::GATEWAY_TIMEOUT)
+///     .with_hook(|state, timeout| {
+///         eprintln!("[{}] timed out after {:?}", request_id(state), timeout)
+///     });
+///
+/// let (chain, pipelines) = single_pipeline(new_pipeline().add(middleware).build());
+/// let _router = build_router(chain, pipelines, |route| {
+///     route.get("/").to(handler);
+/// });
+/// # }
+/// ```
+#[derive(Clone)]
+pub struct TimeoutMiddleware {
+    timeout: Duration,
+    status: StatusCode,
+    hook: Option<Arc<TimeoutHook>>,
+}
+
+impl TimeoutMiddleware {
+    /// Creates a `TimeoutMiddleware` which allows requests `timeout` to be served.
+    pub fn new(timeout: Duration) -> TimeoutMiddleware {
+        TimeoutMiddleware {
+            timeout,
+            status: StatusCode::SERVICE_UNAVAILABLE,
+            hook: None,
+        }
+    }
+
+    /// Changes the status of the response to requests which time out.
+    pub fn with_status(self, status: StatusCode) -> TimeoutMiddleware {
+        TimeoutMiddleware { status, ..self }
+    }
+
+    /// Calls `hook` with the request data and the time limit whenever a request times out, such
+    /// as for recording metrics.
+    pub fn with_hook<F>(self, hook: F) -> TimeoutMiddleware
+    where
+        F: Fn(&State, Duration) + Send + Sync + RefUnwindSafe + 'static,
+    {
+        TimeoutMiddleware {
+            hook: Some(Arc::new(hook)),
+            ..self
+        }
+    }
+}
+
+/// `Middleware` trait implementation.
+impl Middleware for TimeoutMiddleware {
+    /// Races the rest of the chain against the time limit.
+    fn call<Chain>(self, state: State, chain: Chain) -> Box<HandlerFuture>
+    where
+        Chain: FnOnce(State) -> Box<HandlerFuture>,
+    {
+        let snapshot = state.request_snapshot();
+        let timeout = self.timeout;
+        let status = self.status;
+        let hook = self.hook;
+
+        let f = Timeout::new(chain(state), timeout).or_else(move |e| {
+            if e.is_elapsed() {
+                trace!(
+                    "[{}] request not served within {:?}",
+                    request_id(&snapshot),
+                    timeout
+                );
+                if let Some(hook) = hook {
+                    hook(&snapshot, timeout);
+                }
+                let res = create_empty_response(&snapshot, status);
+                return future::ok((snapshot, res));
+            }
+
+            match e.into_inner() {
+                Some(err) => future::err(err),
+                None => {
+                    trace!("[{}] error running timer", request_id(&snapshot));
+                    let err = io::Error::other("timer unavailable");
+                    future::err((snapshot, err.into_handler_error()))
+                }
+            }
+        });
+
+        Box::new(f)
+    }
+}
+
+/// `NewMiddleware` trait implementation.
+impl NewMiddleware for TimeoutMiddleware {
+    type Instance = Self;
+
+    /// Clones the current middleware to a new instance.
+    fn new_middleware(&self) -> io::Result<Self::Instance> {
+        Ok(self.clone())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    use hyper::Uri;
+    use std::sync::atomic::{AtomicUsize, Ordering};
+    use std::time::Instant;
+    use tokio::timer::Delay;
+
+    use crate::helpers::http::response::create_response;
+    use crate::pipeline::new_pipeline;
+    use crate::pipeline::single::single_pipeline;
+    use crate::router::builder::*;
+    use crate::state::FromState;
+    use crate::test::TestServer;
+
+    fn delayed(state: State) -> Box<HandlerFuture> {
+        let delay = match Uri::borrow_from(&state).path() {
+            "/slow" => Duration::from_secs(10),
+            _ => Duration::from_millis(0),
+        };
+
+        let f = Delay::new(Instant::now() + delay).then(move |result| match result {
+            Ok(()) => {
+                let res = create_response(&state, StatusCode::OK, mime::TEXT_PLAIN, "done");
+                Ok((state, res))
+            }
+            Err(e) => Err((state, e.into_handler_error())),
+        });
+        Box::new(f)
+    }
+
+    #[test]
+    fn times_out_slow_requests() {
+        static TIMEOUTS: AtomicUsize = AtomicUsize::new(0);
+
+        let middleware = TimeoutMiddleware::new(Duration::from_millis(50))
+            .with_status(StatusCode::GATEWAY_TIMEOUT)
+            .with_hook(|state, timeout| {
+                assert_eq!(Uri::borrow_from(state).path(), "/slow");
+                assert_eq!(timeout, Duration::from_millis(50));
+                TIMEOUTS.fetch_add(1, Ordering::SeqCst);
+            });
+
+        let (chain, pipelines) = single_pipeline(new_pipeline().add(middleware).build());
+        let test_server = TestServer::new(build_router(chain, pipelines, |route| {
+            route.get("/fast").to(delayed);
+            route.get("/slow").to(delayed);
+        }))
+        .unwrap();
+
+        let response = test_server
+            .client()
+            .get("http://localhost/fast")
+            .perform()
+            .unwrap();
+        assert_eq!(response.status(), StatusCode::OK);
+        assert_eq!(response.read_utf8_body().unwrap(), "done");
+        assert_eq!(TIMEOUTS.load(Ordering::SeqCst), 0);
+
+        let response = test_server
+            .client()
+            .get("http://localhost/slow")
+            .perform()
+            .unwrap();
+        assert_eq!(response.status(), StatusCode::GATEWAY_TIMEOUT);
+        assert_eq!(TIMEOUTS.load(Ordering::SeqCst), 1);
+    }
+}
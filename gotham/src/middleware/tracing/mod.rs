@@ -0,0 +1,553 @@
+//! Tracing middleware, which takes part in distributed traces by following the B3 propagation
+//! format used by Zipkin and understood by Jaeger.
+use std::io;
+use std::panic::RefUnwindSafe;
+use std::sync::Arc;
+use std::time::{Duration, Instant, SystemTime};
+
+use futures::Future;
+use hyper::header::{HeaderMap, HeaderName, HeaderValue};
+use hyper::{Method, StatusCode, Uri};
+use log::debug;
+
+use crate::handler::HandlerFuture;
+use crate::middleware::{Middleware, NewMiddleware};
+use crate::router::introspection::RouteTemplate;
+use crate::state::{FromState, State, StateData};
+
+const TRACE_ID: &str = "x-b3-traceid";
+const SPAN_ID: &str = "x-b3-spanid";
+const PARENT_SPAN_ID: &str = "x-b3-parentspanid";
+const SAMPLED: &str = "x-b3-sampled";
+const FLAGS: &str = "x-b3-flags";
+const SINGLE: &str = "b3";
+
+/// The identity of the span serving a request, and of the trace it belongs to.
+///
+/// `TracingMiddleware` puts the context of each request's span into `State`, where handlers can
+/// use `inject` to propagate the trace to the services they call.
+#[derive(Clone, Debug, PartialEq)]
+pub struct SpanContext {
+    trace_id: String,
+    span_id: String,
+    parent_id: Option<String>,
+    sampled: Option<bool>,
+}
+
+impl StateData for SpanContext {}
+
+impl SpanContext {
+    /// Creates the context of the first span of a new trace.
+    pub fn new_trace() -> SpanContext {
+        SpanContext {
+            trace_id: format!(
+                "{:016x}{:016x}",
+                rand::random::<u64>(),
+                rand::random::<u64>()
+            ),
+            span_id: new_span_id(),
+            parent_id: None,
+            sampled: Some(true),
+        }
+    }
+
+    /// Creates the context of a span within the same trace, whose parent is this span.
+    pub fn child(&self) -> SpanContext {
+        SpanContext {
+            trace_id: self.trace_id.clone(),
+            span_id: new_span_id(),
+            parent_id: Some(self.span_id.clone()),
+            sampled: self.sampled,
+        }
+    }
+
+    /// Reads the context of the calling span from the B3 headers of a request, either the
+    /// `X-B3-*` headers or the single `b3` header. Returns `None` where the headers are missing or
+    /// invalid.
+    pub fn extract(headers: &HeaderMap) -> Option<SpanContext> {
+        let header = |name| headers.get(name).and_then(|value| value.to_str().ok());
+
+        if let Some(single) = header(SINGLE) {
+            return parse_single(single);
+        }
+
+        let trace_id = header(TRACE_ID).filter(|id| is_trace_id(id))?;
+        let span_id = header(SPAN_ID).filter(|id| is_span_id(id))?;
+        let parent_id = header(PARENT_SPAN_ID).filter(|id| is_span_id(id));
+        let sampled = match header(FLAGS) {
+            Some("1") => Some(true),
+            _ => header(SAMPLED).and_then(parse_sampled),
+        };
+
+        Some(SpanContext {
+            trace_id: trace_id.to_owned(),
+            span_id: span_id.to_owned(),
+            parent_id: parent_id.map(ToOwned::to_owned),
+            sampled,
+        })
+    }
+
+    /// Writes the context into the `X-B3-*` headers, such as those of a request to another
+    /// service, replacing any which are already present.
+    pub fn inject(&self, headers: &mut HeaderMap) {
+        let mut insert = |name, value: &str| {
+            // trace and span IDs are always validated hexadecimal
+            if let Ok(value) = HeaderValue::from_str(value) {
+                headers.insert(HeaderName::from_static(name), value);
+            }
+        };
+
+        insert(TRACE_ID, &self.trace_id);
+        insert(SPAN_ID, &self.span_id);
+        if let Some(ref parent_id) = self.parent_id {
+            insert(PARENT_SPAN_ID, parent_id);
+        }
+        if let Some(sampled) = self.sampled {
+            insert(SAMPLED, if sampled { "1" } else { "0" });
+        }
+    }
+
+    /// The ID of the trace, as 16 or 32 hexadecimal characters.
+    pub fn trace_id(&self) -> &str {
+        &self.trace_id
+    }
+
+    /// The ID of the span, as 16 hexadecimal characters.
+    pub fn span_id(&self) -> &str {
+        &self.span_id
+    }
+
+    /// The ID of the parent span, unless this is the first span of the trace.
+    pub fn parent_id(&self) -> Option<&str> {
+        self.parent_id.as_deref()
+    }
+
+    /// Whether the trace is to be reported, where the caller has decided.
+    pub fn sampled(&self) -> Option<bool> {
+        self.sampled
+    }
+}
+
+fn new_span_id() -> String {
+    format!("{:016x}", rand::random::<u64>())
+}
+
+fn is_hex(id: &str, lengths: &[usize]) -> bool {
+    lengths.contains(&id.len()) && id.bytes().all(|b| b.is_ascii_hexdigit())
+}
+
+fn is_trace_id(id: &str) -> bool {
+    is_hex(id, &[16, 32])
+}
+
+fn is_span_id(id: &str) -> bool {
+    is_hex(id, &[16])
+}
+
+fn parse_sampled(value: &str) -> Option<bool> {
+    match value {
+        "1" | "true" | "d" => Some(true),
+        "0" | "false" => Some(false),
+        _ => None,
+    }
+}
+
+// Parses `{trace_id}-{span_id}-{sampled}-{parent_id}`, where the last two parts are optional. A
+// value of only the sampling decision carries no context to continue.
+fn parse_single(value: &str) -> Option<SpanContext> {
+    let mut parts = value.split('-');
+    let trace_id = parts.next().filter(|id| is_trace_id(id))?;
+    let span_id = parts.next().filter(|id| is_span_id(id))?;
+    let sampled = match parts.next() {
+        Some(sampled) => Some(parse_sampled(sampled)?),
+        None => None,
+    };
+    let parent_id = match parts.next() {
+        Some(parent_id) if is_span_id(parent_id) => Some(parent_id.to_owned()),
+        Some(_) => return None,
+        None => None,
+    };
+
+    if parts.next().is_some() {
+        return None;
+    }
+
+    Some(SpanContext {
+        trace_id: trace_id.to_owned(),
+        span_id: span_id.to_owned(),
+        parent_id,
+        sampled,
+    })
+}
+
+/// A finished span, describing how the application served a request.
+#[derive(Clone, Debug)]
+pub struct Span {
+    context: SpanContext,
+    name: String,
+    start: SystemTime,
+    duration: Duration,
+    status: StatusCode,
+}
+
+impl Span {
+    /// The identity of the span.
+    pub fn context(&self) -> &SpanContext {
+        &self.context
+    }
+
+    /// The name of the span, which is the method and the path template of the route, such as
+    /// `GET /users/:id`, or the method and path of the request where it had no route.
+    pub fn name(&self) -> &str {
+        &self.name
+    }
+
+    /// The time the request was received.
+    pub fn start(&self) -> SystemTime {
+        self.start
+    }
+
+    /// The time taken to produce the response.
+    pub fn duration(&self) -> Duration {
+        self.duration
+    }
+
+    /// The status of the response, or of the `HandlerError` serving the request failed with.
+    pub fn status(&self) -> StatusCode {
+        self.status
+    }
+}
+
+/// Receives the spans finished by `TracingMiddleware`, such as to send them to a Zipkin or
+/// Jaeger collector.
+///
+/// `LogReporter` writes each span to the log. Spans are reported once the response has been
+/// produced, on the thread which served the request, so reporters which send spans elsewhere
+/// should buffer them rather than waiting on the network.
+pub trait SpanReporter: Send + Sync + RefUnwindSafe {
+    /// Reports a finished span. Spans of traces which the caller chose not to sample aren't
+    /// reported.
+    fn report(&self, span: &Span);
+}
+
+impl<R> SpanReporter for Arc<R>
+where
+    R: SpanReporter + ?Sized,
+{
+    fn report(&self, span: &Span) {
+        (**self).report(span)
+    }
+}
+
+/// A `SpanReporter` which writes each span to the log at the `debug` level.
+#[derive(Clone, Copy, Debug, Default)]
+pub struct LogReporter;
+
+impl SpanReporter for LogReporter {
+    fn report(&self, span: &Span) {
+        let context = span.context();
+        debug!(
+            "[trace {}] span {} (parent {}) {} {} in {:?}",
+            context.trace_id(),
+            context.span_id(),
+            context.parent_id().unwrap_or("-"),
+            span.name(),
+            span.status().as_u16(),
+            span.duration()
+        );
+    }
+}
+
+/// Middleware binding which makes each request a span of a distributed trace, following the B3
+/// propagation format.
+///
+/// Where a request carries the `X-B3-*` headers or a `b3` header, the request is served as a child
+/// of the calling span, and otherwise a new trace is started. The `SpanContext` of the request is
+/// put into `State`, and its trace and span IDs are added to the response headers. Once the
+/// response has been produced, the span is given to the `SpanReporter`, which is a `LogReporter`
+/// unless `with_reporter` is used.
+///
+/// # Examples
+///
+/// ```rust
+/// # extern crate gotham;
+/// # extern crate hyper;
+/// #
+/// # use hyper::header::HeaderValue;
+/// # use gotham::middleware::tracing::{SpanContext, TracingMiddleware};
+/// # use gotham::pipeline::new_pipeline;
+/// # use gotham::pipeline::single::single_pipeline;
+/// # use gotham::router::builder::*;
+/// # use gotham::state::{FromState, State};
+/// # use gotham::test::TestServer;
+/// #
+/// fn handler(state: State) -> (State, String) {
+///     let trace_id = SpanContext::borrow_from(&state).trace_id().to_owned();
+///     (state, trace_id)
+/// }
+///
+/// # fn main() {
+/// let (chain, pipelines) = single_pipeline(new_pipeline().add(TracingMiddleware::new()).build());
+/// let router = build_router(chain, pipelines, |route| {
+///     route.get("/").to(handler);
+/// });
+///
+/// let test_server = TestServer::new(router).unwrap();
+/// let response = test_server
+///     .client()
+///     .get("http://localhost/")
+///     .with_header("x-b3-traceid", HeaderValue::from_static("463ac35c9f6413ad"))
+///     .with_header("x-b3-spanid", HeaderValue::from_static("a2fb4a1d1a96d312"))
+///     .perform()
+///     .unwrap();
+///
+/// assert_eq!(response.headers()["x-b3-traceid"], "463ac35c9f6413ad");
+/// assert_eq!(response.read_utf8_body().unwrap(), "463ac35c9f6413ad");
+/// # }
+/// ```
+pub struct TracingMiddleware<R = LogReporter>
+where
+    R: SpanReporter,
+{
+    reporter: Arc<R>,
+}
+
+impl TracingMiddleware<LogReporter> {
+    /// Creates a `TracingMiddleware` which writes spans to the log.
+    pub fn new() -> Self {
+        TracingMiddleware {
+            reporter: Arc::new(LogReporter),
+        }
+    }
+}
+
+impl Default for TracingMiddleware<LogReporter> {
+    fn default() -> Self {
+        TracingMiddleware::new()
+    }
+}
+
+impl<R> TracingMiddleware<R>
+where
+    R: SpanReporter,
+{
+    /// Gives finished spans to `reporter`, in place of a `LogReporter`.
+    pub fn with_reporter<T>(self, reporter: T) -> TracingMiddleware<T>
+    where
+        T: SpanReporter,
+    {
+        TracingMiddleware {
+            reporter: Arc::new(reporter),
+        }
+    }
+}
+
+impl<R> Clone for TracingMiddleware<R>
+where
+    R: SpanReporter,
+{
+    fn clone(&self) -> Self {
+        TracingMiddleware {
+            reporter: self.reporter.clone(),
+        }
+    }
+}
+
+/// `Middleware` trait implementation.
+impl<R> Middleware for TracingMiddleware<R>
+where
+    R: SpanReporter + 'static,
+{
+    /// Starts the span of the request, and reports it once the response has been produced.
+    fn call<Chain>(self, mut state: State, chain: Chain) -> Box<HandlerFuture>
+    where
+        Chain: FnOnce(State) -> Box<HandlerFuture>,
+    {
+        let context = match SpanContext::extract(HeaderMap::borrow_from(&state)) {
+            Some(parent) => parent.child(),
+            None => SpanContext::new_trace(),
+        };
+
+        let method = Method::borrow_from(&state);
+        let name = match state.try_borrow::<RouteTemplate>() {
+            Some(template) => format!("{} {}", method, template),
+            None => format!("{} {}", method, Uri::borrow_from(&state).path()),
+        };
+
+        state.put(context.clone());
+        let reporter = self.reporter;
+        let start = SystemTime::now();
+        let started = Instant::now();
+
+        let f = chain(state).then(move |mut result| {
+            let status = match result {
+                Ok((_, ref mut response)) => {
+                    context.inject(response.headers_mut());
+                    response.status()
+                }
+                Err((_, ref err)) => err.status(),
+            };
+
+            if context.sampled() != Some(false) {
+                reporter.report(&Span {
+                    context,
+                    name,
+                    start,
+                    duration: started.elapsed(),
+                    status,
+                });
+            }
+
+            result
+        });
+
+        Box::new(f)
+    }
+}
+
+/// `NewMiddleware` trait implementation.
+impl<R> NewMiddleware for TracingMiddleware<R>
+where
+    R: SpanReporter + 'static,
+{
+    type Instance = Self;
+
+    /// Clones the current middleware to a new instance.
+    fn new_middleware(&self) -> io::Result<Self::Instance> {
+        Ok(self.clone())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    use std::sync::Mutex;
+
+    use crate::pipeline::new_pipeline;
+    use crate::pipeline::single::single_pipeline;
+    use crate::router::builder::*;
+    use crate::test::TestServer;
+
+    #[derive(Default)]
+    struct Collector {
+        spans: Mutex<Vec<Span>>,
+    }
+
+    impl SpanReporter for Collector {
+        fn report(&self, span: &Span) {
+            self.spans.lock().unwrap().push(span.clone());
+        }
+    }
+
+    fn handler(state: State) -> (State, String) {
+        let context = SpanContext::borrow_from(&state);
+        let body = format!("{} {}", context.trace_id(), context.span_id());
+        (state, body)
+    }
+
+    fn test_server(collector: &Arc<Collector>) -> TestServer {
+        let middleware = TracingMiddleware::new().with_reporter(collector.clone());
+        let (chain, pipelines) = single_pipeline(new_pipeline().add(middleware).build());
+        TestServer::new(build_router(chain, pipelines, |route| {
+            route.get("/users/:id").to(handler);
+        }))
+        .unwrap()
+    }
+
+    #[test]
+    fn extracts_b3_headers() {
+        let mut headers = HeaderMap::new();
+        headers.insert(
+            TRACE_ID,
+            HeaderValue::from_static("463ac35c9f6413ad48485a3953bb6124"),
+        );
+        headers.insert(SPAN_ID, HeaderValue::from_static("a2fb4a1d1a96d312"));
+        headers.insert(PARENT_SPAN_ID, HeaderValue::from_static("0020000000000001"));
+        headers.insert(SAMPLED, HeaderValue::from_static("0"));
+
+        let context = SpanContext::extract(&headers).unwrap();
+        assert_eq!(context.trace_id(), "463ac35c9f6413ad48485a3953bb6124");
+        assert_eq!(context.span_id(), "a2fb4a1d1a96d312");
+        assert_eq!(context.parent_id(), Some("0020000000000001"));
+        assert_eq!(context.sampled(), Some(false));
+
+        headers.insert(FLAGS, HeaderValue::from_static("1"));
+        assert_eq!(
+            SpanContext::extract(&headers).unwrap().sampled(),
+            Some(true)
+        );
+
+        headers.insert(SPAN_ID, HeaderValue::from_static("not-a-span-id"));
+        assert_eq!(SpanContext::extract(&headers), None);
+
+        let mut single = HeaderMap::new();
+        single.insert(
+            SINGLE,
+            HeaderValue::from_static("80f198ee56343ba864fe8b2a57d3eff7-e457b5a2e4d86bd1-1"),
+        );
+        let context = SpanContext::extract(&single).unwrap();
+        assert_eq!(context.trace_id(), "80f198ee56343ba864fe8b2a57d3eff7");
+        assert_eq!(context.span_id(), "e457b5a2e4d86bd1");
+        assert_eq!(context.parent_id(), None);
+        assert_eq!(context.sampled(), Some(true));
+
+        single.insert(SINGLE, HeaderValue::from_static("0"));
+        assert_eq!(SpanContext::extract(&single), None);
+    }
+
+    #[test]
+    fn continues_incoming_traces() {
+        let collector = Arc::new(Collector::default());
+        let test_server = test_server(&collector);
+
+        let response = test_server
+            .client()
+            .get("http://localhost/users/1")
+            .with_header(TRACE_ID, HeaderValue::from_static("463ac35c9f6413ad"))
+            .with_header(SPAN_ID, HeaderValue::from_static("a2fb4a1d1a96d312"))
+            .perform()
+            .unwrap();
+
+        let headers = response.headers().clone();
+        let body = response.read_utf8_body().unwrap();
+        let span_id = headers[SPAN_ID].to_str().unwrap().to_owned();
+
+        assert_eq!(headers[TRACE_ID], "463ac35c9f6413ad");
+        assert_eq!(headers[PARENT_SPAN_ID], "a2fb4a1d1a96d312");
+        assert_ne!(span_id, "a2fb4a1d1a96d312");
+        assert_eq!(body, format!("463ac35c9f6413ad {}", span_id));
+
+        let spans = collector.spans.lock().unwrap();
+        assert_eq!(spans.len(), 1);
+        assert_eq!(spans[0].name(), "GET /users/:id");
+        assert_eq!(spans[0].status(), StatusCode::OK);
+        assert_eq!(spans[0].context().span_id(), span_id);
+        assert_eq!(spans[0].context().parent_id(), Some("a2fb4a1d1a96d312"));
+    }
+
+    #[test]
+    fn starts_new_traces_and_skips_unsampled_ones() {
+        let collector = Arc::new(Collector::default());
+        let test_server = test_server(&collector);
+        let client = test_server.client();
+
+        let response = client.get("http://localhost/users/1").perform().unwrap();
+        let trace_id = response.headers()[TRACE_ID].to_str().unwrap();
+        assert!(is_trace_id(trace_id) && trace_id.len() == 32);
+        assert!(response.headers().get(PARENT_SPAN_ID).is_none());
+        assert_eq!(response.headers()[SAMPLED], "1");
+
+        let response = client
+            .get("http://localhost/users/1")
+            .with_header(
+                SINGLE,
+                HeaderValue::from_static("463ac35c9f6413ad-a2fb4a1d1a96d312-0"),
+            )
+            .perform()
+            .unwrap();
+        assert_eq!(response.headers()[SAMPLED], "0");
+
+        assert_eq!(collector.spans.lock().unwrap().len(), 1);
+    }
+}
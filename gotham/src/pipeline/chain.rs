@@ -31,6 +31,12 @@ pub trait PipelineHandleChain<P>: RefUnwindSafe {
     fn call<F>(&self, pipelines: &PipelineSet<P>, state: State, f: F) -> Box<HandlerFuture>
     where
         F: FnOnce(State) -> Box<HandlerFuture> + Send + 'static;
+
+    /// Provides the type names of the `NewMiddleware` values in each `Pipeline` of the chain, in
+    /// the order they're invoked, as reported by `Router::routes`.
+    fn pipeline_names(&self) -> Vec<Vec<&'static str>> {
+        vec![]
+    }
 }
 
 /// Part of a `PipelineHandleChain` which references a `Pipeline` and continues with a tail element.
@@ -55,6 +61,14 @@ where
             }
         }
     }
+
+    fn pipeline_names(&self) -> Vec<Vec<&'static str>> {
+        // The list is reversed, so the tail is invoked first.
+        let (_, ref chain) = *self;
+        let mut names = chain.pipeline_names();
+        names.push(Pipeline::<T>::middleware_names());
+        names
+    }
 }
 
 /// The marker for the end of a `PipelineHandleChain`.
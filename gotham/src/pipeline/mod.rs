@@ -144,6 +144,14 @@ where
             chain: self.chain.construct()?,
         })
     }
+
+    /// Provides the type names of the `NewMiddleware` values in this `Pipeline`, in the order
+    /// they're invoked.
+    pub(crate) fn middleware_names() -> Vec<&'static str> {
+        let mut names = vec![];
+        T::middleware_names(&mut names);
+        names
+    }
 }
 
 impl<T> PipelineInstance<T>
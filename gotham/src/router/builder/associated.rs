@@ -7,6 +7,8 @@ use crate::extractor::{PathExtractor, QueryStringExtractor};
 use crate::pipeline::chain::PipelineHandleChain;
 use crate::pipeline::set::PipelineSet;
 use crate::router::builder::SingleRouteBuilder;
+use crate::router::response::finalizer::ResponseExtenders;
+use crate::router::route::dispatch::RouteExtensions;
 use crate::router::route::matcher::{
     AndRouteMatcher, AnyRouteMatcher, MethodOnlyRouteMatcher, RouteMatcher,
 };
@@ -331,6 +333,10 @@ where
             pipeline_chain: *pipeline_chain,
             pipelines: pipelines.clone(),
             phantom,
+            timeout: None,
+            body_limit: None,
+            extensions: RouteExtensions::default(),
+            response_extenders: ResponseExtenders::default(),
         }
     }
 
@@ -5,18 +5,22 @@ use hyper::Method;
 use log::trace;
 
 use crate::extractor::{NoopPathExtractor, NoopQueryStringExtractor};
-use crate::helpers::http::request::path::split_path_segments;
+use crate::helpers::http::request::path::{has_trailing_slash, split_path_segments};
 use crate::pipeline::chain::PipelineHandleChain;
 use crate::pipeline::set::PipelineSet;
 use crate::router::builder::{
     AssociatedRouteBuilder, DelegateRouteBuilder, RouterBuilder, ScopeBuilder, SingleRouteBuilder,
 };
+use crate::router::response::finalizer::ResponseExtenders;
+use crate::router::route::dispatch::RouteExtensions;
 use crate::router::route::matcher::{
     AnyRouteMatcher, IntoRouteMatcher, MethodOnlyRouteMatcher, RouteMatcher,
 };
 use crate::router::tree::node::Node;
+use crate::router::tree::predicate::SegmentPredicate;
 use crate::router::tree::regex::ConstrainedSegmentRegex;
 use crate::router::tree::segment::SegmentType;
+use crate::router::{Router, SchemePolicy};
 
 /// The type returned when building a route that only considers path and http verb(s) when
 /// determining if it matches a request.
@@ -356,11 +360,121 @@ where
         self.request(vec![Method::OPTIONS], path)
     }
 
+    /// Creates a route which matches requests to the given path made with any of the standard
+    /// HTTP methods, such as for a proxy or a catch-all logging handler.
+    ///
+    /// # Examples
+    ///
+    /// ```rust
+    /// # extern crate gotham;
+    /// # extern crate hyper;
+    /// #
+    /// # use hyper::{Body, Response, StatusCode};
+    /// # use gotham::state::State;
+    /// # use gotham::router::Router;
+    /// # use gotham::router::builder::*;
+    /// # use gotham::test::TestServer;
+    /// #
+    /// # fn my_handler(state: State) -> (State, Response<Body>) {
+    /// #   (state, Response::builder().status(StatusCode::ACCEPTED).body(Body::empty()).unwrap())
+    /// # }
+    /// #
+    /// # fn router() -> Router {
+    /// build_simple_router(|route| {
+    ///     route.any("/request/path").to(my_handler);
+    /// })
+    /// # }
+    /// #
+    /// # fn main() {
+    /// #   let test_server = TestServer::new(router()).unwrap();
+    /// #
+    /// #   let response = test_server.client()
+    /// #       .get("https://example.com/request/path")
+    /// #       .perform()
+    /// #       .unwrap();
+    /// #   assert_eq!(response.status(), StatusCode::ACCEPTED);
+    /// #
+    /// #   let response = test_server.client()
+    /// #       .delete("https://example.com/request/path")
+    /// #       .perform()
+    /// #       .unwrap();
+    /// #   assert_eq!(response.status(), StatusCode::ACCEPTED);
+    /// # }
+    /// ```
+    fn any<'b>(&'b mut self, path: &str) -> DefaultSingleRouteBuilder<'b, C, P> {
+        self.request(standard_methods(), path)
+    }
+
+    /// Creates a route which matches requests to the given path made with any of the standard
+    /// HTTP methods, other than those in `excluded`. Requests made with an excluded method
+    /// receive a `405 Method Not Allowed` response, unless matched by another route.
+    ///
+    /// # Examples
+    ///
+    /// ```rust
+    /// # extern crate gotham;
+    /// # extern crate hyper;
+    /// # extern crate mime;
+    /// #
+    /// # use hyper::{Body, Method, Response, StatusCode};
+    /// # use gotham::state::State;
+    /// # use gotham::router::Router;
+    /// # use gotham::router::builder::*;
+    /// # use gotham::test::TestServer;
+    /// #
+    /// # fn my_handler(state: State) -> (State, Response<Body>) {
+    /// #   (state, Response::builder().status(StatusCode::ACCEPTED).body(Body::empty()).unwrap())
+    /// # }
+    /// #
+    /// # fn router() -> Router {
+    /// build_simple_router(|route| {
+    ///     route
+    ///         .any_except(vec![Method::DELETE], "/request/path")
+    ///         .to(my_handler);
+    /// })
+    /// # }
+    /// #
+    /// # fn main() {
+    /// #   let test_server = TestServer::new(router()).unwrap();
+    /// #
+    /// #   let response = test_server.client()
+    /// #       .post("https://example.com/request/path", b"".to_vec(), mime::TEXT_PLAIN)
+    /// #       .perform()
+    /// #       .unwrap();
+    /// #   assert_eq!(response.status(), StatusCode::ACCEPTED);
+    /// #
+    /// #   let response = test_server.client()
+    /// #       .delete("https://example.com/request/path")
+    /// #       .perform()
+    /// #       .unwrap();
+    /// #   assert_eq!(response.status(), StatusCode::METHOD_NOT_ALLOWED);
+    /// # }
+    /// ```
+    fn any_except<'b>(
+        &'b mut self,
+        excluded: Vec<Method>,
+        path: &str,
+    ) -> DefaultSingleRouteBuilder<'b, C, P> {
+        let methods = standard_methods()
+            .into_iter()
+            .filter(|method| !excluded.contains(method))
+            .collect::<Vec<_>>();
+
+        self.request(methods, path)
+    }
+
     /// Creates a single route which matches any requests to the given `path` with one of the
     /// given `methods`. The `path` can consist of static or dynamic segments, for example:
     ///
     /// * `"/hello/world"` - a static path, matching only a request for exactly `"/hello/world"`
     /// * `"/hello/:name"` - a dynamic path, matching requests for `"/hello/any_value_here"`
+    /// * `"/posts/:id:[0-9]+"` - a constrained dynamic path, matching requests for `"/posts/123"`
+    ///   but not `"/posts/about-us"`
+    /// * `"/users/:id(u32)"` - a typed dynamic path, matching requests for `"/users/123"` but not
+    ///   `"/users/new"`. Integer and float types, `bool` and `uuid` are supported.
+    /// * `"/archive/*/manifest.json"` - a glob path, matching requests for
+    ///   `"/archive/2019/06/manifest.json"`. The glob captures one or more segments under the
+    ///   `"*"` name, as read by `FilePathExtractor`.
     ///
     /// # Examples
     ///
@@ -453,6 +567,40 @@ where
     /// #   assert_eq!(response.status(), StatusCode::ACCEPTED);
     /// # }
     /// ```
+    ///
+    /// Extension methods, such as those used by WebDAV, are matched in the same way as the
+    /// standard methods:
+    ///
+    /// ```rust
+    /// # extern crate gotham;
+    /// # extern crate hyper;
+    /// #
+    /// # use hyper::{Body, Method, Response, StatusCode};
+    /// # use gotham::state::State;
+    /// # use gotham::router::Router;
+    /// # use gotham::router::builder::*;
+    /// # use gotham::test::TestServer;
+    /// #
+    /// # fn my_handler(state: State) -> (State, Response<Body>) {
+    /// #   (state, Response::builder().status(StatusCode::ACCEPTED).body(Body::empty()).unwrap())
+    /// # }
+    /// #
+    /// # fn router() -> Router {
+    /// build_simple_router(|route| {
+    ///     let propfind = Method::from_bytes(b"PROPFIND").unwrap();
+    ///     route.request(vec![propfind], "/request/path").to(my_handler);
+    /// })
+    /// # }
+    /// #
+    /// # fn main() {
+    /// #   let test_server = TestServer::new(router()).unwrap();
+    /// #   let response = test_server.client()
+    /// #       .build_request(Method::from_bytes(b"PROPFIND").unwrap(), "https://example.com/request/path")
+    /// #       .perform()
+    /// #       .unwrap();
+    /// #   assert_eq!(response.status(), StatusCode::ACCEPTED);
+    /// # }
+    /// ```
     fn request<'b, IRM, M>(
         &'b mut self,
         matcher: IRM,
@@ -464,6 +612,7 @@ where
     {
         let (node_builder, pipeline_chain, pipelines) = self.component_refs();
         let node_builder = descend(node_builder, path);
+        node_builder.add_trailing_slash(has_trailing_slash(path));
         let matcher = matcher.into_route_matcher();
 
         SingleRouteBuilder {
@@ -472,6 +621,10 @@ where
             pipeline_chain: *pipeline_chain,
             pipelines: pipelines.clone(),
             phantom: PhantomData,
+            timeout: None,
+            body_limit: None,
+            extensions: RouteExtensions::default(),
+            response_extenders: ResponseExtenders::default(),
         }
     }
 
@@ -530,6 +683,153 @@ where
         f(&mut scope_builder)
     }
 
+    /// Begins defining a new scope, based on a given `path` prefix, where requests must be made
+    /// with the scheme required by the `SchemePolicy`. The policy applies to every route at or
+    /// beneath the `path`, including those defined outside of the scope, and an inner scope may
+    /// replace it.
+    ///
+    /// # Examples
+    ///
+    /// ```rust
+    /// # extern crate gotham;
+    /// # extern crate hyper;
+    ///
+    /// # use hyper::{Body, Response, StatusCode};
+    /// # use hyper::header::LOCATION;
+    /// # use gotham::state::State;
+    /// # use gotham::router::{Router, SchemePolicy};
+    /// # use gotham::router::builder::*;
+    /// # use gotham::test::TestServer;
+    /// #
+    /// # fn login(state: State) -> (State, Response<Body>) {
+    /// #   (state, Response::builder().status(StatusCode::ACCEPTED).body(Body::empty()).unwrap())
+    /// # }
+    /// #
+    /// # fn router() -> Router {
+    /// build_simple_router(|route| {
+    ///     let policy = SchemePolicy::RedirectToHttps(StatusCode::PERMANENT_REDIRECT);
+    ///
+    ///     route.scheme_scope("/account", policy, |route| {
+    ///         // Match requests to `/account/login` made over HTTPS
+    ///         route.post("/login").to(login);
+    ///     });
+    /// })
+    /// # }
+    /// #
+    /// # fn main() {
+    /// #   let test_server = TestServer::new(router()).unwrap();
+    /// #   let response = test_server.client()
+    /// #       .post("http://example.com/account/login?next=%2F", "", mime::TEXT_PLAIN)
+    /// #       .perform()
+    /// #       .unwrap();
+    /// #   assert_eq!(response.status(), StatusCode::PERMANENT_REDIRECT);
+    /// #   assert_eq!(
+    /// #       response.headers()[LOCATION],
+    /// #       "https://example.com/account/login?next=%2F"
+    /// #   );
+    /// # }
+    /// ```
+    fn scheme_scope<F>(&mut self, path: &str, policy: SchemePolicy, f: F)
+    where
+        F: FnOnce(&mut ScopeBuilder<C, P>),
+    {
+        let (node_builder, pipeline_chain, pipelines) = self.component_refs();
+        let node_builder = descend(node_builder, path);
+        node_builder.set_scheme_policy(policy);
+
+        let mut scope_builder = ScopeBuilder {
+            node_builder,
+            pipeline_chain: *pipeline_chain,
+            pipelines: pipelines.clone(),
+        };
+
+        f(&mut scope_builder)
+    }
+
+    /// Begins a new scope beneath a dynamic segment named `name`, which only matches segments of
+    /// the request path accepted by `predicate`. Where the predicate rejects a segment, other
+    /// routes at the same location are considered, so requests can be dispatched to different
+    /// handlers based on the form of a segment. For matching against a regular expression, use a
+    /// `:name:regex` segment in the path instead.
+    ///
+    /// The value of the segment is available to path extractors under `name`.
+    ///
+    /// # Examples
+    ///
+    /// ```rust
+    /// # extern crate gotham;
+    /// # extern crate hyper;
+    /// #
+    /// # use hyper::{Body, Response, StatusCode};
+    /// # use gotham::state::State;
+    /// # use gotham::router::Router;
+    /// # use gotham::router::builder::*;
+    /// # use gotham::test::TestServer;
+    /// #
+    /// # fn show_post(state: State) -> (State, Response<Body>) {
+    /// #   (state, Response::builder().status(StatusCode::ACCEPTED).body(Body::empty()).unwrap())
+    /// # }
+    /// #
+    /// # fn show_page(state: State) -> (State, Response<Body>) {
+    /// #   (state, Response::builder().status(StatusCode::OK).body(Body::empty()).unwrap())
+    /// # }
+    /// #
+    /// fn is_numeric(segment: &str) -> bool {
+    ///     segment.bytes().all(|b| b.is_ascii_digit())
+    /// }
+    ///
+    /// # fn router() -> Router {
+    /// build_simple_router(|route| {
+    ///     route.scope("/posts", |route| {
+    ///         // Matches requests for "/posts/123".
+    ///         route.constrained_segment("id", is_numeric, |route| {
+    ///             route.get("/").to(show_post);
+    ///         });
+    ///
+    ///         // Matches requests for "/posts/about-us".
+    ///         route.get("/:slug").to(show_page);
+    ///     });
+    /// })
+    /// # }
+    /// #
+    /// # fn main() {
+    /// #   let test_server = TestServer::new(router()).unwrap();
+    /// #   let response = test_server.client()
+    /// #       .get("https://example.com/posts/123")
+    /// #       .perform()
+    /// #       .unwrap();
+    /// #   assert_eq!(response.status(), StatusCode::ACCEPTED);
+    /// #
+    /// #   let response = test_server.client()
+    /// #       .get("https://example.com/posts/about-us")
+    /// #       .perform()
+    /// #       .unwrap();
+    /// #   assert_eq!(response.status(), StatusCode::OK);
+    /// # }
+    /// ```
+    fn constrained_segment<F>(&mut self, name: &str, predicate: fn(&str) -> bool, f: F)
+    where
+        F: FnOnce(&mut ScopeBuilder<C, P>),
+    {
+        let (node_builder, pipeline_chain, pipelines) = self.component_refs();
+
+        let segment_type = SegmentType::Predicate {
+            predicate: SegmentPredicate::new(predicate),
+        };
+        if !node_builder.has_child(name, segment_type.clone()) {
+            node_builder.add_child(Node::new(name, segment_type.clone()));
+        }
+        let node_builder = node_builder.borrow_child_mut(name, segment_type).unwrap();
+
+        let mut scope_builder = ScopeBuilder {
+            node_builder,
+            pipeline_chain: *pipeline_chain,
+            pipelines: pipelines.clone(),
+        };
+
+        f(&mut scope_builder)
+    }
+
     /// Begins a new scope at the current location, with an alternate pipeline chain.
     ///
     /// # Examples
@@ -647,7 +947,92 @@ where
         f(&mut scope_builder)
     }
 
-    /// Begins delegating a subpath of the tree.
+    /// Begins a new scope at the current location, which appends a pipeline to the current
+    /// pipeline chain. Middleware in the pipeline runs after that of the enclosing router or
+    /// scope, for only the routes defined in the new scope.
+    ///
+    /// # Examples
+    ///
+    /// ```rust
+    /// # extern crate gotham;
+    /// # extern crate hyper;
+    /// # #[macro_use]
+    /// # extern crate serde_derive;
+    /// #
+    /// # use hyper::{Body, Response, StatusCode};
+    /// # use gotham::state::State;
+    /// # use gotham::middleware::session::{NewSessionMiddleware, SessionData};
+    /// # use gotham::router::Router;
+    /// # use gotham::router::builder::*;
+    /// # use gotham::pipeline::new_pipeline;
+    /// # use gotham::pipeline::set::{finalize_pipeline_set, new_pipeline_set};
+    /// # use gotham::test::TestServer;
+    /// #
+    /// # #[derive(Default, Serialize, Deserialize)]
+    /// # struct Session;
+    /// #
+    /// # #[derive(Default, Serialize, Deserialize)]
+    /// # struct AdminSession;
+    /// #
+    /// # mod admin {
+    /// #   use super::*;
+    /// #   pub fn handler(state: State) -> (State, Response<Body>) {
+    /// #       assert!(state.has::<SessionData<Session>>());
+    /// #       assert!(state.has::<SessionData<AdminSession>>());
+    /// #       (state, Response::builder().status(StatusCode::ACCEPTED).body(Body::empty()).unwrap())
+    /// #   }
+    /// # }
+    /// #
+    /// # fn router() -> Router {
+    /// let pipelines = new_pipeline_set();
+    /// let (pipelines, default) = pipelines.add(
+    ///     new_pipeline()
+    ///         .add(NewSessionMiddleware::default().with_session_type::<Session>())
+    ///         .build()
+    /// );
+    /// let (pipelines, admin) = pipelines.add(
+    ///     new_pipeline()
+    ///         .add(NewSessionMiddleware::default().with_session_type::<AdminSession>())
+    ///         .build()
+    /// );
+    /// let pipeline_set = finalize_pipeline_set(pipelines);
+    ///
+    /// build_router((default, ()), pipeline_set, |route| {
+    ///     // Requests for the admin routes invoke both session middlewares.
+    ///     route.with_pipeline(admin, |route| {
+    ///         route.get("/admin").to(admin::handler);
+    ///     });
+    /// })
+    /// # }
+    /// #
+    /// # fn main() {
+    /// #   let test_server = TestServer::new(router()).unwrap();
+    /// #   let response = test_server.client()
+    /// #       .get("https://example.com/admin")
+    /// #       .perform()
+    /// #       .unwrap();
+    /// #   assert_eq!(response.status(), StatusCode::ACCEPTED);
+    /// # }
+    /// ```
+    fn with_pipeline<F, H>(&mut self, handle: H, f: F)
+    where
+        F: FnOnce(&mut ScopeBuilder<(H, C), P>),
+        (H, C): PipelineHandleChain<P> + Copy + Send + Sync + 'static,
+    {
+        let (node_builder, pipeline_chain, pipelines) = self.component_refs();
+
+        let mut scope_builder = ScopeBuilder {
+            node_builder,
+            pipeline_chain: (handle, *pipeline_chain),
+            pipelines: pipelines.clone(),
+        };
+
+        f(&mut scope_builder)
+    }
+
+    /// Begins delegating a subpath of the tree. The remainder of the request path beneath `path`
+    /// is routed by the delegated `Router`, so a trailing `/*` glob is implied, and may be given
+    /// explicitly.
     ///
     /// # Examples
     ///
@@ -689,7 +1074,7 @@ where
     /// ```
     fn delegate<'b>(&'b mut self, path: &str) -> DelegateRouteBuilder<'b, C, P> {
         let (node_builder, pipeline_chain, pipelines) = self.component_refs();
-        let node_builder = descend(node_builder, path);
+        let node_builder = descend(node_builder, delegated_path(path));
 
         DelegateRouteBuilder {
             node_builder,
@@ -771,7 +1156,7 @@ where
     /// ```
     fn delegate_without_pipelines<'b>(&'b mut self, path: &str) -> DelegateRouteBuilder<'b, (), P> {
         let (node_builder, _pipeline_chain, pipelines) = self.component_refs();
-        let node_builder = descend(node_builder, path);
+        let node_builder = descend(node_builder, delegated_path(path));
 
         DelegateRouteBuilder {
             node_builder,
@@ -780,6 +1165,92 @@ where
         }
     }
 
+    /// Mounts the routes of an existing `Router` beneath the given `path` prefix, so that routers
+    /// built independently, such as by separate modules or crates, can be composed into a single
+    /// tree.
+    ///
+    /// Unlike `delegate`, the routes become part of this router. A request to `/api/users/1` is
+    /// matched against a mounted route for `/users/:id`, with any dynamic segments of the prefix
+    /// also available to its path extractor. Named routes can be used with `UrlFor`, and generate
+    /// paths which include the prefix.
+    ///
+    /// The mounted routes keep the pipelines they were defined with, and the pipelines of this
+    /// scope aren't applied to them. The response extenders and other options of the mounted
+    /// `Router`, and any routes defined through `RouterBuilder::host`, aren't carried over.
+    ///
+    /// # Panics
+    ///
+    /// When the `Router` has been cloned.
+    ///
+    /// # Examples
+    ///
+    /// ```rust
+    /// # extern crate gotham;
+    /// # extern crate hyper;
+    /// # #[macro_use]
+    /// # extern crate serde_derive;
+    /// #
+    /// # use hyper::{Body, Response, StatusCode};
+    /// # use gotham::router::Router;
+    /// # use gotham::router::builder::*;
+    /// # use gotham::router::response::extender::StaticResponseExtender;
+    /// # use gotham::state::{FromState, State, StateData};
+    /// # use gotham::test::TestServer;
+    /// #
+    /// #[derive(Deserialize)]
+    /// struct UserPath {
+    ///     tenant: String,
+    ///     id: u32,
+    /// }
+    /// #
+    /// # impl StateData for UserPath {}
+    /// #
+    /// # impl StaticResponseExtender for UserPath {
+    /// #     type ResBody = Body;
+    /// #     fn extend(_: &mut State, _: &mut Response<Body>) {}
+    /// # }
+    ///
+    /// fn user(state: State) -> (State, String) {
+    ///     let body = {
+    ///         let path = UserPath::borrow_from(&state);
+    ///         format!("{}: user {}", path.tenant, path.id)
+    ///     };
+    ///     (state, body)
+    /// }
+    ///
+    /// // Built elsewhere, without knowledge of where it's mounted.
+    /// fn users_router() -> Router {
+    ///     build_simple_router(|route| {
+    ///         route
+    ///             .get("/users/:id")
+    ///             .with_path_extractor::<UserPath>()
+    ///             .to(user);
+    ///     })
+    /// }
+    ///
+    /// fn router() -> Router {
+    ///     build_simple_router(|route| {
+    ///         route.mount("/tenants/:tenant", users_router());
+    ///     })
+    /// }
+    /// #
+    /// # fn main() {
+    /// #   let test_server = TestServer::new(router()).unwrap();
+    /// #   let response = test_server.client()
+    /// #       .get("https://example.com/tenants/acme/users/7")
+    /// #       .perform()
+    /// #       .unwrap();
+    /// #   assert_eq!(response.status(), StatusCode::OK);
+    /// #   assert_eq!(response.read_utf8_body().unwrap(), "acme: user 7");
+    /// # }
+    /// ```
+    fn mount(&mut self, path: &str, router: Router) {
+        let (node_builder, _pipeline_chain, _pipelines) = self.component_refs();
+        let node_builder = descend(node_builder, path);
+
+        node_builder.merge(router.into_tree().into_root());
+    }
+
     /// Begins associating routes with a fixed path in the tree. In this way, multiple routes can
     /// be quickly associated with a single location.
     ///
@@ -852,6 +1323,7 @@ where
     {
         let (node_builder, pipeline_chain, pipelines) = self.component_refs();
         let node_builder = descend(node_builder, path);
+        node_builder.add_trailing_slash(has_trailing_slash(path));
 
         let mut builder =
             AssociatedRouteBuilder::new(node_builder, *pipeline_chain, pipelines.clone());
@@ -864,6 +1336,30 @@ where
     fn component_refs(&mut self) -> (&mut Node, &mut C, &PipelineSet<P>);
 }
 
+/// The methods matched by `DrawRoutes::any`.
+fn standard_methods() -> Vec<Method> {
+    vec![
+        Method::GET,
+        Method::HEAD,
+        Method::POST,
+        Method::PUT,
+        Method::PATCH,
+        Method::DELETE,
+        Method::OPTIONS,
+        Method::CONNECT,
+        Method::TRACE,
+    ]
+}
+
+/// The path a delegated route is added at, without the trailing glob which delegation implies.
+fn delegated_path(path: &str) -> &str {
+    match path.strip_suffix("/*") {
+        Some("") => "/",
+        Some(path) => path,
+        None => path,
+    }
+}
+
 fn descend<'n>(node_builder: &'n mut Node, path: &str) -> &'n mut Node {
     trace!("[walking to: {}]", path);
 
@@ -891,13 +1387,19 @@ where
             let (segment, segment_type) = match segment.chars().next() {
                 Some(':') => {
                     let segment = &segment[1..];
-                    match segment.find(':') {
-                        Some(n) => {
+                    match (segment.find(':'), segment.find('(')) {
+                        (Some(n), _) => {
                             let (segment, pattern) = segment.split_at(n);
                             let regex = ConstrainedSegmentRegex::new(&pattern[1..]);
                             (segment, SegmentType::Constrained { regex })
                         }
-                        None => (segment, SegmentType::Dynamic),
+                        (None, Some(n)) if segment.ends_with(')') => {
+                            let (segment, ty) = segment.split_at(n);
+                            let pattern = typed_segment_pattern(&ty[1..ty.len() - 1]);
+                            let regex = ConstrainedSegmentRegex::new(pattern);
+                            (segment, SegmentType::Constrained { regex })
+                        }
+                        _ => (segment, SegmentType::Dynamic),
                     }
                 }
                 Some('*') if segment.len() == 1 => (segment, SegmentType::Glob),
@@ -919,6 +1421,19 @@ where
     }
 }
 
+/// Determines the pattern matched by a typed segment, such as `:id(u32)`, which accepts the values
+/// that could be parsed as the type.
+fn typed_segment_pattern(ty: &str) -> &'static str {
+    match ty {
+        "u8" | "u16" | "u32" | "u64" | "u128" | "usize" => r"\+?[0-9]+",
+        "i8" | "i16" | "i32" | "i64" | "i128" | "isize" => r"[+-]?[0-9]+",
+        "f32" | "f64" => r"[+-]?([0-9]+(\.[0-9]*)?|\.[0-9]+)([eE][+-]?[0-9]+)?",
+        "bool" => "true|false",
+        "uuid" | "Uuid" => "[0-9a-fA-F]{8}-?([0-9a-fA-F]{4}-?){3}[0-9a-fA-F]{12}",
+        _ => panic!("unsupported type for path segment: {}", ty),
+    }
+}
+
 impl<'a, C, P> DrawRoutes<C, P> for RouterBuilder<'a, C, P>
 where
     C: PipelineHandleChain<P> + Copy + Send + Sync + 'static,
@@ -952,8 +1467,9 @@ mod tests {
     use std::io;
 
     use futures::future;
-    use hyper::{Body, Response, StatusCode};
+    use hyper::{Body, Method, Response, StatusCode};
 
+    use super::standard_methods;
     use crate::handler::HandlerFuture;
     use crate::helpers::http::response::create_empty_response;
     use crate::middleware::{Middleware, NewMiddleware};
@@ -1040,4 +1556,186 @@ mod tests {
 
         assert_eq!(response.status(), StatusCode::ACCEPTED);
     }
+
+    #[test]
+    fn with_pipeline_applies_to_route_only() {
+        let (pipelines, quick_exit) =
+            set::new_pipeline_set().add(new_pipeline().add(QuickExitMiddleware).build());
+        let pipelines = set::finalize_pipeline_set(pipelines);
+
+        let router = build_router((), pipelines, |route| {
+            route
+                .get("/admin")
+                .with_pipeline(quick_exit)
+                .to(test_handler);
+            route.get("/").to(test_handler);
+        });
+
+        let test_server = TestServer::new(router).unwrap();
+        let client = test_server.client();
+
+        let response = client.get("http://localhost/admin").perform().unwrap();
+        assert_eq!(response.status(), StatusCode::INTERNAL_SERVER_ERROR);
+
+        let response = client.get("http://localhost/").perform().unwrap();
+        assert_eq!(response.status(), StatusCode::ACCEPTED);
+    }
+
+    #[test]
+    fn with_pipeline_applies_to_scope_only() {
+        let (pipelines, quick_exit) =
+            set::new_pipeline_set().add(new_pipeline().add(QuickExitMiddleware).build());
+        let pipelines = set::finalize_pipeline_set(pipelines);
+
+        let router = build_router((), pipelines, |route| {
+            route.with_pipeline(quick_exit, |route| {
+                route.get("/admin").to(test_handler);
+            });
+            route.get("/").to(test_handler);
+        });
+
+        let test_server = TestServer::new(router).unwrap();
+        let client = test_server.client();
+
+        let response = client.get("http://localhost/admin").perform().unwrap();
+        assert_eq!(response.status(), StatusCode::INTERNAL_SERVER_ERROR);
+
+        let response = client.get("http://localhost/").perform().unwrap();
+        assert_eq!(response.status(), StatusCode::ACCEPTED);
+    }
+
+    #[test]
+    fn any_matches_all_standard_methods() {
+        let router = build_simple_router(|route| {
+            route.any("/any").to(test_handler);
+            route
+                .any_except(vec![Method::DELETE], "/except")
+                .to(test_handler);
+        });
+
+        let test_server = TestServer::new(router).unwrap();
+        let client = test_server.client();
+
+        // CONNECT requests target an authority rather than a path, so can't be routed here.
+        for method in standard_methods()
+            .into_iter()
+            .filter(|m| m != Method::CONNECT)
+        {
+            let response = client
+                .build_request(method.clone(), "http://localhost/any")
+                .perform()
+                .unwrap();
+            assert_eq!(response.status(), StatusCode::ACCEPTED, "{}", method);
+
+            let expected = if method == Method::DELETE {
+                StatusCode::METHOD_NOT_ALLOWED
+            } else {
+                StatusCode::ACCEPTED
+            };
+            let response = client
+                .build_request(method.clone(), "http://localhost/except")
+                .perform()
+                .unwrap();
+            assert_eq!(response.status(), expected, "{}", method);
+        }
+    }
+
+    #[test]
+    fn request_matches_extension_methods() {
+        let propfind = Method::from_bytes(b"PROPFIND").unwrap();
+
+        let router = build_simple_router(|route| {
+            route
+                .request(vec![propfind.clone()], "/dav")
+                .to(test_handler);
+        });
+
+        let test_server = TestServer::new(router).unwrap();
+        let client = test_server.client();
+
+        let response = client
+            .build_request(propfind, "http://localhost/dav")
+            .perform()
+            .unwrap();
+        assert_eq!(response.status(), StatusCode::ACCEPTED);
+
+        let response = client.get("http://localhost/dav").perform().unwrap();
+        assert_eq!(response.status(), StatusCode::METHOD_NOT_ALLOWED);
+        assert_eq!(response.headers()[hyper::header::ALLOW], "PROPFIND");
+    }
+
+    #[test]
+    fn delegate_implies_trailing_glob() {
+        let router = build_simple_router(|route| {
+            route.delegate("/test/*").to_router(build_simple_router(|route| {
+                route.get("/inner").to(test_handler);
+            }));
+        });
+
+        let test_server = TestServer::new(router).unwrap();
+        let response = test_server
+            .client()
+            .get("http://localhost/test/inner")
+            .perform()
+            .unwrap();
+
+        assert_eq!(response.status(), StatusCode::ACCEPTED);
+    }
+
+    #[test]
+    fn mount_composes_routers() {
+        let (chain, pipelines) = single_pipeline(new_pipeline().add(QuickExitMiddleware).build());
+
+        let api_router = build_simple_router(|route| {
+            route.get("/").to(test_handler);
+            route.get("/status").name("status").to(test_handler);
+            route.post("/reports").to(test_handler);
+        });
+
+        let router = build_router(chain, pipelines, |route| {
+            route.get("/api/reports").to(test_handler);
+            route.mount("/api", api_router);
+        });
+
+        let url_for = router.data.url_for.clone();
+        assert_eq!(url_for.path("status", &()).unwrap(), "/api/status");
+
+        let test_server = TestServer::new(router).unwrap();
+        let client = test_server.client();
+
+        let response = client.get("http://localhost/api").perform().unwrap();
+        assert_eq!(response.status(), StatusCode::ACCEPTED);
+
+        let response = client.get("http://localhost/api/status").perform().unwrap();
+        assert_eq!(response.status(), StatusCode::ACCEPTED);
+
+        let response = client
+            .post("http://localhost/api/reports", "", mime::TEXT_PLAIN)
+            .perform()
+            .unwrap();
+        assert_eq!(response.status(), StatusCode::ACCEPTED);
+
+        // Routes defined by this router keep its pipelines.
+        let response = client
+            .get("http://localhost/api/reports")
+            .perform()
+            .unwrap();
+        assert_eq!(response.status(), StatusCode::INTERNAL_SERVER_ERROR);
+
+        let response = client.get("http://localhost/status").perform().unwrap();
+        assert_eq!(response.status(), StatusCode::NOT_FOUND);
+    }
+
+    #[test]
+    #[should_panic(expected = "a Router can't be mounted once it has been cloned")]
+    fn mount_rejects_shared_router() {
+        let api_router = build_simple_router(|route| {
+            route.get("/").to(test_handler);
+        });
+        let _shared = api_router.clone();
+
+        build_simple_router(|route| {
+            route.mount("/api", api_router);
+        });
+    }
 }
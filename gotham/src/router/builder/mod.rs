@@ -7,26 +7,39 @@ mod single;
 
 use std::marker::PhantomData;
 use std::panic::RefUnwindSafe;
+use std::sync::Arc;
+use std::time::Duration;
 
 use hyper::{Body, StatusCode};
 
+use crate::extractor::host::extract_host;
 use crate::extractor::{
-    NoopPathExtractor, NoopQueryStringExtractor, PathExtractor, QueryStringExtractor,
+    extract_path, HostExtractor, NoopPathExtractor, NoopQueryStringExtractor, PathExtractor,
+    QueryStringExtractor, QueryStringOptions,
 };
+use crate::handler::Handler;
 use crate::pipeline::chain::PipelineHandleChain;
 use crate::pipeline::set::{finalize_pipeline_set, new_pipeline_set, PipelineSet};
+use crate::router::openapi::{OpenApi, OpenApiHandler};
+use crate::router::provider::ProvidedRouter;
 use crate::router::response::extender::ResponseExtender;
-use crate::router::response::finalizer::ResponseFinalizerBuilder;
-use crate::router::route::dispatch::DispatcherImpl;
-use crate::router::route::matcher::{AnyRouteMatcher, RouteMatcher};
+use crate::router::response::finalizer::{ResponseExtenders, ResponseFinalizerBuilder};
+use crate::router::route::dispatch::{DispatcherImpl, RouteExtensions};
+use crate::router::route::matcher::{AnyRouteMatcher, HostRouteMatcher, RouteMatcher};
 use crate::router::route::{Delegation, Extractors, RouteImpl};
 use crate::router::tree::node::Node;
 use crate::router::tree::Tree;
-use crate::router::Router;
+use crate::router::version::{ApiVersion, Versions};
+use crate::router::{
+    ErrorHandlers, HostExtraction, PathCase, RouteTracing, Router, RouterOptions, TrailingSlash,
+};
+use crate::state::State;
 
 pub use self::associated::{AssociatedRouteBuilder, AssociatedSingleRouteBuilder};
 pub use self::draw::DrawRoutes;
-pub use self::modify::{ExtendRouteMatcher, ReplacePathExtractor, ReplaceQueryStringExtractor};
+pub use self::modify::{
+    ExtendPipelineChain, ExtendRouteMatcher, ReplacePathExtractor, ReplaceQueryStringExtractor,
+};
 pub use self::single::DefineSingleRoute;
 
 /// Builds a `Router` using the provided closure. Routes are defined using the `RouterBuilder`
@@ -90,20 +103,43 @@ where
 {
     let mut tree = Tree::new();
 
-    let response_finalizer = {
+    let (response_finalizer, options, hosts, error_handlers, versions, openapi) = {
         let mut builder = RouterBuilder {
             node_builder: tree.borrow_root_mut(),
             pipeline_chain,
             pipelines,
             response_finalizer_builder: ResponseFinalizerBuilder::internal_new(),
+            options: RouterOptions::default(),
+            hosts: vec![],
+            error_handlers: ErrorHandlers::default(),
+            versions: Versions::default(),
+            openapi: vec![],
         };
 
         f(&mut builder);
 
-        builder.response_finalizer_builder.finalize()
+        (
+            builder.response_finalizer_builder.finalize(),
+            builder.options,
+            builder.hosts,
+            builder.error_handlers,
+            builder.versions,
+            builder.openapi,
+        )
     };
 
-    Router::internal_new(tree, response_finalizer)
+    let router = Router::internal_new(
+        tree,
+        response_finalizer,
+        options,
+        hosts,
+        error_handlers,
+        versions,
+    );
+    for (spec, handler) in openapi {
+        handler.generate(&spec, &router);
+    }
+    router
 }
 
 /// Builds a `Router` with **no** middleware using the provided closure. Routes are defined using
@@ -147,9 +183,739 @@ where
     build_router((), pipelines, f)
 }
 
-/// The top-level builder which is created by `build_router` and passed to the provided closure.
-/// See the `build_router` function and the `DrawRoutes` trait for usage.
-pub struct RouterBuilder<'a, C, P>
+/// The top-level builder which is created by `build_router` and passed to the provided closure.
+/// See the `build_router` function and the `DrawRoutes` trait for usage.
+pub struct RouterBuilder<'a, C, P>
+where
+    C: PipelineHandleChain<P> + Copy + Send + Sync + 'static,
+    P: Send + Sync + 'static,
+{
+    node_builder: &'a mut Node,
+    pipeline_chain: C,
+    pipelines: PipelineSet<P>,
+    response_finalizer_builder: ResponseFinalizerBuilder,
+    options: RouterOptions,
+    hosts: Vec<(HostRouteMatcher, Option<HostExtraction>, Tree)>,
+    error_handlers: ErrorHandlers,
+    versions: Versions,
+    openapi: Vec<(OpenApi, OpenApiHandler)>,
+}
+
+impl<'a, C, P> RouterBuilder<'a, C, P>
+where
+    C: PipelineHandleChain<P> + Copy + Send + Sync + 'static,
+    P: Send + Sync + 'static,
+{
+    /// Adds a `ResponseExtender` to the `ResponseFinalizer` in the `Router`.
+    ///
+    /// ```rust
+    /// # extern crate gotham;
+    /// # extern crate hyper;
+    /// #
+    /// # use hyper::{Body, Response, StatusCode};
+    /// # use hyper::header::WARNING;
+    /// # use gotham::state::State;
+    /// # use gotham::router::Router;
+    /// # use gotham::router::response::extender::ResponseExtender;
+    /// # use gotham::router::builder::*;
+    /// # use gotham::test::TestServer;
+    /// #
+    /// # fn my_handler(state: State) -> (State, Response<Body>) {
+    /// #   (state, Response::builder().status(StatusCode::INTERNAL_SERVER_ERROR).body(Body::empty()).unwrap())
+    /// # }
+    /// #
+    /// struct MyExtender;
+    ///
+    /// impl ResponseExtender<Body> for MyExtender {
+    ///     fn extend(&self, state: &mut State, response: &mut Response<Body>) {
+    ///         // Extender implementation omitted.
+    /// #       let _ = state;
+    /// #       response.headers_mut().insert(WARNING, "299 example.com Deprecated".parse().unwrap());
+    ///     }
+    /// }
+    ///
+    /// fn router() -> Router {
+    ///     build_simple_router(|route| {
+    ///         route.add_response_extender(StatusCode::INTERNAL_SERVER_ERROR, MyExtender);
+    /// #
+    /// #       route.get("/").to(my_handler);
+    ///     })
+    /// }
+    /// #
+    /// # fn main() {
+    /// #   let test_server = TestServer::new(router()).unwrap();
+    /// #   let response = test_server.client()
+    /// #       .get("https://example.com/")
+    /// #       .perform()
+    /// #       .unwrap();
+    /// #   assert_eq!(response.status(), StatusCode::INTERNAL_SERVER_ERROR);
+    /// #
+    /// #   {
+    /// #       let warning = response.headers().get(WARNING).unwrap();
+    /// #       assert_eq!(warning, "299 example.com Deprecated");
+    /// #   }
+    /// # }
+    /// ```
+    pub fn add_response_extender<E>(&mut self, status_code: StatusCode, extender: E)
+    where
+        E: ResponseExtender<Body> + Send + Sync + 'static,
+    {
+        self.response_finalizer_builder
+            .add(status_code, Box::new(extender))
+    }
+
+    /// Sets the handler which responds in place of the `404 Not Found` responses generated by the
+    /// `Router`, such as to produce a JSON body. It's used where no route matches the request, and
+    /// doesn't replace a `404 Not Found` response from the handler of a route.
+    ///
+    /// The handler isn't called within any pipeline, and doesn't apply to any `Router` which
+    /// requests are delegated to.
+    ///
+    /// ```rust
+    /// # extern crate gotham;
+    /// # extern crate hyper;
+    /// # extern crate mime;
+    /// #
+    /// # use hyper::{Body, Response, StatusCode};
+    /// # use gotham::helpers::http::response::create_response;
+    /// # use gotham::state::State;
+    /// # use gotham::router::Router;
+    /// # use gotham::router::builder::*;
+    /// # use gotham::test::TestServer;
+    /// #
+    /// # fn my_handler(state: State) -> (State, Response<Body>) {
+    /// #   (state, Response::builder().status(StatusCode::ACCEPTED).body(Body::empty()).unwrap())
+    /// # }
+    /// #
+    /// fn not_found(state: State) -> (State, Response<Body>) {
+    ///     let body = r#"{"error":"not found"}"#;
+    ///     let res = create_response(&state, StatusCode::NOT_FOUND, mime::APPLICATION_JSON, body);
+    ///     (state, res)
+    /// }
+    ///
+    /// fn router() -> Router {
+    ///     build_simple_router(|route| {
+    ///         route.not_found(not_found);
+    ///         route.get("/").to(my_handler);
+    ///     })
+    /// }
+    /// #
+    /// # fn main() {
+    /// #   let test_server = TestServer::new(router()).unwrap();
+    /// #   let response = test_server.client()
+    /// #       .get("https://example.com/missing")
+    /// #       .perform()
+    /// #       .unwrap();
+    /// #   assert_eq!(response.status(), StatusCode::NOT_FOUND);
+    /// #   assert_eq!(response.read_utf8_body().unwrap(), r#"{"error":"not found"}"#);
+    /// # }
+    /// ```
+    pub fn not_found<H>(&mut self, handler: H)
+    where
+        H: Handler + RefUnwindSafe + Clone + Send + Sync + 'static,
+    {
+        self.error_handlers
+            .set_not_found(move || Ok(handler.clone()));
+    }
+
+    /// Sets the handler which responds in place of the `500 Internal Server Error` responses
+    /// generated by the `Router`, and of any `HandlerError` with a `5xx` status, such as to
+    /// produce a JSON body. The `HandlerError` is put into `State` for the handler to describe,
+    /// where there is one. Where the handler itself fails, the default response is used.
+    ///
+    /// The handler isn't called within any pipeline, and doesn't apply to any `Router` which
+    /// requests are delegated to.
+    ///
+    /// ```rust
+    /// # extern crate futures;
+    /// # extern crate gotham;
+    /// # extern crate hyper;
+    /// # extern crate mime;
+    /// #
+    /// # use std::io;
+    /// # use futures::future;
+    /// # use hyper::{Body, Response, StatusCode};
+    /// # use gotham::handler::{HandlerError, HandlerFuture, IntoHandlerError};
+    /// # use gotham::helpers::http::response::create_response;
+    /// # use gotham::state::State;
+    /// # use gotham::router::Router;
+    /// # use gotham::router::builder::*;
+    /// # use gotham::test::TestServer;
+    /// #
+    /// fn my_handler(state: State) -> Box<HandlerFuture> {
+    ///     let err = io::Error::new(io::ErrorKind::Other, "database unavailable");
+    ///     Box::new(future::err((state, err.into_handler_error())))
+    /// }
+    ///
+    /// fn internal_error(state: State) -> (State, Response<Body>) {
+    ///     let status = state
+    ///         .try_borrow::<HandlerError>()
+    ///         .map(HandlerError::status)
+    ///         .unwrap_or(StatusCode::INTERNAL_SERVER_ERROR);
+    ///     let body = format!(r#"{{"error":{}}}"#, status.as_u16());
+    ///     let res = create_response(&state, status, mime::APPLICATION_JSON, body);
+    ///     (state, res)
+    /// }
+    ///
+    /// fn router() -> Router {
+    ///     build_simple_router(|route| {
+    ///         route.internal_error(internal_error);
+    ///         route.get("/").to(my_handler);
+    ///     })
+    /// }
+    /// #
+    /// # fn main() {
+    /// #   let test_server = TestServer::new(router()).unwrap();
+    /// #   let response = test_server.client()
+    /// #       .get("https://example.com/")
+    /// #       .perform()
+    /// #       .unwrap();
+    /// #   assert_eq!(response.status(), StatusCode::INTERNAL_SERVER_ERROR);
+    /// #   assert_eq!(response.read_utf8_body().unwrap(), r#"{"error":500}"#);
+    /// # }
+    /// ```
+    pub fn internal_error<H>(&mut self, handler: H)
+    where
+        H: Handler + RefUnwindSafe + Clone + Send + Sync + 'static,
+    {
+        self.error_handlers
+            .set_internal_error(move || Ok(handler.clone()));
+    }
+
+    /// Serves an OpenAPI document describing the routes of the `Router` in response to `GET`
+    /// requests for the given path, as generated by `OpenApi` once the `Router` has been built.
+    ///
+    /// ```rust
+    /// # extern crate gotham;
+    /// # extern crate hyper;
+    /// #
+    /// # use hyper::{Body, Response, StatusCode};
+    /// # use gotham::state::State;
+    /// # use gotham::router::Router;
+    /// # use gotham::router::builder::*;
+    /// # use gotham::router::openapi::OpenApi;
+    /// # use gotham::test::TestServer;
+    /// #
+    /// # fn my_handler(state: State) -> (State, Response<Body>) {
+    /// #   (state, Response::builder().status(StatusCode::ACCEPTED).body(Body::empty()).unwrap())
+    /// # }
+    /// #
+    /// fn router() -> Router {
+    ///     build_simple_router(|route| {
+    ///         route.openapi("/openapi.json", OpenApi::new("My API", "1.0.0"));
+    ///         route.get("/users").to(my_handler);
+    ///     })
+    /// }
+    /// #
+    /// # fn main() {
+    /// #   let test_server = TestServer::new(router()).unwrap();
+    /// #   let response = test_server.client()
+    /// #       .get("https://example.com/openapi.json")
+    /// #       .perform()
+    /// #       .unwrap();
+    /// #   assert_eq!(response.status(), StatusCode::OK);
+    /// #   assert!(response.read_utf8_body().unwrap().contains(r#""/users""#));
+    /// # }
+    /// ```
+    pub fn openapi(&mut self, path: &str, spec: OpenApi)
+    where
+        P: RefUnwindSafe,
+    {
+        let handler = OpenApiHandler::new();
+        self.get(path).to_new_handler(handler.clone());
+        self.openapi.push((spec, handler));
+    }
+
+    /// Enables or disables automatic responses to `OPTIONS` requests. When enabled, an `OPTIONS`
+    /// request for a path which has routes for other methods, but none for `OPTIONS`, receives a
+    /// `200 OK` response with an `Allow` header listing the methods which are routed, rather
+    /// than a `405 Method Not Allowed` response. Routes defined for `OPTIONS` take precedence.
+    ///
+    /// Automatic `OPTIONS` responses are disabled by default, and don't apply to any `Router`
+    /// which requests are delegated to.
+    ///
+    /// ```rust
+    /// # extern crate gotham;
+    /// # extern crate hyper;
+    /// #
+    /// # use hyper::{Body, Response, StatusCode};
+    /// # use hyper::header::ALLOW;
+    /// # use gotham::state::State;
+    /// # use gotham::router::Router;
+    /// # use gotham::router::builder::*;
+    /// # use gotham::test::TestServer;
+    /// #
+    /// # fn my_handler(state: State) -> (State, Response<Body>) {
+    /// #   (state, Response::builder().status(StatusCode::ACCEPTED).body(Body::empty()).unwrap())
+    /// # }
+    /// #
+    /// fn router() -> Router {
+    ///     build_simple_router(|route| {
+    ///         route.automatic_options(true);
+    ///         route.get("/request/path").to(my_handler);
+    ///     })
+    /// }
+    /// #
+    /// # fn main() {
+    /// #   let test_server = TestServer::new(router()).unwrap();
+    /// #   let response = test_server.client()
+    /// #       .options("https://example.com/request/path")
+    /// #       .perform()
+    /// #       .unwrap();
+    /// #   assert_eq!(response.status(), StatusCode::OK);
+    /// #   assert_eq!(response.headers().get_all(ALLOW).iter().count(), 2);
+    /// # }
+    /// ```
+    pub fn automatic_options(&mut self, enabled: bool) {
+        self.options.automatic_options = enabled;
+    }
+
+    /// Enables or disables automatic handling of `HEAD` requests. When enabled, a `HEAD` request
+    /// for a path which has a route for `GET` requests, but none for `HEAD`, is dispatched to the
+    /// `GET` route. The body of the response is discarded, while the status and headers are
+    /// sent as they would be for the `GET` request.
+    ///
+    /// The request method in `State` is `GET` while the request is being handled. Automatic
+    /// `HEAD` handling is disabled by default, and doesn't apply to any `Router` which requests
+    /// are delegated to.
+    ///
+    /// ```rust
+    /// # extern crate gotham;
+    /// # extern crate hyper;
+    /// #
+    /// # use hyper::{Body, Response, StatusCode};
+    /// # use hyper::header::CONTENT_LENGTH;
+    /// # use gotham::helpers::http::response::create_response;
+    /// # use gotham::state::State;
+    /// # use gotham::router::Router;
+    /// # use gotham::router::builder::*;
+    /// # use gotham::test::TestServer;
+    /// #
+    /// fn my_handler(state: State) -> (State, Response<Body>) {
+    ///     let res = create_response(&state, StatusCode::OK, mime::TEXT_PLAIN, "Hello!");
+    ///     (state, res)
+    /// }
+    ///
+    /// fn router() -> Router {
+    ///     build_simple_router(|route| {
+    ///         route.automatic_head(true);
+    ///         route.get("/request/path").to(my_handler);
+    ///     })
+    /// }
+    /// #
+    /// # fn main() {
+    /// #   let test_server = TestServer::new(router()).unwrap();
+    /// #   let response = test_server.client()
+    /// #       .head("https://example.com/request/path")
+    /// #       .perform()
+    /// #       .unwrap();
+    /// #   assert_eq!(response.status(), StatusCode::OK);
+    /// #   assert_eq!(response.headers()[CONTENT_LENGTH], "6");
+    /// #   assert!(response.read_body().unwrap().is_empty());
+    /// # }
+    /// ```
+    pub fn automatic_head(&mut self, enabled: bool) {
+        self.options.automatic_head = enabled;
+    }
+
+    /// Enables or disables the `X-HTTP-Method-Override` header. When enabled, a `POST` request
+    /// with the header is routed as a request for the method it names, for clients which can't
+    /// send other methods, such as those behind proxies which reject them.
+    ///
+    /// The request method in `State` is the method named by the header while the request is
+    /// being handled. Requests with other methods, and headers which don't name a valid method,
+    /// are routed as usual. The header is ignored by default.
+    ///
+    /// ```rust
+    /// # extern crate gotham;
+    /// # extern crate hyper;
+    /// # extern crate mime;
+    /// #
+    /// # use hyper::{Body, Response, StatusCode};
+    /// # use hyper::header::HeaderValue;
+    /// # use gotham::state::State;
+    /// # use gotham::router::Router;
+    /// # use gotham::router::builder::*;
+    /// # use gotham::test::TestServer;
+    /// #
+    /// # fn delete_user(state: State) -> (State, Response<Body>) {
+    /// #   (state, Response::builder().status(StatusCode::NO_CONTENT).body(Body::empty()).unwrap())
+    /// # }
+    /// #
+    /// fn router() -> Router {
+    ///     build_simple_router(|route| {
+    ///         route.method_override(true);
+    ///         route.delete("/users/:id").to(delete_user);
+    ///     })
+    /// }
+    /// #
+    /// # fn main() {
+    /// #   let test_server = TestServer::new(router()).unwrap();
+    /// #   let response = test_server.client()
+    /// #       .post("https://example.com/users/1", Body::empty(), mime::TEXT_PLAIN)
+    /// #       .with_header("x-http-method-override", HeaderValue::from_static("DELETE"))
+    /// #       .perform()
+    /// #       .unwrap();
+    /// #   assert_eq!(response.status(), StatusCode::NO_CONTENT);
+    /// # }
+    /// ```
+    pub fn method_override(&mut self, enabled: bool) {
+        self.options.method_override = enabled;
+    }
+
+    /// Enables or disables `405 Method Not Allowed` responses. By default, a request for a path
+    /// which has routes, but none for the request method, receives a `405 Method Not Allowed`
+    /// response with an `Allow` header listing the methods which are routed. When disabled, such
+    /// requests receive a `404 Not Found` response instead, so that the methods supported by a
+    /// path aren't revealed.
+    ///
+    /// Automatic `OPTIONS` responses, when enabled, still list the methods which are routed. The
+    /// setting doesn't apply to any `Router` which requests are delegated to.
+    ///
+    /// ```rust
+    /// # extern crate gotham;
+    /// # extern crate hyper;
+    /// #
+    /// # use hyper::{Body, Response, StatusCode};
+    /// # use hyper::header::ALLOW;
+    /// # use gotham::state::State;
+    /// # use gotham::router::Router;
+    /// # use gotham::router::builder::*;
+    /// # use gotham::test::TestServer;
+    /// #
+    /// # fn my_handler(state: State) -> (State, Response<Body>) {
+    /// #   (state, Response::builder().status(StatusCode::ACCEPTED).body(Body::empty()).unwrap())
+    /// # }
+    /// #
+    /// fn router() -> Router {
+    ///     build_simple_router(|route| {
+    ///         route.method_not_allowed(false);
+    ///         route.get("/request/path").to(my_handler);
+    ///     })
+    /// }
+    /// #
+    /// # fn main() {
+    /// #   let test_server = TestServer::new(router()).unwrap();
+    /// #   let response = test_server.client()
+    /// #       .delete("https://example.com/request/path")
+    /// #       .perform()
+    /// #       .unwrap();
+    /// #   assert_eq!(response.status(), StatusCode::NOT_FOUND);
+    /// #   assert!(response.headers().get(ALLOW).is_none());
+    /// # }
+    /// ```
+    pub fn method_not_allowed(&mut self, enabled: bool) {
+        self.options.hide_allowed_methods = !enabled;
+    }
+
+    /// Sets the policy for requests whose path has, or lacks, a trailing slash. By default,
+    /// `TrailingSlash::MatchBoth` is used, and `/users` and `/users/` match the same routes.
+    ///
+    /// The policy doesn't apply to any `Router` which requests are delegated to.
+    ///
+    /// ```rust
+    /// # extern crate gotham;
+    /// # extern crate hyper;
+    /// #
+    /// # use hyper::{Body, Response, StatusCode};
+    /// # use hyper::header::LOCATION;
+    /// # use gotham::state::State;
+    /// # use gotham::router::{Router, TrailingSlash};
+    /// # use gotham::router::builder::*;
+    /// # use gotham::test::TestServer;
+    /// #
+    /// # fn my_handler(state: State) -> (State, Response<Body>) {
+    /// #   (state, Response::builder().status(StatusCode::ACCEPTED).body(Body::empty()).unwrap())
+    /// # }
+    /// #
+    /// fn router() -> Router {
+    ///     build_simple_router(|route| {
+    ///         route.trailing_slash(TrailingSlash::RedirectToSlash);
+    ///         route.get("/users/").to(my_handler);
+    ///     })
+    /// }
+    /// #
+    /// # fn main() {
+    /// #   let test_server = TestServer::new(router()).unwrap();
+    /// #   let response = test_server.client()
+    /// #       .get("https://example.com/users?page=2")
+    /// #       .perform()
+    /// #       .unwrap();
+    /// #   assert_eq!(response.status(), StatusCode::PERMANENT_REDIRECT);
+    /// #   assert_eq!(response.headers()[LOCATION], "/users/?page=2");
+    /// # }
+    /// ```
+    pub fn trailing_slash(&mut self, policy: TrailingSlash) {
+        self.options.trailing_slash = policy;
+    }
+
+    /// Sets the policy for the case of static segments in the request path. By default,
+    /// `PathCase::Sensitive` is used, and `/users` doesn't match a route for `/Users`.
+    ///
+    /// The policy doesn't apply to any `Router` which requests are delegated to.
+    ///
+    /// ```rust
+    /// # extern crate gotham;
+    /// # extern crate hyper;
+    /// #
+    /// # use hyper::{Body, Response, StatusCode};
+    /// # use hyper::header::LOCATION;
+    /// # use gotham::state::State;
+    /// # use gotham::router::{PathCase, Router};
+    /// # use gotham::router::builder::*;
+    /// # use gotham::test::TestServer;
+    /// #
+    /// # fn my_handler(state: State) -> (State, Response<Body>) {
+    /// #   (state, Response::builder().status(StatusCode::ACCEPTED).body(Body::empty()).unwrap())
+    /// # }
+    /// #
+    /// fn router() -> Router {
+    ///     build_simple_router(|route| {
+    ///         route.path_case(PathCase::RedirectToCanonical);
+    ///         route.get("/Users/:name").to(my_handler);
+    ///     })
+    /// }
+    /// #
+    /// # fn main() {
+    /// #   let test_server = TestServer::new(router()).unwrap();
+    /// #   let response = test_server.client()
+    /// #       .get("https://example.com/USERS/Alice?page=2")
+    /// #       .perform()
+    /// #       .unwrap();
+    /// #   assert_eq!(response.status(), StatusCode::PERMANENT_REDIRECT);
+    /// #   assert_eq!(response.headers()[LOCATION], "/Users/Alice?page=2");
+    /// # }
+    /// ```
+    pub fn path_case(&mut self, policy: PathCase) {
+        self.options.path_case = policy;
+    }
+
+    /// Sets whether requests are traced as they're matched against the routes, to diagnose
+    /// requests which aren't routed as expected. By default, `RouteTracing::Disabled` is used.
+    ///
+    /// ```rust
+    /// # extern crate gotham;
+    /// # extern crate hyper;
+    /// #
+    /// # use hyper::{Body, Response, StatusCode};
+    /// # use gotham::state::State;
+    /// # use gotham::router::{RouteTracing, Router};
+    /// # use gotham::router::builder::*;
+    /// # use gotham::test::TestServer;
+    /// #
+    /// # fn my_handler(state: State) -> (State, Response<Body>) {
+    /// #   (state, Response::builder().status(StatusCode::ACCEPTED).body(Body::empty()).unwrap())
+    /// # }
+    /// #
+    /// fn router() -> Router {
+    ///     build_simple_router(|route| {
+    ///         route.route_tracing(RouteTracing::Header);
+    ///         route.get("/users/:name").to(my_handler);
+    ///     })
+    /// }
+    /// #
+    /// # fn main() {
+    /// #   let test_server = TestServer::new(router()).unwrap();
+    /// #   let response = test_server.client()
+    /// #       .get("https://example.com/posts/1")
+    /// #       .perform()
+    /// #       .unwrap();
+    /// #   assert_eq!(response.status(), StatusCode::NOT_FOUND);
+    /// #   if cfg!(debug_assertions) {
+    /// #       let trace: Vec<_> = response.headers().get_all("x-route-trace").iter().collect();
+    /// #       assert_eq!(*trace.last().unwrap(), "no routable node matched the request path");
+    /// #   }
+    /// # }
+    /// ```
+    pub fn route_tracing(&mut self, policy: RouteTracing) {
+        self.options.route_tracing = policy;
+    }
+
+    /// Sets how the query strings of requests are decoded for the routes of this `Router`, by
+    /// both `QueryStringExtractor` types and query string matchers. A `Router` which requests are
+    /// delegated to uses its own options. By default, `QueryStringOptions::default()` is used.
+    ///
+    /// See `QueryStringOptions` for an example.
+    pub fn query_string_options(&mut self, options: QueryStringOptions) {
+        self.options.query_string = options;
+    }
+
+    /// Defines routes which only match requests for the given host, as determined by the `Host`
+    /// header. The `pattern` may begin with `*.` to match any subdomain, or with a name in braces
+    /// to match a single label, as described by `HostRouteMatcher`.
+    ///
+    /// A request for a matching host is routed only by the routes of that host, taking the first
+    /// match where several hosts apply. Requests for other hosts are routed by the routes defined
+    /// outside of any host.
+    ///
+    /// ```rust
+    /// # extern crate gotham;
+    /// # extern crate hyper;
+    /// #
+    /// # use hyper::{Body, Response, StatusCode};
+    /// # use hyper::header::HOST;
+    /// # use gotham::state::State;
+    /// # use gotham::router::Router;
+    /// # use gotham::router::builder::*;
+    /// # use gotham::test::TestServer;
+    /// #
+    /// # fn api_handler(state: State) -> (State, Response<Body>) {
+    /// #   (state, Response::builder().status(StatusCode::ACCEPTED).body(Body::empty()).unwrap())
+    /// # }
+    /// #
+    /// # fn app_handler(state: State) -> (State, Response<Body>) {
+    /// #   (state, Response::builder().status(StatusCode::OK).body(Body::empty()).unwrap())
+    /// # }
+    /// #
+    /// fn router() -> Router {
+    ///     build_simple_router(|route| {
+    ///         route.host("api.example.com", |route| {
+    ///             route.get("/").to(api_handler);
+    ///         });
+    ///
+    ///         route.get("/").to(app_handler);
+    ///     })
+    /// }
+    /// #
+    /// # fn main() {
+    /// #   let test_server = TestServer::new(router()).unwrap();
+    /// #   let response = test_server.client()
+    /// #       .get("http://localhost/")
+    /// #       .with_header(HOST, "api.example.com".parse().unwrap())
+    /// #       .perform()
+    /// #       .unwrap();
+    /// #   assert_eq!(response.status(), StatusCode::ACCEPTED);
+    /// #
+    /// #   let response = test_server.client()
+    /// #       .get("http://localhost/")
+    /// #       .with_header(HOST, "www.example.com".parse().unwrap())
+    /// #       .perform()
+    /// #       .unwrap();
+    /// #   assert_eq!(response.status(), StatusCode::OK);
+    /// # }
+    /// ```
+    pub fn host<F>(&mut self, pattern: &str, f: F)
+    where
+        F: FnOnce(&mut ScopeBuilder<C, P>),
+    {
+        self.add_host(pattern, None, f)
+    }
+
+    /// Defines routes which only match requests for the given host, as `RouterBuilder::host`
+    /// does, and extracts the label captured by a pattern such as `{tenant}.example.com` into
+    /// `State` as the given `HostExtractor` before the request is routed. Where the label can't
+    /// be deserialized, the response of the `StaticResponseExtender` is used, such as a
+    /// `400 Bad Request`.
+    ///
+    /// See `HostExtractor` for an example.
+    pub fn host_with_extractor<HE, F>(&mut self, pattern: &str, f: F)
+    where
+        HE: HostExtractor<Body>,
+        F: FnOnce(&mut ScopeBuilder<C, P>),
+    {
+        self.add_host(pattern, Some(extract_host::<HE>), f)
+    }
+
+    fn add_host<F>(&mut self, pattern: &str, extraction: Option<HostExtraction>, f: F)
+    where
+        F: FnOnce(&mut ScopeBuilder<C, P>),
+    {
+        let mut tree = Tree::new();
+
+        {
+            let mut scope_builder = ScopeBuilder {
+                node_builder: tree.borrow_root_mut(),
+                pipeline_chain: self.pipeline_chain,
+                pipelines: self.pipelines.clone(),
+            };
+
+            f(&mut scope_builder);
+        }
+
+        self.hosts
+            .push((HostRouteMatcher::new(pattern), extraction, tree));
+    }
+
+    /// Defines the routes of a version of an API, beneath a path prefix of `/v` followed by the
+    /// version number, such as `/v1`. The returned `ApiVersion` can mark the version as
+    /// deprecated, announcing it to clients through the headers of each response from its
+    /// routes.
+    ///
+    /// Where a header has been configured through `RouterBuilder::version_header`, requests
+    /// without the prefix are also routed to the routes of the version named by the header,
+    /// falling back to the routes outside of any version where the version has no matching path.
+    ///
+    /// ```rust
+    /// # extern crate gotham;
+    /// # extern crate hyper;
+    /// #
+    /// # use gotham::state::State;
+    /// # use gotham::router::Router;
+    /// # use gotham::router::builder::*;
+    /// # use gotham::test::TestServer;
+    /// #
+    /// fn list_users_v1(state: State) -> (State, &'static str) {
+    ///     (state, "v1")
+    /// }
+    ///
+    /// fn list_users_v2(state: State) -> (State, &'static str) {
+    ///     (state, "v2")
+    /// }
+    ///
+    /// fn router() -> Router {
+    ///     build_simple_router(|route| {
+    ///         route.version_header("accept-version");
+    ///
+    ///         route.version(1, |route| {
+    ///             route.get("/users").to(list_users_v1);
+    ///         });
+    ///
+    ///         route.version(2, |route| {
+    ///             route.get("/users").to(list_users_v2);
+    ///         });
+    ///     })
+    /// }
+    /// #
+    /// # fn main() {
+    /// #   let test_server = TestServer::new(router()).unwrap();
+    /// #   let response = test_server.client()
+    /// #       .get("http://localhost/v1/users")
+    /// #       .perform()
+    /// #       .unwrap();
+    /// #   assert_eq!(response.read_utf8_body().unwrap(), "v1");
+    /// #
+    /// #   let response = test_server.client()
+    /// #       .get("http://localhost/users")
+    /// #       .with_header("accept-version", "2".parse().unwrap())
+    /// #       .perform()
+    /// #       .unwrap();
+    /// #   assert_eq!(response.read_utf8_body().unwrap(), "v2");
+    /// # }
+    /// ```
+    pub fn version<F>(&mut self, version: u32, f: F) -> &mut ApiVersion
+    where
+        F: FnOnce(&mut ScopeBuilder<C, P>),
+        P: RefUnwindSafe,
+    {
+        self.scope(&format!("/v{}", version), f);
+        self.versions.add(version)
+    }
+
+    /// Sets the header through which a request may name the version of the API it's for, such
+    /// as `Accept-Version`, as described by `RouterBuilder::version`. The header is added to the
+    /// `Vary` header of each response.
+    ///
+    /// # Panics
+    ///
+    /// If `name` isn't a valid header name.
+    pub fn version_header(&mut self, name: &str) {
+        self.versions.set_header(name.parse().unwrap());
+    }
+}
+
+/// A scoped builder, which is created by `DrawRoutes::scope` and passed to the provided closure.
+/// The `DrawRoutes` trait has documentation for using this type.
+pub struct ScopeBuilder<'a, C, P>
 where
     C: PipelineHandleChain<P> + Copy + Send + Sync + 'static,
     P: Send + Sync + 'static,
@@ -157,83 +923,198 @@ where
     node_builder: &'a mut Node,
     pipeline_chain: C,
     pipelines: PipelineSet<P>,
-    response_finalizer_builder: ResponseFinalizerBuilder,
 }
 
-impl<'a, C, P> RouterBuilder<'a, C, P>
+impl<'a, C, P> ScopeBuilder<'a, C, P>
 where
     C: PipelineHandleChain<P> + Copy + Send + Sync + 'static,
     P: Send + Sync + 'static,
 {
-    /// Adds a `ResponseExtender` to the `ResponseFinalizer` in the `Router`.
+    /// Adds a `ResponseExtender` for responses with the given `StatusCode` from the routes at or
+    /// beneath the path of the scope, including those defined outside of the scope. It's used in
+    /// place of the extender added by `RouterBuilder::add_response_extender` for the same
+    /// `StatusCode`, and an inner scope or a route may replace it, as described by
+    /// `DefineSingleRoute::add_response_extender`.
+    ///
+    /// Responses generated by the `Router` where no route matches, such as `404 Not Found`, aren't
+    /// extended by the scope.
     ///
     /// ```rust
     /// # extern crate gotham;
     /// # extern crate hyper;
     /// #
     /// # use hyper::{Body, Response, StatusCode};
-    /// # use hyper::header::WARNING;
+    /// # use gotham::handler::{HandlerFuture, IntoHandlerError};
     /// # use gotham::state::State;
     /// # use gotham::router::Router;
-    /// # use gotham::router::response::extender::ResponseExtender;
     /// # use gotham::router::builder::*;
     /// # use gotham::test::TestServer;
     /// #
-    /// # fn my_handler(state: State) -> (State, Response<Body>) {
-    /// #   (state, Response::builder().status(StatusCode::INTERNAL_SERVER_ERROR).body(Body::empty()).unwrap())
-    /// # }
-    /// #
-    /// struct MyExtender;
-    ///
-    /// impl ResponseExtender<Body> for MyExtender {
-    ///     fn extend(&self, state: &mut State, response: &mut Response<Body>) {
-    ///         // Extender implementation omitted.
-    /// #       let _ = state;
-    /// #       response.headers_mut().insert(WARNING, "299 example.com Deprecated".parse().unwrap());
-    ///     }
+    /// fn failing_handler(state: State) -> Box<HandlerFuture> {
+    ///     let err = std::io::Error::new(std::io::ErrorKind::Other, "unavailable");
+    ///     Box::new(futures::future::err((state, err.into_handler_error())))
     /// }
     ///
     /// fn router() -> Router {
     ///     build_simple_router(|route| {
-    ///         route.add_response_extender(StatusCode::INTERNAL_SERVER_ERROR, MyExtender);
-    /// #
-    /// #       route.get("/").to(my_handler);
+    ///         route.scope("/api", |route| {
+    ///             route.add_response_extender(
+    ///                 StatusCode::INTERNAL_SERVER_ERROR,
+    ///                 |_state: &mut State, res: &mut Response<Body>| {
+    ///                     *res.body_mut() = Body::from(r#"{"error":"internal"}"#);
+    ///                 },
+    ///             );
+    ///             route.get("/users").to(failing_handler);
+    ///         });
     ///     })
     /// }
     /// #
     /// # fn main() {
     /// #   let test_server = TestServer::new(router()).unwrap();
     /// #   let response = test_server.client()
-    /// #       .get("https://example.com/")
+    /// #       .get("https://example.com/api/users")
     /// #       .perform()
     /// #       .unwrap();
     /// #   assert_eq!(response.status(), StatusCode::INTERNAL_SERVER_ERROR);
-    /// #
-    /// #   {
-    /// #       let warning = response.headers().get(WARNING).unwrap();
-    /// #       assert_eq!(warning, "299 example.com Deprecated");
-    /// #   }
+    /// #   assert_eq!(response.read_utf8_body().unwrap(), r#"{"error":"internal"}"#);
     /// # }
     /// ```
     pub fn add_response_extender<E>(&mut self, status_code: StatusCode, extender: E)
     where
         E: ResponseExtender<Body> + Send + Sync + 'static,
     {
-        self.response_finalizer_builder
-            .add(status_code, Box::new(extender))
+        self.node_builder
+            .add_response_extender(status_code, Arc::new(extender));
     }
-}
 
-/// A scoped builder, which is created by `DrawRoutes::scope` and passed to the provided closure.
-/// The `DrawRoutes` trait has documentation for using this type.
-pub struct ScopeBuilder<'a, C, P>
-where
-    C: PipelineHandleChain<P> + Copy + Send + Sync + 'static,
-    P: Send + Sync + 'static,
-{
-    node_builder: &'a mut Node,
-    pipeline_chain: C,
-    pipelines: PipelineSet<P>,
+    /// Limits the size of request bodies for the routes at or beneath the path of the scope to
+    /// `limit` bytes, including those defined outside of the scope. An inner scope or a route may
+    /// replace the limit, as described by `DefineSingleRoute::with_body_limit`.
+    ///
+    /// ```rust
+    /// # extern crate gotham;
+    /// # extern crate hyper;
+    /// # extern crate mime;
+    /// #
+    /// # use hyper::StatusCode;
+    /// # use gotham::state::State;
+    /// # use gotham::router::Router;
+    /// # use gotham::router::builder::*;
+    /// # use gotham::test::TestServer;
+    /// #
+    /// # fn create_user(state: State) -> (State, &'static str) {
+    /// #   (state, "created")
+    /// # }
+    /// #
+    /// # fn upload(state: State) -> (State, &'static str) {
+    /// #   (state, "uploaded")
+    /// # }
+    /// #
+    /// fn router() -> Router {
+    ///     build_simple_router(|route| {
+    ///         route.scope("/api", |route| {
+    ///             route.body_limit(64 * 1024);
+    ///             route.post("/users").to(create_user);
+    ///             route
+    ///                 .post("/uploads")
+    ///                 .with_body_limit(64 * 1024 * 1024)
+    ///                 .to(upload);
+    ///         });
+    ///     })
+    /// }
+    /// #
+    /// # fn main() {
+    /// #   let test_server = TestServer::new(router()).unwrap();
+    /// #   let response = test_server.client()
+    /// #       .post("https://example.com/api/users", vec![b' '; 128 * 1024], mime::APPLICATION_JSON)
+    /// #       .perform()
+    /// #       .unwrap();
+    /// #   assert_eq!(response.status(), StatusCode::PAYLOAD_TOO_LARGE);
+    /// #
+    /// #   let response = test_server.client()
+    /// #       .post("https://example.com/api/uploads", vec![0; 128 * 1024], mime::IMAGE_PNG)
+    /// #       .perform()
+    /// #       .unwrap();
+    /// #   assert_eq!(response.status(), StatusCode::OK);
+    /// # }
+    /// ```
+    pub fn body_limit(&mut self, limit: u64) {
+        self.node_builder.set_body_limit(limit);
+    }
+
+    /// Extracts the dynamic segments of the path of the scope into `State` as `PE`, for every
+    /// route at or beneath the path of the scope, including those defined outside of the scope.
+    /// The extractor of a route beneath the scope then only needs to declare the segments of the
+    /// route itself, while the handler borrows the segments of the scope from `State` as `PE`.
+    ///
+    /// The extractors of the scopes a route is defined in run outermost first, before the
+    /// extractor of the route, and are given every dynamic segment of the request path, so any
+    /// segments they don't declare are ignored. Where an extractor fails, its
+    /// `StaticResponseExtender` provides the response, as for the extractor of a route.
+    ///
+    /// ```rust
+    /// # extern crate gotham;
+    /// # #[macro_use]
+    /// # extern crate gotham_derive;
+    /// # extern crate hyper;
+    /// # #[macro_use]
+    /// # extern crate serde_derive;
+    /// #
+    /// # use hyper::StatusCode;
+    /// # use gotham::state::{FromState, State};
+    /// # use gotham::router::Router;
+    /// # use gotham::router::builder::*;
+    /// # use gotham::test::TestServer;
+    /// #
+    /// #[derive(Deserialize, StateData, StaticResponseExtender)]
+    /// struct UserPath {
+    ///     user_id: u64,
+    /// }
+    ///
+    /// #[derive(Deserialize, StateData, StaticResponseExtender)]
+    /// struct PostPath {
+    ///     post_id: u64,
+    /// }
+    ///
+    /// fn show_post(state: State) -> (State, String) {
+    ///     let user_id = UserPath::borrow_from(&state).user_id;
+    ///     let post_id = PostPath::borrow_from(&state).post_id;
+    ///     (state, format!("post {} of user {}", post_id, user_id))
+    /// }
+    ///
+    /// fn router() -> Router {
+    ///     build_simple_router(|route| {
+    ///         route.scope("/users/:user_id", |route| {
+    ///             route.with_path_extractor::<UserPath>();
+    ///             route
+    ///                 .get("/posts/:post_id")
+    ///                 .with_path_extractor::<PostPath>()
+    ///                 .to(show_post);
+    ///         });
+    ///     })
+    /// }
+    /// #
+    /// # fn main() {
+    /// #   let test_server = TestServer::new(router()).unwrap();
+    /// #   let response = test_server.client()
+    /// #       .get("https://example.com/users/7/posts/42")
+    /// #       .perform()
+    /// #       .unwrap();
+    /// #   assert_eq!(response.read_utf8_body().unwrap(), "post 42 of user 7");
+    /// #
+    /// #   let response = test_server.client()
+    /// #       .get("https://example.com/users/me/posts/42")
+    /// #       .perform()
+    /// #       .unwrap();
+    /// #   assert_eq!(response.status(), StatusCode::BAD_REQUEST);
+    /// # }
+    /// ```
+    pub fn with_path_extractor<PE>(&mut self)
+    where
+        PE: PathExtractor<Body> + Send + Sync + 'static,
+    {
+        self.node_builder.add_path_extraction(extract_path::<PE>);
+    }
 }
 
 /// A delegated builder, which is created by `DrawRoutes::delegate` and returned. The `DrawRoutes`
@@ -267,6 +1148,80 @@ where
 
         self.node_builder.add_route(Box::new(route));
     }
+
+    /// Directs the delegated route to a `Router` chosen by the `provider` for each request, such
+    /// as to route requests to plugins which are loaded after the server has started. The
+    /// provider may use the `DelegatedSegments` in `State` to find the `Router`, and requests for
+    /// which it provides none receive a `404 Not Found` response.
+    ///
+    /// ```rust
+    /// # extern crate gotham;
+    /// # extern crate hyper;
+    /// #
+    /// # use std::collections::HashMap;
+    /// # use std::sync::{Arc, RwLock};
+    /// # use hyper::StatusCode;
+    /// # use gotham::router::Router;
+    /// # use gotham::router::builder::*;
+    /// # use gotham::router::provider::DelegatedSegments;
+    /// # use gotham::state::{FromState, State};
+    /// # use gotham::test::TestServer;
+    /// #
+    /// # fn main() {
+    /// let plugins: Arc<RwLock<HashMap<String, Router>>> = Default::default();
+    ///
+    /// let router = {
+    ///     let plugins = plugins.clone();
+    ///     build_simple_router(move |route| {
+    ///         route
+    ///             .delegate("/plugins/:name/*")
+    ///             .to_router_provider(move |state: &State| {
+    ///                 let name = DelegatedSegments::borrow_from(state).get("name")?;
+    ///                 plugins.read().unwrap().get(name).cloned()
+    ///             });
+    ///     })
+    /// };
+    ///
+    /// // plugins can be loaded once the router is serving requests
+    /// plugins.write().unwrap().insert(
+    ///     "search".to_owned(),
+    ///     build_simple_router(|route| {
+    ///         route.get("/query").to(|state| (state, "results"));
+    ///     }),
+    /// );
+    ///
+    /// let test_server = TestServer::new(router).unwrap();
+    /// let response = test_server
+    ///     .client()
+    ///     .get("http://localhost/plugins/search/query")
+    ///     .perform()
+    ///     .unwrap();
+    /// assert_eq!(response.read_utf8_body().unwrap(), "results");
+    ///
+    /// let response = test_server
+    ///     .client()
+    ///     .get("http://localhost/plugins/unknown/query")
+    ///     .perform()
+    ///     .unwrap();
+    /// assert_eq!(response.status(), StatusCode::NOT_FOUND);
+    /// # }
+    /// ```
+    pub fn to_router_provider<F>(self, provider: F)
+    where
+        F: Fn(&State) -> Option<Router> + Send + Sync + RefUnwindSafe + 'static,
+    {
+        let handler = ProvidedRouter::new(provider);
+        let new_handler = move || Ok(handler.clone());
+        let dispatcher = DispatcherImpl::new(new_handler, self.pipeline_chain, self.pipelines);
+        let route: DelegatedRoute = DelegatedRoute::new(
+            AnyRouteMatcher::new(),
+            Box::new(dispatcher),
+            Extractors::new(),
+            Delegation::External,
+        );
+
+        self.node_builder.add_route(Box::new(route));
+    }
 }
 
 /// Implements the traits required to define a single route, after determining which request paths
@@ -284,6 +1239,10 @@ where
     pipeline_chain: C,
     pipelines: PipelineSet<P>,
     phantom: PhantomData<(PE, QSE)>,
+    timeout: Option<Duration>,
+    body_limit: Option<u64>,
+    extensions: RouteExtensions,
+    response_extenders: ResponseExtenders,
 }
 
 // Trait impls live with the traits.
@@ -308,6 +1267,10 @@ where
             pipeline_chain: self.pipeline_chain,
             pipelines: self.pipelines,
             phantom: PhantomData,
+            timeout: self.timeout,
+            body_limit: self.body_limit,
+            extensions: self.extensions,
+            response_extenders: self.response_extenders,
         }
     }
 }
@@ -316,17 +1279,28 @@ where
 mod tests {
     use super::*;
 
-    use futures::{Future, Stream};
+    use std::io;
+    use std::sync::atomic::{AtomicUsize, Ordering};
+    use std::sync::Arc;
+
+    use futures::{future, Future, Stream};
+    use hyper::header::ACCEPT;
     use hyper::service::Service;
     use hyper::{Body, Request, Response, StatusCode};
     use serde_derive::Deserialize;
 
+    use crate::handler::HandlerFuture;
+    use crate::helpers::http::response::create_empty_response;
     use crate::middleware::cookie::CookieParser;
     use crate::middleware::session::NewSessionMiddleware;
+    use crate::middleware::{Middleware, NewMiddleware};
     use crate::pipeline::new_pipeline;
+    use crate::pipeline::single::single_pipeline;
     use crate::router::response::extender::StaticResponseExtender;
+    use crate::router::route::matcher::AcceptHeaderRouteMatcher;
     use crate::service::GothamService;
     use crate::state::{State, StateData};
+    use crate::test::TestServer;
 
     #[derive(Deserialize)]
     struct SalutationParams {
@@ -353,6 +1327,33 @@ mod tests {
         fn extend(_: &mut State, _: &mut Response<Body>) {}
     }
 
+    #[derive(Deserialize)]
+    struct UserParams {
+        id: u32,
+    }
+
+    impl StateData for UserParams {}
+
+    impl StaticResponseExtender for UserParams {
+        type ResBody = Body;
+        fn extend(_: &mut State, res: &mut Response<Body>) {
+            *res.status_mut() = StatusCode::BAD_REQUEST;
+        }
+    }
+
+    #[derive(Deserialize)]
+    struct ArchiveParams {
+        #[serde(rename = "*")]
+        parts: Vec<String>,
+    }
+
+    impl StateData for ArchiveParams {}
+
+    impl StaticResponseExtender for ArchiveParams {
+        type ResBody = Body;
+        fn extend(_: &mut State, _: &mut Response<Body>) {}
+    }
+
     mod welcome {
         use super::*;
         pub fn index(state: State) -> (State, Response<Body>) {
@@ -615,4 +1616,233 @@ mod tests {
         let response = call(Request::get("/trailing-slash").body(Body::empty()).unwrap());
         assert_eq!(response.status(), StatusCode::OK);
     }
+
+    #[test]
+    fn mid_path_glob_test() {
+        fn manifest(mut state: State) -> (State, Response<Body>) {
+            let params = state.take::<ArchiveParams>();
+            let response = Response::builder()
+                .status(StatusCode::OK)
+                .body(params.parts.join("/").into())
+                .unwrap();
+            (state, response)
+        }
+
+        let router = build_simple_router(|route| {
+            route
+                .get("/archive/*/manifest.json")
+                .with_path_extractor::<ArchiveParams>()
+                .to(manifest);
+        });
+
+        let test_server = TestServer::new(router).unwrap();
+        let client = test_server.client();
+
+        let response = client
+            .get("http://localhost/archive/2019/06/manifest.json")
+            .perform()
+            .unwrap();
+        assert_eq!(response.status(), StatusCode::OK);
+        assert_eq!(response.read_utf8_body().unwrap(), "2019/06");
+
+        let response = client
+            .get("http://localhost/archive/manifest.json")
+            .perform()
+            .unwrap();
+        assert_eq!(response.status(), StatusCode::NOT_FOUND);
+
+        let response = client
+            .get("http://localhost/archive/2019/06")
+            .perform()
+            .unwrap();
+        assert_eq!(response.status(), StatusCode::NOT_FOUND);
+    }
+
+    #[test]
+    fn typed_segment_test() {
+        fn user(mut state: State) -> (State, Response<Body>) {
+            let params = state.take::<UserParams>();
+            let response = Response::builder()
+                .status(StatusCode::OK)
+                .body(format!("User {}", params.id).into())
+                .unwrap();
+            (state, response)
+        }
+
+        fn new_user(state: State) -> (State, Response<Body>) {
+            let response = Response::builder()
+                .status(StatusCode::OK)
+                .body("New user".into())
+                .unwrap();
+            (state, response)
+        }
+
+        let router = build_simple_router(|route| {
+            route
+                .get("/users/:id(u32)")
+                .with_path_extractor::<UserParams>()
+                .to(user);
+            route.get("/users/:name").to(new_user);
+            route
+                .get("/accounts/:id")
+                .with_path_extractor::<UserParams>()
+                .to(user);
+        });
+
+        let test_server = TestServer::new(router).unwrap();
+        let client = test_server.client();
+        let body = |uri| {
+            let response = client.get(uri).perform().unwrap();
+            (response.status(), response.read_utf8_body().unwrap())
+        };
+
+        assert_eq!(
+            body("http://localhost/users/42"),
+            (StatusCode::OK, "User 42".to_owned())
+        );
+        assert_eq!(
+            body("http://localhost/users/new"),
+            (StatusCode::OK, "New user".to_owned())
+        );
+        assert_eq!(
+            body("http://localhost/users/99999999999"),
+            (
+                StatusCode::BAD_REQUEST,
+                "invalid value for `id`: number too large to fit in target type".to_owned()
+            )
+        );
+        assert_eq!(
+            body("http://localhost/accounts/abc"),
+            (
+                StatusCode::BAD_REQUEST,
+                "invalid value for `id`: invalid digit found in string".to_owned()
+            )
+        );
+    }
+
+    #[test]
+    fn content_negotiation_test() {
+        let router = build_simple_router(|route| {
+            route
+                .get("/reports/:id")
+                .add_route_matcher(AcceptHeaderRouteMatcher::new(vec![mime::APPLICATION_JSON]))
+                .to(|state| (state, "json"));
+            route
+                .get("/reports/:id")
+                .add_route_matcher(AcceptHeaderRouteMatcher::new(vec![mime::TEXT_HTML]))
+                .to(|state| (state, "html"));
+        });
+
+        let test_server = TestServer::new(router).unwrap();
+        let client = test_server.client();
+        let body = |accept: &str| {
+            let response = client
+                .get("http://localhost/reports/1")
+                .with_header(ACCEPT, accept.parse().unwrap())
+                .perform()
+                .unwrap();
+            (response.status(), response.read_utf8_body().unwrap())
+        };
+
+        assert_eq!(
+            body("application/json"),
+            (StatusCode::OK, "json".to_owned())
+        );
+        assert_eq!(body("text/html"), (StatusCode::OK, "html".to_owned()));
+        assert_eq!(
+            body("text/html,application/xhtml+xml,*/*;q=0.8"),
+            (StatusCode::OK, "html".to_owned())
+        );
+        assert_eq!(body("*/*"), (StatusCode::OK, "json".to_owned()));
+        assert_eq!(body("text/plain").0, StatusCode::NOT_ACCEPTABLE);
+    }
+
+    #[test]
+    fn route_extension_test() {
+        #[derive(Clone)]
+        struct RequiredRole(&'static str);
+
+        impl StateData for RequiredRole {}
+
+        #[derive(Clone)]
+        struct RoleMiddleware;
+
+        impl NewMiddleware for RoleMiddleware {
+            type Instance = Self;
+
+            fn new_middleware(&self) -> io::Result<Self> {
+                Ok(self.clone())
+            }
+        }
+
+        impl Middleware for RoleMiddleware {
+            fn call<Chain>(self, state: State, chain: Chain) -> Box<HandlerFuture>
+            where
+                Chain: FnOnce(State) -> Box<HandlerFuture> + Send + 'static,
+            {
+                match state.try_borrow::<RequiredRole>() {
+                    Some(role) if role.0 != "guest" => {
+                        let res = create_empty_response(&state, StatusCode::FORBIDDEN);
+                        Box::new(future::ok((state, res)))
+                    }
+                    _ => chain(state),
+                }
+            }
+        }
+
+        let (chain, pipelines) = single_pipeline(new_pipeline().add(RoleMiddleware).build());
+        let router = build_router(chain, pipelines, |route| {
+            route.get("/").to(|state| (state, "index"));
+            route
+                .get("/admin")
+                .with_extension(RequiredRole("admin"))
+                .to(|state| (state, "admin"));
+            route
+                .get("/guest")
+                .with_extension(RequiredRole("admin"))
+                .with_extension(RequiredRole("guest"))
+                .to(|state| (state, "guest"));
+        });
+
+        let test_server = TestServer::new(router).unwrap();
+        let client = test_server.client();
+        let status = |uri| client.get(uri).perform().unwrap().status();
+
+        assert_eq!(status("http://localhost/"), StatusCode::OK);
+        assert_eq!(status("http://localhost/admin"), StatusCode::FORBIDDEN);
+        assert_eq!(status("http://localhost/guest"), StatusCode::OK);
+    }
+
+    #[test]
+    fn closure_handler_test() {
+        let hits = Arc::new(AtomicUsize::new(0));
+
+        let router = build_simple_router(|route| {
+            let counter = hits.clone();
+            route.get("/count").to(move |state| {
+                let count = counter.fetch_add(1, Ordering::SeqCst) + 1;
+                (state, count.to_string())
+            });
+
+            let counter = hits.clone();
+            route.get("/reset").to_new(move || {
+                let counter = counter.clone();
+                move |state: State| -> Box<HandlerFuture> {
+                    counter.store(0, Ordering::SeqCst);
+                    let res = create_empty_response(&state, StatusCode::NO_CONTENT);
+                    Box::new(future::ok((state, res)))
+                }
+            });
+        });
+
+        let test_server = TestServer::new(router).unwrap();
+        let client = test_server.client();
+        let body = |uri| client.get(uri).perform().unwrap().read_utf8_body().unwrap();
+
+        assert_eq!(body("http://localhost/count"), "1");
+        assert_eq!(body("http://localhost/count"), "2");
+        assert_eq!(body("http://localhost/reset"), "");
+        assert_eq!(body("http://localhost/count"), "1");
+        assert_eq!(hits.load(Ordering::SeqCst), 1);
+    }
 }
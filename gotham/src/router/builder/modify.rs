@@ -106,6 +106,49 @@ where
             node_builder: self.node_builder,
             pipeline_chain: self.pipeline_chain,
             pipelines: self.pipelines,
+            timeout: self.timeout,
+            body_limit: self.body_limit,
+            extensions: self.extensions,
+            response_extenders: self.response_extenders,
+        }
+    }
+}
+
+/// Describes the operation of appending a pipeline to the `PipelineHandleChain` of a route. This
+/// trait exists to remove type clutter from the documentation of
+/// `SingleRouteBuilder::with_pipeline`.
+pub trait ExtendPipelineChain<H> {
+    /// The type returned when appending the pipeline handle to the existing chain.
+    type Output: DefineSingleRoute;
+
+    #[doc(hidden)]
+    /// Appends the pipeline referenced by `handle` to the chain, so that it is invoked after the
+    /// pipelines already in the chain.
+    fn extend_pipeline_chain(self, handle: H) -> Self::Output;
+}
+
+impl<'a, M, C, P, PE, QSE, H> ExtendPipelineChain<H> for SingleRouteBuilder<'a, M, C, P, PE, QSE>
+where
+    M: RouteMatcher + Send + Sync + 'static,
+    C: PipelineHandleChain<P> + Send + Sync + 'static,
+    (H, C): PipelineHandleChain<P> + Send + Sync + 'static,
+    P: RefUnwindSafe + Send + Sync + 'static,
+    PE: PathExtractor<Body> + Send + Sync + 'static,
+    QSE: QueryStringExtractor<Body> + Send + Sync + 'static,
+{
+    type Output = SingleRouteBuilder<'a, M, (H, C), P, PE, QSE>;
+
+    fn extend_pipeline_chain(self, handle: H) -> Self::Output {
+        SingleRouteBuilder {
+            matcher: self.matcher,
+            phantom: self.phantom,
+            node_builder: self.node_builder,
+            pipeline_chain: (handle, self.pipeline_chain),
+            pipelines: self.pipelines,
+            timeout: self.timeout,
+            body_limit: self.body_limit,
+            extensions: self.extensions,
+            response_extenders: self.response_extenders,
         }
     }
 }
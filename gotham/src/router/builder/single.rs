@@ -1,17 +1,30 @@
-use hyper::Body;
+use hyper::{Body, StatusCode};
 
 use std::panic::RefUnwindSafe;
+use std::sync::Arc;
+use std::time::Duration;
 
 use crate::extractor::{PathExtractor, QueryStringExtractor};
-use crate::handler::assets::{DirHandler, FileHandler, FileOptions, FilePathExtractor};
+use crate::handler::assets::{
+    DirHandler, EmbeddedFileHandler, FileHandler, FileOptions, FilePathExtractor,
+};
+use crate::handler::redirect::RedirectHandler;
 use crate::handler::{Handler, NewHandler};
 use crate::pipeline::chain::PipelineHandleChain;
 use crate::router::builder::{
-    ExtendRouteMatcher, ReplacePathExtractor, ReplaceQueryStringExtractor, SingleRouteBuilder,
+    ExtendPipelineChain, ExtendRouteMatcher, ReplacePathExtractor, ReplaceQueryStringExtractor,
+    SingleRouteBuilder,
+};
+use crate::router::response::extender::ResponseExtender;
+use crate::router::route::dispatch::{
+    Dispatcher, DispatcherImpl, ExtensionDispatcher, ResponseExtenderDispatcher, TimeoutDispatcher,
+};
+use crate::router::route::matcher::{
+    ContentTypeHeaderRouteMatcher, PredicateRouteMatcher, QueryStringRouteMatcher, RouteMatcher,
+    RoutePredicate,
 };
-use crate::router::route::dispatch::DispatcherImpl;
-use crate::router::route::matcher::RouteMatcher;
 use crate::router::route::{Delegation, Extractors, RouteImpl};
+use crate::state::StateData;
 
 /// Describes the API for defining a single route, after determining which request paths will be
 /// dispatched here. The API here uses chained function calls to build and add the route into the
@@ -59,8 +72,8 @@ use crate::router::route::{Delegation, Extractors, RouteImpl};
 /// ```
 pub trait DefineSingleRoute {
     /// Directs the route to the given `Handler`, automatically creating a `NewHandler` which
-    /// copies the `Handler`. This is the easiest option for code which is using bare functions as
-    /// `Handler` functions.
+    /// clones the `Handler`. This is the easiest option for code which is using bare functions, or
+    /// closures capturing values which can be cloned, as `Handler` functions.
     ///
     /// # Examples
     ///
@@ -104,7 +117,7 @@ pub trait DefineSingleRoute {
     /// ```
     fn to<H>(self, handler: H)
     where
-        H: Handler + RefUnwindSafe + Copy + Send + Sync + 'static;
+        H: Handler + RefUnwindSafe + Clone + Send + Sync + 'static;
 
     /// Directs the route to the given `NewHandler`. This gives more control over how `Handler`
     /// values are constructed.
@@ -170,6 +183,70 @@ pub trait DefineSingleRoute {
     where
         NH: NewHandler + 'static;
 
+    /// Directs the route to the `Handler` which is returned by the given closure, which is called
+    /// once for each request. This allows a closure to create a `Handler` from values it
+    /// captures, without implementing `NewHandler`.
+    ///
+    /// # Examples
+    ///
+    /// ```rust
+    /// # extern crate gotham;
+    /// # extern crate hyper;
+    /// # extern crate futures;
+    /// # extern crate mime;
+    /// #
+    /// # use std::sync::atomic::{AtomicUsize, Ordering};
+    /// # use std::sync::Arc;
+    /// # use hyper::StatusCode;
+    /// # use futures::future;
+    /// # use gotham::handler::HandlerFuture;
+    /// # use gotham::helpers::http::response::create_response;
+    /// # use gotham::state::State;
+    /// # use gotham::router::Router;
+    /// # use gotham::router::builder::*;
+    /// # use gotham::test::TestServer;
+    /// #
+    /// fn router() -> Router {
+    ///     let hits = Arc::new(AtomicUsize::new(0));
+    ///
+    ///     build_simple_router(|route| {
+    ///         route.get("/hits").to_new(move || {
+    ///             let hits = hits.clone();
+    ///
+    ///             move |state: State| -> Box<HandlerFuture> {
+    ///                 let count = hits.fetch_add(1, Ordering::SeqCst) + 1;
+    ///                 let res = create_response(
+    ///                     &state,
+    ///                     StatusCode::OK,
+    ///                     mime::TEXT_PLAIN,
+    ///                     count.to_string(),
+    ///                 );
+    ///                 Box::new(future::ok((state, res)))
+    ///             }
+    ///         });
+    ///     })
+    /// }
+    /// #
+    /// # fn main() {
+    /// #   let test_server = TestServer::new(router()).unwrap();
+    /// #   for expected in &["1", "2"] {
+    /// #       let response = test_server.client()
+    /// #           .get("https://example.com/hits")
+    /// #           .perform()
+    /// #           .unwrap();
+    /// #       assert_eq!(response.read_utf8_body().unwrap(), *expected);
+    /// #   }
+    /// # }
+    /// ```
+    fn to_new<F, H>(self, factory: F)
+    where
+        Self: Sized,
+        F: Fn() -> H + Send + Sync + RefUnwindSafe + 'static,
+        H: Handler + Send + 'static,
+    {
+        self.to_new_handler(move || Ok(factory()))
+    }
+
     /// Directs the route to serve static files from the given root directory.
     /// The route must contain a trailing glob segment, which will be used
     /// to serve any matching names under the given path.
@@ -262,6 +339,93 @@ pub trait DefineSingleRoute {
         self.to_new_handler(FileHandler::new(options));
     }
 
+    /// Directs the route to respond with a redirect to the given `location`, using the given
+    /// redirection `status`. The query string of the request is kept, unless the `location`
+    /// includes its own.
+    ///
+    /// # Panics
+    ///
+    /// When the `status` isn't a redirection (`3xx`) status, or the `location` isn't a valid
+    /// header value.
+    ///
+    /// # Examples
+    ///
+    /// ```rust
+    /// # extern crate gotham;
+    /// # extern crate hyper;
+    /// #
+    /// # use hyper::StatusCode;
+    /// # use hyper::header::LOCATION;
+    /// # use gotham::router::Router;
+    /// # use gotham::router::builder::*;
+    /// # use gotham::test::TestServer;
+    /// #
+    /// # fn router() -> Router {
+    /// build_simple_router(|route| {
+    ///     route
+    ///         .get("/old-path")
+    ///         .redirect_to("/new-path", StatusCode::MOVED_PERMANENTLY);
+    /// })
+    /// # }
+    /// #
+    /// # fn main() {
+    /// #   let test_server = TestServer::new(router()).unwrap();
+    /// #   let response = test_server.client()
+    /// #       .get("https://example.com/old-path?page=2")
+    /// #       .perform()
+    /// #       .unwrap();
+    /// #   assert_eq!(response.status(), StatusCode::MOVED_PERMANENTLY);
+    /// #   assert_eq!(response.headers()[LOCATION], "/new-path?page=2");
+    /// # }
+    /// ```
+    fn redirect_to(self, location: &str, status: StatusCode)
+    where
+        Self: Sized,
+    {
+        self.to_new_handler(RedirectHandler::new(location, status));
+    }
+
+    /// Directs the route to serve static files which are embedded in the binary. The route must
+    /// contain a trailing glob segment, which will be used to find the embedded file to serve.
+    ///
+    /// # Examples
+    ///
+    /// ```rust
+    /// # extern crate gotham;
+    /// # extern crate hyper;
+    /// #
+    /// # use hyper::StatusCode;
+    /// # use gotham::handler::assets::EmbeddedFileHandler;
+    /// # use gotham::router::Router;
+    /// # use gotham::router::builder::*;
+    /// # use gotham::test::TestServer;
+    /// #
+    /// # fn router() -> Router {
+    /// build_simple_router(|route| {
+    ///     route.get("/*").to_embedded(
+    ///         EmbeddedFileHandler::new().with_file("doc.html", b"<html>I am a doc.</html>"),
+    ///     );
+    /// })
+    /// # }
+    /// #
+    /// # fn main() {
+    /// #   let test_server = TestServer::new(router()).unwrap();
+    /// #   let response = test_server.client()
+    /// #       .get("https://example.com/doc.html")
+    /// #       .perform()
+    /// #       .unwrap();
+    /// #   assert_eq!(response.status(), StatusCode::OK);
+    /// # }
+    /// ```
+    fn to_embedded(self, handler: EmbeddedFileHandler)
+    where
+        Self: ReplacePathExtractor<FilePathExtractor> + Sized,
+        Self::Output: DefineSingleRoute,
+    {
+        self.with_path_extractor::<FilePathExtractor>()
+            .to_new_handler(handler);
+    }
+
     /// Applies a `PathExtractor` type to the current route, to extract path parameters into
     /// `State` with the given type.
     ///
@@ -452,6 +616,532 @@ pub trait DefineSingleRoute {
         NRM: RouteMatcher + Send + Sync + 'static,
         Self: ExtendRouteMatcher<NRM>,
         Self::Output: DefineSingleRoute;
+
+    /// Requires the query string of requests to have the parameter `name` with the given `value`
+    /// for the current route to match, using a `QueryStringRouteMatcher`. Routes for the same path
+    /// are tried in the order they were defined, so this allows a handler to be selected by query
+    /// string when defined before the route which handles other requests.
+    ///
+    /// ```
+    /// # extern crate gotham;
+    /// # extern crate hyper;
+    /// #
+    /// # use hyper::{Body, Response, StatusCode};
+    /// # use gotham::state::State;
+    /// # use gotham::router::Router;
+    /// # use gotham::router::builder::*;
+    /// # use gotham::test::TestServer;
+    /// #
+    /// # fn csv_report(state: State) -> (State, Response<Body>) {
+    /// #   (state, Response::builder().status(StatusCode::ACCEPTED).body(Body::empty()).unwrap())
+    /// # }
+    /// #
+    /// # fn report(state: State) -> (State, Response<Body>) {
+    /// #   (state, Response::builder().status(StatusCode::OK).body(Body::empty()).unwrap())
+    /// # }
+    /// #
+    /// # fn router() -> Router {
+    /// build_simple_router(|route| {
+    ///     route.get("/reports/:id")
+    ///          .when_query("format", "csv")
+    ///          .to(csv_report);
+    ///
+    ///     route.get("/reports/:id").to(report);
+    /// })
+    /// # }
+    /// #
+    /// # fn main() {
+    /// #   let test_server = TestServer::new(router()).unwrap();
+    /// #
+    /// #   let response = test_server.client()
+    /// #       .get("https://example.com/reports/1?format=csv")
+    /// #       .perform()
+    /// #       .unwrap();
+    /// #   assert_eq!(response.status(), StatusCode::ACCEPTED);
+    /// #
+    /// #   let response = test_server.client()
+    /// #       .get("https://example.com/reports/1?format=json")
+    /// #       .perform()
+    /// #       .unwrap();
+    /// #   assert_eq!(response.status(), StatusCode::OK);
+    /// # }
+    /// ```
+    fn when_query(
+        self,
+        name: &str,
+        value: &str,
+    ) -> <Self as ExtendRouteMatcher<QueryStringRouteMatcher>>::Output
+    where
+        Self: ExtendRouteMatcher<QueryStringRouteMatcher> + Sized,
+        Self::Output: DefineSingleRoute,
+    {
+        self.add_route_matcher(QueryStringRouteMatcher::new(name, value))
+    }
+
+    /// Requires the query string of requests to have the parameter `name`, with any value or none,
+    /// for the current route to match. See `when_query` for the way routes are selected.
+    fn when_query_present(
+        self,
+        name: &str,
+    ) -> <Self as ExtendRouteMatcher<QueryStringRouteMatcher>>::Output
+    where
+        Self: ExtendRouteMatcher<QueryStringRouteMatcher> + Sized,
+        Self::Output: DefineSingleRoute,
+    {
+        self.add_route_matcher(QueryStringRouteMatcher::present(name))
+    }
+
+    /// Requires the `predicate` to find the current route eligible to handle requests for it to
+    /// match, using a `PredicateRouteMatcher`. A predicate may be a closure taking `&State`, or
+    /// any other `RoutePredicate`. See `when_query` for the way routes are selected.
+    ///
+    /// ```
+    /// # extern crate gotham;
+    /// # extern crate hyper;
+    /// #
+    /// # use hyper::{Body, Response, StatusCode};
+    /// # use hyper::header::{HeaderMap, HeaderValue};
+    /// # use gotham::state::{FromState, State};
+    /// # use gotham::router::Router;
+    /// # use gotham::router::builder::*;
+    /// # use gotham::test::TestServer;
+    /// #
+    /// # fn new_checkout(state: State) -> (State, Response<Body>) {
+    /// #   (state, Response::builder().status(StatusCode::ACCEPTED).body(Body::empty()).unwrap())
+    /// # }
+    /// #
+    /// # fn checkout(state: State) -> (State, Response<Body>) {
+    /// #   (state, Response::builder().status(StatusCode::OK).body(Body::empty()).unwrap())
+    /// # }
+    /// #
+    /// fn in_beta(state: &State) -> bool {
+    ///     HeaderMap::borrow_from(state)
+    ///         .get("x-beta")
+    ///         .map(|value| value == "1")
+    ///         .unwrap_or(false)
+    /// }
+    ///
+    /// # fn router() -> Router {
+    /// build_simple_router(|route| {
+    ///     route.get("/checkout").when(in_beta).to(new_checkout);
+    ///     route.get("/checkout").to(checkout);
+    /// })
+    /// # }
+    /// #
+    /// # fn main() {
+    /// #   let test_server = TestServer::new(router()).unwrap();
+    /// #
+    /// #   let response = test_server.client()
+    /// #       .get("https://example.com/checkout")
+    /// #       .with_header("x-beta", HeaderValue::from_static("1"))
+    /// #       .perform()
+    /// #       .unwrap();
+    /// #   assert_eq!(response.status(), StatusCode::ACCEPTED);
+    /// #
+    /// #   let response = test_server.client()
+    /// #       .get("https://example.com/checkout")
+    /// #       .perform()
+    /// #       .unwrap();
+    /// #   assert_eq!(response.status(), StatusCode::OK);
+    /// # }
+    /// ```
+    fn when<RP>(self, predicate: RP) -> <Self as ExtendRouteMatcher<PredicateRouteMatcher>>::Output
+    where
+        RP: RoutePredicate + 'static,
+        Self: ExtendRouteMatcher<PredicateRouteMatcher> + Sized,
+        Self::Output: DefineSingleRoute,
+    {
+        self.add_route_matcher(PredicateRouteMatcher::new(predicate))
+    }
+
+    /// Requires requests to have a `Content-Type` of one of the `media_types` for the current
+    /// route to match, as determined by `ContentTypeHeaderRouteMatcher`. This allows requests to
+    /// the same path and method to be dispatched to different handlers by the type of their body.
+    /// Where no route accepts the `Content-Type` of a request, it's answered with
+    /// `415 Unsupported Media Type`.
+    ///
+    /// ```
+    /// # extern crate gotham;
+    /// # extern crate hyper;
+    /// # extern crate mime;
+    /// #
+    /// # use hyper::StatusCode;
+    /// # use gotham::state::State;
+    /// # use gotham::router::Router;
+    /// # use gotham::router::builder::*;
+    /// # use gotham::test::TestServer;
+    /// #
+    /// # fn create_document(state: State) -> (State, &'static str) {
+    /// #   (state, "json")
+    /// # }
+    /// #
+    /// # fn upload_document(state: State) -> (State, &'static str) {
+    /// #   (state, "upload")
+    /// # }
+    /// #
+    /// # fn router() -> Router {
+    /// build_simple_router(|route| {
+    ///     route
+    ///         .post("/documents")
+    ///         .when_content_type(vec![mime::APPLICATION_JSON])
+    ///         .to(create_document);
+    ///
+    ///     route
+    ///         .post("/documents")
+    ///         .when_content_type(vec![mime::MULTIPART_FORM_DATA])
+    ///         .to(upload_document);
+    /// })
+    /// # }
+    /// #
+    /// # fn main() {
+    /// #   let test_server = TestServer::new(router()).unwrap();
+    /// #
+    /// #   let response = test_server.client()
+    /// #       .post("https://example.com/documents", "{}", mime::APPLICATION_JSON)
+    /// #       .perform()
+    /// #       .unwrap();
+    /// #   assert_eq!(response.read_utf8_body().unwrap(), "json");
+    /// #
+    /// #   let content_type = "multipart/form-data; boundary=X-BOUNDARY";
+    /// #   let response = test_server.client()
+    /// #       .post("https://example.com/documents", "", content_type.parse().unwrap())
+    /// #       .perform()
+    /// #       .unwrap();
+    /// #   assert_eq!(response.read_utf8_body().unwrap(), "upload");
+    /// #
+    /// #   let response = test_server.client()
+    /// #       .post("https://example.com/documents", "", mime::TEXT_PLAIN)
+    /// #       .perform()
+    /// #       .unwrap();
+    /// #   assert_eq!(response.status(), StatusCode::UNSUPPORTED_MEDIA_TYPE);
+    /// # }
+    /// ```
+    fn when_content_type(
+        self,
+        media_types: Vec<mime::Mime>,
+    ) -> <Self as ExtendRouteMatcher<ContentTypeHeaderRouteMatcher>>::Output
+    where
+        Self: ExtendRouteMatcher<ContentTypeHeaderRouteMatcher> + Sized,
+        Self::Output: DefineSingleRoute,
+    {
+        self.add_route_matcher(ContentTypeHeaderRouteMatcher::new(media_types))
+    }
+
+    /// Appends a pipeline to those which are invoked for the current route, so that its
+    /// middleware runs after the pipelines of the enclosing router or scope, and before the
+    /// handler. The pipeline is referenced by the handle returned when it was added to the
+    /// `PipelineSet` of the router.
+    ///
+    /// # Examples
+    ///
+    /// ```rust
+    /// # extern crate gotham;
+    /// # extern crate hyper;
+    /// # #[macro_use]
+    /// # extern crate serde_derive;
+    /// #
+    /// # use hyper::{Body, Response, StatusCode};
+    /// # use gotham::state::State;
+    /// # use gotham::middleware::session::{NewSessionMiddleware, SessionData};
+    /// # use gotham::router::Router;
+    /// # use gotham::router::builder::*;
+    /// # use gotham::pipeline::new_pipeline;
+    /// # use gotham::pipeline::set::{finalize_pipeline_set, new_pipeline_set};
+    /// # use gotham::test::TestServer;
+    /// #
+    /// # #[derive(Default, Serialize, Deserialize)]
+    /// # struct AdminSession;
+    /// #
+    /// fn admin_handler(state: State) -> (State, Response<Body>) {
+    ///     // Handler implementation elided.
+    /// #   assert!(state.has::<SessionData<AdminSession>>());
+    /// #   (state, Response::builder().status(StatusCode::ACCEPTED).body(Body::empty()).unwrap())
+    /// }
+    ///
+    /// fn public_handler(state: State) -> (State, Response<Body>) {
+    ///     // Handler implementation elided.
+    /// #   assert!(!state.has::<SessionData<AdminSession>>());
+    /// #   (state, Response::builder().status(StatusCode::ACCEPTED).body(Body::empty()).unwrap())
+    /// }
+    ///
+    /// # fn router() -> Router {
+    /// let (pipelines, admin) = new_pipeline_set().add(
+    ///     new_pipeline()
+    ///         .add(NewSessionMiddleware::default().with_session_type::<AdminSession>())
+    ///         .build()
+    /// );
+    /// let pipelines = finalize_pipeline_set(pipelines);
+    ///
+    /// build_router((), pipelines, |route| {
+    ///     route.get("/admin").with_pipeline(admin).to(admin_handler);
+    ///     route.get("/").to(public_handler);
+    /// })
+    /// # }
+    /// #
+    /// # fn main() {
+    /// #   let test_server = TestServer::new(router()).unwrap();
+    /// #
+    /// #   for path in &["https://example.com/admin", "https://example.com/"] {
+    /// #       let response = test_server.client().get(*path).perform().unwrap();
+    /// #       assert_eq!(response.status(), StatusCode::ACCEPTED);
+    /// #   }
+    /// # }
+    /// ```
+    fn with_pipeline<H>(self, handle: H) -> <Self as ExtendPipelineChain<H>>::Output
+    where
+        Self: ExtendPipelineChain<H>,
+        Self::Output: DefineSingleRoute;
+
+    /// Names the current route, so that its path can be generated by `UrlFor`. Routes for
+    /// different methods on the same path may share a name.
+    ///
+    /// # Panics
+    ///
+    /// When the `Router` is built, if the same name was given to routes with different paths.
+    ///
+    /// # Examples
+    ///
+    /// ```rust
+    /// # extern crate gotham;
+    /// # extern crate hyper;
+    /// #
+    /// # use hyper::{Body, Response, StatusCode};
+    /// # use gotham::state::{FromState, State};
+    /// # use gotham::router::Router;
+    /// # use gotham::router::builder::*;
+    /// # use gotham::router::url_for::UrlFor;
+    /// # use gotham::test::TestServer;
+    /// #
+    /// fn my_handler(state: State) -> (State, Response<Body>) {
+    ///     let path = UrlFor::borrow_from(&state).path("about", &()).unwrap();
+    ///     assert_eq!(path, "/about/us");
+    /// #   (state, Response::builder().status(StatusCode::ACCEPTED).body(Body::empty()).unwrap())
+    /// }
+    ///
+    /// # fn router() -> Router {
+    /// build_simple_router(|route| {
+    ///     route.get("/about/us").name("about").to(my_handler);
+    /// })
+    /// # }
+    /// #
+    /// # fn main() {
+    /// #   let test_server = TestServer::new(router()).unwrap();
+    /// #   let response = test_server.client()
+    /// #       .get("https://example.com/about/us")
+    /// #       .perform()
+    /// #       .unwrap();
+    /// #   assert_eq!(response.status(), StatusCode::ACCEPTED);
+    /// # }
+    /// ```
+    fn name(self, name: &str) -> Self;
+
+    /// Limits the time taken to serve requests to the current route, including its pipelines.
+    /// Where the response isn't ready within the `timeout`, the work of serving the request is
+    /// dropped, and a `503 Service Unavailable` response is sent instead. The response can be
+    /// customised with `RouterBuilder::add_response_extender`.
+    ///
+    /// The `State` given to the response extender only contains the request method, URI,
+    /// version and headers, as the `State` of the timed out request is dropped with it. A handler
+    /// which blocks its thread can't be interrupted, so the timeout relies on the handler
+    /// returning a future which yields while it waits.
+    ///
+    /// # Examples
+    ///
+    /// ```rust
+    /// # extern crate futures;
+    /// # extern crate gotham;
+    /// # extern crate hyper;
+    /// # extern crate tokio;
+    /// #
+    /// # use std::time::{Duration, Instant};
+    /// #
+    /// # use futures::Future;
+    /// # use hyper::{Body, Response, StatusCode};
+    /// # use tokio::timer::Delay;
+    /// # use gotham::handler::{HandlerFuture, IntoHandlerError};
+    /// # use gotham::state::State;
+    /// # use gotham::router::Router;
+    /// # use gotham::router::builder::*;
+    /// # use gotham::test::TestServer;
+    /// #
+    /// fn slow_report(state: State) -> Box<HandlerFuture> {
+    ///     let f = Delay::new(Instant::now() + Duration::from_secs(10)).then(|result| match result {
+    ///         Ok(()) => Ok((state, Response::new(Body::from("report")))),
+    ///         Err(e) => Err((state, e.into_handler_error())),
+    ///     });
+    ///
+    ///     Box::new(f)
+    /// }
+    ///
+    /// # fn router() -> Router {
+    /// build_simple_router(|route| {
+    ///     route
+    ///         .get("/reports")
+    ///         .with_timeout(Duration::from_millis(50))
+    ///         .to(slow_report);
+    /// })
+    /// # }
+    /// #
+    /// # fn main() {
+    /// #   let test_server = TestServer::new(router()).unwrap();
+    /// #   let response = test_server.client()
+    /// #       .get("https://example.com/reports")
+    /// #       .perform()
+    /// #       .unwrap();
+    /// #   assert_eq!(response.status(), StatusCode::SERVICE_UNAVAILABLE);
+    /// # }
+    /// ```
+    fn with_timeout(self, timeout: Duration) -> Self;
+
+    /// Limits the size of request bodies for the current route to `limit` bytes, in place of any
+    /// limit of the scope it's defined in, as set by `ScopeBuilder::body_limit`.
+    ///
+    /// A request declaring a larger `Content-Length` is answered with `413 Payload Too Large`
+    /// before the pipelines and handler of the route are invoked. A body without a declared
+    /// length is counted as it's read instead, and reading fails once it exceeds the limit. The
+    /// error of a handler which then fails is given the `413 Payload Too Large` status.
+    ///
+    /// # Examples
+    ///
+    /// ```rust
+    /// # extern crate gotham;
+    /// # extern crate hyper;
+    /// # extern crate mime;
+    /// #
+    /// # use hyper::StatusCode;
+    /// # use gotham::state::State;
+    /// # use gotham::router::Router;
+    /// # use gotham::router::builder::*;
+    /// # use gotham::test::TestServer;
+    /// #
+    /// # fn upload(state: State) -> (State, &'static str) {
+    /// #   (state, "uploaded")
+    /// # }
+    /// #
+    /// # fn router() -> Router {
+    /// build_simple_router(|route| {
+    ///     route.post("/avatar").with_body_limit(16).to(upload);
+    /// })
+    /// # }
+    /// #
+    /// # fn main() {
+    /// #   let test_server = TestServer::new(router()).unwrap();
+    /// #   let response = test_server.client()
+    /// #       .post("https://example.com/avatar", vec![0; 32], mime::IMAGE_PNG)
+    /// #       .perform()
+    /// #       .unwrap();
+    /// #   assert_eq!(response.status(), StatusCode::PAYLOAD_TOO_LARGE);
+    /// # }
+    /// ```
+    fn with_body_limit(self, limit: u64) -> Self;
+
+    /// Attaches a value to the current route, which is put into `State` whenever a request is
+    /// dispatched to the route, before the pipelines of the route are invoked. This allows
+    /// middleware to apply per-route policies, such as the scopes required to access a route,
+    /// which are declared alongside the route itself.
+    ///
+    /// A clone of the value is put into `State` for each request. Where several values of the
+    /// same type are attached, the last one is used.
+    ///
+    /// # Examples
+    ///
+    /// ```rust
+    /// # extern crate gotham;
+    /// # #[macro_use]
+    /// # extern crate gotham_derive;
+    /// # extern crate hyper;
+    /// #
+    /// # use hyper::{Body, Response, StatusCode};
+    /// # use gotham::helpers::http::response::create_empty_response;
+    /// # use gotham::state::{FromState, State};
+    /// # use gotham::router::Router;
+    /// # use gotham::router::builder::*;
+    /// # use gotham::test::TestServer;
+    /// #
+    /// #[derive(Clone, StateData)]
+    /// struct RequiredScope(&'static str);
+    ///
+    /// fn my_handler(state: State) -> (State, Response<Body>) {
+    ///     // Typically read by middleware, such as to authorize the request.
+    ///     assert_eq!(RequiredScope::borrow_from(&state).0, "reports:write");
+    /// #   let res = create_empty_response(&state, StatusCode::ACCEPTED);
+    /// #   (state, res)
+    /// }
+    ///
+    /// # fn router() -> Router {
+    /// build_simple_router(|route| {
+    ///     route
+    ///         .post("/reports")
+    ///         .with_extension(RequiredScope("reports:write"))
+    ///         .to(my_handler);
+    /// })
+    /// # }
+    /// #
+    /// # fn main() {
+    /// #   let test_server = TestServer::new(router()).unwrap();
+    /// #   let response = test_server.client()
+    /// #       .post("https://example.com/reports", "", mime::TEXT_PLAIN)
+    /// #       .perform()
+    /// #       .unwrap();
+    /// #   assert_eq!(response.status(), StatusCode::ACCEPTED);
+    /// # }
+    /// ```
+    fn with_extension<T>(self, value: T) -> Self
+    where
+        T: StateData + Clone + Sync + RefUnwindSafe;
+
+    /// Adds a `ResponseExtender` for responses from the current route with the given
+    /// `StatusCode`, such as to customise the body of its `404 Not Found` or `503 Service
+    /// Unavailable` responses. Errors from the handler with the status code are converted into
+    /// their response, so that it can be extended.
+    ///
+    /// The extender is used in place of those added for the same `StatusCode` by an enclosing
+    /// scope, or by `RouterBuilder::add_response_extender`.
+    ///
+    /// # Examples
+    ///
+    /// ```rust
+    /// # extern crate gotham;
+    /// # extern crate hyper;
+    /// #
+    /// # use hyper::{Body, Response, StatusCode};
+    /// # use gotham::helpers::http::response::create_empty_response;
+    /// # use gotham::state::State;
+    /// # use gotham::router::Router;
+    /// # use gotham::router::builder::*;
+    /// # use gotham::test::TestServer;
+    /// #
+    /// fn missing_report(state: State) -> (State, Response<Body>) {
+    ///     let res = create_empty_response(&state, StatusCode::NOT_FOUND);
+    ///     (state, res)
+    /// }
+    ///
+    /// fn describe_missing_report(_state: &mut State, res: &mut Response<Body>) {
+    ///     *res.body_mut() = Body::from("no such report");
+    /// }
+    ///
+    /// # fn router() -> Router {
+    /// build_simple_router(|route| {
+    ///     route
+    ///         .get("/reports/:id")
+    ///         .add_response_extender(StatusCode::NOT_FOUND, describe_missing_report)
+    ///         .to(missing_report);
+    /// })
+    /// # }
+    /// #
+    /// # fn main() {
+    /// #   let test_server = TestServer::new(router()).unwrap();
+    /// #   let response = test_server.client()
+    /// #       .get("https://example.com/reports/1")
+    /// #       .perform()
+    /// #       .unwrap();
+    /// #   assert_eq!(response.status(), StatusCode::NOT_FOUND);
+    /// #   assert_eq!(response.read_utf8_body().unwrap(), "no such report");
+    /// # }
+    /// ```
+    fn add_response_extender<E>(self, status_code: StatusCode, extender: E) -> Self
+    where
+        E: ResponseExtender<Body> + Send + Sync + 'static;
 }
 
 impl<'a, M, C, P, PE, QSE> DefineSingleRoute for SingleRouteBuilder<'a, M, C, P, PE, QSE>
@@ -464,9 +1154,9 @@ where
 {
     fn to<H>(self, handler: H)
     where
-        H: Handler + RefUnwindSafe + Copy + Send + Sync + 'static,
+        H: Handler + RefUnwindSafe + Clone + Send + Sync + 'static,
     {
-        self.to_new_handler(move || Ok(handler))
+        self.to_new_handler(move || Ok(handler.clone()))
     }
 
     fn to_new_handler<NH>(self, new_handler: NH)
@@ -474,12 +1164,28 @@ where
         NH: NewHandler + 'static,
     {
         let dispatcher = DispatcherImpl::new(new_handler, self.pipeline_chain, self.pipelines);
-        let route: RouteImpl<M, PE, QSE> = RouteImpl::new(
+        let mut dispatcher: Box<dyn Dispatcher + Send + Sync> = match self.timeout {
+            Some(timeout) => Box::new(TimeoutDispatcher::new(dispatcher, timeout)),
+            None => Box::new(dispatcher),
+        };
+        if !self.extensions.is_empty() {
+            dispatcher = Box::new(ExtensionDispatcher::new(dispatcher, self.extensions));
+        }
+        if !self.response_extenders.is_empty() {
+            dispatcher = Box::new(ResponseExtenderDispatcher::new(
+                dispatcher,
+                self.response_extenders,
+            ));
+        }
+        let mut route: RouteImpl<M, PE, QSE> = RouteImpl::new(
             self.matcher,
-            Box::new(dispatcher),
+            dispatcher,
             Extractors::new(),
             Delegation::Internal,
         );
+        if let Some(limit) = self.body_limit {
+            route = route.with_body_limit(limit);
+        }
         self.node_builder.add_route(Box::new(route));
     }
 
@@ -505,4 +1211,42 @@ where
     {
         self.extend_route_matcher(matcher)
     }
+
+    fn with_pipeline<H>(self, handle: H) -> <Self as ExtendPipelineChain<H>>::Output
+    where
+        Self: ExtendPipelineChain<H>,
+    {
+        self.extend_pipeline_chain(handle)
+    }
+
+    fn name(self, name: &str) -> Self {
+        self.node_builder.add_name(name);
+        self
+    }
+
+    fn with_timeout(mut self, timeout: Duration) -> Self {
+        self.timeout = Some(timeout);
+        self
+    }
+
+    fn with_body_limit(mut self, limit: u64) -> Self {
+        self.body_limit = Some(limit);
+        self
+    }
+
+    fn with_extension<T>(mut self, value: T) -> Self
+    where
+        T: StateData + Clone + Sync + RefUnwindSafe,
+    {
+        self.extensions.add(value);
+        self
+    }
+
+    fn add_response_extender<E>(mut self, status_code: StatusCode, extender: E) -> Self
+    where
+        E: ResponseExtender<Body> + Send + Sync + 'static,
+    {
+        self.response_extenders.add(status_code, Arc::new(extender));
+        self
+    }
 }
@@ -0,0 +1,540 @@
+//! Defines a `Router` which is built from a configuration file, rather than in code.
+//!
+//! This allows simple routes, such as static assets and redirects, to be changed without
+//! recompiling. The routes are described by a `RouterConfig`, which can be read from TOML using
+//! `RouterConfig::from_toml`, or from any other format supported by `serde`. Handlers and
+//! pipelines are defined in code, and referred to by name.
+//!
+//! ```toml
+//! [[routes]]
+//! path = "/assets/*"
+//! dir = "public"
+//!
+//! [[routes]]
+//! path = "/favicon.ico"
+//! file = "public/favicon.ico"
+//!
+//! [[routes]]
+//! path = "/blog"
+//! redirect = "https://blog.example.com"
+//! status = 301
+//!
+//! [[routes]]
+//! path = "/status"
+//! methods = ["GET"]
+//! handler = "status"
+//! pipeline = "api"
+//! ```
+//!
+//! Every route has a `path`, and exactly one of:
+//!
+//! * `dir`, to serve the files of a directory, where the path ends with a glob;
+//! * `file`, to serve a single file;
+//! * `redirect`, to redirect to another location, with the `status` defaulting to
+//!   `308 Permanent Redirect`;
+//! * `handler`, to dispatch to a handler registered with `ConfigLoader::handler`.
+//!
+//! Routes match `GET` and `HEAD` requests unless `methods` are given, and are dispatched through
+//! the pipeline chain of the `ConfigLoader` unless a `pipeline` registered with
+//! `ConfigLoader::pipeline` is named.
+
+use std::collections::HashMap;
+use std::error::Error;
+use std::fmt::{self, Display};
+use std::panic::RefUnwindSafe;
+use std::sync::Arc;
+
+use hyper::header::HeaderValue;
+use hyper::{Method, StatusCode};
+use serde_derive::Deserialize;
+
+use crate::handler::{Handler, HandlerFuture, NewHandler};
+use crate::pipeline::chain::PipelineHandleChain;
+use crate::pipeline::set::{finalize_pipeline_set, new_pipeline_set, PipelineSet};
+use crate::router::builder::{build_router, DefineSingleRoute, DrawRoutes, RouterBuilder};
+use crate::router::Router;
+use crate::state::State;
+
+/// The routes of a `Router`, as read from a configuration file. These are built into a `Router`
+/// by `ConfigLoader::load`.
+#[derive(Clone, Debug, Default, Deserialize, PartialEq)]
+#[serde(deny_unknown_fields)]
+pub struct RouterConfig {
+    #[serde(default)]
+    routes: Vec<RouteConfig>,
+}
+
+/// A single route within a `RouterConfig`.
+#[derive(Clone, Debug, Deserialize, PartialEq)]
+#[serde(deny_unknown_fields)]
+struct RouteConfig {
+    path: String,
+    #[serde(default)]
+    methods: Vec<String>,
+    pipeline: Option<String>,
+    dir: Option<String>,
+    file: Option<String>,
+    redirect: Option<String>,
+    status: Option<u16>,
+    handler: Option<String>,
+}
+
+impl RouterConfig {
+    /// Reads a `RouterConfig` from TOML, as described by the module documentation.
+    pub fn from_toml(config: &str) -> Result<RouterConfig, ConfigError> {
+        toml::from_str(config).map_err(|e| ConfigError::Parse(e.to_string()))
+    }
+}
+
+/// The reason that a `Router` couldn't be built from a `RouterConfig`.
+#[derive(Debug, PartialEq)]
+pub enum ConfigError {
+    /// The configuration couldn't be parsed.
+    Parse(String),
+
+    /// The route for the path doesn't have exactly one of `dir`, `file`, `redirect` or `handler`.
+    InvalidTarget(String),
+
+    /// The method isn't a valid HTTP method.
+    InvalidMethod(String),
+
+    /// The redirect for the path has a location which isn't a valid header value, or a status
+    /// which isn't a redirection.
+    InvalidRedirect(String),
+
+    /// No handler was registered with the name.
+    UnknownHandler(String),
+
+    /// No pipeline was registered with the name.
+    UnknownPipeline(String),
+}
+
+impl Display for ConfigError {
+    fn fmt(&self, out: &mut fmt::Formatter) -> fmt::Result {
+        match *self {
+            ConfigError::Parse(ref message) => write!(out, "invalid configuration: {}", message),
+            ConfigError::InvalidTarget(ref path) => write!(
+                out,
+                "route for `{}` needs exactly one of `dir`, `file`, `redirect` or `handler`",
+                path
+            ),
+            ConfigError::InvalidMethod(ref method) => write!(out, "invalid method `{}`", method),
+            ConfigError::InvalidRedirect(ref path) => {
+                write!(out, "invalid redirect for `{}`", path)
+            }
+            ConfigError::UnknownHandler(ref name) => write!(out, "no handler named `{}`", name),
+            ConfigError::UnknownPipeline(ref name) => write!(out, "no pipeline named `{}`", name),
+        }
+    }
+}
+
+impl Error for ConfigError {}
+
+type BoxedHandler = Box<dyn FnOnce(State) -> Box<HandlerFuture> + Send>;
+
+/// A handler registered with `ConfigLoader::handler`, which creates a boxed `Handler` for each
+/// request so that handlers of different types can be named by the configuration.
+#[derive(Clone)]
+struct NamedHandler {
+    new_handler: Arc<dyn Fn() -> BoxedHandler + Send + Sync + RefUnwindSafe>,
+}
+
+impl NewHandler for NamedHandler {
+    type Instance = BoxedHandler;
+
+    fn new_handler(&self) -> crate::error::Result<Self::Instance> {
+        Ok((self.new_handler)())
+    }
+}
+
+/// What a route of the configuration dispatches to.
+enum Target {
+    Dir(String),
+    File(String),
+    Redirect(String, StatusCode),
+    Handler(NamedHandler),
+}
+
+/// A route of the configuration, once its names have been resolved.
+struct ConfiguredRoute {
+    path: String,
+    methods: Vec<Method>,
+    pipeline: Option<String>,
+    target: Target,
+}
+
+impl ConfiguredRoute {
+    fn draw<D, C, P>(&self, route: &mut D)
+    where
+        D: DrawRoutes<C, P>,
+        C: PipelineHandleChain<P> + Copy + Send + Sync + 'static,
+        P: RefUnwindSafe + Send + Sync + 'static,
+    {
+        let builder = route.request(self.methods.clone(), &self.path);
+        match self.target {
+            Target::Dir(ref dir) => builder.to_dir(dir.as_str()),
+            Target::File(ref file) => builder.to_file(file.as_str()),
+            Target::Redirect(ref location, status) => builder.redirect_to(location, status),
+            Target::Handler(ref handler) => builder.to_new_handler(handler.clone()),
+        }
+    }
+}
+
+type DrawWithPipeline<C, P> = Box<dyn Fn(&mut RouterBuilder<C, P>, &ConfiguredRoute)>;
+
+/// Builds a `Router` from a `RouterConfig`, using the handlers and pipelines which the
+/// configuration refers to by name.
+///
+/// # Examples
+///
+/// ```rust
+/// # extern crate gotham;
+/// # extern crate hyper;
+/// #
+/// # use hyper::StatusCode;
+/// # use hyper::header::LOCATION;
+/// # use gotham::router::config::{ConfigLoader, RouterConfig};
+/// # use gotham::state::State;
+/// # use gotham::test::TestServer;
+/// #
+/// fn status(state: State) -> (State, &'static str) {
+///     (state, "ok")
+/// }
+///
+/// # fn main() {
+/// let config = RouterConfig::from_toml(
+///     r#"
+///     [[routes]]
+///     path = "/status"
+///     handler = "status"
+///
+///     [[routes]]
+///     path = "/old"
+///     redirect = "/new"
+///     "#,
+/// )
+/// .unwrap();
+///
+/// let mut loader = ConfigLoader::new();
+/// loader.handler("status", status);
+/// let router = loader.load(&config).unwrap();
+/// #
+/// # let test_server = TestServer::new(router).unwrap();
+/// # let response = test_server.client()
+/// #     .get("https://example.com/status")
+/// #     .perform()
+/// #     .unwrap();
+/// # assert_eq!(response.status(), StatusCode::OK);
+/// # let response = test_server.client()
+/// #     .get("https://example.com/old")
+/// #     .perform()
+/// #     .unwrap();
+/// # assert_eq!(response.status(), StatusCode::PERMANENT_REDIRECT);
+/// # assert_eq!(response.headers()[LOCATION], "/new");
+/// # }
+/// ```
+pub struct ConfigLoader<C, P>
+where
+    C: PipelineHandleChain<P> + Copy + Send + Sync + 'static,
+    P: RefUnwindSafe + Send + Sync + 'static,
+{
+    pipeline_chain: C,
+    pipelines: PipelineSet<P>,
+    handlers: HashMap<String, NamedHandler>,
+    named_pipelines: HashMap<String, DrawWithPipeline<C, P>>,
+}
+
+impl ConfigLoader<(), ()> {
+    /// Creates a `ConfigLoader` whose routes are dispatched without any middleware, unless a
+    /// pipeline is named by the configuration.
+    pub fn new() -> Self {
+        ConfigLoader::with_pipelines((), finalize_pipeline_set(new_pipeline_set()))
+    }
+}
+
+impl Default for ConfigLoader<(), ()> {
+    fn default() -> Self {
+        ConfigLoader::new()
+    }
+}
+
+impl<C, P> ConfigLoader<C, P>
+where
+    C: PipelineHandleChain<P> + Copy + Send + Sync + 'static,
+    P: RefUnwindSafe + Send + Sync + 'static,
+{
+    /// Creates a `ConfigLoader` whose routes are dispatched through the given pipeline chain,
+    /// in the same way as `build_router`, unless a pipeline is named by the configuration.
+    pub fn with_pipelines(pipeline_chain: C, pipelines: PipelineSet<P>) -> Self {
+        ConfigLoader {
+            pipeline_chain,
+            pipelines,
+            handlers: HashMap::new(),
+            named_pipelines: HashMap::new(),
+        }
+    }
+
+    /// Registers a `Handler`, which routes of the configuration with the given `handler` name
+    /// are dispatched to.
+    pub fn handler<H>(&mut self, name: &str, handler: H) -> &mut Self
+    where
+        H: Handler + RefUnwindSafe + Clone + Send + Sync + 'static,
+    {
+        let new_handler = Arc::new(move || -> BoxedHandler {
+            let handler = handler.clone();
+            Box::new(move |state| handler.handle(state))
+        });
+
+        self.handlers
+            .insert(name.to_owned(), NamedHandler { new_handler });
+        self
+    }
+
+    /// Registers a pipeline chain, which routes of the configuration with the given `pipeline`
+    /// name are dispatched through instead of the pipeline chain of the `ConfigLoader`. The
+    /// pipelines of the chain must belong to the `PipelineSet` of the `ConfigLoader`.
+    pub fn pipeline<NC>(&mut self, name: &str, pipeline_chain: NC) -> &mut Self
+    where
+        NC: PipelineHandleChain<P> + Copy + Send + Sync + 'static,
+    {
+        let draw: DrawWithPipeline<C, P> = Box::new(move |route, configured| {
+            route.with_pipeline_chain(pipeline_chain, |route| configured.draw(route))
+        });
+
+        self.named_pipelines.insert(name.to_owned(), draw);
+        self
+    }
+
+    /// Builds a `Router` from the routes of the configuration, in the order they're given.
+    ///
+    /// An error is returned, and no `Router` is built, if any route is invalid or refers to a
+    /// handler or pipeline which wasn't registered.
+    pub fn load(&self, config: &RouterConfig) -> Result<Router, ConfigError> {
+        let routes = config
+            .routes
+            .iter()
+            .map(|route| self.resolve(route))
+            .collect::<Result<Vec<_>, _>>()?;
+
+        Ok(build_router(
+            self.pipeline_chain,
+            self.pipelines.clone(),
+            |builder| {
+                for route in &routes {
+                    match route.pipeline {
+                        Some(ref name) => (self.named_pipelines[name])(builder, route),
+                        None => route.draw(builder),
+                    }
+                }
+            },
+        ))
+    }
+
+    fn resolve(&self, route: &RouteConfig) -> Result<ConfiguredRoute, ConfigError> {
+        let methods = if route.methods.is_empty() {
+            vec![Method::GET, Method::HEAD]
+        } else {
+            route
+                .methods
+                .iter()
+                .map(|method| {
+                    Method::from_bytes(method.to_uppercase().as_bytes())
+                        .map_err(|_| ConfigError::InvalidMethod(method.clone()))
+                })
+                .collect::<Result<_, _>>()?
+        };
+
+        if let Some(ref name) = route.pipeline {
+            if !self.named_pipelines.contains_key(name) {
+                return Err(ConfigError::UnknownPipeline(name.clone()));
+            }
+        }
+
+        if route.status.is_some() && route.redirect.is_none() {
+            return Err(ConfigError::InvalidTarget(route.path.clone()));
+        }
+
+        let target = match (&route.dir, &route.file, &route.redirect, &route.handler) {
+            (Some(dir), None, None, None) => Target::Dir(dir.clone()),
+            (None, Some(file), None, None) => Target::File(file.clone()),
+            (None, None, Some(location), None) => {
+                let status = match route.status {
+                    Some(status) => StatusCode::from_u16(status)
+                        .ok()
+                        .filter(StatusCode::is_redirection),
+                    None => Some(StatusCode::PERMANENT_REDIRECT),
+                };
+
+                match status {
+                    Some(status) if HeaderValue::from_str(location).is_ok() => {
+                        Target::Redirect(location.clone(), status)
+                    }
+                    _ => return Err(ConfigError::InvalidRedirect(route.path.clone())),
+                }
+            }
+            (None, None, None, Some(name)) => match self.handlers.get(name) {
+                Some(handler) => Target::Handler(handler.clone()),
+                None => return Err(ConfigError::UnknownHandler(name.clone())),
+            },
+            _ => return Err(ConfigError::InvalidTarget(route.path.clone())),
+        };
+
+        Ok(ConfiguredRoute {
+            path: route.path.clone(),
+            methods,
+            pipeline: route.pipeline.clone(),
+            target,
+        })
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    use std::io;
+
+    use futures::{future, Future};
+    use hyper::header::{LOCATION, WARNING};
+    use hyper::Response;
+
+    use crate::middleware::{Middleware, NewMiddleware};
+    use crate::pipeline::new_pipeline;
+    use crate::pipeline::set::{finalize_pipeline_set, new_pipeline_set};
+    use crate::test::TestServer;
+
+    #[derive(Clone)]
+    struct WarningMiddleware;
+
+    impl NewMiddleware for WarningMiddleware {
+        type Instance = Self;
+
+        fn new_middleware(&self) -> io::Result<Self> {
+            Ok(self.clone())
+        }
+    }
+
+    impl Middleware for WarningMiddleware {
+        fn call<Chain>(self, state: State, chain: Chain) -> Box<HandlerFuture>
+        where
+            Chain: FnOnce(State) -> Box<HandlerFuture> + Send + 'static,
+        {
+            Box::new(chain(state).and_then(|(state, mut res)| {
+                res.headers_mut()
+                    .insert(WARNING, "299 example.com Beta".parse().unwrap());
+                future::ok((state, res))
+            }))
+        }
+    }
+
+    fn status(state: State) -> (State, Response<hyper::Body>) {
+        let res = Response::new("ok".into());
+        (state, res)
+    }
+
+    #[test]
+    fn loads_routes_from_toml() {
+        let config = RouterConfig::from_toml(
+            r#"
+            [[routes]]
+            path = "/assets/*"
+            dir = "resources/test/assets"
+
+            [[routes]]
+            path = "/doc"
+            file = "resources/test/assets/doc.html"
+
+            [[routes]]
+            path = "/old"
+            redirect = "/new"
+            status = 301
+
+            [[routes]]
+            path = "/status"
+            methods = ["get", "POST"]
+            handler = "status"
+            pipeline = "beta"
+            "#,
+        )
+        .unwrap();
+
+        let pipelines = new_pipeline_set();
+        let (pipelines, beta) = pipelines.add(new_pipeline().add(WarningMiddleware).build());
+        let pipelines = finalize_pipeline_set(pipelines);
+
+        let mut loader = ConfigLoader::with_pipelines((), pipelines);
+        loader
+            .handler("status", status)
+            .pipeline("beta", (beta, ()));
+        let router = loader.load(&config).unwrap();
+
+        let test_server = TestServer::new(router).unwrap();
+        let client = test_server.client();
+
+        let response = client
+            .get("http://localhost/assets/file.txt")
+            .perform()
+            .unwrap();
+        assert_eq!(response.status(), StatusCode::OK);
+
+        let response = client.get("http://localhost/doc").perform().unwrap();
+        assert_eq!(response.status(), StatusCode::OK);
+
+        let response = client.get("http://localhost/old").perform().unwrap();
+        assert_eq!(response.status(), StatusCode::MOVED_PERMANENTLY);
+        assert_eq!(response.headers()[LOCATION], "/new");
+
+        let response = client
+            .post("http://localhost/status", "", mime::TEXT_PLAIN)
+            .perform()
+            .unwrap();
+        assert_eq!(response.status(), StatusCode::OK);
+        assert_eq!(response.headers()[WARNING], "299 example.com Beta");
+
+        // only the configured methods are routed
+        let response = client.delete("http://localhost/status").perform().unwrap();
+        assert_eq!(response.status(), StatusCode::METHOD_NOT_ALLOWED);
+    }
+
+    #[test]
+    fn rejects_invalid_configuration() {
+        let mut loader = ConfigLoader::new();
+        loader.handler("status", status);
+
+        let load = |config: &str| {
+            RouterConfig::from_toml(config)
+                .and_then(|config| loader.load(&config))
+                .err()
+        };
+
+        assert!(matches!(
+            load("[[routes]]\npath = '/'\nunknown = true"),
+            Some(ConfigError::Parse(_))
+        ));
+        assert_eq!(
+            load("[[routes]]\npath = '/'"),
+            Some(ConfigError::InvalidTarget("/".to_owned()))
+        );
+        assert_eq!(
+            load("[[routes]]\npath = '/'\nfile = 'a'\nhandler = 'status'"),
+            Some(ConfigError::InvalidTarget("/".to_owned()))
+        );
+        assert_eq!(
+            load("[[routes]]\npath = '/'\nhandler = 'status'\nmethods = ['G T']"),
+            Some(ConfigError::InvalidMethod("G T".to_owned()))
+        );
+        assert_eq!(
+            load("[[routes]]\npath = '/'\nredirect = '/new'\nstatus = 200"),
+            Some(ConfigError::InvalidRedirect("/".to_owned()))
+        );
+        assert_eq!(
+            load("[[routes]]\npath = '/'\nhandler = 'missing'"),
+            Some(ConfigError::UnknownHandler("missing".to_owned()))
+        );
+        assert_eq!(
+            load("[[routes]]\npath = '/'\nhandler = 'status'\npipeline = 'missing'"),
+            Some(ConfigError::UnknownPipeline("missing".to_owned()))
+        );
+        assert_eq!(load("[[routes]]\npath = '/'\nhandler = 'status'"), None);
+    }
+}
@@ -0,0 +1,75 @@
+//! Defines `Fallthrough`, which allows a handler to pass a request on to the routes matched after
+//! its own.
+
+use hyper::{Body, Response, StatusCode};
+
+use crate::helpers::http::response::create_empty_response;
+use crate::state::{State, StateData};
+
+/// Signals to the `Router` that the handler which put it into `State` didn't handle the request,
+/// so that the request is routed to the next route which matches it, as if the route of the
+/// handler hadn't matched. The response of the handler is discarded.
+///
+/// Routes attached to the same path are tried first, in the order of `Node::select_route`, and
+/// then the routes of any less specific path which matches the request, such as a glob. Once a
+/// route has fallen through, paths whose remaining routes don't match the request are passed
+/// over rather than responding with `405 Method Not Allowed`. Where no route remains, the request
+/// is answered with `404 Not Found`, and a `Router` which the request was delegated to leaves
+/// `Fallthrough` in `State` so that the request falls through the delegating `Router` in turn.
+///
+/// The request is dispatched to the next route with the same `State`, running its pipelines
+/// again, so a handler which falls through must leave the request body in `State`.
+///
+/// # Examples
+///
+/// ```rust
+/// # extern crate gotham;
+/// # extern crate hyper;
+/// #
+/// # use hyper::{Body, Response, StatusCode};
+/// # use gotham::helpers::http::response::create_response;
+/// # use gotham::router::builder::*;
+/// # use gotham::router::fallthrough::fall_through;
+/// # use gotham::state::State;
+/// # use gotham::test::TestServer;
+/// #
+/// fn page(state: State) -> (State, Response<Body>) {
+///     if state.borrow::<hyper::Uri>().path() != "/about" {
+///         return fall_through(state);
+///     }
+///     let res = create_response(&state, StatusCode::OK, mime::TEXT_PLAIN, "about");
+///     (state, res)
+/// }
+///
+/// fn file(state: State) -> (State, &'static str) {
+///     (state, "file")
+/// }
+///
+/// # fn main() {
+/// let router = build_simple_router(|route| {
+///     route.get("/:page").to(page);
+///     route.get("/*").to(file);
+/// });
+///
+/// let test_server = TestServer::new(router).unwrap();
+/// let response = test_server
+///     .client()
+///     .get("http://localhost/index.html")
+///     .perform()
+///     .unwrap();
+///
+/// assert_eq!(response.read_utf8_body().unwrap(), "file");
+/// # }
+/// ```
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub struct Fallthrough;
+
+impl StateData for Fallthrough {}
+
+/// Puts `Fallthrough` into `State`, returning the response which is discarded in favour of the
+/// response of the next matching route.
+pub fn fall_through(mut state: State) -> (State, Response<Body>) {
+    state.put(Fallthrough);
+    let res = create_empty_response(&state, StatusCode::NOT_FOUND);
+    (state, res)
+}
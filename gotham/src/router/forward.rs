@@ -0,0 +1,126 @@
+//! Defines `Forward`, which allows a handler to pass a request on to the route of another path
+//! within the same `Router`.
+
+use hyper::http::uri::{self, PathAndQuery};
+use hyper::{Body, Response, StatusCode, Uri};
+use log::error;
+
+use crate::helpers::http::response::create_empty_response;
+use crate::state::{request_id, FromState, State, StateData};
+
+/// The number of times a request may be forwarded, beyond which the `Router` responds with
+/// `500 Internal Server Error`, as the forwards are most likely to form a loop.
+pub(crate) const MAX_FORWARDS: usize = 10;
+
+/// Signals to the `Router` that the handler which put it into `State` didn't handle the request,
+/// so that the request is routed again as if it had been made to another path, without
+/// redirecting the client. The response of the handler is discarded.
+///
+/// The request keeps its `State`, other than the `Uri`, which is replaced by the `Uri` of the
+/// path it's forwarded to, so that the route it's forwarded to sees the values put into `State`
+/// by the pipelines and handler of the original route. This allows legacy paths to be rewritten,
+/// or error pages to be rendered by their own routes. As with `Fallthrough`, the pipelines of the
+/// route serving the forwarded request are invoked, and a handler which forwards a request must
+/// leave the request body in `State` for the route it's forwarded to.
+///
+/// The path is routed by the outermost `Router`, including where the request was forwarded from
+/// a `Router` which requests are delegated to. Middleware may also forward a request, by
+/// responding with `forward` rather than invoking the rest of the chain.
+///
+/// # Examples
+///
+/// ```rust
+/// # extern crate gotham;
+/// # extern crate hyper;
+/// #
+/// # use hyper::Uri;
+/// # use gotham::router::builder::*;
+/// # use gotham::router::forward::forward;
+/// # use gotham::state::{FromState, State};
+/// # use gotham::test::TestServer;
+/// #
+/// fn legacy_profile(state: State) -> (State, hyper::Response<hyper::Body>) {
+///     forward(state, "/users/me")
+/// }
+///
+/// fn profile(state: State) -> (State, String) {
+///     let path = Uri::borrow_from(&state).path().to_owned();
+///     (state, path)
+/// }
+///
+/// # fn main() {
+/// let router = build_simple_router(|route| {
+///     route.get("/profile.php").to(legacy_profile);
+///     route.get("/users/me").to(profile);
+/// });
+///
+/// let test_server = TestServer::new(router).unwrap();
+/// let response = test_server
+///     .client()
+///     .get("http://localhost/profile.php")
+///     .perform()
+///     .unwrap();
+///
+/// assert_eq!(response.read_utf8_body().unwrap(), "/users/me");
+/// # }
+/// ```
+#[derive(Clone, Debug, PartialEq)]
+pub struct Forward {
+    uri: Uri,
+}
+
+impl Forward {
+    /// The `Uri` the request is forwarded to.
+    pub fn uri(&self) -> &Uri {
+        &self.uri
+    }
+
+    pub(crate) fn into_uri(self) -> Uri {
+        self.uri
+    }
+}
+
+impl StateData for Forward {}
+
+/// Puts `Forward` into `State`, to forward the request to `path`, which may include a query
+/// string. The scheme and authority of the request `Uri` are kept. The response returned is
+/// discarded in favour of the response of the route the request is forwarded to.
+///
+/// Where `path` isn't a valid path, the request isn't forwarded, and the response is
+/// `500 Internal Server Error`.
+pub fn forward(mut state: State, path: &str) -> (State, Response<Body>) {
+    let path_and_query = match path.parse::<PathAndQuery>() {
+        Ok(path_and_query) => path_and_query,
+        Err(e) => {
+            error!(
+                "[{}] unable to forward to {:?}: {}",
+                request_id(&state),
+                path,
+                e
+            );
+            let res = create_empty_response(&state, StatusCode::INTERNAL_SERVER_ERROR);
+            return (state, res);
+        }
+    };
+
+    let mut parts = uri::Parts::from(Uri::borrow_from(&state).clone());
+    parts.path_and_query = Some(path_and_query);
+
+    match Uri::from_parts(parts) {
+        Ok(uri) => {
+            state.put(Forward { uri });
+            let res = create_empty_response(&state, StatusCode::NOT_FOUND);
+            (state, res)
+        }
+        Err(e) => {
+            error!(
+                "[{}] unable to forward to {:?}: {}",
+                request_id(&state),
+                path,
+                e
+            );
+            let res = create_empty_response(&state, StatusCode::INTERNAL_SERVER_ERROR);
+            (state, res)
+        }
+    }
+}
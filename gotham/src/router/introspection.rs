@@ -0,0 +1,654 @@
+//! Defines `RouteDescription`, which describes the routes of a `Router`.
+
+use std::fmt::{self, Display, Formatter};
+use std::sync::Arc;
+
+use hyper::Method;
+use serde::de::{
+    self, Deserialize, DeserializeSeed, Deserializer, IntoDeserializer, MapAccess, SeqAccess,
+    Visitor,
+};
+use serde::forward_to_deserialize_any;
+
+use crate::router::route::Delegation;
+use crate::router::tree::node::Node;
+use crate::router::tree::Tree;
+use crate::state::StateData;
+
+/// Describes a single route defined within a `Router`, as provided by `Router::routes`.
+#[derive(Clone, Debug, PartialEq)]
+pub struct RouteDescription {
+    path: String,
+    host: Option<String>,
+    methods: Option<Vec<Method>>,
+    handler: Option<&'static str>,
+    pipelines: Vec<Vec<&'static str>>,
+    delegated: bool,
+    path_fields: Vec<FieldDescription>,
+    query_string_fields: Vec<FieldDescription>,
+}
+
+impl RouteDescription {
+    /// The path template of the route, as it would be given to the builder. Dynamic segments are
+    /// shown as `:name`, with any regular expression constraint following as `:name:[0-9]+`.
+    pub fn path(&self) -> &str {
+        &self.path
+    }
+
+    /// The host pattern of the route, where it was defined through `RouterBuilder::host`.
+    pub fn host(&self) -> Option<&str> {
+        self.host.as_deref()
+    }
+
+    /// The request methods matched by the route, or `None` where the route doesn't consider the
+    /// request method, such as when requests are delegated to another `Router`.
+    pub fn methods(&self) -> Option<&[Method]> {
+        self.methods.as_deref()
+    }
+
+    /// The type name of the handler, where known. The name is intended for display, and its
+    /// format may change between compiler versions.
+    pub fn handler(&self) -> Option<&str> {
+        self.handler
+    }
+
+    /// The type names of the middleware in each pipeline that requests are dispatched via, in the
+    /// order they're invoked.
+    pub fn pipelines(&self) -> &[Vec<&'static str>] {
+        &self.pipelines
+    }
+
+    /// Whether requests are delegated to another `Router`, under the path of this route.
+    pub fn is_delegated(&self) -> bool {
+        self.delegated
+    }
+
+    /// The fields of the `PathExtractor` of the route, where it's a struct.
+    pub fn path_fields(&self) -> &[FieldDescription] {
+        &self.path_fields
+    }
+
+    /// The fields of the `QueryStringExtractor` of the route, where it's a struct.
+    pub fn query_string_fields(&self) -> &[FieldDescription] {
+        &self.query_string_fields
+    }
+
+    /// The request methods of the route, separated by commas, or `*` where any is matched.
+    fn method_label(&self) -> String {
+        match self.methods {
+            Some(ref methods) => {
+                let methods: Vec<&str> = methods.iter().map(Method::as_str).collect();
+                methods.join(", ")
+            }
+            None => "*".to_owned(),
+        }
+    }
+
+    /// The path template of the route, preceded by its host pattern where it has one.
+    fn location(&self) -> String {
+        match self.host {
+            Some(ref host) => format!("{}{}", host, self.path),
+            None => self.path.clone(),
+        }
+    }
+
+    /// The handler of the route, followed by the middleware of each of its pipelines.
+    fn target(&self) -> String {
+        let mut target = self.handler.unwrap_or("-").to_owned();
+        for pipeline in &self.pipelines {
+            target.push_str(&format!(" [{}]", pipeline.join(", ")));
+        }
+        if self.delegated {
+            target.push_str(" (delegated)");
+        }
+        target
+    }
+}
+
+impl Display for RouteDescription {
+    /// Formats the route as a single line of its methods, path and handler, as listed by
+    /// `Router::dump`.
+    fn fmt(&self, f: &mut Formatter) -> fmt::Result {
+        write!(
+            f,
+            "{} {} -> {}",
+            self.method_label(),
+            self.location(),
+            self.target()
+        )
+    }
+}
+
+/// The path template of the route a request was dispatched to, such as `/users/:id`, as shown by
+/// `RouteDescription::path`. The `Router` puts a `RouteTemplate` into `State` before the route's
+/// pipelines and handler are invoked.
+///
+/// Unlike the request path, the template is the same for every request to the route, which makes
+/// it suitable for grouping requests in logs and metrics.
+///
+/// ```rust
+/// # extern crate gotham;
+/// #
+/// # use gotham::router::builder::*;
+/// # use gotham::router::introspection::RouteTemplate;
+/// # use gotham::state::{FromState, State};
+/// # use gotham::test::TestServer;
+/// #
+/// fn show_user(state: State) -> (State, String) {
+///     let template = RouteTemplate::borrow_from(&state).to_string();
+///     (state, template)
+/// }
+///
+/// # fn main() {
+/// let router = build_simple_router(|route| {
+///     route.get("/users/:id").to(show_user);
+/// });
+///
+/// let test_server = TestServer::new(router).unwrap();
+/// let response = test_server
+///     .client()
+///     .get("http://localhost/users/42")
+///     .perform()
+///     .unwrap();
+/// assert_eq!(response.read_utf8_body().unwrap(), "/users/:id");
+/// # }
+/// ```
+#[derive(Clone, Debug, PartialEq)]
+pub struct RouteTemplate(Arc<str>);
+
+impl RouteTemplate {
+    /// Creates the `RouteTemplate` of the routes of `node`, whose ancestors have the given
+    /// `segments`.
+    pub(crate) fn new(node: &Node, segments: &[String]) -> RouteTemplate {
+        RouteTemplate(Arc::from(node_path(node, segments)))
+    }
+
+    /// The path template of the route.
+    pub fn as_str(&self) -> &str {
+        &self.0
+    }
+}
+
+impl Display for RouteTemplate {
+    fn fmt(&self, f: &mut Formatter) -> fmt::Result {
+        f.write_str(&self.0)
+    }
+}
+
+impl StateData for RouteTemplate {}
+
+/// Formats the routes as a table with a line for each route, aligning the methods, paths and
+/// handlers into columns.
+pub(crate) fn format_routes(routes: &[RouteDescription]) -> String {
+    let rows: Vec<(String, String, String)> = routes
+        .iter()
+        .map(|route| (route.method_label(), route.location(), route.target()))
+        .collect();
+
+    let methods_width = rows.iter().map(|row| row.0.len()).max().unwrap_or(0);
+    let location_width = rows.iter().map(|row| row.1.len()).max().unwrap_or(0);
+
+    let mut table = String::new();
+    for (methods, location, target) in rows {
+        let line = format!(
+            "{:mw$}  {:lw$}  {}",
+            methods,
+            location,
+            target,
+            mw = methods_width,
+            lw = location_width
+        );
+        table.push_str(line.trim_end());
+        table.push('\n');
+    }
+    table
+}
+
+/// Describes a field of the struct used as the `PathExtractor` or `QueryStringExtractor` of a
+/// route, as determined from its `Deserialize` implementation.
+#[derive(Clone, Debug, PartialEq)]
+pub struct FieldDescription {
+    name: &'static str,
+    kind: &'static str,
+    required: bool,
+    values: Option<&'static [&'static str]>,
+}
+
+impl FieldDescription {
+    /// The name of the field, as it appears in the request.
+    pub fn name(&self) -> &str {
+        self.name
+    }
+
+    /// The kind of value the field holds, named as in a JSON Schema: one of `string`, `integer`,
+    /// `number`, `boolean`, `array` or `object`. Where the kind can't be determined, `string` is
+    /// used.
+    pub fn kind(&self) -> &str {
+        self.kind
+    }
+
+    /// Whether a value must be given for the field, which is `false` for an `Option`.
+    pub fn is_required(&self) -> bool {
+        self.required
+    }
+
+    /// The values which are accepted for the field, where it's an enum.
+    pub fn values(&self) -> Option<&[&'static str]> {
+        self.values
+    }
+}
+
+/// Describes the fields of the struct `T`, by deserializing it from placeholder values and
+/// recording what its `Deserialize` implementation asks for. Types which aren't structs have no
+/// fields.
+pub(crate) fn describe_fields<T>() -> Vec<FieldDescription>
+where
+    T: for<'de> Deserialize<'de>,
+{
+    let mut fields = vec![];
+    // deserialization usually fails on the placeholders, but the fields are recorded first
+    let _ = T::deserialize(StructProbe {
+        fields: &mut fields,
+    });
+    fields
+}
+
+/// A `Deserializer` which records the fields requested for a struct.
+struct StructProbe<'a> {
+    fields: &'a mut Vec<FieldDescription>,
+}
+
+impl<'de, 'a> Deserializer<'de> for StructProbe<'a> {
+    type Error = de::value::Error;
+
+    fn deserialize_any<V>(self, _visitor: V) -> Result<V::Value, Self::Error>
+    where
+        V: Visitor<'de>,
+    {
+        Err(de::Error::custom("not a struct"))
+    }
+
+    fn deserialize_struct<V>(
+        self,
+        _name: &'static str,
+        fields: &'static [&'static str],
+        visitor: V,
+    ) -> Result<V::Value, Self::Error>
+    where
+        V: Visitor<'de>,
+    {
+        self.fields
+            .extend(fields.iter().map(|&name| FieldDescription {
+                name,
+                kind: "string",
+                required: true,
+                values: None,
+            }));
+
+        visitor.visit_map(FieldProbes {
+            fields: self.fields,
+            index: 0,
+        })
+    }
+
+    forward_to_deserialize_any! {
+        bool i8 i16 i32 i64 u8 u16 u32 u64 f32 f64 char str string bytes
+        byte_buf option unit unit_struct newtype_struct seq tuple
+        tuple_struct map enum identifier ignored_any
+    }
+}
+
+/// Provides each field of a struct in turn, probing the kind of its value.
+struct FieldProbes<'a> {
+    fields: &'a mut Vec<FieldDescription>,
+    index: usize,
+}
+
+impl<'de, 'a> MapAccess<'de> for FieldProbes<'a> {
+    type Error = de::value::Error;
+
+    fn next_key_seed<K>(&mut self, seed: K) -> Result<Option<K::Value>, Self::Error>
+    where
+        K: DeserializeSeed<'de>,
+    {
+        match self.fields.get(self.index) {
+            Some(field) => seed
+                .deserialize(de::value::BorrowedStrDeserializer::new(field.name))
+                .map(Some),
+            None => Ok(None),
+        }
+    }
+
+    fn next_value_seed<V>(&mut self, seed: V) -> Result<V::Value, Self::Error>
+    where
+        V: DeserializeSeed<'de>,
+    {
+        let field = &mut self.fields[self.index];
+        self.index += 1;
+        seed.deserialize(ValueProbe { field })
+    }
+}
+
+/// A `Deserializer` which records the kind of value requested for a field, and provides a
+/// placeholder of that kind.
+struct ValueProbe<'a> {
+    field: &'a mut FieldDescription,
+}
+
+macro_rules! probe_value {
+    ($($method:ident => $kind:expr, $visit:ident($($value:expr)?);)*) => {
+        $(
+            fn $method<V>(self, visitor: V) -> Result<V::Value, Self::Error>
+            where
+                V: Visitor<'de>,
+            {
+                self.field.kind = $kind;
+                visitor.$visit($($value)?)
+            }
+        )*
+    };
+}
+
+impl<'de, 'a> Deserializer<'de> for ValueProbe<'a> {
+    type Error = de::value::Error;
+
+    probe_value! {
+        deserialize_any => "string", visit_str("");
+        deserialize_bool => "boolean", visit_bool(false);
+        deserialize_i8 => "integer", visit_i8(0);
+        deserialize_i16 => "integer", visit_i16(0);
+        deserialize_i32 => "integer", visit_i32(0);
+        deserialize_i64 => "integer", visit_i64(0);
+        deserialize_u8 => "integer", visit_u8(0);
+        deserialize_u16 => "integer", visit_u16(0);
+        deserialize_u32 => "integer", visit_u32(0);
+        deserialize_u64 => "integer", visit_u64(0);
+        deserialize_f32 => "number", visit_f32(0.0);
+        deserialize_f64 => "number", visit_f64(0.0);
+        deserialize_char => "string", visit_char(' ');
+        deserialize_str => "string", visit_str("");
+        deserialize_string => "string", visit_str("");
+        deserialize_bytes => "string", visit_bytes(&[]);
+        deserialize_byte_buf => "string", visit_bytes(&[]);
+        deserialize_identifier => "string", visit_str("");
+        deserialize_unit => "string", visit_unit();
+        deserialize_ignored_any => "string", visit_unit();
+    }
+
+    fn deserialize_option<V>(self, visitor: V) -> Result<V::Value, Self::Error>
+    where
+        V: Visitor<'de>,
+    {
+        self.field.required = false;
+        visitor.visit_some(self)
+    }
+
+    fn deserialize_unit_struct<V>(
+        self,
+        _name: &'static str,
+        visitor: V,
+    ) -> Result<V::Value, Self::Error>
+    where
+        V: Visitor<'de>,
+    {
+        self.deserialize_unit(visitor)
+    }
+
+    fn deserialize_newtype_struct<V>(
+        self,
+        _name: &'static str,
+        visitor: V,
+    ) -> Result<V::Value, Self::Error>
+    where
+        V: Visitor<'de>,
+    {
+        visitor.visit_newtype_struct(self)
+    }
+
+    fn deserialize_seq<V>(self, visitor: V) -> Result<V::Value, Self::Error>
+    where
+        V: Visitor<'de>,
+    {
+        self.field.kind = "array";
+        visitor.visit_seq(EmptySeq)
+    }
+
+    fn deserialize_tuple<V>(self, _len: usize, visitor: V) -> Result<V::Value, Self::Error>
+    where
+        V: Visitor<'de>,
+    {
+        self.deserialize_seq(visitor)
+    }
+
+    fn deserialize_tuple_struct<V>(
+        self,
+        _name: &'static str,
+        _len: usize,
+        visitor: V,
+    ) -> Result<V::Value, Self::Error>
+    where
+        V: Visitor<'de>,
+    {
+        self.deserialize_seq(visitor)
+    }
+
+    fn deserialize_map<V>(self, _visitor: V) -> Result<V::Value, Self::Error>
+    where
+        V: Visitor<'de>,
+    {
+        self.field.kind = "object";
+        Err(de::Error::custom("unable to probe nested values"))
+    }
+
+    fn deserialize_struct<V>(
+        self,
+        _name: &'static str,
+        _fields: &'static [&'static str],
+        visitor: V,
+    ) -> Result<V::Value, Self::Error>
+    where
+        V: Visitor<'de>,
+    {
+        self.deserialize_map(visitor)
+    }
+
+    fn deserialize_enum<V>(
+        self,
+        _name: &'static str,
+        variants: &'static [&'static str],
+        visitor: V,
+    ) -> Result<V::Value, Self::Error>
+    where
+        V: Visitor<'de>,
+    {
+        self.field.values = Some(variants);
+        match variants.first() {
+            Some(variant) => visitor.visit_enum(variant.into_deserializer()),
+            None => Err(de::Error::custom("unable to probe enum without variants")),
+        }
+    }
+}
+
+/// A sequence without any elements, used as the placeholder for an `array`.
+struct EmptySeq;
+
+impl<'de> SeqAccess<'de> for EmptySeq {
+    type Error = de::value::Error;
+
+    fn next_element_seed<T>(&mut self, _seed: T) -> Result<Option<T::Value>, Self::Error>
+    where
+        T: DeserializeSeed<'de>,
+    {
+        Ok(None)
+    }
+}
+
+/// Appends a `RouteDescription` for each route of the `Tree`, in the order of traversal.
+pub(crate) fn describe_tree(tree: &Tree, host: Option<&str>, routes: &mut Vec<RouteDescription>) {
+    describe_node(tree.borrow_root(), host, &mut vec![], routes);
+}
+
+/// The path template of `node`, whose ancestors have the given `segments`.
+fn node_path(node: &Node, segments: &[String]) -> String {
+    let mut path = format!("/{}", segments.join("/"));
+    if node.requires_trailing_slash() && !segments.is_empty() {
+        path.push('/');
+    }
+    path
+}
+
+fn describe_node(
+    node: &Node,
+    host: Option<&str>,
+    segments: &mut Vec<String>,
+    routes: &mut Vec<RouteDescription>,
+) {
+    if !node.routes().is_empty() {
+        let path = node_path(node, segments);
+
+        for route in node.routes() {
+            routes.push(RouteDescription {
+                path: path.clone(),
+                host: host.map(str::to_owned),
+                methods: route.methods(),
+                handler: route.handler_name(),
+                pipelines: route.pipeline_names(),
+                delegated: route.delegation() == Delegation::External,
+                path_fields: route.path_fields(),
+                query_string_fields: route.query_string_fields(),
+            });
+        }
+    }
+
+    for child in node.children() {
+        segments.push(child.describe_segment());
+        describe_node(child, host, segments, routes);
+        segments.pop();
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    use std::io;
+
+    use futures::future;
+
+    use crate::handler::HandlerFuture;
+    use crate::middleware::{Middleware, NewMiddleware};
+    use crate::pipeline::new_pipeline;
+    use crate::pipeline::single::single_pipeline;
+    use crate::router::builder::*;
+    use crate::state::State;
+
+    #[derive(Clone, Copy)]
+    struct NoopMiddleware;
+
+    impl NewMiddleware for NoopMiddleware {
+        type Instance = Self;
+
+        fn new_middleware(&self) -> io::Result<Self> {
+            Ok(*self)
+        }
+    }
+
+    impl Middleware for NoopMiddleware {
+        fn call<Chain>(self, state: State, chain: Chain) -> Box<HandlerFuture>
+        where
+            Chain: FnOnce(State) -> Box<HandlerFuture> + 'static,
+        {
+            chain(state)
+        }
+    }
+
+    fn handler(state: State) -> Box<HandlerFuture> {
+        Box::new(future::ok((
+            state,
+            hyper::Response::new(hyper::Body::empty()),
+        )))
+    }
+
+    #[test]
+    fn describes_routes() {
+        let (chain, pipelines) = single_pipeline(new_pipeline().add(NoopMiddleware).build());
+
+        let router = build_router(chain, pipelines, |route| {
+            route.get("/").to(handler);
+            route.post("/users/:id:[0-9]+/").to(handler);
+            route.get("/files/*").to(handler);
+            route
+                .delegate("/api")
+                .to_router(build_simple_router(|route| {
+                    route.get("/").to(handler);
+                }));
+            route.host("*.example.com", |route| {
+                route.patch("/").to(handler);
+            });
+        });
+
+        let routes = router.routes();
+        let summary: Vec<_> = routes
+            .iter()
+            .map(|r| (r.host(), r.path(), r.methods(), r.is_delegated()))
+            .collect();
+
+        assert_eq!(
+            summary,
+            vec![
+                (
+                    Some("*.example.com"),
+                    "/",
+                    Some(&[Method::PATCH][..]),
+                    false
+                ),
+                (None, "/", Some(&[Method::GET][..]), false),
+                (None, "/api", None, true),
+                (None, "/files/*", Some(&[Method::GET][..]), false),
+                (None, "/users/:id:[0-9]+/", Some(&[Method::POST][..]), false),
+            ]
+        );
+
+        assert!(routes[1].handler().unwrap().ends_with("tests::handler"));
+        assert_eq!(routes[1].pipelines().len(), 1);
+        assert!(routes[1].pipelines()[0][0].ends_with("NoopMiddleware"));
+        assert!(routes[2].handler().unwrap().ends_with("Router"));
+    }
+
+    #[test]
+    fn formats_routes() {
+        let router = build_simple_router(|route| {
+            route.get_or_head("/users/:id").to(handler);
+            route
+                .delegate("/api")
+                .to_router(build_simple_router(|route| {
+                    route.get("/").to(handler);
+                }));
+            route.host("*.example.com", |route| {
+                route.patch("/").to(handler);
+            });
+        });
+
+        let handler = type_name_of(handler);
+        let router_name = std::any::type_name::<crate::router::Router>();
+
+        assert_eq!(
+            router.routes()[2].to_string(),
+            format!("GET, HEAD /users/:id -> {}", handler)
+        );
+        assert_eq!(
+            router.dump(),
+            format!(
+                "PATCH      *.example.com/  {handler}\n\
+                 *          /api            {router} (delegated)\n\
+                 GET, HEAD  /users/:id      {handler}\n",
+                handler = handler,
+                router = router_name
+            )
+        );
+    }
+
+    fn type_name_of<T>(_: T) -> &'static str {
+        std::any::type_name::<T>()
+    }
+}
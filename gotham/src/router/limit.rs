@@ -0,0 +1,162 @@
+//! Defines `BodyLimit`, which limits the size of request bodies for the routes configured through
+//! `DefineSingleRoute::with_body_limit` and `ScopeBuilder::body_limit`.
+
+use std::io;
+use std::sync::atomic::{AtomicBool, Ordering};
+use std::sync::Arc;
+
+use futures::{Async, Future, Poll, Stream};
+use hyper::header::{HeaderMap, CONTENT_LENGTH};
+use hyper::{Body, Chunk, Response, StatusCode};
+use log::trace;
+
+use crate::handler::HandlerFuture;
+use crate::helpers::http::response::create_empty_response;
+use crate::state::{request_id, FromState, State};
+
+/// Limits the size of the body of a request to a number of bytes.
+///
+/// A request declaring a larger `Content-Length` is refused with `413 Payload Too Large` before
+/// it's dispatched. The length of a body which isn't declared is only known as it's read, so the
+/// body is counted as it's read instead, failing once the limit is exceeded. The error from a
+/// handler which then fails is given the `413 Payload Too Large` status.
+pub(crate) struct BodyLimit {
+    limit: u64,
+    exceeded: Arc<AtomicBool>,
+}
+
+impl BodyLimit {
+    /// Creates a new `BodyLimit` of `limit` bytes.
+    pub(crate) fn new(limit: u64) -> Self {
+        BodyLimit {
+            limit,
+            exceeded: Arc::new(AtomicBool::new(false)),
+        }
+    }
+
+    /// Applies the limit to the request body in `State`, providing the response to send instead
+    /// where the request declares a length over the limit.
+    pub(crate) fn apply(&self, state: &mut State) -> Option<Response<Body>> {
+        let content_length = HeaderMap::borrow_from(state)
+            .get(CONTENT_LENGTH)
+            .and_then(|len| len.to_str().ok())
+            .and_then(|len| len.parse::<u64>().ok());
+
+        match content_length {
+            Some(len) if len > self.limit => {
+                trace!(
+                    "[{}] request body of {} bytes exceeds the limit of {}",
+                    request_id(state),
+                    len,
+                    self.limit
+                );
+                Some(create_empty_response(state, StatusCode::PAYLOAD_TOO_LARGE))
+            }
+            // hyper ensures that the body is no longer than the length declared
+            Some(_) => None,
+            None => {
+                if let Some(body) = state.try_take::<Body>() {
+                    state.put(Body::wrap_stream(LimitedBody {
+                        body,
+                        remaining: self.limit,
+                        exceeded: self.exceeded.clone(),
+                    }));
+                }
+                None
+            }
+        }
+    }
+
+    /// Gives the error from a request whose body exceeded the limit as it was read the
+    /// `413 Payload Too Large` status.
+    pub(crate) fn finish(self, future: Box<HandlerFuture>) -> Box<HandlerFuture> {
+        let exceeded = self.exceeded;
+
+        Box::new(future.or_else(move |(state, err)| {
+            if exceeded.load(Ordering::SeqCst) {
+                trace!("[{}] request body exceeded the limit", request_id(&state));
+                return Err((state, err.with_status(StatusCode::PAYLOAD_TOO_LARGE)));
+            }
+            Err((state, err))
+        }))
+    }
+}
+
+// Wraps a request body, to fail once more than `remaining` bytes are read.
+struct LimitedBody {
+    body: Body,
+    remaining: u64,
+    exceeded: Arc<AtomicBool>,
+}
+
+impl Stream for LimitedBody {
+    type Item = Chunk;
+    type Error = Box<dyn std::error::Error + Send + Sync>;
+
+    fn poll(&mut self) -> Poll<Option<Chunk>, Self::Error> {
+        match self.body.poll()? {
+            Async::Ready(Some(chunk)) => {
+                let len = chunk.len() as u64;
+                if len > self.remaining {
+                    self.exceeded.store(true, Ordering::SeqCst);
+                    let err = io::Error::other("request body exceeds the limit");
+                    return Err(Box::new(err));
+                }
+                self.remaining -= len;
+                Ok(Async::Ready(Some(chunk)))
+            }
+            other => Ok(other),
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    use hyper::header::HeaderValue;
+
+    use crate::state::set_request_id;
+
+    fn state_with_body(content_length: Option<&str>, body: Body) -> State {
+        let mut state = State::new();
+        let mut headers = HeaderMap::new();
+        if let Some(len) = content_length {
+            headers.insert(CONTENT_LENGTH, HeaderValue::from_str(len).unwrap());
+        }
+        state.put(headers);
+        state.put(body);
+        set_request_id(&mut state);
+        state
+    }
+
+    #[test]
+    fn refuses_declared_lengths_over_the_limit() {
+        let limit = BodyLimit::new(4);
+
+        let mut state = state_with_body(Some("5"), Body::from("12345"));
+        let res = limit.apply(&mut state).unwrap();
+        assert_eq!(res.status(), StatusCode::PAYLOAD_TOO_LARGE);
+
+        let mut state = state_with_body(Some("4"), Body::from("1234"));
+        assert!(limit.apply(&mut state).is_none());
+    }
+
+    #[test]
+    fn fails_undeclared_lengths_over_the_limit() {
+        let limit = BodyLimit::new(4);
+
+        let body = futures::stream::iter_ok::<_, io::Error>(vec!["12", "34"]);
+        let mut state = state_with_body(None, Body::wrap_stream(body));
+        assert!(limit.apply(&mut state).is_none());
+        let body = Body::take_from(&mut state).concat2().wait().unwrap();
+        assert_eq!(body.as_ref(), b"1234");
+        assert!(!limit.exceeded.load(Ordering::SeqCst));
+
+        let body = futures::stream::iter_ok::<_, io::Error>(vec!["123", "45"]);
+        let mut state = state_with_body(None, Body::wrap_stream(body));
+        assert!(limit.apply(&mut state).is_none());
+        assert!(Body::take_from(&mut state).concat2().wait().is_err());
+        assert!(limit.exceeded.load(Ordering::SeqCst));
+    }
+}
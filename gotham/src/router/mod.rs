@@ -1,42 +1,253 @@
 //! Defines the Gotham `Router` and supporting types.
 
 pub mod builder;
+pub mod config;
+pub mod fallthrough;
+pub mod forward;
+pub mod introspection;
+pub(crate) mod limit;
 pub mod non_match;
+pub mod openapi;
+pub mod provider;
 pub mod response;
 pub mod route;
 pub mod tree;
+pub mod url_for;
+pub mod version;
 
+use std::borrow::Borrow;
+use std::panic::RefUnwindSafe;
 use std::sync::Arc;
 
-use futures::{future, Future};
-use hyper::header::ALLOW;
-use hyper::{Body, Response, StatusCode};
-use log::{error, trace};
+use futures::future::{self, Either};
+use futures::Future;
+use hyper::body::Payload;
+use hyper::header::{HeaderMap, HeaderValue, ALLOW, CONTENT_LENGTH, CONTENT_TYPE, LOCATION};
+use hyper::{Body, Method, Response, StatusCode, Uri};
+use log::{debug, error, trace};
+use percent_encoding::utf8_percent_encode;
 
 use crate::error::*;
-use crate::handler::{Handler, HandlerFuture, IntoResponse, NewHandler};
-use crate::helpers::http::request::path::RequestPathSegments;
-use crate::helpers::http::response::create_empty_response;
-use crate::router::response::finalizer::ResponseFinalizer;
+use crate::extractor::{ExtractionError, QueryStringOptions};
+use crate::handler::{
+    Handler, HandlerError, HandlerFuture, IntoHandlerError, IntoResponse, NewHandler,
+};
+use crate::helpers::http::request::path::{
+    has_trailing_slash, split_path_segments, RequestPathSegments,
+};
+use crate::helpers::http::response::{create_empty_response, create_permanent_redirect};
+use crate::helpers::http::PercentDecoded;
+use crate::router::fallthrough::Fallthrough;
+use crate::router::forward::{Forward, MAX_FORWARDS};
+use crate::router::introspection::RouteDescription;
+use crate::router::limit::BodyLimit;
+use crate::router::provider::DelegatedSegments;
+use crate::router::response::finalizer::{ResponseExtenders, ResponseFinalizer};
+use crate::router::route::matcher::host::request_host;
+use crate::router::route::matcher::{HostRouteMatcher, RouteMatcher};
 use crate::router::route::{Delegation, Route};
+use crate::router::tree::node::{route_id, Node};
 use crate::router::tree::segment::SegmentMapping;
 use crate::router::tree::Tree;
-use crate::state::{request_id, State};
+use crate::router::url_for::{UrlFor, PATH_SEGMENT};
+use crate::router::version::Versions;
+use crate::state::{request_id, request_scheme, FromState, Scheme, State};
 
 struct RouterData {
     tree: Tree,
     response_finalizer: ResponseFinalizer,
+    options: RouterOptions,
+    url_for: UrlFor,
+    hosts: Vec<HostTree>,
+    error_handlers: ErrorHandlers,
+    versions: Versions,
 }
 
 impl RouterData {
-    fn new(tree: Tree, response_finalizer: ResponseFinalizer) -> RouterData {
+    fn new(
+        tree: Tree,
+        response_finalizer: ResponseFinalizer,
+        options: RouterOptions,
+        hosts: Vec<(HostRouteMatcher, Option<HostExtraction>, Tree)>,
+        error_handlers: ErrorHandlers,
+        versions: Versions,
+    ) -> RouterData {
+        let mut tree = tree;
+        tree.borrow_root_mut().set_templates(&mut vec![]);
+        let url_for = UrlFor::from_tree(&tree);
+        let hosts = hosts
+            .into_iter()
+            .map(|(matcher, extraction, mut tree)| {
+                tree.borrow_root_mut().set_templates(&mut vec![]);
+                HostTree {
+                    url_for: UrlFor::from_tree(&tree),
+                    matcher,
+                    extraction,
+                    tree,
+                }
+            })
+            .collect();
+
         RouterData {
             tree,
             response_finalizer,
+            options,
+            url_for,
+            hosts,
+            error_handlers,
+            versions,
         }
     }
 }
 
+/// A handler which replaces a response that the `Router` would otherwise generate itself.
+type ErrorHandler = Box<dyn Fn(State) -> Box<HandlerFuture> + Send + Sync + RefUnwindSafe>;
+
+/// The handlers which replace the `404 Not Found` and `500 Internal Server Error` responses of
+/// the `Router`, as configured by `RouterBuilder::not_found` and `RouterBuilder::internal_error`.
+#[derive(Default)]
+pub(crate) struct ErrorHandlers {
+    not_found: Option<ErrorHandler>,
+    internal_error: Option<ErrorHandler>,
+}
+
+impl ErrorHandlers {
+    pub(crate) fn set_not_found<NH>(&mut self, new_handler: NH)
+    where
+        NH: NewHandler + 'static,
+    {
+        self.not_found = Some(error_handler(new_handler));
+    }
+
+    pub(crate) fn set_internal_error<NH>(&mut self, new_handler: NH)
+    where
+        NH: NewHandler + 'static,
+    {
+        self.internal_error = Some(error_handler(new_handler));
+    }
+}
+
+fn error_handler<NH>(new_handler: NH) -> ErrorHandler
+where
+    NH: NewHandler + 'static,
+{
+    Box::new(move |state| match new_handler.new_handler() {
+        Ok(handler) => handler.handle(state),
+        Err(e) => Box::new(future::err((state, e.compat().into_handler_error()))),
+    })
+}
+
+/// Extracts the label captured by the pattern of a host into `State`, as defined by
+/// `RouterBuilder::host_with_extractor`, providing the response to send instead where it can't
+/// be extracted.
+pub(crate) type HostExtraction = fn(&mut State, &str, &str) -> Option<Response<Body>>;
+
+/// Extracts the dynamic segments of the request path into `State` for the routes of a scope,
+/// providing the response to send instead where they can't be extracted.
+pub(crate) type PathExtraction = fn(&mut State, &SegmentMapping<'_>) -> Option<Response<Body>>;
+
+/// A `Tree` which routes the requests for a matching host, as defined by `RouterBuilder::host`.
+struct HostTree {
+    matcher: HostRouteMatcher,
+    extraction: Option<HostExtraction>,
+    tree: Tree,
+    url_for: UrlFor,
+}
+
+/// Behaviours of the `Router` which are configured through the `RouterBuilder`.
+#[derive(Clone, Copy, Debug, Default)]
+struct RouterOptions {
+    automatic_options: bool,
+    automatic_head: bool,
+    method_override: bool,
+    hide_allowed_methods: bool,
+    trailing_slash: TrailingSlash,
+    path_case: PathCase,
+    route_tracing: RouteTracing,
+    query_string: QueryStringOptions,
+}
+
+/// Determines how the `Router` treats a trailing slash in the request path, as configured by
+/// `RouterBuilder::trailing_slash`.
+#[derive(Clone, Copy, Debug, Default, PartialEq)]
+pub enum TrailingSlash {
+    /// Requests match routes regardless of a trailing slash, so `/users` and `/users/` are
+    /// routed identically. This is the default.
+    #[default]
+    MatchBoth,
+    /// Requests only match routes whose path was defined with the same trailing slash, so a
+    /// route for `/users` doesn't match a request for `/users/`, which receives a `404 Not Found`
+    /// response.
+    Strict,
+    /// Requests without a trailing slash receive a `308 Permanent Redirect` response to the same
+    /// path with a trailing slash appended.
+    RedirectToSlash,
+    /// Requests with a trailing slash receive a `308 Permanent Redirect` response to the same
+    /// path with the trailing slash removed.
+    RedirectToNoSlash,
+}
+
+/// Determines how the `Router` treats the case of static segments in the request path, as
+/// configured by `RouterBuilder::path_case`.
+///
+/// Dynamic segments and globs capture the request path as given, regardless of this policy.
+#[derive(Clone, Copy, Debug, Default, PartialEq)]
+pub enum PathCase {
+    /// Static segments only match a request path with the same case, so a route for `/Users`
+    /// doesn't match a request for `/users`. This is the default.
+    #[default]
+    Sensitive,
+    /// Static segments match a request path regardless of case, so `/Users`, `/users` and
+    /// `/USERS` are routed identically. A segment with the exact case is preferred where several
+    /// could match.
+    Insensitive,
+    /// Requests whose path only matches regardless of case receive a `308 Permanent Redirect`
+    /// response to the path with the case of the defined route.
+    RedirectToCanonical,
+}
+
+/// Determines whether the `Router` records how each request was matched against its routes, as
+/// configured by `RouterBuilder::route_tracing`.
+///
+/// A trace describes each node of the `Tree` visited while matching the request path, the
+/// outcome of matching each route at the node which was reached, and the reason the request had
+/// no matching route, where it didn't. The traces of any `Router` which requests are delegated to
+/// are determined by the policy of that `Router`.
+#[derive(Clone, Copy, Debug, Default, PartialEq)]
+pub enum RouteTracing {
+    /// Requests aren't traced. This is the default.
+    #[default]
+    Disabled,
+    /// Each step of the trace is logged at the `debug` level.
+    Log,
+    /// Each step of the trace is logged, and in builds with debug assertions enabled, is also
+    /// added to the response as an `X-Route-Trace` header.
+    Header,
+}
+
+/// The header which describes a step of the trace of a request, where the `Router` was
+/// configured with `RouteTracing::Header`.
+const ROUTE_TRACE: &str = "x-route-trace";
+
+/// The header which names the method a `POST` request is routed as, where the `Router` was
+/// configured with `RouterBuilder::method_override`.
+const METHOD_OVERRIDE: &str = "x-http-method-override";
+
+/// Restricts the scheme of requests which are routed within a scope, as configured by
+/// `DrawRoutes::scheme_scope`. The scheme of a request is determined by `state::request_scheme`.
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub enum SchemePolicy {
+    /// Only requests made over HTTPS are routed, and others receive a `404 Not Found` response.
+    Https,
+    /// Only requests made over plain HTTP are routed, and others receive a `404 Not Found`
+    /// response.
+    Http,
+    /// Requests made over plain HTTP receive a redirect to the same URL using HTTPS, with the
+    /// given status. This is typically `301 Moved Permanently`, or `308 Permanent Redirect` where
+    /// the request method must be kept.
+    RedirectToHttps(StatusCode),
+}
+
 /// Responsible for dispatching HTTP requests to defined routes, and responding with appropriate
 /// error codes when a valid `Route` is unable to be determined or the dispatch cannot be
 /// performed.
@@ -67,53 +278,16 @@ impl NewHandler for Router {
 impl Handler for Router {
     /// Handles the `Request` by determining the correct `Route` from the internal `Tree`, storing
     /// any path related variables in `State` and dispatching to the associated `Handler`.
-    fn handle(self, mut state: State) -> Box<HandlerFuture> {
+    fn handle(self, state: State) -> Box<HandlerFuture> {
         trace!("[{}] starting", request_id(&state));
 
-        let future = match state.try_take::<RequestPathSegments>() {
-            Some(rps) => {
-                if let Some((node, params, processed)) = self.data.tree.traverse(&rps.segments()) {
-                    match node.select_route(&state) {
-                        Ok(route) => match route.delegation() {
-                            Delegation::External => {
-                                trace!("[{}] delegating to secondary router", request_id(&state));
-
-                                state.put(rps.subsegments(processed));
-                                route.dispatch(state)
-                            }
-                            Delegation::Internal => {
-                                trace!("[{}] dispatching to route", request_id(&state));
-                                self.dispatch(state, params, route)
-                            }
-                        },
-                        Err(non_match) => {
-                            let (status, allow) = non_match.deconstruct();
-
-                            trace!("[{}] responding with error status", request_id(&state));
-                            let mut res = create_empty_response(&state, status);
-                            if let StatusCode::METHOD_NOT_ALLOWED = status {
-                                for allowed in allow {
-                                    res.headers_mut().append(
-                                        ALLOW,
-                                        allowed.as_str().to_string().parse().unwrap(),
-                                    );
-                                }
-                            }
-                            Box::new(future::ok((state, res)))
-                        }
-                    }
-                } else {
-                    trace!("[{}] did not find routable node", request_id(&state));
-                    let res = create_empty_response(&state, StatusCode::NOT_FOUND);
-                    Box::new(future::ok((state, res)))
-                }
-            }
-            None => {
-                trace!("[{}] invalid request path segments", request_id(&state));
-                let res = create_empty_response(&state, StatusCode::INTERNAL_SERVER_ERROR);
-                Box::new(future::ok((state, res)))
-            }
-        };
+        // a delegated router leaves forwarded requests to the outermost router
+        let outermost = !state.has::<UrlFor>();
+
+        let mut future = self.handle_request(state);
+        if outermost {
+            future = self.follow_forwards(future, 0);
+        }
 
         self.finalize_response(future)
     }
@@ -126,23 +300,663 @@ impl Router {
         note = "use the new `gotham::router::builder` API to construct a Router"
     )]
     pub fn new(tree: Tree, response_finalizer: ResponseFinalizer) -> Router {
-        Router::internal_new(tree, response_finalizer)
+        Router::internal_new(
+            tree,
+            response_finalizer,
+            RouterOptions::default(),
+            vec![],
+            ErrorHandlers::default(),
+            Versions::default(),
+        )
     }
 
     /// Same as `new`, but private and not deprecated.
-    fn internal_new(tree: Tree, response_finalizer: ResponseFinalizer) -> Router {
-        let router_data = RouterData::new(tree, response_finalizer);
+    fn internal_new(
+        tree: Tree,
+        response_finalizer: ResponseFinalizer,
+        options: RouterOptions,
+        hosts: Vec<(HostRouteMatcher, Option<HostExtraction>, Tree)>,
+        error_handlers: ErrorHandlers,
+        versions: Versions,
+    ) -> Router {
+        let router_data = RouterData::new(
+            tree,
+            response_finalizer,
+            options,
+            hosts,
+            error_handlers,
+            versions,
+        );
         Router {
             data: Arc::new(router_data),
         }
     }
 
+    /// Describes every route defined within this `Router`, such as to generate documentation or
+    /// to list the routes while debugging. Routes defined through `RouterBuilder::host` are listed
+    /// first, followed by the other routes, in the order requests are matched against them.
+    ///
+    /// Routes of a `Router` which requests are delegated to aren't included, and are instead
+    /// described by a single delegated route.
+    ///
+    /// ```rust
+    /// # extern crate gotham;
+    /// # extern crate hyper;
+    /// #
+    /// # use hyper::Method;
+    /// # use gotham::router::builder::*;
+    /// # use gotham::state::State;
+    /// #
+    /// fn show_user(state: State) -> (State, &'static str) {
+    ///     (state, "user")
+    /// }
+    ///
+    /// # fn main() {
+    /// let router = build_simple_router(|route| {
+    ///     route.get_or_head("/users/:id").to(show_user);
+    /// });
+    ///
+    /// let routes = router.routes();
+    /// assert_eq!(routes.len(), 1);
+    /// assert_eq!(routes[0].path(), "/users/:id");
+    /// assert_eq!(routes[0].methods(), Some(&[Method::GET, Method::HEAD][..]));
+    /// assert!(routes[0].handler().unwrap().ends_with("show_user"));
+    /// # }
+    /// ```
+    pub fn routes(&self) -> Vec<RouteDescription> {
+        let mut routes = vec![];
+
+        for host in &self.data.hosts {
+            let pattern = host.matcher.pattern();
+            introspection::describe_tree(&host.tree, Some(&pattern), &mut routes);
+        }
+
+        introspection::describe_tree(&self.data.tree, None, &mut routes);
+        routes
+    }
+
+    /// Lists the routes of the `Router` as a table, with a line for each route giving its request
+    /// methods, path template, handler and the middleware of its pipelines, in the order of
+    /// `Router::routes`. The table is intended to be logged at startup, or compared in tests to
+    /// detect routes which are removed by accident. Each route is formatted as by the `Display`
+    /// implementation of `RouteDescription`, except that the columns are aligned.
+    ///
+    /// Routes defined through `RouterBuilder::host` are shown with the host pattern ahead of
+    /// their path, and routes which don't consider the request method, such as delegated routes,
+    /// are shown with `*` methods.
+    ///
+    /// ```rust
+    /// # extern crate gotham;
+    /// #
+    /// # use gotham::router::builder::*;
+    /// # use gotham::state::State;
+    /// #
+    /// fn show_user(state: State) -> (State, &'static str) {
+    ///     (state, "user")
+    /// }
+    ///
+    /// # fn main() {
+    /// let router = build_simple_router(|route| {
+    ///     route.get_or_head("/users/:id").to(show_user);
+    ///     route.delete("/users/:id").to(show_user);
+    /// });
+    ///
+    /// let table = router.dump();
+    /// let lines: Vec<&str> = table.lines().collect();
+    /// assert_eq!(lines.len(), 2);
+    /// assert!(lines[0].starts_with("GET, HEAD  /users/:id  "));
+    /// assert!(lines[1].starts_with("DELETE     /users/:id  "));
+    /// assert!(lines[1].ends_with("show_user"));
+    /// # }
+    /// ```
+    pub fn dump(&self) -> String {
+        introspection::format_routes(&self.routes())
+    }
+
+    /// Takes the `Tree` of routes defined outside of any host, as used by `DrawRoutes::mount`.
+    ///
+    /// # Panics
+    ///
+    /// When the `Router` has been cloned, as the routes can't be shared.
+    pub(crate) fn into_tree(self) -> Tree {
+        match Arc::try_unwrap(self.data) {
+            Ok(data) => data.tree,
+            Err(_) => panic!("a Router can't be mounted once it has been cloned"),
+        }
+    }
+
+    /// Determines the `Tree` which routes the request. The first host defined through
+    /// `RouterBuilder::host` which matches the request is used, falling back to the routes
+    /// defined outside of any host.
+    fn select_tree(&self, state: &State) -> (&Tree, &UrlFor) {
+        for host in &self.data.hosts {
+            if host.matcher.is_match(state).is_ok() {
+                return (&host.tree, &host.url_for);
+            }
+        }
+
+        (&self.data.tree, &self.data.url_for)
+    }
+
+    /// Extracts the label captured from the host of the request into `State`, where the first
+    /// host which matches the request was defined through `RouterBuilder::host_with_extractor`.
+    fn extract_host(&self, state: &mut State) -> Option<Response<Body>> {
+        let host = self
+            .data
+            .hosts
+            .iter()
+            .find(|host| host.matcher.is_match(state).is_ok());
+
+        if let Some(HostTree {
+            matcher,
+            extraction: Some(extract),
+            ..
+        }) = host
+        {
+            let capture = request_host(state).and_then(|host| matcher.capture(host));
+            if let Some((name, label)) = capture {
+                return extract(state, name, &label);
+            }
+        }
+
+        None
+    }
+
+    /// Routes the request, before the response is finalized.
+    fn handle_request(&self, mut state: State) -> Box<HandlerFuture> {
+        let (tree, url_for) = self.select_tree(&state);
+
+        // each router decodes the query string for its own routes
+        state.put(self.data.options.query_string);
+
+        if self.data.options.method_override {
+            override_method(&mut state);
+        }
+
+        if let Some(mut res) = self.extract_host(&mut state) {
+            describe_extraction_error(&state, &mut res);
+            return Box::new(future::ok((state, res)));
+        }
+
+        // a delegated router leaves the names of the outermost router in place
+        if !state.has::<UrlFor>() {
+            state.put(url_for.clone());
+        }
+
+        match state.try_take::<RequestPathSegments>() {
+            Some(rps) if !self.data.versions.is_empty() => {
+                let ignore_case = self.data.options.path_case != PathCase::Sensitive;
+                let (rps, version) = self.data.versions.select(&state, tree, rps, ignore_case);
+                let version = version.cloned();
+                let router = self.clone();
+
+                let f = self
+                    .route(state, rps, Excluded::default())
+                    .map(move |(state, mut res)| {
+                        router.data.versions.extend(version.as_ref(), &mut res);
+                        (state, res)
+                    });
+                Box::new(f)
+            }
+            Some(rps) => self.route(state, rps, Excluded::default()),
+            None => {
+                trace!("[{}] invalid request path segments", request_id(&state));
+                let res = create_empty_response(&state, StatusCode::INTERNAL_SERVER_ERROR);
+                self.error_response(state, res)
+            }
+        }
+    }
+
+    /// Routes the request again as a request to another path, where the handler of the route
+    /// put `Forward` into `State`, having been forwarded `forwards` times already.
+    fn follow_forwards(&self, future: Box<HandlerFuture>, forwards: usize) -> Box<HandlerFuture> {
+        let router = self.clone();
+        let f = future.and_then(move |(mut state, res)| match state.try_take::<Forward>() {
+            Some(_) if forwards == MAX_FORWARDS => {
+                error!(
+                    "[{}] request was forwarded more than {} times",
+                    request_id(&state),
+                    MAX_FORWARDS
+                );
+                let res = create_empty_response(&state, StatusCode::INTERNAL_SERVER_ERROR);
+                Either::B(future::ok((state, res)))
+            }
+            Some(forward) => {
+                let uri = forward.into_uri();
+                trace!("[{}] forwarding to {}", request_id(&state), uri);
+                ResponseExtenders::discard(&mut state);
+                state.put(RequestPathSegments::new(uri.path()));
+                state.put(uri);
+
+                let future = router.handle_request(state);
+                Either::A(router.follow_forwards(future, forwards + 1))
+            }
+            None => Either::B(future::ok((state, res))),
+        });
+
+        Box::new(f)
+    }
+
+    /// Routes the request to the best matching route which isn't excluded, following on to the
+    /// next matching route where the handler falls through.
+    fn route(
+        &self,
+        state: State,
+        rps: RequestPathSegments,
+        excluded: Excluded,
+    ) -> Box<HandlerFuture> {
+        if self.data.options.route_tracing == RouteTracing::Disabled {
+            return self.route_traced(state, rps, excluded, &mut None);
+        }
+
+        let id = request_id(&state).to_owned();
+        let mut trace = Some(vec![]);
+        if !excluded.routes.is_empty() {
+            record(&mut trace, || {
+                "routing again after a route fell through".to_owned()
+            });
+        }
+
+        let future = self.route_traced(state, rps, excluded, &mut trace);
+        let trace = trace.unwrap_or_default();
+
+        for entry in &trace {
+            debug!("[{}] route trace: {}", id, entry);
+        }
+
+        if self.data.options.route_tracing == RouteTracing::Header && cfg!(debug_assertions) {
+            let f = future.map(move |(state, mut res)| {
+                let headers = res.headers_mut();
+                for entry in trace {
+                    let value = HeaderValue::from_str(&entry)
+                        .or_else(|_| HeaderValue::from_str(&entry.escape_default().to_string()));
+                    if let Ok(value) = value {
+                        headers.append(ROUTE_TRACE, value);
+                    }
+                }
+                (state, res)
+            });
+            return Box::new(f);
+        }
+
+        future
+    }
+
+    /// Routes the request as described by `route`, describing each step in the `trace`, where
+    /// the `Router` traces requests.
+    fn route_traced(
+        &self,
+        mut state: State,
+        rps: RequestPathSegments,
+        mut excluded: Excluded,
+        trace: &mut Option<Vec<String>>,
+    ) -> Box<HandlerFuture> {
+        let (tree, _) = self.select_tree(&state);
+        let ignore_case = self.data.options.path_case != PathCase::Sensitive;
+
+        let (node, params, processed, folded) = match tree.traverse_with(
+            rps.segments(),
+            ignore_case,
+            &excluded.nodes,
+            trace.as_mut(),
+        ) {
+            Some(traversal) => traversal,
+            None if excluded.routes.is_empty() => {
+                trace!("[{}] did not find routable node", request_id(&state));
+                record(trace, || {
+                    "no routable node matched the request path".to_owned()
+                });
+                let res = create_empty_response(&state, StatusCode::NOT_FOUND);
+                return self.error_response(state, res);
+            }
+            None => {
+                // a delegating router may have routes left to fall through to
+                trace!("[{}] no route left to fall through to", request_id(&state));
+                record(trace, || "no route was left to fall through to".to_owned());
+                state.put(Fallthrough);
+                let res = create_empty_response(&state, StatusCode::NOT_FOUND);
+                return self.error_response(state, res);
+            }
+        };
+
+        if self.data.options.path_case == PathCase::RedirectToCanonical && !folded.is_empty() {
+            record(trace, || {
+                "redirecting to the path with the case of its routes".to_owned()
+            });
+            let res = self.canonical_path_response(&state, &rps, &folded);
+            return Box::new(future::ok((state, res)));
+        }
+
+        match node.select_route_excluding(&state, &excluded.routes, trace.as_mut()) {
+            Ok(route) => {
+                let id = route_id(route.as_ref());
+                let future =
+                    self.dispatch_route(state, &rps, node, processed, params, route.as_ref());
+                self.fall_through_from(future, rps, excluded, id)
+            }
+            Err(_) if !excluded.routes.is_empty() => {
+                // pass over the node once a route has fallen through
+                excluded.nodes.push(node.id());
+                self.route_traced(state, rps, excluded, trace)
+            }
+            Err(non_match) => {
+                let (status, allow) = non_match.deconstruct();
+
+                if self.is_automatic_head(&state, status, &allow) {
+                    // Route the request as a `GET` request, restoring the method once a
+                    // response has been produced.
+                    state.put(Method::GET);
+                    if let Ok(route) = node.select_route(&state) {
+                        trace!("[{}] dispatching HEAD to GET route", request_id(&state));
+                        record(trace, || {
+                            "dispatching the HEAD request to a GET route".to_owned()
+                        });
+                        let id = route_id(route.as_ref());
+                        let future = self.dispatch_route(
+                            state,
+                            &rps,
+                            node,
+                            processed,
+                            params,
+                            route.as_ref(),
+                        );
+                        return self.fall_through_from(discard_body(future), rps, excluded, id);
+                    }
+                    state.put(Method::HEAD);
+                }
+
+                record(trace, || {
+                    format!("no route matched, responding with {}", status)
+                });
+                let res = self.non_match_response(&state, status, allow);
+                self.error_response(state, res)
+            }
+        }
+    }
+
+    /// Routes the request again, excluding the route it was dispatched to, where the handler of
+    /// the route put `Fallthrough` into `State`.
+    fn fall_through_from(
+        &self,
+        future: Box<HandlerFuture>,
+        rps: RequestPathSegments,
+        mut excluded: Excluded,
+        route: usize,
+    ) -> Box<HandlerFuture> {
+        let router = self.clone();
+        let f = future.and_then(
+            move |(mut state, res)| match state.try_take::<Fallthrough>() {
+                Some(Fallthrough) => {
+                    trace!("[{}] falling through to next route", request_id(&state));
+                    ResponseExtenders::discard(&mut state);
+                    excluded.routes.push(route);
+                    Either::A(router.route(state, rps, excluded))
+                }
+                None => Either::B(future::ok((state, res))),
+            },
+        );
+
+        Box::new(f)
+    }
+
+    fn dispatch_route<'a>(
+        &self,
+        mut state: State,
+        rps: &RequestPathSegments,
+        node: &Node,
+        processed: usize,
+        params: SegmentMapping<'a>,
+        route: &(dyn Route<ResBody = Body> + Send + Sync),
+    ) -> Box<HandlerFuture> {
+        if let Some(res) = self.scheme_response(&state, node) {
+            return self.error_response(state, res);
+        }
+
+        if let Some(template) = node.template() {
+            state.put(template.clone());
+        }
+
+        match route.delegation() {
+            Delegation::External => {
+                trace!("[{}] delegating to secondary router", request_id(&state));
+
+                if !params.is_empty() {
+                    state.put(DelegatedSegments::from_mapping(&params));
+                }
+                state.put(rps.subsegments(processed));
+                route.dispatch(state)
+            }
+            Delegation::Internal => {
+                if let Some(res) = self.trailing_slash_response(&state, node) {
+                    return self.error_response(state, res);
+                }
+
+                let limit = route.body_limit().or_else(|| node.body_limit());
+                let limit = limit.map(BodyLimit::new);
+                if let Some(ref limit) = limit {
+                    if let Some(res) = limit.apply(&mut state) {
+                        return self.error_response(state, res);
+                    }
+                }
+
+                trace!("[{}] dispatching to route", request_id(&state));
+                let mut future = self.dispatch(state, node.path_extractions(), params, route);
+                if let Some(limit) = limit {
+                    future = limit.finish(future);
+                }
+
+                let extenders = node.response_extenders();
+                if extenders.is_empty() {
+                    future
+                } else {
+                    extenders.extend(future)
+                }
+            }
+        }
+    }
+
+    /// Creates the response for a request whose scheme doesn't satisfy the `SchemePolicy` of the
+    /// node it was routed to, if any.
+    fn scheme_response(&self, state: &State, node: &Node) -> Option<Response<Body>> {
+        let status = match (node.scheme_policy()?, request_scheme(state)) {
+            (SchemePolicy::Https, Scheme::Https)
+            | (SchemePolicy::Http, Scheme::Http)
+            | (SchemePolicy::RedirectToHttps(_), Scheme::Https) => return None,
+            (SchemePolicy::RedirectToHttps(status), Scheme::Http) => status,
+            _ => {
+                trace!("[{}] scheme does not match route", request_id(state));
+                return Some(create_empty_response(state, StatusCode::NOT_FOUND));
+            }
+        };
+
+        let host = match request_host(state) {
+            Some(host) => host,
+            None => {
+                trace!("[{}] unable to redirect without a host", request_id(state));
+                return Some(create_empty_response(state, StatusCode::BAD_REQUEST));
+            }
+        };
+
+        let uri = Uri::borrow_from(state);
+        let location = match uri.query() {
+            Some(query) => format!("https://{}{}?{}", host, uri.path(), query),
+            None => format!("https://{}{}", host, uri.path()),
+        };
+
+        trace!("[{}] redirecting to {}", request_id(state), location);
+        let mut res = create_empty_response(state, status);
+        res.headers_mut()
+            .insert(LOCATION, location.parse().unwrap());
+        Some(res)
+    }
+
+    /// Creates the response for a request whose path doesn't satisfy the `TrailingSlash` policy,
+    /// if any.
+    fn trailing_slash_response(&self, state: &State, node: &Node) -> Option<Response<Body>> {
+        let uri = Uri::borrow_from(state);
+        let path = uri.path();
+        let trailing_slash = has_trailing_slash(path);
+
+        let with_slash = match self.data.options.trailing_slash {
+            TrailingSlash::MatchBoth => return None,
+            TrailingSlash::Strict => {
+                if node.accepts_trailing_slash(trailing_slash) {
+                    return None;
+                }
+
+                trace!(
+                    "[{}] trailing slash does not match route",
+                    request_id(state)
+                );
+                return Some(create_empty_response(state, StatusCode::NOT_FOUND));
+            }
+            TrailingSlash::RedirectToSlash if !trailing_slash && path != "/" => true,
+            TrailingSlash::RedirectToNoSlash if trailing_slash => false,
+            _ => return None,
+        };
+
+        // the location is built from the segments the request was routed by, so that empty
+        // segments can't make it a protocol-relative URL such as `//example.com/`
+        let segments: Vec<&str> = split_path_segments(path)
+            .filter(|segment| PercentDecoded::new(segment).is_some())
+            .collect();
+        let location = join_segments(&segments, with_slash);
+
+        let location = match uri.query() {
+            Some(query) => format!("{}?{}", location, query),
+            None => location,
+        };
+
+        trace!("[{}] redirecting to {}", request_id(state), location);
+        Some(create_permanent_redirect(state, location))
+    }
+
+    /// Creates the redirect for a request whose path only matched regardless of case, replacing
+    /// each of the `folded` segments with the segment of the route it matched.
+    fn canonical_path_response(
+        &self,
+        state: &State,
+        rps: &RequestPathSegments,
+        folded: &[(usize, &str)],
+    ) -> Response<Body> {
+        let uri = Uri::borrow_from(state);
+        let mut segments: Vec<String> = split_path_segments(uri.path())
+            .filter(|segment| PercentDecoded::new(segment).is_some())
+            .map(str::to_owned)
+            .collect();
+
+        // a delegated router only sees the end of the request path
+        let offset = segments.len().saturating_sub(rps.segments().len());
+        for &(index, canonical) in folded {
+            if let Some(segment) = segments.get_mut(offset + index) {
+                *segment = utf8_percent_encode(canonical, PATH_SEGMENT).to_string();
+            }
+        }
+
+        let mut location = join_segments(&segments, has_trailing_slash(uri.path()));
+
+        if let Some(query) = uri.query() {
+            location = format!("{}?{}", location, query);
+        }
+
+        trace!("[{}] redirecting to {}", request_id(state), location);
+        create_permanent_redirect(state, location)
+    }
+
+    /// Responds with a response generated by the `Router`, unless a handler was registered to
+    /// replace responses with its status.
+    fn error_response(&self, state: State, res: Response<Body>) -> Box<HandlerFuture> {
+        let handler = match res.status() {
+            StatusCode::NOT_FOUND => self.data.error_handlers.not_found.as_ref(),
+            StatusCode::INTERNAL_SERVER_ERROR => self.data.error_handlers.internal_error.as_ref(),
+            _ => None,
+        };
+
+        match handler {
+            Some(handler) => {
+                trace!(
+                    "[{}] replacing {} response",
+                    request_id(&state),
+                    res.status()
+                );
+                handler(state)
+            }
+            None => Box::new(future::ok((state, res))),
+        }
+    }
+
+    /// Determines whether a `HEAD` request which didn't match any route should be dispatched to
+    /// the route for `GET` requests to the same path.
+    fn is_automatic_head(&self, state: &State, status: StatusCode, allow: &[Method]) -> bool {
+        self.data.options.automatic_head
+            && status == StatusCode::METHOD_NOT_ALLOWED
+            && *Method::borrow_from(state) == Method::HEAD
+            && allow.contains(&Method::GET)
+    }
+
+    /// Creates the response for a request which didn't match any route at the node it was
+    /// routed to, populating the `Allow` header where the request method wasn't permitted, unless
+    /// the `Router` was configured to respond with `404 Not Found` instead.
+    fn non_match_response(
+        &self,
+        state: &State,
+        status: StatusCode,
+        mut allow: Vec<Method>,
+    ) -> Response<Body> {
+        if status != StatusCode::METHOD_NOT_ALLOWED {
+            trace!("[{}] responding with error status", request_id(state));
+            return create_empty_response(state, status);
+        }
+
+        if self.data.options.automatic_head
+            && allow.contains(&Method::GET)
+            && !allow.contains(&Method::HEAD)
+        {
+            allow.push(Method::HEAD);
+        }
+
+        let status = if self.data.options.automatic_options
+            && *Method::borrow_from(state) == Method::OPTIONS
+        {
+            trace!("[{}] responding with allowed methods", request_id(state));
+            allow.push(Method::OPTIONS);
+            StatusCode::OK
+        } else if self.data.options.hide_allowed_methods {
+            trace!("[{}] hiding allowed methods", request_id(state));
+            return create_empty_response(state, StatusCode::NOT_FOUND);
+        } else {
+            trace!("[{}] responding with error status", request_id(state));
+            status
+        };
+
+        allow.sort_unstable_by(|a, b| a.as_ref().cmp(b.as_ref()));
+
+        let mut res = create_empty_response(state, status);
+        for allowed in allow {
+            res.headers_mut()
+                .append(ALLOW, allowed.as_str().to_string().parse().unwrap());
+        }
+        res
+    }
+
     fn dispatch<'a>(
         &self,
         mut state: State,
+        extractions: &[PathExtraction],
         params: SegmentMapping<'a>,
-        route: &Box<dyn Route<ResBody = Body> + Send + Sync>,
+        route: &(dyn Route<ResBody = Body> + Send + Sync),
     ) -> Box<HandlerFuture> {
+        for extract in extractions {
+            if let Some(mut res) = extract(&mut state, &params) {
+                error!(
+                    "[{}] the server cannot or will not process the request due to a client error on the request path",
+                    request_id(&state)
+                );
+                describe_extraction_error(&state, &mut res);
+                return Box::new(future::ok((state, res)));
+            }
+        }
+
         match route.extract_request_path(&mut state, params) {
             Ok(()) => {
                 trace!("[{}] extracted request path", request_id(&state));
@@ -158,6 +972,7 @@ impl Router {
 
                         let mut res = Response::new(Body::empty());
                         route.extend_response_on_query_string_error(&mut state, &mut res);
+                        describe_extraction_error(&state, &mut res);
                         Box::new(future::ok((state, res)))
                     }
                 }
@@ -169,6 +984,7 @@ impl Router {
                 );
                 let mut res = Response::new(Body::empty());
                 route.extend_response_on_path_error(&mut state, &mut res);
+                describe_extraction_error(&state, &mut res);
                 Box::new(future::ok((state, res)))
             }
         }
@@ -176,36 +992,161 @@ impl Router {
 
     fn finalize_response(&self, result: Box<HandlerFuture>) -> Box<HandlerFuture> {
         let response_finalizer = self.data.response_finalizer.clone();
+        let router = self.clone();
         let f = result
-            .or_else(|(state, err)| {
-                trace!(
-                    "[{}] converting error into http response \
-                     during finalization: {:?}",
-                    request_id(&state),
-                    err
-                );
-                let response = err.into_response(&state);
-                future::ok((state, response))
+            .or_else(move |(mut state, err)| {
+                if err.status().is_server_error() {
+                    if let Some(ref handler) = router.data.error_handlers.internal_error {
+                        trace!(
+                            "[{}] handling error during finalization: {:?}",
+                            request_id(&state),
+                            err
+                        );
+                        state.put(err);
+                        return Either::A(handler(state).or_else(error_into_response));
+                    }
+                }
+
+                Either::B(error_into_response((state, err)))
             })
             .and_then(move |(state, res)| {
                 trace!("[{}] handler complete", request_id(&state));
                 response_finalizer.finalize(state, res)
             });
 
-        Box::new(f)
+        Box::new(f)
+    }
+}
+
+/// Replaces the method of a `POST` request in `State` with the method named by its
+/// `X-HTTP-Method-Override` header, where the header names a valid method.
+fn override_method(state: &mut State) {
+    if *Method::borrow_from(state) != Method::POST {
+        return;
+    }
+
+    let method = HeaderMap::try_borrow_from(state)
+        .and_then(|headers| headers.get(METHOD_OVERRIDE))
+        .and_then(|value| value.to_str().ok())
+        .and_then(|value| Method::from_bytes(value.trim().to_ascii_uppercase().as_bytes()).ok());
+
+    if let Some(method) = method {
+        trace!(
+            "[{}] routing POST request as {} for method override",
+            request_id(state),
+            method
+        );
+        state.put(method);
+    }
+}
+
+/// Records a step of routing a request, where the `Router` traces requests.
+fn record<F>(trace: &mut Option<Vec<String>>, entry: F)
+where
+    F: FnOnce() -> String,
+{
+    if let Some(ref mut trace) = trace {
+        trace.push(entry());
+    }
+}
+
+/// The routes which a request has fallen through, and the nodes whose routes it can no longer be
+/// dispatched to, as identified by `route_id` and `Node::id`.
+#[derive(Default)]
+struct Excluded {
+    routes: Vec<usize>,
+    nodes: Vec<usize>,
+}
+
+/// Converts a `HandlerError` into the response which is sent in its place.
+fn error_into_response(
+    (state, err): (State, HandlerError),
+) -> future::FutureResult<(State, Response<Body>), (State, HandlerError)> {
+    trace!(
+        "[{}] converting error into http response \
+         during finalization: {:?}",
+        request_id(&state),
+        err
+    );
+    let response = err.into_response(&state);
+    future::ok((state, response))
+}
+
+/// Uses the `ExtractionError` as the body of a `400 Bad Request` response to a request whose path
+/// or query string couldn't be extracted, where the extender didn't provide a body.
+fn describe_extraction_error(state: &State, res: &mut Response<Body>) {
+    if res.status() != StatusCode::BAD_REQUEST || res.body().content_length() != Some(0) {
+        return;
+    }
+
+    if let Some(err) = state.try_borrow::<ExtractionError>() {
+        res.headers_mut().insert(
+            CONTENT_TYPE,
+            mime::TEXT_PLAIN_UTF_8.as_ref().parse().unwrap(),
+        );
+        *res.body_mut() = Body::from(err.message().to_owned());
+    }
+}
+
+/// Discards the body of the response to a `HEAD` request which was dispatched to a `GET` route,
+/// keeping the status and headers, and restores the request method in `State`. The length of
+/// the body is kept as the `Content-Length` header, where known.
+fn discard_body(future: Box<HandlerFuture>) -> Box<HandlerFuture> {
+    let f = future.then(|result| {
+        let result = match result {
+            Ok((mut state, res)) => {
+                state.put(Method::HEAD);
+                let (mut parts, body) = res.into_parts();
+                if let Some(len) = body.content_length() {
+                    parts
+                        .headers
+                        .entry(CONTENT_LENGTH)
+                        .unwrap()
+                        .or_insert(len.into());
+                }
+                Ok((state, Response::from_parts(parts, Body::empty())))
+            }
+            Err((mut state, err)) => {
+                state.put(Method::HEAD);
+                Err((state, err))
+            }
+        };
+        future::result(result)
+    });
+
+    Box::new(f)
+}
+
+/// Joins path segments onto a single leading `/`, with a trailing `/` if requested and there is
+/// at least one segment.
+fn join_segments<S>(segments: &[S], trailing_slash: bool) -> String
+where
+    S: Borrow<str>,
+{
+    let mut path = format!("/{}", segments.join("/"));
+    if trailing_slash && !segments.is_empty() {
+        path.push('/');
     }
+    path
 }
 
 #[cfg(test)]
 mod tests {
     use super::*;
-    use hyper::header::{HeaderMap, CONTENT_LENGTH};
+    use futures::Stream;
+    use hyper::header::{HeaderMap, LOCATION};
     use hyper::{Body, Method, Uri};
+    use serde_derive::Deserialize;
     use std::str::FromStr;
 
     use crate::extractor::{NoopPathExtractor, NoopQueryStringExtractor};
     use crate::handler::HandlerError;
+    use crate::helpers::http::response::create_response;
     use crate::pipeline::set::*;
+    use crate::router::builder::*;
+    use crate::router::fallthrough::fall_through;
+    use crate::router::forward::forward;
+    use crate::router::response::extender::StaticResponseExtender;
     use crate::router::response::finalizer::ResponseFinalizerBuilder;
     use crate::router::route::dispatch::DispatcherImpl;
     use crate::router::route::matcher::MethodOnlyRouteMatcher;
@@ -213,7 +1154,8 @@ mod tests {
     use crate::router::tree::node::Node;
     use crate::router::tree::segment::SegmentType;
     use crate::router::tree::Tree;
-    use crate::state::set_request_id;
+    use crate::state::{set_request_id, StateData};
+    use crate::test::TestServer;
 
     fn handler(state: State) -> (State, Response<Body>) {
         (state, Response::new(Body::empty()))
@@ -402,4 +1344,860 @@ mod tests {
             Err(_) => unreachable!("Router should have correctly handled request"),
         };
     }
+
+    #[test]
+    fn automatic_options_lists_allowed_methods() {
+        let router = build_simple_router(|route| {
+            route.automatic_options(true);
+            route.get("/").to(handler);
+            route.post("/").to(handler);
+            route.options("/explicit").to(|state| {
+                let res = create_empty_response(&state, StatusCode::ACCEPTED);
+                (state, res)
+            });
+        });
+
+        match send_request(router.clone(), Method::OPTIONS, "https://test.gotham.rs") {
+            Ok((_state, res)) => {
+                assert_eq!(res.status(), StatusCode::OK);
+                let allow: Vec<_> = res.headers().get_all(ALLOW).iter().collect();
+                assert_eq!(allow, vec!["GET", "OPTIONS", "POST"]);
+            }
+            Err(_) => unreachable!("Router should have handled request"),
+        };
+
+        match send_request(
+            router.clone(),
+            Method::OPTIONS,
+            "https://test.gotham.rs/explicit",
+        ) {
+            Ok((_state, res)) => assert_eq!(res.status(), StatusCode::ACCEPTED),
+            Err(_) => unreachable!("Router should have handled request"),
+        };
+
+        match send_request(router, Method::OPTIONS, "https://test.gotham.rs/missing") {
+            Ok((_state, res)) => assert_eq!(res.status(), StatusCode::NOT_FOUND),
+            Err(_) => unreachable!("Router should have handled request"),
+        };
+    }
+
+    #[test]
+    fn automatic_options_disabled_by_default() {
+        let router = build_simple_router(|route| {
+            route.get("/").to(handler);
+        });
+
+        match send_request(router, Method::OPTIONS, "https://test.gotham.rs") {
+            Ok((_state, res)) => {
+                assert_eq!(res.status(), StatusCode::METHOD_NOT_ALLOWED);
+                assert_eq!(res.headers()[ALLOW], "GET");
+            }
+            Err(_) => unreachable!("Router should have handled request"),
+        };
+    }
+
+    #[test]
+    fn method_not_allowed_can_be_hidden() {
+        let router = build_simple_router(|route| {
+            route.method_not_allowed(false);
+            route.get("/").to(handler);
+        });
+
+        match send_request(router.clone(), Method::POST, "https://test.gotham.rs") {
+            Ok((_state, res)) => {
+                assert_eq!(res.status(), StatusCode::NOT_FOUND);
+                assert!(res.headers().get(ALLOW).is_none());
+            }
+            Err(_) => unreachable!("Router should have handled request"),
+        };
+
+        match send_request(router, Method::GET, "https://test.gotham.rs") {
+            Ok((_state, res)) => assert_eq!(res.status(), StatusCode::OK),
+            Err(_) => unreachable!("Router should have handled request"),
+        };
+    }
+
+    #[test]
+    fn automatic_head_dispatches_to_get_route() {
+        let router = build_simple_router(|route| {
+            route.automatic_head(true);
+            route.automatic_options(true);
+            route.get("/").to(|state| {
+                assert_eq!(*Method::borrow_from(&state), Method::GET);
+                let res = create_response(&state, StatusCode::OK, mime::TEXT_PLAIN, "Hello!");
+                (state, res)
+            });
+            route.post("/form").to(handler);
+        });
+
+        match send_request(router.clone(), Method::HEAD, "https://test.gotham.rs") {
+            Ok((state, res)) => {
+                assert_eq!(*Method::borrow_from(&state), Method::HEAD);
+                assert_eq!(res.status(), StatusCode::OK);
+                assert_eq!(res.headers()[CONTENT_LENGTH], "6");
+                let body = res.into_body().concat2().wait().unwrap();
+                assert!(body.is_empty());
+            }
+            Err(_) => unreachable!("Router should have handled request"),
+        };
+
+        match send_request(router.clone(), Method::OPTIONS, "https://test.gotham.rs") {
+            Ok((_state, res)) => {
+                let allow: Vec<_> = res.headers().get_all(ALLOW).iter().collect();
+                assert_eq!(allow, vec!["GET", "HEAD", "OPTIONS"]);
+            }
+            Err(_) => unreachable!("Router should have handled request"),
+        };
+
+        match send_request(router, Method::HEAD, "https://test.gotham.rs/form") {
+            Ok((_state, res)) => {
+                assert_eq!(res.status(), StatusCode::METHOD_NOT_ALLOWED);
+                assert_eq!(res.headers()[ALLOW], "POST");
+            }
+            Err(_) => unreachable!("Router should have handled request"),
+        };
+    }
+
+    #[test]
+    fn method_override_routes_post_requests() {
+        let router = |enabled| {
+            build_simple_router(|route| {
+                route.method_override(enabled);
+                route.post("/users/1").to(|state| (state, "updated"));
+                route.delete("/users/1").to(|state| {
+                    assert_eq!(*Method::borrow_from(&state), Method::DELETE);
+                    (state, "deleted")
+                });
+                route.get("/users/1").to(|state| (state, "shown"));
+            })
+        };
+
+        let request = |router, method: &'static str| {
+            let test_server = TestServer::new(router).unwrap();
+            let response = test_server
+                .client()
+                .post("http://localhost/users/1", Body::empty(), mime::TEXT_PLAIN)
+                .with_header(METHOD_OVERRIDE, HeaderValue::from_static(method))
+                .perform()
+                .unwrap();
+            response.read_utf8_body().unwrap()
+        };
+
+        assert_eq!(request(router(true), "DELETE"), "deleted");
+        assert_eq!(request(router(true), "delete"), "deleted");
+        assert_eq!(request(router(true), "not a method"), "updated");
+        assert_eq!(request(router(false), "DELETE"), "updated");
+
+        // only POST requests are overridden
+        let test_server = TestServer::new(router(true)).unwrap();
+        let response = test_server
+            .client()
+            .get("http://localhost/users/1")
+            .with_header(METHOD_OVERRIDE, HeaderValue::from_static("DELETE"))
+            .perform()
+            .unwrap();
+        assert_eq!(response.read_utf8_body().unwrap(), "shown");
+    }
+
+    #[test]
+    fn response_extenders_of_routes_and_scopes() {
+        fn unavailable(state: State) -> (State, Response<Body>) {
+            let res = create_empty_response(&state, StatusCode::SERVICE_UNAVAILABLE);
+            (state, res)
+        }
+
+        fn body(text: &'static str) -> impl Fn(&mut State, &mut Response<Body>) + Clone {
+            move |_state, res| *res.body_mut() = Body::from(text)
+        }
+
+        let router = build_simple_router(|route| {
+            route.add_response_extender(StatusCode::SERVICE_UNAVAILABLE, body("router"));
+            route.scope("/api", |route| {
+                route.add_response_extender(StatusCode::SERVICE_UNAVAILABLE, body("scope"));
+                route.get("/status").to(unavailable);
+                route
+                    .get("/jobs")
+                    .add_response_extender(StatusCode::SERVICE_UNAVAILABLE, body("route"))
+                    .to(unavailable);
+                route.get("/jobs/:id").to(unavailable);
+            });
+            route.get("/").to(unavailable);
+        });
+
+        let body = |uri: &str| match send_request(router.clone(), Method::GET, uri) {
+            Ok((_state, res)) => {
+                assert_eq!(res.status(), StatusCode::SERVICE_UNAVAILABLE);
+                let body = res.into_body().concat2().wait().unwrap();
+                String::from_utf8(body.to_vec()).unwrap()
+            }
+            Err(_) => unreachable!("Router should have handled request"),
+        };
+
+        assert_eq!(body("https://test.gotham.rs/"), "router");
+        assert_eq!(body("https://test.gotham.rs/api/status"), "scope");
+        assert_eq!(body("https://test.gotham.rs/api/jobs"), "route");
+        assert_eq!(body("https://test.gotham.rs/api/jobs/1"), "scope");
+    }
+
+    #[test]
+    fn body_limits_of_routes_and_scopes() {
+        let router = build_simple_router(|route| {
+            route.scope("/api", |route| {
+                route.body_limit(8);
+                route.post("/users").to(handler);
+                route.post("/uploads").with_body_limit(64).to(handler);
+                route.scope("/bulk", |route| {
+                    route.body_limit(32);
+                    route.post("/users").to(handler);
+                });
+            });
+            route.post("/").to(handler);
+        });
+
+        let test_server = TestServer::new(router).unwrap();
+        let status = |uri: &str, len: usize| {
+            test_server
+                .client()
+                .post(uri, vec![b'a'; len], mime::TEXT_PLAIN)
+                .perform()
+                .unwrap()
+                .status()
+        };
+
+        assert_eq!(status("http://localhost/", 128), StatusCode::OK);
+        assert_eq!(status("http://localhost/api/users", 8), StatusCode::OK);
+        assert_eq!(
+            status("http://localhost/api/users", 9),
+            StatusCode::PAYLOAD_TOO_LARGE
+        );
+        assert_eq!(status("http://localhost/api/uploads", 64), StatusCode::OK);
+        assert_eq!(
+            status("http://localhost/api/uploads", 65),
+            StatusCode::PAYLOAD_TOO_LARGE
+        );
+        assert_eq!(
+            status("http://localhost/api/bulk/users", 32),
+            StatusCode::OK
+        );
+        assert_eq!(
+            status("http://localhost/api/bulk/users", 33),
+            StatusCode::PAYLOAD_TOO_LARGE
+        );
+    }
+
+    #[test]
+    fn trailing_slash_policies() {
+        fn router(policy: TrailingSlash) -> Router {
+            build_simple_router(|route| {
+                route.trailing_slash(policy);
+                route.get("/").to(handler);
+                route.get("/users").to(handler);
+                route.get("/groups/").to(handler);
+            })
+        }
+
+        let status =
+            |router: &Router, uri: &str| match send_request(router.clone(), Method::GET, uri) {
+                Ok((_state, res)) => (
+                    res.status(),
+                    res.headers()
+                        .get(LOCATION)
+                        .map(|l| l.to_str().unwrap().to_owned()),
+                ),
+                Err(_) => unreachable!("Router should have handled request"),
+            };
+
+        let ok = (StatusCode::OK, None);
+        let not_found = (StatusCode::NOT_FOUND, None);
+        let redirect = |location: &str| (StatusCode::PERMANENT_REDIRECT, Some(location.to_owned()));
+
+        let match_both = router(TrailingSlash::MatchBoth);
+        assert_eq!(status(&match_both, "https://test.gotham.rs/users"), ok);
+        assert_eq!(status(&match_both, "https://test.gotham.rs/users/"), ok);
+        assert_eq!(status(&match_both, "https://test.gotham.rs/groups"), ok);
+
+        let strict = router(TrailingSlash::Strict);
+        assert_eq!(status(&strict, "https://test.gotham.rs/"), ok);
+        assert_eq!(status(&strict, "https://test.gotham.rs/users"), ok);
+        assert_eq!(status(&strict, "https://test.gotham.rs/users/"), not_found);
+        assert_eq!(status(&strict, "https://test.gotham.rs/groups/"), ok);
+        assert_eq!(status(&strict, "https://test.gotham.rs/groups"), not_found);
+
+        let to_slash = router(TrailingSlash::RedirectToSlash);
+        assert_eq!(status(&to_slash, "https://test.gotham.rs/"), ok);
+        assert_eq!(status(&to_slash, "https://test.gotham.rs/users/"), ok);
+        assert_eq!(
+            status(&to_slash, "https://test.gotham.rs/users?page=2"),
+            redirect("/users/?page=2")
+        );
+        assert_eq!(
+            status(&to_slash, "https://test.gotham.rs/missing"),
+            not_found
+        );
+        assert_eq!(
+            status(&to_slash, "https://test.gotham.rs//users"),
+            redirect("/users/")
+        );
+
+        let to_no_slash = router(TrailingSlash::RedirectToNoSlash);
+        assert_eq!(status(&to_no_slash, "https://test.gotham.rs/"), ok);
+        assert_eq!(status(&to_no_slash, "https://test.gotham.rs/groups"), ok);
+        assert_eq!(
+            status(&to_no_slash, "https://test.gotham.rs/groups/"),
+            redirect("/groups")
+        );
+        assert_eq!(
+            status(&to_no_slash, "https://test.gotham.rs//groups//"),
+            redirect("/groups")
+        );
+        assert_eq!(
+            status(&to_no_slash, "https://test.gotham.rs///"),
+            redirect("/")
+        );
+    }
+
+    #[test]
+    fn host_routes() {
+        fn accepted(state: State) -> (State, Response<Body>) {
+            let res = create_empty_response(&state, StatusCode::ACCEPTED);
+            (state, res)
+        }
+
+        fn created(state: State) -> (State, Response<Body>) {
+            let res = create_empty_response(&state, StatusCode::CREATED);
+            (state, res)
+        }
+
+        let router = build_simple_router(|route| {
+            route.host("api.example.com", |route| {
+                route.get("/").to(accepted);
+            });
+            route.host("*.example.com", |route| {
+                route.get("/").to(created);
+                route.get("/tenant").to(created);
+            });
+            route.get("/").to(handler);
+            route.get("/tenant").to(handler);
+        });
+
+        let status = |uri: &str| match send_request(router.clone(), Method::GET, uri) {
+            Ok((_state, res)) => res.status(),
+            Err(_) => unreachable!("Router should have handled request"),
+        };
+
+        assert_eq!(status("https://api.example.com/"), StatusCode::ACCEPTED);
+        assert_eq!(
+            status("https://API.example.com:8443/"),
+            StatusCode::ACCEPTED
+        );
+        assert_eq!(status("https://acme.example.com/"), StatusCode::CREATED);
+        assert_eq!(
+            status("https://acme.example.com/tenant"),
+            StatusCode::CREATED
+        );
+        assert_eq!(status("https://example.com/tenant"), StatusCode::OK);
+        assert_eq!(status("https://test.gotham.rs/"), StatusCode::OK);
+
+        // routes outside of the host aren't used for its requests
+        assert_eq!(
+            status("https://api.example.com/tenant"),
+            StatusCode::NOT_FOUND
+        );
+    }
+
+    #[test]
+    fn host_extractor_routes() {
+        #[derive(Deserialize)]
+        struct Shard {
+            shard: u8,
+        }
+
+        impl StateData for Shard {}
+
+        impl StaticResponseExtender for Shard {
+            type ResBody = Body;
+
+            fn extend(_state: &mut State, res: &mut Response<Body>) {
+                *res.status_mut() = StatusCode::BAD_REQUEST;
+            }
+        }
+
+        let router = build_simple_router(|route| {
+            route.host_with_extractor::<Shard, _>("{shard}.example.com", |route| {
+                route.get("/").to(handler);
+            });
+            route.get("/").to(handler);
+        });
+
+        match send_request(router.clone(), Method::GET, "https://12.example.com/") {
+            Ok((state, res)) => {
+                assert_eq!(res.status(), StatusCode::OK);
+                assert_eq!(state.borrow::<Shard>().shard, 12);
+            }
+            Err(_) => unreachable!("Router should have handled request"),
+        };
+
+        match send_request(router.clone(), Method::GET, "https://eu.example.com/") {
+            Ok((state, res)) => {
+                assert_eq!(res.status(), StatusCode::BAD_REQUEST);
+                assert!(!state.has::<Shard>());
+            }
+            Err(_) => unreachable!("Router should have handled request"),
+        };
+
+        // a host with more than one label before the suffix isn't matched
+        match send_request(router, Method::GET, "https://eu.12.example.com/") {
+            Ok((state, res)) => {
+                assert_eq!(res.status(), StatusCode::OK);
+                assert!(!state.has::<Shard>());
+            }
+            Err(_) => unreachable!("Router should have handled request"),
+        };
+    }
+
+    #[test]
+    fn scheme_scopes() {
+        let router = build_simple_router(|route| {
+            route.scheme_scope("/secure", SchemePolicy::Https, |route| {
+                route.get("/").to(handler);
+                route.scheme_scope("/legacy", SchemePolicy::Http, |route| {
+                    route.get("/").to(handler);
+                });
+            });
+            let redirect = SchemePolicy::RedirectToHttps(StatusCode::MOVED_PERMANENTLY);
+            route.scheme_scope("/account", redirect, |route| {
+                route.get("/").to(handler);
+            });
+            route.get("/secure/later").to(handler);
+            route.get("/").to(handler);
+        });
+
+        let status = |uri: &str| match send_request(router.clone(), Method::GET, uri) {
+            Ok((_state, res)) => (
+                res.status(),
+                res.headers()
+                    .get(LOCATION)
+                    .map(|l| l.to_str().unwrap().to_owned()),
+            ),
+            Err(_) => unreachable!("Router should have handled request"),
+        };
+
+        let ok = (StatusCode::OK, None);
+        let not_found = (StatusCode::NOT_FOUND, None);
+
+        assert_eq!(status("https://test.gotham.rs/secure"), ok);
+        assert_eq!(status("http://test.gotham.rs/secure"), not_found);
+        assert_eq!(status("http://test.gotham.rs/secure/later"), not_found);
+        assert_eq!(status("http://test.gotham.rs/secure/legacy"), ok);
+        assert_eq!(status("https://test.gotham.rs/secure/legacy"), not_found);
+        assert_eq!(status("http://test.gotham.rs/"), ok);
+
+        assert_eq!(status("https://test.gotham.rs/account"), ok);
+        assert_eq!(
+            status("http://test.gotham.rs:8080/account?tab=2"),
+            (
+                StatusCode::MOVED_PERMANENTLY,
+                Some("https://test.gotham.rs/account?tab=2".to_owned())
+            )
+        );
+    }
+
+    #[test]
+    fn path_case_policies() {
+        fn accepted(state: State) -> (State, Response<Body>) {
+            let res = create_empty_response(&state, StatusCode::ACCEPTED);
+            (state, res)
+        }
+
+        let build = |policy| {
+            build_simple_router(|route| {
+                route.path_case(policy);
+                route.get("/Users/:name").to(handler);
+                route.get("/users/admin").to(accepted);
+            })
+        };
+
+        let request =
+            |router: &Router, uri: &str| match send_request(router.clone(), Method::GET, uri) {
+                Ok((_state, res)) => (
+                    res.status(),
+                    res.headers()
+                        .get(LOCATION)
+                        .map(|location| location.to_str().unwrap().to_owned()),
+                ),
+                Err(_) => unreachable!("Router should have handled request"),
+            };
+
+        let router = build(PathCase::Sensitive);
+        assert_eq!(
+            request(&router, "https://test.gotham.rs/USERS/alice").0,
+            StatusCode::NOT_FOUND
+        );
+
+        let router = build(PathCase::Insensitive);
+        assert_eq!(
+            request(&router, "https://test.gotham.rs/USERS/alice").0,
+            StatusCode::OK
+        );
+        // an exact match is preferred over one regardless of case
+        assert_eq!(
+            request(&router, "https://test.gotham.rs/users/admin").0,
+            StatusCode::ACCEPTED
+        );
+        assert_eq!(
+            request(&router, "https://test.gotham.rs/Users/admin").0,
+            StatusCode::OK
+        );
+
+        let router = build(PathCase::RedirectToCanonical);
+        assert_eq!(
+            request(&router, "https://test.gotham.rs/USERS/Alice/?page=2"),
+            (
+                StatusCode::PERMANENT_REDIRECT,
+                Some("/Users/Alice/?page=2".to_owned())
+            )
+        );
+        assert_eq!(
+            request(&router, "https://test.gotham.rs/Users/alice"),
+            (StatusCode::OK, None)
+        );
+    }
+
+    #[test]
+    fn error_handlers_replace_router_responses() {
+        fn not_found(state: State) -> (State, Response<Body>) {
+            let res = create_response(
+                &state,
+                StatusCode::NOT_FOUND,
+                mime::APPLICATION_JSON,
+                r#"{"error":"not found"}"#,
+            );
+            (state, res)
+        }
+
+        fn internal_error(state: State) -> (State, Response<Body>) {
+            let body = match state.try_borrow::<HandlerError>() {
+                Some(err) => format!(r#"{{"error":{}}}"#, err.status().as_u16()),
+                None => r#"{"error":"internal"}"#.to_owned(),
+            };
+            let res = create_response(
+                &state,
+                StatusCode::INTERNAL_SERVER_ERROR,
+                mime::APPLICATION_JSON,
+                body,
+            );
+            (state, res)
+        }
+
+        fn missing(state: State) -> (State, Response<Body>) {
+            let res = create_empty_response(&state, StatusCode::NOT_FOUND);
+            (state, res)
+        }
+
+        fn failing(state: State) -> Box<HandlerFuture> {
+            let err = std::io::Error::other("unavailable")
+                .into_handler_error()
+                .with_status(StatusCode::SERVICE_UNAVAILABLE);
+            Box::new(future::err((state, err)))
+        }
+
+        fn rejected(state: State) -> Box<HandlerFuture> {
+            let err = std::io::Error::other("rejected")
+                .into_handler_error()
+                .with_status(StatusCode::BAD_REQUEST);
+            Box::new(future::err((state, err)))
+        }
+
+        let router = build_simple_router(|route| {
+            route.not_found(not_found);
+            route.internal_error(internal_error);
+            route.get("/").to(handler);
+            route.get("/missing").to(missing);
+            route.get("/failing").to(failing);
+            route.get("/rejected").to(rejected);
+        });
+
+        let request = |uri: &str| match send_request(router.clone(), Method::GET, uri) {
+            Ok((_state, res)) => {
+                let status = res.status();
+                let body = res.into_body().concat2().wait().unwrap().to_vec();
+                (status, String::from_utf8(body).unwrap())
+            }
+            Err(_) => unreachable!("Router should have handled request"),
+        };
+
+        assert_eq!(
+            request("https://test.gotham.rs/unknown"),
+            (StatusCode::NOT_FOUND, r#"{"error":"not found"}"#.to_owned())
+        );
+        assert_eq!(
+            request("https://test.gotham.rs/failing"),
+            (
+                StatusCode::INTERNAL_SERVER_ERROR,
+                r#"{"error":503}"#.to_owned()
+            )
+        );
+
+        // responses from the handlers of routes are left as they are
+        assert_eq!(
+            request("https://test.gotham.rs/missing"),
+            (StatusCode::NOT_FOUND, String::new())
+        );
+        assert_eq!(
+            request("https://test.gotham.rs/rejected"),
+            (StatusCode::BAD_REQUEST, String::new())
+        );
+    }
+
+    #[test]
+    fn handlers_fall_through_to_later_routes() {
+        fn responder(body: &'static str) -> impl Fn(State) -> (State, Response<Body>) + Clone {
+            move |state| {
+                let res = create_response(&state, StatusCode::OK, mime::TEXT_PLAIN, body);
+                (state, res)
+            }
+        }
+
+        fn page(state: State) -> (State, Response<Body>) {
+            match Uri::borrow_from(&state).path() {
+                "/pages/about" => responder("about")(state),
+                _ => fall_through(state),
+            }
+        }
+
+        fn draft(state: State) -> (State, Response<Body>) {
+            match Uri::borrow_from(&state).path() {
+                "/pages/draft" => responder("draft")(state),
+                _ => fall_through(state),
+            }
+        }
+
+        let router = build_simple_router(|route| {
+            route.get("/pages/:slug").to(page);
+            route.get("/pages/:slug").to(draft);
+            route.post("/pages/:slug").to(responder("posted"));
+            route.get("/pages/*").to(responder("file"));
+            route.get("/missing").to(fall_through);
+            route
+                .delegate("/nested")
+                .to_router(build_simple_router(|route| {
+                    route.get("/:slug").to(fall_through);
+                }));
+            route.get("/nested/*").to(responder("outer"));
+        });
+
+        let request = |uri: &str| match send_request(router.clone(), Method::GET, uri) {
+            Ok((_state, res)) => {
+                let status = res.status();
+                let body = res.into_body().concat2().wait().unwrap().to_vec();
+                (status, String::from_utf8(body).unwrap())
+            }
+            Err(_) => unreachable!("Router should have handled request"),
+        };
+
+        assert_eq!(
+            request("https://test.gotham.rs/pages/about"),
+            (StatusCode::OK, "about".to_owned())
+        );
+        assert_eq!(
+            request("https://test.gotham.rs/pages/draft"),
+            (StatusCode::OK, "draft".to_owned())
+        );
+        assert_eq!(
+            request("https://test.gotham.rs/pages/index.html"),
+            (StatusCode::OK, "file".to_owned())
+        );
+        assert_eq!(
+            request("https://test.gotham.rs/missing"),
+            (StatusCode::NOT_FOUND, String::new())
+        );
+        assert_eq!(
+            request("https://test.gotham.rs/nested/page"),
+            (StatusCode::OK, "outer".to_owned())
+        );
+    }
+
+    #[test]
+    fn scopes_extract_their_path_segments() {
+        macro_rules! path_extractor {
+            ($name:ident { $field:ident }) => {
+                #[derive(Deserialize)]
+                struct $name {
+                    $field: u64,
+                }
+
+                impl StateData for $name {}
+
+                impl StaticResponseExtender for $name {
+                    type ResBody = Body;
+
+                    fn extend(_state: &mut State, res: &mut Response<Body>) {
+                        *res.status_mut() = StatusCode::BAD_REQUEST;
+                    }
+                }
+            };
+        }
+
+        path_extractor!(OrgPath { org_id });
+        path_extractor!(UserPath { user_id });
+        path_extractor!(PostPath { post_id });
+
+        fn post(state: State) -> (State, Response<Body>) {
+            let body = format!(
+                "{}/{}/{}",
+                OrgPath::borrow_from(&state).org_id,
+                UserPath::borrow_from(&state).user_id,
+                PostPath::borrow_from(&state).post_id
+            );
+            let res = create_response(&state, StatusCode::OK, mime::TEXT_PLAIN, body);
+            (state, res)
+        }
+
+        fn user(state: State) -> (State, Response<Body>) {
+            let body = UserPath::borrow_from(&state).user_id.to_string();
+            let res = create_response(&state, StatusCode::OK, mime::TEXT_PLAIN, body);
+            (state, res)
+        }
+
+        let router = build_simple_router(|route| {
+            route.scope("/orgs/:org_id", |route| {
+                route.scope("/users/:user_id", |route| {
+                    route.get("/").to(user);
+                    route.with_path_extractor::<UserPath>();
+                    route
+                        .get("/posts/:post_id")
+                        .with_path_extractor::<PostPath>()
+                        .to(post);
+                });
+                route.with_path_extractor::<OrgPath>();
+            });
+        });
+
+        let request = |uri: &str| match send_request(router.clone(), Method::GET, uri) {
+            Ok((_state, res)) => {
+                let status = res.status();
+                let body = res.into_body().concat2().wait().unwrap().to_vec();
+                (status, String::from_utf8(body).unwrap())
+            }
+            Err(_) => unreachable!("Router should have handled request"),
+        };
+
+        assert_eq!(
+            request("https://test.gotham.rs/orgs/1/users/2/posts/3"),
+            (StatusCode::OK, "1/2/3".to_owned())
+        );
+        assert_eq!(
+            request("https://test.gotham.rs/orgs/1/users/2"),
+            (StatusCode::OK, "2".to_owned())
+        );
+        assert_eq!(
+            request("https://test.gotham.rs/orgs/x/users/2/posts/3").0,
+            StatusCode::BAD_REQUEST
+        );
+        assert_eq!(
+            request("https://test.gotham.rs/orgs/1/users/x/posts/3").0,
+            StatusCode::BAD_REQUEST
+        );
+    }
+
+    #[test]
+    fn handlers_forward_to_other_paths() {
+        struct Legacy;
+
+        impl StateData for Legacy {}
+
+        fn legacy(mut state: State) -> (State, Response<Body>) {
+            state.put(Legacy);
+            forward(state, "/users/me?tab=profile")
+        }
+
+        fn profile(state: State) -> (State, Response<Body>) {
+            let body = format!("{} {}", Uri::borrow_from(&state), state.has::<Legacy>());
+            let res = create_response(&state, StatusCode::OK, mime::TEXT_PLAIN, body);
+            (state, res)
+        }
+
+        let router = build_simple_router(|route| {
+            route.get("/profile.php").to(legacy);
+            route.get("/users/me").to(profile);
+            route.get("/loop").to(|state| forward(state, "/loop"));
+            route
+                .delegate("/nested")
+                .to_router(build_simple_router(|route| {
+                    route
+                        .get("/profile")
+                        .to(|state| forward(state, "/profile.php"));
+                }));
+        });
+
+        let request = |uri: &str| match send_request(router.clone(), Method::GET, uri) {
+            Ok((_state, res)) => {
+                let status = res.status();
+                let body = res.into_body().concat2().wait().unwrap().to_vec();
+                (status, String::from_utf8(body).unwrap())
+            }
+            Err(_) => unreachable!("Router should have handled request"),
+        };
+
+        assert_eq!(
+            request("https://test.gotham.rs/profile.php"),
+            (
+                StatusCode::OK,
+                "https://test.gotham.rs/users/me?tab=profile true".to_owned()
+            )
+        );
+        assert_eq!(
+            request("https://test.gotham.rs/nested/profile"),
+            (
+                StatusCode::OK,
+                "https://test.gotham.rs/users/me?tab=profile true".to_owned()
+            )
+        );
+        assert_eq!(
+            request("https://test.gotham.rs/loop").0,
+            StatusCode::INTERNAL_SERVER_ERROR
+        );
+    }
+
+    #[test]
+    fn route_tracing_describes_matching() {
+        let router = build_simple_router(|route| {
+            route.route_tracing(RouteTracing::Header);
+            route.scope("/api", |route| {
+                route.post("/users/:id").to(handler);
+            });
+        });
+
+        let trace = |method: Method, uri: &str| match send_request(router.clone(), method, uri) {
+            Ok((_state, res)) => res
+                .headers()
+                .get_all(ROUTE_TRACE)
+                .iter()
+                .map(|value| value.to_str().unwrap().to_owned())
+                .collect::<Vec<_>>(),
+            Err(_) => unreachable!("Router should have handled request"),
+        };
+
+        assert_eq!(
+            trace(Method::GET, "https://test.gotham.rs/api/users/1"),
+            vec![
+                "segment `api` matched `api`",
+                "segment `users` matched `users`",
+                "segment `1` matched `:id`",
+                "`:id` matched the request path",
+                "route 0 of `:id` (gotham::router::tests::handler) didn't match, with status \
+                 405 Method Not Allowed",
+                "no route matched, responding with 405 Method Not Allowed",
+            ]
+        );
+
+        assert_eq!(
+            trace(Method::GET, "https://test.gotham.rs/api/posts"),
+            vec![
+                "segment `api` matched `api`",
+                "segment `posts` matched no child of `api`",
+                "no routable node matched the request path",
+            ]
+        );
+    }
 }
@@ -122,7 +122,11 @@ fn higher_precedence_status(lhs: StatusCode, rhs: StatusCode) -> StatusCode {
 }
 
 // This customised set prevents memory allocations while computing `Allow` lists, except in the
-// case where extension methods are provided using the `hyper::Method::Extension` variant.
+// case where extension methods are provided, such as with `Method::from_bytes(b"PROPFIND")`.
+//
+// Extension methods can't be enumerated, so a set which doesn't restrict them (as created by
+// `RouteNonMatch::new`) holds `None`, which keeps any extension methods from the other set when
+// taking an intersection or union.
 #[derive(Clone)]
 struct MethodSet {
     connect: bool,
@@ -134,7 +138,7 @@ struct MethodSet {
     post: bool,
     put: bool,
     trace: bool,
-    other: HashSet<Method>,
+    other: Option<HashSet<Method>>,
 }
 
 impl MethodSet {
@@ -149,7 +153,10 @@ impl MethodSet {
             post: self.post && other.post,
             put: self.put && other.put,
             trace: self.trace && other.trace,
-            other: self.other.intersection(&other.other).cloned().collect(),
+            other: match (self.other, other.other) {
+                (Some(lhs), Some(rhs)) => Some(lhs.intersection(&rhs).cloned().collect()),
+                (lhs, rhs) => lhs.or(rhs),
+            },
         }
     }
 
@@ -164,7 +171,10 @@ impl MethodSet {
             post: self.post || other.post,
             put: self.put || other.put,
             trace: self.trace || other.trace,
-            other: self.other.union(&other.other).cloned().collect(),
+            other: match (self.other, other.other) {
+                (Some(lhs), Some(rhs)) => Some(lhs.union(&rhs).cloned().collect()),
+                (lhs, rhs) => lhs.or(rhs),
+            },
         }
     }
 }
@@ -181,7 +191,7 @@ impl Default for MethodSet {
             post: true,
             put: true,
             trace: false,
-            other: HashSet::default(),
+            other: None,
         }
     }
 }
@@ -249,7 +259,7 @@ impl<'a> From<&'a [Method]> for MethodSet {
             post,
             put,
             trace,
-            other,
+            other: Some(other),
         }
     }
 }
@@ -271,7 +281,7 @@ impl From<MethodSet> for Vec<Method> {
         let mut result = methods_with_flags
             .iter()
             .filter_map(|&(ref method, flag)| if flag { Some(method.clone()) } else { None })
-            .chain(method_set.other.into_iter())
+            .chain(method_set.other.unwrap_or_default())
             .collect::<Vec<Method>>();
 
         result.sort_unstable_by(|a, b| a.as_ref().cmp(b.as_ref()));
@@ -403,4 +413,36 @@ mod tests {
             ]
         );
     }
+
+    #[test]
+    fn extension_method_tests() {
+        let propfind = Method::from_bytes(b"PROPFIND").unwrap();
+
+        let (status, allow_list) = RouteNonMatch::new(StatusCode::NOT_ACCEPTABLE)
+            .intersection(
+                RouteNonMatch::new(StatusCode::METHOD_NOT_ALLOWED)
+                    .with_allow_list(&[Method::GET, propfind.clone()]),
+            )
+            .deconstruct();
+        assert_eq!(status, StatusCode::NOT_ACCEPTABLE);
+        assert_eq!(&allow_list[..], &[Method::GET, propfind.clone()]);
+
+        let (_, allow_list) = RouteNonMatch::new(StatusCode::METHOD_NOT_ALLOWED)
+            .with_allow_list(&[Method::GET])
+            .intersection(
+                RouteNonMatch::new(StatusCode::METHOD_NOT_ALLOWED)
+                    .with_allow_list(&[Method::GET, propfind.clone()]),
+            )
+            .deconstruct();
+        assert_eq!(&allow_list[..], &[Method::GET]);
+
+        let (_, allow_list) = RouteNonMatch::new(StatusCode::METHOD_NOT_ALLOWED)
+            .with_allow_list(&[Method::GET])
+            .union(
+                RouteNonMatch::new(StatusCode::METHOD_NOT_ALLOWED)
+                    .with_allow_list(&[Method::HEAD, propfind.clone()]),
+            )
+            .deconstruct();
+        assert_eq!(&allow_list[..], &[Method::GET, Method::HEAD, propfind]);
+    }
 }
@@ -0,0 +1,422 @@
+//! Defines `OpenApi`, which generates an OpenAPI 3.0 document describing the routes of a `Router`.
+
+use std::sync::{Arc, RwLock};
+
+use hyper::{Body, Method, Response, StatusCode};
+use serde_json::{json, Map, Value};
+
+use crate::error::Result;
+use crate::handler::{Handler, HandlerFuture, IntoHandlerFuture, NewHandler};
+use crate::helpers::http::response::create_response;
+use crate::router::introspection::{FieldDescription, RouteDescription};
+use crate::router::Router;
+use crate::state::State;
+
+/// Generates an [OpenAPI 3.0](https://spec.openapis.org/oas/v3.0.3) document which describes the
+/// routes of a `Router`, as provided by `Router::routes`.
+///
+/// Each route becomes an operation of its path, with a parameter for each dynamic segment of the
+/// path and each field of its `QueryStringExtractor`. The types of parameters are taken from the
+/// `PathExtractor` and `QueryStringExtractor` where they're structs, and are otherwise described
+/// as strings. Request and response bodies aren't described, as they're produced by handlers.
+///
+/// Routes for hosts are included alongside the others, and routes of a `Router` which requests
+/// are delegated to aren't included. The document can be served by the `Router` it describes
+/// using `RouterBuilder::openapi`.
+///
+/// # Examples
+///
+/// ```rust
+/// # extern crate gotham;
+/// # #[macro_use]
+/// # extern crate gotham_derive;
+/// # #[macro_use]
+/// # extern crate serde_derive;
+/// #
+/// # use gotham::router::builder::*;
+/// # use gotham::router::openapi::OpenApi;
+/// # use gotham::state::State;
+/// #
+/// # #[allow(dead_code)]
+/// #[derive(Deserialize, StateData, StaticResponseExtender)]
+/// struct UserPath {
+///     id: u64,
+/// }
+///
+/// fn show_user(state: State) -> (State, &'static str) {
+///     (state, "user")
+/// }
+///
+/// # fn main() {
+/// let router = build_simple_router(|route| {
+///     route
+///         .get("/users/:id")
+///         .with_path_extractor::<UserPath>()
+///         .to(show_user);
+/// });
+///
+/// let document = OpenApi::new("Users", "1.0.0").document(&router);
+/// let parameter = &document["paths"]["/users/{id}"]["get"]["parameters"][0];
+///
+/// assert_eq!(parameter["name"], "id");
+/// assert_eq!(parameter["in"], "path");
+/// assert_eq!(parameter["schema"]["type"], "integer");
+/// # }
+/// ```
+#[derive(Clone, Debug)]
+pub struct OpenApi {
+    title: String,
+    version: String,
+    description: Option<String>,
+}
+
+impl OpenApi {
+    /// Creates an `OpenApi` for an API with the given title and version.
+    pub fn new(title: &str, version: &str) -> Self {
+        OpenApi {
+            title: title.to_owned(),
+            version: version.to_owned(),
+            description: None,
+        }
+    }
+
+    /// Sets the description of the API.
+    pub fn with_description(&mut self, description: &str) -> &mut Self {
+        self.description = Some(description.to_owned());
+        self
+    }
+
+    /// Generates the document which describes the routes of the `Router`.
+    pub fn document(&self, router: &Router) -> Value {
+        let mut info = json!({
+            "title": self.title,
+            "version": self.version,
+        });
+
+        if let Some(ref description) = self.description {
+            info["description"] = json!(description);
+        }
+
+        let mut paths = Map::new();
+        for route in router.routes() {
+            describe_route(&route, &mut paths);
+        }
+
+        json!({
+            "openapi": "3.0.3",
+            "info": info,
+            "paths": paths,
+        })
+    }
+}
+
+/// Adds the operations of a route to the paths of the document. Where another route already
+/// describes the same operation, such as for another host, the first is kept.
+fn describe_route(route: &RouteDescription, paths: &mut Map<String, Value>) {
+    let methods = match route.methods() {
+        Some(methods) if !route.is_delegated() => methods,
+        _ => return,
+    };
+
+    let mut path = String::new();
+    let mut parameters = vec![];
+
+    for segment in route.path().split('/').filter(|s| !s.is_empty()) {
+        path.push('/');
+
+        let (name, pattern) = if segment == "*" {
+            (segment, None)
+        } else if let Some(dynamic) = segment.strip_prefix(':') {
+            let mut parts = dynamic.splitn(2, ':');
+            (parts.next().unwrap(), parts.next())
+        } else {
+            path.push_str(segment);
+            continue;
+        };
+
+        path.push('{');
+        path.push_str(name);
+        path.push('}');
+
+        let mut schema = match route.path_fields().iter().find(|f| f.name() == name) {
+            Some(field) => field_schema(field),
+            None => json!({ "type": "string" }),
+        };
+        if let Some(pattern) = pattern {
+            schema["pattern"] = json!(format!("^{}$", pattern));
+        }
+
+        parameters.push(json!({
+            "name": name,
+            "in": "path",
+            "required": true,
+            "schema": schema,
+        }));
+    }
+
+    if path.is_empty() || route.path().ends_with('/') {
+        path.push('/');
+    }
+
+    for field in route.query_string_fields() {
+        parameters.push(json!({
+            "name": field.name(),
+            "in": "query",
+            "required": field.is_required(),
+            "schema": field_schema(field),
+        }));
+    }
+
+    let description = match route.handler() {
+        Some(handler) => format!("The response of `{}`.", handler),
+        None => "The response of the route.".to_owned(),
+    };
+
+    let mut operation = json!({
+        "responses": {
+            "default": { "description": description },
+        },
+    });
+    if !parameters.is_empty() {
+        operation["parameters"] = Value::Array(parameters);
+    }
+
+    let item = paths
+        .entry(path)
+        .or_insert_with(|| Value::Object(Map::new()));
+
+    for method in methods {
+        if let Some(name) = operation_name(method) {
+            if let Value::Object(ref mut item) = *item {
+                item.entry(name).or_insert_with(|| operation.clone());
+            }
+        }
+    }
+}
+
+/// The name of the operation for the method within a path item, for the methods which an OpenAPI
+/// document is able to describe.
+fn operation_name(method: &Method) -> Option<&'static str> {
+    match *method {
+        Method::GET => Some("get"),
+        Method::PUT => Some("put"),
+        Method::POST => Some("post"),
+        Method::DELETE => Some("delete"),
+        Method::OPTIONS => Some("options"),
+        Method::HEAD => Some("head"),
+        Method::PATCH => Some("patch"),
+        Method::TRACE => Some("trace"),
+        _ => None,
+    }
+}
+
+fn field_schema(field: &FieldDescription) -> Value {
+    let mut schema = match field.kind() {
+        "array" => json!({ "type": "array", "items": { "type": "string" } }),
+        kind => json!({ "type": kind }),
+    };
+
+    if let Some(values) = field.values() {
+        schema["enum"] = json!(values);
+    }
+
+    schema
+}
+
+/// A `Handler` which responds with an OpenAPI document, as served by `RouterBuilder::openapi`.
+///
+/// The document is generated once the `Router` which serves it has been built.
+#[derive(Clone)]
+pub(crate) struct OpenApiHandler {
+    document: Arc<RwLock<String>>,
+}
+
+impl OpenApiHandler {
+    pub(crate) fn new() -> Self {
+        OpenApiHandler {
+            document: Arc::new(RwLock::new(String::new())),
+        }
+    }
+
+    /// Generates the document which is served, describing the given `Router`.
+    pub(crate) fn generate(&self, spec: &OpenApi, router: &Router) {
+        *self.document.write().unwrap() = spec.document(router).to_string();
+    }
+}
+
+impl NewHandler for OpenApiHandler {
+    type Instance = Self;
+
+    fn new_handler(&self) -> Result<Self::Instance> {
+        Ok(self.clone())
+    }
+}
+
+impl Handler for OpenApiHandler {
+    fn handle(self, state: State) -> Box<HandlerFuture> {
+        let body = self.document.read().unwrap().clone();
+        let res: Response<Body> =
+            create_response(&state, StatusCode::OK, mime::APPLICATION_JSON, body);
+        (state, res).into_handler_future()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    use serde_derive::Deserialize;
+
+    use crate::router::builder::*;
+    use crate::router::response::extender::StaticResponseExtender;
+    use crate::state::StateData;
+    use crate::test::TestServer;
+
+    #[derive(Deserialize)]
+    #[allow(dead_code)]
+    enum Order {
+        #[serde(rename = "asc")]
+        Ascending,
+        #[serde(rename = "desc")]
+        Descending,
+    }
+
+    #[derive(Deserialize)]
+    #[allow(dead_code)]
+    struct ListQuery {
+        page: Option<u32>,
+        order: Order,
+        tag: Vec<String>,
+        #[serde(rename = "q")]
+        search: String,
+    }
+
+    impl StateData for ListQuery {}
+
+    impl StaticResponseExtender for ListQuery {
+        type ResBody = Body;
+
+        fn extend(_state: &mut State, _res: &mut Response<Body>) {}
+    }
+
+    #[derive(Deserialize)]
+    #[allow(dead_code)]
+    struct ItemPath {
+        id: u64,
+    }
+
+    impl StateData for ItemPath {}
+
+    impl StaticResponseExtender for ItemPath {
+        type ResBody = Body;
+
+        fn extend(_state: &mut State, _res: &mut Response<Body>) {}
+    }
+
+    fn handler(state: State) -> (State, &'static str) {
+        (state, "ok")
+    }
+
+    fn router() -> Router {
+        build_simple_router(|route| {
+            route.openapi("/openapi.json", OpenApi::new("Items", "2.1.0"));
+            route.get_or_head("/").to(handler);
+            route
+                .get("/items")
+                .with_query_string_extractor::<ListQuery>()
+                .to(handler);
+            route
+                .request(vec![Method::PUT, Method::DELETE], "/items/:id/")
+                .with_path_extractor::<ItemPath>()
+                .to(handler);
+            route.get("/files/:kind:[a-z]+/*").to(handler);
+            route
+                .delegate("/admin")
+                .to_router(build_simple_router(|route| {
+                    route.get("/").to(handler);
+                }));
+        })
+    }
+
+    #[test]
+    fn documents_routes() {
+        let mut spec = OpenApi::new("Items", "2.1.0");
+        spec.with_description("Stored items.");
+        let document = spec.document(&router());
+
+        assert_eq!(document["openapi"], "3.0.3");
+        assert_eq!(
+            document["info"],
+            json!({ "title": "Items", "version": "2.1.0", "description": "Stored items." })
+        );
+
+        let paths = document["paths"].as_object().unwrap();
+        let mut names: Vec<_> = paths.keys().map(String::as_str).collect();
+        names.sort();
+        assert_eq!(
+            names,
+            vec![
+                "/",
+                "/files/{kind}/{*}",
+                "/items",
+                "/items/{id}/",
+                "/openapi.json"
+            ]
+        );
+
+        let root = paths["/"].as_object().unwrap();
+        assert!(root.contains_key("get") && root.contains_key("head"));
+        assert!(root["get"]["responses"]["default"]["description"]
+            .as_str()
+            .unwrap()
+            .ends_with("tests::handler`."));
+
+        assert_eq!(
+            paths["/items"]["get"]["parameters"],
+            json!([
+                { "name": "page", "in": "query", "required": false,
+                  "schema": { "type": "integer" } },
+                { "name": "order", "in": "query", "required": true,
+                  "schema": { "type": "string", "enum": ["asc", "desc"] } },
+                { "name": "tag", "in": "query", "required": true,
+                  "schema": { "type": "array", "items": { "type": "string" } } },
+                { "name": "q", "in": "query", "required": true,
+                  "schema": { "type": "string" } },
+            ])
+        );
+
+        let item = &paths["/items/{id}/"];
+        assert!(item.get("get").is_none());
+        assert_eq!(item["put"], item["delete"]);
+        assert_eq!(
+            item["put"]["parameters"],
+            json!([{ "name": "id", "in": "path", "required": true,
+                     "schema": { "type": "integer" } }])
+        );
+
+        assert_eq!(
+            paths["/files/{kind}/{*}"]["get"]["parameters"],
+            json!([
+                { "name": "kind", "in": "path", "required": true,
+                  "schema": { "type": "string", "pattern": "^[a-z]+$" } },
+                { "name": "*", "in": "path", "required": true,
+                  "schema": { "type": "string" } },
+            ])
+        );
+    }
+
+    #[test]
+    fn serves_document() {
+        let test_server = TestServer::new(router()).unwrap();
+        let response = test_server
+            .client()
+            .get("http://localhost/openapi.json")
+            .perform()
+            .unwrap();
+
+        assert_eq!(response.status(), StatusCode::OK);
+        let document: Value = serde_json::from_slice(&response.read_body().unwrap()).unwrap();
+        assert_eq!(document["info"]["title"], "Items");
+        assert!(document["paths"]["/items"]["get"].is_object());
+    }
+}
@@ -0,0 +1,91 @@
+//! Defines `DelegatedSegments`, and the `Handler` which delegates requests to a `Router` chosen
+//! at request time, as defined by `DelegateRouteBuilder::to_router_provider`.
+
+use std::collections::HashMap;
+use std::panic::RefUnwindSafe;
+use std::sync::Arc;
+
+use hyper::StatusCode;
+use log::trace;
+
+use crate::handler::{Handler, HandlerFuture, IntoHandlerFuture};
+use crate::helpers::http::response::create_empty_response;
+use crate::router::tree::segment::SegmentMapping;
+use crate::router::Router;
+use crate::state::{request_id, State, StateData};
+
+/// The dynamic segments of the path which a request was delegated beneath, such as `name` for a
+/// delegation of `/plugins/:name`. These are put into `State` before the request is dispatched to
+/// a delegated `Router`, so that a router provider can determine the `Router` to use.
+#[derive(Clone, Debug, Default, PartialEq)]
+pub struct DelegatedSegments {
+    segments: HashMap<String, String>,
+}
+
+impl DelegatedSegments {
+    pub(crate) fn from_mapping(mapping: &SegmentMapping) -> Self {
+        let segments = mapping
+            .iter()
+            .map(|(name, values)| {
+                let values: Vec<&str> = values.iter().map(|v| v.as_ref()).collect();
+                ((*name).to_owned(), values.join("/"))
+            })
+            .collect();
+
+        DelegatedSegments { segments }
+    }
+
+    /// Provides the value of the dynamic segment `name`, decoded from the request path.
+    pub fn get(&self, name: &str) -> Option<&str> {
+        self.segments.get(name).map(String::as_str)
+    }
+}
+
+impl StateData for DelegatedSegments {}
+
+/// A `Handler` which dispatches each request to the `Router` provided for it, or responds with
+/// `404 Not Found` where none is provided.
+pub(crate) struct ProvidedRouter<F>
+where
+    F: Fn(&State) -> Option<Router> + Send + Sync + RefUnwindSafe,
+{
+    provider: Arc<F>,
+}
+
+impl<F> ProvidedRouter<F>
+where
+    F: Fn(&State) -> Option<Router> + Send + Sync + RefUnwindSafe,
+{
+    pub(crate) fn new(provider: F) -> Self {
+        ProvidedRouter {
+            provider: Arc::new(provider),
+        }
+    }
+}
+
+impl<F> Clone for ProvidedRouter<F>
+where
+    F: Fn(&State) -> Option<Router> + Send + Sync + RefUnwindSafe,
+{
+    fn clone(&self) -> Self {
+        ProvidedRouter {
+            provider: self.provider.clone(),
+        }
+    }
+}
+
+impl<F> Handler for ProvidedRouter<F>
+where
+    F: Fn(&State) -> Option<Router> + Send + Sync + RefUnwindSafe,
+{
+    fn handle(self, state: State) -> Box<HandlerFuture> {
+        match (self.provider)(&state) {
+            Some(router) => router.handle(state),
+            None => {
+                trace!("[{}] no router was provided", request_id(&state));
+                let res = create_empty_response(&state, StatusCode::NOT_FOUND);
+                (state, res).into_handler_future()
+            }
+        }
+    }
+}
@@ -4,12 +4,12 @@
 use std::collections::HashMap;
 use std::sync::Arc;
 
-use futures::future;
+use futures::{future, Future};
 use hyper::{Body, Response, StatusCode};
 use log::trace;
 
-use crate::handler::HandlerFuture;
-use crate::state::{request_id, State};
+use crate::handler::{HandlerFuture, IntoResponse};
+use crate::state::{request_id, State, StateData};
 
 use crate::router::response::extender::ResponseExtender;
 
@@ -64,6 +64,17 @@ impl ResponseFinalizer {
     /// Finalize the `Response` if a `ResponseFinalizer` has been supplied for the
     /// status code assigned to the `Response`.
     pub fn finalize(&self, mut state: State, mut res: Response<Body>) -> Box<HandlerFuture> {
+        if let Some(ExtendedResponse(status)) = state.try_take::<ExtendedResponse>() {
+            if status == res.status() {
+                trace!(
+                    "[{}] {} response extended by its route",
+                    request_id(&state),
+                    status
+                );
+                return Box::new(future::ok((state, res)));
+            }
+        }
+
         match self.data.get(&res.status()) {
             Some(extender) => {
                 trace!(
@@ -85,3 +96,90 @@ impl ResponseFinalizer {
         Box::new(future::ok((state, res)))
     }
 }
+
+/// Records in `State` that the `Response` was extended by the `ResponseExtenders` of its route or
+/// scope, so that the `ResponseFinalizer` of the `Router` leaves it as it is.
+struct ExtendedResponse(StatusCode);
+
+impl StateData for ExtendedResponse {}
+
+/// The `ResponseExtender` values which apply to a single route or scope, as configured by
+/// `DefineSingleRoute::add_response_extender` and `ScopeBuilder::add_response_extender`. They're
+/// invoked in place of those of the `Router` for the same `StatusCode`.
+#[derive(Clone, Default)]
+pub(crate) struct ResponseExtenders {
+    data: HashMap<StatusCode, Arc<dyn ResponseExtender<Body> + Send + Sync>>,
+}
+
+impl ResponseExtenders {
+    /// Adds an extender for responses with the `status_code`, replacing any existing extender.
+    pub(crate) fn add(
+        &mut self,
+        status_code: StatusCode,
+        extender: Arc<dyn ResponseExtender<Body> + Send + Sync>,
+    ) {
+        trace!(" adding route response extender for {}", status_code);
+        self.data.insert(status_code, extender);
+    }
+
+    /// Adds the extenders of `other` for the status codes which don't yet have an extender.
+    pub(crate) fn inherit(&mut self, other: &ResponseExtenders) {
+        for (status_code, extender) in &other.data {
+            self.data
+                .entry(*status_code)
+                .or_insert_with(|| extender.clone());
+        }
+    }
+
+    /// Determines whether no extenders have been added.
+    pub(crate) fn is_empty(&self) -> bool {
+        self.data.is_empty()
+    }
+
+    /// Extends the `Response` produced by the `future` where an extender has been added for its
+    /// status code. An error with such a status code is converted into its `Response` first, so
+    /// that it can be extended.
+    ///
+    /// Responses which were already extended by more specific extenders are left as they are.
+    pub(crate) fn extend(&self, future: Box<HandlerFuture>) -> Box<HandlerFuture> {
+        let extenders = self.clone();
+        let f = future.then(move |result| {
+            let (mut state, mut res) = match result {
+                Ok((state, res)) => (state, res),
+                Err((state, err)) => {
+                    if !extenders.data.contains_key(&err.status()) {
+                        return Err((state, err));
+                    }
+                    let res = err.into_response(&state);
+                    (state, res)
+                }
+            };
+
+            let extended = state
+                .try_borrow::<ExtendedResponse>()
+                .map(|extended| extended.0 == res.status())
+                .unwrap_or(false);
+
+            if !extended {
+                if let Some(extender) = extenders.data.get(&res.status()) {
+                    trace!(
+                        "[{}] invoking {} route response extender",
+                        request_id(&state),
+                        res.status()
+                    );
+                    extender.extend(&mut state, &mut res);
+                    state.put(ExtendedResponse(res.status()));
+                }
+            }
+
+            Ok((state, res))
+        });
+
+        Box::new(f)
+    }
+
+    /// Forgets that a `Response` was extended, where it has been discarded.
+    pub(crate) fn discard(state: &mut State) {
+        state.try_take::<ExtendedResponse>();
+    }
+}
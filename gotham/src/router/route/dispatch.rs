@@ -1,18 +1,35 @@
 //! Defines the route `Dispatcher` and supporting types.
 
-use futures::future;
+use futures::{future, Future};
+use hyper::StatusCode;
 use log::trace;
+use std::any::type_name;
+use std::io;
 use std::panic::RefUnwindSafe;
+use std::time::Duration;
+use tokio::timer::Timeout;
 
 use crate::handler::{Handler, HandlerFuture, IntoHandlerError, NewHandler};
+use crate::helpers::http::response::create_empty_response;
 use crate::pipeline::chain::PipelineHandleChain;
 use crate::pipeline::set::PipelineSet;
-use crate::state::{request_id, State};
+use crate::router::response::finalizer::ResponseExtenders;
+use crate::state::{request_id, State, StateData};
 
 /// Used by `Router` to dispatch requests via pipelines and finally into the configured `Handler`.
 pub trait Dispatcher: RefUnwindSafe {
     /// Dispatches a request via pipelines and `Handler` represented by this `Dispatcher`.
     fn dispatch(&self, state: State) -> Box<HandlerFuture>;
+
+    /// Provides the type name of the `Handler`, where known.
+    fn handler_name(&self) -> Option<&'static str> {
+        None
+    }
+
+    /// Provides the type names of the middleware in each pipeline the request is dispatched via.
+    fn pipeline_names(&self) -> Vec<Vec<&'static str>> {
+        vec![]
+    }
 }
 
 /// Default implementation of the `Dispatcher` trait.
@@ -70,6 +87,207 @@ where
             }
         }
     }
+
+    fn handler_name(&self) -> Option<&'static str> {
+        Some(type_name::<H::Instance>())
+    }
+
+    fn pipeline_names(&self) -> Vec<Vec<&'static str>> {
+        self.pipeline_chain.pipeline_names()
+    }
+}
+
+impl Dispatcher for Box<dyn Dispatcher + Send + Sync> {
+    fn dispatch(&self, state: State) -> Box<HandlerFuture> {
+        (**self).dispatch(state)
+    }
+
+    fn handler_name(&self) -> Option<&'static str> {
+        (**self).handler_name()
+    }
+
+    fn pipeline_names(&self) -> Vec<Vec<&'static str>> {
+        (**self).pipeline_names()
+    }
+}
+
+/// Puts a value attached to a route into `State`.
+type PutExtension = Box<dyn Fn(&mut State) + Send + Sync + RefUnwindSafe>;
+
+/// Values attached to a route through `DefineSingleRoute::with_extension`, which are put into
+/// `State` whenever a request is dispatched to the route.
+#[derive(Default)]
+pub(crate) struct RouteExtensions {
+    extensions: Vec<PutExtension>,
+}
+
+impl RouteExtensions {
+    /// Adds a value, which is cloned into `State` for each request.
+    pub(crate) fn add<T>(&mut self, value: T)
+    where
+        T: StateData + Clone + Sync + RefUnwindSafe,
+    {
+        self.extensions
+            .push(Box::new(move |state| state.put(value.clone())));
+    }
+
+    /// Determines whether no values have been added.
+    pub(crate) fn is_empty(&self) -> bool {
+        self.extensions.is_empty()
+    }
+}
+
+/// A `Dispatcher` which puts the `RouteExtensions` of a route into `State` before dispatching.
+pub(crate) struct ExtensionDispatcher<D>
+where
+    D: Dispatcher,
+{
+    dispatcher: D,
+    extensions: RouteExtensions,
+}
+
+impl<D> ExtensionDispatcher<D>
+where
+    D: Dispatcher,
+{
+    /// Creates a new `ExtensionDispatcher`, which puts the `extensions` into `State` before
+    /// dispatching via `dispatcher`.
+    pub(crate) fn new(dispatcher: D, extensions: RouteExtensions) -> Self {
+        ExtensionDispatcher {
+            dispatcher,
+            extensions,
+        }
+    }
+}
+
+impl<D> Dispatcher for ExtensionDispatcher<D>
+where
+    D: Dispatcher,
+{
+    fn dispatch(&self, mut state: State) -> Box<HandlerFuture> {
+        trace!("[{}] adding route extensions", request_id(&state));
+        for put in &self.extensions.extensions {
+            put(&mut state);
+        }
+
+        self.dispatcher.dispatch(state)
+    }
+
+    fn handler_name(&self) -> Option<&'static str> {
+        self.dispatcher.handler_name()
+    }
+
+    fn pipeline_names(&self) -> Vec<Vec<&'static str>> {
+        self.dispatcher.pipeline_names()
+    }
+}
+
+/// A `Dispatcher` which extends the responses of a route with the `ResponseExtenders` added
+/// through `DefineSingleRoute::add_response_extender`.
+pub(crate) struct ResponseExtenderDispatcher<D>
+where
+    D: Dispatcher,
+{
+    dispatcher: D,
+    extenders: ResponseExtenders,
+}
+
+impl<D> ResponseExtenderDispatcher<D>
+where
+    D: Dispatcher,
+{
+    /// Creates a new `ResponseExtenderDispatcher`, which extends the responses of `dispatcher`
+    /// with the `extenders`.
+    pub(crate) fn new(dispatcher: D, extenders: ResponseExtenders) -> Self {
+        ResponseExtenderDispatcher {
+            dispatcher,
+            extenders,
+        }
+    }
+}
+
+impl<D> Dispatcher for ResponseExtenderDispatcher<D>
+where
+    D: Dispatcher,
+{
+    fn dispatch(&self, state: State) -> Box<HandlerFuture> {
+        self.extenders.extend(self.dispatcher.dispatch(state))
+    }
+
+    fn handler_name(&self) -> Option<&'static str> {
+        self.dispatcher.handler_name()
+    }
+
+    fn pipeline_names(&self) -> Vec<Vec<&'static str>> {
+        self.dispatcher.pipeline_names()
+    }
+}
+
+/// A `Dispatcher` which responds with `503 Service Unavailable` when the requests it dispatches
+/// aren't served within a time limit, as configured by `DefineSingleRoute::with_timeout`. The
+/// work of serving the request is dropped once the limit elapses.
+pub(crate) struct TimeoutDispatcher<D>
+where
+    D: Dispatcher,
+{
+    dispatcher: D,
+    timeout: Duration,
+}
+
+impl<D> TimeoutDispatcher<D>
+where
+    D: Dispatcher,
+{
+    /// Creates a new `TimeoutDispatcher`, which limits `dispatcher` to the given `timeout`.
+    pub(crate) fn new(dispatcher: D, timeout: Duration) -> Self {
+        TimeoutDispatcher {
+            dispatcher,
+            timeout,
+        }
+    }
+}
+
+impl<D> Dispatcher for TimeoutDispatcher<D>
+where
+    D: Dispatcher,
+{
+    fn dispatch(&self, state: State) -> Box<HandlerFuture> {
+        let snapshot = state.request_snapshot();
+        let timeout = self.timeout;
+
+        let f = Timeout::new(self.dispatcher.dispatch(state), timeout).or_else(move |e| {
+            if e.is_elapsed() {
+                trace!(
+                    "[{}] request not served within {:?}",
+                    request_id(&snapshot),
+                    timeout
+                );
+                let res = create_empty_response(&snapshot, StatusCode::SERVICE_UNAVAILABLE);
+                return future::ok((snapshot, res));
+            }
+
+            // Where the timeout didn't elapse, the error came from either the inner future or
+            // the timer itself.
+            match e.into_inner() {
+                Some(err) => future::err(err),
+                None => {
+                    trace!("[{}] error running timer", request_id(&snapshot));
+                    let err = io::Error::other("timer unavailable");
+                    future::err((snapshot, err.into_handler_error()))
+                }
+            }
+        });
+
+        Box::new(f)
+    }
+
+    fn handler_name(&self) -> Option<&'static str> {
+        self.dispatcher.handler_name()
+    }
+
+    fn pipeline_names(&self) -> Vec<Vec<&'static str>> {
+        self.dispatcher.pipeline_names()
+    }
 }
 
 #[cfg(test)]
@@ -78,12 +296,15 @@ mod tests {
     use std::io;
     use std::sync::Arc;
 
-    use hyper::{Body, Response, StatusCode};
+    use std::time::Instant;
+
+    use hyper::{Body, Response, StatusCode, Uri};
+    use tokio::timer::Delay;
 
     use crate::middleware::{Middleware, NewMiddleware};
     use crate::pipeline::new_pipeline;
     use crate::pipeline::set::*;
-    use crate::state::StateData;
+    use crate::state::FromState;
     use crate::test::TestServer;
 
     fn handler(state: State) -> (State, Response<Body>) {
@@ -217,4 +438,46 @@ mod tests {
         let buf = response.read_body().unwrap();
         assert_eq!(buf.as_slice(), b"24");
     }
+
+    #[test]
+    fn timeout_dispatcher_test() {
+        fn delayed(state: State, delay: Duration) -> Box<HandlerFuture> {
+            let f = Delay::new(Instant::now() + delay).then(move |result| match result {
+                Ok(()) => Ok(handler(state)),
+                Err(e) => Err((state, e.into_handler_error())),
+            });
+            Box::new(f)
+        }
+
+        let test_server = TestServer::new(|| {
+            Ok(move |mut state: State| {
+                let delay = match Uri::borrow_from(&state).path() {
+                    "/slow" => Duration::from_secs(10),
+                    _ => Duration::from_millis(0),
+                };
+                state.put(Number { value: 7 });
+
+                let new_handler = move || Ok(move |state| delayed(state, delay));
+                let pipelines = finalize_pipeline_set(new_pipeline_set());
+                let dispatcher = DispatcherImpl::new(new_handler, (), pipelines);
+                TimeoutDispatcher::new(dispatcher, Duration::from_millis(50)).dispatch(state)
+            })
+        })
+        .unwrap();
+
+        let response = test_server
+            .client()
+            .get("http://localhost/fast")
+            .perform()
+            .unwrap();
+        assert_eq!(response.status(), StatusCode::OK);
+        assert_eq!(response.read_utf8_body().unwrap(), "7");
+
+        let response = test_server
+            .client()
+            .get("http://localhost/slow")
+            .perform()
+            .unwrap();
+        assert_eq!(response.status(), StatusCode::SERVICE_UNAVAILABLE);
+    }
 }
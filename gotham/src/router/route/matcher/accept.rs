@@ -1,11 +1,10 @@
 //! Defines the `AcceptHeaderRouterMatcher`.
 
-use hyper::header::{HeaderMap, HeaderValue, ACCEPT};
+use hyper::header::{HeaderMap, ACCEPT};
 use hyper::StatusCode;
 use log::trace;
 use mime;
 
-use crate::error;
 use crate::router::non_match::RouteNonMatch;
 use crate::router::route::RouteMatcher;
 use crate::state::{request_id, FromState, State};
@@ -14,7 +13,13 @@ use crate::state::{request_id, FromState, State};
 /// includes one or more supported media types. A missing `Accept` header, or the value of `*/*`
 /// will also positvely match.
 ///
-/// Quality values within `Accept` header values are not considered by this matcher.
+/// Quality values within `Accept` header values are used to determine the `RouteMatcher::quality`
+/// of a match, so that several routes for the same path can serve different media types, with
+/// the request dispatched to the route for the most preferred type. A media type given a quality
+/// value of `0` isn't accepted.
+///
+/// Requests which don't match receive a `406 Not Acceptable` response, which can be customised
+/// with `RouterBuilder::add_response_extender`.
 ///
 /// # Examples
 ///
@@ -66,6 +71,19 @@ use crate::state::{request_id, FromState, State};
 /// headers.insert(ACCEPT, "image/*".parse().unwrap());
 /// state.put(headers);
 /// assert!(matcher.is_match(&state).is_ok());
+///
+/// // A list of media ranges with quality values
+/// let mut headers = HeaderMap::new();
+/// headers.insert(ACCEPT, "text/html, application/*;q=0.5".parse().unwrap());
+/// state.put(headers);
+/// assert!(matcher.is_match(&state).is_ok());
+/// assert_eq!(matcher.quality(&state), 0.5);
+///
+/// // A supported media type which isn't accepted
+/// let mut headers = HeaderMap::new();
+/// headers.insert(ACCEPT, "application/json;q=0, */*;q=0.1".parse().unwrap());
+/// state.put(headers);
+/// assert_eq!(matcher.quality(&state), 0.1);
 /// #
 /// #   });
 /// # }
@@ -82,41 +100,157 @@ impl AcceptHeaderRouteMatcher {
             supported_media_types,
         }
     }
+
+    /// Determines the quality value of the most preferred supported media type, or `None` where
+    /// the client has not specified an `Accept` header.
+    fn accepted_quality(&self, headers: &HeaderMap) -> Option<f32> {
+        let ranges = media_ranges(headers)?;
+
+        let quality = self
+            .supported_media_types
+            .iter()
+            .map(|supported| quality_of(&ranges, supported))
+            .fold(0.0, f32::max);
+
+        Some(quality)
+    }
 }
 
 impl RouteMatcher for AcceptHeaderRouteMatcher {
     /// Determines if the `Request` was made using an `Accept` header that includes one or more
     /// supported media types. A missing `Accept` header, or the value of `*/*` will also positvely
     /// match.
-    ///
-    /// Quality values within `Accept` header values are not considered by the matcher, as the
-    /// matcher is only able to indicate whether a successful match has been found.
     fn is_match(&self, state: &State) -> Result<(), RouteNonMatch> {
-        // Request method is valid, ensure valid Accept header
-        match HeaderMap::borrow_from(state).get(ACCEPT) {
+        match self.accepted_quality(HeaderMap::borrow_from(state)) {
             // The client has not specified an `Accept` header.
             None => Ok(()),
 
-            // Or the header is any type, so it's fine.
-            Some(header) if header == "*/*" => Ok(()),
-
-            // Otherwise we have to validate the header is a match.
-            Some(mime_header) => parse_mime_type(mime_header)
-                .map_err(|_| RouteNonMatch::new(StatusCode::NOT_ACCEPTABLE))
-                .and_then(|mime_type| {
-                    if self.supported_media_types.contains(&mime_type) {
-                        return Ok(());
-                    }
-                    trace!(
-                        "[{}] did not provide an Accept with media types supported by this Route",
-                        request_id(&state)
-                    );
-                    Err(RouteNonMatch::new(StatusCode::NOT_ACCEPTABLE))
-                }),
+            // At least one of the supported media types is acceptable.
+            Some(quality) if quality > 0.0 => Ok(()),
+
+            Some(_) => {
+                trace!(
+                    "[{}] did not provide an Accept with media types supported by this Route",
+                    request_id(state)
+                );
+                Err(RouteNonMatch::new(StatusCode::NOT_ACCEPTABLE))
+            }
         }
     }
+
+    /// Provides the quality value given by the `Accept` header to the most preferred supported
+    /// media type.
+    fn quality(&self, state: &State) -> f32 {
+        self.accepted_quality(HeaderMap::borrow_from(state))
+            .unwrap_or(1.0)
+    }
+}
+
+/// A media range from an `Accept` header, with its quality value.
+struct MediaRange {
+    mime: mime::Mime,
+    quality: f32,
 }
 
-fn parse_mime_type(hv: &HeaderValue) -> error::Result<mime::Mime> {
-    Ok(hv.to_str()?.parse()?)
+/// Parses the media ranges of all `Accept` headers, skipping any which are invalid. Where no
+/// `Accept` header is present, `None` is returned.
+fn media_ranges(headers: &HeaderMap) -> Option<Vec<MediaRange>> {
+    let mut values = headers.get_all(ACCEPT).iter().peekable();
+    values.peek()?;
+
+    let ranges = values
+        .filter_map(|value| value.to_str().ok())
+        .flat_map(|value| value.split(','))
+        .filter_map(|range| range.trim().parse::<mime::Mime>().ok())
+        .map(|mime| {
+            let quality = mime
+                .get_param("q")
+                .and_then(|q| q.as_str().parse::<f32>().ok())
+                .map_or(1.0, |q| q.clamp(0.0, 1.0));
+
+            MediaRange { mime, quality }
+        })
+        .collect();
+
+    Some(ranges)
+}
+
+/// Determines the quality value of a supported media type, taken from the most specific media
+/// range which includes it.
+fn quality_of(ranges: &[MediaRange], supported: &mime::Mime) -> f32 {
+    let mut best: Option<(u8, f32)> = None;
+
+    for range in ranges {
+        if let Some(specificity) = specificity(&range.mime, supported) {
+            best = match best {
+                Some((s, q)) if s > specificity || (s == specificity && q >= range.quality) => {
+                    Some((s, q))
+                }
+                _ => Some((specificity, range.quality)),
+            };
+        }
+    }
+
+    best.map_or(0.0, |(_, quality)| quality)
+}
+
+/// Determines how specifically the media `range` includes the `supported` media type, if at all.
+/// Wildcards in the supported media type, such as `image/*`, include any matching range.
+fn specificity(range: &mime::Mime, supported: &mime::Mime) -> Option<u8> {
+    let names_match = |a: mime::Name, b: mime::Name| a == mime::STAR || b == mime::STAR || a == b;
+
+    if !names_match(range.type_(), supported.type_())
+        || !names_match(range.subtype(), supported.subtype())
+    {
+        return None;
+    }
+
+    let specificity = if range.type_() == mime::STAR {
+        0
+    } else if range.subtype() == mime::STAR {
+        1
+    } else {
+        2
+    };
+
+    Some(specificity)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn quality(supported: Vec<mime::Mime>, accept: &[&str]) -> Option<f32> {
+        let mut headers = HeaderMap::new();
+        for value in accept {
+            headers.append(ACCEPT, value.parse().unwrap());
+        }
+        AcceptHeaderRouteMatcher::new(supported).accepted_quality(&headers)
+    }
+
+    #[test]
+    fn quality_tests() {
+        let json = || vec![mime::APPLICATION_JSON];
+
+        assert_eq!(quality(json(), &[]), None);
+        assert_eq!(quality(json(), &["application/json"]), Some(1.0));
+        assert_eq!(quality(json(), &["text/html, */*;q=0.8"]), Some(0.8));
+        assert_eq!(
+            quality(json(), &["text/html", "application/*;q=0.4"]),
+            Some(0.4)
+        );
+        assert_eq!(
+            quality(json(), &["*/*;q=0.9, application/json;q=0"]),
+            Some(0.0)
+        );
+        assert_eq!(quality(json(), &["text/plain"]), Some(0.0));
+        assert_eq!(quality(json(), &["nonsense"]), Some(0.0));
+        assert_eq!(quality(json(), &["application/json;q=2"]), Some(1.0));
+
+        let images = vec![mime::IMAGE_STAR, mime::TEXT_PLAIN];
+        assert_eq!(
+            quality(images, &["image/png;q=0.6, text/plain;q=0.3"]),
+            Some(0.6)
+        );
+    }
 }
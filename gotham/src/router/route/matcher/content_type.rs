@@ -13,6 +13,10 @@ use crate::state::{request_id, FromState, State};
 /// that includes a supported media type. The matcher will fail if the Content-Type
 /// header is missing.
 ///
+/// Media types are compared by their type and subtype, so parameters of the request media type
+/// such as the `boundary` of `multipart/form-data` are ignored. A supported media type with a
+/// `*` subtype, such as `image/*`, supports every subtype of its type.
+///
 /// # Examples
 ///
 /// ```rust
@@ -52,6 +56,12 @@ use crate::state::{request_id, FromState, State};
 /// headers.insert(CONTENT_TYPE, "application/json".parse().unwrap());
 /// state.put(headers);
 /// assert!(matcher.is_match(&state).is_ok());
+///
+/// // Parameters of the media type are ignored
+/// let mut headers = HeaderMap::new();
+/// headers.insert(CONTENT_TYPE, "application/json; charset=utf-8".parse().unwrap());
+/// state.put(headers);
+/// assert!(matcher.is_match(&state).is_ok());
 /// #
 /// #   });
 /// # }
@@ -79,10 +89,19 @@ impl RouteMatcher for ContentTypeHeaderRouteMatcher {
 
             // Header was provided.
             Some(content_type) => {
-                let mime = content_type.to_str().unwrap().parse().unwrap();
+                let mime = content_type
+                    .to_str()
+                    .ok()
+                    .and_then(|content_type| content_type.parse::<mime::Mime>().ok());
 
-                if self.supported_media_types.contains(&mime) {
-                    return Ok(());
+                if let Some(mime) = mime {
+                    if self.supported_media_types.iter().any(|supported| {
+                        supported.type_() == mime.type_()
+                            && (supported.subtype() == mime::STAR
+                                || supported.subtype() == mime.subtype())
+                    }) {
+                        return Ok(());
+                    }
                 }
 
                 trace!(
@@ -95,3 +114,33 @@ impl RouteMatcher for ContentTypeHeaderRouteMatcher {
         }
     }
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    use hyper::header::HeaderValue;
+
+    fn with_content_type(content_type: &'static str) -> State {
+        let mut state = State::new();
+        let mut headers = HeaderMap::new();
+        headers.insert(CONTENT_TYPE, HeaderValue::from_static(content_type));
+        state.put(headers);
+        state
+    }
+
+    #[test]
+    fn content_type_tests() {
+        let matcher =
+            ContentTypeHeaderRouteMatcher::new(vec![mime::MULTIPART_FORM_DATA, mime::IMAGE_STAR]);
+
+        let matches = |content_type| matcher.is_match(&with_content_type(content_type)).is_ok();
+
+        assert!(matches("multipart/form-data; boundary=X-BOUNDARY"));
+        assert!(matches("image/png"));
+        assert!(matches("IMAGE/JPEG"));
+        assert!(!matches("multipart/mixed"));
+        assert!(!matches("application/json"));
+        assert!(!matches("not a media type"));
+    }
+}
@@ -0,0 +1,253 @@
+//! Defines the `HostRouteMatcher`.
+
+use hyper::header::{HeaderMap, HOST};
+use hyper::{StatusCode, Uri};
+use log::trace;
+
+use crate::router::non_match::RouteNonMatch;
+use crate::router::route::RouteMatcher;
+use crate::state::{request_id, FromState, State};
+
+/// A `RouteMatcher` that succeeds when the `Request` was made for a given host, as determined by
+/// the `Host` header or the authority of the request URI. Ports are ignored, and hosts are
+/// compared case-insensitively.
+///
+/// A pattern starting with `*.` matches any subdomain of the remaining host, so `*.example.com`
+/// matches `api.example.com` and `eu.api.example.com`, but not `example.com`.
+///
+/// A pattern starting with a name in braces, such as `{tenant}.example.com`, matches a single
+/// label before the remaining host, so it matches `acme.example.com` but not
+/// `eu.acme.example.com`. The label can be extracted into `State` by defining the routes of the
+/// host with `RouterBuilder::host_with_extractor`.
+///
+/// # Examples
+///
+/// ```rust
+/// # extern crate gotham;
+/// # extern crate hyper;
+/// # fn main() {
+/// #   use hyper::header::{HeaderMap, HOST};
+/// #   use gotham::state::State;
+/// #   use gotham::router::route::matcher::RouteMatcher;
+/// #   use gotham::router::route::matcher::host::HostRouteMatcher;
+/// #
+/// #   State::with_new(|state| {
+/// #
+/// let matcher = HostRouteMatcher::new("*.example.com");
+///
+/// let mut headers = HeaderMap::new();
+/// headers.insert(HOST, "api.example.com:8080".parse().unwrap());
+/// state.put(headers);
+/// assert!(matcher.is_match(&state).is_ok());
+///
+/// let mut headers = HeaderMap::new();
+/// headers.insert(HOST, "example.com".parse().unwrap());
+/// state.put(headers);
+/// assert!(matcher.is_match(&state).is_err());
+/// #
+/// #   });
+/// # }
+/// ```
+#[derive(Clone, Debug)]
+pub struct HostRouteMatcher {
+    pattern: HostPattern,
+}
+
+#[derive(Clone, Debug)]
+enum HostPattern {
+    Exact(String),
+    Subdomain(String),
+    Capture { name: String, suffix: String },
+}
+
+impl HostRouteMatcher {
+    /// Creates a new `HostRouteMatcher` for the given host pattern.
+    pub fn new(pattern: &str) -> Self {
+        if pattern.starts_with('{') {
+            if let Some(end) = pattern.find("}.") {
+                let pattern = HostPattern::Capture {
+                    name: pattern[1..end].to_owned(),
+                    suffix: pattern[end + 1..].to_ascii_lowercase(),
+                };
+                return HostRouteMatcher { pattern };
+            }
+        }
+
+        let pattern = pattern.to_ascii_lowercase();
+
+        let pattern = if pattern.starts_with("*.") {
+            HostPattern::Subdomain(pattern[1..].to_owned())
+        } else {
+            HostPattern::Exact(pattern)
+        };
+
+        HostRouteMatcher { pattern }
+    }
+
+    /// Provides the host pattern, in lowercase.
+    pub(crate) fn pattern(&self) -> String {
+        match self.pattern {
+            HostPattern::Exact(ref host) => host.clone(),
+            HostPattern::Subdomain(ref suffix) => format!("*{}", suffix),
+            HostPattern::Capture {
+                ref name,
+                ref suffix,
+            } => format!("{{{}}}{}", name, suffix),
+        }
+    }
+
+    /// Provides the name of the label captured by the pattern, and the label of the `host`, in
+    /// lowercase, where the pattern captures a label and the `host` matches it.
+    pub(crate) fn capture(&self, host: &str) -> Option<(&str, String)> {
+        match self.pattern {
+            HostPattern::Capture {
+                ref name,
+                ref suffix,
+            } if self.matches(host) => {
+                let label = &host[..host.len() - suffix.len()];
+                Some((name, label.to_ascii_lowercase()))
+            }
+            _ => None,
+        }
+    }
+
+    /// Determines whether the `host`, without a port, matches the pattern.
+    fn matches(&self, host: &str) -> bool {
+        match self.pattern {
+            HostPattern::Exact(ref expected) => host.eq_ignore_ascii_case(expected),
+            HostPattern::Subdomain(ref suffix) => has_subdomain(host, suffix),
+            HostPattern::Capture { ref suffix, .. } => {
+                has_subdomain(host, suffix) && !host[..host.len() - suffix.len()].contains('.')
+            }
+        }
+    }
+}
+
+impl RouteMatcher for HostRouteMatcher {
+    /// Determines if the `Request` was made for a host matching the pattern. A request without a
+    /// host doesn't match.
+    fn is_match(&self, state: &State) -> Result<(), RouteNonMatch> {
+        match request_host(state) {
+            Some(host) if self.matches(host) => Ok(()),
+            _ => {
+                trace!(
+                    "[{}] did not request a host matching this Route",
+                    request_id(state)
+                );
+                Err(RouteNonMatch::new(StatusCode::NOT_FOUND))
+            }
+        }
+    }
+}
+
+/// Determines whether the `host` has a non-empty subdomain before the `suffix`, which begins
+/// with a `.`.
+fn has_subdomain(host: &str, suffix: &str) -> bool {
+    host.len() > suffix.len()
+        && host.is_char_boundary(host.len() - suffix.len())
+        && host[host.len() - suffix.len()..].eq_ignore_ascii_case(suffix)
+}
+
+/// Finds the host the request was made for, without a port.
+pub(crate) fn request_host(state: &State) -> Option<&str> {
+    let header = HeaderMap::try_borrow_from(state)
+        .and_then(|headers| headers.get(HOST))
+        .and_then(|host| host.to_str().ok());
+
+    let host = match header {
+        Some(host) => host,
+        None => return Uri::try_borrow_from(state).and_then(Uri::host),
+    };
+
+    // IPv6 hosts are bracketed, so the port follows the closing bracket
+    let end = match host.rfind(']') {
+        Some(n) => n + 1,
+        None => host.rfind(':').unwrap_or(host.len()),
+    };
+
+    Some(&host[..end])
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn state_for(host: Option<&str>, uri: &str) -> State {
+        let mut state = State::new();
+        let mut headers = HeaderMap::new();
+        if let Some(host) = host {
+            headers.insert(HOST, host.parse().unwrap());
+        }
+        state.put(headers);
+        state.put(uri.parse::<Uri>().unwrap());
+        state
+    }
+
+    #[test]
+    fn exact_host_tests() {
+        let matcher = HostRouteMatcher::new("API.example.com");
+
+        let state = state_for(Some("api.EXAMPLE.com"), "/");
+        assert!(matcher.is_match(&state).is_ok());
+
+        let state = state_for(Some("api.example.com:443"), "/");
+        assert!(matcher.is_match(&state).is_ok());
+
+        let state = state_for(None, "https://api.example.com/users");
+        assert!(matcher.is_match(&state).is_ok());
+
+        let state = state_for(Some("app.example.com"), "/");
+        assert!(matcher.is_match(&state).is_err());
+
+        let state = state_for(None, "/");
+        assert!(matcher.is_match(&state).is_err());
+    }
+
+    #[test]
+    fn wildcard_host_tests() {
+        let matcher = HostRouteMatcher::new("*.example.com");
+
+        let state = state_for(Some("tenant.example.com"), "/");
+        assert!(matcher.is_match(&state).is_ok());
+
+        let state = state_for(Some("a.b.example.com:8080"), "/");
+        assert!(matcher.is_match(&state).is_ok());
+
+        let state = state_for(Some("example.com"), "/");
+        assert!(matcher.is_match(&state).is_err());
+
+        let state = state_for(Some("badexample.com"), "/");
+        assert!(matcher.is_match(&state).is_err());
+    }
+
+    #[test]
+    fn capture_host_tests() {
+        let matcher = HostRouteMatcher::new("{tenant}.Example.com");
+        assert_eq!(matcher.pattern(), "{tenant}.example.com");
+
+        let state = state_for(Some("Acme.example.com:8080"), "/");
+        assert!(matcher.is_match(&state).is_ok());
+        assert_eq!(
+            matcher.capture(request_host(&state).unwrap()),
+            Some(("tenant", "acme".to_owned()))
+        );
+
+        let state = state_for(Some("eu.acme.example.com"), "/");
+        assert!(matcher.is_match(&state).is_err());
+
+        let state = state_for(Some("example.com"), "/");
+        assert!(matcher.is_match(&state).is_err());
+        assert_eq!(matcher.capture("example.com"), None);
+
+        let matcher = HostRouteMatcher::new("*.example.com");
+        assert_eq!(matcher.capture("acme.example.com"), None);
+    }
+
+    #[test]
+    fn ipv6_host_tests() {
+        let matcher = HostRouteMatcher::new("[::1]");
+
+        let state = state_for(Some("[::1]:7878"), "/");
+        assert!(matcher.is_match(&state).is_ok());
+    }
+}
@@ -4,10 +4,17 @@ pub mod accept;
 pub mod and;
 pub mod any;
 pub mod content_type;
+pub mod host;
+pub mod predicate;
+pub mod query_string;
 
 pub use self::accept::AcceptHeaderRouteMatcher;
 pub use self::and::AndRouteMatcher;
 pub use self::any::AnyRouteMatcher;
+pub use self::content_type::ContentTypeHeaderRouteMatcher;
+pub use self::host::HostRouteMatcher;
+pub use self::predicate::{PredicateRouteMatcher, RoutePredicate};
+pub use self::query_string::QueryStringRouteMatcher;
 
 use std::panic::RefUnwindSafe;
 
@@ -22,6 +29,21 @@ use crate::state::{request_id, FromState, State};
 pub trait RouteMatcher: RefUnwindSafe + Clone {
     /// Determines if the `Request` meets pre-defined conditions.
     fn is_match(&self, state: &State) -> Result<(), RouteNonMatch>;
+
+    /// Determines how well a matching `Request` is served, between `0.0` and `1.0`. Where several
+    /// routes for a path match, the `Router` dispatches to the one with the highest quality, such
+    /// as the route producing the media type most preferred by the `Accept` header.
+    ///
+    /// By default, every matching `Request` is served equally well.
+    fn quality(&self, _state: &State) -> f32 {
+        1.0
+    }
+
+    /// Provides the request methods which can be matched, as reported by `Router::routes`. `None`
+    /// indicates that the method isn't considered.
+    fn methods(&self) -> Option<Vec<Method>> {
+        None
+    }
 }
 
 /// Allow various types to represent themselves as a `RouteMatcher`
@@ -111,4 +133,8 @@ impl RouteMatcher for MethodOnlyRouteMatcher {
                 .with_allow_list(self.methods.as_slice()))
         }
     }
+
+    fn methods(&self) -> Option<Vec<Method>> {
+        Some(self.methods.clone())
+    }
 }
@@ -0,0 +1,130 @@
+//! Defines the `RoutePredicate` trait and the `PredicateRouteMatcher`.
+
+use std::panic::RefUnwindSafe;
+use std::sync::Arc;
+
+use hyper::StatusCode;
+use log::trace;
+
+use crate::router::non_match::RouteNonMatch;
+use crate::router::route::RouteMatcher;
+use crate::state::{request_id, State};
+
+/// Determines whether a route is eligible to handle a request, from the request data in `State`
+/// such as the headers, method and URI. Predicates are added to routes with
+/// `DefineSingleRoute::when`.
+///
+/// This is implemented for closures taking `&State` and returning a `bool`, and can be
+/// implemented by other types to express conditions such as feature flags or A/B tests.
+pub trait RoutePredicate: RefUnwindSafe + Send + Sync {
+    /// Determines whether the route is eligible to handle the request.
+    fn is_eligible(&self, state: &State) -> bool;
+}
+
+impl<F> RoutePredicate for F
+where
+    F: Fn(&State) -> bool + RefUnwindSafe + Send + Sync,
+{
+    fn is_eligible(&self, state: &State) -> bool {
+        self(state)
+    }
+}
+
+/// A `RouteMatcher` that succeeds when its `RoutePredicate` finds the route eligible to handle
+/// the request.
+///
+/// As with other matchers, the routes of a path are tried in the order they were defined, so a
+/// route with a predicate should come before a route for the same path without one.
+///
+/// # Examples
+///
+/// ```rust
+/// # extern crate gotham;
+/// # extern crate hyper;
+/// # fn main() {
+/// #   use hyper::header::HeaderMap;
+/// #   use gotham::state::{FromState, State};
+/// #   use gotham::router::route::matcher::RouteMatcher;
+/// #   use gotham::router::route::matcher::predicate::PredicateRouteMatcher;
+/// #
+/// #   State::with_new(|state| {
+/// #
+/// let matcher = PredicateRouteMatcher::new(|state: &State| {
+///     HeaderMap::borrow_from(state).contains_key("x-beta")
+/// });
+///
+/// let mut headers = HeaderMap::new();
+/// headers.insert("x-beta", "1".parse().unwrap());
+/// state.put(headers);
+/// assert!(matcher.is_match(&state).is_ok());
+///
+/// state.put(HeaderMap::new());
+/// assert!(matcher.is_match(&state).is_err());
+/// #
+/// #   });
+/// # }
+/// ```
+#[derive(Clone)]
+pub struct PredicateRouteMatcher {
+    predicate: Arc<dyn RoutePredicate>,
+}
+
+impl PredicateRouteMatcher {
+    /// Creates a new `PredicateRouteMatcher` for the given predicate.
+    pub fn new<P>(predicate: P) -> Self
+    where
+        P: RoutePredicate + 'static,
+    {
+        PredicateRouteMatcher {
+            predicate: Arc::new(predicate),
+        }
+    }
+}
+
+impl RouteMatcher for PredicateRouteMatcher {
+    /// Determines if the predicate finds the route eligible to handle the `Request`.
+    fn is_match(&self, state: &State) -> Result<(), RouteNonMatch> {
+        if self.predicate.is_eligible(state) {
+            return Ok(());
+        }
+
+        trace!(
+            "[{}] predicate did not find this Route eligible",
+            request_id(state)
+        );
+        Err(RouteNonMatch::new(StatusCode::NOT_FOUND))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    use hyper::Method;
+
+    use crate::state::FromState;
+
+    struct MethodIs(Method);
+
+    impl RoutePredicate for MethodIs {
+        fn is_eligible(&self, state: &State) -> bool {
+            *Method::borrow_from(state) == self.0
+        }
+    }
+
+    #[test]
+    fn predicate_tests() {
+        let mut state = State::new();
+        state.put(Method::PUT);
+
+        assert!(PredicateRouteMatcher::new(MethodIs(Method::PUT))
+            .is_match(&state)
+            .is_ok());
+        assert!(PredicateRouteMatcher::new(MethodIs(Method::GET))
+            .is_match(&state)
+            .is_err());
+        assert!(PredicateRouteMatcher::new(|_: &State| false)
+            .is_match(&state)
+            .is_err());
+    }
+}
@@ -0,0 +1,158 @@
+//! Defines the `QueryStringRouteMatcher`.
+
+use hyper::{StatusCode, Uri};
+use log::trace;
+
+use crate::extractor::QueryStringOptions;
+use crate::helpers::http::request::query_string;
+use crate::router::non_match::RouteNonMatch;
+use crate::router::route::RouteMatcher;
+use crate::state::{request_id, FromState, State};
+
+/// A `RouteMatcher` that succeeds when the query string of the `Request` has a given parameter,
+/// optionally with a given value. Where a parameter is repeated, any of its values may match.
+///
+/// This allows several routes to share a path, and be selected by the query string. The routes
+/// of a path are tried in the order they were defined, so routes using this matcher should come
+/// before a route for the same path without it.
+///
+/// # Examples
+///
+/// ```rust
+/// # extern crate gotham;
+/// # extern crate hyper;
+/// # fn main() {
+/// #   use hyper::Uri;
+/// #   use gotham::state::State;
+/// #   use gotham::router::route::matcher::RouteMatcher;
+/// #   use gotham::router::route::matcher::query_string::QueryStringRouteMatcher;
+/// #
+/// #   State::with_new(|state| {
+/// #
+/// let matcher = QueryStringRouteMatcher::new("format", "csv");
+///
+/// state.put("/reports?format=csv".parse::<Uri>().unwrap());
+/// assert!(matcher.is_match(&state).is_ok());
+///
+/// state.put("/reports?format=json".parse::<Uri>().unwrap());
+/// assert!(matcher.is_match(&state).is_err());
+///
+/// let matcher = QueryStringRouteMatcher::present("download");
+///
+/// state.put("/reports?download".parse::<Uri>().unwrap());
+/// assert!(matcher.is_match(&state).is_ok());
+///
+/// state.put("/reports".parse::<Uri>().unwrap());
+/// assert!(matcher.is_match(&state).is_err());
+/// #
+/// #   });
+/// # }
+/// ```
+#[derive(Clone, Debug)]
+pub struct QueryStringRouteMatcher {
+    name: String,
+    value: Option<String>,
+}
+
+impl QueryStringRouteMatcher {
+    /// Creates a new `QueryStringRouteMatcher` which requires the parameter `name` to have the
+    /// given `value`.
+    pub fn new(name: &str, value: &str) -> Self {
+        QueryStringRouteMatcher {
+            name: name.to_owned(),
+            value: Some(value.to_owned()),
+        }
+    }
+
+    /// Creates a new `QueryStringRouteMatcher` which requires the parameter `name` to be present,
+    /// with or without a value.
+    pub fn present(name: &str) -> Self {
+        QueryStringRouteMatcher {
+            name: name.to_owned(),
+            value: None,
+        }
+    }
+
+    fn matches(&self, query: Option<&str>, options: &QueryStringOptions) -> bool {
+        match self.value {
+            Some(ref expected) => query_string::split(query, options)
+                .get(&self.name)
+                .map(|values| values.iter().any(|v| v.as_ref() == expected))
+                .unwrap_or(false),
+            None => query
+                .map(|query| {
+                    query
+                        .split(&['&', ';'][..])
+                        .filter_map(|pair| pair.split('=').next())
+                        .filter_map(|key| query_string::decode_key(key, options))
+                        .any(|(key, _)| key == self.name)
+                })
+                .unwrap_or(false),
+        }
+    }
+}
+
+impl RouteMatcher for QueryStringRouteMatcher {
+    /// Determines if the query string of the `Request` has the required parameter.
+    fn is_match(&self, state: &State) -> Result<(), RouteNonMatch> {
+        let options = state
+            .try_borrow::<QueryStringOptions>()
+            .cloned()
+            .unwrap_or_default();
+
+        if self.matches(Uri::borrow_from(state).query(), &options) {
+            return Ok(());
+        }
+
+        trace!(
+            "[{}] query string did not match `{}` parameter of this Route",
+            request_id(state),
+            self.name
+        );
+
+        Err(RouteNonMatch::new(StatusCode::NOT_FOUND))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn value_tests() {
+        let matcher = QueryStringRouteMatcher::new("format", "csv file");
+        let options = QueryStringOptions::default();
+
+        assert!(matcher.matches(Some("format=csv+file"), &options));
+        assert!(matcher.matches(Some("page=2&format=json;format=csv%20file"), &options));
+        assert!(!matcher.matches(Some("format=json"), &options));
+        assert!(!matcher.matches(Some("format"), &options));
+        assert!(!matcher.matches(None, &options));
+    }
+
+    #[test]
+    fn presence_tests() {
+        let matcher = QueryStringRouteMatcher::present("dry run");
+        let options = QueryStringOptions::default();
+
+        assert!(matcher.matches(Some("dry+run"), &options));
+        assert!(matcher.matches(Some("a=1&dry%20run=false"), &options));
+        assert!(!matcher.matches(Some("a=dry+run"), &options));
+        assert!(!matcher.matches(None, &options));
+    }
+
+    #[test]
+    fn options_tests() {
+        let options = QueryStringOptions::default()
+            .with_plus_as_space(false)
+            .with_bracketed_arrays(true);
+
+        let matcher = QueryStringRouteMatcher::new("tag", "a+b");
+        assert!(matcher.matches(Some("tag[]=x&tag[]=a+b"), &options));
+        assert!(!matcher.matches(Some("tag=a%20b"), &options));
+
+        let matcher = QueryStringRouteMatcher::present("tag");
+        assert!(matcher.matches(Some("tag[]"), &options));
+        assert!(!matcher.matches(Some("tag[]"), &QueryStringOptions::default()));
+    }
+}
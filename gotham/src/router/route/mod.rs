@@ -2,7 +2,8 @@
 //!
 //! The `Router` will identify one or more `Route` instances that match the path of a request, and
 //! iterate to find the first matching `Route` (indicated by `Route::is_match`). The request will
-//! be dispatched to the first `Route` which matches.
+//! be dispatched to the first `Route` which matches, unless a later `Route` indicates that it
+//! serves the request better through `Route::quality`.
 
 pub mod dispatch;
 pub mod matcher;
@@ -10,12 +11,15 @@ pub mod matcher;
 use std::marker::PhantomData;
 use std::panic::RefUnwindSafe;
 
-use hyper::{Body, Response, Uri};
+use hyper::{Body, Method, Response, Uri};
 use log::debug;
 
-use crate::extractor::{self, PathExtractor, QueryStringExtractor};
+use crate::extractor::{
+    self, ExtractionError, PathExtractor, QueryStringExtractor, QueryStringOptions,
+};
 use crate::handler::HandlerFuture;
 use crate::helpers::http::request::query_string;
+use crate::router::introspection::{describe_fields, FieldDescription};
 use crate::router::non_match::RouteNonMatch;
 use crate::router::route::dispatch::Dispatcher;
 use crate::router::route::matcher::RouteMatcher;
@@ -40,7 +44,8 @@ pub enum Delegation {
 /// matching the path segments successfully. The steps taken in dispatching to a `Route` are:
 ///
 /// 1. Given a list of routes that match the request path, determine the first `Route` which
-///    indicates a match via `Route::is_match`;
+///    indicates a match via `Route::is_match`, preferring any later `Route` with a higher
+///    `Route::quality`;
 /// 2. Determine whether the route's `Delegation` is `Internal` or `External`. If `External`, halt
 ///    processing and dispatch to the inner `Router`;
 /// 3. Run `PathExtractor` and `QueryStringExtractor` logic to popuate `State` with the necessary
@@ -56,9 +61,47 @@ pub trait Route: RefUnwindSafe {
     /// Determines if this `Route` should be invoked, based on the request data in `State.
     fn is_match(&self, state: &State) -> Result<(), RouteNonMatch>;
 
+    /// Determines how well a matching request is served by this `Route`, as described by
+    /// `RouteMatcher::quality`.
+    fn quality(&self, _state: &State) -> f32 {
+        1.0
+    }
+
+    /// Provides the request methods this `Route` matches, as described by
+    /// `RouteMatcher::methods`.
+    fn methods(&self) -> Option<Vec<Method>> {
+        None
+    }
+
+    /// Provides the type name of the `Handler` this `Route` dispatches to, where known.
+    fn handler_name(&self) -> Option<&'static str> {
+        None
+    }
+
+    /// Provides the type names of the middleware in each pipeline this `Route` dispatches via.
+    fn pipeline_names(&self) -> Vec<Vec<&'static str>> {
+        vec![]
+    }
+
+    /// Describes the fields of the `PathExtractor` of this `Route`, where known.
+    fn path_fields(&self) -> Vec<FieldDescription> {
+        vec![]
+    }
+
+    /// Describes the fields of the `QueryStringExtractor` of this `Route`, where known.
+    fn query_string_fields(&self) -> Vec<FieldDescription> {
+        vec![]
+    }
+
     /// Determines if this `Route` intends to delegate requests to a secondary `Router` instance.
     fn delegation(&self) -> Delegation;
 
+    /// Provides the limit on the size of request bodies for this `Route`, in bytes, where it has
+    /// its own.
+    fn body_limit(&self) -> Option<u64> {
+        None
+    }
+
     /// Extracts dynamic components of the `Request` path and stores the `PathExtractor` in `State`.
     fn extract_request_path<'a>(
         &self,
@@ -100,6 +143,7 @@ where
     dispatcher: Box<dyn Dispatcher + Send + Sync>,
     _extractors: Extractors<PE, QSE>,
     delegation: Delegation,
+    body_limit: Option<u64>,
 }
 
 /// Extractors used by `RouteImpl` to acquire request data and change into a type safe form
@@ -131,8 +175,16 @@ where
             dispatcher,
             _extractors,
             delegation,
+            body_limit: None,
         }
     }
+
+    /// Limits the size of request bodies for this `RouteImpl` to `limit` bytes, as described by
+    /// `DefineSingleRoute::with_body_limit`.
+    pub fn with_body_limit(mut self, limit: u64) -> Self {
+        self.body_limit = Some(limit);
+        self
+    }
 }
 
 impl<PE, QSE> Extractors<PE, QSE>
@@ -161,10 +213,38 @@ where
         self.matcher.is_match(state)
     }
 
+    fn quality(&self, state: &State) -> f32 {
+        self.matcher.quality(state)
+    }
+
+    fn methods(&self) -> Option<Vec<Method>> {
+        self.matcher.methods()
+    }
+
+    fn handler_name(&self) -> Option<&'static str> {
+        self.dispatcher.handler_name()
+    }
+
+    fn pipeline_names(&self) -> Vec<Vec<&'static str>> {
+        self.dispatcher.pipeline_names()
+    }
+
+    fn path_fields(&self) -> Vec<FieldDescription> {
+        describe_fields::<PE>()
+    }
+
+    fn query_string_fields(&self) -> Vec<FieldDescription> {
+        describe_fields::<QSE>()
+    }
+
     fn delegation(&self) -> Delegation {
         self.delegation
     }
 
+    fn body_limit(&self) -> Option<u64> {
+        self.body_limit
+    }
+
     fn dispatch(&self, state: State) -> Box<HandlerFuture> {
         self.dispatcher.dispatch(state)
     }
@@ -178,6 +258,7 @@ where
             Ok(val) => Ok(state.put(val)),
             Err(e) => {
                 debug!("[{}] path extractor failed: {}", request_id(&state), e);
+                state.put(ExtractionError::new(e.to_string()));
                 Err(ExtractorFailed)
             }
         }
@@ -190,7 +271,11 @@ where
     fn extract_query_string(&self, state: &mut State) -> Result<(), ExtractorFailed> {
         let result: Result<QSE, _> = {
             let uri = state.borrow::<Uri>();
-            let query_string_mapping = query_string::split(uri.query());
+            let options = state
+                .try_borrow::<QueryStringOptions>()
+                .cloned()
+                .unwrap_or_default();
+            let query_string_mapping = query_string::split(uri.query(), &options);
             extractor::internal::from_query_string_mapping(&query_string_mapping)
         };
 
@@ -202,6 +287,7 @@ where
                     request_id(&state),
                     e
                 );
+                state.put(ExtractionError::new(e.to_string()));
                 Err(ExtractorFailed)
             }
         }
@@ -2,12 +2,14 @@
 
 use crate::helpers::http::PercentDecoded;
 use crate::router::route::Route;
-use crate::router::tree::node::Node;
-use crate::router::tree::segment::{SegmentMapping, SegmentType};
+use crate::router::tree::node::{Node, TraversalMatch};
+use crate::router::tree::segment::SegmentType;
 use hyper::Body;
 use log::trace;
 
 pub mod node;
+pub mod predicate;
+pub(crate) mod radix;
 pub mod regex;
 pub mod segment;
 
@@ -39,6 +41,16 @@ impl Tree {
         self.root.add_route(route);
     }
 
+    /// Borrow the root `Node`.
+    pub(crate) fn borrow_root(&self) -> &Node {
+        &self.root
+    }
+
+    /// Takes the root `Node`.
+    pub(crate) fn into_root(self) -> Node {
+        self.root
+    }
+
     /// Borrow the root `NodeBuilder` as mutable.
     pub fn borrow_root_mut(&mut self) -> &mut Node {
         &mut self.root
@@ -52,13 +64,20 @@ impl Tree {
         self.root.has_child(segment, segment_type)
     }
 
-    /// Attempt to acquire a path from the `Tree` which matches the `Request` path and is routable.
-    pub(crate) fn traverse<'a>(
+    /// Attempt to acquire a path from the `Tree` which matches the `Request` path and is routable,
+    /// optionally matching static segments regardless of case and passing over excluded nodes.
+    /// The segments which only matched regardless of case are returned alongside their canonical
+    /// form, and each step of the traversal is described in the `trace`, where provided.
+    pub(crate) fn traverse_with<'a>(
         &'a self,
         req_path_segments: &'a [PercentDecoded],
-    ) -> Option<(&Node, SegmentMapping<'a>, usize)> {
+        ignore_case: bool,
+        excluded: &[usize],
+        trace: Option<&mut Vec<String>>,
+    ) -> Option<TraversalMatch<'a>> {
         trace!(" starting tree traversal");
-        self.root.match_node(req_path_segments)
+        self.root
+            .match_node_with(req_path_segments, ignore_case, excluded, trace)
     }
 }
 
@@ -105,8 +124,13 @@ mod tests {
         tree.add_child(activate_node_builder);
 
         let request_path_segments = RequestPathSegments::new("/%61ctiv%61te/workflow5");
-        match tree.traverse(request_path_segments.segments().as_slice()) {
-            Some((node, params, processed)) => {
+        match tree.traverse_with(
+            request_path_segments.segments().as_slice(),
+            false,
+            &[],
+            None,
+        ) {
+            Some((node, params, processed, _)) => {
                 assert!(node.is_routable());
                 assert_eq!(processed, 2);
                 assert_eq!(
@@ -118,10 +142,15 @@ mod tests {
         }
 
         assert!(tree
-            .traverse(&[PercentDecoded::new("/").unwrap()])
+            .traverse_with(&[PercentDecoded::new("/").unwrap()], false, &[], None)
             .is_none());
         assert!(tree
-            .traverse(&[PercentDecoded::new("/activate").unwrap()])
+            .traverse_with(
+                &[PercentDecoded::new("/activate").unwrap()],
+                false,
+                &[],
+                None
+            )
             .is_none());
     }
 }
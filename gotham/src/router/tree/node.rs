@@ -4,13 +4,45 @@ use hyper::{Body, StatusCode};
 use log::trace;
 
 use crate::helpers::http::PercentDecoded;
+use crate::router::introspection::RouteTemplate;
 use crate::router::non_match::RouteNonMatch;
+use crate::router::response::extender::ResponseExtender;
+use crate::router::response::finalizer::ResponseExtenders;
 use crate::router::route::{Delegation, Route};
+use crate::router::tree::radix::RadixIndex;
 use crate::router::tree::segment::{SegmentMapping, SegmentType};
+use crate::router::{PathExtraction, SchemePolicy};
 use crate::state::{request_id, State};
 
 use std::cmp::Ordering;
 use std::collections::HashMap;
+use std::sync::Arc;
+
+/// The result of matching a `Node` using `Node::match_node_with`: the matched `Node`, its segment
+/// mapping and the number of segments processed, along with the index of each request path
+/// segment which only matched regardless of case, and the segment of the `Node` it matched.
+pub(crate) type TraversalMatch<'a> = (&'a Node, SegmentMapping<'a>, usize, Vec<(usize, &'a str)>);
+
+/// The options of a traversal beyond the request path, and the segments matched regardless of
+/// case along the way.
+struct Traversal<'a, 'b> {
+    ignore_case: bool,
+    excluded: &'b [usize],
+    folded: Vec<(usize, &'a str)>,
+    trace: Option<&'b mut Vec<String>>,
+}
+
+impl<'a, 'b> Traversal<'a, 'b> {
+    /// Records a step of the traversal, where the traversal is being traced.
+    fn record<F>(&mut self, entry: F)
+    where
+        F: FnOnce() -> String,
+    {
+        if let Some(ref mut trace) = self.trace {
+            trace.push(entry());
+        }
+    }
+}
 
 /// A recursive member of `Tree`, representative of segment(s) in a request path.
 ///
@@ -22,6 +54,15 @@ pub struct Node {
     segment_type: SegmentType,
     routes: Vec<Box<dyn Route<ResBody = Body> + Send + Sync>>,
     children: Vec<Node>,
+    static_children: RadixIndex,
+    with_trailing_slash: bool,
+    without_trailing_slash: bool,
+    names: Vec<String>,
+    scheme_policy: Option<SchemePolicy>,
+    response_extenders: ResponseExtenders,
+    body_limit: Option<u64>,
+    path_extractions: Vec<PathExtraction>,
+    template: Option<RouteTemplate>,
 }
 
 impl Node {
@@ -32,22 +73,262 @@ impl Node {
             segment: segment.to_string(),
             routes: vec![],
             children: vec![],
+            static_children: RadixIndex::default(),
+            with_trailing_slash: false,
+            without_trailing_slash: false,
+            names: vec![],
+            scheme_policy: None,
+            response_extenders: ResponseExtenders::default(),
+            body_limit: None,
+            path_extractions: vec![],
+            template: None,
         }
     }
 
     /// Adds a new child `Node` instance to this `Node`.
-    pub fn add_child(&mut self, node: Node) -> &mut Self {
+    pub fn add_child(&mut self, mut node: Node) -> &mut Self {
+        if let Some(policy) = self.scheme_policy {
+            node.inherit_scheme_policy(policy);
+        }
+        if !self.response_extenders.is_empty() {
+            node.inherit_response_extenders(&self.response_extenders);
+        }
+        if let Some(limit) = self.body_limit {
+            node.inherit_body_limit(limit);
+        }
+        if !self.path_extractions.is_empty() {
+            node.inherit_path_extractions(&self.path_extractions);
+        }
         self.children.push(node);
         self.children.sort();
+        self.index_static_children();
         self
     }
 
+    /// Rebuilds the index of the static children, which come first among the sorted children.
+    fn index_static_children(&mut self) {
+        let mut index = RadixIndex::default();
+        for (i, child) in self.children.iter().enumerate() {
+            if child.segment_type != SegmentType::Static {
+                break;
+            }
+            index.insert(&child.segment, i);
+        }
+        self.static_children = index;
+    }
+
+    /// Merges the routes and children of another `Node`, representing the same segment, into this
+    /// `Node`. Routes and children which already exist are kept ahead of those being merged.
+    pub(crate) fn merge(&mut self, other: Node) {
+        self.routes.extend(other.routes);
+        self.with_trailing_slash |= other.with_trailing_slash;
+        self.without_trailing_slash |= other.without_trailing_slash;
+
+        for name in &other.names {
+            self.add_name(name);
+        }
+
+        for child in other.children {
+            match self.borrow_child_mut(&child.segment, child.segment_type.clone()) {
+                Some(existing) => existing.merge(child),
+                None => {
+                    self.add_child(child);
+                }
+            }
+        }
+    }
+
     /// Adds a `Route` to this `Node`, to be potentially evaluated by the `Router`.
     pub fn add_route(&mut self, route: Box<dyn Route<ResBody = Body> + Send + Sync>) -> &mut Self {
         self.routes.push(route);
         self
     }
 
+    /// Records that a route was defined for this `Node` with or without a trailing slash, as used
+    /// by `TrailingSlash::Strict`.
+    pub(crate) fn add_trailing_slash(&mut self, trailing_slash: bool) {
+        if trailing_slash {
+            self.with_trailing_slash = true;
+        } else {
+            self.without_trailing_slash = true;
+        }
+    }
+
+    /// Determines whether a request path with or without a trailing slash matches the routes of
+    /// this `Node`. Where the routes weren't defined through the builder, both are accepted.
+    pub(crate) fn accepts_trailing_slash(&self, trailing_slash: bool) -> bool {
+        match (self.with_trailing_slash, self.without_trailing_slash) {
+            (false, false) => true,
+            (with, without) => {
+                if trailing_slash {
+                    with
+                } else {
+                    without
+                }
+            }
+        }
+    }
+
+    /// Applies the `SchemePolicy` to this `Node` and every `Node` beneath it, including those which
+    /// are added later.
+    pub(crate) fn set_scheme_policy(&mut self, policy: SchemePolicy) {
+        self.scheme_policy = Some(policy);
+        for child in &mut self.children {
+            child.set_scheme_policy(policy);
+        }
+    }
+
+    /// Applies the `SchemePolicy` of a parent to this `Node` and its children, other than those
+    /// which have their own.
+    fn inherit_scheme_policy(&mut self, policy: SchemePolicy) {
+        if self.scheme_policy.is_none() {
+            self.scheme_policy = Some(policy);
+            for child in &mut self.children {
+                child.inherit_scheme_policy(policy);
+            }
+        }
+    }
+
+    /// Provides the `SchemePolicy` which applies to the routes of this `Node`, if any.
+    pub(crate) fn scheme_policy(&self) -> Option<SchemePolicy> {
+        self.scheme_policy
+    }
+
+    /// Adds a `ResponseExtender` for the routes of this `Node` and every `Node` beneath it,
+    /// including those which are added later, other than those with their own extender for the
+    /// `status_code`.
+    pub(crate) fn add_response_extender(
+        &mut self,
+        status_code: StatusCode,
+        extender: Arc<dyn ResponseExtender<Body> + Send + Sync>,
+    ) {
+        let mut extenders = ResponseExtenders::default();
+        extenders.add(status_code, extender);
+
+        // extenders already added at this node are replaced, unlike those of children
+        let mut replaced = extenders.clone();
+        replaced.inherit(&self.response_extenders);
+        self.response_extenders = replaced;
+
+        for child in &mut self.children {
+            child.inherit_response_extenders(&extenders);
+        }
+    }
+
+    /// Applies the `ResponseExtenders` of a parent to this `Node` and its children, for the
+    /// status codes which they don't have their own extender for.
+    fn inherit_response_extenders(&mut self, extenders: &ResponseExtenders) {
+        self.response_extenders.inherit(extenders);
+        for child in &mut self.children {
+            child.inherit_response_extenders(extenders);
+        }
+    }
+
+    /// Provides the `ResponseExtenders` which apply to the routes of this `Node`.
+    pub(crate) fn response_extenders(&self) -> &ResponseExtenders {
+        &self.response_extenders
+    }
+
+    /// Limits the size of request bodies for the routes of this `Node` and every `Node` beneath
+    /// it, including those which are added later, other than those with their own limit.
+    pub(crate) fn set_body_limit(&mut self, limit: u64) {
+        self.body_limit = Some(limit);
+        for child in &mut self.children {
+            child.inherit_body_limit(limit);
+        }
+    }
+
+    /// Applies the body limit of a parent to this `Node` and its children, other than those
+    /// which have their own.
+    fn inherit_body_limit(&mut self, limit: u64) {
+        if self.body_limit.is_none() {
+            self.body_limit = Some(limit);
+            for child in &mut self.children {
+                child.inherit_body_limit(limit);
+            }
+        }
+    }
+
+    /// Provides the limit on the size of request bodies for the routes of this `Node`, if any.
+    pub(crate) fn body_limit(&self) -> Option<u64> {
+        self.body_limit
+    }
+
+    /// Records the `RouteTemplate` of the routes of this `Node` and every `Node` beneath it, where
+    /// `segments` are the segments of the ancestors of this `Node`.
+    pub(crate) fn set_templates(&mut self, segments: &mut Vec<String>) {
+        if !self.routes.is_empty() {
+            self.template = Some(RouteTemplate::new(self, segments));
+        }
+
+        for child in &mut self.children {
+            segments.push(child.describe_segment());
+            child.set_templates(segments);
+            segments.pop();
+        }
+    }
+
+    /// Provides the `RouteTemplate` of the routes of this `Node`, once the `Router` is built.
+    pub(crate) fn template(&self) -> Option<&RouteTemplate> {
+        self.template.as_ref()
+    }
+
+    /// Adds a `PathExtraction` for the routes of this `Node` and every `Node` beneath it,
+    /// including those which are added later.
+    pub(crate) fn add_path_extraction(&mut self, extraction: PathExtraction) {
+        self.path_extractions.push(extraction);
+        for child in &mut self.children {
+            child.add_path_extraction(extraction);
+        }
+    }
+
+    /// Applies the `PathExtraction`s of a parent to this `Node` and its children, ahead of their
+    /// own.
+    fn inherit_path_extractions(&mut self, extractions: &[PathExtraction]) {
+        let own = std::mem::replace(&mut self.path_extractions, extractions.to_vec());
+        self.path_extractions.extend(own);
+        for child in &mut self.children {
+            child.inherit_path_extractions(extractions);
+        }
+    }
+
+    /// Provides the `PathExtraction`s which apply to the routes of this `Node`, outermost first.
+    pub(crate) fn path_extractions(&self) -> &[PathExtraction] {
+        &self.path_extractions
+    }
+
+    /// Records a name for the routes of this `Node`, as used by `UrlFor` to generate its path.
+    pub(crate) fn add_name(&mut self, name: &str) {
+        if !self.names.iter().any(|n| n == name) {
+            self.names.push(name.to_owned());
+        }
+    }
+
+    /// Provides the names given to the routes of this `Node`.
+    pub(crate) fn names(&self) -> &[String] {
+        &self.names
+    }
+
+    /// Determines whether the routes of this `Node` were only defined with a trailing slash.
+    pub(crate) fn requires_trailing_slash(&self) -> bool {
+        self.with_trailing_slash && !self.without_trailing_slash
+    }
+
+    /// Provides the `Route` instances of this `Node`, in the order they were added.
+    pub(crate) fn routes(&self) -> &[Box<dyn Route<ResBody = Body> + Send + Sync>] {
+        &self.routes
+    }
+
+    /// Provides the children of this `Node`, in the order they're visited during traversal.
+    pub(crate) fn children(&self) -> &[Node] {
+        &self.children
+    }
+
+    /// Provides the type of the segment represented by this `Node`.
+    pub(crate) fn segment_type(&self) -> &SegmentType {
+        &self.segment_type
+    }
+
     /// Borrows a child `Node` based on the defined segment bounds.
     pub fn borrow_child(&self, segment: &str, segment_type: SegmentType) -> Option<&Node> {
         self.children
@@ -68,7 +349,10 @@ impl Node {
 
     /// Determines if a child exists based on the defined segment bounds.
     pub fn has_child(&self, segment: &str, segment_type: SegmentType) -> bool {
-        self.borrow_child(segment, segment_type).is_some()
+        match segment_type {
+            SegmentType::Static => self.static_children.get(segment).is_some(),
+            _ => self.borrow_child(segment, segment_type).is_some(),
+        }
     }
 
     /// Determines if this `Node` has any valid `Route` values attached.
@@ -88,8 +372,9 @@ impl Node {
     ///
     /// 1. Static
     /// 2. Constrained
-    /// 3. Dynamic
-    /// 4. Glob
+    /// 3. Predicate
+    /// 4. Dynamic
+    /// 5. Glob
     ///
     /// This method is a wrapping of an internal recursive implementation to mask the required
     /// types needed for the recursion.
@@ -97,13 +382,58 @@ impl Node {
         &'a self,
         segments: &'a [PercentDecoded],
     ) -> Option<(&'a Node, SegmentMapping<'a>, usize)> {
+        self.match_node_with(segments, false, &[], None)
+            .map(|(node, params, processed, _)| (node, params, processed))
+    }
+
+    /// Traverses this `Node` and its children in the same way as `match_node`, optionally
+    /// matching `Static` segments regardless of case where no child matches the exact case, and
+    /// passing over the nodes whose `Node::id` is excluded.
+    ///
+    /// Where nodes are excluded, a failed descent into a child is undone so that the remaining
+    /// children are tried, as a path may have been matched by an excluded node alone.
+    ///
+    /// The segments of the request path which were matched regardless of case are also
+    /// returned, as their index and the segment of the `Node` they matched. Where a `trace` is
+    /// provided, each step of the traversal is described in it.
+    pub(crate) fn match_node_with<'a>(
+        &'a self,
+        segments: &'a [PercentDecoded],
+        ignore_case: bool,
+        excluded: &[usize],
+        trace: Option<&mut Vec<String>>,
+    ) -> Option<TraversalMatch<'a>> {
         // accumulators for recursion
         let mut params = HashMap::new();
         let mut processed = 0;
+        let mut traversal = Traversal {
+            ignore_case,
+            excluded,
+            folded: vec![],
+            trace,
+        };
 
         // process and map the results through to the required form
-        self.inner_match_node(segments, &mut params, &mut processed)
-            .map(|node| (node, params, processed))
+        self.inner_match_node(segments, &mut params, &mut processed, &mut traversal)
+            .map(|node| (node, params, processed, traversal.folded))
+    }
+
+    /// Describes the segment of this `Node` as it's written in the path of a route, such as
+    /// `users`, `:id` or `:id:[0-9]+`.
+    pub(crate) fn describe_segment(&self) -> String {
+        match self.segment_type {
+            SegmentType::Static | SegmentType::Glob => self.segment.clone(),
+            SegmentType::Constrained { ref regex } => {
+                let pattern = regex.as_str();
+                format!(":{}:{}", self.segment, &pattern[1..pattern.len() - 1])
+            }
+            SegmentType::Dynamic | SegmentType::Predicate { .. } => format!(":{}", self.segment),
+        }
+    }
+
+    /// Identifies this `Node` within its `Tree`, for the duration of the `Tree`.
+    pub(crate) fn id(&self) -> usize {
+        self as *const Node as usize
     }
 
     /// Retrieves a reference to the contained segment value.
@@ -116,8 +446,8 @@ impl Node {
     /// Determines if a `Route` instance associated with this `Node` is willing to `Handle` the
     /// request.
     ///
-    /// Where multiple `Route` instances could possibly handle the `Request` only the first, ordered
-    /// per creation, is invoked.
+    /// Where multiple `Route` instances could possibly handle the `Request` only the one with the
+    /// highest `Route::quality` is invoked, taking the first, ordered per creation, among equals.
     ///
     /// Where no `Route` instances will accept the `Request` the resulting Error will be the
     /// union of the `RouteNonMatch` values returned from each `Route`.
@@ -127,17 +457,59 @@ impl Node {
     pub fn select_route(
         &self,
         state: &State,
+    ) -> Result<&Box<dyn Route<ResBody = Body> + Send + Sync>, RouteNonMatch> {
+        self.select_route_excluding(state, &[], None)
+    }
+
+    /// Determines the `Route` willing to `Handle` the request in the same way as `select_route`,
+    /// passing over the routes whose `route_id` is excluded. Where a `trace` is provided, the
+    /// outcome of matching each route is described in it.
+    pub(crate) fn select_route_excluding(
+        &self,
+        state: &State,
+        excluded: &[usize],
+        mut trace: Option<&mut Vec<String>>,
     ) -> Result<&Box<dyn Route<ResBody = Body> + Send + Sync>, RouteNonMatch> {
         let mut err = Ok(());
+        let mut best: Option<(&Box<dyn Route<ResBody = Body> + Send + Sync>, f32)> = None;
+
+        // check for matching routes, taking the first of those serving the request best
+        for (i, r) in self.routes.iter().enumerate() {
+            let mut record = |outcome: &str| {
+                if let Some(ref mut trace) = trace {
+                    trace.push(format!(
+                        "route {} of `{}` ({}) {}",
+                        i,
+                        self.describe_segment(),
+                        r.handler_name().unwrap_or("unnamed handler"),
+                        outcome
+                    ));
+                }
+            };
+
+            if excluded.contains(&route_id(r.as_ref())) {
+                record("was passed over after falling through");
+                continue;
+            }
 
-        // check for matching routes
-        for r in self.routes.iter() {
             match r.is_match(state) {
                 Ok(()) => {
-                    trace!("[{}] found matching route", request_id(state));
-                    return Ok(r);
+                    let quality = r.quality(state);
+                    record(&format!("matched with quality {}", quality));
+                    match best {
+                        Some((_, q)) if q >= quality => {}
+                        _ => best = Some((r, quality)),
+                    }
+                    // no later route can serve the request better
+                    if quality >= 1.0 {
+                        break;
+                    }
                 }
                 Err(e) => {
+                    record(&format!(
+                        "didn't match, with status {}",
+                        StatusCode::from(e.clone())
+                    ));
                     // concat errors
                     err = match err {
                         Err(e0) => Err(e.union(e0)),
@@ -147,6 +519,11 @@ impl Node {
             }
         }
 
+        if let Some((r, _)) = best {
+            trace!("[{}] found matching route", request_id(state));
+            return Ok(r);
+        }
+
         // unpack required for types
         if let Err(e) = err {
             trace!(
@@ -175,20 +552,34 @@ impl Node {
         segments: &'a [PercentDecoded],
         params: &mut SegmentMapping<'a>,
         processed: &mut usize,
+        traversal: &mut Traversal<'a, '_>,
     ) -> Option<&'a Node> {
         let next_segment = segments.split_first();
+        let excluded = traversal.excluded.contains(&self.id());
 
         // stop if we're done
         if next_segment.is_none() {
+            if excluded {
+                traversal.record(|| format!("`{}` was passed over", self.describe_segment()));
+                return None;
+            }
             if !self.is_routable() {
+                traversal.record(|| format!("`{}` has no routes", self.describe_segment()));
                 return None;
             }
+            traversal.record(|| format!("`{}` matched the request path", self.describe_segment()));
             return Some(self);
         }
 
         // check for external delegates, and stop
         if let Some(route) = self.routes.first() {
-            if route.delegation() == Delegation::External {
+            if route.delegation() == Delegation::External && !excluded {
+                traversal.record(|| {
+                    format!(
+                        "`{}` delegates the remaining path to another router",
+                        self.describe_segment()
+                    )
+                });
                 return Some(self);
             }
         }
@@ -197,8 +588,33 @@ impl Node {
 
         *processed += 1;
 
+        // a glob in the middle of a path may need to swallow segments which look like the
+        // start of one of its children, so it has to be able to undo a failed descent
+        let is_glob = self.segment_type == SegmentType::Glob;
+
+        // as does any node where excluded nodes may leave a matching child without a route
+        let may_rewind = is_glob || !traversal.excluded.is_empty();
+
+        // only the static child with the exact segment is a candidate, found through the index,
+        // unless none has the exact segment and case is being ignored
+        let statics = self.static_children.len();
+        let candidates = match self.static_children.get(segment.as_ref()) {
+            Some(i) => i..i + 1,
+            None if traversal.ignore_case => 0..statics,
+            None => 0..0,
+        };
+
         // check all children first
-        for child in &self.children {
+        for child in self.children[candidates]
+            .iter()
+            .chain(&self.children[statics..])
+        {
+            let checkpoint = if may_rewind {
+                Some((params.clone(), *processed, traversal.folded.len()))
+            } else {
+                None
+            };
+
             match child.segment_type {
                 // Globbing matches everything, so we append the segment value
                 // to the parameters against the child segment name.
@@ -215,7 +631,12 @@ impl Node {
                 SegmentType::Static => {
                     // check for raw string match
                     if child.segment != segment.as_ref() {
-                        continue;
+                        // where ignoring case, a child with a different case may match as
+                        // long as no child matches exactly
+                        if !eq_ignoring_case(&child.segment, segment.as_ref()) {
+                            continue;
+                        }
+                        traversal.folded.push((*processed - 1, &child.segment));
                     }
                 }
 
@@ -231,6 +652,15 @@ impl Node {
                     params.insert(&child.segment, vec![&segment]);
                 }
 
+                // Predicate matches are the same as constrained matches, other
+                // than using a function to check the segment value.
+                SegmentType::Predicate { ref predicate } => {
+                    if !predicate.is_match(segment.as_ref()) {
+                        continue;
+                    }
+                    params.insert(&child.segment, vec![&segment]);
+                }
+
                 // Dynamic matches match every value, so we just attach the
                 // segment value to the parameters list (just like with the
                 // constrained type).
@@ -243,25 +673,71 @@ impl Node {
             // If we hit this point, we've determined that the child node is
             // the correct node to delegate to, so we continue the recursion
             // on the child node, passing in the same parameters.
-            return child.inner_match_node(remaining, params, processed);
+            traversal.record(|| {
+                format!(
+                    "segment `{}` matched `{}`",
+                    segment.as_ref(),
+                    child.describe_segment()
+                )
+            });
+            let matched = child.inner_match_node(remaining, params, processed, traversal);
+
+            match checkpoint {
+                // the child couldn't complete the match, so rewind and try the
+                // remaining children (or let the glob consume the segment).
+                Some((saved, count, folded_count)) if matched.is_none() => {
+                    traversal
+                        .record(|| format!("backtracking from `{}`", child.describe_segment()));
+                    *params = saved;
+                    *processed = count;
+                    traversal.folded.truncate(folded_count);
+                }
+                _ => return matched,
+            }
         }
 
-        // If there are no children, but this is a globbing node, then we can
+        // If no children match, but this is a globbing node, then we can
         // continue the nesting by just shifting the path segments and calling
         // `inner_match_node` on ourself again (to simulate wildcards).
         if let SegmentType::Glob = self.segment_type {
+            traversal.record(|| {
+                format!(
+                    "segment `{}` was consumed by `{}`",
+                    segment.as_ref(),
+                    self.describe_segment()
+                )
+            });
             // push the segment to the parameters of the glob
             if let Some(path) = params.get_mut(self.segment()) {
                 path.push(&segment);
             }
             // call again, but after shifting the segments to the next
-            return self.inner_match_node(remaining, params, processed);
+            return self.inner_match_node(remaining, params, processed, traversal);
         }
 
+        traversal.record(|| {
+            format!(
+                "segment `{}` matched no child of `{}`",
+                segment.as_ref(),
+                self.describe_segment()
+            )
+        });
         None
     }
 }
 
+/// Identifies a `Route` within the `Node` it's attached to, for the duration of its `Tree`.
+pub(crate) fn route_id(route: &(dyn Route<ResBody = Body> + Send + Sync)) -> usize {
+    route as *const _ as *const () as usize
+}
+
+/// Compares two path segments for equality, ignoring their case.
+fn eq_ignoring_case(a: &str, b: &str) -> bool {
+    a.chars()
+        .flat_map(char::to_lowercase)
+        .eq(b.chars().flat_map(char::to_lowercase))
+}
+
 impl Eq for Node {}
 impl PartialEq for Node {
     /// Compares two `Node` values for equality based on the segments they represent.
@@ -299,6 +775,7 @@ mod tests {
     use crate::router::route::dispatch::DispatcherImpl;
     use crate::router::route::matcher::MethodOnlyRouteMatcher;
     use crate::router::route::{Delegation, Extractors, Route, RouteImpl};
+    use crate::router::tree::predicate::SegmentPredicate;
     use crate::router::tree::regex::ConstrainedSegmentRegex;
     use crate::state::{set_request_id, State};
 
@@ -394,6 +871,22 @@ mod tests {
         seg_resource.add_child(seg_id);
         root.add_child(seg_resource);
 
+        // Ensure predicate matching is tried before dynamic siblings
+        // GET: /post/<id> where id passes the predicate, otherwise /post/<slug>
+        let mut seg_post = Node::new("post", SegmentType::Static);
+        let mut seg_post_id = Node::new(
+            "id",
+            SegmentType::Predicate {
+                predicate: SegmentPredicate::new(|s| s.chars().all(|c| c.is_ascii_digit())),
+            },
+        );
+        seg_post_id.add_route(get_route(pipeline_set.clone()));
+        let mut seg_post_slug = Node::new("slug", SegmentType::Dynamic);
+        seg_post_slug.add_route(get_route(pipeline_set.clone()));
+        seg_post.add_child(seg_post_slug);
+        seg_post.add_child(seg_post_id);
+        root.add_child(seg_post);
+
         // Ensure traversal will backtrack and find the correct path if it goes down an ultimately
         // invalid branch, in this case seg6 initially being matched by the dynamic handler segdyn1
         // which matches every segment it sees.
@@ -415,6 +908,16 @@ mod tests {
         let mut seg10 = Node::new("seg10", SegmentType::Glob);
         seg10.add_route(get_route(pipeline_set.clone()));
 
+        // Ensure globs in the middle of a path give up segments matching their children
+        // GET: /archive/*/manifest.json
+        let mut seg_archive = Node::new("archive", SegmentType::Static);
+        let mut seg_archive_glob = Node::new("*", SegmentType::Glob);
+        let mut seg_manifest = Node::new("manifest.json", SegmentType::Static);
+        seg_manifest.add_route(get_route(pipeline_set.clone()));
+        seg_archive_glob.add_child(seg_manifest);
+        seg_archive.add_child(seg_archive_glob);
+        root.add_child(seg_archive);
+
         segdyn1.add_child(seg7);
         seg5.add_child(seg6);
         seg5.add_child(segdyn1);
@@ -485,6 +988,23 @@ mod tests {
             None => panic!("traversal should have succeeded here"),
         }
 
+        // GET /archive/2019/manifest.json/v2/manifest.json
+        let rs = RequestPathSegments::new("/archive/2019/manifest.json/v2/manifest.json");
+        match root.match_node(&rs.segments()) {
+            Some((node, params, processed)) => {
+                assert_eq!(node.segment, "manifest.json");
+                assert_eq!(processed, 5);
+
+                let glob: Vec<&str> = params["*"].iter().map(|s| s.as_ref()).collect();
+                assert_eq!(glob, vec!["2019", "manifest.json", "v2"]);
+            }
+            None => panic!("traversal should have succeeded here"),
+        }
+
+        // The glob must capture at least one segment
+        let rs = RequestPathSegments::new("/archive/manifest.json");
+        assert!(root.match_node(&rs.segments()).is_none());
+
         let rs = RequestPathSegments::new("/resource/5001");
         let expected_segment = "id";
         match root.match_node(&rs.segments()) {
@@ -494,6 +1014,18 @@ mod tests {
             }
             None => panic!("traversal should have succeeded here"),
         }
+
+        let rs = RequestPathSegments::new("/post/42");
+        match root.match_node(&rs.segments()) {
+            Some((node, _params, _processed)) => assert_eq!(node.segment, "id"),
+            None => panic!("traversal should have succeeded here"),
+        }
+
+        let rs = RequestPathSegments::new("/post/hello-world");
+        match root.match_node(&rs.segments()) {
+            Some((node, _params, _processed)) => assert_eq!(node.segment, "slug"),
+            None => panic!("traversal should have succeeded here"),
+        }
     }
 
     #[test]
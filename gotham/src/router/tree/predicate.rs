@@ -0,0 +1,57 @@
+//! Defines the wrapping type for a segment-matching predicate.
+
+use std::cmp::Ordering;
+use std::fmt;
+
+/// A function which determines whether a single segment of a request path matches a
+/// `SegmentType::Predicate` node, as added by `DrawRoutes::constrained_segment`.
+///
+/// Values are compared by the address of the function, so that a segment defined twice with the
+/// same predicate refers to the same `Node`.
+#[derive(Clone, Copy)]
+pub struct SegmentPredicate {
+    predicate: fn(&str) -> bool,
+}
+
+impl SegmentPredicate {
+    /// Creates a new `SegmentPredicate` from the provided function.
+    pub fn new(predicate: fn(&str) -> bool) -> Self {
+        SegmentPredicate { predicate }
+    }
+
+    /// Returns true if and only if the predicate accepts the string given.
+    #[inline]
+    pub(crate) fn is_match(&self, s: &str) -> bool {
+        (self.predicate)(s)
+    }
+
+    fn address(&self) -> usize {
+        self.predicate as usize
+    }
+}
+
+impl fmt::Debug for SegmentPredicate {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        write!(f, "SegmentPredicate({:#x})", self.address())
+    }
+}
+
+impl PartialEq for SegmentPredicate {
+    fn eq(&self, other: &Self) -> bool {
+        self.address() == other.address()
+    }
+}
+
+impl Eq for SegmentPredicate {}
+
+impl PartialOrd for SegmentPredicate {
+    fn partial_cmp(&self, other: &SegmentPredicate) -> Option<Ordering> {
+        Some(self.cmp(other))
+    }
+}
+
+impl Ord for SegmentPredicate {
+    fn cmp(&self, other: &Self) -> Ordering {
+        self.address().cmp(&other.address())
+    }
+}
@@ -0,0 +1,209 @@
+//! Defines `RadixIndex`, which locates the static children of a `Node` by their segment.
+
+/// A radix tree over the segments of the static children of a `Node`, mapping each segment to
+/// the position of its child.
+///
+/// Segments sharing a prefix share the edges of the tree, so a lookup compares the bytes of the
+/// request path segment along a single path from the root, rather than against each child in
+/// turn, and doesn't allocate.
+#[derive(Clone, Debug)]
+pub(crate) struct RadixIndex {
+    // the root is always the first entry, with an empty prefix
+    entries: Vec<Entry>,
+    len: usize,
+}
+
+#[derive(Clone, Debug)]
+struct Entry {
+    prefix: Box<[u8]>,
+    value: Option<usize>,
+    // the first byte of the prefix of each child entry, and its position, ordered by the byte
+    children: Vec<(u8, usize)>,
+}
+
+impl Entry {
+    fn new(prefix: &[u8], value: Option<usize>) -> Self {
+        Entry {
+            prefix: prefix.into(),
+            value,
+            children: vec![],
+        }
+    }
+
+    fn child(&self, byte: u8) -> Result<usize, usize> {
+        self.children.binary_search_by_key(&byte, |&(b, _)| b)
+    }
+}
+
+impl Default for RadixIndex {
+    fn default() -> Self {
+        RadixIndex {
+            entries: vec![Entry::new(&[], None)],
+            len: 0,
+        }
+    }
+}
+
+impl RadixIndex {
+    /// The number of segments in the index.
+    pub(crate) fn len(&self) -> usize {
+        self.len
+    }
+
+    /// Adds a segment to the index, replacing the value of the segment where it's already
+    /// present.
+    pub(crate) fn insert(&mut self, segment: &str, value: usize) {
+        let mut current = 0;
+        let mut key = segment.as_bytes();
+
+        loop {
+            if key.is_empty() {
+                if self.entries[current].value.replace(value).is_none() {
+                    self.len += 1;
+                }
+                return;
+            }
+
+            let pos = match self.entries[current].child(key[0]) {
+                Ok(pos) => pos,
+                Err(pos) => {
+                    self.entries.push(Entry::new(key, Some(value)));
+                    let child = self.entries.len() - 1;
+                    self.entries[current].children.insert(pos, (key[0], child));
+                    self.len += 1;
+                    return;
+                }
+            };
+
+            let child = self.entries[current].children[pos].1;
+            let common = common_prefix(&self.entries[child].prefix, key);
+
+            if common < self.entries[child].prefix.len() {
+                // split the edge, so the shared part of the prefix leads to both the existing
+                // entry and the new segment
+                let prefix = self.entries[child].prefix.clone();
+                self.entries[child].prefix = prefix[common..].into();
+
+                let mut split = Entry::new(&prefix[..common], None);
+                split.children.push((prefix[common], child));
+                self.entries.push(split);
+
+                let split = self.entries.len() - 1;
+                self.entries[current].children[pos].1 = split;
+                current = split;
+            } else {
+                current = child;
+            }
+
+            key = &key[common..];
+        }
+    }
+
+    /// Finds the value of a segment in the index.
+    pub(crate) fn get(&self, segment: &str) -> Option<usize> {
+        let mut entry = &self.entries[0];
+        let mut key = segment.as_bytes();
+
+        loop {
+            if key.is_empty() {
+                return entry.value;
+            }
+
+            let pos = entry.child(key[0]).ok()?;
+            entry = &self.entries[entry.children[pos].1];
+
+            if !key.starts_with(&entry.prefix) {
+                return None;
+            }
+            key = &key[entry.prefix.len()..];
+        }
+    }
+}
+
+/// The length of the prefix shared by two byte strings.
+fn common_prefix(a: &[u8], b: &[u8]) -> usize {
+    a.iter().zip(b).take_while(|(a, b)| a == b).count()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    use std::hint::black_box;
+    use std::time::Instant;
+
+    #[test]
+    fn finds_inserted_segments() {
+        let segments = [
+            "users", "user", "usage", "us", "u", "profile", "profiles", "pro", "é", "éa",
+        ];
+
+        let mut index = RadixIndex::default();
+        for (i, segment) in segments.iter().enumerate() {
+            index.insert(segment, i);
+        }
+
+        assert_eq!(index.len(), segments.len());
+        for (i, segment) in segments.iter().enumerate() {
+            assert_eq!(index.get(segment), Some(i), "{}", segment);
+        }
+
+        for missing in &[
+            "", "use", "userss", "usa", "p", "prof", "profilex", "x", "\u{e8}",
+        ] {
+            assert_eq!(index.get(missing), None, "{}", missing);
+        }
+
+        index.insert("user", 42);
+        assert_eq!(index.get("user"), Some(42));
+        assert_eq!(index.len(), segments.len());
+    }
+
+    // Compares lookups through the index with the linear scan over the children of a `Node` which
+    // the index replaced. Run with `cargo test --release --lib radix -- --ignored --nocapture`.
+    #[test]
+    #[ignore]
+    fn bench_against_linear_scan() {
+        const ROUNDS: usize = 200;
+
+        for &routes in &[100, 500, 1000] {
+            let segments: Vec<String> = (0..routes)
+                .map(|i| match i % 4 {
+                    0 => format!("users{}", i),
+                    1 => format!("products-{}", i),
+                    2 => format!("api_v{}", i),
+                    _ => format!("{}-reports", i),
+                })
+                .collect();
+
+            let mut index = RadixIndex::default();
+            for (i, segment) in segments.iter().enumerate() {
+                index.insert(segment, i);
+            }
+
+            let start = Instant::now();
+            for _ in 0..ROUNDS {
+                for segment in &segments {
+                    black_box(segments.iter().position(|s| s == black_box(segment)));
+                }
+            }
+            let linear = start.elapsed();
+
+            let start = Instant::now();
+            for _ in 0..ROUNDS {
+                for segment in &segments {
+                    black_box(index.get(black_box(segment)));
+                }
+            }
+            let radix = start.elapsed();
+
+            let lookups = (ROUNDS * routes) as f64;
+            println!(
+                "{} routes: linear scan {:.1}ns/lookup, radix index {:.1}ns/lookup",
+                routes,
+                linear.as_nanos() as f64 / lookups,
+                radix.as_nanos() as f64 / lookups,
+            );
+        }
+    }
+}
@@ -2,6 +2,7 @@
 use std::collections::HashMap;
 
 use crate::helpers::http::PercentDecoded;
+use crate::router::tree::predicate::SegmentPredicate;
 use crate::router::tree::regex::ConstrainedSegmentRegex;
 
 /// Mapping of segment names into the collection of values for that segment.
@@ -22,6 +23,12 @@ pub enum SegmentType {
         regex: ConstrainedSegmentRegex,
     },
 
+    /// Uses the supplied predicate to determine match against incoming request paths.
+    Predicate {
+        /// Predicate used to match against a single segment of a request path.
+        predicate: SegmentPredicate,
+    },
+
     /// Matches any corresponding segment for incoming request paths.
     Dynamic,
 
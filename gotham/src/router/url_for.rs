@@ -0,0 +1,341 @@
+//! Defines `UrlFor`, which generates the paths of named routes.
+
+use std::collections::HashMap;
+use std::error::Error;
+use std::fmt::{self, Display};
+use std::sync::Arc;
+
+use percent_encoding::{utf8_percent_encode, AsciiSet, NON_ALPHANUMERIC};
+use serde::Serialize;
+use serde_json::Value;
+
+use crate::router::tree::node::Node;
+use crate::router::tree::segment::SegmentType;
+use crate::router::tree::Tree;
+use crate::state::StateData;
+
+// Characters which may be left as-is in a generated path segment.
+pub(crate) const PATH_SEGMENT: &AsciiSet = &NON_ALPHANUMERIC
+    .remove(b'-')
+    .remove(b'.')
+    .remove(b'_')
+    .remove(b'~');
+
+/// Generates the paths of routes which were named using `DefineSingleRoute::name`, so that
+/// handlers don't need to build URLs by hand.
+///
+/// The `Router` which receives a request from the server puts its `UrlFor` into `State` before
+/// dispatching. Routes defined within a `Router` which requests are delegated to aren't included.
+/// `UrlFor` is cheap to clone, and can be passed to templates which need to link to other routes.
+///
+/// ```rust
+/// # extern crate gotham;
+/// # extern crate hyper;
+/// # #[macro_use]
+/// # extern crate serde_derive;
+/// #
+/// # use hyper::{Body, Response, StatusCode};
+/// # use gotham::helpers::http::response::create_response;
+/// # use gotham::state::{FromState, State, StateData};
+/// # use gotham::router::Router;
+/// # use gotham::router::builder::*;
+/// # use gotham::router::response::extender::StaticResponseExtender;
+/// # use gotham::router::url_for::UrlFor;
+/// # use gotham::test::TestServer;
+/// #
+/// #[derive(Deserialize, Serialize)]
+/// struct UserPath {
+///     id: u32,
+/// }
+/// #
+/// # impl StateData for UserPath {}
+/// #
+/// # impl StaticResponseExtender for UserPath {
+/// #     type ResBody = Body;
+/// #     fn extend(_: &mut State, _: &mut Response<Body>) {}
+/// # }
+///
+/// fn create_user(state: State) -> (State, Response<Body>) {
+///     let location = UrlFor::borrow_from(&state)
+///         .path("user_detail", &UserPath { id: 42 })
+///         .unwrap();
+///
+///     let res = create_response(&state, StatusCode::CREATED, mime::TEXT_PLAIN, location);
+///     (state, res)
+/// }
+/// #
+/// # fn user_detail(state: State) -> (State, Response<Body>) {
+/// #   (state, Response::builder().status(StatusCode::OK).body(Body::empty()).unwrap())
+/// # }
+///
+/// fn router() -> Router {
+///     build_simple_router(|route| {
+///         route.post("/users").to(create_user);
+///         route
+///             .get("/users/:id")
+///             .name("user_detail")
+///             .with_path_extractor::<UserPath>()
+///             .to(user_detail);
+///     })
+/// }
+/// #
+/// # fn main() {
+/// #   let test_server = TestServer::new(router()).unwrap();
+/// #   let response = test_server.client()
+/// #       .post("https://example.com/users", "", mime::TEXT_PLAIN)
+/// #       .perform()
+/// #       .unwrap();
+/// #   assert_eq!(response.status(), StatusCode::CREATED);
+/// #   assert_eq!(response.read_utf8_body().unwrap(), "/users/42");
+/// # }
+/// ```
+#[derive(Clone, Debug, Default)]
+pub struct UrlFor {
+    paths: Arc<HashMap<String, NamedPath>>,
+}
+
+/// The segments of the path of a named route, and whether it ends with a trailing slash.
+#[derive(Clone, Debug, Default)]
+struct NamedPath {
+    parts: Vec<PathPart>,
+    trailing_slash: bool,
+}
+
+#[derive(Clone, Debug)]
+enum PathPart {
+    Static(String),
+    Param(String),
+    Glob(String),
+}
+
+impl UrlFor {
+    /// Collects the named routes within the `Tree`.
+    ///
+    /// # Panics
+    ///
+    /// When the same name was given to routes with different paths.
+    pub(crate) fn from_tree(tree: &Tree) -> UrlFor {
+        let mut paths = HashMap::new();
+        collect_paths(tree.borrow_root(), &mut vec![], &mut paths);
+
+        UrlFor {
+            paths: Arc::new(paths),
+        }
+    }
+
+    /// Generates the path of the route with the given `name`. The `params` are used to fill the
+    /// dynamic segments of the path, and are typically the `PathExtractor` of the route. Each
+    /// field is matched to the segment of the same name, and a glob segment (`*`) is filled from
+    /// a sequence of values.
+    ///
+    /// For a route without dynamic segments, `&()` can be used as the `params`.
+    pub fn path<T>(&self, name: &str, params: &T) -> Result<String, UrlForError>
+    where
+        T: Serialize,
+    {
+        let named = self
+            .paths
+            .get(name)
+            .ok_or_else(|| UrlForError::UnknownRoute(name.to_owned()))?;
+
+        let params = match serde_json::to_value(params) {
+            Ok(Value::Object(params)) => params,
+            Ok(Value::Null) => serde_json::Map::new(),
+            Ok(_) => {
+                return Err(UrlForError::InvalidParams(
+                    "parameters must be a struct or map".to_owned(),
+                ))
+            }
+            Err(e) => return Err(UrlForError::InvalidParams(e.to_string())),
+        };
+
+        let mut path = String::new();
+
+        for part in &named.parts {
+            match part {
+                PathPart::Static(segment) => {
+                    path.push('/');
+                    path.push_str(segment);
+                }
+                PathPart::Param(param) => {
+                    let value = params
+                        .get(param)
+                        .ok_or_else(|| UrlForError::MissingParam(param.clone()))?;
+                    path.push('/');
+                    path.push_str(&encode_segment(param, value)?);
+                }
+                PathPart::Glob(param) => {
+                    let values = match params.get(param) {
+                        Some(Value::Array(values)) if !values.is_empty() => values,
+                        _ => return Err(UrlForError::MissingParam(param.clone())),
+                    };
+                    for value in values {
+                        path.push('/');
+                        path.push_str(&encode_segment(param, value)?);
+                    }
+                }
+            }
+        }
+
+        if path.is_empty() || named.trailing_slash {
+            path.push('/');
+        }
+
+        Ok(path)
+    }
+}
+
+impl StateData for UrlFor {}
+
+/// The reason that `UrlFor` wasn't able to generate a path.
+#[derive(Debug, PartialEq)]
+pub enum UrlForError {
+    /// No route was given the name.
+    UnknownRoute(String),
+
+    /// The parameters didn't include a value for the named segment.
+    MissingParam(String),
+
+    /// The parameters couldn't be used to fill the segments of the path, such as when a field
+    /// contains a nested struct.
+    InvalidParams(String),
+}
+
+impl Display for UrlForError {
+    fn fmt(&self, out: &mut fmt::Formatter) -> fmt::Result {
+        match *self {
+            UrlForError::UnknownRoute(ref name) => write!(out, "no route named `{}`", name),
+            UrlForError::MissingParam(ref name) => write!(out, "missing value for `{}`", name),
+            UrlForError::InvalidParams(ref message) => {
+                write!(out, "invalid parameters: {}", message)
+            }
+        }
+    }
+}
+
+impl Error for UrlForError {}
+
+fn collect_paths(node: &Node, parts: &mut Vec<PathPart>, paths: &mut HashMap<String, NamedPath>) {
+    for name in node.names() {
+        let named = NamedPath {
+            parts: parts.clone(),
+            trailing_slash: node.requires_trailing_slash(),
+        };
+
+        if paths.insert(name.clone(), named).is_some() {
+            panic!("route name `{}` is used by more than one path", name);
+        }
+    }
+
+    for child in node.children() {
+        let part = match child.segment_type() {
+            SegmentType::Static => PathPart::Static(child.segment().to_owned()),
+            SegmentType::Glob => PathPart::Glob(child.segment().to_owned()),
+            _ => PathPart::Param(child.segment().to_owned()),
+        };
+
+        parts.push(part);
+        collect_paths(child, parts, paths);
+        parts.pop();
+    }
+}
+
+fn encode_segment(param: &str, value: &Value) -> Result<String, UrlForError> {
+    let value = match value {
+        Value::String(s) => s.clone(),
+        Value::Number(n) => n.to_string(),
+        Value::Bool(b) => b.to_string(),
+        _ => {
+            return Err(UrlForError::InvalidParams(format!(
+                "`{}` must be a string, number or boolean",
+                param
+            )))
+        }
+    };
+
+    Ok(utf8_percent_encode(&value, PATH_SEGMENT).to_string())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    use serde_derive::Serialize;
+
+    use crate::router::builder::*;
+
+    #[derive(Serialize)]
+    struct FileParams<'a> {
+        owner: &'a str,
+        #[serde(rename = "*")]
+        parts: Vec<&'a str>,
+    }
+
+    fn url_for() -> UrlFor {
+        let router = build_simple_router(|route| {
+            route.get("/").name("index").to(|state| (state, "index"));
+            route
+                .get("/about/")
+                .name("about")
+                .to(|state| (state, "about"));
+            route
+                .get("/users/:id:[0-9]+")
+                .name("user_detail")
+                .to(|state| (state, "user"));
+            route
+                .get("/files/:owner/*")
+                .name("file")
+                .to(|state| (state, "file"));
+        });
+
+        router.data.url_for.clone()
+    }
+
+    #[test]
+    fn generates_paths_of_named_routes() {
+        let url_for = url_for();
+
+        assert_eq!(url_for.path("index", &()).unwrap(), "/");
+        assert_eq!(url_for.path("about", &()).unwrap(), "/about/");
+
+        let mut params = HashMap::new();
+        params.insert("id", 42);
+        assert_eq!(url_for.path("user_detail", &params).unwrap(), "/users/42");
+
+        let params = FileParams {
+            owner: "jane doe",
+            parts: vec!["docs", "a/b.txt"],
+        };
+        assert_eq!(
+            url_for.path("file", &params).unwrap(),
+            "/files/jane%20doe/docs/a%2Fb.txt"
+        );
+    }
+
+    #[test]
+    fn describes_failures() {
+        let url_for = url_for();
+
+        assert_eq!(
+            url_for.path("missing", &()),
+            Err(UrlForError::UnknownRoute("missing".to_owned()))
+        );
+        assert_eq!(
+            url_for.path("user_detail", &()),
+            Err(UrlForError::MissingParam("id".to_owned()))
+        );
+        assert!(url_for.path("user_detail", &42).is_err());
+    }
+
+    #[test]
+    #[should_panic(expected = "route name `user` is used by more than one path")]
+    fn rejects_duplicate_names() {
+        build_simple_router(|route| {
+            route
+                .get("/users")
+                .name("user")
+                .to(|state| (state, "users"));
+            route.get("/user").name("user").to(|state| (state, "user"));
+        });
+    }
+}
@@ -0,0 +1,295 @@
+//! Defines `ApiVersion`, which describes a version of an API defined through
+//! `RouterBuilder::version`.
+
+use std::time::{SystemTime, UNIX_EPOCH};
+
+use httpdate::fmt_http_date;
+use hyper::header::{HeaderMap, HeaderName, HeaderValue, VARY};
+use hyper::{Body, Response};
+
+use crate::helpers::http::request::path::RequestPathSegments;
+use crate::router::tree::Tree;
+use crate::state::{FromState, State};
+
+/// A version of an API, whose routes are defined by `RouterBuilder::version` beneath a path
+/// prefix such as `/v1`.
+///
+/// A version may be marked as deprecated, and given a date after which it's no longer served,
+/// which is announced to clients through the `Deprecation` and `Sunset` headers of each response
+/// from the routes of the version.
+///
+/// # Examples
+///
+/// ```rust
+/// # extern crate gotham;
+/// # extern crate hyper;
+/// #
+/// # use std::time::{Duration, UNIX_EPOCH};
+/// # use hyper::StatusCode;
+/// # use gotham::router::builder::*;
+/// # use gotham::state::State;
+/// # use gotham::test::TestServer;
+/// #
+/// fn list_users(state: State) -> (State, &'static str) {
+///     (state, "[]")
+/// }
+///
+/// # fn main() {
+/// let router = build_simple_router(|route| {
+///     route
+///         .version(1, |route| {
+///             route.get("/users").to(list_users);
+///         })
+///         .deprecated(UNIX_EPOCH + Duration::from_secs(1_688_169_599))
+///         .sunset(UNIX_EPOCH + Duration::from_secs(1_735_689_600));
+///
+///     route.version(2, |route| {
+///         route.get("/users").to(list_users);
+///     });
+/// });
+///
+/// let test_server = TestServer::new(router).unwrap();
+/// let response = test_server
+///     .client()
+///     .get("http://localhost/v1/users")
+///     .perform()
+///     .unwrap();
+///
+/// assert_eq!(response.status(), StatusCode::OK);
+/// assert_eq!(response.headers()["deprecation"], "@1688169599");
+/// assert_eq!(response.headers()["sunset"], "Wed, 01 Jan 2025 00:00:00 GMT");
+/// # }
+/// ```
+#[derive(Clone, Debug, PartialEq)]
+pub struct ApiVersion {
+    number: u32,
+    deprecated: Option<SystemTime>,
+    sunset: Option<SystemTime>,
+}
+
+impl ApiVersion {
+    pub(crate) fn new(number: u32) -> Self {
+        ApiVersion {
+            number,
+            deprecated: None,
+            sunset: None,
+        }
+    }
+
+    /// The number of the version, as used in its path prefix.
+    pub fn number(&self) -> u32 {
+        self.number
+    }
+
+    /// Marks the version as deprecated since the given time, which is sent to clients as the
+    /// `Deprecation` header.
+    pub fn deprecated(&mut self, since: SystemTime) -> &mut Self {
+        self.deprecated = Some(since);
+        self
+    }
+
+    /// Sets the time after which the version will no longer be served, which is sent to clients
+    /// as the `Sunset` header.
+    pub fn sunset(&mut self, at: SystemTime) -> &mut Self {
+        self.sunset = Some(at);
+        self
+    }
+
+    /// Determines whether the version has been marked as deprecated.
+    pub fn is_deprecated(&self) -> bool {
+        self.deprecated.is_some()
+    }
+
+    /// The first segment of the paths of the version.
+    pub(crate) fn prefix(&self) -> String {
+        format!("v{}", self.number)
+    }
+
+    /// Adds the `Deprecation` and `Sunset` headers to a response from a route of the version.
+    fn extend(&self, headers: &mut HeaderMap) {
+        if let Some(since) = self.deprecated {
+            let seconds = since
+                .duration_since(UNIX_EPOCH)
+                .map(|d| d.as_secs())
+                .unwrap_or(0);
+            headers.insert("deprecation", format!("@{}", seconds).parse().unwrap());
+        }
+
+        if let Some(at) = self.sunset {
+            headers.insert("sunset", fmt_http_date(at).parse().unwrap());
+        }
+    }
+}
+
+/// The versions of an API defined through `RouterBuilder::version`, and the header through
+/// which a request may name the version it's for.
+#[derive(Default)]
+pub(crate) struct Versions {
+    header: Option<HeaderName>,
+    versions: Vec<ApiVersion>,
+}
+
+impl Versions {
+    /// Adds a version, returning the existing version where it was already added.
+    pub(crate) fn add(&mut self, number: u32) -> &mut ApiVersion {
+        let index = match self.versions.iter().position(|v| v.number == number) {
+            Some(index) => index,
+            None => {
+                self.versions.push(ApiVersion::new(number));
+                self.versions.len() - 1
+            }
+        };
+
+        &mut self.versions[index]
+    }
+
+    pub(crate) fn is_empty(&self) -> bool {
+        self.versions.is_empty()
+    }
+
+    pub(crate) fn set_header(&mut self, header: HeaderName) {
+        self.header = Some(header);
+    }
+
+    /// Determines the version which a request is for, if any. A request is for a version where
+    /// its path begins with the prefix of the version, or where its header names the version
+    /// and the prefixed path is routable. In the latter case, the prefixed path is returned to
+    /// be routed in place of the request path.
+    pub(crate) fn select(
+        &self,
+        state: &State,
+        tree: &Tree,
+        rps: RequestPathSegments,
+        ignore_case: bool,
+    ) -> (RequestPathSegments, Option<&ApiVersion>) {
+        if let Some(first) = rps.segments().first() {
+            let first = first.as_ref();
+            let prefixed = self.versions.iter().find(|v| {
+                let prefix = v.prefix();
+                first == prefix || (ignore_case && first.eq_ignore_ascii_case(&prefix))
+            });
+
+            if prefixed.is_some() {
+                return (rps, prefixed);
+            }
+        }
+
+        let requested = self
+            .header
+            .as_ref()
+            .and_then(|header| HeaderMap::borrow_from(state).get(header))
+            .and_then(parse_version)
+            .and_then(|number| self.versions.iter().find(|v| v.number == number));
+
+        if let Some(version) = requested {
+            let versioned = rps.prefixed(&version.prefix());
+            if tree
+                .traverse_with(versioned.segments(), ignore_case, &[], None)
+                .is_some()
+            {
+                return (versioned, Some(version));
+            }
+        }
+
+        (rps, None)
+    }
+
+    /// Adds the headers of the version to a response from one of its routes, and notes that the
+    /// response varies by the version header, where one is configured.
+    pub(crate) fn extend(&self, version: Option<&ApiVersion>, res: &mut Response<Body>) {
+        let headers = res.headers_mut();
+
+        if let Some(ref header) = self.header {
+            headers.append(VARY, HeaderValue::from_str(header.as_str()).unwrap());
+        }
+
+        if let Some(version) = version {
+            version.extend(headers);
+        }
+    }
+}
+
+/// Parses a version named by a request header, as either `1` or `v1`.
+fn parse_version(value: &HeaderValue) -> Option<u32> {
+    let value = value.to_str().ok()?.trim();
+    let number = value
+        .strip_prefix('v')
+        .or_else(|| value.strip_prefix('V'))
+        .unwrap_or(value);
+    number.parse().ok()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    use std::time::Duration;
+
+    use hyper::StatusCode;
+
+    use crate::router::builder::*;
+    use crate::test::TestServer;
+
+    fn handler(body: &'static str) -> impl Fn(State) -> (State, &'static str) + Clone {
+        move |state| (state, body)
+    }
+
+    #[test]
+    fn routes_versions() {
+        let router = build_simple_router(|route| {
+            route.version_header("accept-version");
+            route
+                .version(1, |route| {
+                    route.get("/users").to(handler("v1"));
+                })
+                .deprecated(UNIX_EPOCH + Duration::from_secs(1_688_169_599));
+            route.version(2, |route| {
+                route.get("/users").to(handler("v2"));
+            });
+            route.get("/health").to(handler("ok"));
+        });
+
+        let test_server = TestServer::new(router).unwrap();
+        let client = test_server.client();
+        let request = |path: &str, version: Option<&'static str>| {
+            let mut request = client.get(format!("http://localhost{}", path));
+            if let Some(version) = version {
+                request = request.with_header("accept-version", HeaderValue::from_static(version));
+            }
+            request.perform().unwrap()
+        };
+
+        let response = request("/v1/users", None);
+        assert_eq!(response.headers()["deprecation"], "@1688169599");
+        assert_eq!(response.headers()[VARY], "accept-version");
+        assert_eq!(response.read_utf8_body().unwrap(), "v1");
+
+        let response = request("/users", Some("v2"));
+        assert!(response.headers().get("deprecation").is_none());
+        assert_eq!(response.read_utf8_body().unwrap(), "v2");
+
+        // the path takes precedence over the header
+        let response = request("/v1/users", Some("2"));
+        assert_eq!(response.read_utf8_body().unwrap(), "v1");
+
+        // paths outside of the version are still routed
+        let response = request("/health", Some("1"));
+        assert!(response.headers().get("deprecation").is_none());
+        assert_eq!(response.read_utf8_body().unwrap(), "ok");
+
+        let response = request("/users", Some("3"));
+        assert_eq!(response.status(), StatusCode::NOT_FOUND);
+        let response = request("/users", None);
+        assert_eq!(response.status(), StatusCode::NOT_FOUND);
+    }
+
+    #[test]
+    fn parses_header_versions() {
+        let parse = |value: &'static str| parse_version(&HeaderValue::from_static(value));
+
+        assert_eq!(parse("1"), Some(1));
+        assert_eq!(parse(" v2 "), Some(2));
+        assert_eq!(parse("V3"), Some(3));
+        assert_eq!(parse("latest"), None);
+    }
+}
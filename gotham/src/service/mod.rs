@@ -17,7 +17,8 @@ use log::debug;
 use crate::handler::NewHandler;
 use crate::helpers::http::request::path::RequestPathSegments;
 use crate::state::client_addr::put_client_addr;
-use crate::state::{set_request_id, State};
+use crate::state::scheme::put_scheme;
+use crate::state::{set_request_id, Scheme, State};
 
 mod trap;
 
@@ -28,6 +29,7 @@ where
     T: NewHandler + 'static,
 {
     handler: Arc<T>,
+    scheme: Scheme,
 }
 
 impl<T> GothamService<T>
@@ -37,12 +39,19 @@ where
     pub(crate) fn new(handler: T) -> GothamService<T> {
         GothamService {
             handler: Arc::new(handler),
+            scheme: Scheme::Http,
         }
     }
 
+    /// Sets the `Scheme` reported for requests on the connections of this service.
+    pub(crate) fn with_scheme(self, scheme: Scheme) -> GothamService<T> {
+        GothamService { scheme, ..self }
+    }
+
     pub(crate) fn connect(&self, client_addr: SocketAddr) -> ConnectedGothamService<T> {
         ConnectedGothamService {
             client_addr,
+            scheme: self.scheme,
             handler: self.handler.clone(),
         }
     }
@@ -56,6 +65,7 @@ where
 {
     handler: Arc<T>,
     client_addr: SocketAddr,
+    scheme: Scheme,
 }
 
 impl<T> Service for ConnectedGothamService<T>
@@ -71,6 +81,7 @@ where
         let mut state = State::new();
 
         put_client_addr(&mut state, self.client_addr);
+        put_scheme(&mut state, self.scheme);
 
         let (
             request::Parts {
@@ -4,7 +4,9 @@ pub(crate) mod client_addr;
 mod data;
 mod from_state;
 pub mod request_id;
+pub(crate) mod scheme;
 
+use hyper::{HeaderMap, Method, Uri, Version};
 use log::trace;
 
 use std::any::{Any, TypeId};
@@ -14,8 +16,9 @@ pub use crate::state::client_addr::client_addr;
 pub use crate::state::data::StateData;
 pub use crate::state::from_state::FromState;
 pub use crate::state::request_id::request_id;
+pub use crate::state::scheme::{request_scheme, Scheme};
 
-pub(crate) use crate::state::request_id::set_request_id;
+pub(crate) use crate::state::request_id::{replace_request_id, set_request_id};
 
 /// Provides storage for request state, and stores one item of each type. The types used for
 /// storage must implement the `gotham::state::StateData` trait to allow its storage. The
@@ -58,6 +61,33 @@ impl State {
         }
     }
 
+    /// Creates a new `State` containing a copy of the request data stored by `GothamService`,
+    /// other than the request body. This is for internal Gotham use, to respond to a request
+    /// once its `State` is no longer available, such as when its handler has timed out.
+    pub(crate) fn request_snapshot(&self) -> State {
+        let mut state = State::new();
+
+        if let Some(method) = self.try_borrow::<Method>() {
+            state.put(method.clone());
+        }
+        if let Some(uri) = self.try_borrow::<Uri>() {
+            state.put(uri.clone());
+        }
+        if let Some(version) = self.try_borrow::<Version>() {
+            state.put(*version);
+        }
+        if let Some(headers) = self.try_borrow::<HeaderMap>() {
+            state.put(headers.clone());
+        }
+        if let Some(addr) = client_addr(self) {
+            client_addr::put_client_addr(&mut state, addr);
+        }
+
+        scheme::put_scheme(&mut state, request_scheme(self));
+        request_id::copy_request_id(self, &mut state);
+        state
+    }
+
     /// Creates a new, empty `State` and yields it mutably into the provided closure. This is
     /// intended only for use in the documentation tests for `State`, since the `State` container
     /// cannot be constructed otherwise.
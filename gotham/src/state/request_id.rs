@@ -43,6 +43,21 @@ pub(crate) fn set_request_id<'a>(state: &'a mut State) -> &'a str {
     request_id(state)
 }
 
+/// Replaces the request ID, such as with the value of a header trusted by `RequestIdMiddleware`.
+pub(crate) fn replace_request_id(state: &mut State, val: String) {
+    trace!("[{}] RequestId replaced", val);
+    state.put(RequestId { val });
+}
+
+/// Copies the request ID, if one has been set, into another `State`.
+pub(super) fn copy_request_id(from: &State, to: &mut State) {
+    if let Some(request_id) = RequestId::try_borrow_from(from) {
+        to.put(RequestId {
+            val: request_id.val.clone(),
+        });
+    }
+}
+
 /// Returns the request ID associated with the current request.
 ///
 /// This is typically used for logging and correlating events that occurred within a request.
@@ -0,0 +1,74 @@
+//! Defines storage for the scheme of the connection a request was received on
+
+use hyper::Uri;
+
+use crate::state::{FromState, State, StateData};
+
+/// The scheme a request was made with, as reported by `request_scheme`.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum Scheme {
+    /// The request was received over a plain connection.
+    Http,
+    /// The request was received over a TLS connection.
+    Https,
+}
+
+struct ConnectionScheme {
+    scheme: Scheme,
+}
+
+impl StateData for ConnectionScheme {}
+
+pub(crate) fn put_scheme(state: &mut State, scheme: Scheme) {
+    state.put(ConnectionScheme { scheme })
+}
+
+/// Returns the `Scheme` of the request. This is `Scheme::Https` when the request was received by
+/// a server started through `gotham::tls`, and `Scheme::Http` for other servers.
+///
+/// Where the connection wasn't established by Gotham, the scheme of the request URI is used when
+/// present, otherwise `Scheme::Http` is assumed.
+///
+/// # Examples
+///
+/// ```rust
+/// # extern crate gotham;
+/// # extern crate hyper;
+/// # extern crate mime;
+/// #
+/// # use hyper::{Body, Response, StatusCode};
+/// # use gotham::helpers::http::response::create_response;
+/// # use gotham::state::{request_scheme, Scheme, State};
+/// # use gotham::test::TestServer;
+/// #
+/// fn my_handler(state: State) -> (State, Response<Body>) {
+///     let body = match request_scheme(&state) {
+///         Scheme::Https => "secure",
+///         Scheme::Http => "insecure",
+///     };
+///
+///     let response = create_response(&state, StatusCode::OK, mime::TEXT_PLAIN, body);
+///     (state, response)
+/// }
+/// #
+/// # fn main() {
+/// #   let test_server = TestServer::new(|| Ok(my_handler)).unwrap();
+/// #   let response = test_server
+/// #       .client()
+/// #       .get("http://localhost/")
+/// #       .perform()
+/// #       .unwrap();
+/// #
+/// #   assert_eq!(response.read_utf8_body().unwrap(), "insecure");
+/// # }
+/// ```
+pub fn request_scheme(state: &State) -> Scheme {
+    if let Some(connection) = ConnectionScheme::try_borrow_from(state) {
+        return connection.scheme;
+    }
+
+    match Uri::try_borrow_from(state).and_then(Uri::scheme_part) {
+        Some(scheme) if scheme.as_str().eq_ignore_ascii_case("https") => Scheme::Https,
+        _ => Scheme::Http,
+    }
+}
@@ -6,9 +6,10 @@ use tokio::net::TcpListener;
 use tokio::runtime::TaskExecutor;
 use tokio_rustls::{rustls, TlsAcceptor};
 
-use super::{bind_server, new_runtime, tcp_listener};
+use super::{bind_server_with_scheme, new_runtime, tcp_listener};
 
 use super::handler::NewHandler;
+use super::state::Scheme;
 
 pub mod test;
 
@@ -86,10 +87,12 @@ where
     NH: NewHandler + 'static,
 {
     let tls = TlsAcceptor::from(Arc::new(tls_config));
-    bind_server(listener, new_handler, move |socket| {
+    let accept = move |socket| {
         tls.accept(socket).map_err(|e| {
             error!(target: "gotham::tls", "TLS handshake error: {:?}", e);
             ()
         })
-    })
+    };
+
+    bind_server_with_scheme(listener, new_handler, accept, Scheme::Https)
 }
@@ -235,7 +235,7 @@ mod tests {
 
     use crate::handler::{Handler, HandlerFuture, IntoHandlerError, NewHandler};
     use crate::helpers::http::response::create_response;
-    use crate::state::{client_addr, FromState, State};
+    use crate::state::{client_addr, request_scheme, FromState, State};
     use futures::{future, Stream};
     use http::header::CONTENT_TYPE;
     use log::info;
@@ -271,6 +271,15 @@ mod tests {
 
                     Box::new(future::ok((state, response)))
                 }
+                "/scheme" => {
+                    info!("TestHandler responding to /scheme");
+                    let response = Response::builder()
+                        .status(StatusCode::OK)
+                        .body(format!("{:?}", request_scheme(&state)).into())
+                        .unwrap();
+
+                    Box::new(future::ok((state, response)))
+                }
                 _ => unreachable!(),
             }
         }
@@ -364,6 +373,24 @@ mod tests {
         assert_eq!(received_addr, client_addr);
     }
 
+    #[test]
+    fn sets_scheme() {
+        let new_service = || {
+            Ok(TestHandler {
+                response: String::new(),
+            })
+        };
+
+        let test_server = TestServer::new(new_service).unwrap();
+        let response = test_server
+            .client()
+            .get("https://localhost/scheme")
+            .perform()
+            .unwrap();
+
+        assert_eq!(response.read_utf8_body().unwrap(), "Https");
+    }
+
     #[test]
     fn async_echo() {
         fn handler(mut state: State) -> Box<HandlerFuture> {
@@ -84,7 +84,7 @@ use gotham::state::{request_id, State};
 
 mod repo;
 
-pub use crate::repo::Repo;
+pub use crate::repo::{Repo, RepoError};
 
 /// A Gotham compatible Middleware that manages a pool of Diesel connections via a `Repo` and hands
 /// out connections to other Middleware and Handlers that require them via the Gotham `State`
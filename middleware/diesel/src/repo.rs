@@ -5,8 +5,46 @@ use futures::future::{poll_fn, Future};
 use gotham_derive::StateData;
 use log::error;
 use r2d2::{CustomizeConnection, Pool, PooledConnection};
+use std::error::Error;
+use std::fmt::{self, Display};
 use tokio_threadpool::blocking;
 
+/// The error returned by `Repo::try_run`, where either no connection could be checked out of the
+/// pool, or the closure given a connection failed.
+#[derive(Debug)]
+pub enum RepoError<E> {
+    /// No connection became available within the `connection_timeout` of the pool, such as
+    /// when every connection is in use, or the database can't be reached.
+    Pool(r2d2::Error),
+
+    /// The closure given a connection returned an error.
+    Query(E),
+}
+
+impl<E> Display for RepoError<E>
+where
+    E: Display,
+{
+    fn fmt(&self, out: &mut fmt::Formatter) -> fmt::Result {
+        match *self {
+            RepoError::Pool(ref e) => write!(out, "unable to check out a connection: {}", e),
+            RepoError::Query(ref e) => e.fmt(out),
+        }
+    }
+}
+
+impl<E> Error for RepoError<E>
+where
+    E: Error + 'static,
+{
+    fn source(&self) -> Option<&(dyn Error + 'static)> {
+        match *self {
+            RepoError::Pool(ref e) => Some(e),
+            RepoError::Query(ref e) => Some(e),
+        }
+    }
+}
+
 /// A database "repository", for running database workloads.
 /// Manages a connection pool and running blocking tasks using
 /// `tokio_threadpool::blocking` which does not block the tokio event loop.
@@ -109,13 +147,44 @@ where
         database_url: &str,
         builder: r2d2::Builder<ConnectionManager<T>>,
     ) -> Self {
+        Self::try_from_pool_builder(database_url, builder).expect("could not initiate test db pool")
+    }
+
+    /// Creates a repo with a pool builder, in the same way as `from_pool_builder`, returning the
+    /// error where the pool can't open its initial connections rather than panicking.
+    ///
+    /// ```rust
+    /// # use diesel::sqlite::SqliteConnection;
+    /// use r2d2::Pool;
+    ///
+    /// type Repo = gotham_middleware_diesel::Repo<SqliteConnection>;
+    /// let result = Repo::try_from_pool_builder("/nonexistent/directory/db.sqlite",
+    ///     Pool::builder()
+    ///         .connection_timeout(std::time::Duration::from_millis(100))
+    /// );
+    /// assert!(result.is_err());
+    /// ```
+    pub fn try_from_pool_builder(
+        database_url: &str,
+        builder: r2d2::Builder<ConnectionManager<T>>,
+    ) -> Result<Self, r2d2::Error> {
         let manager = ConnectionManager::new(database_url);
-        let connection_pool = builder
-            .build(manager)
-            .expect("could not initiate test db pool");
+        let connection_pool = builder.build(manager)?;
+        Ok(Repo { connection_pool })
+    }
+
+    /// Creates a repo from a connection pool which has already been built, such as one which is
+    /// shared with other parts of the application.
+    pub fn from_pool(connection_pool: Pool<ConnectionManager<T>>) -> Self {
         Repo { connection_pool }
     }
 
+    /// The connection pool of the repo, such as for checking out a connection directly or for
+    /// reporting `Pool::state` to monitoring.
+    pub fn pool(&self) -> &Pool<ConnectionManager<T>> {
+        &self.connection_pool
+    }
+
     /// Creates a repo for use in tests, where queries are executed
     /// with an isolated test transaction and rolled back when
     /// the connection is dropped. This allows tests to run in parallel
@@ -135,7 +204,60 @@ where
     /// Runs the given closure in a way that is safe for blocking IO to the
     /// database without blocking the tokio reactor.
     /// The closure will be passed a `Connection` from the pool to use.
+    ///
+    /// # Panics
+    ///
+    /// The returned future panics where no connection can be checked out of the pool within its
+    /// `connection_timeout`. Use `try_run` to handle that as an error instead.
     pub fn run<F, R, E>(&self, f: F) -> impl Future<Item = R, Error = E>
+    where
+        F: FnOnce(PooledConnection<ConnectionManager<T>>) -> Result<R, E>
+            + Send
+            + std::marker::Unpin
+            + 'static,
+        T: Send + 'static,
+    {
+        self.try_run(f).map_err(|e| match e {
+            RepoError::Query(e) => e,
+            RepoError::Pool(e) => panic!("unable to check out a database connection: {}", e),
+        })
+    }
+
+    /// Runs the given closure in the same way as `run`, failing with `RepoError::Pool` where no
+    /// connection can be checked out of the pool within its `connection_timeout`, such as when
+    /// the pool is exhausted.
+    ///
+    /// ```rust
+    /// # extern crate tokio;
+    /// # use diesel::sqlite::SqliteConnection;
+    /// # use diesel::RunQueryDsl;
+    /// # use gotham_middleware_diesel::RepoError;
+    /// # use r2d2::Pool;
+    /// # use std::time::Duration;
+    /// # use tokio::runtime::Runtime;
+    /// #
+    /// # let mut runtime = Runtime::new().unwrap();
+    /// type Repo = gotham_middleware_diesel::Repo<SqliteConnection>;
+    /// let repo = Repo::from_pool_builder(":memory:",
+    ///     Pool::builder()
+    ///         .max_size(1)
+    ///         .connection_timeout(Duration::from_millis(100))
+    /// );
+    ///
+    /// // hold the only connection, so that the pool is exhausted
+    /// let held = repo.pool().get().unwrap();
+    ///
+    /// let result = runtime.block_on(repo.try_run(|conn| {
+    ///     diesel::sql_query("SELECT 1").execute(&conn)
+    /// }));
+    ///
+    /// match result {
+    ///     Err(RepoError::Pool(e)) => eprintln!("database unavailable: {}", e),
+    ///     _ => panic!("expected the pool to be exhausted"),
+    /// }
+    /// # drop(held);
+    /// ```
+    pub fn try_run<F, R, E>(&self, f: F) -> impl Future<Item = R, Error = RepoError<E>>
     where
         F: FnOnce(PooledConnection<ConnectionManager<T>>) -> Result<R, E>
             + Send
@@ -149,15 +271,39 @@ where
         // `f.take()` allows the borrow checker to be sure `f` is not moved into the inner closure
         // multiple times if `poll_fn` is called multple times.
         let mut f = Some(f);
-        poll_fn(move || blocking(|| (f.take().unwrap())(pool.get().unwrap()))).then(
-            |future_result| match future_result {
-                Ok(query_result) => match query_result {
-                    Ok(result) => future::ok(result),
-                    Err(error) => future::err(error),
-                },
-                Err(e) => panic!("Error running async database task: {:?}", e),
+        poll_fn(move || {
+            blocking(|| {
+                let conn = pool.get().map_err(RepoError::Pool)?;
+                (f.take().unwrap())(conn).map_err(RepoError::Query)
+            })
+        })
+        .then(|future_result| match future_result {
+            Ok(query_result) => match query_result {
+                Ok(result) => future::ok(result),
+                Err(error) => future::err(error),
             },
-        )
+            Err(e) => panic!("Error running async database task: {:?}", e),
+        })
+    }
+
+    /// Checks that a connection can be checked out of the pool and used, by running `SELECT 1`,
+    /// such as for a health check endpoint.
+    ///
+    /// ```rust
+    /// # extern crate tokio;
+    /// # use diesel::sqlite::SqliteConnection;
+    /// # use tokio::runtime::Runtime;
+    /// #
+    /// # let mut runtime = Runtime::new().unwrap();
+    /// type Repo = gotham_middleware_diesel::Repo<SqliteConnection>;
+    /// let repo = Repo::new(":memory:");
+    /// assert!(runtime.block_on(repo.check()).is_ok());
+    /// ```
+    pub fn check(&self) -> impl Future<Item = (), Error = RepoError<diesel::result::Error>>
+    where
+        T: Send + 'static,
+    {
+        self.try_run(|conn| conn.execute("SELECT 1").map(|_| ()))
     }
 }
 
@@ -5,7 +5,9 @@
 //! Requests that lack a token are returned with the
 //! Status Code `400: Bad Request`. Tokens that fail
 //! validation cause the middleware to return Status Code
-//! `401: Unauthorized`.
+//! `401: Unauthorized`. Routes and scopes which need
+//! particular claims can also require them, returning
+//! Status Code `403: Forbidden` when they're missing.
 #![warn(missing_docs, deprecated)]
 extern crate futures;
 extern crate gotham;
@@ -14,6 +16,8 @@ extern crate gotham_derive;
 extern crate hyper;
 extern crate jsonwebtoken;
 extern crate serde;
+#[cfg_attr(test, macro_use)]
+extern crate serde_json;
 #[macro_use]
 extern crate log;
 #[cfg(test)]
@@ -21,7 +25,9 @@ extern crate log;
 extern crate serde_derive;
 
 mod middleware;
+mod require_claims;
 mod state_data;
 
 pub use self::middleware::JWTMiddleware;
+pub use self::require_claims::RequireClaimsMiddleware;
 pub use self::state_data::AuthorizationToken;
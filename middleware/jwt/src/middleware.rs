@@ -1,18 +1,24 @@
-use crate::state_data::AuthorizationToken;
+use crate::state_data::{AuthorizationToken, RawClaims};
 use futures::{future, Future};
 use gotham::{
     handler::HandlerFuture,
     helpers::http::response::create_empty_response,
-    middleware::{Middleware, NewMiddleware},
+    middleware::{auth::AuthenticatedUser, Middleware, NewMiddleware},
     state::{request_id, FromState, State},
 };
 use hyper::{
     header::{HeaderMap, AUTHORIZATION},
     StatusCode,
 };
-use jsonwebtoken::{decode, Validation};
+use jsonwebtoken::{decode, Algorithm, TokenData, Validation};
 use serde::de::Deserialize;
-use std::{io, marker::PhantomData, panic::RefUnwindSafe};
+use serde_json::{Map, Value};
+use std::{
+    io,
+    marker::PhantomData,
+    panic::RefUnwindSafe,
+    time::{Duration, SystemTime, UNIX_EPOCH},
+};
 
 const DEFAULT_SCHEME: &str = "Bearer";
 
@@ -28,6 +34,15 @@ const DEFAULT_SCHEME: &str = "Bearer";
 /// Tokens that fail validation cause the middleware
 /// to return Status Code `401: Unauthorized`.
 ///
+/// Tokens are signed with HS256 by default, or with
+/// RS256 when created by `rs256`. Their `exp` claim is
+/// always checked, as is their `nbf` claim where it's
+/// present, and their `iss` and `aud` claims are
+/// checked when an issuer or audience is configured.
+/// Where a valid token has a `sub` claim, an
+/// `AuthenticatedUser` with the subject as its username
+/// is put into `State` alongside the `AuthorizationToken`.
+///
 /// Example:
 /// ```rust
 /// extern crate futures;
@@ -86,12 +101,23 @@ const DEFAULT_SCHEME: &str = "Bearer";
 /// # }
 /// ```
 pub struct JWTMiddleware<T> {
-    secret: String,
+    key: Vec<u8>,
+    secret: bool,
     validation: Validation,
+    audience: Option<String>,
     scheme: String,
     claims: PhantomData<T>,
 }
 
+// Whether tokens signed with `algorithm` are verified with a shared secret, rather than with a
+// public key.
+fn uses_secret(algorithm: Algorithm) -> bool {
+    matches!(
+        algorithm,
+        Algorithm::HS256 | Algorithm::HS384 | Algorithm::HS512
+    )
+}
+
 impl<T> JWTMiddleware<T>
 where
     T: for<'de> Deserialize<'de> + Send + Sync,
@@ -99,19 +125,43 @@ where
     /// Creates a JWTMiddleware instance from the provided secret,
     /// which, by default, uses HS256 as the crypto scheme.
     pub fn new<S: Into<String>>(secret: S) -> Self {
-        let validation = Validation::default();
+        Self::with_key(secret.into().into_bytes(), Algorithm::HS256)
+    }
 
+    /// Creates a JWTMiddleware instance from the provided
+    /// DER encoded RSA public key, which uses RS256 as the
+    /// crypto scheme.
+    pub fn rs256(public_key: &[u8]) -> Self {
+        Self::with_key(public_key.to_vec(), Algorithm::RS256)
+    }
+
+    fn with_key(key: Vec<u8>, algorithm: Algorithm) -> Self {
         JWTMiddleware {
-            secret: secret.into(),
-            validation,
+            key,
+            secret: uses_secret(algorithm),
+            validation: Validation::new(algorithm),
+            audience: None,
             scheme: DEFAULT_SCHEME.into(),
             claims: PhantomData,
         }
     }
 
     /// Create a new instance of the middleware by appending new
-    /// validation constraints.
-    pub fn validation(self, validation: Validation) -> Self {
+    /// validation constraints, which replace the issuer and leeway
+    /// set before.
+    ///
+    /// Only the algorithms which use the kind of key the middleware
+    /// was created with are accepted, so that a public key can't be
+    /// used as an HS256 secret. Where none of the algorithms do, the
+    /// algorithm the middleware was created with is kept.
+    pub fn validation(self, mut validation: Validation) -> Self {
+        let secret = self.secret;
+        validation
+            .algorithms
+            .retain(|&algorithm| uses_secret(algorithm) == secret);
+        if validation.algorithms.is_empty() {
+            validation.algorithms = self.validation.algorithms.clone();
+        }
         JWTMiddleware { validation, ..self }
     }
 
@@ -122,6 +172,70 @@ where
             ..self
         }
     }
+
+    /// Create a new instance of the middleware which only accepts
+    /// tokens whose `iss` claim is the provided issuer.
+    pub fn issuer<S: Into<String>>(mut self, issuer: S) -> Self {
+        self.validation.iss = Some(issuer.into());
+        self
+    }
+
+    /// Create a new instance of the middleware which only accepts
+    /// tokens whose `aud` claim is, or contains, the provided audience.
+    pub fn audience<S: Into<String>>(self, audience: S) -> Self {
+        JWTMiddleware {
+            audience: Some(audience.into()),
+            ..self
+        }
+    }
+
+    /// Create a new instance of the middleware which accepts tokens
+    /// that expired, or that aren't valid yet, by up to the leeway.
+    pub fn leeway(mut self, leeway: Duration) -> Self {
+        self.validation.leeway = leeway.as_secs() as i64;
+        self
+    }
+}
+
+impl<T> JWTMiddleware<T>
+where
+    T: for<'de> Deserialize<'de>,
+{
+    fn decode(&self, token: &str) -> Result<(TokenData<T>, Map<String, Value>), String> {
+        let data = decode::<Map<String, Value>>(token, &self.key, &self.validation)
+            .map_err(|e| e.to_string())?;
+        let raw = data.claims;
+
+        // `Validation` requires an `nbf` claim once it checks them, but the claim is optional
+        if let Some(nbf) = raw.get("nbf").and_then(Value::as_i64) {
+            let now = SystemTime::now()
+                .duration_since(UNIX_EPOCH)
+                .map(|now| now.as_secs() as i64)
+                .unwrap_or(0);
+            if nbf > now + self.validation.leeway {
+                return Err("Immature signature".to_owned());
+            }
+        }
+
+        if let Some(ref audience) = self.audience {
+            let valid = match raw.get("aud") {
+                Some(Value::String(aud)) => aud == audience,
+                Some(Value::Array(auds)) => auds.iter().any(|aud| aud == audience),
+                _ => false,
+            };
+            if !valid {
+                return Err("Invalid audience".to_owned());
+            }
+        }
+
+        let claims =
+            serde_json::from_value(Value::Object(raw.clone())).map_err(|e| e.to_string())?;
+        let token = TokenData {
+            header: data.header,
+            claims,
+        };
+        Ok((token, raw))
+    }
 }
 
 impl<T> Middleware for JWTMiddleware<T>
@@ -148,8 +262,12 @@ where
             return Box::new(future::ok((state, res)));
         }
 
-        match decode::<T>(&token.unwrap(), self.secret.as_ref(), &self.validation) {
-            Ok(token) => {
+        match self.decode(&token.unwrap()) {
+            Ok((token, raw)) => {
+                if let Some(Value::String(sub)) = raw.get("sub") {
+                    state.put(AuthenticatedUser::new(sub.as_str()));
+                }
+                state.put(RawClaims(raw));
                 state.put(AuthorizationToken(token));
 
                 let res = chain(state).and_then(|(state, res)| {
@@ -160,7 +278,7 @@ where
                 Box::new(res)
             }
             Err(e) => {
-                trace!("[{}] error jwt middleware", e);
+                trace!("[{}] error jwt middleware: {}", request_id(&state), e);
                 let res = create_empty_response(&state, StatusCode::UNAUTHORIZED);
                 Box::new(future::ok((state, res)))
             }
@@ -176,8 +294,10 @@ where
 
     fn new_middleware(&self) -> io::Result<Self::Instance> {
         Ok(JWTMiddleware {
-            secret: self.secret.clone(),
+            key: self.key.clone(),
+            secret: self.secret,
             validation: self.validation.clone(),
+            audience: self.audience.clone(),
             scheme: self.scheme.clone(),
             claims: PhantomData,
         })
@@ -198,6 +318,8 @@ mod tests {
     use jsonwebtoken::{encode, Algorithm, Header};
 
     const SECRET: &str = "some-secret";
+    const PRIVATE_KEY: &[u8] = include_bytes!("../resources/test/private_rsa_key.der");
+    const PUBLIC_KEY: &[u8] = include_bytes!("../resources/test/public_rsa_key.der");
 
     #[derive(Debug, Deserialize, Serialize)]
     pub struct Claims {
@@ -358,4 +480,125 @@ mod tests {
 
         assert_eq!(res.status(), StatusCode::OK);
     }
+
+    fn now() -> u64 {
+        SystemTime::now()
+            .duration_since(UNIX_EPOCH)
+            .unwrap()
+            .as_secs()
+    }
+
+    fn user_handler(state: State) -> (State, String) {
+        let user = AuthenticatedUser::borrow_from(&state).username().to_owned();
+        (state, user)
+    }
+
+    fn status(middleware: JWTMiddleware<Claims>, token: &str) -> StatusCode {
+        let test_server = TestServer::new(router(middleware)).unwrap();
+        test_server
+            .client()
+            .get("https://example.com")
+            .with_header(AUTHORIZATION, format!("Bearer {}", token).parse().unwrap())
+            .perform()
+            .unwrap()
+            .status()
+    }
+
+    #[test]
+    fn jwt_middleware_authenticated_user_test() {
+        let token = token(Algorithm::HS256);
+        let (chain, pipelines) =
+            single_pipeline(new_pipeline().add(default_jwt_middleware()).build());
+        let test_server = TestServer::new(build_router(chain, pipelines, |route| {
+            route.get("/").to(user_handler);
+        }))
+        .unwrap();
+        let res = test_server
+            .client()
+            .get("https://example.com")
+            .with_header(AUTHORIZATION, format!("Bearer {}", token).parse().unwrap())
+            .perform()
+            .unwrap();
+
+        assert_eq!(res.status(), StatusCode::OK);
+        assert_eq!(res.read_utf8_body().unwrap(), "test@example.net");
+    }
+
+    #[test]
+    fn jwt_middleware_rs256_token_test() {
+        let claims = json!({ "sub": "test@example.net", "exp": now() + 60 });
+        let token = encode(&Header::new(Algorithm::RS256), &claims, PRIVATE_KEY).unwrap();
+
+        assert_eq!(
+            status(JWTMiddleware::rs256(PUBLIC_KEY), &token),
+            StatusCode::OK
+        );
+
+        // tokens signed with another algorithm are rejected
+        assert_eq!(
+            status(
+                JWTMiddleware::rs256(PUBLIC_KEY),
+                &self::token(Algorithm::HS256)
+            ),
+            StatusCode::UNAUTHORIZED
+        );
+
+        // including those using the public key as a secret, whatever the validation
+        let forged = encode(&Header::default(), &claims, PUBLIC_KEY).unwrap();
+        for validation in &[Validation::default(), Validation::new(Algorithm::RS256)] {
+            let middleware = JWTMiddleware::rs256(PUBLIC_KEY).validation(validation.clone());
+            assert_eq!(status(middleware, &forged), StatusCode::UNAUTHORIZED);
+        }
+        let middleware = JWTMiddleware::rs256(PUBLIC_KEY).validation(Validation::default());
+        assert_eq!(status(middleware, &token), StatusCode::OK);
+    }
+
+    #[test]
+    fn jwt_middleware_issuer_and_audience_test() {
+        let middleware = || {
+            JWTMiddleware::<Claims>::new(SECRET)
+                .issuer("issuer")
+                .audience("api")
+        };
+        let valid = json!({
+            "sub": "test@example.net",
+            "exp": now() + 60,
+            "iss": "issuer",
+            "aud": ["web", "api"],
+        });
+        let hs256 = |claims: &Value| encode(&Header::default(), claims, SECRET.as_ref()).unwrap();
+
+        assert_eq!(status(middleware(), &hs256(&valid)), StatusCode::OK);
+
+        let mut wrong_issuer = valid.clone();
+        wrong_issuer["iss"] = json!("other");
+        let mut wrong_audience = valid.clone();
+        wrong_audience["aud"] = json!("web");
+
+        for claims in &[wrong_issuer, wrong_audience] {
+            assert_eq!(
+                status(middleware(), &hs256(claims)),
+                StatusCode::UNAUTHORIZED
+            );
+        }
+    }
+
+    #[test]
+    fn jwt_middleware_leeway_test() {
+        let middleware = || JWTMiddleware::<Claims>::new(SECRET).leeway(Duration::from_secs(120));
+        let hs256 = |claims: &Value| encode(&Header::default(), claims, SECRET.as_ref()).unwrap();
+
+        let expired = json!({ "sub": "test@example.net", "exp": now() - 60 });
+        assert_eq!(status(middleware(), &hs256(&expired)), StatusCode::OK);
+        assert_eq!(
+            status(JWTMiddleware::new(SECRET), &hs256(&expired)),
+            StatusCode::UNAUTHORIZED
+        );
+
+        let immature = json!({ "sub": "test@example.net", "exp": now() + 600, "nbf": now() + 300 });
+        assert_eq!(
+            status(middleware(), &hs256(&immature)),
+            StatusCode::UNAUTHORIZED
+        );
+    }
 }
@@ -0,0 +1,211 @@
+use crate::state_data::RawClaims;
+use futures::future;
+use gotham::{
+    handler::HandlerFuture,
+    helpers::http::response::create_empty_response,
+    middleware::{Middleware, NewMiddleware},
+    state::{request_id, State},
+};
+use hyper::StatusCode;
+use serde_json::{Map, Value};
+use std::{io, sync::Arc};
+
+/// This middleware verifies that the JSON Web Token
+/// validated by a `JWTMiddleware` earlier in the
+/// pipeline chain has the configured claims, so that it
+/// can be added to the pipeline of a scope to protect
+/// its routes.
+///
+/// A claim matches where it's equal to the configured
+/// value, or where it's an array containing the value,
+/// so that `claim("roles", "admin")` accepts a token
+/// with `"roles": ["admin"]`. Requests whose token lacks
+/// a claim cause the middleware to return Status Code
+/// `403: Forbidden`.
+///
+/// Example:
+/// ```rust
+/// extern crate gotham;
+/// extern crate gotham_middleware_jwt;
+/// extern crate serde_json;
+///
+/// use gotham::{
+///     pipeline::{
+///         new_pipeline,
+///         set::{finalize_pipeline_set, new_pipeline_set},
+///     },
+///     router::{builder::*, Router},
+///     state::State,
+/// };
+/// use gotham_middleware_jwt::{JWTMiddleware, RequireClaimsMiddleware};
+///
+/// fn handler(state: State) -> (State, &'static str) {
+///     (state, "hello")
+/// }
+///
+/// fn router() -> Router {
+///     let pipelines = new_pipeline_set();
+///     let (pipelines, api) = pipelines.add(
+///         new_pipeline()
+///             .add(JWTMiddleware::<serde_json::Value>::new("secret"))
+///             .build(),
+///     );
+///     let (pipelines, admin) = pipelines.add(
+///         new_pipeline()
+///             .add(RequireClaimsMiddleware::new().claim("roles", "admin"))
+///             .build(),
+///     );
+///     let pipeline_set = finalize_pipeline_set(pipelines);
+///     build_router((api, ()), pipeline_set, |route| {
+///         route.get("/profile").to(handler);
+///         route.with_pipeline_chain((admin, (api, ())), |route| {
+///             route.get("/admin").to(handler);
+///         });
+///     })
+/// }
+///
+/// # fn main() {
+/// #    let _ = router();
+/// # }
+/// ```
+#[derive(Clone, Debug, Default)]
+pub struct RequireClaimsMiddleware {
+    claims: Arc<Vec<(String, Value)>>,
+}
+
+impl RequireClaimsMiddleware {
+    /// Creates a RequireClaimsMiddleware instance which
+    /// doesn't require any claims.
+    pub fn new() -> Self {
+        RequireClaimsMiddleware::default()
+    }
+
+    /// Create a new instance of the middleware which also
+    /// requires the claim `name` to be, or to contain, `value`.
+    pub fn claim<V: Into<Value>>(self, name: &str, value: V) -> Self {
+        let mut claims = self.claims;
+        Arc::make_mut(&mut claims).push((name.to_owned(), value.into()));
+        RequireClaimsMiddleware { claims }
+    }
+}
+
+fn has_claim(claims: &Map<String, Value>, name: &str, value: &Value) -> bool {
+    match claims.get(name) {
+        Some(Value::Array(values)) if !value.is_array() => values.contains(value),
+        Some(claim) => claim == value,
+        None => false,
+    }
+}
+
+impl Middleware for RequireClaimsMiddleware {
+    fn call<Chain>(self, state: State, chain: Chain) -> Box<HandlerFuture>
+    where
+        Chain: FnOnce(State) -> Box<HandlerFuture>,
+    {
+        let allowed = match state.try_borrow::<RawClaims>() {
+            Some(RawClaims(raw)) => self
+                .claims
+                .iter()
+                .all(|(name, value)| has_claim(raw, name, value)),
+            None => {
+                error!(
+                    "[{}] RequireClaimsMiddleware used without a JWTMiddleware",
+                    request_id(&state)
+                );
+                false
+            }
+        };
+
+        if allowed {
+            chain(state)
+        } else {
+            trace!("[{}] forbidden jwt claims middleware", request_id(&state));
+            let res = create_empty_response(&state, StatusCode::FORBIDDEN);
+            Box::new(future::ok((state, res)))
+        }
+    }
+}
+
+impl NewMiddleware for RequireClaimsMiddleware {
+    type Instance = RequireClaimsMiddleware;
+
+    fn new_middleware(&self) -> io::Result<Self::Instance> {
+        Ok(self.clone())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::middleware::JWTMiddleware;
+    use gotham::{
+        pipeline::{
+            new_pipeline,
+            set::{finalize_pipeline_set, new_pipeline_set},
+        },
+        router::{builder::*, Router},
+        test::TestServer,
+    };
+    use hyper::header::AUTHORIZATION;
+    use jsonwebtoken::{encode, Header};
+
+    const SECRET: &str = "some-secret";
+
+    fn handler(state: State) -> (State, &'static str) {
+        (state, "hello")
+    }
+
+    fn router() -> Router {
+        let pipelines = new_pipeline_set();
+        let (pipelines, api) = pipelines.add(
+            new_pipeline()
+                .add(JWTMiddleware::<Value>::new(SECRET))
+                .build(),
+        );
+        let (pipelines, admin) = pipelines.add(
+            new_pipeline()
+                .add(
+                    RequireClaimsMiddleware::new()
+                        .claim("roles", "admin")
+                        .claim("tenant", 7),
+                )
+                .build(),
+        );
+        let pipeline_set = finalize_pipeline_set(pipelines);
+        build_router((api, ()), pipeline_set, |route| {
+            route.get("/").to(handler);
+            route.with_pipeline_chain((admin, (api, ())), |route| {
+                route.get("/admin").to(handler);
+            });
+        })
+    }
+
+    fn status(test_server: &TestServer, path: &str, claims: &Value) -> StatusCode {
+        let token = encode(&Header::default(), claims, SECRET.as_ref()).unwrap();
+        test_server
+            .client()
+            .get(format!("https://example.com{}", path))
+            .with_header(AUTHORIZATION, format!("Bearer {}", token).parse().unwrap())
+            .perform()
+            .unwrap()
+            .status()
+    }
+
+    #[test]
+    fn require_claims_middleware_test() {
+        let test_server = TestServer::new(router()).unwrap();
+        let admin = json!({ "exp": 10_000_000_000u64, "roles": ["user", "admin"], "tenant": 7 });
+        let user = json!({ "exp": 10_000_000_000u64, "roles": ["user"], "tenant": 7 });
+
+        assert_eq!(status(&test_server, "/", &user), StatusCode::OK);
+        assert_eq!(status(&test_server, "/admin", &admin), StatusCode::OK);
+        assert_eq!(status(&test_server, "/admin", &user), StatusCode::FORBIDDEN);
+
+        let res = test_server
+            .client()
+            .get("https://example.com/admin")
+            .perform()
+            .unwrap();
+        assert_eq!(res.status(), StatusCode::BAD_REQUEST);
+    }
+}
@@ -1,5 +1,11 @@
 pub use jsonwebtoken::TokenData;
+use serde_json::{Map, Value};
 
 /// Struct to contain the JSON Web Token on a per-request basis.
 #[derive(StateData, Debug)]
 pub struct AuthorizationToken<T: Send + 'static>(pub TokenData<T>);
+
+/// Every claim of the JSON Web Token, kept for `RequireClaimsMiddleware`
+/// so that it doesn't depend on the claims type of the application.
+#[derive(StateData, Debug)]
+pub(crate) struct RawClaims(pub(crate) Map<String, Value>);